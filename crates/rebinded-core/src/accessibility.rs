@@ -0,0 +1,496 @@
+//! Accessibility modes that apply to every key
+//!
+//! Strategies only see the keys a binding names; these modes see every key
+//! event before bindings and strategies do, including keys that are only
+//! passed through. They are turned on in the `[settings.accessibility]` table.
+//!
+//! With sticky keys, tapping a modifier latches it: the OS keeps seeing it held
+//! until the next key is released, so shortcuts can be typed one key at a
+//! time. Tapping it again while latched locks it until it is tapped a third
+//! time. Each change is announced with a notification unless `feedback` is off.
+//!
+//! With slow keys, a key only registers once it has been held for
+//! `slow_keys_ms`: the press is held back and sent when the time is up, and a
+//! key released sooner never reaches the OS. `slow_keys` limits this to some
+//! keys. Keys with bindings or strategies are left to them (`gated_hold` does
+//! the same for bound keys).
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+use tracing::debug;
+
+use crate::key::{KeyCode, KeyEvent};
+use crate::platform::EventResponse;
+use crate::strategy::PlatformHandle;
+
+/// Settings from the `[settings.accessibility]` table
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct AccessibilitySettings {
+    /// Latch modifiers when tapped, releasing them after the next key
+    #[serde(default)]
+    pub sticky_keys: bool,
+    /// How long a key has to be held before it registers; off when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slow_keys_ms: Option<u64>,
+    /// Keys slow keys applies to; every key when empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub slow_keys: Vec<String>,
+    /// Show a notification when a mode changes state (a modifier latches,
+    /// locks or releases)
+    #[serde(default = "default_feedback")]
+    pub feedback: bool,
+}
+
+fn default_feedback() -> bool {
+    true
+}
+
+impl Default for AccessibilitySettings {
+    fn default() -> Self {
+        Self {
+            sticky_keys: false,
+            slow_keys_ms: None,
+            slow_keys: Vec::new(),
+            feedback: default_feedback(),
+        }
+    }
+}
+
+/// Modifier keys sticky keys latches, with the name feedback uses for them
+#[cfg(unix)]
+const MODIFIERS: [(u32, &str); 8] = [
+    (29, "Ctrl"),   // KEY_LEFTCTRL
+    (97, "Ctrl"),   // KEY_RIGHTCTRL
+    (42, "Shift"),  // KEY_LEFTSHIFT
+    (54, "Shift"),  // KEY_RIGHTSHIFT
+    (56, "Alt"),    // KEY_LEFTALT
+    (100, "AltGr"), // KEY_RIGHTALT
+    (125, "Super"), // KEY_LEFTMETA
+    (126, "Super"), // KEY_RIGHTMETA
+];
+
+/// Modifier keys sticky keys latches, with the name feedback uses for them
+#[cfg(windows)]
+const MODIFIERS: [(u32, &str); 8] = [
+    (0xA2, "Ctrl"),  // VK_LCONTROL
+    (0xA3, "Ctrl"),  // VK_RCONTROL
+    (0xA0, "Shift"), // VK_LSHIFT
+    (0xA1, "Shift"), // VK_RSHIFT
+    (0xA4, "Alt"),   // VK_LMENU
+    (0xA5, "AltGr"), // VK_RMENU
+    (0x5B, "Win"),   // VK_LWIN
+    (0x5C, "Win"),   // VK_RWIN
+];
+
+/// The feedback name of `key` if it is a modifier
+fn modifier_name(key: KeyCode) -> Option<&'static str> {
+    MODIFIERS
+        .iter()
+        .find(|(code, _)| KeyCode::new(*code) == key)
+        .map(|(_, name)| *name)
+}
+
+/// The accessibility modes of a loaded config, with their state
+#[derive(Debug, Default)]
+pub struct Accessibility {
+    slow_keys: Option<RefCell<SlowKeys>>,
+    sticky_keys: Option<RefCell<StickyKeys>>,
+}
+
+impl Accessibility {
+    /// The modes `settings` turn on. `bound` are the keys bindings or
+    /// strategies handle, which slow keys leaves alone.
+    pub fn new(settings: &AccessibilitySettings, bound: HashSet<KeyCode>) -> Self {
+        let slow_keys = settings.slow_keys_ms.map(|ms| {
+            let keys = (!settings.slow_keys.is_empty()).then(|| {
+                settings
+                    .slow_keys
+                    .iter()
+                    .filter_map(|key| KeyCode::from_config_str(key))
+                    .collect()
+            });
+            RefCell::new(SlowKeys::new(Duration::from_millis(ms), keys, bound))
+        });
+        Self {
+            slow_keys,
+            sticky_keys: settings
+                .sticky_keys
+                .then(|| RefCell::new(StickyKeys::new(settings.feedback))),
+        }
+    }
+
+    /// Whether any mode is on, in which case every key has to reach the engine
+    pub fn is_active(&self) -> bool {
+        self.slow_keys.is_some() || self.sticky_keys.is_some()
+    }
+
+    /// Let every mode see a key event. Returns the response when a mode
+    /// consumes the event; otherwise it continues to strategies and bindings.
+    pub fn process(&self, event: &KeyEvent, platform: &PlatformHandle) -> Option<EventResponse> {
+        // A press slow keys holds back hasn't happened yet as far as the
+        // other modes are concerned
+        if let Some(slow_keys) = &self.slow_keys
+            && let Some(response) = slow_keys.borrow_mut().process(event, platform)
+        {
+            return Some(response);
+        }
+        let sticky_keys = self.sticky_keys.as_ref()?;
+        sticky_keys.borrow_mut().process(event, platform)
+    }
+}
+
+/// Slow keys state
+#[derive(Debug)]
+struct SlowKeys {
+    delay: Duration,
+    /// Keys it applies to; every platform key when `None`
+    keys: Option<HashSet<KeyCode>>,
+    /// Keys bindings or strategies handle
+    bound: HashSet<KeyCode>,
+    /// Keys held and not released yet, with the cancel sender of the timer
+    /// that registers them. A closed sender means the timer already fired.
+    held: HashMap<KeyCode, oneshot::Sender<()>>,
+}
+
+impl SlowKeys {
+    fn new(delay: Duration, keys: Option<HashSet<KeyCode>>, bound: HashSet<KeyCode>) -> Self {
+        Self {
+            delay,
+            keys,
+            bound,
+            held: HashMap::new(),
+        }
+    }
+
+    fn applies_to(&self, key: KeyCode) -> bool {
+        key.is_platform_key()
+            && !self.bound.contains(&key)
+            && self.keys.as_ref().is_none_or(|keys| keys.contains(&key))
+    }
+
+    fn process(&mut self, event: &KeyEvent, platform: &PlatformHandle) -> Option<EventResponse> {
+        let key = event.key;
+        if !self.applies_to(key) {
+            return None;
+        }
+
+        if !event.down {
+            let cancel_tx = self.held.remove(&key)?;
+            // The timer already pressed the key, so the release goes through
+            if cancel_tx.send(()).is_err() {
+                return None;
+            }
+            debug!(%key, "slow keys: released before registering");
+            return Some(EventResponse::Block);
+        }
+
+        // Repeats of a registered key reach the OS like autorepeat
+        if let Some(cancel_tx) = self.held.get(&key) {
+            return (!cancel_tx.is_closed()).then_some(EventResponse::Block);
+        }
+
+        let (cancel_tx, cancel_rx) = oneshot::channel();
+        // Counted from the physical press, not from when this runs
+        let deadline = event.time + self.delay;
+        let platform = platform.clone();
+        tokio::spawn(async move {
+            tokio::select! {
+                // A release that arrived in time wins over an elapsed timer
+                biased;
+                _ = cancel_rx => {}
+                () = platform.clock().sleep_until(deadline) => {
+                    debug!(%key, "slow keys: held long enough, registering");
+                    platform.send_raw_key(key, true);
+                }
+            }
+        });
+        self.held.insert(key, cancel_tx);
+        Some(EventResponse::Block)
+    }
+}
+
+/// How a modifier is being held for the user
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Latch {
+    /// Until the next key is released
+    Latched,
+    /// Until the modifier is tapped again
+    Locked,
+}
+
+/// Sticky keys state
+struct StickyKeys {
+    feedback: bool,
+    /// Modifiers the OS sees held although the user let go of them
+    latched: HashMap<KeyCode, Latch>,
+    /// Modifier pressed with no other key since, which latches if released now
+    tapped: Option<KeyCode>,
+    /// Key pressed while modifiers were latched, whose release unlatches them
+    releases_latch: Option<KeyCode>,
+    /// Where latched modifiers are released if the config is dropped first
+    platform: Option<PlatformHandle>,
+}
+
+impl std::fmt::Debug for StickyKeys {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StickyKeys")
+            .field("latched", &self.latched)
+            .field("tapped", &self.tapped)
+            .finish()
+    }
+}
+
+impl StickyKeys {
+    fn new(feedback: bool) -> Self {
+        Self {
+            feedback,
+            latched: HashMap::new(),
+            tapped: None,
+            releases_latch: None,
+            platform: None,
+        }
+    }
+
+    fn process(&mut self, event: &KeyEvent, platform: &PlatformHandle) -> Option<EventResponse> {
+        self.platform = Some(platform.clone());
+        let key = event.key;
+        let Some(name) = modifier_name(key) else {
+            if event.down {
+                self.tapped = None;
+                if self.latched.values().any(|latch| *latch == Latch::Latched) {
+                    self.releases_latch = Some(key);
+                }
+            } else if self.releases_latch == Some(key) {
+                self.releases_latch = None;
+                self.release(Latch::Latched, platform);
+            }
+            return None;
+        };
+
+        match (event.down, self.latched.get(&key).copied()) {
+            // Already held in the OS since it latched
+            (true, Some(_)) => Some(EventResponse::Block),
+            (true, None) => {
+                self.tapped = Some(key);
+                None
+            }
+            (false, Some(Latch::Latched)) => {
+                self.latched.insert(key, Latch::Locked);
+                self.announce(platform, format!("{name} locked"));
+                Some(EventResponse::Block)
+            }
+            // The release reaches the OS and lets go of the lock
+            (false, Some(Latch::Locked)) => {
+                self.latched.remove(&key);
+                self.tapped = None;
+                self.announce(platform, format!("{name} released"));
+                None
+            }
+            (false, None) if self.tapped == Some(key) => {
+                self.tapped = None;
+                self.latched.insert(key, Latch::Latched);
+                self.announce(platform, format!("{name} latched"));
+                Some(EventResponse::Block)
+            }
+            // Held while typing another key, like without sticky keys
+            (false, None) => None,
+        }
+    }
+
+    /// Release every modifier held with `latch`
+    fn release(&mut self, latch: Latch, platform: &PlatformHandle) {
+        self.latched.retain(|key, held| {
+            if *held != latch {
+                return true;
+            }
+            debug!(%key, "releasing latched modifier");
+            platform.send_raw_key(*key, false);
+            false
+        });
+    }
+
+    fn announce(&self, platform: &PlatformHandle, message: String) {
+        debug!(message, "sticky keys");
+        if self.feedback {
+            platform.notify(&message);
+        }
+    }
+}
+
+impl Drop for StickyKeys {
+    /// A reload replaces the config; modifiers it held must not stay down
+    fn drop(&mut self) {
+        if let Some(platform) = self.platform.take() {
+            for key in self.latched.keys() {
+                platform.send_raw_key(*key, false);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::MockPlatform;
+    use crate::platform::mock::PlatformCall;
+
+    fn sticky_keys(feedback: bool) -> Accessibility {
+        let settings = AccessibilitySettings {
+            sticky_keys: true,
+            feedback,
+            ..Default::default()
+        };
+        Accessibility::new(&settings, HashSet::new())
+    }
+
+    fn press(accessibility: &Accessibility, key: KeyCode, down: bool, platform: &PlatformHandle) {
+        accessibility.process(&KeyEvent::new(key, down), platform);
+    }
+
+    fn shift() -> KeyCode {
+        KeyCode::new(MODIFIERS[2].0)
+    }
+
+    #[test]
+    fn test_sticky_keys_latch_and_release() {
+        let mock = MockPlatform::new();
+        let platform = PlatformHandle::from_mock(&mock);
+        let accessibility = sticky_keys(true);
+        let a = KeyCode::new(0x41);
+
+        // Tapping shift latches it: the release is held back from the OS
+        assert_eq!(
+            accessibility.process(&KeyEvent::new(shift(), true), &platform),
+            None
+        );
+        assert_eq!(
+            accessibility.process(&KeyEvent::new(shift(), false), &platform),
+            Some(EventResponse::Block)
+        );
+        // The next key goes through, and its release lets go of shift
+        assert_eq!(
+            accessibility.process(&KeyEvent::new(a, true), &platform),
+            None
+        );
+        assert_eq!(
+            accessibility.process(&KeyEvent::new(a, false), &platform),
+            None
+        );
+        assert_eq!(
+            mock.calls(),
+            vec![
+                PlatformCall::Notify("Shift latched".to_string()),
+                PlatformCall::SendRawKey {
+                    key: shift(),
+                    down: false
+                },
+            ]
+        );
+
+        // Shift held while typing behaves as usual
+        mock.clear_calls();
+        press(&accessibility, shift(), true, &platform);
+        press(&accessibility, a, true, &platform);
+        press(&accessibility, a, false, &platform);
+        assert_eq!(
+            accessibility.process(&KeyEvent::new(shift(), false), &platform),
+            None
+        );
+        assert!(mock.calls().is_empty());
+    }
+
+    #[test]
+    fn test_sticky_keys_lock() {
+        let mock = MockPlatform::new();
+        let platform = PlatformHandle::from_mock(&mock);
+        let accessibility = sticky_keys(false);
+        let a = KeyCode::new(0x41);
+
+        for _ in 0..2 {
+            press(&accessibility, shift(), true, &platform);
+            press(&accessibility, shift(), false, &platform);
+        }
+        // Locked shift survives typing
+        press(&accessibility, a, true, &platform);
+        press(&accessibility, a, false, &platform);
+        assert!(mock.calls().is_empty());
+
+        // A third tap passes the release through to the OS
+        assert_eq!(
+            accessibility.process(&KeyEvent::new(shift(), true), &platform),
+            Some(EventResponse::Block)
+        );
+        assert_eq!(
+            accessibility.process(&KeyEvent::new(shift(), false), &platform),
+            None
+        );
+        drop(accessibility);
+        assert!(mock.calls().is_empty());
+    }
+
+    #[test]
+    fn test_latched_modifiers_released_on_drop() {
+        let mock = MockPlatform::new();
+        let platform = PlatformHandle::from_mock(&mock);
+        let accessibility = sticky_keys(false);
+        press(&accessibility, shift(), true, &platform);
+        press(&accessibility, shift(), false, &platform);
+        drop(accessibility);
+        assert_eq!(
+            mock.calls(),
+            vec![PlatformCall::SendRawKey {
+                key: shift(),
+                down: false
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_slow_keys() {
+        let mock = MockPlatform::new();
+        let platform = PlatformHandle::from_mock(&mock);
+        let (a, b, bound) = (KeyCode::new(0x41), KeyCode::new(0x42), KeyCode::new(0x7C));
+        let settings = AccessibilitySettings {
+            slow_keys_ms: Some(30),
+            ..Default::default()
+        };
+        let accessibility = Accessibility::new(&settings, HashSet::from([bound]));
+
+        // A tap shorter than the threshold never registers
+        let process = |key, down| accessibility.process(&KeyEvent::new(key, down), &platform);
+        assert_eq!(process(a, true), Some(EventResponse::Block));
+        assert_eq!(process(a, false), Some(EventResponse::Block));
+
+        // A long enough hold is pressed for the OS, and the release goes through
+        assert_eq!(process(b, true), Some(EventResponse::Block));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(process(b, true), None);
+        assert_eq!(process(b, false), None);
+
+        // Bound keys are left to their binding
+        assert_eq!(process(bound, true), None);
+        assert_eq!(
+            mock.calls(),
+            vec![PlatformCall::SendRawKey { key: b, down: true }]
+        );
+    }
+
+    #[test]
+    fn test_slow_keys_subset() {
+        let mock = MockPlatform::new();
+        let platform = PlatformHandle::from_mock(&mock);
+        let settings = AccessibilitySettings {
+            slow_keys_ms: Some(30),
+            slow_keys: vec!["0x41".to_string()],
+            ..Default::default()
+        };
+        let accessibility = Accessibility::new(&settings, HashSet::new());
+        let b = KeyEvent::new(KeyCode::new(0x42), true);
+        assert_eq!(accessibility.process(&b, &platform), None);
+    }
+}
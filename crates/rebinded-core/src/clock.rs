@@ -0,0 +1,180 @@
+//! Time as strategies see it
+//!
+//! Strategies time holds and taps from [`KeyEvent::time`](crate::key::KeyEvent)
+//! and wait on timers through a [`Clock`] carried by the
+//! [`PlatformHandle`](crate::strategy::PlatformHandle). The daemon uses
+//! [`SystemClock`]; tests and trace replays use [`MockClock`], which only moves
+//! when told to, so a 200ms hold resolves exactly at 200ms without anything
+//! waiting for it.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tokio::sync::oneshot;
+
+/// A future that completes when a [`Clock`] reaches some time
+pub type Sleep = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// A source of the current time and of timers that follow it
+pub trait Clock: Send + Sync {
+    /// The current time
+    fn now(&self) -> Instant;
+
+    /// Complete once the clock reaches `deadline`, or at once if it has
+    fn sleep_until(&self, deadline: Instant) -> Sleep;
+
+    /// Complete once `duration` has passed on this clock
+    fn sleep(&self, duration: Duration) -> Sleep {
+        self.sleep_until(self.now() + duration)
+    }
+}
+
+/// Real time, with tokio timers
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> Sleep {
+        Box::pin(tokio::time::sleep_until(deadline.into()))
+    }
+}
+
+/// A clock that stands still until [`advance`](Self::advance)d
+///
+/// Timers due by the new time fire in deadline order, each seeing the clock at
+/// its own deadline, and the tasks they wake run before the clock moves on.
+/// That hand-off relies on the current-thread runtime the engine runs on.
+pub struct MockClock {
+    state: Mutex<MockState>,
+}
+
+struct MockState {
+    now: Instant,
+    sleepers: Vec<(Instant, oneshot::Sender<()>)>,
+}
+
+/// How often [`MockClock`] yields after waking timers. Each yield lets the
+/// woken tasks and anything they spawn take one more step.
+const SETTLE_YIELDS: usize = 8;
+
+impl MockClock {
+    /// A clock standing at `start`
+    pub fn new(start: Instant) -> Self {
+        Self {
+            state: Mutex::new(MockState {
+                now: start,
+                sleepers: Vec::new(),
+            }),
+        }
+    }
+
+    /// Move the clock forward by `duration`, firing the timers due on the way
+    pub async fn advance(&self, duration: Duration) {
+        let target = self.now() + duration;
+        self.advance_to(target).await;
+    }
+
+    /// Move the clock forward to `target`, firing the timers due on the way.
+    /// A `target` in the past leaves the clock where it is.
+    pub async fn advance_to(&self, target: Instant) {
+        // Tasks spawned before the call get to register their timers first
+        settle().await;
+        loop {
+            let due = {
+                let mut state = self.state.lock().unwrap();
+                let next = state
+                    .sleepers
+                    .iter()
+                    .map(|(deadline, _)| *deadline)
+                    .filter(|deadline| *deadline <= target)
+                    .min();
+                let Some(next) = next else {
+                    state.now = state.now.max(target);
+                    return;
+                };
+                state.now = state.now.max(next);
+                let (due, waiting): (Vec<_>, Vec<_>) = std::mem::take(&mut state.sleepers)
+                    .into_iter()
+                    .partition(|(deadline, _)| *deadline <= next);
+                state.sleepers = waiting;
+                due
+            };
+            for (_, wake) in due {
+                let _ = wake.send(());
+            }
+            settle().await;
+        }
+    }
+}
+
+async fn settle() {
+    for _ in 0..SETTLE_YIELDS {
+        tokio::task::yield_now().await;
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.state.lock().unwrap().now
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> Sleep {
+        let mut state = self.state.lock().unwrap();
+        if deadline <= state.now {
+            return Box::pin(std::future::ready(()));
+        }
+        let (wake, woken) = oneshot::channel();
+        state.sleepers.push((deadline, wake));
+        // A clock dropped with timers pending never fires them
+        Box::pin(async move {
+            if woken.await.is_err() {
+                std::future::pending::<()>().await;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_mock_clock_fires_in_order() {
+        let start = Instant::now();
+        let clock = Arc::new(MockClock::new(start));
+        let fired = Arc::new(Mutex::new(Vec::new()));
+        for ms in [300, 100, 200] {
+            let (clock, fired) = (clock.clone(), fired.clone());
+            tokio::spawn(async move {
+                clock.sleep(Duration::from_millis(ms)).await;
+                fired.lock().unwrap().push((ms, clock.now() - start));
+            });
+        }
+
+        clock.advance(Duration::from_millis(150)).await;
+        assert_eq!(*fired.lock().unwrap(), [(100, Duration::from_millis(100))]);
+        assert_eq!(clock.now() - start, Duration::from_millis(150));
+
+        clock.advance(Duration::from_millis(1000)).await;
+        assert_eq!(
+            *fired.lock().unwrap(),
+            [
+                (100, Duration::from_millis(100)),
+                (200, Duration::from_millis(200)),
+                (300, Duration::from_millis(300)),
+            ]
+        );
+
+        // Past deadlines complete at once, and the clock never goes back
+        clock.sleep_until(start).await;
+        clock.advance_to(start).await;
+        assert_eq!(clock.now() - start, Duration::from_millis(1150));
+    }
+}
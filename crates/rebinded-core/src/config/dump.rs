@@ -0,0 +1,226 @@
+//! Rendering the resolved configuration back to TOML (`rebinded config dump`)
+//!
+//! Shows what the daemon runs with rather than what the file says: settings
+//! and strategies with defaults filled in, `strategy@binding` clones with their
+//! overrides applied, group and range bindings expanded to one binding per key,
+//! and every key name replaced by the name it resolves to. Secrets are redacted.
+
+use super::{Config, ExecuteNotification, RuntimeConfig, StrategyConfig};
+use crate::integrations::Settings;
+use crate::key::{self, InputEventId};
+
+/// Shown in place of passwords and tokens
+const REDACTED: &str = "<redacted>";
+
+/// The resolved configuration as normalized TOML, with tables sorted by name
+pub fn dump(config: &Config, runtime: &RuntimeConfig) -> String {
+    let mut root = toml::Table::new();
+    root.insert(
+        "settings".to_string(),
+        toml::Value::Table(settings_table(&config.settings)),
+    );
+
+    let mut strategies = toml::Table::new();
+    for (name, strategy) in &runtime.strategy_configs {
+        let mut table = strategy_table(strategy);
+        if let Some(condition) = runtime.strategy_conditions.get(name)
+            && let Ok(condition) = toml::Value::try_from(condition)
+        {
+            table.insert("condition".to_string(), condition);
+        }
+        strategies.insert(name.clone(), toml::Value::Table(table));
+    }
+    root.insert("strategies".to_string(), toml::Value::Table(strategies));
+
+    let mut bindings = toml::Table::new();
+    for (code, binding) in &runtime.bindings {
+        let mut table = toml::Table::new();
+        table.insert("action".to_string(), binding.action_source.clone());
+        // Overrides are already folded into the `strategy@binding` clones
+        match binding.strategies.as_slice() {
+            [] => {}
+            [strategy] => {
+                table.insert("strategy".to_string(), strategy.value().clone().into());
+            }
+            chain => {
+                let chain = chain.iter().map(|s| s.value().clone().into()).collect();
+                table.insert("strategy".to_string(), toml::Value::Array(chain));
+            }
+        }
+        match &binding.notify_on_execute {
+            Some(ExecuteNotification::Describe) => {
+                table.insert("notify_on_execute".to_string(), true.into());
+            }
+            Some(ExecuteNotification::Message(message)) => {
+                table.insert("notify_on_execute".to_string(), message.clone().into());
+            }
+            None => {}
+        }
+        bindings.insert(key::config_name(*code), toml::Value::Table(table));
+    }
+    root.insert("bindings".to_string(), toml::Value::Table(bindings));
+
+    if !config.hid.is_empty()
+        && let Ok(hid) = toml::Value::try_from(&config.hid)
+    {
+        root.insert("hid".to_string(), hid);
+    }
+
+    let managed = &config.managed;
+    if !managed.locked_bindings.is_empty() || !managed.allow_commands {
+        let mut table = toml::Table::new();
+        let locked = managed
+            .locked_bindings
+            .iter()
+            .map(|key| key.value().clone().into())
+            .collect();
+        table.insert("locked_bindings".to_string(), toml::Value::Array(locked));
+        table.insert("allow_commands".to_string(), managed.allow_commands.into());
+        root.insert("managed".to_string(), toml::Value::Table(table));
+    }
+
+    root.to_string()
+}
+
+/// Settings with defaults filled in, and secrets from the config or the
+/// environment redacted
+fn settings_table(settings: &Settings) -> toml::Table {
+    let mut table = toml::Table::try_from(settings).unwrap_or_default();
+    let secrets = [
+        (
+            "obs",
+            "password",
+            settings.obs.password.is_some() || std::env::var_os("OBS_WEBSOCKET_PASSWORD").is_some(),
+        ),
+        (
+            "home_assistant",
+            "token",
+            settings.home_assistant.token.is_some() || std::env::var_os("HASS_TOKEN").is_some(),
+        ),
+    ];
+    for (section, field, set) in secrets {
+        if set && let Some(toml::Value::Table(section)) = table.get_mut(section) {
+            section.insert(field.to_string(), REDACTED.into());
+        }
+    }
+    table
+}
+
+/// A strategy's fields with defaults filled in and key names resolved
+fn strategy_table(strategy: &StrategyConfig) -> toml::Table {
+    if let StrategyConfig::Registered {
+        type_name,
+        settings,
+    } = strategy
+    {
+        let mut table = settings.clone();
+        table.insert("type".to_string(), type_name.clone().into());
+        return table;
+    }
+
+    let mut table = toml::Table::try_from(strategy).unwrap_or_default();
+    let key_fields: &[&str] = match strategy {
+        StrategyConfig::TapHold { .. } => &["tap_key", "hold_key"],
+        StrategyConfig::Combo { .. } => &["keys"],
+        StrategyConfig::Leader { .. } => &["key", "sequences"],
+        StrategyConfig::GatedHold { .. } => &["diverts"],
+        StrategyConfig::MouseKeys { .. } => &["keys", "toggle"],
+        StrategyConfig::Mirror { .. } => &["trigger", "pairs"],
+        StrategyConfig::Repeat { .. }
+        | StrategyConfig::Debounce { .. }
+        | StrategyConfig::PushToTalk { .. }
+        | StrategyConfig::Script { .. }
+        | StrategyConfig::Registered { .. } => &[],
+    };
+    for field in key_fields {
+        if let Some(value) = table.get_mut(*field) {
+            resolve_key_names(value);
+        }
+    }
+    table
+}
+
+/// Replace key names with the names they resolve to: strings and array items
+/// are key names, and so are the keys (not the values) of tables
+fn resolve_key_names(value: &mut toml::Value) {
+    match value {
+        toml::Value::String(name) => *name = resolved_name(name),
+        toml::Value::Array(items) => items.iter_mut().for_each(resolve_key_names),
+        toml::Value::Table(table) => {
+            *table = std::mem::take(table)
+                .into_iter()
+                .map(|(name, value)| (resolved_name(&name), value))
+                .collect();
+        }
+        _ => {}
+    }
+}
+
+fn resolved_name(name: &str) -> String {
+    match InputEventId::from_config_str(name) {
+        Some(InputEventId::Key(code)) => key::config_name(code),
+        Some(id) => id.to_string(),
+        None => name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::load_from_str;
+
+    #[tokio::test]
+    async fn test_dump_resolves_config() {
+        let (config, runtime) = load_from_str(
+            "test.toml",
+            r#"
+            [settings.home_assistant]
+            url = "http://localhost:8123"
+            token = "secret"
+
+            [strategies.hold]
+            type = "tap_hold"
+            hold_ms = 200
+            tap_key = "KEY_ESC"
+            condition = { window = { binary = "firefox" } }
+
+            [bindings.media]
+            keys = ["f13..f14"]
+            action = "media_play_pause"
+            strategy = "hold"
+            hold_ms = 300
+            notify_on_execute = true
+            "#
+            .to_string(),
+        )
+        .await
+        .unwrap();
+
+        let dumped = dump(&config, &runtime);
+        let table: toml::Table = dumped.parse().unwrap();
+
+        assert_eq!(table["settings"]["slow_event_ms"].as_integer(), Some(20));
+        assert_eq!(
+            table["settings"]["home_assistant"]["token"].as_str(),
+            Some(REDACTED)
+        );
+
+        let hold = &table["strategies"]["hold@media"];
+        assert_eq!(hold["hold_ms"].as_integer(), Some(300));
+        assert_eq!(hold["tap_key"].as_str(), Some("esc"));
+        assert_eq!(
+            hold["condition"]["window"]["binary"].as_str(),
+            Some("firefox")
+        );
+
+        for key in ["f13", "f14"] {
+            let binding = &table["bindings"][key];
+            assert_eq!(binding["action"].as_str(), Some("media_play_pause"));
+            assert_eq!(binding["strategy"].as_str(), Some("hold@media"));
+            assert_eq!(binding["notify_on_execute"].as_bool(), Some(true));
+        }
+
+        // The dump is itself a valid config
+        load_from_str("dump.toml", dumped).await.unwrap();
+    }
+}
@@ -0,0 +1,310 @@
+//! Configuration error types with source location tracking
+//!
+//! Provides rich diagnostic output using miette for configuration validation errors.
+
+// False positives from miette's derive macros - fields are used but rustc doesn't see it
+#![allow(unused_assignments)]
+
+use super::types::Span;
+use miette::{Diagnostic, NamedSource, SourceSpan};
+use thiserror::Error;
+
+/// Convert byte offset to 1-based line number
+pub fn byte_offset_to_line(content: &str, offset: usize) -> usize {
+    content[..offset.min(content.len())]
+        .chars()
+        .filter(|&c| c == '\n')
+        .count()
+        + 1
+}
+
+/// A single validation issue with location information
+#[derive(Debug, Clone)]
+pub struct ConfigIssue {
+    /// Byte span in source
+    pub span: Span,
+    /// Primary error message
+    pub message: String,
+    /// Label shown at the span location
+    pub label: String,
+    /// Optional help text with suggestions
+    pub help: Option<String>,
+}
+
+/// Help for an unrecognized key: the closest known name, if any, and the
+/// accepted formats
+pub fn unknown_key_help(key: &str) -> String {
+    let formats = "valid formats: hex (0x7C), decimal (124), or key name (space, enter)";
+    match crate::key::suggest_key_name(key) {
+        Some(name) => format!("did you mean '{name}'? {formats}"),
+        None => formats.to_string(),
+    }
+}
+
+impl ConfigIssue {
+    /// Create an issue for an unresolvable key name
+    pub fn unknown_key(span: Span, key: &str) -> Self {
+        Self {
+            span,
+            message: format!("unknown key '{key}'"),
+            label: "not a valid key name or code".to_string(),
+            help: Some(unknown_key_help(key)),
+        }
+    }
+
+    /// Create an issue for a scancode key on a platform that can't match them
+    pub fn unsupported_scancode(span: Span, key: &str) -> Self {
+        Self {
+            span,
+            message: format!("scancode key '{key}' is only supported on Windows"),
+            label: "scancode key".to_string(),
+            help: Some("use the key's name or code instead (rebinded keys lists them)".to_string()),
+        }
+    }
+
+    /// Create an issue for a MIDI key on a platform without a MIDI backend
+    pub fn unsupported_midi(span: Span, key: &str) -> Self {
+        Self {
+            span,
+            message: format!("MIDI key '{key}' is only supported on Linux"),
+            label: "MIDI key".to_string(),
+            help: None,
+        }
+    }
+
+    /// Create an issue for a key range that can't be expanded
+    pub fn invalid_key_range(span: Span, reason: String) -> Self {
+        Self {
+            span,
+            message: reason,
+            label: "invalid key range".to_string(),
+            help: Some(
+                "ranges are inclusive: numeric (0x7C..0x87) or named with a numeric suffix (f13..f24)"
+                    .to_string(),
+            ),
+        }
+    }
+
+    /// Create an issue for a reference to an undefined strategy
+    pub fn undefined_strategy(span: Span, name: &str, defined: &[&str]) -> Self {
+        let help = if defined.is_empty() {
+            "no strategies are defined in this config".to_string()
+        } else {
+            format!("defined strategies: {}", defined.join(", "))
+        };
+        Self {
+            span,
+            message: format!("undefined strategy '{name}'"),
+            label: "strategy not found".to_string(),
+            help: Some(help),
+        }
+    }
+
+    /// Create an issue for a user binding of a key the system config locks
+    pub fn locked_binding(span: Span, key_display: &str, locked_at: &str) -> Self {
+        Self {
+            span,
+            message: format!("key '{key_display}' is locked by the system config"),
+            label: "locked key".to_string(),
+            help: Some(format!(
+                "locked by [managed] locked_bindings at {locked_at}; ask an administrator to change it"
+            )),
+        }
+    }
+
+    /// Create an issue for a user config running a command when the system
+    /// config disallows it
+    pub fn command_not_allowed(span: Span) -> Self {
+        Self {
+            span,
+            message: "commands are disabled by the system config".to_string(),
+            label: "runs a command".to_string(),
+            help: Some("[managed] allow_commands = false forbids run, dbus and http actions and focus launch commands in user configs, and plugins next to them".to_string()),
+        }
+    }
+
+    /// Create an issue for a duplicate key binding, naming where the key was
+    /// first bound (`line 3`, or `line 3 of /etc/rebinded/config.toml`)
+    pub fn duplicate_binding(span: Span, key_display: &str, first_defined: &str) -> Self {
+        Self {
+            span,
+            message: format!("duplicate binding for key '{key_display}'"),
+            label: "duplicate".to_string(),
+            help: Some(format!("first defined at {first_defined}")),
+        }
+    }
+}
+
+/// One config file taking part in a load
+///
+/// Layers share a single span space: each starts at `offset`, past the end of
+/// the layer before it, so a span alone tells which file it points into.
+#[derive(Debug, Clone)]
+pub struct ConfigSource {
+    pub name: String,
+    pub content: String,
+    pub offset: usize,
+    /// Part of the admin-managed system config rather than the user's
+    pub system: bool,
+}
+
+impl ConfigSource {
+    /// Whether a span points into this layer
+    pub fn contains(&self, span: &Span) -> bool {
+        span.start >= self.offset && span.start <= self.offset + self.content.len()
+    }
+
+    /// A span relative to the start of this layer's file
+    pub fn local(&self, span: &Span) -> Span {
+        span.start - self.offset..span.end - self.offset
+    }
+
+    /// `line N` for a span in this layer, with the file name appended when
+    /// `relative_to` is another layer
+    pub fn describe(&self, span: &Span, relative_to: &ConfigSource) -> String {
+        let line = byte_offset_to_line(&self.content, self.local(span).start);
+        if self.offset == relative_to.offset {
+            format!("line {line}")
+        } else {
+            format!("line {line} of {}", self.name)
+        }
+    }
+
+    fn named_source(&self) -> NamedSource<String> {
+        NamedSource::new(self.name.clone(), self.content.clone())
+    }
+}
+
+/// Individual validation issue wrapped for miette's `#[related]` attribute
+#[derive(Debug, Error, Diagnostic)]
+#[error("{message}")]
+#[allow(unused_assignments)] // Fields used by miette's derive macros
+pub struct ConfigIssueDiagnostic {
+    message: String,
+    /// The layer the issue is in, when it isn't the top-level source
+    #[source_code]
+    src: Option<NamedSource<String>>,
+    #[label("{label}")]
+    span: SourceSpan,
+    label: String,
+    #[help]
+    help: Option<String>,
+}
+
+/// Collection of configuration validation errors
+///
+/// This is the main diagnostic type returned when config validation fails.
+/// It contains the source file and all issues found, sorted by position.
+/// Issues in a base layer carry that layer's file as their own source.
+#[derive(Debug, Error, Diagnostic)]
+#[error(
+    "configuration has {count} error{s}",
+    count = self.issues.len(),
+    s = if self.issues.len() == 1 { "" } else { "s" }
+)]
+#[diagnostic(code(rebinded::config::validation))]
+#[allow(unused_assignments)] // Fields used by miette's derive macros
+pub struct ConfigValidationError {
+    #[source_code]
+    src: NamedSource<String>,
+
+    #[related]
+    issues: Vec<ConfigIssueDiagnostic>,
+}
+
+impl ConfigValidationError {
+    /// Create a validation error from collected issues
+    ///
+    /// Issues are sorted by source position for deterministic output. The
+    /// last source, the one that overrides the others, is the top-level one.
+    #[allow(unused_assignments)] // Field assignments used by miette's derive macros
+    pub fn new(sources: &[ConfigSource], mut issues: Vec<ConfigIssue>) -> Self {
+        // Sort by span start for deterministic, readable output
+        issues.sort_by_key(|i| i.span.start);
+
+        let top = sources
+            .last()
+            .expect("a config load has at least one source");
+        let diagnostics = issues
+            .into_iter()
+            .map(|issue| {
+                let source = sources
+                    .iter()
+                    .find(|source| source.contains(&issue.span))
+                    .unwrap_or(top);
+                let span = source.local(&issue.span);
+                ConfigIssueDiagnostic {
+                    message: issue.message,
+                    src: (source.offset != top.offset).then(|| source.named_source()),
+                    span: (span.start, span.len()).into(),
+                    label: issue.label,
+                    help: issue.help,
+                }
+            })
+            .collect();
+
+        Self {
+            src: top.named_source(),
+            issues: diagnostics,
+        }
+    }
+}
+
+/// Top-level configuration errors
+#[derive(Debug, Error, Diagnostic)]
+pub enum ConfigError {
+    #[error("failed to read config file: {path}")]
+    #[diagnostic(code(rebinded::config::io))]
+    Io {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("failed to fetch config from {url}: {message}")]
+    #[diagnostic(code(rebinded::config::remote))]
+    Remote { url: String, message: String },
+
+    #[error("failed to parse config")]
+    #[diagnostic(code(rebinded::config::parse))]
+    #[allow(unused_assignments)] // Fields used by miette's derive macros
+    Parse {
+        #[source_code]
+        src: NamedSource<String>,
+        #[label("parse error")]
+        span: Option<SourceSpan>,
+        msg: String,
+    },
+
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Validation(#[from] ConfigValidationError),
+}
+
+impl ConfigError {
+    pub fn io(path: impl Into<String>, source: std::io::Error) -> Self {
+        Self::Io {
+            path: path.into(),
+            source,
+        }
+    }
+
+    pub fn remote(url: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::Remote {
+            url: url.into(),
+            message: message.into(),
+        }
+    }
+
+    #[allow(unused_assignments)] // Field assignments used by miette's derive macros
+    pub fn parse(source: &ConfigSource, err: toml::de::Error) -> Self {
+        Self::Parse {
+            src: source.named_source(),
+            span: err.span().map(|r| {
+                let r = source.local(&r);
+                (r.start, r.len()).into()
+            }),
+            msg: err.message().to_string(),
+        }
+    }
+}
@@ -0,0 +1,201 @@
+//! Tracing how one key would be handled (`rebinded explain`)
+//!
+//! Walks the same steps as the engine for a key press in a given window:
+//! strategies subscribed to the key, the binding, each conditional rule in
+//! order, the strategy chain and the action it ends in. Nothing is executed;
+//! power and monitor conditions are checked against the machine as it is now.
+
+use super::{ActionSpec, Condition, RuntimeConfig, WindowInfo};
+use crate::key::{self, InputEventId, KeyCode};
+use crate::platform::EventResponse;
+
+/// Describe, line by line, what a press of `key` would do with `window` focused
+pub fn explain(runtime: &RuntimeConfig, key: KeyCode, window: &WindowInfo) -> String {
+    let mut out = String::new();
+    let mut line = |text: String| {
+        out.push_str(&text);
+        out.push('\n');
+    };
+
+    line(format!(
+        "key: {} ({}, {:#06x})",
+        key::config_name(key),
+        key.display_name(),
+        key.code()
+    ));
+    line(format!(
+        "window: title {:?}, class {:?}, binary {:?}",
+        window.title, window.class, window.binary
+    ));
+
+    let strategy_state = |name: &str| {
+        if runtime.strategy_active(name, window) {
+            format!("{name} (active)")
+        } else {
+            format!("{name} (inactive: condition does not match)")
+        }
+    };
+
+    // Subscribed strategies see the key before its binding, and may block it
+    let mut subscribed: Vec<&String> = Vec::new();
+    for id in [InputEventId::Key(key), InputEventId::AnyKey] {
+        for name in runtime.subscriptions.get(&id).into_iter().flatten() {
+            if !subscribed.contains(&name) {
+                subscribed.push(name);
+            }
+        }
+    }
+    subscribed.sort();
+    if !subscribed.is_empty() {
+        let names: Vec<String> = subscribed.iter().map(|name| strategy_state(name)).collect();
+        line(format!("subscribed strategies: {}", names.join(", ")));
+    }
+
+    let Some(binding) = runtime.bindings.get(&key) else {
+        line(if subscribed.is_empty() {
+            "binding: none".to_string()
+        } else {
+            "binding: none (subscribed strategies may still block the key)".to_string()
+        });
+        line("result: the key passes through".to_string());
+        return out;
+    };
+    line(format!("binding: [bindings.{}]", key::config_name(key)));
+
+    let action = match &binding.action {
+        ActionSpec::Simple(action) => Some(action),
+        ActionSpec::Conditional(rules) => {
+            let mut chosen = None;
+            for (i, rule) in rules.iter().enumerate() {
+                let verdict = match chosen {
+                    Some(_) => "not checked",
+                    None if rule.condition.is_empty() || rule.condition.matches(window) => {
+                        chosen = Some(&rule.action);
+                        "matches"
+                    }
+                    None => "no match",
+                };
+                line(format!(
+                    "rule {}: {} -> {}: {verdict}",
+                    i + 1,
+                    describe_condition(&rule.condition),
+                    rule.action
+                ));
+            }
+            chosen
+        }
+    };
+    let Some(action) = action else {
+        line("result: no rule matches, so the key passes through".to_string());
+        return out;
+    };
+    line(format!("action: {action}"));
+
+    let names: Vec<&String> = binding.strategies.iter().map(|s| s.value()).collect();
+    if !names.is_empty() {
+        let states: Vec<String> = names.iter().map(|name| strategy_state(name)).collect();
+        line(format!("strategies: {}", states.join(" -> ")));
+    }
+    let active: Vec<&String> = names
+        .into_iter()
+        .filter(|name| runtime.strategy_active(name, window))
+        .collect();
+
+    let result = match action.as_response() {
+        Some(EventResponse::Passthrough) => "the key passes through".to_string(),
+        Some(EventResponse::Block) if active.is_empty() => "the key is blocked".to_string(),
+        _ if !active.is_empty() => {
+            let chain: Vec<&str> = active.iter().map(|name| name.as_str()).collect();
+            format!(
+                "{} decides what happens to the key and when {action} runs",
+                chain.join(" -> ")
+            )
+        }
+        _ => format!("{action} runs on press, and the key is blocked"),
+    };
+    line(format!("result: {result}"));
+    out
+}
+
+/// A condition as it would be written in the config
+fn describe_condition(condition: &Condition) -> String {
+    if condition.is_empty() {
+        return "(always)".to_string();
+    }
+    toml::Value::try_from(condition)
+        .map(|value| value.to_string())
+        .unwrap_or_else(|_| format!("{condition:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::load_from_str;
+
+    #[tokio::test]
+    async fn test_explain_conditional_rules() {
+        let toml = r#"
+            [bindings.0x7C]
+            action = [
+                { condition = { window = { binary = "firefox" } }, action = "browser_back" },
+                { action = "media_play_pause" },
+            ]
+        "#;
+        let (_, runtime) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+        let window = WindowInfo {
+            binary: "kitty".to_string(),
+            ..Default::default()
+        };
+        let text = explain(&runtime, KeyCode::new(0x7C), &window);
+
+        assert!(
+            text.contains(
+                r#"rule 1: { window = { binary = "firefox" } } -> browser_back: no match"#
+            ),
+            "{text}"
+        );
+        assert!(
+            text.contains("rule 2: (always) -> media_play_pause: matches"),
+            "{text}"
+        );
+        assert!(
+            text.contains("result: media_play_pause runs on press, and the key is blocked"),
+            "{text}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_explain_strategy_and_unbound() {
+        let toml = r#"
+            [strategies.hold]
+            type = "gated_hold"
+            initial_hold_ms = 500
+            repeat_window_ms = 1000
+            condition = { window = { binary = "mpv" } }
+
+            [bindings.0x7C]
+            action = "media_next"
+            strategy = "hold"
+        "#;
+        let (_, runtime) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+
+        let mpv = WindowInfo {
+            binary: "mpv".to_string(),
+            ..Default::default()
+        };
+        let text = explain(&runtime, KeyCode::new(0x7C), &mpv);
+        assert!(text.contains("strategies: hold (active)"), "{text}");
+        assert!(
+            text.contains("result: hold decides what happens to the key and when media_next runs"),
+            "{text}"
+        );
+
+        let text = explain(&runtime, KeyCode::new(0x7C), &WindowInfo::default());
+        assert!(text.contains("hold (inactive"), "{text}");
+        assert!(text.contains("media_next runs on press"), "{text}");
+
+        let text = explain(&runtime, KeyCode::new(0x7D), &WindowInfo::default());
+        assert!(text.contains("binding: none"), "{text}");
+        assert!(text.contains("result: the key passes through"), "{text}");
+    }
+}
@@ -0,0 +1,4357 @@
+//! Configuration loading, parsing, and validation
+//!
+//! This module handles:
+//! - Loading config from TOML files
+//! - Parsing with span preservation for error reporting
+//! - Validating all references and key names
+//! - Building the runtime configuration
+
+mod dump;
+mod error;
+mod explain;
+mod remote;
+pub mod template;
+mod types;
+
+pub use dump::dump;
+pub use error::{ConfigError, ConfigIssue, ConfigSource, ConfigValidationError};
+pub use explain::explain;
+pub use remote::is_url;
+pub use types::{
+    Action, ActionSpec, Binding, Condition, ConditionalAction, ExecuteNotification, Managed,
+    Spanned, StrategyConfig, WindowCondition, WindowInfo,
+};
+
+use crate::accessibility::Accessibility;
+use crate::integrations::Settings;
+use crate::integrations::dbus::DbusCall;
+use crate::integrations::home_assistant::ServiceCall;
+use crate::integrations::http::Url;
+use crate::integrations::obs::ObsRequest;
+use crate::integrations::shell::ShellCommand;
+use crate::integrations::webhook::Webhook;
+use crate::key::{InputEventId, KeyCode};
+use crate::platform::{
+    EventResponse, FocusTarget, HidDevice, KeyRoutes, MediaCommand, MicCommand, PlayerTarget,
+    SessionCommand, SnapPosition, TriggerTargets,
+};
+use crate::strategy::{
+    ComboConfig, ComboStrategy, DebounceConfig, DebounceStrategy, GatedHoldConfig,
+    GatedHoldStrategy, KeyStrategy, LeaderConfig, LeaderStrategy, MirrorConfig, MirrorStrategy,
+    MouseControl, MouseKeysConfig, MouseKeysStrategy, PushToTalkApp, PushToTalkConfig,
+    PushToTalkStrategy, RepeatConfig, RepeatStrategy, StrategyRegistry, TapHoldConfig,
+    TapHoldStrategy, mirror_pairs,
+};
+use serde::Deserialize;
+use serde::de::IntoDeserializer;
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use toml::de::{DeTable, DeValue};
+use tracing::warn;
+
+/// Parsed configuration before validation
+///
+/// Contains raw parsed data with source spans preserved for error reporting.
+/// Uses HashMap with Spanned keys - the Spanned type implements Hash/Eq based
+/// on value only (ignoring span), so lookups work correctly while preserving
+/// span information for error reporting.
+#[derive(Debug)]
+pub struct Config {
+    /// Strategy definitions keyed by name
+    pub strategies: HashMap<Spanned<String>, StrategyConfig>,
+    /// Window conditions limiting where a strategy applies, keyed by strategy name
+    pub strategy_conditions: HashMap<String, Condition>,
+    /// Key bindings keyed by key name string
+    pub bindings: HashMap<Spanned<String>, Binding>,
+    /// Integration settings from the \[settings\] table
+    pub settings: Settings,
+    /// HID devices from the \[\[hid\]\] tables
+    pub hid: Vec<HidDevice>,
+    /// Restrictions from the system config's \[managed\] table
+    pub managed: Managed,
+}
+
+/// Runtime configuration with resolved key codes and instantiated strategies
+///
+/// This is built from Config at startup, resolving all key name strings
+/// to platform-native KeyCodes for fast lookup during event processing.
+pub struct RuntimeConfig {
+    /// Maps key codes to their bindings
+    pub bindings: HashMap<KeyCode, Binding>,
+    /// Instantiated strategies, keyed by name
+    pub strategies: HashMap<String, Rc<RefCell<dyn KeyStrategy>>>,
+    /// Maps subscribed event IDs to the strategies that want to receive them
+    pub subscriptions: HashMap<InputEventId, Vec<String>>,
+    /// Window conditions limiting where a strategy applies, keyed by strategy name
+    pub strategy_conditions: HashMap<String, Condition>,
+    /// The config each strategy was built from, including `strategy@binding`
+    /// clones with overrides applied
+    pub strategy_configs: HashMap<String, StrategyConfig>,
+    /// HID devices the platform reads `hid:` keys from
+    pub hid: Vec<HidDevice>,
+    /// Accessibility modes applied to every key, with their state
+    pub accessibility: Accessibility,
+}
+
+impl std::fmt::Debug for RuntimeConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RuntimeConfig")
+            .field("bindings", &self.bindings)
+            .field(
+                "strategies",
+                &format!("<{} strategies>", self.strategies.len()),
+            )
+            .field(
+                "subscriptions",
+                &format!("<{} subscribed events>", self.subscriptions.len()),
+            )
+            .finish()
+    }
+}
+
+impl RuntimeConfig {
+    /// Whether a strategy applies in the given window
+    ///
+    /// Strategies without a condition apply everywhere.
+    pub fn strategy_active(&self, name: &str, window: &WindowInfo) -> bool {
+        self.strategy_conditions
+            .get(name)
+            .is_none_or(|condition| condition.matches(window))
+    }
+
+    /// Resolve which action to take for a given key and window context
+    pub fn resolve_action(&self, key: KeyCode, window: &WindowInfo) -> Option<&Action> {
+        let binding = self.bindings.get(&key)?;
+
+        match &binding.action {
+            ActionSpec::Simple(action) => Some(action),
+            ActionSpec::Conditional(rules) => {
+                for rule in rules {
+                    if rule.condition.is_empty() || rule.condition.matches(window) {
+                        return Some(&rule.action);
+                    }
+                }
+                // Implicit passthrough when no rules match
+                None
+            }
+        }
+    }
+
+    /// Keys used by bindings or strategy subscriptions
+    fn bound_keys(&self) -> impl Iterator<Item = &KeyCode> {
+        let subscribed = self.subscriptions.keys().filter_map(|id| match id {
+            InputEventId::Key(key) => Some(key),
+            _ => None,
+        });
+        self.bindings.keys().chain(subscribed)
+    }
+
+    /// Scancodes used by bindings or strategy subscriptions, which the
+    /// platform has to report by scancode instead of by key code
+    pub fn scancodes(&self) -> BTreeSet<u16> {
+        self.bound_keys().filter_map(KeyCode::scancode).collect()
+    }
+
+    /// Whether any binding or strategy subscription uses a MIDI input, which
+    /// decides if the platform opens MIDI devices at all
+    pub fn uses_midi(&self) -> bool {
+        self.bound_keys().any(|key| key.midi().is_some())
+    }
+
+    /// Whether any binding or strategy subscription uses a gamepad button,
+    /// which decides if the platform reads gamepads at all
+    pub fn uses_gamepad(&self) -> bool {
+        self.bound_keys().any(|key| key.gamepad().is_some())
+    }
+
+    /// Bound keys and the actions they perform, for the trigger endpoint
+    pub fn trigger_targets(&self) -> TriggerTargets {
+        TriggerTargets::new(self.bindings.iter().map(|(key, binding)| {
+            let actions = match &binding.action {
+                ActionSpec::Simple(action) => vec![action.to_string()],
+                ActionSpec::Conditional(rules) => {
+                    rules.iter().map(|rule| rule.action.to_string()).collect()
+                }
+            };
+            (*key, actions)
+        }))
+    }
+
+    /// How the platform can treat each key without asking the event handler
+    ///
+    /// Bindings that always block or always pass through, with no strategy or
+    /// window condition, are answered by the platform directly. Other bound
+    /// keys and keys strategies subscribe to go through the handler. `None`
+    /// when a strategy subscribes to every key or an accessibility mode is on.
+    pub fn key_routes(&self) -> Option<KeyRoutes> {
+        if self.subscriptions.contains_key(&InputEventId::AnyKey) || self.accessibility.is_active()
+        {
+            return None;
+        }
+        let mut routes = KeyRoutes::default();
+        for id in self.subscriptions.keys() {
+            if let InputEventId::Key(key) = id {
+                routes.handled.insert(*key);
+            }
+        }
+        for (key, binding) in &self.bindings {
+            if routes.handled.contains(key) {
+                continue;
+            }
+            let fixed = match &binding.action {
+                ActionSpec::Simple(action) if binding.strategies.is_empty() => action.as_response(),
+                _ => None,
+            };
+            match fixed {
+                Some(EventResponse::Block) => routes.blocked.insert(*key),
+                Some(EventResponse::Passthrough | EventResponse::Continue) => false,
+                None => routes.handled.insert(*key),
+            };
+        }
+        Some(routes)
+    }
+}
+
+/// Path of the system-wide config every user config is layered over:
+/// `/etc/rebinded/config.toml`, or `%ProgramData%\\rebinded\\config.toml` on Windows
+pub fn system_config_path() -> PathBuf {
+    #[cfg(windows)]
+    let root = std::env::var_os("ProgramData")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(r"C:\ProgramData"));
+    #[cfg(not(windows))]
+    let root = PathBuf::from("/etc");
+    root.join("rebinded").join("config.toml")
+}
+
+/// Whether the system config lets user configs run commands, read from its
+/// `[managed]` table before anything else loads so plugins in the user's config
+/// directory can be skipped. A system config that can't be read or parsed
+/// allows nothing; loading it reports why.
+pub fn user_commands_allowed() -> bool {
+    let path = system_config_path();
+    if !path.is_file() {
+        return true;
+    }
+    let table = match std::fs::read_to_string(&path).map(|text| text.parse::<toml::Table>()) {
+        Ok(Ok(table)) => table,
+        _ => return false,
+    };
+    table
+        .get("managed")
+        .and_then(|managed| managed.get("allow_commands"))
+        .is_none_or(|allow| allow.as_bool() != Some(false))
+}
+
+/// How [`load_with`] treats the files it reads
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LoadOptions {
+    /// Accept `http://` config URLs as well as `https://` ones
+    pub allow_plain_http: bool,
+}
+
+/// Load and validate configuration from a file or `https://` URL, with
+/// default [`LoadOptions`]
+pub async fn load(path: impl AsRef<Path>) -> Result<(Config, RuntimeConfig), ConfigError> {
+    load_with(path, LoadOptions::default()).await
+}
+
+/// Load and validate configuration from a file or URL
+///
+/// When the system-wide config exists it is loaded first, with this file
+/// layered over it (see [`load_layers`]). Files listed in a layer's `include`
+/// are layered beneath it. Remote files are cached once they load, and the
+/// cached copy stands in when the server is unreachable or sends a config
+/// that fails to load. Returns the parsed config and runtime config, or a
+/// detailed error with source locations for all validation issues found.
+pub async fn load_with(
+    path: impl AsRef<Path>,
+    options: LoadOptions,
+) -> Result<(Config, RuntimeConfig), ConfigError> {
+    let path = path.as_ref();
+    let system = system_config_path();
+    let mut locations = Vec::new();
+    if system.is_file() {
+        locations.push((system.display().to_string(), true));
+    }
+    if system != path {
+        locations.push((path.display().to_string(), false));
+    }
+
+    let mut system_layers = Vec::new();
+    let mut user_layers = Vec::new();
+    let mut fetched = Vec::new();
+    for (location, is_system) in locations {
+        let layers = if is_system {
+            &mut system_layers
+        } else {
+            &mut user_layers
+        };
+        let (name, content, remote) = read_layer(&location, options).await?;
+        for include in includes(&content) {
+            let include = resolve_include(&location, &include);
+            let (name, content, remote) = read_layer(&include, options).await?;
+            fetched.extend(remote.map(|remote| (is_system, layers.len(), remote)));
+            layers.push((name, content));
+        }
+        fetched.extend(remote.map(|remote| (is_system, layers.len(), remote)));
+        layers.push((name, content));
+    }
+
+    match load_layers(system_layers.clone(), user_layers.clone()).await {
+        Ok(loaded) => {
+            for (_, _, remote) in &fetched {
+                remote.store();
+            }
+            Ok(loaded)
+        }
+        Err(err)
+            if fetched
+                .iter()
+                .any(|(_, _, remote)| remote.fallback.is_some()) =>
+        {
+            warn!("remote config failed to load, using the cached copy: {err}");
+            for (is_system, index, remote) in &fetched {
+                let layers = if *is_system {
+                    &mut system_layers
+                } else {
+                    &mut user_layers
+                };
+                if let Some(fallback) = &remote.fallback {
+                    layers[*index].1 = fallback.clone();
+                }
+            }
+            load_layers(system_layers, user_layers).await
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Read one config layer, from a file or a URL
+async fn read_layer(
+    location: &str,
+    options: LoadOptions,
+) -> Result<(String, String, Option<remote::Fetched>), ConfigError> {
+    if remote::is_url(location) {
+        let fetched = remote::fetch(location, options.allow_plain_http).await?;
+        return Ok((location.to_string(), fetched.content.clone(), Some(fetched)));
+    }
+    let content = std::fs::read_to_string(location).map_err(|e| ConfigError::io(location, e))?;
+    Ok((location.to_string(), content, None))
+}
+
+/// The `include` list of a config, read ahead of parsing
+///
+/// Malformed configs yield nothing here; the full parse reports them.
+fn includes(content: &str) -> Vec<String> {
+    #[derive(Deserialize)]
+    struct Includes {
+        #[serde(default)]
+        include: Vec<String>,
+    }
+    toml::from_str::<Includes>(content)
+        .map(|includes| includes.include)
+        .unwrap_or_default()
+}
+
+/// Where an include points: URLs as they are, and paths relative to the
+/// including file's directory (or URL)
+fn resolve_include(including: &str, include: &str) -> String {
+    if remote::is_url(include) {
+        return include.to_string();
+    }
+    if remote::is_url(including) {
+        return match including.rsplit_once('/') {
+            Some((base, _)) => format!("{base}/{include}"),
+            None => include.to_string(),
+        };
+    }
+    match Path::new(including).parent() {
+        Some(dir) => dir.join(include).display().to_string(),
+        None => include.to_string(),
+    }
+}
+
+/// Load and validate configuration from a string
+///
+/// Useful for testing and when config content is already in memory.
+pub async fn load_from_str(
+    source_name: &str,
+    content: String,
+) -> Result<(Config, RuntimeConfig), ConfigError> {
+    load_layers(Vec::new(), vec![(source_name.to_string(), content)]).await
+}
+
+/// Load and validate configuration from `(name, content)` layers, base first:
+/// the system config's layers, then the user's
+///
+/// Later layers take precedence: a binding or strategy they define replaces
+/// the base layer's entry of the same name, `[settings]` tables are merged
+/// value by value, and `[[hid]]` devices from every layer are kept. Keys
+/// bound under different names in different layers (`f13` and `0x7C`) are
+/// reported as duplicates pointing at both files. The system layers' `[managed]`
+/// table restricts what the user layers may do.
+pub async fn load_layers(
+    system: Vec<(String, String)>,
+    user: Vec<(String, String)>,
+) -> Result<(Config, RuntimeConfig), ConfigError> {
+    let mut loader = ConfigLoader::new(system, user);
+    loader.parse_and_build()
+}
+
+/// Internal config loader that tracks parsing state and validation issues
+struct ConfigLoader {
+    /// Config layers, base first, sharing one span space
+    sources: Vec<ConfigSource>,
+    issues: Vec<ConfigIssue>,
+    /// `hid:` keys defined by this config's \[\[hid\]\] tables
+    hid_keys: HashSet<KeyCode>,
+}
+
+impl ConfigLoader {
+    fn new(system: Vec<(String, String)>, user: Vec<(String, String)>) -> Self {
+        let mut sources: Vec<ConfigSource> = Vec::new();
+        let layers = system
+            .into_iter()
+            .map(|layer| (layer, true))
+            .chain(user.into_iter().map(|layer| (layer, false)));
+        for ((name, content), system) in layers {
+            // A gap of one byte keeps a span at the very end of one layer from
+            // reading as the start of the next
+            let offset = sources
+                .last()
+                .map_or(0, |last| last.offset + last.content.len() + 1);
+            sources.push(ConfigSource {
+                name,
+                content,
+                offset,
+                system,
+            });
+        }
+        Self {
+            sources,
+            issues: Vec::new(),
+            hid_keys: HashSet::new(),
+        }
+    }
+
+    /// Parse content and build runtime config
+    fn parse_and_build(&mut self) -> Result<(Config, RuntimeConfig), ConfigError> {
+        // Parse into spanned tables for location tracking. Each layer is parsed
+        // behind blank padding up to its offset, so its spans land in the shared
+        // span space. DeTable<'a> borrows the text, which is kept outside self
+        // since parse_table needs self mutably.
+        let texts: Vec<String> = self
+            .sources
+            .iter()
+            .map(|source| " ".repeat(source.offset) + source.content.as_str())
+            .collect();
+        let mut merged: Option<DeTable> = None;
+        for (index, text) in texts.iter().enumerate() {
+            let source = &self.sources[index];
+            let mut table = DeTable::parse(text)
+                .map_err(|e| ConfigError::parse(source, e))?
+                .into_inner();
+            if !source.system {
+                self.restrict_user_layer(&mut table, merged.as_ref());
+            }
+            match &mut merged {
+                Some(base) => merge_layer(base, table),
+                None => merged = Some(table),
+            }
+        }
+
+        let config = self.parse_table(merged.unwrap_or_default());
+        let runtime = self.build_runtime(&config);
+
+        if self.issues.is_empty() {
+            Ok((config, runtime))
+        } else {
+            Err(ConfigValidationError::new(&self.sources, std::mem::take(&mut self.issues)).into())
+        }
+    }
+
+    /// Hold a user layer to the system config's \[managed\] restrictions,
+    /// before it is merged over `base`
+    fn restrict_user_layer(&mut self, table: &mut DeTable, base: Option<&DeTable>) {
+        if let Some((key, _)) = table.iter().find(|(key, _)| key.get_ref() == "managed") {
+            self.issues.push(ConfigIssue {
+                span: key.span(),
+                message: "[managed] can only be set in the system config".to_string(),
+                label: "user config".to_string(),
+                help: Some(format!("move it to {}", system_config_path().display())),
+            });
+            table.remove("managed");
+        }
+
+        let allow_commands = base
+            .and_then(|base| base.get("managed"))
+            .and_then(|managed| match managed.get_ref() {
+                DeValue::Table(managed) => managed.get("allow_commands"),
+                _ => None,
+            })
+            .is_none_or(|allow| !matches!(allow.get_ref(), DeValue::Boolean(false)));
+        if !allow_commands {
+            let mut spans = Vec::new();
+            for (key, value) in table.iter() {
+                if matches!(key.get_ref().as_ref(), "bindings" | "strategies") {
+                    command_spans(value.get_ref(), &mut spans);
+                }
+            }
+            for span in spans {
+                self.issues.push(ConfigIssue::command_not_allowed(span));
+            }
+        }
+    }
+
+    /// Whether a span points into a user layer
+    fn in_user_layer(&self, span: &types::Span) -> bool {
+        self.sources
+            .iter()
+            .find(|source| source.contains(span))
+            .is_some_and(|source| !source.system)
+    }
+
+    /// Where a span was defined, for pointing at an earlier definition from
+    /// an issue at `relative_to`
+    fn describe_span(&self, span: &types::Span, relative_to: &types::Span) -> String {
+        let find = |span: &types::Span| {
+            self.sources
+                .iter()
+                .find(|source| source.contains(span))
+                .unwrap_or(&self.sources[self.sources.len() - 1])
+        };
+        find(span).describe(span, find(relative_to))
+    }
+
+    /// Parse the root TOML table into a Config
+    fn parse_table(&mut self, table: DeTable) -> Config {
+        let mut strategies = HashMap::new();
+        let mut strategy_conditions = HashMap::new();
+        let mut bindings = HashMap::new();
+        let mut settings = Settings::default();
+        let mut hid = Vec::new();
+        let mut managed = Managed::default();
+
+        for (key, value) in table {
+            let key_str = key.get_ref().as_ref();
+
+            match key_str {
+                "strategies" => {
+                    (strategies, strategy_conditions) = self.parse_strategies(value);
+                }
+                "bindings" => {
+                    bindings = self.parse_bindings(value);
+                }
+                "settings" => {
+                    settings = self.parse_settings(value);
+                }
+                "hid" => {
+                    hid = self.parse_hid(value);
+                }
+                "managed" => {
+                    managed = self.parse_managed(value);
+                }
+                "include" => {
+                    // Already layered in by load(); only the shape is checked here
+                    let span = value.span();
+                    if Vec::<String>::deserialize(value.into_deserializer()).is_err() {
+                        self.issues.push(ConfigIssue {
+                            span,
+                            message: "include must be a list of file paths or URLs".to_string(),
+                            label: "expected array of strings".to_string(),
+                            help: Some(
+                                "example: include = [\"https://config.lan/shared.toml\"]"
+                                    .to_string(),
+                            ),
+                        });
+                    }
+                }
+                _ => {
+                    // Unknown top-level key - could add a warning here
+                }
+            }
+        }
+
+        Config {
+            strategies,
+            strategy_conditions,
+            bindings,
+            settings,
+            hid,
+            managed,
+        }
+    }
+
+    /// Parse the system config's \[managed\] table
+    fn parse_managed(&mut self, value: toml::Spanned<DeValue>) -> Managed {
+        let mut managed = Managed::default();
+        let span = value.span();
+        let DeValue::Table(table) = value.into_inner() else {
+            self.issues.push(ConfigIssue {
+                span,
+                message: "[managed] must be a table".to_string(),
+                label: "expected table".to_string(),
+                help: None,
+            });
+            return managed;
+        };
+
+        for (key, value) in table {
+            match key.get_ref().as_ref() {
+                "locked_bindings" => managed.locked_bindings = self.parse_key_list(value),
+                "allow_commands" => match value.get_ref() {
+                    DeValue::Boolean(allow) => managed.allow_commands = *allow,
+                    _ => self.issues.push(ConfigIssue {
+                        span: value.span(),
+                        message: "allow_commands must be a bool".to_string(),
+                        label: "expected bool".to_string(),
+                        help: None,
+                    }),
+                },
+                name => self.issues.push(ConfigIssue {
+                    span: key.span(),
+                    message: format!("unknown [managed] setting '{name}'"),
+                    label: "unknown setting".to_string(),
+                    help: Some("valid settings: locked_bindings, allow_commands".to_string()),
+                }),
+            }
+        }
+        managed
+    }
+
+    /// Parse the \[\[hid\]\] tables, defining their `hid:` key names
+    fn parse_hid(&mut self, value: toml::Spanned<DeValue>) -> Vec<HidDevice> {
+        let span = value.span();
+        let devices = match Vec::<HidDevice>::deserialize(value.into_deserializer()) {
+            Ok(devices) => devices,
+            Err(e) => {
+                self.issues.push(ConfigIssue {
+                    span: e.span().unwrap_or(span),
+                    message: format!("invalid hid device: {}", e.message()),
+                    label: "invalid hid device".to_string(),
+                    help: Some(
+                        "example: [[hid]] device = \"*Foot Switch*\", keys = { pedal = \"0xFF00:0x01\" }"
+                            .to_string(),
+                    ),
+                });
+                return Vec::new();
+            }
+        };
+        if cfg!(windows) && !devices.is_empty() {
+            self.issues.push(ConfigIssue {
+                span,
+                message: "[[hid]] devices are only supported on Linux".to_string(),
+                label: "hid device".to_string(),
+                help: None,
+            });
+            return Vec::new();
+        }
+        for device in &devices {
+            if device.device.is_none() && device.vendor.is_none() && device.product.is_none() {
+                self.issues.push(ConfigIssue {
+                    span: span.clone(),
+                    message: "hid device needs `device`, `vendor` or `product` to select it"
+                        .to_string(),
+                    label: "hid device".to_string(),
+                    help: None,
+                });
+            }
+            for name in device.keys.keys() {
+                let key = KeyCode::from_hid_name(name);
+                if !self.hid_keys.insert(key) {
+                    self.issues.push(ConfigIssue {
+                        span: span.clone(),
+                        message: format!("hid key '{name}' is defined more than once"),
+                        label: "duplicate hid key".to_string(),
+                        help: None,
+                    });
+                }
+            }
+        }
+        devices
+    }
+
+    /// Parse a key specifier, treating `hid:` keys this config doesn't define
+    /// as unknown
+    fn parse_key(&self, key_str: &str) -> Option<KeyCode> {
+        KeyCode::from_config_str(key_str)
+            .filter(|key| key.hid_name().is_none() || self.hid_keys.contains(key))
+    }
+
+    /// Parse the \[settings\] section
+    fn parse_settings(&mut self, value: toml::Spanned<DeValue>) -> Settings {
+        let span = value.span();
+        match Settings::deserialize(value.into_deserializer()) {
+            Ok(settings) => {
+                if cfg!(windows) && settings.lirc.socket.is_some() {
+                    self.issues.push(ConfigIssue {
+                        span: span.clone(),
+                        message: "lirc is only supported on Linux".to_string(),
+                        label: "lirc socket".to_string(),
+                        help: None,
+                    });
+                }
+                if cfg!(windows) && settings.cec.enabled {
+                    self.issues.push(ConfigIssue {
+                        span: span.clone(),
+                        message: "HDMI-CEC input is only supported on Linux".to_string(),
+                        label: "cec".to_string(),
+                        help: None,
+                    });
+                }
+                for key in &settings.accessibility.slow_keys {
+                    if KeyCode::from_config_str(key).is_none() {
+                        self.issues.push(ConfigIssue {
+                            span: span.clone(),
+                            message: format!("unknown key '{key}' in slow_keys"),
+                            label: "slow_keys".to_string(),
+                            help: Some("run `rebinded keys` to list key names".to_string()),
+                        });
+                    }
+                }
+                if let Some(listen) = settings.trigger.listen {
+                    if !listen.ip().is_loopback() && !settings.trigger.allow_remote {
+                        self.issues.push(ConfigIssue {
+                            span: span.clone(),
+                            message: format!(
+                                "trigger endpoint {listen} is reachable from other devices"
+                            ),
+                            label: "trigger listen".to_string(),
+                            help: Some(
+                                "listen on 127.0.0.1, or set allow_remote = true to expose it"
+                                    .to_string(),
+                            ),
+                        });
+                    }
+                    if settings.trigger.token.as_deref().is_none_or(str::is_empty) {
+                        self.issues.push(ConfigIssue {
+                            span: span.clone(),
+                            message: "trigger endpoint needs a token".to_string(),
+                            label: "trigger token".to_string(),
+                            help: Some(
+                                "requests must send it as `Authorization: Bearer <token>`"
+                                    .to_string(),
+                            ),
+                        });
+                    }
+                }
+                if let Some(url) = &settings.home_assistant.url
+                    && let Err(e) = Url::parse(url)
+                {
+                    self.issues.push(ConfigIssue {
+                        span,
+                        message: format!("invalid home_assistant url {e}"),
+                        label: "invalid url".to_string(),
+                        help: Some(
+                            "example: url = \"http://homeassistant.local:8123\"".to_string(),
+                        ),
+                    });
+                }
+                settings
+            }
+            Err(e) => {
+                self.issues.push(ConfigIssue {
+                    span: e.span().unwrap_or(span),
+                    message: format!("invalid settings: {}", e.message()),
+                    label: "invalid setting".to_string(),
+                    help: None,
+                });
+                Settings::default()
+            }
+        }
+    }
+
+    /// Parse the \[strategies\] section, along with any per-strategy window conditions
+    fn parse_strategies(
+        &mut self,
+        value: toml::Spanned<DeValue>,
+    ) -> (
+        HashMap<Spanned<String>, StrategyConfig>,
+        HashMap<String, Condition>,
+    ) {
+        let mut result = HashMap::new();
+        let mut conditions = HashMap::new();
+
+        let DeValue::Table(table) = value.into_inner() else {
+            return (result, conditions);
+        };
+
+        for (name_spanned, config_spanned) in table {
+            let name = name_spanned.get_ref().to_string();
+            let name_span = name_spanned.span();
+            let config_span = config_spanned.span();
+
+            if let Some(condition) = self.parse_strategy_condition(&config_spanned) {
+                conditions.insert(name.clone(), condition);
+            }
+
+            // Registered strategy types carry their fields through as TOML
+            if let Some(type_name) = registered_type(config_spanned.get_ref()) {
+                match toml::Table::deserialize(config_spanned.into_deserializer()) {
+                    Ok(mut settings) => {
+                        settings.remove("type");
+                        settings.remove("condition");
+                        let config = StrategyConfig::Registered {
+                            type_name,
+                            settings,
+                        };
+                        result.insert(Spanned::new(name, name_span), config);
+                    }
+                    Err(e) => {
+                        self.issues.push(ConfigIssue {
+                            span: config_span,
+                            message: format!("invalid strategy config: {e}"),
+                            label: "invalid strategy".to_string(),
+                            help: None,
+                        });
+                    }
+                }
+                continue;
+            }
+
+            // Deserialize the strategy config directly using IntoDeserializer
+            match StrategyConfig::deserialize(config_spanned.into_deserializer()) {
+                Ok(config) => {
+                    result.insert(Spanned::new(name, name_span), config);
+                }
+                Err(e) => {
+                    self.issues.push(ConfigIssue {
+                        span: config_span,
+                        message: format!("invalid strategy config: {e}"),
+                        label: "invalid strategy".to_string(),
+                        help: None,
+                    });
+                }
+            }
+        }
+
+        (result, conditions)
+    }
+
+    /// Parse the optional `condition` field of a strategy table
+    fn parse_strategy_condition(&mut self, value: &toml::Spanned<DeValue>) -> Option<Condition> {
+        let DeValue::Table(table) = value.get_ref() else {
+            return None;
+        };
+        let (_, condition_value) = table
+            .iter()
+            .find(|(k, _)| k.get_ref().as_ref() == "condition")?;
+
+        match Condition::deserialize(condition_value.clone().into_deserializer()) {
+            Ok(condition) if !condition.is_empty() => Some(condition),
+            Ok(_) => None,
+            Err(e) => {
+                self.issues.push(ConfigIssue {
+                    span: condition_value.span(),
+                    message: format!("invalid strategy condition: {e}"),
+                    label: "invalid condition".to_string(),
+                    help: Some(
+                        "example: condition = { window = { binary = \"firefox\" } }".to_string(),
+                    ),
+                });
+                None
+            }
+        }
+    }
+
+    /// Parse the \[bindings\] section
+    fn parse_bindings(
+        &mut self,
+        value: toml::Spanned<DeValue>,
+    ) -> HashMap<Spanned<String>, Binding> {
+        let mut result = HashMap::new();
+
+        let DeValue::Table(table) = value.into_inner() else {
+            return result;
+        };
+
+        for (key_spanned, binding_spanned) in table {
+            let key_name = key_spanned.get_ref().to_string();
+            let key_span = key_spanned.span();
+
+            if let Some(binding) = self.parse_binding(binding_spanned) {
+                result.insert(Spanned::new(key_name, key_span), binding);
+            }
+        }
+
+        result
+    }
+
+    /// Parse a single binding entry
+    fn parse_binding(&mut self, value: toml::Spanned<DeValue>) -> Option<Binding> {
+        let binding_span = value.span();
+        let DeValue::Table(table) = value.into_inner() else {
+            self.issues.push(ConfigIssue {
+                span: binding_span,
+                message: "binding must be a table".to_string(),
+                label: "expected table".to_string(),
+                help: Some("example: [bindings.f13]\naction = \"media_play_pause\"".to_string()),
+            });
+            return None;
+        };
+
+        let mut action: Option<(ActionSpec, toml::Value)> = None;
+        let mut strategies: Vec<Spanned<String>> = Vec::new();
+        let mut keys: Vec<Spanned<String>> = Vec::new();
+        let mut overrides: Vec<(Spanned<String>, toml::Value)> = Vec::new();
+        let mut notify_on_execute = None;
+
+        for (field_key, field_value) in table {
+            let field_name = field_key.get_ref().as_ref();
+
+            match field_name {
+                "action" => {
+                    let source = toml::Value::deserialize(field_value.clone().into_deserializer());
+                    action = self.parse_action_spec(field_value).zip(source.ok());
+                }
+                "strategy" => {
+                    strategies = self.parse_strategy_list(field_value);
+                }
+                "keys" => {
+                    keys = self.parse_key_list(field_value);
+                }
+                "notify_on_execute" => {
+                    notify_on_execute = self.parse_notify_on_execute(field_value);
+                }
+                _ => {
+                    // Anything else overrides a setting of the binding's strategy,
+                    // validated once strategies are known
+                    let field = Spanned::new(field_name.to_string(), field_key.span());
+                    match toml::Value::deserialize(field_value.into_deserializer()) {
+                        Ok(value) => overrides.push((field, value)),
+                        Err(e) => self.issues.push(ConfigIssue {
+                            span: field.span().clone(),
+                            message: format!("invalid value for '{}': {e}", field.value()),
+                            label: "invalid value".to_string(),
+                            help: None,
+                        }),
+                    }
+                }
+            }
+        }
+
+        let Some((action, action_source)) = action else {
+            self.issues.push(ConfigIssue {
+                span: binding_span,
+                message: "binding missing required 'action' field".to_string(),
+                label: "missing action".to_string(),
+                help: Some("add: action = \"media_play_pause\"".to_string()),
+            });
+            return None;
+        };
+
+        Some(Binding {
+            action,
+            action_source,
+            strategies,
+            keys,
+            overrides,
+            notify_on_execute,
+        })
+    }
+
+    /// Parse a binding's `notify_on_execute` field: a bool, or a message to show
+    fn parse_notify_on_execute(
+        &mut self,
+        value: toml::Spanned<DeValue>,
+    ) -> Option<ExecuteNotification> {
+        let span = value.span();
+        match value.into_inner() {
+            DeValue::Boolean(true) => Some(ExecuteNotification::Describe),
+            DeValue::Boolean(false) => None,
+            DeValue::String(message) => Some(ExecuteNotification::Message(message.to_string())),
+            _ => {
+                self.issues.push(ConfigIssue {
+                    span,
+                    message: "notify_on_execute must be a bool or a message string".to_string(),
+                    label: "invalid type".to_string(),
+                    help: Some("example: notify_on_execute = \"Mic muted\"".to_string()),
+                });
+                None
+            }
+        }
+    }
+
+    /// Parse a binding's `strategy` field: a single name or an ordered chain of names
+    fn parse_strategy_list(&mut self, value: toml::Spanned<DeValue>) -> Vec<Spanned<String>> {
+        let span = value.span();
+        match value.into_inner() {
+            DeValue::String(s) => vec![Spanned::new(s.to_string(), span)],
+            DeValue::Array(arr) => {
+                if arr.is_empty() {
+                    self.issues.push(ConfigIssue {
+                        span,
+                        message: "strategy chain is empty".to_string(),
+                        label: "empty array".to_string(),
+                        help: Some("remove the field to execute the action directly".to_string()),
+                    });
+                    return Vec::new();
+                }
+
+                let mut strategies: Vec<Spanned<String>> = Vec::new();
+                for item in arr {
+                    let item_span = item.span();
+                    let DeValue::String(s) = item.get_ref() else {
+                        self.issues.push(ConfigIssue {
+                            span: item_span,
+                            message: "strategy chain entries must be strings".to_string(),
+                            label: "expected string".to_string(),
+                            help: None,
+                        });
+                        continue;
+                    };
+
+                    let name = Spanned::new(s.to_string(), item_span);
+                    if strategies.contains(&name) {
+                        self.issues.push(ConfigIssue {
+                            span: name.span().clone(),
+                            message: format!("strategy '{s}' appears twice in chain"),
+                            label: "duplicate strategy".to_string(),
+                            help: None,
+                        });
+                        continue;
+                    }
+                    strategies.push(name);
+                }
+                strategies
+            }
+            _ => {
+                self.issues.push(ConfigIssue {
+                    span,
+                    message: "strategy must be a string or an array of strings".to_string(),
+                    label: "expected string or array".to_string(),
+                    help: Some(
+                        "example: strategy = \"hold\"\n\
+                         or a chain: strategy = [\"debounce\", \"hold\"]"
+                            .to_string(),
+                    ),
+                });
+                Vec::new()
+            }
+        }
+    }
+
+    /// Parse a `keys = [...]` list of key specifiers for a group binding
+    fn parse_key_list(&mut self, value: toml::Spanned<DeValue>) -> Vec<Spanned<String>> {
+        let span = value.span();
+        let DeValue::Array(arr) = value.into_inner() else {
+            self.issues.push(ConfigIssue {
+                span,
+                message: "keys must be an array of strings".to_string(),
+                label: "expected array".to_string(),
+                help: Some("example: keys = [\"f13\", \"f14\", \"f20..f24\"]".to_string()),
+            });
+            return Vec::new();
+        };
+
+        if arr.is_empty() {
+            self.issues.push(ConfigIssue {
+                span,
+                message: "keys must not be empty".to_string(),
+                label: "empty key list".to_string(),
+                help: None,
+            });
+            return Vec::new();
+        }
+
+        let mut keys = Vec::new();
+        for item in arr {
+            let item_span = item.span();
+            if let DeValue::String(s) = item.get_ref() {
+                keys.push(Spanned::new(s.to_string(), item_span));
+            } else {
+                self.issues.push(ConfigIssue {
+                    span: item_span,
+                    message: "key must be a string".to_string(),
+                    label: "expected string".to_string(),
+                    help: None,
+                });
+            }
+        }
+        keys
+    }
+
+    /// Parse an action specification (simple string or conditional array)
+    fn parse_action_spec(&mut self, value: toml::Spanned<DeValue>) -> Option<ActionSpec> {
+        let span = value.span();
+
+        match value.into_inner() {
+            DeValue::String(s) => {
+                // Simple action string
+                match parse_action(&s) {
+                    Ok(action) => Some(ActionSpec::Simple(action)),
+                    Err(e) => {
+                        self.issues.push(ConfigIssue {
+                            span,
+                            message: e,
+                            label: "unknown action".to_string(),
+                            help: Some(
+                                "valid actions: media_play_pause, media_next, media_previous, \
+                                 media_stop, volume_up, volume_down, volume_mute, \
+                                 mic_mute, mic_unmute, mic_toggle, browser_back, \
+                                 browser_forward, desktop_next, desktop_previous, \
+                                 desktop_<n>, window_next, window_previous, \
+                                 snap_left, snap_right, snap_top, snap_bottom, \
+                                 snap_top_left, snap_top_right, snap_bottom_left, \
+                                 snap_bottom_right, snap_maximize, lock_screen, \
+                                 system_sleep, display_off, passthrough, block"
+                                    .to_string(),
+                            ),
+                        });
+                        None
+                    }
+                }
+            }
+            DeValue::Table(table) => {
+                // Action with arguments, e.g. { clipboard = "text" } or
+                // { type = "volume_up", step = 5 }
+                let field_spans = action_field_spans(&table);
+                let table = toml::Spanned::new(span.clone(), DeValue::Table(table));
+                let parsed = toml::Value::deserialize(table.into_deserializer())
+                    .map_err(|e| e.message().to_string())
+                    .and_then(|value| parse_action_value(&value));
+                match parsed {
+                    Ok(action) => Some(ActionSpec::Simple(action)),
+                    Err(e) => {
+                        self.issues.push(ConfigIssue {
+                            span: action_error_span(&field_spans, &e).unwrap_or(span),
+                            message: e,
+                            label: "invalid action".to_string(),
+                            help: Some(
+                                "actions with arguments: clipboard, obs, home_assistant, http, dbus, notify, run, focus, \
+                                 media_* (player), volume_up/volume_down (step)\n\
+                                 example: action = { clipboard = \"text\" }\n\
+                                 or: action = { type = \"volume_up\", step = 5 }"
+                                    .to_string(),
+                            ),
+                        });
+                        None
+                    }
+                }
+            }
+            DeValue::Array(arr) if !is_conditional_array(&arr) => {
+                // Action chain, e.g. ["media_play_pause", { run = "..." }]
+                let steps = toml::Spanned::new(span.clone(), DeValue::Array(arr));
+                let parsed = toml::Value::deserialize(steps.into_deserializer())
+                    .map_err(|e| e.message().to_string())
+                    .and_then(|value| parse_action_value(&value));
+                match parsed {
+                    Ok(action) => Some(ActionSpec::Simple(action)),
+                    Err(e) => {
+                        self.issues.push(ConfigIssue {
+                            span,
+                            message: e,
+                            label: "invalid action chain".to_string(),
+                            help: Some(
+                                "each step is an action string or table: \
+                                 action = [\"media_play_pause\", { run = \"...\" }]"
+                                    .to_string(),
+                            ),
+                        });
+                        None
+                    }
+                }
+            }
+            DeValue::Array(arr) => {
+                // Conditional action array
+                let mut rules = Vec::new();
+                for item in arr {
+                    let item_span = item.span();
+                    match ConditionalAction::deserialize(item.into_deserializer()) {
+                        Ok(rule) => rules.push(rule),
+                        Err(e) => {
+                            self.issues.push(ConfigIssue {
+                                span: item_span,
+                                message: format!("invalid conditional rule: {e}"),
+                                label: "invalid rule".to_string(),
+                                help: None,
+                            });
+                        }
+                    }
+                }
+                if rules.is_empty() {
+                    None
+                } else {
+                    Some(ActionSpec::Conditional(rules))
+                }
+            }
+            _ => {
+                self.issues.push(ConfigIssue {
+                    span,
+                    message: "action must be a string, table or array".to_string(),
+                    label: "invalid type".to_string(),
+                    help: Some(
+                        "use a string for simple actions: action = \"media_play_pause\"\n\
+                         or an array for conditional: action = [{ condition = ..., action = ... }]\n\
+                         or an array of steps for a chain: action = [\"media_next\", \"volume_up\"]"
+                            .to_string(),
+                    ),
+                });
+                None
+            }
+        }
+    }
+
+    /// Build runtime config with validation
+    fn build_runtime(&mut self, config: &Config) -> RuntimeConfig {
+        // Collect strategy names for reference validation
+        let strategy_names: Vec<&str> = config
+            .strategies
+            .keys()
+            .map(|name| name.value().as_str())
+            .collect();
+
+        // Keys user layers may not bind, with the span that locks them
+        let mut locked_keys: HashMap<KeyCode, types::Span> = HashMap::new();
+        for spec in &config.managed.locked_bindings {
+            for key_code in self.resolve_key_spec(spec) {
+                locked_keys.insert(key_code, spec.span().clone());
+            }
+        }
+        // Strategies the system config's bindings of locked keys use
+        let mut locked_strategies: HashSet<&str> = HashSet::new();
+
+        // Track seen key codes to detect duplicates
+        let mut seen_keys: HashMap<KeyCode, types::Span> = HashMap::new();
+        let mut bindings = HashMap::new();
+
+        // Strategies cloned with per-binding overrides, instantiated alongside the originals
+        let mut derived_strategies: Vec<(Spanned<String>, StrategyConfig)> = Vec::new();
+        let mut strategy_conditions = config.strategy_conditions.clone();
+
+        for (key_spanned, binding) in &config.bindings {
+            let key_str = key_spanned.value();
+            let runtime_binding = self.apply_overrides(
+                key_spanned,
+                binding,
+                config,
+                &mut derived_strategies,
+                &mut strategy_conditions,
+            );
+
+            // Group bindings list their keys explicitly; the table name is just a label
+            let key_specs: Vec<&Spanned<String>> = if binding.keys.is_empty() {
+                vec![key_spanned]
+            } else {
+                binding.keys.iter().collect()
+            };
+
+            for key_spec in key_specs {
+                let key_span = key_spec.span().clone();
+
+                for key_code in self.resolve_key_spec(key_spec) {
+                    if let Some(locked_at) = locked_keys.get(&key_code) {
+                        if self.in_user_layer(&key_span) {
+                            let locked_at = self.describe_span(locked_at, &key_span);
+                            self.issues.push(ConfigIssue::locked_binding(
+                                key_span.clone(),
+                                &key_code.display_name(),
+                                &locked_at,
+                            ));
+                            continue;
+                        }
+                        locked_strategies
+                            .extend(binding.strategies.iter().map(|s| s.value().as_str()));
+                    }
+
+                    // Check for duplicate bindings (same key code from different strings,
+                    // overlapping ranges, or a range covering an explicit binding)
+                    if let Some(original_span) = seen_keys.get(&key_code) {
+                        let first_defined = self.describe_span(original_span, &key_span);
+                        self.issues.push(ConfigIssue::duplicate_binding(
+                            key_span.clone(),
+                            &key_code.display_name(),
+                            &first_defined,
+                        ));
+                        continue;
+                    }
+                    seen_keys.insert(key_code, key_span.clone());
+                    bindings.insert(key_code, runtime_binding.clone());
+                }
+            }
+
+            // Validate strategy references
+            for strategy_ref in &binding.strategies {
+                let strategy_name = strategy_ref.value();
+                if !strategy_names.contains(&strategy_name.as_str()) {
+                    self.issues.push(ConfigIssue::undefined_strategy(
+                        strategy_ref.span().clone(),
+                        strategy_name,
+                        &strategy_names,
+                    ));
+                }
+            }
+
+            // Warn if conditional binding has no catch-all rule
+            if let ActionSpec::Conditional(rules) = &binding.action {
+                let has_catch_all = rules.iter().any(|rule| rule.condition.is_empty());
+                if !has_catch_all {
+                    warn!(
+                        key = key_str,
+                        "conditional binding has no catch-all rule; \
+                         key will passthrough when no conditions match"
+                    );
+                }
+            }
+        }
+
+        for name in config.strategies.keys() {
+            if locked_strategies.contains(name.value().as_str()) && self.in_user_layer(name.span())
+            {
+                self.issues.push(ConfigIssue {
+                    span: name.span().clone(),
+                    message: format!(
+                        "strategy '{}' is used by a locked binding and can't be redefined",
+                        name.value()
+                    ),
+                    label: "locked strategy".to_string(),
+                    help: Some("define a strategy under a different name".to_string()),
+                });
+            }
+        }
+
+        // Instantiate strategies
+        let mut strategies: HashMap<String, Rc<RefCell<dyn KeyStrategy>>> = HashMap::new();
+        let mut strategy_configs = HashMap::new();
+        let all_strategies = config.strategies.iter().chain(
+            derived_strategies
+                .iter()
+                .map(|(name, config)| (name, config)),
+        );
+        for (name, strategy_config) in all_strategies {
+            if let Some(strategy) = self.build_strategy(name, strategy_config) {
+                strategies.insert(name.value().clone(), strategy);
+                strategy_configs.insert(name.value().clone(), strategy_config.clone());
+            }
+        }
+
+        // Build subscription routing map
+        let mut subscriptions: HashMap<InputEventId, Vec<String>> = HashMap::new();
+        for (name, strategy) in &strategies {
+            for event_id in strategy.borrow().subscriptions() {
+                subscriptions
+                    .entry(event_id)
+                    .or_default()
+                    .push(name.clone());
+            }
+        }
+
+        // Keys bindings and strategies handle are left alone by slow keys
+        let bound_keys = bindings
+            .keys()
+            .copied()
+            .chain(subscriptions.keys().filter_map(|id| match id {
+                InputEventId::Key(key) => Some(*key),
+                _ => None,
+            }))
+            .collect();
+
+        RuntimeConfig {
+            bindings,
+            strategies,
+            subscriptions,
+            strategy_conditions,
+            strategy_configs,
+            hid: config.hid.clone(),
+            accessibility: Accessibility::new(&config.settings.accessibility, bound_keys),
+        }
+    }
+
+    /// Apply a binding's strategy overrides
+    ///
+    /// Each referenced strategy that has an overridden setting is cloned under a
+    /// derived name (`strategy@binding`) with the new values, and the returned
+    /// binding points at the clone. Overrides no strategy in the chain accepts
+    /// are recorded as issues.
+    fn apply_overrides(
+        &mut self,
+        label: &Spanned<String>,
+        binding: &Binding,
+        config: &Config,
+        derived: &mut Vec<(Spanned<String>, StrategyConfig)>,
+        conditions: &mut HashMap<String, Condition>,
+    ) -> Binding {
+        let mut result = binding.clone();
+        if binding.overrides.is_empty() {
+            return result;
+        }
+
+        let mut applied: HashSet<&str> = HashSet::new();
+        for strategy_ref in &mut result.strategies {
+            // Undefined strategies are reported during reference validation
+            let Some(base) = config.strategies.get(strategy_ref) else {
+                applied.extend(binding.overrides.iter().map(|(f, _)| f.as_str()));
+                continue;
+            };
+            let Some(mut fields) = strategy_fields(base) else {
+                continue;
+            };
+
+            let mut changed = false;
+            for (field, value) in &binding.overrides {
+                if field.as_str() != "type" && fields.contains_key(field.as_str()) {
+                    fields.insert(field.value().clone(), value.clone());
+                    applied.insert(field.as_str());
+                    changed = true;
+                }
+            }
+            if !changed {
+                continue;
+            }
+
+            let overridden = match base {
+                StrategyConfig::Registered { type_name, .. } => Ok(StrategyConfig::Registered {
+                    type_name: type_name.clone(),
+                    settings: fields,
+                }),
+                _ => toml::Value::Table(fields).try_into::<StrategyConfig>(),
+            };
+            match overridden {
+                Ok(strategy_config) => {
+                    let name = format!("{}@{}", strategy_ref.value(), label.value());
+                    if let Some(condition) = conditions.get(strategy_ref.as_str()).cloned() {
+                        conditions.insert(name.clone(), condition);
+                    }
+                    *strategy_ref = Spanned::new(name, strategy_ref.span().clone());
+                    derived.push((strategy_ref.clone(), strategy_config));
+                }
+                Err(e) => {
+                    self.issues.push(ConfigIssue {
+                        span: label.span().clone(),
+                        message: format!(
+                            "invalid override of strategy '{}': {}",
+                            strategy_ref.value(),
+                            e.message()
+                        ),
+                        label: "invalid override".to_string(),
+                        help: None,
+                    });
+                }
+            }
+        }
+
+        for (field, _) in &binding.overrides {
+            if !applied.contains(field.as_str()) {
+                self.issues.push(ConfigIssue {
+                    span: field.span().clone(),
+                    message: format!("unknown binding field '{}'", field.value()),
+                    label: "unknown field".to_string(),
+                    help: Some(
+                        "other than action, strategy, keys and notify_on_execute, binding fields must \
+                         override a setting of the binding's strategy"
+                            .to_string(),
+                    ),
+                });
+            }
+        }
+
+        result
+    }
+
+    /// Instantiate a strategy from its config
+    ///
+    /// Returns `None` (with an issue recorded) if the strategy can't be created.
+    fn build_strategy(
+        &mut self,
+        name: &Spanned<String>,
+        strategy_config: &StrategyConfig,
+    ) -> Option<Rc<RefCell<dyn KeyStrategy>>> {
+        let strategy: Rc<RefCell<dyn KeyStrategy>> = match strategy_config {
+            StrategyConfig::GatedHold {
+                initial_hold_ms,
+                repeat_window_ms,
+                diverts,
+                shared_gate,
+            } => {
+                // Parse diverts: convert string keys/values to InputEventId/Action
+                let mut parsed_diverts = HashMap::new();
+                for (event_str, action_str) in diverts {
+                    // Parse event identifier
+                    let Some(event_id) = InputEventId::from_config_str(event_str) else {
+                        self.issues.push(ConfigIssue {
+                            span: name.span().clone(),
+                            message: format!("invalid divert event: '{event_str}'"),
+                            label: "unknown event".to_string(),
+                            help: Some(
+                                "valid events: scroll_up, scroll_down, or key names".to_string(),
+                            ),
+                        });
+                        continue;
+                    };
+
+                    // Parse action
+                    let action = match parse_action(action_str) {
+                        Ok(action) => action,
+                        Err(e) => {
+                            self.issues.push(ConfigIssue {
+                                span: name.span().clone(),
+                                message: format!("invalid divert action: {e}"),
+                                label: "unknown action".to_string(),
+                                help: Some(
+                                    "valid actions: volume_up, volume_down, etc.".to_string(),
+                                ),
+                            });
+                            continue;
+                        }
+                    };
+
+                    parsed_diverts.insert(event_id, action);
+                }
+
+                Rc::new(RefCell::new(GatedHoldStrategy::new(GatedHoldConfig {
+                    initial_hold_ms: *initial_hold_ms,
+                    repeat_window_ms: *repeat_window_ms,
+                    diverts: parsed_diverts,
+                    shared_gate: *shared_gate,
+                })))
+            }
+            StrategyConfig::TapHold {
+                hold_ms,
+                tap_key,
+                hold_key,
+            } => {
+                let tap_key = tap_key
+                    .as_deref()
+                    .and_then(|key| self.resolve_strategy_key(name, "tap_key", key));
+                let hold_key = hold_key
+                    .as_deref()
+                    .and_then(|key| self.resolve_strategy_key(name, "hold_key", key));
+
+                Rc::new(RefCell::new(TapHoldStrategy::new(TapHoldConfig {
+                    hold_ms: *hold_ms,
+                    tap_key,
+                    hold_key,
+                })))
+            }
+            StrategyConfig::Repeat {
+                start_interval_ms,
+                min_interval_ms,
+                ramp_ms,
+            } => {
+                if min_interval_ms > start_interval_ms {
+                    self.issues.push(ConfigIssue {
+                        span: name.span().clone(),
+                        message: "min_interval_ms must not exceed start_interval_ms"
+                            .to_string(),
+                        label: "invalid repeat intervals".to_string(),
+                        help: Some(format!(
+                            "start_interval_ms is {start_interval_ms}, min_interval_ms is {min_interval_ms}"
+                        )),
+                    });
+                }
+                if *min_interval_ms == 0 {
+                    self.issues.push(ConfigIssue {
+                        span: name.span().clone(),
+                        message: "min_interval_ms must be greater than zero".to_string(),
+                        label: "invalid repeat intervals".to_string(),
+                        help: None,
+                    });
+                }
+
+                Rc::new(RefCell::new(RepeatStrategy::new(RepeatConfig {
+                    start_interval_ms: *start_interval_ms,
+                    min_interval_ms: *min_interval_ms,
+                    ramp_ms: *ramp_ms,
+                })))
+            }
+            StrategyConfig::Debounce { chatter_ms } => {
+                Rc::new(RefCell::new(DebounceStrategy::new(DebounceConfig {
+                    chatter_ms: *chatter_ms,
+                })))
+            }
+            StrategyConfig::Mirror { trigger, pairs } => {
+                let trigger = self.resolve_strategy_key(name, "trigger key", trigger);
+
+                let mut mirror = mirror_pairs();
+                for (key_str, mirror_str) in pairs {
+                    let key = self.resolve_strategy_key(name, "pair key", key_str);
+                    let other = self.resolve_strategy_key(name, "pair key", mirror_str);
+                    if let (Some(key), Some(other)) = (key, other) {
+                        mirror.insert(key, other);
+                        mirror.insert(other, key);
+                    }
+                }
+
+                if let Some(trigger) = trigger
+                    && mirror.contains_key(&trigger)
+                {
+                    self.issues.push(ConfigIssue {
+                        span: name.span().clone(),
+                        message: "mirror trigger cannot also be a mirrored key".to_string(),
+                        label: "ambiguous key".to_string(),
+                        help: Some(
+                            "use a key outside the letter and number rows, e.g. space".to_string(),
+                        ),
+                    });
+                }
+
+                Rc::new(RefCell::new(MirrorStrategy::new(MirrorConfig {
+                    trigger: trigger.unwrap_or(KeyCode::new(0)),
+                    pairs: mirror,
+                })))
+            }
+            StrategyConfig::PushToTalk { apps } => {
+                let mut ptt_apps = Vec::new();
+                for app in apps {
+                    if app.window.is_empty() {
+                        self.issues.push(ConfigIssue {
+                            span: name.span().clone(),
+                            message: format!(
+                                "push-to-talk app with key '{}' matches every window",
+                                app.key
+                            ),
+                            label: "missing app".to_string(),
+                            help: Some(
+                                "name the app, e.g. { binary = \"Discord*\", key = \"f13\" }"
+                                    .to_string(),
+                            ),
+                        });
+                        continue;
+                    }
+                    if let Some(key) = self.resolve_strategy_key(name, "push-to-talk key", &app.key)
+                    {
+                        ptt_apps.push(PushToTalkApp {
+                            window: app.window.clone(),
+                            key,
+                        });
+                    }
+                }
+
+                Rc::new(RefCell::new(PushToTalkStrategy::new(PushToTalkConfig {
+                    apps: ptt_apps,
+                })))
+            }
+            StrategyConfig::MouseKeys {
+                keys,
+                toggle,
+                speed,
+                max_speed,
+                accel_ms,
+            } => {
+                let mut controls = HashMap::new();
+                for (key_str, control_str) in keys {
+                    let Some(key) = self.resolve_strategy_key(name, "mouse key", key_str) else {
+                        continue;
+                    };
+                    match control_str.parse::<MouseControl>() {
+                        Ok(control) => {
+                            controls.insert(key, control);
+                        }
+                        Err(e) => {
+                            self.issues.push(ConfigIssue {
+                                span: name.span().clone(),
+                                message: format!("invalid mouse key for '{key_str}': {e}"),
+                                label: "unknown pointer control".to_string(),
+                                help: None,
+                            });
+                        }
+                    }
+                }
+
+                let toggle = toggle
+                    .as_ref()
+                    .and_then(|key_str| self.resolve_strategy_key(name, "toggle key", key_str));
+                if let Some(toggle) = toggle
+                    && controls.contains_key(&toggle)
+                {
+                    self.issues.push(ConfigIssue {
+                        span: name.span().clone(),
+                        message: "toggle key cannot also be a mouse key".to_string(),
+                        label: "ambiguous key".to_string(),
+                        help: None,
+                    });
+                }
+
+                Rc::new(RefCell::new(MouseKeysStrategy::new(MouseKeysConfig {
+                    keys: controls,
+                    toggle,
+                    speed: *speed,
+                    max_speed: *max_speed,
+                    accel_ms: *accel_ms,
+                })))
+            }
+            StrategyConfig::Combo {
+                keys,
+                window_ms,
+                action,
+            } => {
+                let mut combo_keys = Vec::new();
+                let mut all_resolved = true;
+                for key_str in keys {
+                    match self.resolve_strategy_key(name, "combo key", key_str) {
+                        Some(key) if !combo_keys.contains(&key) => combo_keys.push(key),
+                        Some(_) => {}
+                        None => all_resolved = false,
+                    }
+                }
+                // Unknown keys were already reported; only flag a genuinely short list
+                if all_resolved && combo_keys.len() < 2 {
+                    self.issues.push(ConfigIssue {
+                        span: name.span().clone(),
+                        message: "combo needs at least two distinct keys".to_string(),
+                        label: "too few keys".to_string(),
+                        help: Some("example: keys = [\"j\", \"k\"]".to_string()),
+                    });
+                }
+
+                let action = match parse_action(action) {
+                    Ok(action) => action,
+                    Err(e) => {
+                        self.issues.push(ConfigIssue {
+                            span: name.span().clone(),
+                            message: format!("invalid combo action: {e}"),
+                            label: "unknown action".to_string(),
+                            help: None,
+                        });
+                        Action::Block
+                    }
+                };
+
+                Rc::new(RefCell::new(ComboStrategy::new(ComboConfig {
+                    keys: combo_keys,
+                    window_ms: *window_ms,
+                    action,
+                })))
+            }
+            StrategyConfig::Leader {
+                key,
+                timeout_ms,
+                sequences,
+            } => {
+                let leader = self.resolve_strategy_key(name, "leader key", key);
+
+                let mut parsed_sequences = HashMap::new();
+                for (key_str, action_str) in sequences {
+                    let Some(key) = self.resolve_strategy_key(name, "sequence key", key_str) else {
+                        continue;
+                    };
+                    match parse_action(action_str) {
+                        Ok(action) => {
+                            parsed_sequences.insert(key, action);
+                        }
+                        Err(e) => {
+                            self.issues.push(ConfigIssue {
+                                span: name.span().clone(),
+                                message: format!("invalid sequence action: {e}"),
+                                label: "unknown action".to_string(),
+                                help: None,
+                            });
+                        }
+                    }
+                }
+
+                if let Some(leader) = leader
+                    && parsed_sequences.contains_key(&leader)
+                {
+                    self.issues.push(ConfigIssue {
+                        span: name.span().clone(),
+                        message: "leader key cannot also be a sequence key".to_string(),
+                        label: "ambiguous sequence".to_string(),
+                        help: Some("pressing the leader while pending cancels".to_string()),
+                    });
+                }
+
+                Rc::new(RefCell::new(LeaderStrategy::new(LeaderConfig {
+                    leader: leader.unwrap_or(KeyCode::new(0)),
+                    timeout_ms: *timeout_ms,
+                    sequences: parsed_sequences,
+                })))
+            }
+            StrategyConfig::Script { file, settings } => {
+                let layer = self
+                    .sources
+                    .iter()
+                    .find(|source| source.contains(name.span()));
+                let path = match layer {
+                    Some(layer) => resolve_include(&layer.name, file),
+                    None => file.clone(),
+                };
+                let loaded = if remote::is_url(&path) {
+                    Err("scripts must be local files".to_string())
+                } else {
+                    load_script(Path::new(&path), settings)
+                };
+                match loaded {
+                    Ok(strategy) => strategy,
+                    Err(e) => {
+                        self.issues.push(ConfigIssue {
+                            span: name.span().clone(),
+                            message: format!("script {path} failed to load: {e}"),
+                            label: "script error".to_string(),
+                            help: None,
+                        });
+                        return None;
+                    }
+                }
+            }
+            StrategyConfig::Registered {
+                type_name,
+                settings,
+            } => {
+                // Checked while parsing, and types are never unregistered
+                match StrategyRegistry::global().build(type_name, settings)? {
+                    Ok(strategy) => strategy,
+                    Err(e) => {
+                        self.issues.push(ConfigIssue {
+                            span: name.span().clone(),
+                            message: format!(
+                                "strategy type '{type_name}' failed to initialize: {e}"
+                            ),
+                            label: "strategy error".to_string(),
+                            help: None,
+                        });
+                        return None;
+                    }
+                }
+            }
+        };
+        Some(strategy)
+    }
+
+    /// Resolve a key name used inside a strategy definition
+    ///
+    /// Records an issue against the strategy name if the key is unknown.
+    fn resolve_strategy_key(
+        &mut self,
+        strategy_name: &Spanned<String>,
+        field: &str,
+        key_str: &str,
+    ) -> Option<KeyCode> {
+        let key_code = self.parse_key(key_str);
+        if cfg!(not(windows)) && key_code.is_some_and(|key| key.scancode().is_some()) {
+            self.issues.push(ConfigIssue::unsupported_scancode(
+                strategy_name.span().clone(),
+                key_str,
+            ));
+            return None;
+        }
+        if cfg!(windows) && key_code.is_some_and(|key| key.midi().is_some()) {
+            self.issues.push(ConfigIssue::unsupported_midi(
+                strategy_name.span().clone(),
+                key_str,
+            ));
+            return None;
+        }
+        if key_code.is_none() {
+            self.issues.push(ConfigIssue {
+                span: strategy_name.span().clone(),
+                message: format!("invalid {field}: unknown key '{key_str}'"),
+                label: "unknown key".to_string(),
+                help: Some(error::unknown_key_help(key_str)),
+            });
+        }
+        key_code
+    }
+
+    /// Resolve a single key specifier (key name, code, or range) to key codes
+    ///
+    /// Records an issue and returns an empty list if the specifier is invalid.
+    fn resolve_key_spec(&mut self, key_spec: &Spanned<String>) -> Vec<KeyCode> {
+        let key_str = key_spec.value();
+        let key_span = key_spec.span().clone();
+
+        if key_str.contains("..") {
+            return match KeyCode::range_from_config_str(key_str) {
+                Ok(codes) => codes,
+                Err(reason) => {
+                    self.issues
+                        .push(ConfigIssue::invalid_key_range(key_span, reason));
+                    Vec::new()
+                }
+            };
+        }
+
+        match self.parse_key(key_str) {
+            Some(key_code) if cfg!(not(windows)) && key_code.scancode().is_some() => {
+                self.issues
+                    .push(ConfigIssue::unsupported_scancode(key_span, key_str));
+                Vec::new()
+            }
+            Some(key_code) if cfg!(windows) && key_code.midi().is_some() => {
+                self.issues
+                    .push(ConfigIssue::unsupported_midi(key_span, key_str));
+                Vec::new()
+            }
+            Some(key_code) => vec![key_code],
+            None => {
+                self.issues
+                    .push(ConfigIssue::unknown_key(key_span, key_str));
+                Vec::new()
+            }
+        }
+    }
+}
+
+/// Spans of the parts of a config value that run commands or reach other
+/// programs: `run`, `dbus` and `http` actions, written `{ run = ... }` or
+/// `{ type = "run", ... }`, and `focus` launch commands
+fn command_spans(value: &DeValue, spans: &mut Vec<types::Span>) {
+    match value {
+        DeValue::Table(table) => {
+            for (key, value) in table {
+                let runs = match key.get_ref().as_ref() {
+                    "run" | "dbus" | "http" | "launch" => true,
+                    "type" => matches!(
+                        value.get_ref(),
+                        DeValue::String(name) if matches!(name.as_ref(), "run" | "dbus" | "http")
+                    ),
+                    _ => false,
+                };
+                if runs {
+                    spans.push(key.span());
+                }
+                command_spans(value.get_ref(), spans);
+            }
+        }
+        DeValue::Array(items) => {
+            for item in items {
+                command_spans(item.get_ref(), spans);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Layer a config table over the tables before it
+///
+/// `[bindings]` and `[strategies]` entries replace the base entry of the same
+/// name whole, `[settings]` tables merge down to individual values, `[[hid]]`
+/// devices add to the base list, and anything else is replaced.
+fn merge_layer<'i>(base: &mut DeTable<'i>, layer: DeTable<'i>) {
+    for (key, value) in layer {
+        let depth = match key.get_ref().as_ref() {
+            "settings" => usize::MAX,
+            "bindings" | "strategies" => 1,
+            _ => 0,
+        };
+        let span = value.span();
+        match (
+            base.get_mut("hid"),
+            key.get_ref().as_ref(),
+            value.into_inner(),
+        ) {
+            (Some(existing), "hid", DeValue::Array(added))
+                if matches!(existing.get_ref(), DeValue::Array(_)) =>
+            {
+                if let DeValue::Array(devices) = existing.get_mut() {
+                    for device in added {
+                        devices.push(device);
+                    }
+                }
+            }
+            (_, _, value) => merge_entry(base, key, toml::Spanned::new(span, value), depth),
+        }
+    }
+}
+
+/// Set one entry of a layered table, merging into an existing table `depth`
+/// levels deep before replacing values outright
+fn merge_entry<'i>(
+    base: &mut DeTable<'i>,
+    key: toml::Spanned<std::borrow::Cow<'i, str>>,
+    value: toml::Spanned<DeValue<'i>>,
+    depth: usize,
+) {
+    let name = key.get_ref().to_string();
+    let span = value.span();
+    match (base.get_mut(name.as_str()), value.into_inner()) {
+        (Some(existing), DeValue::Table(table))
+            if depth > 0 && matches!(existing.get_ref(), DeValue::Table(_)) =>
+        {
+            if let DeValue::Table(existing) = existing.get_mut() {
+                for (key, value) in table {
+                    merge_entry(existing, key, value, depth - 1);
+                }
+            }
+        }
+        (_, value) => {
+            // Replace the key too, so its span points at the layer that set it
+            base.remove(name.as_str());
+            base.insert(key, toml::Spanned::new(span, value));
+        }
+    }
+}
+
+/// Parse an action value: an action string, a single-key table naming an action
+/// that takes arguments, a `{ type = "...", ... }` table, or an array of chain steps
+fn parse_action_value(value: &toml::Value) -> Result<Action, String> {
+    match parse_action_step(value)? {
+        Action::Delay(_) => Err(
+            "delay_ms only applies between the steps of an action chain \
+                                 (e.g. [\"media_next\", { delay_ms = 200 }, \"media_next\"])"
+                .to_string(),
+        ),
+        action => Ok(action),
+    }
+}
+
+/// Parse one step of an action chain: any action, or a `{ delay_ms = ... }` pause
+fn parse_action_step(value: &toml::Value) -> Result<Action, String> {
+    match value {
+        toml::Value::String(s) => parse_action(s),
+        toml::Value::Table(table) if table.contains_key("if") => parse_branch_action(table),
+        toml::Value::Table(table) if table.contains_key("type") => parse_typed_action(table),
+        toml::Value::Table(table) => {
+            let mut entries = table.iter();
+            let (Some((name, args)), None) = (entries.next(), entries.next()) else {
+                return Err("action table must have exactly one key naming the action".to_string());
+            };
+            parse_action_args(name, args)
+        }
+        toml::Value::Array(steps) => parse_action_chain(steps),
+        other => Err(format!(
+            "action must be a string, table or array, found {}",
+            other.type_str()
+        )),
+    }
+}
+
+/// Parse `{ if = { window = { ... } }, then = ..., else = ... }`. Each branch is
+/// any action or chain, and may contain delays like a chain step.
+fn parse_branch_action(table: &toml::Table) -> Result<Action, String> {
+    if let Some(field) = table
+        .keys()
+        .find(|k| !matches!(k.as_str(), "if" | "then" | "else"))
+    {
+        return Err(format!(
+            "unknown field `{field}` in branch, expected `if`, `then` or `else`"
+        ));
+    }
+
+    let condition = Condition::deserialize(table["if"].clone())
+        .map_err(|e| format!("invalid branch condition: {}", e.message()))?;
+    if condition.is_empty() {
+        return Err("branch condition is empty".to_string());
+    }
+    let then = table
+        .get("then")
+        .ok_or_else(|| "branch is missing `then`".to_string())
+        .and_then(|value| parse_action_step(value).map_err(|e| format!("in `then`: {e}")))?;
+    let otherwise = table
+        .get("else")
+        .map(|value| parse_action_step(value).map_err(|e| format!("in `else`: {e}")))
+        .transpose()?;
+
+    for action in std::iter::once(&then).chain(&otherwise) {
+        if matches!(action, Action::Passthrough | Action::Block) {
+            return Err("passthrough and block cannot be used in a branch; \
+                        use conditional rules to decide whether the key passes through"
+                .to_string());
+        }
+    }
+
+    Ok(Action::Branch {
+        condition,
+        then: Box::new(then),
+        otherwise: otherwise.map(Box::new),
+    })
+}
+
+/// Parse `{ send = "ctrl+shift+esc" }`, a shortcut of `+`-separated key
+/// names. Generic modifier names (`ctrl`, `shift`, `alt`, `win`) mean the left
+/// key under whichever name this platform gives it.
+fn parse_send_action(args: &toml::Value) -> Result<Action, String> {
+    let shortcut = string_arg("send", "shortcut", args)?;
+    let keys = shortcut
+        .split('+')
+        .map(|name| {
+            let name = name.trim();
+            let candidates: &[&str] = match name.to_lowercase().as_str() {
+                "ctrl" | "control" => &["leftctrl", "lctrl"],
+                "shift" => &["leftshift", "lshift"],
+                "alt" => &["leftalt", "lalt"],
+                "win" | "super" | "meta" | "cmd" => &["leftmeta", "lwin"],
+                _ => &[name],
+            };
+            candidates
+                .iter()
+                .find_map(|candidate| KeyCode::from_config_str(candidate))
+                .ok_or_else(|| format!("unknown key '{name}' in shortcut '{shortcut}'"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Action::Send(keys))
+}
+
+/// Parse `{ type = "volume_up", step = 5 }`: the action named by `type`, with the
+/// remaining fields as its arguments
+fn parse_typed_action(table: &toml::Table) -> Result<Action, String> {
+    let Some(toml::Value::String(name)) = table.get("type") else {
+        return Err("action `type` must be a string".to_string());
+    };
+    let mut args = table.clone();
+    args.remove("type");
+    if args.is_empty() {
+        return parse_action(name);
+    }
+    // notify's argument is a bare string, named as a field here
+    if name == "notify"
+        && args.len() == 1
+        && let Some(message) = args.get("message")
+    {
+        return parse_action_args(name, message);
+    }
+    parse_action_args(name, &toml::Value::Table(args))
+}
+
+/// Parse the arguments of the action called `name`
+fn parse_action_args(name: &str, args: &toml::Value) -> Result<Action, String> {
+    match name {
+        "clipboard" => parse_clipboard_action(args),
+        "obs" => ObsRequest::deserialize(args.clone())
+            .map(Action::Obs)
+            .map_err(|e| format!("invalid obs action: {}", e.message())),
+        "home_assistant" => ServiceCall::from_args(args)
+            .map(Action::HomeAssistant)
+            .map_err(|e| format!("invalid home_assistant action: {e}")),
+        "dbus" => DbusCall::from_args(args)
+            .map(Action::Dbus)
+            .map_err(|e| format!("invalid dbus action: {e}")),
+        "notify" => string_arg(name, "message", args).map(Action::Notify),
+        "send" => parse_send_action(args),
+        "focus" => parse_focus_action(args),
+        "run" => ShellCommand::from_args(args)
+            .map(Action::Run)
+            .map_err(|e| format!("invalid run action: {e}")),
+        "http" => Webhook::from_args(args)
+            .map(Action::Http)
+            .map_err(|e| format!("invalid http action: {e}")),
+        "volume_up" | "volume_down" => parse_volume_step_action(name, args),
+        "delay_ms" => match args {
+            toml::Value::Integer(ms) => u64::try_from(*ms)
+                .map(Action::Delay)
+                .map_err(|_| "delay_ms must not be negative".to_string()),
+            _ => Err("delay_ms takes a number of milliseconds: { delay_ms = 200 }".to_string()),
+        },
+        "media_play_pause" | "media_pause" | "media_next" | "media_previous" | "media_stop" => {
+            parse_player_media_action(name, args)
+        }
+        _ => match parse_action(name) {
+            Ok(_) => Err(format!("action '{name}' takes no arguments")),
+            Err(e) => Err(e),
+        },
+    }
+}
+
+/// Parse `focus = { binary = "slack", launch = "slack" }`: a window condition
+/// naming the window to raise, plus an optional command to start the application
+fn parse_focus_action(args: &toml::Value) -> Result<Action, String> {
+    const FIELDS: &[&str] = &[
+        "title",
+        "not_title",
+        "class",
+        "not_class",
+        "binary",
+        "not_binary",
+        "launch",
+    ];
+
+    let toml::Value::Table(table) = args else {
+        return Err(
+            "focus takes a window to match: { focus = { binary = \"slack\" } }".to_string(),
+        );
+    };
+    if let Some(field) = table.keys().find(|k| !FIELDS.contains(&k.as_str())) {
+        return Err(format!(
+            "invalid focus action: unknown field `{field}`, expected one of {}",
+            FIELDS.join(", ")
+        ));
+    }
+
+    let mut condition = table.clone();
+    let launch = condition
+        .remove("launch")
+        .map(|launch| ShellCommand::from_args(&launch))
+        .transpose()
+        .map_err(|e| format!("invalid focus launch command: {e}"))?;
+    let window = WindowCondition::deserialize(toml::Value::Table(condition))
+        .map_err(|e| format!("invalid focus action: {}", e.message()))?;
+    if window.is_empty() {
+        return Err("focus needs a title, class or binary to match".to_string());
+    }
+
+    Ok(Action::Focus(FocusTarget { window, launch }))
+}
+
+/// The string argument of `{ notify = "..." }`
+fn string_arg(name: &str, field: &str, args: &toml::Value) -> Result<String, String> {
+    match args {
+        toml::Value::String(s) if !s.trim().is_empty() => Ok(s.clone()),
+        _ => Err(format!(
+            "{name} takes a {field} string: {{ {name} = \"...\" }}"
+        )),
+    }
+}
+
+/// Parse `{ type = "volume_up", step = 5 }`, a volume change by `step` percent
+fn parse_volume_step_action(name: &str, args: &toml::Value) -> Result<Action, String> {
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct VolumeArgs {
+        step: u8,
+    }
+
+    let args = VolumeArgs::deserialize(args.clone())
+        .map_err(|e| format!("invalid {name} action: {}", e.message()))?;
+    if !(1..=100).contains(&args.step) {
+        return Err(format!(
+            "invalid {name} action: step must be between 1 and 100 (percent)"
+        ));
+    }
+    let step = i32::from(args.step);
+    Ok(Action::VolumeStep(if name == "volume_down" {
+        -step
+    } else {
+        step
+    }))
+}
+
+/// Spans of a `type = "..."` action table's fields, for pointing errors at the
+/// offending field. The `type` entry spans its value, the rest span their keys.
+fn action_field_spans(table: &DeTable) -> Vec<(String, types::Span)> {
+    if !table.iter().any(|(k, _)| k.get_ref().as_ref() == "type") {
+        return Vec::new();
+    }
+    table
+        .iter()
+        .map(|(key, value)| {
+            let name = key.get_ref().to_string();
+            let span = if name == "type" {
+                value.span()
+            } else {
+                key.span()
+            };
+            (name, span)
+        })
+        .collect()
+}
+
+/// The field of a `type` action table that an argument error refers to
+fn action_error_span(fields: &[(String, types::Span)], message: &str) -> Option<types::Span> {
+    let find = |name: &str| {
+        fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, span)| span.clone())
+    };
+    if message.starts_with("unknown action") || message.contains("takes no arguments") {
+        return find("type");
+    }
+    // serde names fields in backticks ("unknown field `foo`")
+    if let Some((field, _)) = fields
+        .iter()
+        .find(|(field, _)| field != "type" && message.contains(&format!("`{field}`")))
+    {
+        return find(field);
+    }
+    // With a single argument, a bad value can only be that one
+    match fields {
+        [(a, _), (b, _)] => find(if a == "type" { b } else { a }),
+        _ => None,
+    }
+}
+
+/// Parse the steps of an action chain. Nested chains are flattened so every
+/// step runs in order on the chain's task.
+fn parse_action_chain(steps: &[toml::Value]) -> Result<Action, String> {
+    if steps.is_empty() {
+        return Err("action chain is empty".to_string());
+    }
+    let mut actions = Vec::with_capacity(steps.len());
+    for (i, step) in steps.iter().enumerate() {
+        match parse_action_step(step).map_err(|e| format!("chain step {}: {e}", i + 1))? {
+            Action::Chain(nested) => actions.extend(nested),
+            Action::Passthrough | Action::Block => {
+                return Err(format!(
+                    "chain step {}: passthrough and block cannot be chained",
+                    i + 1
+                ));
+            }
+            action => actions.push(action),
+        }
+    }
+    Ok(Action::Chain(actions))
+}
+
+/// Whether an action array holds conditional rules rather than chain steps
+fn is_conditional_array(items: &[toml::Spanned<DeValue>]) -> bool {
+    items.iter().any(|item| match item.get_ref() {
+        DeValue::Table(table) => table
+            .iter()
+            .any(|(k, _)| matches!(k.get_ref().as_ref(), "condition" | "action")),
+        _ => false,
+    })
+}
+
+/// Parse `media_next = { player = "spotify" }` or `media_pause = { binary =
+/// "spotify" }`, a transport action aimed at one player
+fn parse_player_media_action(name: &str, args: &toml::Value) -> Result<Action, String> {
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct PlayerArgs {
+        player: Option<String>,
+        binary: Option<String>,
+    }
+
+    let args = PlayerArgs::deserialize(args.clone())
+        .map_err(|e| format!("invalid {name} action: {}", e.message()))?;
+    let player = match (args.player, args.binary) {
+        (Some(player), None) if !player.is_empty() => PlayerTarget::Name(player),
+        (None, Some(binary)) if !binary.is_empty() => PlayerTarget::Binary(binary),
+        (Some(_), Some(_)) => {
+            return Err(format!(
+                "invalid {name} action: give either player or binary, not both"
+            ));
+        }
+        (None, None) => {
+            return Err(format!("invalid {name} action: missing player or binary"));
+        }
+        _ => return Err(format!("invalid {name} action: player must not be empty")),
+    };
+    let command = match name {
+        "media_play_pause" => MediaCommand::PlayPause,
+        "media_pause" => MediaCommand::Pause,
+        "media_next" => MediaCommand::Next,
+        "media_previous" => MediaCommand::Previous,
+        _ => MediaCommand::Stop,
+    };
+    Ok(Action::PlayerMedia { player, command })
+}
+
+/// Parse the arguments of `clipboard = "text"` or
+/// `clipboard = { text = "...", paste = true }`
+fn parse_clipboard_action(args: &toml::Value) -> Result<Action, String> {
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct ClipboardArgs {
+        text: String,
+        #[serde(default)]
+        paste: bool,
+    }
+
+    let (text, paste) = match args {
+        toml::Value::String(text) => (text.clone(), false),
+        args => {
+            let args = ClipboardArgs::deserialize(args.clone())
+                .map_err(|e| format!("invalid clipboard action: {}", e.message()))?;
+            (args.text, args.paste)
+        }
+    };
+    template::validate(&text).map_err(|e| format!("invalid clipboard text: {e}"))?;
+    Ok(Action::Clipboard { text, paste })
+}
+
+/// Parse an action string into an Action enum
+fn parse_action(s: &str) -> Result<Action, String> {
+    match s {
+        "media_play_pause" => Ok(Action::MediaPlayPause),
+        "media_next" => Ok(Action::MediaNext),
+        "media_previous" => Ok(Action::MediaPrevious),
+        "media_stop" => Ok(Action::MediaStop),
+        "media_pause" => Err(
+            "media_pause pauses one player, named with player or binary: \
+             { media_pause = { player = \"spotify\" } }"
+                .to_string(),
+        ),
+        "volume_up" => Ok(Action::VolumeUp),
+        "volume_down" => Ok(Action::VolumeDown),
+        "volume_mute" => Ok(Action::VolumeMute),
+        "mic_mute" => Ok(Action::Mic(MicCommand::Mute)),
+        "mic_unmute" => Ok(Action::Mic(MicCommand::Unmute)),
+        "mic_toggle" => Ok(Action::Mic(MicCommand::Toggle)),
+        "browser_back" => Ok(Action::BrowserBack),
+        "browser_forward" => Ok(Action::BrowserForward),
+        "desktop_next" => Ok(Action::DesktopNext),
+        "desktop_previous" => Ok(Action::DesktopPrevious),
+        "window_next" => Ok(Action::WindowNext),
+        "window_previous" => Ok(Action::WindowPrevious),
+        "lock_screen" => Ok(Action::Session(SessionCommand::Lock)),
+        "system_sleep" => Ok(Action::Session(SessionCommand::Sleep)),
+        "display_off" => Ok(Action::Session(SessionCommand::DisplayOff)),
+        "passthrough" => Ok(Action::Passthrough),
+        "block" => Ok(Action::Block),
+        _ => {
+            if let Some(position) = SnapPosition::ALL.into_iter().find(|p| p.name() == s) {
+                return Ok(Action::Snap(position));
+            }
+            if let Some(n) = s.strip_prefix("desktop_") {
+                return match n.parse::<u32>() {
+                    Ok(n) if n >= 1 => Ok(Action::Desktop(n)),
+                    _ => Err(format!(
+                        "invalid desktop '{n}' in '{s}': desktops are numbered from 1"
+                    )),
+                };
+            }
+            Err(format!("unknown action '{s}'"))
+        }
+    }
+}
+
+/// Read and compile the script of a `script` strategy
+#[cfg(feature = "script")]
+fn load_script(
+    path: &Path,
+    settings: &toml::Table,
+) -> Result<Rc<RefCell<dyn KeyStrategy>>, String> {
+    use crate::strategy::{ScriptConfig, ScriptStrategy};
+
+    let source = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let mut settings = settings.clone();
+    settings.remove("condition");
+    let strategy = ScriptStrategy::new(ScriptConfig {
+        name: path.display().to_string(),
+        source,
+        settings,
+    })
+    .map_err(|e| e.to_string())?;
+    Ok(Rc::new(RefCell::new(strategy)))
+}
+
+#[cfg(not(feature = "script"))]
+fn load_script(
+    _path: &Path,
+    _settings: &toml::Table,
+) -> Result<Rc<RefCell<dyn KeyStrategy>>, String> {
+    Err("script strategies need rebinded built with the `script` feature".to_string())
+}
+
+/// A strategy config's settings as a TOML table, for applying overrides
+fn strategy_fields(config: &StrategyConfig) -> Option<toml::Table> {
+    match config {
+        StrategyConfig::Registered { settings, .. } => Some(settings.clone()),
+        _ => toml::Table::try_from(config).ok(),
+    }
+}
+
+/// The registered strategy type a strategy table's `type` refers to, if any
+fn registered_type(value: &DeValue) -> Option<String> {
+    let DeValue::Table(table) = value else {
+        return None;
+    };
+    let (_, type_value) = table.iter().find(|(k, _)| k.get_ref().as_ref() == "type")?;
+    let DeValue::String(type_name) = type_value.get_ref() else {
+        return None;
+    };
+    StrategyRegistry::global()
+        .contains(type_name)
+        .then(|| type_name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::types::WindowCondition;
+    use crate::platform::MonitorCondition;
+    use assert2::assert;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_simple_action_parsing() {
+        let toml = r#"
+            [bindings.0x7C]
+            action = "media_play_pause"
+        "#;
+        let result = load_from_str("test.toml", toml.to_string()).await;
+        assert!(result.is_ok());
+        let (config, _) = result.unwrap();
+        assert!(config.bindings.len() == 1);
+    }
+
+    #[tokio::test]
+    async fn test_conditional_action_parsing() {
+        let toml = r#"
+            [bindings.0x80]
+            action = [
+                { condition = { window = { title = "*vivaldi*" } }, action = "browser_back" },
+            ]
+        "#;
+        let result = load_from_str("test.toml", toml.to_string()).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_power_condition_parsing() {
+        let toml = r#"
+            [bindings.0x80]
+            action = [
+                { condition = { power = { on_battery = true, battery_below = 30 } }, action = "media_stop" },
+                { action = "media_play_pause" },
+            ]
+        "#;
+        let (config, _) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+        let ActionSpec::Conditional(rules) = &config.bindings.values().next().unwrap().action
+        else {
+            panic!("expected conditional rules");
+        };
+        let power = rules[0].condition.power.as_ref().unwrap();
+        assert!(power.on_battery == Some(true) && power.battery_below == Some(30));
+
+        let toml = r#"
+            [bindings.0x80]
+            action = [{ condition = { power = { plugged = true } }, action = "media_stop" }]
+        "#;
+        assert!(load_from_str("test.toml", toml.to_string()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_monitor_condition_parsing() {
+        let toml = r#"
+            [bindings.0x80]
+            action = [
+                { condition = { monitor = 2 }, action = "media_stop" },
+                { condition = { window = { binary = "obs*" }, monitor = "HDMI-*" }, action = "media_next" },
+            ]
+        "#;
+        let (config, _) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+        let ActionSpec::Conditional(rules) = &config.bindings.values().next().unwrap().action
+        else {
+            panic!("expected conditional rules");
+        };
+        assert!(rules[0].condition.monitor == Some(MonitorCondition::Index(2)));
+        assert!(rules[1].condition.monitor == Some(MonitorCondition::Name("HDMI-*".to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_strategy_config() {
+        let toml = r#"
+            [strategies.scroll]
+            type = "gated_hold"
+            initial_hold_ms = 150
+            repeat_window_ms = 2000
+
+            [bindings.0x7E]
+            action = "media_previous"
+            strategy = "scroll"
+        "#;
+        let result = load_from_str("test.toml", toml.to_string()).await;
+        assert!(result.is_ok());
+        let (config, runtime) = result.unwrap();
+        assert!(config.strategies.len() == 1);
+        assert!(runtime.strategies.contains_key("scroll"));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_action_name() {
+        let toml = r#"
+            [bindings.0x7C]
+            action = "invalid_action"
+        "#;
+        let result = load_from_str("test.toml", toml.to_string()).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_undefined_strategy_error() {
+        let toml = r#"
+            [bindings.0x7C]
+            action = "media_play_pause"
+            strategy = "nonexistent"
+        "#;
+        let result = load_from_str("test.toml", toml.to_string()).await;
+        assert!(result.is_err());
+        let err = result.unwrap_err();
+        let msg = format!("{err:?}");
+        assert!(msg.contains("nonexistent"));
+    }
+
+    #[tokio::test]
+    async fn test_strategy_chain() {
+        let toml = r#"
+            [strategies.volume]
+            type = "repeat"
+            start_interval_ms = 300
+            min_interval_ms = 50
+
+            [strategies.scroll]
+            type = "gated_hold"
+            initial_hold_ms = 100
+            repeat_window_ms = 1000
+
+            [bindings.0x7C]
+            action = "volume_up"
+            strategy = ["scroll", "volume"]
+        "#;
+        let (_, runtime) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+        let binding = runtime.bindings.get(&KeyCode::new(0x7C)).unwrap();
+        let chain: Vec<&str> = binding.strategies.iter().map(|s| s.as_str()).collect();
+        assert_eq!(chain, ["scroll", "volume"]);
+    }
+
+    #[tokio::test]
+    async fn test_strategy_chain_undefined_entry() {
+        let toml = r#"
+            [strategies.scroll]
+            type = "gated_hold"
+            initial_hold_ms = 100
+            repeat_window_ms = 1000
+
+            [bindings.0x7C]
+            action = "media_next"
+            strategy = ["scroll", "nonexistent"]
+        "#;
+        let result = load_from_str("test.toml", toml.to_string()).await;
+        assert!(result.is_err());
+        let msg = format!("{:?}", result.unwrap_err());
+        assert!(msg.contains("nonexistent"));
+    }
+
+    #[tokio::test]
+    async fn test_strategy_chain_duplicate_entry() {
+        let toml = r#"
+            [strategies.scroll]
+            type = "gated_hold"
+            initial_hold_ms = 100
+            repeat_window_ms = 1000
+
+            [bindings.0x7C]
+            action = "media_next"
+            strategy = ["scroll", "scroll"]
+        "#;
+        let result = load_from_str("test.toml", toml.to_string()).await;
+        assert!(result.is_err());
+        let msg = format!("{:?}", result.unwrap_err());
+        assert!(msg.contains("appears twice in chain"));
+    }
+
+    #[tokio::test]
+    async fn test_strategy_condition() {
+        let toml = r#"
+            [strategies.scroll]
+            type = "gated_hold"
+            initial_hold_ms = 100
+            repeat_window_ms = 1000
+            condition = { window = { binary = "firefox" } }
+
+            [strategies.volume]
+            type = "repeat"
+            start_interval_ms = 300
+            min_interval_ms = 50
+        "#;
+        let (_, runtime) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+
+        let firefox = WindowInfo {
+            binary: "firefox".to_string(),
+            ..Default::default()
+        };
+        let other = WindowInfo::default();
+        assert!(runtime.strategy_active("scroll", &firefox));
+        assert!(!runtime.strategy_active("scroll", &other));
+        assert!(runtime.strategy_active("volume", &other));
+    }
+
+    #[tokio::test]
+    async fn test_strategy_condition_invalid() {
+        let toml = r#"
+            [strategies.scroll]
+            type = "gated_hold"
+            initial_hold_ms = 100
+            repeat_window_ms = 1000
+            condition = "firefox"
+        "#;
+        let result = load_from_str("test.toml", toml.to_string()).await;
+        let msg = format!("{:?}", result.unwrap_err());
+        assert!(msg.contains("invalid strategy condition"));
+    }
+
+    #[tokio::test]
+    async fn test_binding_overrides_strategy_setting() {
+        let toml = r#"
+            [strategies.scroll]
+            type = "gated_hold"
+            initial_hold_ms = 100
+            repeat_window_ms = 1000
+
+            [bindings.0x7C]
+            action = "media_next"
+            strategy = "scroll"
+            initial_hold_ms = 300
+
+            [bindings.0x7D]
+            action = "media_previous"
+            strategy = "scroll"
+        "#;
+        let (_, runtime) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+
+        let overridden = runtime.bindings.get(&KeyCode::new(0x7C)).unwrap();
+        assert_eq!(overridden.strategies[0].as_str(), "scroll@0x7C");
+        assert!(runtime.strategies.contains_key("scroll@0x7C"));
+
+        let plain = runtime.bindings.get(&KeyCode::new(0x7D)).unwrap();
+        assert_eq!(plain.strategies[0].as_str(), "scroll");
+    }
+
+    #[tokio::test]
+    async fn test_binding_override_unknown_field() {
+        let toml = r#"
+            [strategies.scroll]
+            type = "gated_hold"
+            initial_hold_ms = 100
+            repeat_window_ms = 1000
+
+            [bindings.0x7C]
+            action = "media_next"
+            strategy = "scroll"
+            hold_ms = 300
+        "#;
+        let result = load_from_str("test.toml", toml.to_string()).await;
+        let msg = format!("{:?}", result.unwrap_err());
+        assert!(msg.contains("unknown binding field 'hold_ms'"));
+    }
+
+    #[tokio::test]
+    async fn test_binding_override_wrong_type() {
+        let toml = r#"
+            [strategies.scroll]
+            type = "gated_hold"
+            initial_hold_ms = 100
+            repeat_window_ms = 1000
+
+            [bindings.0x7C]
+            action = "media_next"
+            strategy = "scroll"
+            initial_hold_ms = "slow"
+        "#;
+        let result = load_from_str("test.toml", toml.to_string()).await;
+        let msg = format!("{:?}", result.unwrap_err());
+        assert!(msg.contains("invalid override of strategy 'scroll'"));
+    }
+
+    #[test]
+    fn test_parse_volume_actions() {
+        assert_eq!(parse_action("volume_up"), Ok(Action::VolumeUp));
+        assert_eq!(parse_action("volume_down"), Ok(Action::VolumeDown));
+        assert_eq!(parse_action("volume_mute"), Ok(Action::VolumeMute));
+    }
+
+    #[test]
+    fn test_parse_mic_actions() {
+        assert_eq!(parse_action("mic_mute"), Ok(Action::Mic(MicCommand::Mute)));
+        assert_eq!(
+            parse_action("mic_unmute"),
+            Ok(Action::Mic(MicCommand::Unmute))
+        );
+        assert_eq!(
+            parse_action("mic_toggle"),
+            Ok(Action::Mic(MicCommand::Toggle))
+        );
+        assert_eq!(Action::Mic(MicCommand::Toggle).to_string(), "mic_toggle");
+    }
+
+    #[test]
+    fn test_parse_desktop_actions() {
+        assert_eq!(parse_action("desktop_next"), Ok(Action::DesktopNext));
+        assert_eq!(
+            parse_action("desktop_previous"),
+            Ok(Action::DesktopPrevious)
+        );
+        assert_eq!(parse_action("desktop_3"), Ok(Action::Desktop(3)));
+        assert!(parse_action("desktop_0").is_err());
+        assert!(parse_action("desktop_left").is_err());
+    }
+
+    #[test]
+    fn test_parse_window_actions() {
+        assert_eq!(parse_action("window_next"), Ok(Action::WindowNext));
+        assert_eq!(parse_action("window_previous"), Ok(Action::WindowPrevious));
+        assert_eq!(Action::WindowPrevious.to_string(), "window_previous");
+    }
+
+    #[test]
+    fn test_parse_snap_actions() {
+        assert_eq!(
+            parse_action("snap_left"),
+            Ok(Action::Snap(SnapPosition::Left))
+        );
+        assert_eq!(
+            parse_action("snap_bottom_right"),
+            Ok(Action::Snap(SnapPosition::BottomRight))
+        );
+        assert_eq!(
+            Action::Snap(SnapPosition::Maximize).to_string(),
+            "snap_maximize"
+        );
+        assert!(parse_action("snap_middle").is_err());
+    }
+
+    #[test]
+    fn test_parse_session_actions() {
+        assert_eq!(
+            parse_action("lock_screen"),
+            Ok(Action::Session(SessionCommand::Lock))
+        );
+        assert_eq!(
+            Action::Session(SessionCommand::Lock).to_string(),
+            "lock_screen"
+        );
+        assert_eq!(
+            parse_action("system_sleep"),
+            Ok(Action::Session(SessionCommand::Sleep))
+        );
+        assert_eq!(
+            parse_action("display_off"),
+            Ok(Action::Session(SessionCommand::DisplayOff))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_clipboard_action() {
+        let toml = r#"
+            [bindings.0x7C]
+            action = { clipboard = "Thanks, will take a look!" }
+
+            [bindings.0x7D]
+            action = { clipboard = { text = "LGTM", paste = true } }
+
+            [bindings.0x7E]
+            action = [
+                { condition = { window = { binary = "slack" } }, action = { clipboard = "on it" } },
+            ]
+        "#;
+        let (_, runtime) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+        let action = |code: u32| {
+            runtime
+                .resolve_action(KeyCode::new(code), &WindowInfo::default())
+                .cloned()
+        };
+        assert_eq!(
+            action(0x7C),
+            Some(Action::Clipboard {
+                text: "Thanks, will take a look!".to_string(),
+                paste: false,
+            })
+        );
+        assert_eq!(
+            action(0x7D),
+            Some(Action::Clipboard {
+                text: "LGTM".to_string(),
+                paste: true,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_clipboard_template_checked_on_load() {
+        let toml = r#"
+            [bindings.0x7C]
+            action = { clipboard = "{date:%F} {window.title}" }
+        "#;
+        assert!(load_from_str("test.toml", toml.to_string()).await.is_ok());
+
+        let toml = r#"
+            [bindings.0x7C]
+            action = { clipboard = { text = "{window.titel}", paste = true } }
+        "#;
+        let msg = format!(
+            "{:?}",
+            load_from_str("test.toml", toml.to_string())
+                .await
+                .unwrap_err()
+        );
+        assert!(msg.contains("unknown template variable `window.titel`"));
+    }
+
+    #[tokio::test]
+    async fn test_player_media_action() {
+        let toml = r#"
+            [bindings.0x7C]
+            action = { media_play_pause = { player = "spotify" } }
+
+            [bindings.0x7D]
+            action = { media_next = { player = "org.mpris.MediaPlayer2.mpv" } }
+
+            [bindings.0x7E]
+            action = { media_pause = { binary = "spotify*" } }
+        "#;
+        let (_, runtime) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+        let action = |code: u32| {
+            runtime
+                .resolve_action(KeyCode::new(code), &WindowInfo::default())
+                .cloned()
+        };
+        assert_eq!(
+            action(0x7C),
+            Some(Action::PlayerMedia {
+                player: PlayerTarget::Name("spotify".to_string()),
+                command: MediaCommand::PlayPause,
+            })
+        );
+        assert_eq!(
+            action(0x7C).unwrap().to_string(),
+            "media_play_pause (spotify)"
+        );
+        assert_eq!(
+            action(0x7D),
+            Some(Action::PlayerMedia {
+                player: PlayerTarget::Name("org.mpris.MediaPlayer2.mpv".to_string()),
+                command: MediaCommand::Next,
+            })
+        );
+        assert_eq!(
+            action(0x7E),
+            Some(Action::PlayerMedia {
+                player: PlayerTarget::Binary("spotify*".to_string()),
+                command: MediaCommand::Pause,
+            })
+        );
+        assert_eq!(
+            action(0x7E).unwrap().to_string(),
+            "media_pause (binary spotify*)"
+        );
+
+        for spec in [
+            r#"{ media_next = "spotify" }"#,
+            r#"{ media_next = { player = "" } }"#,
+            r#"{ media_next = { binary = "" } }"#,
+            r#"{ media_pause = { player = "spotify", binary = "spotify" } }"#,
+            r#"{ media_pause = {} }"#,
+            r#""media_pause""#,
+            r#"{ media_next = { player = "spotify", app = "x" } }"#,
+            r#"{ volume_up = { player = "spotify" } }"#,
+        ] {
+            let toml = format!("[bindings.0x7C]\naction = {spec}\n");
+            assert!(
+                load_from_str("test.toml", toml).await.is_err(),
+                "expected error for {spec}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_action_chain() {
+        use crate::platform::MockPlatform;
+        use crate::platform::mock::PlatformCall;
+        use crate::strategy::PlatformHandle;
+
+        let toml = r#"
+            [bindings.0x7C]
+            action = ["media_play_pause", { notify = "paused" }, ["volume_down", "volume_down"]]
+
+            [bindings.0x7D]
+            action = [
+                { condition = { window = { binary = "mpv" } }, action = ["media_stop", { run = "true" }] },
+            ]
+        "#;
+        let (_, runtime) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+        let window = |binary: &str| WindowInfo {
+            binary: binary.to_string(),
+            ..Default::default()
+        };
+
+        let chain = runtime
+            .resolve_action(KeyCode::new(0x7C), &window(""))
+            .cloned()
+            .unwrap();
+        assert_eq!(
+            chain,
+            Action::Chain(vec![
+                Action::MediaPlayPause,
+                Action::Notify("paused".to_string()),
+                Action::VolumeDown,
+                Action::VolumeDown,
+            ])
+        );
+        assert_eq!(
+            chain.to_string(),
+            "media_play_pause, notify paused, volume_down, volume_down"
+        );
+        assert_eq!(
+            runtime
+                .resolve_action(KeyCode::new(0x7D), &window("mpv"))
+                .cloned(),
+            Some(Action::Chain(vec![
+                Action::MediaStop,
+                Action::Run(ShellCommand {
+                    command: "true".to_string(),
+                    shell: Default::default(),
+                }),
+            ]))
+        );
+
+        // Steps run in order on a spawned task
+        let platform = MockPlatform::new();
+        let handle = PlatformHandle::from_mock(&platform);
+        handle.execute(&chain);
+        assert!(platform.calls().is_empty());
+        tokio::task::yield_now().await;
+        assert_eq!(
+            platform.calls(),
+            vec![
+                PlatformCall::SendMedia(MediaCommand::PlayPause),
+                PlatformCall::Notify("paused".to_string()),
+                PlatformCall::SendMedia(MediaCommand::VolumeDown),
+                PlatformCall::SendMedia(MediaCommand::VolumeDown),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_send_action() {
+        use crate::platform::MockPlatform;
+        use crate::platform::mock::PlatformCall;
+        use crate::strategy::PlatformHandle;
+
+        let toml = r#"
+            [bindings.0x7C]
+            action = { send = "ctrl+shift+esc" }
+        "#;
+        let (_, runtime) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+        let action = runtime
+            .resolve_action(KeyCode::new(0x7C), &WindowInfo::default())
+            .cloned()
+            .unwrap();
+        // Linux and Windows name the left modifiers differently
+        let key = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|name| KeyCode::from_config_str(name))
+                .unwrap()
+        };
+        let (ctrl, shift, esc) = (
+            key(&["leftctrl", "lctrl"]),
+            key(&["leftshift", "lshift"]),
+            key(&["esc"]),
+        );
+        assert_eq!(action, Action::Send(vec![ctrl, shift, esc]));
+
+        // Modifiers go down first and come up last
+        let platform = MockPlatform::new();
+        PlatformHandle::from_mock(&platform).execute(&action);
+        let edge = |key, down| PlatformCall::SendRawKey { key, down };
+        assert_eq!(
+            platform.calls(),
+            vec![
+                edge(ctrl, true),
+                edge(shift, true),
+                edge(esc, true),
+                edge(esc, false),
+                edge(shift, false),
+                edge(ctrl, false),
+            ]
+        );
+
+        let toml = r#"
+            [bindings.0x7C]
+            action = { send = "ctrl+nosuchkey" }
+        "#;
+        let msg = format!(
+            "{:?}",
+            load_from_str("test.toml", toml.to_string())
+                .await
+                .unwrap_err()
+        );
+        assert!(msg.contains("unknown key 'nosuchkey'"));
+    }
+
+    #[tokio::test]
+    async fn test_chain_delay_steps() {
+        use crate::platform::MockPlatform;
+        use crate::platform::mock::PlatformCall;
+        use crate::strategy::PlatformHandle;
+
+        let toml = r#"
+            [bindings.0x7C]
+            action = ["media_next", { delay_ms = 50 }, "media_next"]
+        "#;
+        let (_, runtime) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+        let chain = runtime
+            .resolve_action(KeyCode::new(0x7C), &WindowInfo::default())
+            .cloned()
+            .unwrap();
+        assert_eq!(
+            chain,
+            Action::Chain(vec![
+                Action::MediaNext,
+                Action::Delay(50),
+                Action::MediaNext
+            ])
+        );
+
+        let platform = MockPlatform::new();
+        let handle = PlatformHandle::from_mock(&platform);
+        handle.execute(&chain);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(
+            platform.calls(),
+            vec![PlatformCall::SendMedia(MediaCommand::Next)]
+        );
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(
+            platform.calls(),
+            vec![
+                PlatformCall::SendMedia(MediaCommand::Next),
+                PlatformCall::SendMedia(MediaCommand::Next),
+            ]
+        );
+
+        for spec in [
+            "{ delay_ms = 200 }",
+            r#"["media_next", { delay_ms = -1 }]"#,
+            r#"["media_next", { delay_ms = "200" }]"#,
+            r#"[{ condition = { window = { binary = "x" } }, action = { delay_ms = 5 } }]"#,
+        ] {
+            let toml = format!("[bindings.0x7C]\naction = {spec}\n");
+            assert!(
+                load_from_str("test.toml", toml).await.is_err(),
+                "expected error for {spec}"
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_dry_run_skips_commands() {
+        use crate::platform::MockPlatform;
+        use crate::platform::mock::PlatformCall;
+        use crate::strategy::PlatformHandle;
+
+        let marker = std::env::temp_dir().join(format!("rebinded-dry-run-{}", std::process::id()));
+        let _ = std::fs::remove_file(&marker);
+        let toml = format!(
+            "[bindings.0x7C]\naction = [{{ run = \"touch {}\" }}, \"media_next\"]\n",
+            marker.display()
+        );
+        let (_, runtime) = load_from_str("test.toml", toml).await.unwrap();
+        let chain = runtime
+            .resolve_action(KeyCode::new(0x7C), &WindowInfo::default())
+            .cloned()
+            .unwrap();
+
+        let platform = MockPlatform::new();
+        let handle = PlatformHandle::from_mock(&platform);
+        handle.execute(&chain);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(
+            platform.calls(),
+            vec![PlatformCall::SendMedia(MediaCommand::Next)]
+        );
+        assert!(!marker.exists());
+    }
+
+    #[tokio::test]
+    async fn test_scancode_bindings() {
+        let toml = r#"
+            [bindings."sc:0x64"]
+            action = "media_next"
+        "#;
+        let result = load_from_str("test.toml", toml.to_string()).await;
+        if cfg!(windows) {
+            let (_, runtime) = result.unwrap();
+            assert!(runtime.bindings.contains_key(&KeyCode::from_scancode(0x64)));
+            assert_eq!(runtime.scancodes(), BTreeSet::from([0x64]));
+        } else {
+            assert!(result.is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_midi_bindings() {
+        let toml = r#"
+            [bindings."midi:note:36"]
+            action = "media_play_pause"
+        "#;
+        let result = load_from_str("test.toml", toml.to_string()).await;
+        if cfg!(windows) {
+            assert!(result.is_err());
+        } else {
+            let (_, runtime) = result.unwrap();
+            let key = KeyCode::from_midi(crate::key::MidiInput::Note(36));
+            assert!(runtime.bindings.contains_key(&key));
+            assert!(runtime.uses_midi());
+        }
+
+        let (_, runtime) = load_from_str("test.toml", String::new()).await.unwrap();
+        assert!(!runtime.uses_midi());
+    }
+
+    #[tokio::test]
+    async fn test_gamepad_bindings() {
+        let toml = r#"
+            [settings.gamepad]
+            devices = ["*Xbox*"]
+
+            [bindings."pad:start"]
+            action = "media_play_pause"
+        "#;
+        let (config, runtime) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+        let key = KeyCode::from_gamepad(crate::key::GamepadButton::Start);
+        assert!(runtime.bindings.contains_key(&key));
+        assert!(runtime.uses_gamepad());
+        assert!(!runtime.uses_midi());
+        assert_eq!(config.settings.gamepad.devices, vec!["*Xbox*".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_slow_keys_settings() {
+        let toml = r#"
+            [settings.accessibility]
+            slow_keys_ms = 300
+            slow_keys = ["0x41", "no_such_key"]
+        "#;
+        assert!(load_from_str("test.toml", toml.to_string()).await.is_err());
+
+        let toml = r#"
+            [settings.accessibility]
+            slow_keys_ms = 300
+        "#;
+        let (_, runtime) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+        assert!(runtime.accessibility.is_active());
+        assert!(runtime.key_routes().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_trigger_settings() {
+        let toml = r#"
+            [settings.trigger]
+            listen = "127.0.0.1:7878"
+            token = "secret"
+
+            [bindings.f13]
+            action = [
+                { condition = { window = { binary = "obs*" } }, action = "media_next" },
+                { action = "media_play_pause" },
+            ]
+        "#;
+        let (config, runtime) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+        assert_eq!(config.settings.trigger.token.as_deref(), Some("secret"));
+        let f13 = KeyCode::from_config_str("f13");
+        let targets = runtime.trigger_targets();
+        assert_eq!(targets.resolve("f13"), f13);
+        assert_eq!(targets.resolve("media_next"), f13);
+        assert_eq!(targets.resolve("media_play_pause"), f13);
+        assert_eq!(targets.resolve("f14"), None);
+
+        // Reachable from other devices only on request, and never without a token
+        for settings in [
+            r#"listen = "0.0.0.0:7878"
+            token = "secret""#,
+            r#"listen = "127.0.0.1:7878""#,
+        ] {
+            let toml = format!("[settings.trigger]\n{settings}");
+            assert!(load_from_str("test.toml", toml).await.is_err());
+        }
+        let toml = r#"
+            [settings.trigger]
+            listen = "0.0.0.0:7878"
+            token = "secret"
+            allow_remote = true
+        "#;
+        assert!(load_from_str("test.toml", toml.to_string()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_hid_devices() {
+        let toml = r#"
+            [[hid]]
+            device = "*Foot Switch*"
+            keys = { test_hid_left = "0xFF00:0x01", test_hid_right = "0xFF00:0x03" }
+
+            [bindings."hid:test_hid_left"]
+            action = "media_play_pause"
+        "#;
+        let result = load_from_str("test.toml", toml.to_string()).await;
+        if cfg!(windows) {
+            assert!(result.is_err());
+            return;
+        }
+        let (config, runtime) = result.unwrap();
+        assert_eq!(config.hid.len(), 1);
+        assert_eq!(runtime.hid, config.hid);
+        assert!(
+            runtime
+                .bindings
+                .contains_key(&KeyCode::from_hid_name("test_hid_left"))
+        );
+
+        // Keys defined by an earlier config aren't known to one without them
+        let toml = r#"
+            [bindings."hid:test_hid_right"]
+            action = "media_next"
+        "#;
+        assert!(load_from_str("test.toml", toml.to_string()).await.is_err());
+
+        for invalid in [
+            r#"[[hid]]
+               keys = { pedal = "0xFF00:0x01" }"#,
+            r#"[[hid]]
+               device = "pedal"
+               keys = { pedal = "0xFF00" }"#,
+        ] {
+            let result = load_from_str("test.toml", invalid.to_string()).await;
+            assert!(result.is_err(), "{invalid}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_branch_in_chain() {
+        use crate::platform::MockPlatform;
+        use crate::platform::mock::PlatformCall;
+        use crate::strategy::PlatformHandle;
+
+        let toml = r#"
+            [bindings.0x7C]
+            action = [
+                "media_play_pause",
+                { if = { window = { binary = "mpv" } }, then = ["media_stop", "media_next"], else = { notify = "not mpv" } },
+                "volume_up",
+            ]
+
+            [bindings.0x7D]
+            action = { if = { window = { not_binary = "mpv" } }, then = "media_next" }
+        "#;
+        let (_, runtime) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+        let window = WindowInfo::default();
+        let chain = runtime
+            .resolve_action(KeyCode::new(0x7C), &window)
+            .cloned()
+            .unwrap();
+        let Action::Chain(steps) = &chain else {
+            panic!("expected chain, got {chain:?}");
+        };
+        assert_eq!(
+            steps[1],
+            Action::Branch {
+                condition: Condition {
+                    window: WindowCondition {
+                        binary: Some("mpv".to_string()),
+                        ..Default::default()
+                    },
+                    power: None,
+                    monitor: None,
+                },
+                then: Box::new(Action::Chain(vec![Action::MediaStop, Action::MediaNext])),
+                otherwise: Some(Box::new(Action::Notify("not mpv".to_string()))),
+            }
+        );
+
+        // The mock's focused window is empty, so the `else` branch runs, in order
+        let platform = MockPlatform::new();
+        let handle = PlatformHandle::from_mock(&platform);
+        handle.execute(&chain);
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(
+            platform.calls(),
+            vec![
+                PlatformCall::SendMedia(MediaCommand::PlayPause),
+                PlatformCall::Notify("not mpv".to_string()),
+                PlatformCall::SendMedia(MediaCommand::VolumeUp),
+            ]
+        );
+
+        platform.clear_calls();
+        let branch = runtime.resolve_action(KeyCode::new(0x7D), &window).unwrap();
+        handle.execute(branch);
+        assert_eq!(
+            platform.calls(),
+            vec![PlatformCall::SendMedia(MediaCommand::Next)]
+        );
+
+        for spec in [
+            r#"{ if = { window = { binary = "mpv" } } }"#,
+            r#"{ if = {}, then = "media_next" }"#,
+            r#"{ if = { window = { binary = "mpv" } }, then = "media_next", otherwise = "media_stop" }"#,
+            r#"{ if = { window = { binray = "mpv" } }, then = "media_next" }"#,
+            r#"{ if = { window = { binary = "mpv" } }, then = "passthrough" }"#,
+            r#"{ if = { window = { binary = "mpv" } }, then = "bogus" }"#,
+        ] {
+            let toml = format!("[bindings.0x7C]\naction = {spec}\n");
+            assert!(
+                load_from_str("test.toml", toml).await.is_err(),
+                "expected error for {spec}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_invalid_action_chain() {
+        for spec in [
+            "[]",
+            r#"["media_next", "bogus"]"#,
+            r#"["media_next", "passthrough"]"#,
+            r#"["media_next", { run = "" }]"#,
+            "[1, 2]",
+        ] {
+            let toml = format!("[bindings.0x7C]\naction = {spec}\n");
+            assert!(
+                load_from_str("test.toml", toml).await.is_err(),
+                "expected error for {spec}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_typed_action_table() {
+        let toml = r#"
+            [bindings.0x7C]
+            action = { type = "volume_up", step = 5 }
+
+            [bindings.0x7D]
+            action = { type = "volume_down", step = 10 }
+
+            [bindings.0x7E]
+            action = { type = "media_next", player = "spotify" }
+
+            [bindings.0x7F]
+            action = ["media_play_pause", { type = "notify", message = "paused" }, { type = "browser_back" }]
+        "#;
+        let (_, runtime) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+        let action = |code: u32| {
+            runtime
+                .resolve_action(KeyCode::new(code), &WindowInfo::default())
+                .cloned()
+        };
+        assert_eq!(action(0x7C), Some(Action::VolumeStep(5)));
+        assert_eq!(action(0x7D), Some(Action::VolumeStep(-10)));
+        assert_eq!(action(0x7D).unwrap().to_string(), "volume_down 10%");
+        assert_eq!(
+            action(0x7E),
+            Some(Action::PlayerMedia {
+                player: PlayerTarget::Name("spotify".to_string()),
+                command: MediaCommand::Next,
+            })
+        );
+        assert_eq!(
+            action(0x7F),
+            Some(Action::Chain(vec![
+                Action::MediaPlayPause,
+                Action::Notify("paused".to_string()),
+                Action::BrowserBack,
+            ]))
+        );
+
+        for spec in [
+            r#"{ type = "volume_up", step = 0 }"#,
+            r#"{ type = "volume_up", step = "5" }"#,
+            r#"{ type = "volume_up", stepp = 5 }"#,
+            r#"{ type = "browser_back", step = 5 }"#,
+            r#"{ type = "bogus", step = 5 }"#,
+            r#"{ type = 3 }"#,
+        ] {
+            let toml = format!("[bindings.0x7C]\naction = {spec}\n");
+            assert!(
+                load_from_str("test.toml", toml).await.is_err(),
+                "expected error for {spec}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_typed_action_error_spans() {
+        fn error_at(source: &str) -> Option<&str> {
+            let root = DeTable::parse(source).unwrap().into_inner();
+            let (_, value) = root.into_iter().next().unwrap();
+            let DeValue::Table(table) = value.into_inner() else {
+                panic!("expected table");
+            };
+            let fields = action_field_spans(&table);
+            let value =
+                toml::Value::Table(toml::from_str::<toml::Table>(source).unwrap())["action"]
+                    .clone();
+            let error = parse_action_value(&value).unwrap_err();
+            action_error_span(&fields, &error).map(|span| &source[span])
+        }
+
+        assert_eq!(
+            error_at(r#"action = { type = "volume_up", stepp = 5 }"#),
+            Some("stepp")
+        );
+        assert_eq!(
+            error_at(r#"action = { type = "volume_up", step = 500 }"#),
+            Some("step")
+        );
+        assert_eq!(
+            error_at(r#"action = { type = "bogus", step = 5 }"#),
+            Some("\"bogus\"")
+        );
+        assert_eq!(error_at(r#"action = { clipboard = 5 }"#), None);
+    }
+
+    #[tokio::test]
+    async fn test_obs_action_and_settings() {
+        use crate::integrations::obs::ObsToggle;
+
+        let toml = r#"
+            [settings.obs]
+            port = 4456
+            password = "secret"
+
+            [bindings.0x7C]
+            action = { obs = { scene = "Gaming" } }
+
+            [bindings.0x7D]
+            action = { obs = { toggle = "record" } }
+        "#;
+        let (config, runtime) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+        assert!(config.settings.obs.port == 4456);
+        assert!(config.settings.obs.host == "localhost");
+        assert!(config.settings.obs.password.as_deref() == Some("secret"));
+
+        let action = |code: u32| {
+            runtime
+                .resolve_action(KeyCode::new(code), &WindowInfo::default())
+                .cloned()
+        };
+        assert_eq!(
+            action(0x7C),
+            Some(Action::Obs(ObsRequest::Scene("Gaming".to_string())))
+        );
+        assert_eq!(
+            action(0x7D),
+            Some(Action::Obs(ObsRequest::Toggle(ObsToggle::Record)))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_home_assistant_action() {
+        let toml = r#"
+            [settings.home_assistant]
+            url = "http://homeassistant.local:8123"
+
+            [bindings.0x7C]
+            action = { home_assistant = { service = "light.toggle", entity_id = "light.desk" } }
+        "#;
+        let (config, runtime) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+        assert!(
+            config.settings.home_assistant.url.as_deref()
+                == Some("http://homeassistant.local:8123")
+        );
+        let action = runtime.resolve_action(KeyCode::new(0x7C), &WindowInfo::default());
+        let Some(Action::HomeAssistant(call)) = action else {
+            panic!("expected home_assistant action, got {action:?}");
+        };
+        assert!(call.domain == "light");
+        assert!(call.service == "toggle");
+        assert!(call.body == r#"{"entity_id":"light.desk"}"#);
+    }
+
+    #[tokio::test]
+    async fn test_http_action() {
+        let toml = r#"
+            [bindings.0x7C]
+            action = { http = { url = "http://nas.local:8080/hook", body = "pressed" } }
+        "#;
+        let (_, runtime) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+        let action = runtime.resolve_action(KeyCode::new(0x7C), &WindowInfo::default());
+        let Some(Action::Http(webhook)) = action else {
+            panic!("expected http action, got {action:?}");
+        };
+        assert!(webhook.method == "POST");
+        assert!(webhook.url.port == 8080);
+        assert!(webhook.body == "pressed");
+    }
+
+    #[tokio::test]
+    async fn test_dbus_action() {
+        let toml = r#"
+            [bindings.0x7C]
+            action = { dbus = { destination = "org.freedesktop.ScreenSaver", path = "/org/freedesktop/ScreenSaver", interface = "org.freedesktop.ScreenSaver", method = "Lock" } }
+        "#;
+        let (_, runtime) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+        let action = runtime.resolve_action(KeyCode::new(0x7C), &WindowInfo::default());
+        let Some(Action::Dbus(call)) = action else {
+            panic!("expected dbus action, got {action:?}");
+        };
+        assert!(call.destination == "org.freedesktop.ScreenSaver");
+        assert!(call.method == "Lock");
+        assert!(action.unwrap().to_string() == "dbus org.freedesktop.ScreenSaver.Lock");
+    }
+
+    #[tokio::test]
+    async fn test_focus_action() {
+        use crate::integrations::shell::Shell;
+
+        let toml = r#"
+            [bindings.0x7C]
+            action = { focus = { binary = "slack*", launch = "slack" } }
+
+            [bindings.0x7D]
+            action = { focus = { class = "kitty", not_title = "*vim*" } }
+        "#;
+        let (_, runtime) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+        let action = |code: u32| {
+            runtime
+                .resolve_action(KeyCode::new(code), &WindowInfo::default())
+                .cloned()
+                .unwrap()
+        };
+        assert_eq!(
+            action(0x7C),
+            Action::Focus(FocusTarget {
+                window: WindowCondition {
+                    binary: Some("slack*".to_string()),
+                    ..Default::default()
+                },
+                launch: Some(ShellCommand {
+                    command: "slack".to_string(),
+                    shell: Shell::System,
+                }),
+            })
+        );
+        assert_eq!(action(0x7C).to_string(), "focus slack*");
+        let Action::Focus(target) = action(0x7D) else {
+            panic!("expected focus action");
+        };
+        assert!(target.launch.is_none());
+        assert!(target.window.class.as_deref() == Some("kitty"));
+
+        for spec in [
+            r#"{ focus = "slack" }"#,
+            r#"{ focus = { launch = "slack" } }"#,
+            r#"{ focus = { binary = "slack", exec = "slack" } }"#,
+            r#"{ focus = { binary = "slack", launch = "" } }"#,
+        ] {
+            let toml = format!("[bindings.0x7C]\naction = {spec}\n");
+            assert!(
+                load_from_str("test.toml", toml).await.is_err(),
+                "expected error for {spec}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_notify_action_and_feedback() {
+        let toml = r#"
+            [bindings.0x7C]
+            action = { notify = "hello" }
+
+            [bindings.0x7D]
+            action = "volume_mute"
+            notify_on_execute = true
+
+            [bindings.0x7E]
+            action = "volume_mute"
+            notify_on_execute = "Mic toggled"
+
+            [bindings.0x7F]
+            action = "volume_mute"
+            notify_on_execute = false
+        "#;
+        let (_, runtime) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+        let window = WindowInfo::default();
+        let resolve = |code: u32| {
+            let binding = &runtime.bindings[&KeyCode::new(code)];
+            let action = runtime.resolve_action(KeyCode::new(code), &window).unwrap();
+            (action.clone(), binding.notification(action))
+        };
+
+        assert!(resolve(0x7C) == (Action::Notify("hello".to_string()), None));
+        assert!(resolve(0x7D) == (Action::VolumeMute, Some("volume_mute".to_string())));
+        assert!(resolve(0x7E) == (Action::VolumeMute, Some("Mic toggled".to_string())));
+        assert!(resolve(0x7F) == (Action::VolumeMute, None));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_notify_on_execute() {
+        let toml = r#"
+            [bindings.0x7C]
+            action = "volume_mute"
+            notify_on_execute = 3
+        "#;
+        let err = load_from_str("test.toml", toml.to_string())
+            .await
+            .unwrap_err();
+        assert!(format!("{err:?}").contains("notify_on_execute must be"));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_settings() {
+        let toml = r#"
+            [settings.obs]
+            prot = 4456
+        "#;
+        let err = load_from_str("test.toml", toml.to_string())
+            .await
+            .unwrap_err();
+        assert!(format!("{err:?}").contains("invalid settings"));
+    }
+
+    #[tokio::test]
+    async fn test_home_assistant_url_scheme() {
+        let toml = r#"
+            [settings.home_assistant]
+            url = "https://example.ui.nabu.casa"
+        "#;
+        assert!(load_from_str("test.toml", toml.to_string()).await.is_ok());
+
+        let toml = r#"
+            [settings.home_assistant]
+            url = "ws://homeassistant.local:8123"
+        "#;
+        let err = load_from_str("test.toml", toml.to_string())
+            .await
+            .unwrap_err();
+        assert!(format!("{err:?}").contains("unsupported scheme"));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_action_table() {
+        for action in [
+            r#"{ clipboard = { text = "x", pasted = true } }"#,
+            r#"{ clipboard = "x", paste = true }"#,
+            r#"{ clipboard = 5 }"#,
+            r#"{ teleport = "x" }"#,
+            r#"{ obs = { toggle = "lights" } }"#,
+            r#"{ home_assistant = { service = "toggle" } }"#,
+            r#"{ http = { url = "ftp://example.com" } }"#,
+            r#"{ notify = { message = "x" } }"#,
+        ] {
+            let toml = format!("[bindings.0x7C]\naction = {action}\n");
+            let result = load_from_str("test.toml", toml).await;
+            assert!(result.is_err(), "expected error for {action}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_conditional_desktop_action() {
+        let toml = r#"
+            [bindings.f13]
+            action = [
+                { condition = { window = { binary = "code" } }, action = "desktop_2" },
+                { action = "desktop_next" },
+            ]
+        "#;
+        let (config, _) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+        assert_eq!(config.bindings.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_binding_error() {
+        // Both hex codes resolve to the same key
+        let toml = r#"
+            [bindings.0x7C]
+            action = "media_play_pause"
+
+            [bindings.124]
+            action = "block"
+        "#;
+        let result = load_from_str("test.toml", toml.to_string()).await;
+        assert!(result.is_err());
+        let err = result.unwrap_err();
+        let msg = format!("{err:?}");
+        assert!(msg.contains("duplicate"));
+    }
+
+    #[tokio::test]
+    async fn test_user_layer_overrides_system_layer() {
+        let system = r#"
+            [settings]
+            slow_event_ms = 50
+            obs = { host = "obs.lan", port = 4456 }
+
+            [bindings.f13]
+            action = "media_play_pause"
+
+            [bindings.f14]
+            action = "media_next"
+        "#;
+        let user = r#"
+            [settings.obs]
+            port = 4457
+
+            [bindings.f13]
+            action = "block"
+        "#;
+        let (config, runtime) = load_layers(
+            vec![("system.toml".to_string(), system.to_string())],
+            vec![("user.toml".to_string(), user.to_string())],
+        )
+        .await
+        .unwrap();
+
+        assert!(config.bindings.len() == 2);
+        let f13 = KeyCode::from_config_str("f13").unwrap();
+        assert!(runtime.resolve_action(f13, &WindowInfo::default()) == Some(&Action::Block));
+        // Settings merge value by value rather than table by table
+        assert!(config.settings.slow_event_ms == 50);
+        assert!(config.settings.obs.host == "obs.lan");
+        assert!(config.settings.obs.port == 4457);
+    }
+
+    #[tokio::test]
+    async fn test_managed_locks_bindings_and_commands() {
+        let system = r#"
+            [managed]
+            locked_bindings = ["f13"]
+            allow_commands = false
+
+            [strategies.slow]
+            type = "debounce"
+            chatter_ms = 30
+
+            [bindings.f13]
+            action = { run = "corp-vpn toggle" }
+            strategy = "slow"
+        "#;
+        let load = |user: &str| {
+            load_layers(
+                vec![("system.toml".to_string(), system.to_string())],
+                vec![("user.toml".to_string(), user.to_string())],
+            )
+        };
+
+        // The system config may run commands and bind locked keys itself
+        let (config, _) = load("[bindings.f14]\naction = \"media_next\"")
+            .await
+            .unwrap();
+        assert!(!config.managed.allow_commands);
+        assert!(config.bindings.len() == 2);
+
+        let msg = format!(
+            "{:?}",
+            load("[bindings.f13]\naction = \"block\"")
+                .await
+                .unwrap_err()
+        );
+        assert!(msg.contains("locked by the system config"));
+
+        let msg = format!(
+            "{:?}",
+            load("[strategies.slow]\ntype = \"debounce\"\nchatter_ms = 5")
+                .await
+                .unwrap_err()
+        );
+        assert!(msg.contains("used by a locked binding"));
+
+        let user = r#"
+            [bindings.f14]
+            action = ["media_next", { run = "rm -rf ~" }]
+
+            [bindings.f15]
+            action = { focus = { binary = "slack", launch = "slack" } }
+
+            [bindings.f16]
+            action = { http = { url = "http://nas.local/hooks/backup" } }
+
+            [bindings.f17]
+            action = { type = "dbus", destination = "org.freedesktop.ScreenSaver", path = "/org/freedesktop/ScreenSaver", interface = "org.freedesktop.ScreenSaver", method = "Lock" }
+        "#;
+        let err = load(user).await.unwrap_err();
+        let ConfigError::Validation(validation) = &err else {
+            panic!("expected a validation error, got {err:?}");
+        };
+        assert!(miette::Diagnostic::related(validation).map_or(0, |issues| issues.count()) == 4);
+        assert!(format!("{err:?}").contains("commands are disabled"));
+
+        let msg = format!(
+            "{:?}",
+            load("[managed]\nallow_commands = true").await.unwrap_err()
+        );
+        assert!(msg.contains("can only be set in the system config"));
+    }
+
+    #[tokio::test]
+    async fn test_include_layers_beneath_including_file() {
+        let dir = std::env::temp_dir().join(format!("rebinded-include-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("shared.toml"),
+            "[bindings.f13]\naction = \"media_next\"\n[bindings.f14]\naction = \"media_previous\"\n",
+        )
+        .unwrap();
+        let path = dir.join("config.toml");
+        std::fs::write(
+            &path,
+            "include = [\"shared.toml\"]\n[bindings.f13]\naction = \"block\"\n",
+        )
+        .unwrap();
+
+        let (config, runtime) = load(&path).await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(config.bindings.len() == 2);
+        let f13 = KeyCode::from_config_str("f13").unwrap();
+        assert!(runtime.resolve_action(f13, &WindowInfo::default()) == Some(&Action::Block));
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_binding_across_layers() {
+        let system = r#"
+            [bindings.0x7C]
+            action = "media_play_pause"
+        "#;
+        let user = r#"
+            [bindings.124]
+            action = "block"
+        "#;
+        let err = load_layers(
+            vec![("system.toml".to_string(), system.to_string())],
+            vec![("user.toml".to_string(), user.to_string())],
+        )
+        .await
+        .unwrap_err();
+        let msg = format!("{err:?}");
+        assert!(msg.contains("duplicate"));
+        assert!(msg.contains("of system.toml") || msg.contains("of user.toml"));
+    }
+
+    #[tokio::test]
+    async fn test_range_binding_expands() {
+        let toml = r#"
+            [bindings."0x7C..0x7E"]
+            action = "media_play_pause"
+        "#;
+        let (config, runtime) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+        assert!(config.bindings.len() == 1);
+        assert!(runtime.bindings.len() == 3);
+        for code in 0x7C..=0x7E {
+            assert!(runtime.bindings.contains_key(&KeyCode::new(code)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_key_set_binding_expands() {
+        let toml = r#"
+            [bindings.media_keys]
+            keys = ["0x7C", "0x80..0x81"]
+            action = "media_next"
+        "#;
+        let (_, runtime) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+        assert!(runtime.bindings.len() == 3);
+        assert!(runtime.bindings.contains_key(&KeyCode::new(0x7C)));
+        assert!(runtime.bindings.contains_key(&KeyCode::new(0x81)));
+        // The group label itself is never resolved as a key
+        assert!(!runtime.bindings.contains_key(&KeyCode::new(0)));
+    }
+
+    #[tokio::test]
+    async fn test_range_overlapping_explicit_binding_is_duplicate() {
+        let toml = r#"
+            [bindings."0x7C..0x80"]
+            action = "media_play_pause"
+
+            [bindings.0x7E]
+            action = "block"
+        "#;
+        let result = load_from_str("test.toml", toml.to_string()).await;
+        assert!(result.is_err());
+        let msg = format!("{:?}", result.unwrap_err());
+        assert!(msg.contains("duplicate"));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_range_error() {
+        let toml = r#"
+            [bindings."0x80..0x7C"]
+            action = "media_play_pause"
+        "#;
+        let result = load_from_str("test.toml", toml.to_string()).await;
+        assert!(result.is_err());
+        let msg = format!("{:?}", result.unwrap_err());
+        assert!(msg.contains("reversed"));
+    }
+
+    #[tokio::test]
+    async fn test_empty_key_set_error() {
+        let toml = r#"
+            [bindings.group]
+            keys = []
+            action = "media_next"
+        "#;
+        let result = load_from_str("test.toml", toml.to_string()).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_multiple_errors_collected() {
+        let toml = r#"
+            [bindings.invalid_key_1]
+            action = "media_play_pause"
+
+            [bindings.invalid_key_2]
+            action = "media_next"
+            strategy = "undefined_strategy"
+        "#;
+        let result = load_from_str("test.toml", toml.to_string()).await;
+        assert!(result.is_err());
+
+        // Should have multiple errors
+        if let Err(ConfigError::Validation(v)) = result {
+            // Check that debug output mentions both issues
+            let msg = format!("{v:?}");
+            assert!(msg.contains("invalid_key_1") || msg.contains("invalid_key_2"));
+        } else {
+            panic!("expected validation error");
+        }
+    }
+
+    #[test]
+    fn test_window_condition_matching() {
+        let condition = WindowCondition {
+            title: Some("*vivaldi*".to_string()),
+            ..Default::default()
+        };
+
+        let matching = WindowInfo {
+            title: "GitHub - vivaldi".to_string(),
+            ..Default::default()
+        };
+        let non_matching = WindowInfo {
+            title: "Firefox".to_string(),
+            ..Default::default()
+        };
+
+        assert!(condition.matches(&matching));
+        assert!(!condition.matches(&non_matching));
+    }
+
+    #[test]
+    fn test_negation_condition() {
+        let condition = WindowCondition {
+            not_binary: Some("*game*".to_string()),
+            ..Default::default()
+        };
+
+        let browser = WindowInfo {
+            binary: "vivaldi".to_string(),
+            ..Default::default()
+        };
+        let game = WindowInfo {
+            binary: "somegame".to_string(),
+            ..Default::default()
+        };
+
+        assert!(condition.matches(&browser));
+        assert!(!condition.matches(&game));
+    }
+
+    #[tokio::test]
+    async fn test_gated_hold_with_diverts() {
+        let toml = r#"
+            [strategies.scroll]
+            type = "gated_hold"
+            initial_hold_ms = 110
+            repeat_window_ms = 2000
+            diverts = { scroll_up = "volume_up", scroll_down = "volume_down" }
+
+            [bindings.0x7E]
+            action = "media_previous"
+            strategy = "scroll"
+        "#;
+        let result = load_from_str("test.toml", toml.to_string()).await;
+        assert!(result.is_ok());
+        let (config, runtime) = result.unwrap();
+        assert!(config.strategies.len() == 1);
+        assert!(runtime.strategies.contains_key("scroll"));
+        // Verify subscriptions were built
+        assert!(runtime.subscriptions.len() == 2); // scroll_up and scroll_down
+    }
+
+    #[tokio::test]
+    async fn test_gated_hold_invalid_divert_event() {
+        let toml = r#"
+            [strategies.scroll]
+            type = "gated_hold"
+            initial_hold_ms = 110
+            repeat_window_ms = 2000
+            diverts = { invalid_event = "volume_up" }
+
+            [bindings.0x7E]
+            action = "media_previous"
+            strategy = "scroll"
+        "#;
+        let result = load_from_str("test.toml", toml.to_string()).await;
+        assert!(result.is_err());
+        let msg = format!("{:?}", result.unwrap_err());
+        assert!(msg.contains("invalid divert event"));
+    }
+
+    #[tokio::test]
+    async fn test_tap_hold_config() {
+        let toml = r#"
+            [strategies.caps]
+            type = "tap_hold"
+            hold_ms = 200
+            tap_key = "0x01"
+            hold_key = "0x1D"
+
+            [bindings.0x3A]
+            action = "block"
+            strategy = "caps"
+        "#;
+        let (_, runtime) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+        assert!(runtime.strategies.contains_key("caps"));
+    }
+
+    #[tokio::test]
+    async fn test_tap_hold_invalid_key() {
+        let toml = r#"
+            [strategies.caps]
+            type = "tap_hold"
+            hold_ms = 200
+            hold_key = "not_a_key"
+        "#;
+        let result = load_from_str("test.toml", toml.to_string()).await;
+        assert!(result.is_err());
+        let msg = format!("{:?}", result.unwrap_err());
+        assert!(msg.contains("invalid hold_key"));
+    }
+
+    #[cfg(not(feature = "script"))]
+    #[tokio::test]
+    async fn test_script_needs_feature() {
+        let toml = r#"
+            [strategies.caps]
+            type = "script"
+            file = "caps.rhai"
+        "#;
+        let result = load_from_str("test.toml", toml.to_string()).await;
+        let msg = format!("{:?}", result.unwrap_err());
+        assert!(msg.contains("`script` feature"));
+    }
+
+    #[tokio::test]
+    async fn test_repeat_config() {
+        let toml = r#"
+            [strategies.volume]
+            type = "repeat"
+            start_interval_ms = 300
+            min_interval_ms = 40
+            ramp_ms = 1500
+
+            [bindings.0x7C]
+            action = "volume_up"
+            strategy = "volume"
+        "#;
+        let (_, runtime) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+        assert!(runtime.strategies.contains_key("volume"));
+    }
+
+    #[tokio::test]
+    async fn test_repeat_min_above_start_error() {
+        let toml = r#"
+            [strategies.volume]
+            type = "repeat"
+            start_interval_ms = 100
+            min_interval_ms = 200
+        "#;
+        let result = load_from_str("test.toml", toml.to_string()).await;
+        assert!(result.is_err());
+        let msg = format!("{:?}", result.unwrap_err());
+        assert!(msg.contains("min_interval_ms"));
+    }
+
+    #[tokio::test]
+    async fn test_debounce_config() {
+        let toml = r#"
+            [strategies.chatter]
+            type = "debounce"
+            chatter_ms = 30
+
+            [bindings.chattering]
+            keys = ["0x41", "0x45"]
+            action = "block"
+            strategy = "chatter"
+        "#;
+        let (_, runtime) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+        assert!(runtime.strategies.contains_key("chatter"));
+        let routes = runtime.key_routes().unwrap();
+        assert!(routes.handled.contains(&KeyCode::new(0x41)));
+    }
+
+    #[tokio::test]
+    async fn test_push_to_talk_config() {
+        let toml = r#"
+            [strategies.ptt]
+            type = "push_to_talk"
+            apps = [{ binary = "Discord*", key = "0x7C" }]
+
+            [bindings.0x83]
+            action = "block"
+            strategy = "ptt"
+        "#;
+        let (_, runtime) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+        assert!(runtime.strategies.contains_key("ptt"));
+
+        let toml = r#"
+            [strategies.ptt]
+            type = "push_to_talk"
+            apps = [{ key = "0x7C" }]
+        "#;
+        let result = load_from_str("test.toml", toml.to_string()).await;
+        let msg = format!("{:?}", result.unwrap_err());
+        assert!(msg.contains("matches every window"));
+    }
+
+    #[tokio::test]
+    async fn test_mirror_config() {
+        let toml = r#"
+            [strategies.one_handed]
+            type = "mirror"
+            trigger = "0xFFF0"
+            pairs = { "0xFFF1" = "0xFFF2" }
+        "#;
+        let (_, runtime) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+        let subscribed = |code| {
+            runtime
+                .subscriptions
+                .contains_key(&InputEventId::Key(KeyCode::new(code)))
+        };
+        assert!(subscribed(0xFFF0) && subscribed(0xFFF1) && subscribed(0xFFF2));
+        assert!(
+            mirror_pairs()
+                .keys()
+                .all(|key| { runtime.subscriptions.contains_key(&InputEventId::Key(*key)) })
+        );
+
+        let (key, _) = mirror_pairs().into_iter().next().unwrap();
+        let toml = format!(
+            r#"
+            [strategies.one_handed]
+            type = "mirror"
+            trigger = "{}"
+        "#,
+            key.code()
+        );
+        let result = load_from_str("test.toml", toml).await;
+        let msg = format!("{:?}", result.unwrap_err());
+        assert!(msg.contains("mirror trigger cannot also be a mirrored key"));
+    }
+
+    #[tokio::test]
+    async fn test_mouse_keys_config() {
+        let toml = r#"
+            [strategies.pointer]
+            type = "mouse_keys"
+            toggle = "0x90"
+            keys = { "0x68" = "up", "0x65" = "left_button" }
+        "#;
+        let (_, runtime) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+        assert!(runtime.strategies.contains_key("pointer"));
+        let routes = runtime.key_routes().unwrap();
+        for code in [0x68, 0x65, 0x90] {
+            assert!(routes.handled.contains(&KeyCode::new(code)));
+        }
+
+        let toml = r#"
+            [strategies.pointer]
+            type = "mouse_keys"
+            keys = { "0x68" = "upward" }
+        "#;
+        let result = load_from_str("test.toml", toml.to_string()).await;
+        let msg = format!("{:?}", result.unwrap_err());
+        assert!(msg.contains("unknown pointer control 'upward'"));
+    }
+
+    #[tokio::test]
+    async fn test_combo_config_subscribes_to_all_keys() {
+        let toml = r#"
+            [strategies.jk]
+            type = "combo"
+            keys = ["0x24", "0x25"]
+            window_ms = 50
+            action = "media_play_pause"
+        "#;
+        let (_, runtime) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+        assert!(runtime.strategies.contains_key("jk"));
+        assert!(runtime.subscriptions.contains_key(&InputEventId::AnyKey));
+    }
+
+    #[tokio::test]
+    async fn test_combo_needs_two_keys() {
+        let toml = r#"
+            [strategies.jk]
+            type = "combo"
+            keys = ["0x24", "36"]
+            window_ms = 50
+            action = "media_play_pause"
+        "#;
+        let result = load_from_str("test.toml", toml.to_string()).await;
+        assert!(result.is_err());
+        let msg = format!("{:?}", result.unwrap_err());
+        assert!(msg.contains("at least two"));
+    }
+
+    #[tokio::test]
+    async fn test_leader_config() {
+        let toml = r#"
+            [strategies.leader]
+            type = "leader"
+            key = "0x7C"
+            timeout_ms = 1000
+            sequences = { "25" = "media_play_pause", "49" = "media_next" }
+        "#;
+        let (_, runtime) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+        assert!(runtime.strategies.contains_key("leader"));
+        assert!(runtime.subscriptions.contains_key(&InputEventId::AnyKey));
+    }
+
+    #[tokio::test]
+    async fn test_key_routes() {
+        let toml = r#"
+            [strategies.scroll]
+            type = "gated_hold"
+            initial_hold_ms = 150
+            repeat_window_ms = 2000
+            diverts = { "0x7F" = "volume_up", scroll_up = "volume_down" }
+
+            [bindings.0x7E]
+            action = "media_previous"
+            strategy = "scroll"
+
+            [bindings.0x7C]
+            action = "block"
+
+            [bindings.0x7D]
+            action = "passthrough"
+
+            [bindings.0x80]
+            action = "block"
+            strategy = "scroll"
+
+            [bindings.0x81]
+            action = [{ condition = { window = { binary = "mpv" } }, action = "block" }]
+        "#;
+        let (_, runtime) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+        let routes = runtime.key_routes().unwrap();
+        assert_eq!(
+            routes.handled,
+            HashSet::from([0x7E, 0x7F, 0x80, 0x81].map(KeyCode::new))
+        );
+        assert_eq!(routes.blocked, HashSet::from([KeyCode::new(0x7C)]));
+
+        // A strategy watching every key needs to see them all
+        let toml = r#"
+            [strategies.leader]
+            type = "leader"
+            key = "0x7C"
+            timeout_ms = 1000
+            sequences = { "25" = "media_play_pause" }
+        "#;
+        let (_, runtime) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+        assert_eq!(runtime.key_routes(), None);
+    }
+
+    #[tokio::test]
+    async fn test_leader_invalid_sequence_action() {
+        let toml = r#"
+            [strategies.leader]
+            type = "leader"
+            key = "0x7C"
+            timeout_ms = 1000
+            sequences = { "25" = "not_an_action" }
+        "#;
+        let result = load_from_str("test.toml", toml.to_string()).await;
+        assert!(result.is_err());
+        let msg = format!("{:?}", result.unwrap_err());
+        assert!(msg.contains("invalid sequence action"));
+    }
+
+    #[tokio::test]
+    async fn test_gated_hold_invalid_divert_action() {
+        let toml = r#"
+            [strategies.scroll]
+            type = "gated_hold"
+            initial_hold_ms = 110
+            repeat_window_ms = 2000
+            diverts = { scroll_up = "invalid_action" }
+
+            [bindings.0x7E]
+            action = "media_previous"
+            strategy = "scroll"
+        "#;
+        let result = load_from_str("test.toml", toml.to_string()).await;
+        assert!(result.is_err());
+        let msg = format!("{:?}", result.unwrap_err());
+        assert!(msg.contains("invalid divert action"));
+    }
+}
@@ -0,0 +1,252 @@
+//! Config files fetched over HTTPS
+//!
+//! A remote config (`--config https://...`, or an `include` URL) is cached with
+//! its ETag once it has loaded successfully. Later fetches send `If-None-Match`,
+//! and when the server can't be reached the cached copy is used instead, so a
+//! daemon starting offline keeps the last good bindings.
+//!
+//! A config can bind keys to shell commands, so one fetched over plain
+//! `http://` would let anyone on the network path run commands on this
+//! machine. Such URLs are refused unless the loader opted in with
+//! [`LoadOptions::allow_plain_http`](super::LoadOptions::allow_plain_http).
+
+use super::ConfigError;
+use crate::integrations::http::{self, Url};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// How long a fetch may take before falling back to the cache
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Whether a config location names a URL rather than a file
+pub fn is_url(location: &str) -> bool {
+    location.starts_with("http://") || location.starts_with("https://")
+}
+
+/// A fetched config file
+#[derive(Debug, Clone)]
+pub struct Fetched {
+    url: String,
+    cache: Cache,
+    pub content: String,
+    etag: Option<String>,
+    /// Whether `content` came from the server rather than the cache
+    fresh: bool,
+    /// The last good copy, when the server sent something newer
+    pub fallback: Option<String>,
+}
+
+impl Fetched {
+    /// Remember this copy as the last good one
+    pub fn store(&self) {
+        if !self.fresh {
+            return;
+        }
+        if let Err(e) = self.cache.write(&self.content, self.etag.as_deref()) {
+            warn!(url = self.url, "failed to cache remote config: {e}");
+        }
+    }
+}
+
+/// Fetch a config file, revalidating the cached copy when there is one.
+/// `http://` URLs are refused unless `allow_http` is set.
+pub async fn fetch(location: &str, allow_http: bool) -> Result<Fetched, ConfigError> {
+    let dir = dirs::cache_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("rebinded")
+        .join("remote");
+    fetch_cached_in(location, &dir, allow_http).await
+}
+
+async fn fetch_cached_in(
+    location: &str,
+    dir: &Path,
+    allow_http: bool,
+) -> Result<Fetched, ConfigError> {
+    let url = Url::parse(location).map_err(|e| ConfigError::remote(location, e))?;
+    if !url.tls && !allow_http {
+        return Err(ConfigError::remote(
+            location,
+            "refusing a config over plain http://, where anyone on the network could change \
+             its bindings and commands; use https://, or pass --allow-http-config",
+        ));
+    }
+    let cache = Cache::new(dir, location);
+    let cached = cache.read();
+
+    let mut headers = Vec::new();
+    if let Some((_, Some(etag))) = &cached {
+        headers.push(("If-None-Match", etag.as_str()));
+    }
+    let result = tokio::time::timeout(FETCH_TIMEOUT, http::request("GET", &url, &headers, b""))
+        .await
+        .map_err(|_| "timed out".to_string())
+        .and_then(|response| response.map_err(|e| format!("{e:#}")));
+
+    let error = match result {
+        Ok(response) if response.status == 304 && cached.is_some() => {
+            debug!(url = location, "remote config unchanged");
+            let (content, etag) = cached.unwrap_or_default();
+            return Ok(Fetched {
+                url: location.to_string(),
+                cache,
+                content,
+                etag,
+                fresh: false,
+                fallback: None,
+            });
+        }
+        Ok(response) if response.is_success() => match String::from_utf8(response.body.clone()) {
+            Ok(content) => {
+                let fallback = cached.map(|(cached, _)| cached);
+                return Ok(Fetched {
+                    url: location.to_string(),
+                    cache,
+                    etag: response.header("etag").map(str::to_string),
+                    fresh: true,
+                    fallback: fallback.filter(|cached| *cached != content),
+                    content,
+                });
+            }
+            Err(_) => "response is not UTF-8".to_string(),
+        },
+        Ok(response) => format!("HTTP {}: {}", response.status, response.body_preview()),
+        Err(e) => e,
+    };
+
+    match cached {
+        Some((content, etag)) => {
+            warn!(
+                url = location,
+                "failed to fetch remote config ({error}), using the cached copy"
+            );
+            Ok(Fetched {
+                url: location.to_string(),
+                cache,
+                content,
+                etag,
+                fresh: false,
+                fallback: None,
+            })
+        }
+        None => Err(ConfigError::remote(
+            location,
+            format!("{error} (no cached copy to fall back on)"),
+        )),
+    }
+}
+
+/// Cached copy of one remote config, and its ETag
+#[derive(Debug, Clone)]
+struct Cache {
+    path: PathBuf,
+}
+
+impl Cache {
+    /// Files are named by a hash of the URL, so no two URLs share one
+    fn new(dir: &Path, url: &str) -> Self {
+        let name: String = Sha256::digest(url.as_bytes())
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        Self {
+            path: dir.join(format!("{name}.toml")),
+        }
+    }
+
+    fn etag_path(&self) -> PathBuf {
+        self.path.with_extension("etag")
+    }
+
+    fn read(&self) -> Option<(String, Option<String>)> {
+        let content = std::fs::read_to_string(&self.path).ok()?;
+        let etag = std::fs::read_to_string(self.etag_path()).ok();
+        Some((content, etag))
+    }
+
+    fn write(&self, content: &str, etag: Option<&str>) -> std::io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&self.path, content)?;
+        match etag {
+            Some(etag) => std::fs::write(self.etag_path(), etag),
+            None => match std::fs::remove_file(self.etag_path()) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+                _ => Ok(()),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Answer one request with `response`, returning the request received
+    async fn serve_once(listener: &TcpListener, response: &str) -> String {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut received = Vec::new();
+        let mut buf = [0u8; 1024];
+        while !received.ends_with(b"\r\n\r\n") {
+            let n = stream.read(&mut buf).await.unwrap();
+            received.extend_from_slice(&buf[..n]);
+        }
+        stream.write_all(response.as_bytes()).await.unwrap();
+        String::from_utf8(received).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_fetch_caches_and_revalidates() {
+        let dir = std::env::temp_dir().join(format!("rebinded-remote-{}", std::process::id()));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let url = format!("http://127.0.0.1:{port}/shared.toml");
+        let body = "[bindings.f13]\naction = \"media_next\"\n";
+
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        );
+        let (fetched, _) = tokio::join!(
+            fetch_cached_in(&url, &dir, true),
+            serve_once(&listener, &response)
+        );
+        let fetched = fetched.unwrap();
+        assert_eq!(fetched.content, body);
+        fetched.store();
+
+        // Unchanged on the server: the cached copy is used
+        let (fetched, request) = tokio::join!(
+            fetch_cached_in(&url, &dir, true),
+            serve_once(&listener, "HTTP/1.1 304 Not Modified\r\n\r\n")
+        );
+        assert!(request.contains("If-None-Match: \"v1\"\r\n"));
+        assert_eq!(fetched.unwrap().content, body);
+
+        // Server gone: the cached copy is used
+        drop(listener);
+        let fetched = fetch_cached_in(&url, &dir, true).await.unwrap();
+        assert_eq!(fetched.content, body);
+
+        // Plain http is refused without the opt-in, even with a cached copy
+        let err = fetch_cached_in(&url, &dir, false).await.unwrap_err();
+        assert!(err.to_string().contains("https://"), "{err}");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(fetch_cached_in(&url, &dir, true).await.is_err());
+    }
+
+    #[test]
+    fn test_cache_names_are_distinct() {
+        let dir = Path::new("cache");
+        let a = Cache::new(dir, "https://example.com/a-b.toml");
+        let b = Cache::new(dir, "https://example.com/a_b.toml");
+        assert_ne!(a.path, b.path);
+        assert_eq!(a.path, Cache::new(dir, "https://example.com/a-b.toml").path);
+    }
+}
@@ -0,0 +1,366 @@
+//! Template variables in `clipboard` action text
+//!
+//! `{date}` and `{time}` insert the local date and time, and take a
+//! strftime-style format after a colon (`{date:%d.%m.%Y}`). `{clipboard}` is
+//! the current clipboard text, and `{window.title}`, `{window.class}` and
+//! `{window.binary}` describe the focused window. All are read when the action
+//! runs. `{{` is a literal `{`, for text that would otherwise read as a
+//! variable; any other brace is kept as is, so JSON snippets need no escaping.
+
+use crate::config::WindowInfo;
+
+const VARIABLES: &str = "date, time, clipboard, window.title, window.class or window.binary";
+
+/// One part of a parsed template
+#[derive(Debug, PartialEq)]
+enum Piece<'a> {
+    Text(&'a str),
+    /// Local time with a strftime-style format
+    Time(&'a str),
+    Clipboard,
+    WindowTitle,
+    WindowClass,
+    WindowBinary,
+}
+
+/// What variables are replaced with
+pub struct Context<'a> {
+    pub window: &'a WindowInfo,
+    /// Reads the clipboard; only called when the template uses it
+    pub clipboard: &'a dyn Fn() -> Option<String>,
+    pub now: LocalTime,
+}
+
+/// Whether `text` has anything to expand, so plain text can skip the work
+pub fn is_templated(text: &str) -> bool {
+    parse(text).is_ok_and(|pieces| pieces.iter().any(|piece| !matches!(piece, Piece::Text(_))))
+        || text.contains("{{")
+}
+
+/// Check a template when the config loads, so typos in variable names or
+/// formats are reported then rather than typed out
+pub fn validate(text: &str) -> Result<(), String> {
+    for piece in parse(text)? {
+        if let Piece::Time(format) = piece {
+            format_time(format, &LocalTime::default())?;
+        }
+    }
+    Ok(())
+}
+
+/// Replace every variable in a template checked by [`validate`]
+pub fn expand(text: &str, context: &Context) -> String {
+    let Ok(pieces) = parse(text) else {
+        return text.to_string();
+    };
+    let mut out = String::with_capacity(text.len());
+    for piece in pieces {
+        match piece {
+            Piece::Text(text) => out.push_str(text),
+            Piece::Time(format) => {
+                out.push_str(&format_time(format, &context.now).unwrap_or_default())
+            }
+            Piece::Clipboard => out.push_str(&(context.clipboard)().unwrap_or_default()),
+            Piece::WindowTitle => out.push_str(&context.window.title),
+            Piece::WindowClass => out.push_str(&context.window.class),
+            Piece::WindowBinary => out.push_str(&context.window.binary),
+        }
+    }
+    out
+}
+
+fn parse(text: &str) -> Result<Vec<Piece<'_>>, String> {
+    let mut pieces = Vec::new();
+    let mut rest = text;
+    while let Some(brace) = rest.find('{') {
+        let (before, from_brace) = rest.split_at(brace);
+        if !before.is_empty() {
+            pieces.push(Piece::Text(before));
+        }
+
+        if let Some(after) = from_brace.strip_prefix("{{") {
+            pieces.push(Piece::Text("{"));
+            rest = after;
+            continue;
+        }
+
+        // A variable starts with a lowercase name; other braces are literal
+        let inner = &from_brace[1..];
+        let name_len = inner
+            .find(|c: char| !(c.is_ascii_lowercase() || c == '.' || c == '_'))
+            .unwrap_or(inner.len());
+        let name = &inner[..name_len];
+        let opens_variable =
+            !name.is_empty() && matches!(inner[name_len..].chars().next(), Some(':' | '}') | None);
+        if !opens_variable {
+            pieces.push(Piece::Text("{"));
+            rest = inner;
+            continue;
+        }
+
+        let Some(close) = inner.find('}') else {
+            return Err(format!(
+                "template variable `{{{name}` is missing its closing `}}`"
+            ));
+        };
+        let format = inner[name_len..close].strip_prefix(':');
+        pieces.push(match (name, format) {
+            ("date", format) => Piece::Time(format.unwrap_or("%Y-%m-%d")),
+            ("time", format) => Piece::Time(format.unwrap_or("%H:%M:%S")),
+            ("clipboard", None) => Piece::Clipboard,
+            ("window.title", None) => Piece::WindowTitle,
+            ("window.class", None) => Piece::WindowClass,
+            ("window.binary", None) => Piece::WindowBinary,
+            ("clipboard" | "window.title" | "window.class" | "window.binary", Some(_)) => {
+                return Err(format!("template variable `{name}` takes no format"));
+            }
+            _ => {
+                return Err(format!(
+                    "unknown template variable `{name}`, expected {VARIABLES} \
+                     (write `{{{{` for a literal brace)"
+                ));
+            }
+        });
+        rest = &inner[close + 1..];
+    }
+    if !rest.is_empty() {
+        pieces.push(Piece::Text(rest));
+    }
+    Ok(pieces)
+}
+
+/// A local date and time, broken down
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocalTime {
+    pub year: i32,
+    /// 1-12
+    pub month: u32,
+    /// 1-31
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    /// Days since Sunday, 0-6
+    pub weekday: u32,
+}
+
+impl Default for LocalTime {
+    fn default() -> Self {
+        // 2000-01-01 was a Saturday
+        Self {
+            year: 2000,
+            month: 1,
+            day: 1,
+            hour: 0,
+            minute: 0,
+            second: 0,
+            weekday: 6,
+        }
+    }
+}
+
+impl LocalTime {
+    /// The current local time
+    pub fn now() -> Self {
+        backend::now()
+    }
+
+    /// Day of the year, 1-366
+    fn ordinal(&self) -> u32 {
+        const DAYS_BEFORE: [u32; 12] = [0, 31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334];
+        let leap = (self.year % 4 == 0 && self.year % 100 != 0) || self.year % 400 == 0;
+        let month = self.month.clamp(1, 12) as usize;
+        DAYS_BEFORE[month - 1] + self.day + u32::from(leap && month > 2)
+    }
+}
+
+const WEEKDAYS: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// Format `time` like strftime, with the common conversions: `%Y %y %m %d %e
+/// %H %I %M %S %p %a %A %b %B %j %F %T %R %%`
+fn format_time(format: &str, time: &LocalTime) -> Result<String, String> {
+    let weekday = WEEKDAYS[time.weekday as usize % 7];
+    let month = MONTHS[(time.month.clamp(1, 12) - 1) as usize];
+    let hour12 = match time.hour % 12 {
+        0 => 12,
+        hour => hour,
+    };
+
+    let mut out = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        let formatted = match chars.next() {
+            Some('Y') => time.year.to_string(),
+            Some('y') => format!("{:02}", time.year.rem_euclid(100)),
+            Some('m') => format!("{:02}", time.month),
+            Some('d') => format!("{:02}", time.day),
+            Some('e') => format!("{:2}", time.day),
+            Some('H') => format!("{:02}", time.hour),
+            Some('I') => format!("{hour12:02}"),
+            Some('M') => format!("{:02}", time.minute),
+            Some('S') => format!("{:02}", time.second),
+            Some('p') => if time.hour < 12 { "AM" } else { "PM" }.to_string(),
+            Some('a') => weekday[..3].to_string(),
+            Some('A') => weekday.to_string(),
+            Some('b') => month[..3].to_string(),
+            Some('B') => month.to_string(),
+            Some('j') => format!("{:03}", time.ordinal()),
+            Some('F') => format!("{}-{:02}-{:02}", time.year, time.month, time.day),
+            Some('T') => format!("{:02}:{:02}:{:02}", time.hour, time.minute, time.second),
+            Some('R') => format!("{:02}:{:02}", time.hour, time.minute),
+            Some('%') => "%".to_string(),
+            Some(other) => return Err(format!("unsupported time format `%{other}`")),
+            None => return Err("time format ends with a lone `%`".to_string()),
+        };
+        out.push_str(&formatted);
+    }
+    Ok(out)
+}
+
+#[cfg(unix)]
+mod backend {
+    use super::LocalTime;
+
+    pub fn now() -> LocalTime {
+        // SAFETY: localtime_r only writes the tm it is given
+        let tm = unsafe {
+            let now = libc::time(std::ptr::null_mut());
+            let mut tm: libc::tm = std::mem::zeroed();
+            libc::localtime_r(&now, &mut tm);
+            tm
+        };
+        LocalTime {
+            year: tm.tm_year + 1900,
+            month: tm.tm_mon as u32 + 1,
+            day: tm.tm_mday as u32,
+            hour: tm.tm_hour as u32,
+            minute: tm.tm_min as u32,
+            second: tm.tm_sec as u32,
+            weekday: tm.tm_wday as u32,
+        }
+    }
+}
+
+#[cfg(windows)]
+mod backend {
+    use super::LocalTime;
+
+    pub fn now() -> LocalTime {
+        use windows::Win32::System::SystemInformation::GetLocalTime;
+
+        // SAFETY: GetLocalTime has no preconditions
+        let time = unsafe { GetLocalTime() };
+        LocalTime {
+            year: i32::from(time.wYear),
+            month: u32::from(time.wMonth),
+            day: u32::from(time.wDay),
+            hour: u32::from(time.wHour),
+            minute: u32::from(time.wMinute),
+            second: u32::from(time.wSecond),
+            weekday: u32::from(time.wDayOfWeek),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(window: &WindowInfo) -> Context<'_> {
+        Context {
+            window,
+            clipboard: &|| Some("copied".to_string()),
+            now: LocalTime {
+                year: 2024,
+                month: 3,
+                day: 5,
+                hour: 14,
+                minute: 7,
+                second: 9,
+                weekday: 2,
+            },
+        }
+    }
+
+    #[test]
+    fn test_expand_variables() {
+        let window = WindowInfo {
+            title: "Inbox".to_string(),
+            binary: "thunderbird".to_string(),
+            ..Default::default()
+        };
+        let context = context(&window);
+        let expand = |text| expand(text, &context);
+
+        assert_eq!(expand("{date}"), "2024-03-05");
+        assert_eq!(expand("{time}"), "14:07:09");
+        assert_eq!(
+            expand("{date:%a %d %b %Y, %I:%M %p}"),
+            "Tue 05 Mar 2024, 02:07 PM"
+        );
+        assert_eq!(expand("day {date:%j}"), "day 065");
+        assert_eq!(
+            expand("> {clipboard} ({window.title} in {window.binary})"),
+            "> copied (Inbox in thunderbird)"
+        );
+    }
+
+    #[test]
+    fn test_literal_braces() {
+        let window = WindowInfo::default();
+        let context = context(&window);
+
+        assert_eq!(expand("{{date}", &context), "{date}");
+        assert_eq!(
+            expand(r#"{"id": {"n": 1}}"#, &context),
+            r#"{"id": {"n": 1}}"#
+        );
+        assert_eq!(expand("fn main() {}", &context), "fn main() {}");
+        assert!(!is_templated(r#"{"id": 1}"#));
+        assert!(is_templated("{clipboard}"));
+        assert!(is_templated("{{"));
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate("{date:%Y} {window.title} {{x}").is_ok());
+        assert!(
+            validate("{window.titel}")
+                .unwrap_err()
+                .contains("unknown template variable")
+        );
+        assert!(validate("{date:%Q}").unwrap_err().contains("%Q"));
+        assert!(
+            validate("{clipboard:%Y}")
+                .unwrap_err()
+                .contains("takes no format")
+        );
+        assert!(validate("{date").unwrap_err().contains("closing"));
+    }
+}
@@ -0,0 +1,734 @@
+//! Configuration type definitions
+//!
+//! Contains the data structures representing parsed configuration.
+
+use serde::{Deserialize, Serialize};
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+
+/// Byte span in the source file
+pub type Span = Range<usize>;
+
+/// A value with its source location
+///
+/// Hash and Eq are implemented based on the value only, ignoring the span.
+/// This allows using Spanned<T> as HashMap keys where semantically equal
+/// values (regardless of where they appear in source) should be treated as equal.
+#[derive(Debug, Clone)]
+pub struct Spanned<T> {
+    value: T,
+    span: Span,
+}
+
+impl<T> Spanned<T> {
+    pub fn new(value: T, span: Span) -> Self {
+        Self { value, span }
+    }
+
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+}
+
+impl<T> std::ops::Deref for Spanned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+/// Hash based on value only - span is metadata, not identity
+impl<T: Hash> Hash for Spanned<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.value.hash(state);
+    }
+}
+
+/// Equality based on value only - span is metadata, not identity
+impl<T: PartialEq> PartialEq for Spanned<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl<T: Eq> Eq for Spanned<T> {}
+
+use std::collections::HashMap;
+
+/// Strategy configuration variants
+///
+/// Each variant corresponds to a strategy implementation. The `type` field
+/// in TOML determines which variant is used.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StrategyConfig {
+    /// Gated hold: require hold before activation, with repeat window
+    GatedHold {
+        /// How long the key must be held before first activation (ms)
+        initial_hold_ms: u64,
+        /// Window during which repeated presses activate immediately (ms)
+        repeat_window_ms: u64,
+        /// Events that divert the strategy to alternative actions.
+        /// Keys are event identifiers (e.g., "scroll_up", "scroll_down"),
+        /// values are action names (e.g., "volume_up", "volume_down").
+        #[serde(default)]
+        diverts: HashMap<String, String>,
+        /// Whether all keys bound to this strategy share one gate (default: true).
+        /// When false, each key has its own hold and repeat state.
+        #[serde(default = "default_true")]
+        shared_gate: bool,
+    },
+    /// Tap-hold: short press taps a key, long press holds another (or runs the action)
+    TapHold {
+        /// How long the key must be held before it counts as a hold (ms)
+        hold_ms: u64,
+        /// Key tapped on a short press (default: replay the bound key)
+        #[serde(default)]
+        tap_key: Option<String>,
+        /// Key held down while the bound key is held past `hold_ms`
+        /// (default: execute the binding's action once)
+        #[serde(default)]
+        hold_key: Option<String>,
+    },
+    /// Repeat: execute on press, then repeat while held with a shrinking interval
+    Repeat {
+        /// Delay before the first repeat, and the starting repeat interval (ms)
+        start_interval_ms: u64,
+        /// Fastest repeat interval, reached after `ramp_ms` of holding (ms)
+        min_interval_ms: u64,
+        /// Time over which the interval shrinks from start to min (ms)
+        #[serde(default)]
+        ramp_ms: u64,
+    },
+    /// Combo: fire an action when all keys are pressed within a short window
+    Combo {
+        /// Keys that must be pressed together (at least two)
+        keys: Vec<String>,
+        /// Maximum time between the first and last press (ms)
+        window_ms: u64,
+        /// Action name executed when the combo fires
+        action: String,
+    },
+    /// Leader: swallow a leader key, then map the next key press to an action
+    Leader {
+        /// Key that opens pending mode
+        key: String,
+        /// How long to wait for the follow-up key (ms)
+        timeout_ms: u64,
+        /// Follow-up key names mapped to action names
+        sequences: HashMap<String, String>,
+    },
+    /// Debounce: ignore presses that come too soon after the key's last release
+    Debounce {
+        /// Presses within this long of the last release are chatter (ms)
+        chatter_ms: u64,
+    },
+    /// Mouse keys: move the pointer, click and scroll from the keyboard
+    MouseKeys {
+        /// Key names mapped to pointer controls (e.g. "up", "left_button",
+        /// "scroll_down")
+        keys: HashMap<String, String>,
+        /// Key that switches mouse keys on and off (default: always on)
+        #[serde(default)]
+        toggle: Option<String>,
+        /// Pointer speed when movement starts (pixels per second)
+        #[serde(default = "default_pointer_speed")]
+        speed: u32,
+        /// Pointer speed after `accel_ms` of movement (pixels per second)
+        #[serde(default = "default_pointer_max_speed")]
+        max_speed: u32,
+        /// Time over which the speed rises from `speed` to `max_speed` (ms)
+        #[serde(default = "default_pointer_accel_ms")]
+        accel_ms: u64,
+    },
+    /// Mirror: while the trigger is held, keys type their mirror image across
+    /// the keyboard, for one-handed typing
+    Mirror {
+        /// Key that mirrors the keyboard while held (tapped alone, it types itself)
+        trigger: String,
+        /// Extra key pairs mirrored both ways, on top of the QWERTY mirror image
+        #[serde(default)]
+        pairs: HashMap<String, String>,
+    },
+    /// Push-to-talk: while held, hold the focused or running app's own
+    /// push-to-talk key, or else toggle the microphone
+    PushToTalk {
+        /// Apps with a push-to-talk key, checked in order
+        #[serde(default)]
+        apps: Vec<PushToTalkAppConfig>,
+    },
+    /// Script: a Rhai script handles the keys (needs the `script` feature)
+    Script {
+        /// Script file, relative to the config file naming it
+        file: String,
+        /// Remaining fields, which the script starts with as `this`
+        #[serde(flatten)]
+        settings: toml::Table,
+    },
+    /// Strategy type from the [`StrategyRegistry`](crate::strategy::StrategyRegistry),
+    /// registered by a library user or a loaded plugin.
+    /// Not deserialized directly: any `type` naming a registered type maps here.
+    #[serde(skip)]
+    Registered {
+        /// Registered type name (the strategy `type`)
+        type_name: String,
+        /// Remaining strategy fields, passed to the type's constructor
+        settings: toml::Table,
+    },
+}
+
+/// An app with its own push-to-talk key, in a `push_to_talk` strategy
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PushToTalkAppConfig {
+    /// Which app: matched against the focused window, and by `binary`
+    /// against running processes
+    #[serde(flatten)]
+    pub window: WindowCondition,
+    /// The app's push-to-talk key
+    pub key: String,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_pointer_speed() -> u32 {
+    300
+}
+
+fn default_pointer_max_speed() -> u32 {
+    1500
+}
+
+fn default_pointer_accel_ms() -> u64 {
+    1000
+}
+
+/// Restrictions the system config places on user configs, from its
+/// `[managed]` table
+#[derive(Debug, Clone)]
+pub struct Managed {
+    /// Keys user configs may not bind, as key names, codes or ranges. Strategies
+    /// the system config uses for them can't be redefined either.
+    pub locked_bindings: Vec<Spanned<String>>,
+    /// Whether user configs may run commands: `run`, `dbus` and `http`
+    /// actions, `focus` launch commands, and plugins from the user's config
+    /// directory
+    pub allow_commands: bool,
+}
+
+impl Default for Managed {
+    fn default() -> Self {
+        Self {
+            locked_bindings: Vec::new(),
+            allow_commands: true,
+        }
+    }
+}
+
+/// A key binding configuration
+#[derive(Debug, Clone)]
+pub struct Binding {
+    /// The action(s) to perform
+    pub action: ActionSpec,
+    /// The `action` value as written, for `config dump`
+    pub action_source: toml::Value,
+    /// Named strategies applied in order (with spans for error reporting).
+    /// Empty when the binding executes its action directly.
+    pub strategies: Vec<Spanned<String>>,
+    /// Explicit key set sharing this binding. When non-empty, the binding's
+    /// table name is only a label and each entry here is resolved instead.
+    /// Entries may be single keys or ranges ("f13..f24").
+    pub keys: Vec<Spanned<String>>,
+    /// Strategy settings overridden for this binding only (e.g. `initial_hold_ms = 300`).
+    /// Any binding field besides `action`, `strategy`, `keys` and `notify_on_execute`
+    /// lands here.
+    pub overrides: Vec<(Spanned<String>, toml::Value)>,
+    /// Notification shown after the binding's action executes
+    pub notify_on_execute: Option<ExecuteNotification>,
+}
+
+impl Binding {
+    /// The notification to show after `action` (resolved from this binding) executes
+    pub fn notification(&self, action: &Action) -> Option<String> {
+        match self.notify_on_execute.as_ref()? {
+            ExecuteNotification::Describe => Some(action.to_string()),
+            ExecuteNotification::Message(message) => Some(message.clone()),
+        }
+    }
+}
+
+/// What `notify_on_execute` shows
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExecuteNotification {
+    /// `notify_on_execute = true`: name the action that ran
+    Describe,
+    /// `notify_on_execute = "Mic muted"`: show a fixed message
+    Message(String),
+}
+
+/// Action specification - either simple or conditional
+#[derive(Debug, Clone)]
+pub enum ActionSpec {
+    /// Simple action with no conditions
+    Simple(Action),
+    /// List of conditional rules, evaluated in order
+    Conditional(Vec<ConditionalAction>),
+}
+
+/// A conditional action rule
+#[derive(Debug, Clone, Deserialize)]
+pub struct ConditionalAction {
+    #[serde(default)]
+    pub condition: Condition,
+    pub action: Action,
+}
+
+/// Window, power and monitor matching condition - all fields are ANDed together
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Condition {
+    #[serde(default)]
+    pub window: WindowCondition,
+    /// Power source the machine must be on, e.g. `{ on_battery = true }`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub power: Option<crate::platform::PowerCondition>,
+    /// Monitor holding the focused window, by number or name, e.g. `2` or `"HDMI-*"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub monitor: Option<crate::platform::MonitorCondition>,
+}
+
+impl Condition {
+    pub fn is_empty(&self) -> bool {
+        self.window.is_empty() && self.power.is_none() && self.monitor.is_none()
+    }
+
+    /// Whether the condition holds with `window` focused, given the current
+    /// power state and the monitor the window is on
+    pub fn matches(&self, window: &WindowInfo) -> bool {
+        self.window.matches(window)
+            && self
+                .power
+                .as_ref()
+                .is_none_or(|power| power.matches(&crate::platform::power_state()))
+            && self.monitor.as_ref().is_none_or(|monitor| {
+                crate::platform::active_monitor().is_some_and(|info| monitor.matches(&info))
+            })
+    }
+}
+
+/// Conditions for matching the active window
+/// Supports both positive matches (title, class, binary) and negations (not_title, not_class, not_binary)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct WindowCondition {
+    /// Glob pattern to match window title
+    pub title: Option<String>,
+    /// Glob pattern that must NOT match window title
+    pub not_title: Option<String>,
+    /// Glob pattern to match window class (X11 WM_CLASS / Windows class name)
+    pub class: Option<String>,
+    /// Glob pattern that must NOT match window class
+    pub not_class: Option<String>,
+    /// Glob pattern to match executable name (without path)
+    pub binary: Option<String>,
+    /// Glob pattern that must NOT match executable name
+    pub not_binary: Option<String>,
+}
+
+impl WindowCondition {
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none()
+            && self.not_title.is_none()
+            && self.class.is_none()
+            && self.not_class.is_none()
+            && self.binary.is_none()
+            && self.not_binary.is_none()
+    }
+
+    /// Check if the condition matches the given window info
+    /// All specified fields must match (AND logic)
+    pub fn matches(&self, info: &WindowInfo) -> bool {
+        let matches_glob =
+            |pattern: &str, value: &str| -> bool { glob_match::glob_match(pattern, value) };
+
+        // Positive matches: if specified, must match
+        if let Some(ref pattern) = self.title
+            && !matches_glob(pattern, &info.title)
+        {
+            return false;
+        }
+        if let Some(ref pattern) = self.class
+            && !matches_glob(pattern, &info.class)
+        {
+            return false;
+        }
+        if let Some(ref pattern) = self.binary
+            && !matches_glob(pattern, &info.binary)
+        {
+            return false;
+        }
+
+        // Negative matches: if specified, must NOT match
+        if let Some(ref pattern) = self.not_title
+            && matches_glob(pattern, &info.title)
+        {
+            return false;
+        }
+        if let Some(ref pattern) = self.not_class
+            && matches_glob(pattern, &info.class)
+        {
+            return false;
+        }
+        if let Some(ref pattern) = self.not_binary
+            && matches_glob(pattern, &info.binary)
+        {
+            return false;
+        }
+
+        true
+    }
+}
+
+/// Information about the currently focused window (filled by platform layer)
+#[derive(Debug, Default, Clone)]
+pub struct WindowInfo {
+    pub title: String,
+    pub class: String,
+    pub binary: String,
+}
+
+impl WindowInfo {
+    /// Whether both windows belong to the same application.
+    /// Titles are ignored since they change within an application (e.g. browser tabs).
+    pub fn same_application(&self, other: &WindowInfo) -> bool {
+        self.binary == other.binary && self.class == other.class
+    }
+}
+
+/// Available actions that can be bound to keys
+///
+/// Deserialized from the same values accepted in simple bindings: an action
+/// string, or a single-key table for actions that take arguments.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "toml::Value")]
+pub enum Action {
+    // Media actions
+    MediaPlayPause,
+    MediaNext,
+    MediaPrevious,
+    MediaStop,
+
+    // Volume actions
+    VolumeUp,
+    VolumeDown,
+    VolumeMute,
+    /// Change the volume by a custom step in percent (negative lowers it),
+    /// e.g. `{ type = "volume_up", step = 5 }`
+    VolumeStep(i32),
+
+    /// Transport command sent to one player rather than the OS default, e.g.
+    /// `{ media_play_pause = { player = "spotify" } }` or
+    /// `{ media_pause = { binary = "spotify" } }`
+    PlayerMedia {
+        player: crate::platform::PlayerTarget,
+        command: crate::platform::MediaCommand,
+    },
+
+    /// Mute, unmute or toggle the default microphone: "mic_mute",
+    /// "mic_unmute", "mic_toggle"
+    Mic(crate::platform::MicCommand),
+
+    // Browser actions
+    BrowserBack,
+    BrowserForward,
+
+    // Virtual desktop / workspace actions
+    DesktopNext,
+    DesktopPrevious,
+    /// Switch to a numbered desktop (1-based), e.g. "desktop_2"
+    Desktop(u32),
+
+    // Window switching actions
+    WindowNext,
+    WindowPrevious,
+    /// Snap the focused window to a half, quarter or all of its monitor,
+    /// e.g. "snap_left", "snap_top_right", "snap_maximize"
+    Snap(crate::platform::SnapPosition),
+
+    /// Lock the session, suspend or turn the displays off: "lock_screen",
+    /// "system_sleep", "display_off"
+    Session(crate::platform::SessionCommand),
+
+    /// Place text on the clipboard, optionally pasting it with Ctrl+V.
+    /// Written as `{ clipboard = "text" }` or `{ clipboard = { text = "...", paste = true } }`.
+    Clipboard {
+        text: String,
+        paste: bool,
+    },
+
+    /// Control OBS Studio over obs-websocket, e.g. `{ obs = { scene = "Gaming" } }`
+    Obs(crate::integrations::obs::ObsRequest),
+
+    /// Call a Home Assistant service, e.g.
+    /// `{ home_assistant = { service = "light.toggle", entity_id = "light.desk" } }`
+    HomeAssistant(crate::integrations::home_assistant::ServiceCall),
+
+    /// Send an HTTP request, e.g. `{ http = { url = "http://...", body = "..." } }`
+    Http(crate::integrations::webhook::Webhook),
+
+    /// Call a D-Bus method (Linux), e.g.
+    /// `{ dbus = { destination = "...", path = "/...", interface = "...", method = "..." } }`
+    Dbus(crate::integrations::dbus::DbusCall),
+
+    /// Show a desktop notification, e.g. `{ notify = "Mic muted" }`
+    Notify(String),
+
+    /// Press a shortcut, e.g. `{ send = "ctrl+shift+esc" }`: the keys go down
+    /// in order and come back up in reverse
+    Send(Vec<crate::key::KeyCode>),
+
+    /// Bring an application's window to the front, launching it if configured, e.g.
+    /// `{ focus = { binary = "slack", launch = "slack" } }`
+    Focus(crate::platform::FocusTarget),
+
+    /// Run a shell command, e.g. `{ run = "notify-send paused" }` or
+    /// `{ run = { command = "...", shell = "powershell" } }`
+    Run(crate::integrations::shell::ShellCommand),
+
+    /// Several actions executed in order, written as an array:
+    /// `action = ["media_play_pause", { run = "notify-send paused" }]`
+    Chain(Vec<Action>),
+
+    /// Pause between chain steps, written `{ delay_ms = 200 }`. Only valid inside a chain.
+    Delay(u64),
+
+    /// Pick an action by the window focused when this step runs:
+    /// `{ if = { window = { ... } }, then = ..., else = ... }`
+    Branch {
+        condition: Condition,
+        then: Box<Action>,
+        otherwise: Option<Box<Action>>,
+    },
+
+    // Pass the key through unchanged
+    Passthrough,
+
+    // Block the key entirely
+    Block,
+}
+
+impl Action {
+    /// Execute this action using the platform.
+    ///
+    /// This method is primarily used in tests and for direct platform execution.
+    /// In normal operation, prefer using `PlatformHandle::execute` or `StrategyContext::execute`.
+    ///
+    /// Note: `Passthrough` and `Block` are handled at the event loop level,
+    /// not here - calling execute on them is a no-op.
+    pub fn execute(&self, platform: &impl crate::platform::PlatformInterface) {
+        use crate::platform::{DesktopCommand, MediaCommand, SyntheticKey, WindowSwitch};
+        use tracing::debug;
+
+        debug!(?self, "executing action");
+
+        match self {
+            Action::MediaPlayPause => platform.send_media(MediaCommand::PlayPause),
+            Action::MediaNext => platform.send_media(MediaCommand::Next),
+            Action::MediaPrevious => platform.send_media(MediaCommand::Previous),
+            Action::MediaStop => platform.send_media(MediaCommand::Stop),
+            Action::VolumeUp => platform.send_media(MediaCommand::VolumeUp),
+            Action::VolumeDown => platform.send_media(MediaCommand::VolumeDown),
+            Action::VolumeMute => platform.send_media(MediaCommand::VolumeMute),
+            Action::VolumeStep(percent) => platform.change_volume(*percent),
+            Action::PlayerMedia { player, command } => platform.send_player_media(player, *command),
+            Action::Mic(cmd) => platform.set_mic(*cmd),
+            Action::BrowserBack => platform.send_key(SyntheticKey::BrowserBack),
+            Action::BrowserForward => platform.send_key(SyntheticKey::BrowserForward),
+            Action::DesktopNext => platform.switch_desktop(DesktopCommand::Next),
+            Action::DesktopPrevious => platform.switch_desktop(DesktopCommand::Previous),
+            Action::Desktop(n) => platform.switch_desktop(DesktopCommand::Goto(*n)),
+            Action::WindowNext => platform.switch_window(WindowSwitch::Next),
+            Action::WindowPrevious => platform.switch_window(WindowSwitch::Previous),
+            Action::Snap(position) => platform.snap_window(*position),
+            Action::Session(cmd) => platform.control_session(*cmd),
+            Action::Clipboard { text, paste } => {
+                let text = crate::config::template::expand(
+                    text,
+                    &crate::config::template::Context {
+                        window: &platform.get_active_window(),
+                        clipboard: &|| platform.get_clipboard(),
+                        now: crate::config::template::LocalTime::now(),
+                    },
+                );
+                platform.set_clipboard(&text, *paste)
+            }
+            Action::Obs(request) => platform.state().integrations().send_obs(request.clone()),
+            Action::HomeAssistant(call) => platform
+                .state()
+                .integrations()
+                .send_home_assistant(call.clone()),
+            Action::Http(webhook) => crate::integrations::webhook::send(webhook.clone()),
+            Action::Dbus(call) => crate::integrations::dbus::send(call.clone()),
+            Action::Notify(message) => platform.notify(message),
+            Action::Send(keys) => {
+                keys.iter()
+                    .for_each(|key| platform.send_raw_key(*key, true));
+                keys.iter()
+                    .rev()
+                    .for_each(|key| platform.send_raw_key(*key, false));
+            }
+            Action::Focus(target) => platform.focus_window(target),
+            Action::Run(command) => crate::integrations::shell::run(command.clone()),
+            Action::Chain(actions) => actions.iter().for_each(|action| action.execute(platform)),
+            Action::Delay(ms) => std::thread::sleep(std::time::Duration::from_millis(*ms)),
+            Action::Branch { .. } => {
+                if let Some(action) = self.branch_target(&platform.get_active_window()) {
+                    action.execute(platform);
+                }
+            }
+            Action::Passthrough | Action::Block => {}
+        }
+    }
+
+    /// The action a `Branch` takes in `window`, or `None` when the condition fails
+    /// without an `else`. Other actions return themselves.
+    pub fn branch_target(&self, window: &WindowInfo) -> Option<&Action> {
+        match self {
+            Action::Branch {
+                condition,
+                then,
+                otherwise,
+            } => {
+                if condition.matches(window) {
+                    Some(then)
+                } else {
+                    otherwise.as_deref()
+                }
+            }
+            action => Some(action),
+        }
+    }
+
+    /// Returns the corresponding EventResponse for non-executable actions.
+    ///
+    /// - `Passthrough` → `Some(EventResponse::Passthrough)`
+    /// - `Block` → `Some(EventResponse::Block)`
+    /// - All other actions → `None` (must be executed via platform)
+    pub fn as_response(&self) -> Option<crate::platform::EventResponse> {
+        match self {
+            Action::Passthrough => Some(crate::platform::EventResponse::Passthrough),
+            Action::Block => Some(crate::platform::EventResponse::Block),
+            _ => None,
+        }
+    }
+}
+
+impl TryFrom<toml::Value> for Action {
+    type Error = String;
+
+    fn try_from(value: toml::Value) -> Result<Self, Self::Error> {
+        super::parse_action_value(&value)
+    }
+}
+
+/// Short description of an action, matching its config spelling where it has one
+impl std::fmt::Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use crate::integrations::obs::ObsRequest;
+        use crate::platform::{MediaCommand, MicCommand, SessionCommand};
+
+        match self {
+            Action::MediaPlayPause => f.write_str("media_play_pause"),
+            Action::MediaNext => f.write_str("media_next"),
+            Action::MediaPrevious => f.write_str("media_previous"),
+            Action::MediaStop => f.write_str("media_stop"),
+            Action::VolumeUp => f.write_str("volume_up"),
+            Action::VolumeDown => f.write_str("volume_down"),
+            Action::VolumeMute => f.write_str("volume_mute"),
+            Action::VolumeStep(percent) if *percent < 0 => {
+                write!(f, "volume_down {}%", percent.unsigned_abs())
+            }
+            Action::VolumeStep(percent) => write!(f, "volume_up {percent}%"),
+            Action::PlayerMedia { player, command } => {
+                let name = match command {
+                    MediaCommand::PlayPause => "media_play_pause",
+                    MediaCommand::Pause => "media_pause",
+                    MediaCommand::Next => "media_next",
+                    MediaCommand::Previous => "media_previous",
+                    MediaCommand::Stop => "media_stop",
+                    MediaCommand::VolumeUp => "volume_up",
+                    MediaCommand::VolumeDown => "volume_down",
+                    MediaCommand::VolumeMute => "volume_mute",
+                };
+                write!(f, "{name} ({player})")
+            }
+            Action::Mic(cmd) => f.write_str(match cmd {
+                MicCommand::Mute => "mic_mute",
+                MicCommand::Unmute => "mic_unmute",
+                MicCommand::Toggle => "mic_toggle",
+            }),
+            Action::BrowserBack => f.write_str("browser_back"),
+            Action::BrowserForward => f.write_str("browser_forward"),
+            Action::DesktopNext => f.write_str("desktop_next"),
+            Action::DesktopPrevious => f.write_str("desktop_previous"),
+            Action::Desktop(n) => write!(f, "desktop_{n}"),
+            Action::WindowNext => f.write_str("window_next"),
+            Action::WindowPrevious => f.write_str("window_previous"),
+            Action::Snap(position) => f.write_str(position.name()),
+            Action::Session(cmd) => f.write_str(match cmd {
+                SessionCommand::Lock => "lock_screen",
+                SessionCommand::Sleep => "system_sleep",
+                SessionCommand::DisplayOff => "display_off",
+            }),
+            Action::Clipboard { .. } => f.write_str("clipboard"),
+            Action::Obs(ObsRequest::Scene(scene)) => write!(f, "obs scene {scene}"),
+            Action::Obs(ObsRequest::Toggle(output)) => write!(f, "obs toggle {output:?}"),
+            Action::Obs(ObsRequest::ToggleMute(input)) => write!(f, "obs toggle_mute {input}"),
+            Action::HomeAssistant(call) => {
+                write!(f, "home_assistant {}.{}", call.domain, call.service)
+            }
+            Action::Http(webhook) => write!(
+                f,
+                "http {} {}{}",
+                webhook.method, webhook.url.host, webhook.url.path
+            ),
+            Action::Dbus(call) => write!(f, "dbus {}.{}", call.interface, call.method),
+            Action::Notify(message) => write!(f, "notify {message}"),
+            Action::Send(keys) => {
+                let keys: Vec<String> = keys.iter().map(ToString::to_string).collect();
+                write!(f, "send {}", keys.join("+"))
+            }
+            Action::Focus(target) => {
+                let window = &target.window;
+                let name = [&window.binary, &window.class, &window.title]
+                    .into_iter()
+                    .find_map(|pattern| pattern.as_deref())
+                    .unwrap_or("window");
+                write!(f, "focus {name}")
+            }
+            Action::Run(command) => write!(f, "run {}", command.command),
+            Action::Chain(actions) => {
+                for (i, action) in actions.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{action}")?;
+                }
+                Ok(())
+            }
+            Action::Delay(ms) => write!(f, "delay {ms}ms"),
+            Action::Branch {
+                then, otherwise, ..
+            } => match otherwise {
+                Some(otherwise) => write!(f, "if window matches: {then}, else: {otherwise}"),
+                None => write!(f, "if window matches: {then}"),
+            },
+            Action::Passthrough => f.write_str("passthrough"),
+            Action::Block => f.write_str("block"),
+        }
+    }
+}
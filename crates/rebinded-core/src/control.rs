@@ -0,0 +1,647 @@
+//! Control protocol of a running daemon
+//!
+//! Clients connect to the daemon's control socket (see the `rebinded` binary),
+//! send one request as a single line and read the reply until the daemon
+//! closes the connection. Requests are JSON-RPC 2.0:
+//!
+//! ```text
+//! → {"jsonrpc":"2.0","id":1,"method":"profile","params":{"name":"gaming"}}
+//! ← {"jsonrpc":"2.0","id":1,"result":{"profile":"gaming"}}
+//! → {"jsonrpc":"2.0","id":2,"method":"disable","params":{"key":"f13"}}
+//! ← {"jsonrpc":"2.0","id":2,"error":{"code":-32000,"message":"..."}}
+//! ```
+//!
+//! Methods, their params and results:
+//! - `pause` / `resume` / `toggle`: `{"paused": bool}`. While paused every
+//!   input passes through untouched.
+//! - `reload`: re-read the active config file; `{"config": path}`
+//! - `profile` `{"name"}`: load `profiles/<name>.toml` next to the config
+//!   (`default` returns to the main config); `{"profile": name}`
+//! - `enable` / `disable` `{"key"}`: bind a key again, or pass it through
+//!   untouched; `{"key": name, "enabled": bool}`
+//! - `status`: `{"paused", "profile", "config", "bindings", "strategies", "disabled"}`
+//! - `state`: every strategy's internal state, keyed by strategy name
+//! - `stats`: event handling latency, `{"events", "p50_ms", "p99_ms", "max_ms",
+//!   "slow", "slow_threshold_ms"}`, or `null` before the first event
+//!
+//! [`Request`] and [`Response`] are the typed forms of both sides. The daemon
+//! also accepts a request as plain text (`profile gaming`, see
+//! [`Request::parse`]) and answers those with the reply's text form, prefixed
+//! with `error: ` on failure.
+
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde_json::{Value, json};
+
+use crate::key::{self, KeyCode};
+
+/// Invalid JSON
+pub const PARSE_ERROR: i64 = -32700;
+/// Valid JSON that isn't a JSON-RPC request
+pub const INVALID_REQUEST: i64 = -32600;
+/// No such method
+pub const METHOD_NOT_FOUND: i64 = -32601;
+/// Missing or invalid params for the method
+pub const INVALID_PARAMS: i64 = -32602;
+/// The daemon couldn't carry out the request (e.g. a config that fails to load)
+pub const REQUEST_FAILED: i64 = -32000;
+
+/// Longest request or reply line either side reads, in bytes
+pub const MAX_LINE_LEN: usize = 1024 * 1024;
+
+/// A control request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Request {
+    Pause,
+    Resume,
+    Toggle,
+    Reload,
+    /// Switch profile; `None` is the main config
+    Profile(Option<String>),
+    Enable(KeyCode),
+    Disable(KeyCode),
+    Status,
+    State,
+    Stats,
+}
+
+impl Request {
+    /// Parse a text command such as `disable f13` or `profile gaming`
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let mut words = line.split_whitespace();
+        let command = words.next().ok_or("empty command")?;
+        let arg = words.next();
+        if words.next().is_some() {
+            return Err(format!("too many arguments for '{command}'"));
+        }
+
+        match (command, arg) {
+            ("profile" | "enable" | "disable", None) => {
+                Err(format!("'{command}' needs an argument"))
+            }
+            ("pause" | "resume" | "toggle" | "reload" | "status" | "state" | "stats", Some(_)) => {
+                Err(format!("'{command}' takes no arguments"))
+            }
+            _ => Self::from_method(command, arg).map_err(|e| e.message),
+        }
+    }
+
+    /// The request for `method` with its single argument, if it takes one
+    fn from_method(method: &str, arg: Option<&str>) -> Result<Self, RpcError> {
+        let request = match (method, arg) {
+            ("pause", _) => Self::Pause,
+            ("resume", _) => Self::Resume,
+            ("toggle", _) => Self::Toggle,
+            ("reload", _) => Self::Reload,
+            ("status", _) => Self::Status,
+            ("state", _) => Self::State,
+            ("stats", _) => Self::Stats,
+            ("profile", Some("default")) => Self::Profile(None),
+            ("profile", Some(name)) => {
+                if !is_profile_name(name) {
+                    return Err(RpcError::invalid_params(format!(
+                        "invalid profile name '{name}'"
+                    )));
+                }
+                Self::Profile(Some(name.to_string()))
+            }
+            ("enable" | "disable", Some(key)) => {
+                let code = KeyCode::from_config_str(key)
+                    .ok_or_else(|| RpcError::invalid_params(format!("unknown key '{key}'")))?;
+                if method == "enable" {
+                    Self::Enable(code)
+                } else {
+                    Self::Disable(code)
+                }
+            }
+            ("profile" | "enable" | "disable", None) => {
+                return Err(RpcError::invalid_params(format!(
+                    "'{method}' needs an argument"
+                )));
+            }
+            _ => {
+                return Err(RpcError::new(
+                    METHOD_NOT_FOUND,
+                    format!("unknown command '{method}'"),
+                ));
+            }
+        };
+        Ok(request)
+    }
+
+    /// The JSON-RPC method name
+    pub fn method(&self) -> &'static str {
+        match self {
+            Self::Pause => "pause",
+            Self::Resume => "resume",
+            Self::Toggle => "toggle",
+            Self::Reload => "reload",
+            Self::Profile(_) => "profile",
+            Self::Enable(_) => "enable",
+            Self::Disable(_) => "disable",
+            Self::Status => "status",
+            Self::State => "state",
+            Self::Stats => "stats",
+        }
+    }
+
+    /// The request as a JSON-RPC request line (without the newline)
+    pub fn to_json_rpc(&self, id: u64) -> String {
+        let mut message = json!({ "jsonrpc": "2.0", "id": id, "method": self.method() });
+        let params = match self {
+            Self::Profile(name) => Some(json!({ "name": name.as_deref().unwrap_or("default") })),
+            Self::Enable(key) | Self::Disable(key) => {
+                Some(json!({ "key": key::config_name(*key) }))
+            }
+            _ => None,
+        };
+        if let Some(params) = params {
+            message["params"] = params;
+        }
+        message.to_string()
+    }
+
+    /// Parse a JSON-RPC request line, returning its id and the request. Errors
+    /// come with the id to answer them under (`null` if it couldn't be read).
+    pub fn from_json_rpc(line: &str) -> Result<(Value, Self), (Value, RpcError)> {
+        if line.len() > MAX_LINE_LEN {
+            return Err((Value::Null, RpcError::new(PARSE_ERROR, "request too long")));
+        }
+        let message = serde_json::from_str::<Value>(line.trim())
+            .map_err(|e| (Value::Null, RpcError::new(PARSE_ERROR, e.to_string())))?;
+        let id = message.get("id").cloned().unwrap_or(Value::Null);
+        let method = match (message.get("jsonrpc"), message.get("method")) {
+            (Some(Value::String(version)), Some(Value::String(method))) if version == "2.0" => {
+                method
+            }
+            _ => {
+                let error = RpcError::new(INVALID_REQUEST, "not a JSON-RPC 2.0 request");
+                return Err((id, error));
+            }
+        };
+
+        let arg = match method.as_str() {
+            "profile" => "name",
+            "enable" | "disable" => "key",
+            _ => "",
+        };
+        let arg = message
+            .get("params")
+            .and_then(|params| params.get(arg))
+            .and_then(Value::as_str);
+        match Self::from_method(method, arg) {
+            Ok(request) => Ok((id, request)),
+            Err(error) => Err((id, error)),
+        }
+    }
+}
+
+/// Profile names become file names, so keep them to a safe character set
+fn is_profile_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// The daemon's answer to a successful request
+#[derive(Debug, Clone, PartialEq)]
+pub enum Response {
+    /// Whether input is now paused (`pause`, `resume`, `toggle`)
+    Paused(bool),
+    /// Config file that was reloaded
+    Reloaded(PathBuf),
+    /// Profile now active; `None` is the main config
+    ProfileSwitched(Option<String>),
+    /// A key was enabled or disabled
+    KeyEnabled {
+        key: KeyCode,
+        enabled: bool,
+    },
+    Status(Status),
+    /// Each strategy's name and internal state, sorted by name
+    State(Vec<(String, Value)>),
+    /// `None` until an event has been handled
+    Stats(Option<Stats>),
+}
+
+/// Daemon state reported by `status`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Status {
+    pub paused: bool,
+    /// Active profile; `None` is the main config
+    pub profile: Option<String>,
+    /// Config file in effect
+    pub config: PathBuf,
+    pub bindings: usize,
+    pub strategies: usize,
+    /// Keys passed through untouched, sorted
+    pub disabled: Vec<KeyCode>,
+}
+
+/// Event handling latency reported by `stats`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stats {
+    /// Events handled since the daemon started
+    pub events: u64,
+    /// Percentiles over recent events
+    pub p50: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+    /// Events that took longer than `slow_threshold`
+    pub slow: u64,
+    pub slow_threshold: Duration,
+}
+
+impl Response {
+    /// The JSON-RPC `result` for this response
+    pub fn to_json(&self) -> Value {
+        match self {
+            Self::Paused(paused) => json!({ "paused": paused }),
+            Self::Reloaded(path) => json!({ "config": path.display().to_string() }),
+            Self::ProfileSwitched(name) => {
+                json!({ "profile": name.as_deref().unwrap_or("default") })
+            }
+            Self::KeyEnabled { key, enabled } => {
+                json!({ "key": key::config_name(*key), "enabled": enabled })
+            }
+            Self::Status(status) => json!({
+                "paused": status.paused,
+                "profile": status.profile.as_deref().unwrap_or("default"),
+                "config": status.config.display().to_string(),
+                "bindings": status.bindings,
+                "strategies": status.strategies,
+                "disabled": status
+                    .disabled
+                    .iter()
+                    .map(|key| key::config_name(*key))
+                    .collect::<Vec<_>>(),
+            }),
+            Self::State(states) => Value::Object(
+                states
+                    .iter()
+                    .map(|(name, state)| (name.clone(), state.clone()))
+                    .collect(),
+            ),
+            Self::Stats(None) => Value::Null,
+            Self::Stats(Some(stats)) => json!({
+                "events": stats.events,
+                "p50_ms": millis(stats.p50),
+                "p99_ms": millis(stats.p99),
+                "max_ms": millis(stats.max),
+                "slow": stats.slow,
+                "slow_threshold_ms": millis(stats.slow_threshold),
+            }),
+        }
+    }
+
+    /// Read the `result` the daemon returned for `request`
+    pub fn from_json(request: &Request, result: &Value) -> Result<Self, String> {
+        let field = |name: &str| {
+            result
+                .get(name)
+                .ok_or_else(|| format!("'{}' result has no '{name}'", request.method()))
+        };
+        let string = |name: &str| {
+            field(name)?
+                .as_str()
+                .ok_or_else(|| format!("'{name}' is not a string"))
+        };
+        let number = |name: &str| {
+            field(name)?
+                .as_f64()
+                .ok_or_else(|| format!("'{name}' is not a number"))
+        };
+        let boolean = |name: &str| {
+            field(name)?
+                .as_bool()
+                .ok_or_else(|| format!("'{name}' is not a boolean"))
+        };
+        let key = |name: &str| {
+            let key = string(name)?;
+            KeyCode::from_config_str(key).ok_or_else(|| format!("unknown key '{key}'"))
+        };
+        let profile = |name: &str| {
+            string(name).map(|profile| (profile != "default").then(|| profile.to_string()))
+        };
+
+        Ok(match request {
+            Request::Pause | Request::Resume | Request::Toggle => Self::Paused(boolean("paused")?),
+            Request::Reload => Self::Reloaded(PathBuf::from(string("config")?)),
+            Request::Profile(_) => Self::ProfileSwitched(profile("profile")?),
+            Request::Enable(_) | Request::Disable(_) => Self::KeyEnabled {
+                key: key("key")?,
+                enabled: boolean("enabled")?,
+            },
+            Request::Status => {
+                let disabled = field("disabled")?
+                    .as_array()
+                    .ok_or("'disabled' is not an array")?
+                    .iter()
+                    .map(|key| {
+                        key.as_str()
+                            .and_then(KeyCode::from_config_str)
+                            .ok_or_else(|| format!("unknown disabled key {key}"))
+                    })
+                    .collect::<Result<_, _>>()?;
+                Self::Status(Status {
+                    paused: boolean("paused")?,
+                    profile: profile("profile")?,
+                    config: PathBuf::from(string("config")?),
+                    bindings: number("bindings")? as usize,
+                    strategies: number("strategies")? as usize,
+                    disabled,
+                })
+            }
+            Request::State => match result {
+                Value::Object(entries) => Self::State(
+                    entries
+                        .iter()
+                        .map(|(name, state)| (name.clone(), state.clone()))
+                        .collect(),
+                ),
+                _ => return Err("'state' result is not an object".to_string()),
+            },
+            Request::Stats if result.is_null() => Self::Stats(None),
+            Request::Stats => Self::Stats(Some(Stats {
+                events: number("events")? as u64,
+                p50: from_millis(number("p50_ms")?),
+                p99: from_millis(number("p99_ms")?),
+                max: from_millis(number("max_ms")?),
+                slow: number("slow")? as u64,
+                slow_threshold: from_millis(number("slow_threshold_ms")?),
+            })),
+        })
+    }
+}
+
+/// The text form, as `rebinded ctl` prints it
+impl fmt::Display for Response {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Paused(true) => f.write_str("paused"),
+            Self::Paused(false) => f.write_str("resumed"),
+            Self::Reloaded(path) => write!(f, "reloaded {}", path.display()),
+            Self::ProfileSwitched(name) => write!(
+                f,
+                "switched to profile {}",
+                name.as_deref().unwrap_or("default")
+            ),
+            Self::KeyEnabled { key, enabled: true } => write!(f, "enabled {key}"),
+            Self::KeyEnabled {
+                key,
+                enabled: false,
+            } => write!(f, "disabled {key}"),
+            Self::Status(status) => {
+                let disabled: Vec<String> =
+                    status.disabled.iter().map(|key| key.to_string()).collect();
+                write!(
+                    f,
+                    "state: {}\nprofile: {}\nconfig: {}\nbindings: {}\nstrategies: {}\ndisabled: {}",
+                    if status.paused { "paused" } else { "running" },
+                    status.profile.as_deref().unwrap_or("default"),
+                    status.config.display(),
+                    status.bindings,
+                    status.strategies,
+                    if disabled.is_empty() {
+                        "none".to_string()
+                    } else {
+                        disabled.join(", ")
+                    },
+                )
+            }
+            Self::State(states) if states.is_empty() => f.write_str("no strategies"),
+            Self::State(states) => {
+                let lines: Vec<String> = states
+                    .iter()
+                    .map(|(name, state)| format!("{name}: {state}"))
+                    .collect();
+                f.write_str(&lines.join("\n"))
+            }
+            Self::Stats(None) => f.write_str("no events handled yet"),
+            Self::Stats(Some(stats)) => write!(
+                f,
+                "events: {}\np50: {:.2}ms\np99: {:.2}ms\nmax: {:.2}ms\nslow: {} (over {:.2}ms)",
+                stats.events,
+                millis(stats.p50),
+                millis(stats.p99),
+                millis(stats.max),
+                stats.slow,
+                millis(stats.slow_threshold),
+            ),
+        }
+    }
+}
+
+/// A JSON-RPC error object
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RpcError {
+    /// One of the error code constants in this module
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    fn invalid_params(message: String) -> Self {
+        Self::new(INVALID_PARAMS, message)
+    }
+}
+
+impl fmt::Display for RpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// A JSON-RPC response line (without the newline) answering request `id`
+pub fn reply(id: Value, outcome: &Result<Response, RpcError>) -> String {
+    match outcome {
+        Ok(response) => json!({ "jsonrpc": "2.0", "id": id, "result": response.to_json() }),
+        Err(error) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": error.code, "message": error.message },
+        }),
+    }
+    .to_string()
+}
+
+/// Read the daemon's JSON-RPC reply to `request`
+pub fn parse_reply(request: &Request, line: &str) -> Result<Response, RpcError> {
+    let invalid = |e: String| RpcError::new(PARSE_ERROR, format!("invalid reply: {e}"));
+    if line.len() > MAX_LINE_LEN {
+        return Err(invalid("too long".to_string()));
+    }
+    let message = serde_json::from_str::<Value>(line.trim()).map_err(|e| invalid(e.to_string()))?;
+    if let Some(error) = message.get("error") {
+        return Err(RpcError::new(
+            error.get("code").and_then(Value::as_i64).unwrap_or(0),
+            error
+                .get("message")
+                .and_then(Value::as_str)
+                .unwrap_or("unknown error"),
+        ));
+    }
+    let result = message
+        .get("result")
+        .ok_or_else(|| invalid("no result".to_string()))?;
+    Response::from_json(request, result).map_err(invalid)
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn from_millis(ms: f64) -> Duration {
+    Duration::from_secs_f64(ms.max(0.0) / 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_request() {
+        assert_eq!(Request::parse("pause\n"), Ok(Request::Pause));
+        assert_eq!(Request::parse("  status "), Ok(Request::Status));
+        assert_eq!(
+            Request::parse("profile default"),
+            Ok(Request::Profile(None))
+        );
+        assert_eq!(
+            Request::parse("profile gaming"),
+            Ok(Request::Profile(Some("gaming".to_string())))
+        );
+        assert_eq!(
+            Request::parse("disable 0x7C"),
+            Ok(Request::Disable(KeyCode::new(0x7C)))
+        );
+        assert_eq!(
+            Request::parse("fly"),
+            Err("unknown command 'fly'".to_string())
+        );
+
+        for line in [
+            "",
+            "jump",
+            "pause now",
+            "profile",
+            "profile ../etc",
+            "enable",
+            "disable not_a_key",
+            "profile a b",
+            "stats now",
+        ] {
+            assert!(Request::parse(line).is_err(), "expected error for {line:?}");
+        }
+    }
+
+    #[test]
+    fn test_json_rpc_requests() {
+        for request in [
+            Request::Toggle,
+            Request::Profile(None),
+            Request::Profile(Some("gaming".to_string())),
+            Request::Disable(KeyCode::new(0x7C)),
+            Request::Stats,
+        ] {
+            let line = request.to_json_rpc(7);
+            assert_eq!(Request::from_json_rpc(&line), Ok((json!(7), request)));
+        }
+        assert_eq!(
+            Request::Profile(Some("gaming".to_string())).to_json_rpc(1),
+            r#"{"jsonrpc":"2.0","id":1,"method":"profile","params":{"name":"gaming"}}"#
+        );
+
+        let error = |line: &str| Request::from_json_rpc(line).unwrap_err();
+        assert_eq!(error("{").1.code, PARSE_ERROR);
+        assert_eq!(
+            error(r#"{"id":3,"method":"pause"}"#).1.code,
+            INVALID_REQUEST
+        );
+        assert_eq!(
+            error(r#"{"jsonrpc":"2.0","id":"a","method":"fly"}"#),
+            (
+                json!("a"),
+                RpcError::new(METHOD_NOT_FOUND, "unknown command 'fly'")
+            )
+        );
+        assert_eq!(
+            error(r#"{"jsonrpc":"2.0","id":4,"method":"profile","params":{"name":"../etc"}}"#)
+                .1
+                .code,
+            INVALID_PARAMS
+        );
+        // Deep nesting is refused, not recursed into until the stack runs out
+        assert_eq!(error(&"[".repeat(100_000)).1.code, PARSE_ERROR);
+        assert_eq!(
+            error(&" ".repeat(MAX_LINE_LEN + 1)),
+            (Value::Null, RpcError::new(PARSE_ERROR, "request too long"))
+        );
+    }
+
+    #[test]
+    fn test_replies_round_trip() {
+        let status = Status {
+            paused: false,
+            profile: Some("gaming".to_string()),
+            config: PathBuf::from("/home/u/.config/rebinded/profiles/gaming.toml"),
+            bindings: 3,
+            strategies: 1,
+            disabled: vec![KeyCode::new(0x7C)],
+        };
+        let stats = Stats {
+            events: 2,
+            p50: Duration::from_millis(2),
+            p99: Duration::from_millis(8),
+            max: Duration::from_millis(8),
+            slow: 1,
+            slow_threshold: Duration::from_millis(5),
+        };
+        let state = json!({ "held": true, "remaining_ms": 120 });
+
+        for (request, response) in [
+            (Request::Toggle, Response::Paused(true)),
+            (Request::Profile(None), Response::ProfileSwitched(None)),
+            (
+                Request::Enable(KeyCode::new(0x7C)),
+                Response::KeyEnabled {
+                    key: KeyCode::new(0x7C),
+                    enabled: true,
+                },
+            ),
+            (Request::Status, Response::Status(status)),
+            (
+                Request::State,
+                Response::State(vec![("gate".to_string(), state)]),
+            ),
+            (Request::Stats, Response::Stats(None)),
+            (Request::Stats, Response::Stats(Some(stats.clone()))),
+        ] {
+            let line = reply(json!(1), &Ok(response.clone()));
+            assert_eq!(parse_reply(&request, &line), Ok(response));
+        }
+
+        let failed = RpcError::new(REQUEST_FAILED, "config has errors");
+        let line = reply(Value::Null, &Err(failed.clone()));
+        assert_eq!(
+            line,
+            r#"{"jsonrpc":"2.0","id":null,"error":{"code":-32000,"message":"config has errors"}}"#
+        );
+        assert_eq!(parse_reply(&Request::Reload, &line), Err(failed));
+
+        assert_eq!(
+            Response::Stats(Some(stats)).to_string(),
+            "events: 2\np50: 2.00ms\np99: 8.00ms\nmax: 8.00ms\nslow: 1 (over 5.00ms)"
+        );
+    }
+}
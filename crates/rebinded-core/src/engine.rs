@@ -0,0 +1,501 @@
+//! Event resolution
+//!
+//! Routes input events from a platform through the strategies and bindings of a
+//! [`RuntimeConfig`], deciding whether the OS sees each event. This is what the
+//! daemon runs for every hooked event; embedders drive it the same way.
+//!
+//! Every handled event is also published to the platform's observers (see
+//! [`subscribe`]), so tools can watch what the engine does without touching
+//! the event path.
+
+use std::cell::RefCell;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use tokio::sync::broadcast;
+use tracing::{debug, trace};
+
+use crate::config::{Action, Binding, RuntimeConfig, WindowInfo};
+use crate::key::{InputEvent, InputEventId, KeyEvent};
+use crate::platform::{EventResponse, PlatformInterface, PlatformState};
+use crate::strategy::{PlatformHandle, StrategyContext};
+
+/// Events an observer can fall behind by before it misses some
+pub(crate) const OBSERVER_BUFFER: usize = 256;
+
+/// An event the engine handled, and what it did with it
+#[derive(Debug, Clone)]
+pub struct ProcessedEvent {
+    pub event: InputEvent,
+    /// Focused window, if the event reached binding resolution (bound keys
+    /// that no subscribed strategy consumed)
+    pub window: Option<WindowInfo>,
+    /// Action the binding resolved to in that window
+    pub action: Option<Action>,
+    /// What the OS was told
+    pub response: EventResponse,
+    /// Time spent handling the event
+    pub latency: Duration,
+}
+
+/// Receive every event handled on the platform owning `state` from now on,
+/// in order, e.g. `engine::subscribe(platform.state())`
+///
+/// Publishing never waits for observers: one that falls more than
+/// [`OBSERVER_BUFFER`] events behind gets [`broadcast::error::RecvError::Lagged`]
+/// and skips ahead.
+pub fn subscribe(state: &PlatformState) -> broadcast::Receiver<ProcessedEvent> {
+    state.events().subscribe()
+}
+
+/// Run `platform`'s event loop, handling every event with `config` until
+/// `shutdown` completes or input ends
+///
+/// Works with any [`PlatformInterface`], including a
+/// [`MockPlatform`](crate::platform::MockPlatform) fed scripted events.
+pub async fn run<P: PlatformInterface>(
+    platform: &mut P,
+    config: &RuntimeConfig,
+    shutdown: impl Future<Output = ()>,
+) -> anyhow::Result<()> {
+    let last_window = RefCell::new(None);
+    platform
+        .run(
+            |event, handle| handle_event(event, handle, config, &last_window),
+            shutdown,
+        )
+        .await
+}
+
+/// Notify every strategy if focus moved to another application since the last
+/// window query, so state from the previous application doesn't leak over
+pub fn notify_focus_change(
+    window: &WindowInfo,
+    platform: &PlatformHandle,
+    config: &RuntimeConfig,
+    last_window: &RefCell<Option<WindowInfo>>,
+) {
+    let changed = {
+        let mut last = last_window.borrow_mut();
+        let changed = last
+            .as_ref()
+            .is_some_and(|last| !last.same_application(window));
+        *last = Some(window.clone());
+        changed
+    };
+    if !changed {
+        return;
+    }
+
+    debug!(
+        binary = window.binary,
+        class = window.class,
+        "focus changed"
+    );
+    let ctx = StrategyContext::new(platform.clone(), &Action::Block);
+    for strategy in config.strategies.values() {
+        strategy.borrow_mut().on_focus_change(window, &ctx);
+    }
+}
+
+/// Handle an input event from the platform
+pub async fn handle_event(
+    event: InputEvent,
+    platform: PlatformHandle,
+    config: &RuntimeConfig,
+    last_window: &RefCell<Option<WindowInfo>>,
+) -> EventResponse {
+    let started = Instant::now();
+    let state = platform.state().clone();
+    let events = state.events();
+    // Observers are optional; without any, nothing is cloned for them
+    let observed = (events.receiver_count() > 0).then(|| event.clone());
+    let (response, resolved) = route(event, platform, config, last_window, started);
+    if let Some(event) = observed {
+        let (window, action) = resolved.unzip();
+        // Fails only when every observer unsubscribed since the check
+        let _ = events.send(ProcessedEvent {
+            event,
+            window,
+            action: action.flatten().cloned(),
+            response,
+            latency: started.elapsed(),
+        });
+    }
+    response
+}
+
+/// Decide what happens to an event, returning the response along with the
+/// focused window and resolved action when it got as far as binding resolution
+fn route<'a>(
+    event: InputEvent,
+    platform: PlatformHandle,
+    config: &'a RuntimeConfig,
+    last_window: &RefCell<Option<WindowInfo>>,
+    started: Instant,
+) -> (EventResponse, Option<(WindowInfo, Option<&'a Action>)>) {
+    let event_id = event.id();
+
+    // Accessibility modes see every key before strategies and bindings do
+    if let InputEvent::Key(key_event) = &event
+        && let Some(response) = config.accessibility.process(key_event, &platform)
+    {
+        return (response, None);
+    }
+
+    // Collect strategies subscribed to this event, plus catch-all key subscribers
+    let mut strategy_names: Vec<&String> = config
+        .subscriptions
+        .get(&event_id)
+        .into_iter()
+        .flatten()
+        .collect();
+    if matches!(event, InputEvent::Key(_)) {
+        for name in config
+            .subscriptions
+            .get(&InputEventId::AnyKey)
+            .into_iter()
+            .flatten()
+        {
+            if !strategy_names.contains(&name) {
+                strategy_names.push(name);
+            }
+        }
+    }
+
+    // Check if any strategy is subscribed to this event
+    if !strategy_names.is_empty() {
+        trace!(
+            ?event_id,
+            ?strategy_names,
+            "routing to subscribed strategies"
+        );
+
+        // Route to each subscribed strategy. Once one blocks, the rest only see
+        // the event as diverted and the OS never receives it.
+        let mut routed = event.clone();
+        for strategy_name in strategy_names {
+            if config.strategy_conditions.contains_key(strategy_name)
+                && !config.strategy_active(strategy_name, &platform.get_active_window())
+            {
+                continue;
+            }
+            if let Some(strategy) = config.strategies.get(strategy_name) {
+                // For subscribed events, we use a dummy action since the strategy
+                // will use its own divert actions
+                let ctx = StrategyContext::new(platform.clone(), &Action::Block);
+                let response = strategy.borrow_mut().process(&routed, &ctx);
+
+                if response == EventResponse::Block && !matches!(routed, InputEvent::Divert(_)) {
+                    routed = InputEvent::Divert(Box::new(event.clone()));
+                }
+            }
+        }
+        if matches!(routed, InputEvent::Divert(_)) {
+            return (EventResponse::Block, None);
+        }
+
+        // No strategy blocked, check if this is a key event that also has bindings
+        // (fall through to normal handling below)
+    }
+
+    // Only keys have bindings; anything no strategy claimed passes through
+    let key_event = match &event {
+        InputEvent::Key(key_event) => key_event,
+        InputEvent::MouseButton { .. } | InputEvent::Scroll { .. } | InputEvent::Divert(_) => {
+            return (EventResponse::Passthrough, None);
+        }
+    };
+
+    // Check if this key has a binding - if not, pass through
+    let Some(binding) = config.bindings.get(&key_event.key) else {
+        return (EventResponse::Passthrough, None);
+    };
+
+    // Resolve the action based on window context
+    let window = platform.get_active_window();
+    notify_focus_change(&window, &platform, config, last_window);
+    let Some(action) = config.resolve_action(key_event.key, &window) else {
+        return (EventResponse::Passthrough, Some((window, None)));
+    };
+
+    // Strategies whose window condition doesn't match are skipped. Releases always
+    // reach them so a press that started in a matching window is never left stuck.
+    let active_strategies: Vec<&String> = binding
+        .strategies
+        .iter()
+        .map(|s| s.value())
+        .filter(|name| !key_event.down || config.strategy_active(name, &window))
+        .collect();
+
+    // Handle passthrough/block actions directly. A blocked key with a strategy still
+    // reaches the strategy, which may emit keys of its own (e.g. tap_hold).
+    if let Some(response) = action.as_response()
+        && (response != EventResponse::Block || active_strategies.is_empty())
+    {
+        return (response, Some((window, Some(action))));
+    }
+
+    // If binding has strategies, run the chain in order. A strategy that blocks
+    // consumes the event; one that continues or passes it through hands it to
+    // the next. What the last strategy decides settles the event.
+    if !active_strategies.is_empty() {
+        let ctx = StrategyContext::new(platform.clone(), action)
+            .with_notification(binding.notification(action));
+        let mut last = EventResponse::Passthrough;
+        for strategy_name in active_strategies {
+            let Some(strategy) = config.strategies.get(strategy_name) else {
+                // This should not happen if validation is working correctly
+                debug!(
+                    strategy = strategy_name,
+                    key = ?key_event.key,
+                    "strategy not found, skipping in chain"
+                );
+                continue;
+            };
+
+            last = strategy.borrow_mut().process(&event, &ctx);
+            if last == EventResponse::Block {
+                trace!(strategy = strategy_name, "strategy consumed event");
+                return (EventResponse::Block, Some((window, Some(action))));
+            }
+        }
+        let response = match last {
+            // Accepted by the whole chain: a `block` binding hands the key
+            // itself on, anything else runs as if it had no strategies
+            EventResponse::Continue if matches!(action, Action::Block) => {
+                EventResponse::Passthrough
+            }
+            EventResponse::Continue => {
+                execute_directly(key_event, action, binding, &window, &platform, started)
+            }
+            _ => EventResponse::Passthrough,
+        };
+        return (response, Some((window, Some(action))));
+    }
+
+    let response = execute_directly(key_event, action, binding, &window, &platform, started);
+    (response, Some((window, Some(action))))
+}
+
+/// Run a binding's action for a key no strategy handles: on key-down only,
+/// with the key itself blocked either way
+fn execute_directly(
+    key_event: &KeyEvent,
+    action: &Action,
+    binding: &Binding,
+    window: &WindowInfo,
+    platform: &PlatformHandle,
+    started: Instant,
+) -> EventResponse {
+    if key_event.down {
+        debug!(
+            key = %key_event.key,
+            action = %action,
+            window.binary = window.binary,
+            window.title = window.title,
+            latency_us = started.elapsed().as_micros() as u64,
+            "executing action directly"
+        );
+        platform.execute(action);
+        if let Some(message) = binding.notification(action) {
+            platform.notify(&message);
+        }
+    }
+    EventResponse::Block
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::load_from_str;
+    use crate::key::{KeyCode, KeyEvent};
+    use crate::platform::mock::PlatformCall;
+    use crate::platform::{MediaCommand, MockPlatform};
+    use crate::strategy::KeyStrategy;
+
+    #[tokio::test]
+    async fn test_run_against_mock_platform() {
+        let toml = r#"
+            [bindings.0x7C]
+            action = "media_play_pause"
+        "#;
+        let (_, runtime) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+
+        let mut platform = MockPlatform::new();
+        for (code, down) in [(0x7C, true), (0x7C, false), (0x7D, true)] {
+            platform.push_event(InputEvent::Key(KeyEvent::new(KeyCode::new(code), down)));
+        }
+        run(&mut platform, &runtime, std::future::pending())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            platform.responses(),
+            vec![
+                EventResponse::Block,
+                EventResponse::Block,
+                EventResponse::Passthrough,
+            ]
+        );
+        assert_eq!(
+            platform.calls(),
+            vec![PlatformCall::SendMedia(MediaCommand::PlayPause)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_observers_see_processed_events() {
+        let toml = r#"
+            [bindings.0x7E]
+            action = "media_next"
+        "#;
+        let (_, runtime) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+
+        let mut platform = MockPlatform::new();
+        let mut events = subscribe(platform.state());
+        // Another platform's observers see none of this platform's events
+        let mut elsewhere = subscribe(MockPlatform::new().state());
+        for code in [0x7E, 0x7F] {
+            platform.push_event(InputEvent::Key(KeyEvent::new(KeyCode::new(code), true)));
+        }
+        run(&mut platform, &runtime, std::future::pending())
+            .await
+            .unwrap();
+
+        let mut seen = Vec::new();
+        while let Ok(processed) = events.try_recv() {
+            if let InputEvent::Key(key_event) = &processed.event {
+                seen.push((key_event.key.code(), processed.action, processed.response));
+            }
+        }
+        assert_eq!(
+            seen,
+            vec![
+                (0x7E, Some(Action::MediaNext), EventResponse::Block),
+                (0x7F, None, EventResponse::Passthrough),
+            ]
+        );
+        assert!(elsewhere.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sticky_keys_apply_before_bindings() {
+        let toml = r#"
+            [settings.accessibility]
+            sticky_keys = true
+            feedback = false
+
+            [bindings.0x7C]
+            action = "media_play_pause"
+        "#;
+        let (_, runtime) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+        assert!(runtime.key_routes().is_none());
+        let shift =
+            KeyCode::from_config_str(if cfg!(windows) { "lshift" } else { "leftshift" }).unwrap();
+
+        let mut platform = MockPlatform::new();
+        for (key, down) in [
+            (shift, true),
+            (shift, false),
+            (KeyCode::new(0x7C), true),
+            (KeyCode::new(0x7C), false),
+        ] {
+            platform.push_event(InputEvent::Key(KeyEvent::new(key, down)));
+        }
+        run(&mut platform, &runtime, std::future::pending())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            platform.responses(),
+            vec![
+                EventResponse::Passthrough,
+                EventResponse::Block,
+                EventResponse::Block,
+                EventResponse::Block,
+            ]
+        );
+        // The bound key fires with shift latched, and its release lets go of shift
+        assert_eq!(
+            platform.calls(),
+            vec![
+                PlatformCall::SendMedia(MediaCommand::PlayPause),
+                PlatformCall::SendRawKey {
+                    key: shift,
+                    down: false
+                },
+            ]
+        );
+    }
+
+    /// A strategy that answers every event the same way
+    struct Fixed(EventResponse);
+
+    impl KeyStrategy for Fixed {
+        fn process(&mut self, _event: &InputEvent, _ctx: &StrategyContext) -> EventResponse {
+            self.0
+        }
+    }
+
+    #[tokio::test]
+    async fn test_chain_responses() {
+        use std::rc::Rc;
+
+        let toml = r#"
+            [strategies.first]
+            type = "debounce"
+            chatter_ms = 30
+            [strategies.second]
+            type = "debounce"
+            chatter_ms = 30
+
+            [bindings.0x7C]
+            action = "media_next"
+            strategy = ["first", "second"]
+
+            [bindings.0x7D]
+            action = "block"
+            strategy = ["first", "second"]
+        "#;
+        let (_, mut runtime) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+
+        use EventResponse::{Block, Continue, Passthrough};
+        for (first, second, code, response, executed) in [
+            // Accepted all the way: the action runs, or a blocked key goes through
+            (Continue, Continue, 0x7C, Block, true),
+            (Continue, Continue, 0x7D, Passthrough, false),
+            // The last strategy decides
+            (Continue, Passthrough, 0x7C, Passthrough, false),
+            (Passthrough, Continue, 0x7C, Block, true),
+            (Continue, Block, 0x7C, Block, false),
+            // A block ends the chain early
+            (Block, Continue, 0x7C, Block, false),
+        ] {
+            runtime
+                .strategies
+                .insert("first".to_string(), Rc::new(RefCell::new(Fixed(first))));
+            runtime
+                .strategies
+                .insert("second".to_string(), Rc::new(RefCell::new(Fixed(second))));
+
+            let mut platform = MockPlatform::new();
+            platform.push_event(InputEvent::Key(KeyEvent::new(KeyCode::new(code), true)));
+            run(&mut platform, &runtime, std::future::pending())
+                .await
+                .unwrap();
+
+            assert_eq!(
+                platform.responses(),
+                [response],
+                "{first:?} then {second:?}"
+            );
+            let expected: &[PlatformCall] = if executed {
+                &[PlatformCall::SendMedia(MediaCommand::Next)]
+            } else {
+                &[]
+            };
+            assert_eq!(platform.calls(), expected, "{first:?} then {second:?}");
+        }
+    }
+}
@@ -0,0 +1,310 @@
+//! D-Bus method call action (Linux)
+//!
+//! `{ dbus = { destination = "...", path = "/...", interface = "...", method = "...", args = [...] } }`
+//! calls a method on the session bus (or the system bus with `bus = "system"`)
+//! and ignores the reply. Failed calls are logged.
+//!
+//! Plain TOML values map to D-Bus types as string → `s`, bool → `b`,
+//! integer → `i` and float → `d`. Other types are written as a single-key
+//! table naming the type, e.g. `{ u32 = 0 }` or `{ object_path = "/org/x" }`.
+
+use serde::Deserialize;
+use std::time::Duration;
+#[cfg(unix)]
+use tracing::debug;
+use tracing::warn;
+
+/// Which message bus a call goes to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Bus {
+    #[default]
+    Session,
+    System,
+}
+
+/// One typed method argument
+#[derive(Debug, Clone, PartialEq)]
+pub enum DbusArg {
+    Bool(bool),
+    Byte(u8),
+    I16(i16),
+    U16(u16),
+    I32(i32),
+    U32(u32),
+    I64(i64),
+    U64(u64),
+    Double(f64),
+    Str(String),
+    ObjectPath(String),
+}
+
+// Doubles are checked to be finite when parsed, so float equality is total here
+impl Eq for DbusArg {}
+
+/// A method call bound to a key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DbusCall {
+    pub bus: Bus,
+    pub destination: String,
+    pub path: String,
+    pub interface: String,
+    pub method: String,
+    pub args: Vec<DbusArg>,
+    pub timeout_ms: u64,
+}
+
+impl DbusCall {
+    /// Build a call from its action table
+    pub fn from_args(args: &toml::Value) -> Result<Self, String> {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Args {
+            #[serde(default)]
+            bus: Bus,
+            destination: String,
+            path: String,
+            interface: String,
+            method: String,
+            #[serde(default)]
+            args: Vec<toml::Value>,
+            #[serde(default = "default_timeout_ms")]
+            timeout_ms: u64,
+        }
+
+        let args = Args::deserialize(args.clone()).map_err(|e| e.message().to_string())?;
+        if !is_object_path(&args.path) {
+            return Err(format!("invalid object path '{}'", args.path));
+        }
+        for (field, name) in [
+            ("destination", &args.destination),
+            ("interface", &args.interface),
+        ] {
+            if !is_dotted_name(name) {
+                return Err(format!("invalid {field} '{name}'"));
+            }
+        }
+        if args.method.is_empty() || args.method.contains(['.', '/']) {
+            return Err(format!("invalid method '{}'", args.method));
+        }
+
+        Ok(Self {
+            bus: args.bus,
+            destination: args.destination,
+            path: args.path,
+            interface: args.interface,
+            method: args.method,
+            args: args
+                .args
+                .iter()
+                .enumerate()
+                .map(|(i, value)| parse_arg(value).map_err(|e| format!("args[{i}]: {e}")))
+                .collect::<Result<_, _>>()?,
+            timeout_ms: args.timeout_ms,
+        })
+    }
+}
+
+fn default_timeout_ms() -> u64 {
+    2000
+}
+
+/// `/` or `/segment/segment`, segments made of `[A-Za-z0-9_]`
+fn is_object_path(path: &str) -> bool {
+    path == "/"
+        || path.strip_prefix('/').is_some_and(|rest| {
+            rest.split('/').all(|segment| {
+                !segment.is_empty()
+                    && segment
+                        .bytes()
+                        .all(|b| b.is_ascii_alphanumeric() || b == b'_')
+            })
+        })
+}
+
+/// A bus or interface name such as `org.freedesktop.Notifications` (or a unique `:1.42`)
+fn is_dotted_name(name: &str) -> bool {
+    let name = name.strip_prefix(':').unwrap_or(name);
+    name.contains('.')
+        && name.split('.').all(|element| {
+            !element.is_empty()
+                && element
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-')
+        })
+}
+
+fn parse_arg(value: &toml::Value) -> Result<DbusArg, String> {
+    fn int<T: TryFrom<i64>>(value: &toml::Value, ty: &str) -> Result<T, String> {
+        value
+            .as_integer()
+            .and_then(|n| T::try_from(n).ok())
+            .ok_or_else(|| format!("expected a {ty} integer, found {value}"))
+    }
+
+    match value {
+        toml::Value::Boolean(b) => Ok(DbusArg::Bool(*b)),
+        toml::Value::String(s) => Ok(DbusArg::Str(s.clone())),
+        toml::Value::Float(f) if f.is_finite() => Ok(DbusArg::Double(*f)),
+        toml::Value::Integer(n) => i32::try_from(*n)
+            .map(DbusArg::I32)
+            .map_err(|_| format!("{n} does not fit in int32; write {{ i64 = {n} }}")),
+        toml::Value::Table(table) if table.len() == 1 => {
+            let (ty, value) = table.iter().next().unwrap();
+            match ty.as_str() {
+                "byte" | "u8" => int(value, ty).map(DbusArg::Byte),
+                "i16" => int(value, ty).map(DbusArg::I16),
+                "u16" => int(value, ty).map(DbusArg::U16),
+                "i32" => int(value, ty).map(DbusArg::I32),
+                "u32" => int(value, ty).map(DbusArg::U32),
+                "i64" => int(value, ty).map(DbusArg::I64),
+                "u64" => int(value, ty).map(DbusArg::U64),
+                "double" => match value {
+                    toml::Value::Float(f) if f.is_finite() => Ok(DbusArg::Double(*f)),
+                    toml::Value::Integer(n) => Ok(DbusArg::Double(*n as f64)),
+                    _ => Err(format!("expected a finite number, found {value}")),
+                },
+                "object_path" => match value.as_str() {
+                    Some(path) if is_object_path(path) => Ok(DbusArg::ObjectPath(path.to_string())),
+                    _ => Err(format!("invalid object path {value}")),
+                },
+                _ => Err(format!(
+                    "unknown type '{ty}' (expected byte, i16, u16, i32, u32, i64, u64, double or object_path)"
+                )),
+            }
+        }
+        other => Err(format!("unsupported argument {other}")),
+    }
+}
+
+/// Make the call on a background task, logging the outcome
+pub fn send(call: DbusCall) {
+    tokio::spawn(async move {
+        let target = format!("{}.{}", call.interface, call.method);
+        let timeout = Duration::from_millis(call.timeout_ms);
+        match tokio::time::timeout(timeout, execute(&call)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => warn!(target, "D-Bus call failed: {e:#}"),
+            Err(_) => warn!(target, "D-Bus call timed out"),
+        }
+    });
+}
+
+#[cfg(unix)]
+async fn execute(call: &DbusCall) -> anyhow::Result<()> {
+    use tokio::sync::OnceCell;
+    use zbus::zvariant::{ObjectPath, StructureBuilder, Value};
+
+    static SESSION: OnceCell<zbus::Connection> = OnceCell::const_new();
+    static SYSTEM: OnceCell<zbus::Connection> = OnceCell::const_new();
+
+    let conn = match call.bus {
+        Bus::Session => SESSION.get_or_try_init(zbus::Connection::session).await?,
+        Bus::System => SYSTEM.get_or_try_init(zbus::Connection::system).await?,
+    };
+
+    let destination = call.destination.as_str();
+    let path = call.path.as_str();
+    let interface = call.interface.as_str();
+    let method = call.method.as_str();
+
+    if call.args.is_empty() {
+        conn.call_method(Some(destination), path, Some(interface), method, &())
+            .await?;
+    } else {
+        let mut body = StructureBuilder::new();
+        for arg in &call.args {
+            body = body.append_field(match arg {
+                DbusArg::Bool(b) => Value::from(*b),
+                DbusArg::Byte(n) => Value::from(*n),
+                DbusArg::I16(n) => Value::from(*n),
+                DbusArg::U16(n) => Value::from(*n),
+                DbusArg::I32(n) => Value::from(*n),
+                DbusArg::U32(n) => Value::from(*n),
+                DbusArg::I64(n) => Value::from(*n),
+                DbusArg::U64(n) => Value::from(*n),
+                DbusArg::Double(f) => Value::from(*f),
+                DbusArg::Str(s) => Value::from(s.as_str()),
+                DbusArg::ObjectPath(p) => Value::from(ObjectPath::try_from(p.as_str())?),
+            });
+        }
+        conn.call_method(
+            Some(destination),
+            path,
+            Some(interface),
+            method,
+            &body.build()?,
+        )
+        .await?;
+    }
+
+    debug!(destination, path, interface, method, "D-Bus call succeeded");
+    Ok(())
+}
+
+#[cfg(not(unix))]
+async fn execute(_call: &DbusCall) -> anyhow::Result<()> {
+    Err(anyhow::anyhow!("D-Bus actions are only available on Linux"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> toml::Value {
+        toml::from_str::<toml::Table>(s).unwrap()["dbus"].clone()
+    }
+
+    #[test]
+    fn test_dbus_call_from_args() {
+        let call = DbusCall::from_args(&args(
+            r#"dbus = { destination = "org.freedesktop.ScreenSaver", path = "/org/freedesktop/ScreenSaver", interface = "org.freedesktop.ScreenSaver", method = "Lock" }"#,
+        ))
+        .unwrap();
+        assert_eq!(call.bus, Bus::Session);
+        assert_eq!(call.method, "Lock");
+        assert!(call.args.is_empty());
+        assert_eq!(call.timeout_ms, 2000);
+
+        let call = DbusCall::from_args(&args(
+            r#"dbus = { bus = "system", destination = "org.freedesktop.login1", path = "/org/freedesktop/login1", interface = "org.freedesktop.login1.Manager", method = "Suspend", args = [true, "x", 3, 1.5, { u32 = 7 }, { object_path = "/a/b_c" }] }"#,
+        ))
+        .unwrap();
+        assert_eq!(call.bus, Bus::System);
+        assert_eq!(
+            call.args,
+            vec![
+                DbusArg::Bool(true),
+                DbusArg::Str("x".to_string()),
+                DbusArg::I32(3),
+                DbusArg::Double(1.5),
+                DbusArg::U32(7),
+                DbusArg::ObjectPath("/a/b_c".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_dbus_call() {
+        let base = r#"destination = "org.x.Y", interface = "org.x.Y", method = "Go""#;
+        for spec in [
+            format!(r#"dbus = {{ {base}, path = "relative" }}"#),
+            format!(r#"dbus = {{ {base}, path = "/trailing/" }}"#),
+            format!(r#"dbus = {{ {base}, path = "/x", bus = "user" }}"#),
+            format!(r#"dbus = {{ {base}, path = "/x", args = [{{ u32 = -1 }}] }}"#),
+            format!(r#"dbus = {{ {base}, path = "/x", args = [{{ float = 1 }}] }}"#),
+            format!(r#"dbus = {{ {base}, path = "/x", args = [5000000000] }}"#),
+            format!(r#"dbus = {{ {base}, path = "/x", args = [[1, 2]] }}"#),
+            format!(r#"dbus = {{ {base}, path = "/x", args = [nan] }}"#),
+            r#"dbus = { destination = "nodots", interface = "org.x.Y", method = "Go", path = "/x" }"#
+                .to_string(),
+            r#"dbus = { destination = "org.x.Y", interface = "org.x.Y", method = "a.b", path = "/x" }"#
+                .to_string(),
+        ] {
+            assert!(
+                DbusCall::from_args(&args(&spec)).is_err(),
+                "expected error for {spec}"
+            );
+        }
+    }
+}
@@ -0,0 +1,181 @@
+//! Home Assistant service calls over its REST API
+//!
+//! `home_assistant` actions POST to `/api/services/<domain>/<service>` using a
+//! long-lived access token (Profile → Security in the Home Assistant UI).
+
+use super::http::{self, Url};
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Connection settings from `[settings.home_assistant]`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct HomeAssistantSettings {
+    /// Base URL of the instance, e.g. "http://homeassistant.local:8123"
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Long-lived access token. Falls back to the `HASS_TOKEN` environment variable.
+    #[serde(default)]
+    pub token: Option<String>,
+    /// How long a service call may take before it is abandoned (ms)
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_timeout_ms() -> u64 {
+    5000
+}
+
+impl Default for HomeAssistantSettings {
+    fn default() -> Self {
+        Self {
+            url: None,
+            token: None,
+            timeout_ms: default_timeout_ms(),
+        }
+    }
+}
+
+/// A service call bound to a key, e.g.
+/// `{ home_assistant = { service = "light.toggle", entity_id = "light.desk" } }`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceCall {
+    pub domain: String,
+    pub service: String,
+    /// JSON request body (the service data)
+    pub body: String,
+}
+
+impl ServiceCall {
+    /// Build a call from its action table: `service`, plus an optional `entity_id`
+    /// and `data` table merged into the service data
+    pub fn from_args(args: &toml::Value) -> Result<Self, String> {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Args {
+            service: String,
+            #[serde(default)]
+            entity_id: Option<toml::Value>,
+            #[serde(default)]
+            data: toml::Table,
+        }
+
+        let args = Args::deserialize(args.clone()).map_err(|e| e.message().to_string())?;
+        let Some((domain, service)) = args.service.split_once('.') else {
+            return Err(format!(
+                "service '{}' must be written as <domain>.<service>, e.g. light.toggle",
+                args.service
+            ));
+        };
+
+        let mut data = args.data;
+        if let Some(entity_id) = args.entity_id {
+            data.insert("entity_id".to_string(), entity_id);
+        }
+
+        Ok(Self {
+            domain: domain.to_string(),
+            service: service.to_string(),
+            body: serde_json::to_string(&data).map_err(|e| e.to_string())?,
+        })
+    }
+}
+
+/// Call a service with `settings` on a background task, logging the outcome
+pub fn send(settings: HomeAssistantSettings, call: ServiceCall) {
+    tokio::spawn(async move {
+        let timeout = Duration::from_millis(settings.timeout_ms);
+        let service = format!("{}.{}", call.domain, call.service);
+        match tokio::time::timeout(timeout, execute(&settings, &call)).await {
+            Ok(Ok(())) => debug!(service, "home assistant call succeeded"),
+            Ok(Err(e)) => warn!(service, "home assistant call failed: {e:#}"),
+            Err(_) => warn!(service, "home assistant call timed out"),
+        }
+    });
+}
+
+async fn execute(settings: &HomeAssistantSettings, call: &ServiceCall) -> Result<()> {
+    let base = settings
+        .url
+        .as_deref()
+        .ok_or_else(|| anyhow!("no url set in [settings.home_assistant]"))?;
+    let url = Url::parse(base)
+        .map_err(|e| anyhow!(e))?
+        .join(&format!("api/services/{}/{}", call.domain, call.service));
+    let token = settings
+        .token
+        .clone()
+        .or_else(|| std::env::var("HASS_TOKEN").ok())
+        .ok_or_else(|| anyhow!("no token set in [settings.home_assistant] or HASS_TOKEN"))?;
+
+    let authorization = format!("Bearer {token}");
+    let response = http::request(
+        "POST",
+        &url,
+        &[
+            ("Authorization", &authorization),
+            ("Content-Type", "application/json"),
+        ],
+        call.body.as_bytes(),
+    )
+    .await?;
+
+    if !response.is_success() {
+        return Err(anyhow!(
+            "HTTP {}: {}",
+            response.status,
+            response.body_preview()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    fn args(s: &str) -> toml::Value {
+        toml::from_str::<toml::Table>(s).unwrap()["home_assistant"].clone()
+    }
+
+    #[test]
+    fn test_service_call_from_args() {
+        let call = ServiceCall::from_args(&args(
+            r#"home_assistant = { service = "light.turn_on", entity_id = "light.desk", data = { brightness = 128 } }"#,
+        ))
+        .unwrap();
+        assert_eq!(call.domain, "light");
+        assert_eq!(call.service, "turn_on");
+        let body = serde_json::from_str::<Value>(&call.body).unwrap();
+        assert_eq!(
+            body.get("entity_id").and_then(Value::as_str),
+            Some("light.desk")
+        );
+        assert_eq!(body.get("brightness").and_then(Value::as_f64), Some(128.0));
+
+        let call =
+            ServiceCall::from_args(&args(r#"home_assistant = { service = "scene.turn_on" }"#))
+                .unwrap();
+        assert_eq!(call.body, "{}");
+    }
+
+    #[test]
+    fn test_invalid_service_call() {
+        assert!(
+            ServiceCall::from_args(&args(r#"home_assistant = { service = "toggle" }"#)).is_err()
+        );
+        assert!(
+            ServiceCall::from_args(&args(r#"home_assistant = { entity_id = "light.desk" }"#))
+                .is_err()
+        );
+        assert!(
+            ServiceCall::from_args(&args(
+                r#"home_assistant = { service = "light.toggle", entity = "light.desk" }"#
+            ))
+            .is_err()
+        );
+    }
+}
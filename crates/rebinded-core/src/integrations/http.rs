@@ -0,0 +1,378 @@
+//! Minimal HTTP/1.1 client for integrations
+//!
+//! Sends one request per connection (`Connection: close`) and reads the whole
+//! response. `https://` URLs go through rustls, verifying the server against
+//! the Mozilla root certificates bundled by `webpki-roots`.
+
+use anyhow::{Context, Result, anyhow};
+use std::sync::{Arc, OnceLock};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+use tokio_rustls::rustls::{self, ClientConfig, RootCertStore, pki_types::ServerName};
+
+/// Largest response read before giving up, far beyond any integration reply
+/// or config file
+const MAX_RESPONSE_BYTES: u64 = 16 * 1024 * 1024;
+
+/// A parsed `http[s]://host[:port]/path` URL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Url {
+    /// Whether this is an `https://` URL
+    pub tls: bool,
+    pub host: String,
+    pub port: u16,
+    /// Path and query, always starting with `/`
+    pub path: String,
+}
+
+impl Url {
+    pub fn parse(url: &str) -> Result<Self, String> {
+        let (tls, rest) = match url.split_once("://") {
+            Some(("http", rest)) => (false, rest),
+            Some(("https", rest)) => (true, rest),
+            Some((scheme, _)) => return Err(format!("'{url}': unsupported scheme '{scheme}'")),
+            None => return Err(format!("'{url}': expected an http:// or https:// URL")),
+        };
+
+        let (authority, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (
+                host,
+                port.parse()
+                    .map_err(|_| format!("'{url}': invalid port '{port}'"))?,
+            ),
+            None => (authority, if tls { 443 } else { 80 }),
+        };
+        if host.is_empty() {
+            return Err(format!("'{url}': missing host"));
+        }
+
+        Ok(Self {
+            tls,
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+        })
+    }
+
+    /// `host[:port]` for the `Host` header, leaving out the scheme's default port
+    pub fn authority(&self) -> String {
+        match (self.tls, self.port) {
+            (false, 80) | (true, 443) => self.host.clone(),
+            (_, port) => format!("{}:{port}", self.host),
+        }
+    }
+
+    /// This URL with `suffix` appended to the path (avoiding a doubled `/`)
+    pub fn join(&self, suffix: &str) -> Url {
+        let mut joined = self.clone();
+        joined.path = format!(
+            "{}/{}",
+            self.path.trim_end_matches('/'),
+            suffix.trim_start_matches('/')
+        );
+        joined
+    }
+}
+
+/// A received response
+#[derive(Debug)]
+pub struct Response {
+    pub status: u16,
+    /// Header names and values, in the order received
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// The value of a header, matching its name case-insensitively
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The start of the body as text, for error messages
+    pub fn body_preview(&self) -> String {
+        let text = String::from_utf8_lossy(&self.body);
+        let text = text.trim();
+        match text.char_indices().nth(200) {
+            Some((i, _)) => format!("{}...", &text[..i]),
+            None => text.to_string(),
+        }
+    }
+}
+
+/// Send a request and read the full response
+pub async fn request(
+    method: &str,
+    url: &Url,
+    headers: &[(&str, &str)],
+    body: &[u8],
+) -> Result<Response> {
+    let stream = TcpStream::connect((url.host.as_str(), url.port))
+        .await
+        .with_context(|| format!("failed to connect to {}:{}", url.host, url.port))?;
+    if !url.tls {
+        return exchange(stream, method, url, headers, body).await;
+    }
+
+    let name = ServerName::try_from(url.host.clone())
+        .map_err(|_| anyhow!("'{}' is not a valid TLS server name", url.host))?;
+    let stream = TlsConnector::from(tls_config())
+        .connect(name, stream)
+        .await
+        .with_context(|| format!("TLS handshake with {} failed", url.host))?;
+    exchange(stream, method, url, headers, body).await
+}
+
+/// Client settings shared by every TLS connection
+fn tls_config() -> Arc<ClientConfig> {
+    static CONFIG: OnceLock<Arc<ClientConfig>> = OnceLock::new();
+    CONFIG
+        .get_or_init(|| {
+            let roots = RootCertStore {
+                roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+            };
+            let config = ClientConfig::builder_with_provider(Arc::new(
+                rustls::crypto::ring::default_provider(),
+            ))
+            .with_safe_default_protocol_versions()
+            .expect("ring supports the default protocol versions")
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+            Arc::new(config)
+        })
+        .clone()
+}
+
+/// Write the request to an open connection and read the response to its end
+async fn exchange<S: AsyncRead + AsyncWrite + Unpin>(
+    mut stream: S,
+    method: &str,
+    url: &Url,
+    headers: &[(&str, &str)],
+    body: &[u8],
+) -> Result<Response> {
+    let mut head = format!(
+        "{method} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nContent-Length: {}\r\n",
+        url.path,
+        url.authority(),
+        body.len()
+    );
+    for (name, value) in headers {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    head.push_str("\r\n");
+
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body).await?;
+
+    let mut raw = Vec::new();
+    // One byte past the limit tells a response that fills it from one that's too big
+    let read = (&mut stream)
+        .take(MAX_RESPONSE_BYTES + 1)
+        .read_to_end(&mut raw)
+        .await;
+    if raw.len() as u64 > MAX_RESPONSE_BYTES {
+        return Err(anyhow!(
+            "response is larger than {} MiB",
+            MAX_RESPONSE_BYTES / 1024 / 1024
+        ));
+    }
+    match read {
+        Ok(_) => {}
+        // Plenty of HTTPS servers close without a TLS close_notify; the
+        // response is still complete, since the request asked to close
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof && !raw.is_empty() => {}
+        Err(e) => return Err(e.into()),
+    }
+    parse_response(&raw)
+}
+
+fn parse_response(raw: &[u8]) -> Result<Response> {
+    let head_end = raw
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .ok_or_else(|| anyhow!("incomplete HTTP response"))?;
+    let head = String::from_utf8_lossy(&raw[..head_end]);
+    let body = &raw[head_end + 4..];
+
+    let mut lines = head.lines();
+    let status_line = lines.next().unwrap_or_default();
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| anyhow!("invalid HTTP status line: {status_line}"))?;
+
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect();
+    let chunked = headers.iter().any(|(name, value)| {
+        name.eq_ignore_ascii_case("transfer-encoding") && value.eq_ignore_ascii_case("chunked")
+    });
+    let body = if chunked {
+        decode_chunked(body)?
+    } else {
+        body.to_vec()
+    };
+
+    Ok(Response {
+        status,
+        headers,
+        body,
+    })
+}
+
+fn decode_chunked(mut data: &[u8]) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+    loop {
+        let line_end = data
+            .windows(2)
+            .position(|w| w == b"\r\n")
+            .ok_or_else(|| anyhow!("truncated chunked body"))?;
+        let size_field = String::from_utf8_lossy(&data[..line_end]);
+        // Chunk extensions after ';' carry nothing we need
+        let size_hex = size_field.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size_hex, 16)
+            .map_err(|_| anyhow!("invalid chunk size '{size_hex}'"))?;
+        data = &data[line_end + 2..];
+        if size == 0 {
+            return Ok(body);
+        }
+        let chunk = data
+            .get(..size)
+            .ok_or_else(|| anyhow!("truncated chunked body"))?;
+        body.extend_from_slice(chunk);
+        data = data.get(size + 2..).unwrap_or_default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_parse_url() {
+        assert_eq!(
+            Url::parse("http://homeassistant.local:8123").unwrap(),
+            Url {
+                tls: false,
+                host: "homeassistant.local".to_string(),
+                port: 8123,
+                path: "/".to_string(),
+            }
+        );
+        let url = Url::parse("http://example.com/hooks/key?id=3").unwrap();
+        assert_eq!(url.port, 80);
+        assert_eq!(url.path, "/hooks/key?id=3");
+        assert_eq!(
+            Url::parse("http://ha:8123/")
+                .unwrap()
+                .join("/api/services/light/toggle")
+                .path,
+            "/api/services/light/toggle"
+        );
+
+        let url = Url::parse("https://example.com/rebinded.toml").unwrap();
+        assert!(url.tls);
+        assert_eq!(url.port, 443);
+        assert!(Url::parse("ftp://example.com").unwrap_err().contains("ftp"));
+        assert!(Url::parse("example.com/path").is_err());
+        assert!(Url::parse("http://host:port/").is_err());
+        assert!(Url::parse("http:///path").is_err());
+    }
+
+    #[test]
+    fn test_authority() {
+        let authority = |url: &str| Url::parse(url).unwrap().authority();
+        assert_eq!(authority("http://example.com/x"), "example.com");
+        assert_eq!(authority("https://example.com/x"), "example.com");
+        assert_eq!(authority("http://ha.local:8123/"), "ha.local:8123");
+        assert_eq!(authority("https://example.com:80/"), "example.com:80");
+        assert_eq!(authority("http://example.com:443/"), "example.com:443");
+    }
+
+    #[test]
+    fn test_chunked_response() {
+        let raw = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nETag: \"abc\"\r\n\r\n4\r\nWiki\r\n5;x=y\r\npedia\r\n0\r\n\r\n";
+        let response = parse_response(raw).unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.header("etag"), Some("\"abc\""));
+        assert_eq!(response.body, b"Wikipedia");
+    }
+
+    #[tokio::test]
+    async fn test_request_round_trip() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            let mut buf = [0u8; 1024];
+            // Read until the whole (small) body has arrived
+            while !received.ends_with(b"{\"on\":true}") {
+                let n = stream.read(&mut buf).await.unwrap();
+                assert!(n > 0, "client closed early");
+                received.extend_from_slice(&buf[..n]);
+            }
+            stream
+                .write_all(b"HTTP/1.1 401 Unauthorized\r\nContent-Length: 12\r\n\r\nbad token!!!")
+                .await
+                .unwrap();
+            String::from_utf8(received).unwrap()
+        });
+
+        let url = Url::parse(&format!("http://127.0.0.1:{port}/api/x")).unwrap();
+        let response = request(
+            "POST",
+            &url,
+            &[("Content-Type", "application/json")],
+            b"{\"on\":true}",
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status, 401);
+        assert!(!response.is_success());
+        assert_eq!(response.body_preview(), "bad token!!!");
+
+        let received = server.await.unwrap();
+        assert!(received.starts_with("POST /api/x HTTP/1.1\r\n"));
+        assert!(received.contains(&format!("Host: 127.0.0.1:{port}\r\n")));
+        assert!(received.contains("Content-Type: application/json\r\n"));
+        assert!(received.contains("Content-Length: 11\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_oversized_response() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = stream.read(&mut buf).await;
+            let _ = stream.write_all(b"HTTP/1.1 200 OK\r\n\r\n").await;
+            let chunk = vec![b'x'; 64 * 1024];
+            // Stops once the client hangs up
+            while stream.write_all(&chunk).await.is_ok() {}
+        });
+
+        let url = Url::parse(&format!("http://127.0.0.1:{port}/")).unwrap();
+        let err = request("GET", &url, &[], b"").await.unwrap_err();
+        assert!(err.to_string().contains("larger than"), "{err}");
+    }
+}
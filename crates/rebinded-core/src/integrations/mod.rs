@@ -0,0 +1,112 @@
+//! Integrations with external applications and services
+//!
+//! Actions here talk to other programs rather than the OS input stack. Each
+//! integration runs its requests on background tasks and logs failures, so a
+//! slow or missing service never delays key handling.
+
+pub mod dbus;
+pub mod home_assistant;
+pub mod http;
+pub mod obs;
+pub mod shell;
+pub mod webhook;
+
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, RwLock};
+
+/// Settings from the `[settings]` table
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Settings {
+    /// obs-websocket connection used by `obs` actions
+    #[serde(default)]
+    pub obs: obs::ObsSettings,
+    /// Home Assistant instance used by `home_assistant` actions
+    #[serde(default)]
+    pub home_assistant: home_assistant::HomeAssistantSettings,
+    /// Which gamepads button bindings are read from
+    #[serde(default)]
+    pub gamepad: crate::platform::GamepadSettings,
+    /// lircd connection IR remote buttons are read from
+    #[serde(default)]
+    pub lirc: crate::platform::LircSettings,
+    /// HDMI-CEC adapter TV remote keys are read from
+    #[serde(default)]
+    pub cec: crate::platform::CecSettings,
+    /// HTTP endpoint that fires bindings for other devices and scripts
+    #[serde(default)]
+    pub trigger: crate::platform::TriggerSettings,
+    /// Accessibility modes applied to every key
+    #[serde(default)]
+    pub accessibility: crate::accessibility::AccessibilitySettings,
+    /// When to pass every key through, e.g. while a game is focused
+    #[serde(default)]
+    pub auto_disable: crate::platform::AutoDisableSettings,
+    /// How injected keys are sent: `virtual_key`, or `scancode` for games
+    /// that ignore virtual keys (Windows)
+    #[serde(default)]
+    pub injection: crate::platform::InjectionMode,
+    /// Show a notification when the mode keys are in changes: a profile
+    /// switch, a pause or resume, or auto-disable kicking in
+    #[serde(default)]
+    pub notify_mode_changes: bool,
+    /// Events that take longer than this to handle are logged as slow
+    #[serde(default = "default_slow_event_ms")]
+    pub slow_event_ms: u64,
+}
+
+fn default_slow_event_ms() -> u64 {
+    20
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            obs: Default::default(),
+            home_assistant: Default::default(),
+            gamepad: Default::default(),
+            lirc: Default::default(),
+            cec: Default::default(),
+            trigger: Default::default(),
+            accessibility: Default::default(),
+            auto_disable: Default::default(),
+            injection: Default::default(),
+            notify_mode_changes: false,
+            slow_event_ms: default_slow_event_ms(),
+        }
+    }
+}
+
+/// Settings and connections of the integrations one platform runs actions
+/// through, kept in its [`PlatformState`](crate::platform::PlatformState)
+#[derive(Default)]
+pub struct Integrations {
+    obs: Arc<obs::ObsClient>,
+    home_assistant: RwLock<home_assistant::HomeAssistantSettings>,
+}
+
+impl Integrations {
+    /// Apply settings to every integration
+    pub fn configure(&self, settings: &Settings) {
+        self.obs.configure(settings.obs.clone());
+        *self
+            .home_assistant
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = settings.home_assistant.clone();
+    }
+
+    /// Send a request to OBS on a background task
+    pub fn send_obs(&self, request: obs::ObsRequest) {
+        self.obs.send(request);
+    }
+
+    /// Call a Home Assistant service on a background task
+    pub fn send_home_assistant(&self, call: home_assistant::ServiceCall) {
+        let settings = self
+            .home_assistant
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        home_assistant::send(settings, call);
+    }
+}
@@ -0,0 +1,453 @@
+//! OBS Studio control via obs-websocket (protocol v5)
+//!
+//! `obs` actions are sent over one lazily-opened connection per platform using
+//! the settings from `[settings.obs]`. If OBS restarts, the next action reconnects.
+//!
+//! Only the client side of the protocol that rebinded needs is implemented here:
+//! the Hello/Identify exchange (including password authentication) and single
+//! requests. tokio-tungstenite handles the WebSocket itself.
+
+use anyhow::{Context, Result, anyhow};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio_tungstenite::WebSocketStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::http::header::SEC_WEBSOCKET_PROTOCOL;
+use tracing::{debug, warn};
+
+/// Connection settings from `[settings.obs]`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ObsSettings {
+    /// Host running OBS (default: localhost)
+    #[serde(default = "default_host")]
+    pub host: String,
+    /// obs-websocket port (default: 4455)
+    #[serde(default = "default_port")]
+    pub port: u16,
+    /// Server password. Falls back to the `OBS_WEBSOCKET_PASSWORD` environment
+    /// variable so it can be kept out of the config file.
+    #[serde(default)]
+    pub password: Option<String>,
+    /// How long an action may wait on OBS before it is abandoned (ms)
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_host() -> String {
+    "localhost".to_string()
+}
+
+fn default_port() -> u16 {
+    4455
+}
+
+fn default_timeout_ms() -> u64 {
+    2000
+}
+
+impl Default for ObsSettings {
+    fn default() -> Self {
+        Self {
+            host: default_host(),
+            port: default_port(),
+            password: None,
+            timeout_ms: default_timeout_ms(),
+        }
+    }
+}
+
+/// An OBS operation bound to a key, e.g. `{ obs = { scene = "Gaming" } }`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ObsRequest {
+    /// Switch the program scene
+    Scene(String),
+    /// Start or stop an output
+    Toggle(ObsToggle),
+    /// Toggle mute on an audio input by name (e.g. "Mic/Aux")
+    ToggleMute(String),
+}
+
+/// Outputs that `toggle` can start and stop
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ObsToggle {
+    Stream,
+    Record,
+    RecordPause,
+    VirtualCam,
+    ReplayBuffer,
+}
+
+impl ObsRequest {
+    /// The obs-websocket `requestType` and `requestData` for this request
+    fn to_request(&self) -> (&'static str, Option<Value>) {
+        match self {
+            ObsRequest::Scene(scene) => (
+                "SetCurrentProgramScene",
+                Some(json!({ "sceneName": scene })),
+            ),
+            ObsRequest::Toggle(output) => {
+                let request_type = match output {
+                    ObsToggle::Stream => "ToggleStream",
+                    ObsToggle::Record => "ToggleRecord",
+                    ObsToggle::RecordPause => "ToggleRecordPause",
+                    ObsToggle::VirtualCam => "ToggleVirtualCam",
+                    ObsToggle::ReplayBuffer => "ToggleReplayBuffer",
+                };
+                (request_type, None)
+            }
+            ObsRequest::ToggleMute(input) => {
+                ("ToggleInputMute", Some(json!({ "inputName": input })))
+            }
+        }
+    }
+}
+
+/// The obs-websocket client of one platform: its settings and open connection
+#[derive(Default)]
+pub struct ObsClient {
+    settings: RwLock<ObsSettings>,
+    /// The open connection, if any. Taken out while in use so a request that
+    /// fails or times out halfway never leaves a half-read connection behind.
+    connection: tokio::sync::Mutex<Option<ObsConnection>>,
+}
+
+impl ObsClient {
+    /// Use `settings` for future requests, dropping any connection opened with the old ones
+    pub fn configure(&self, settings: ObsSettings) {
+        *self.settings.write().unwrap_or_else(|e| e.into_inner()) = settings;
+        if let Ok(mut connection) = self.connection.try_lock() {
+            *connection = None;
+        }
+    }
+
+    /// Send a request to OBS on a background task. Failures are logged, never
+    /// reported back to the caller, so the input hook is never held up by OBS.
+    pub fn send(self: &Arc<Self>, request: ObsRequest) {
+        let client = Arc::clone(self);
+        let settings = self
+            .settings
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+
+        tokio::spawn(async move {
+            let timeout = Duration::from_millis(settings.timeout_ms);
+            match tokio::time::timeout(timeout, client.execute(&settings, &request)).await {
+                Ok(Ok(())) => debug!(?request, "obs request succeeded"),
+                Ok(Err(e)) => warn!(?request, "obs request failed: {e:#}"),
+                Err(_) => warn!(?request, "obs request timed out"),
+            }
+        });
+    }
+
+    async fn execute(&self, settings: &ObsSettings, request: &ObsRequest) -> Result<()> {
+        let mut slot = self.connection.lock().await;
+
+        // OBS may have closed the cached connection since it was last used, so a
+        // failure there gets one retry on a fresh connection
+        if let Some(mut connection) = slot.take() {
+            match connection.call(request).await {
+                Ok(status) => {
+                    *slot = Some(connection);
+                    return check_status(&status);
+                }
+                Err(e) => debug!("obs connection lost, reconnecting: {e:#}"),
+            }
+        }
+
+        let mut connection = ObsConnection::connect(settings).await?;
+        let status = connection.call(request).await?;
+        *slot = Some(connection);
+        check_status(&status)
+    }
+}
+
+/// Turn a `requestStatus` object into an error if the request failed
+fn check_status(status: &Value) -> Result<()> {
+    if status.get("result").and_then(Value::as_bool) == Some(true) {
+        return Ok(());
+    }
+    let code = status
+        .get("code")
+        .and_then(Value::as_i64)
+        .unwrap_or_default();
+    let comment = status
+        .get("comment")
+        .and_then(Value::as_str)
+        .unwrap_or("no details");
+    Err(anyhow!("OBS rejected request (code {code}): {comment}"))
+}
+
+// ============================================================================
+// Protocol
+// ============================================================================
+
+/// obs-websocket message opcodes
+mod op {
+    pub const HELLO: u64 = 0;
+    pub const IDENTIFY: u64 = 1;
+    pub const IDENTIFIED: u64 = 2;
+    pub const REQUEST: u64 = 6;
+    pub const REQUEST_RESPONSE: u64 = 7;
+}
+
+type Socket = WebSocketStream<TcpStream>;
+
+/// An identified obs-websocket session
+struct ObsConnection {
+    socket: Socket,
+    next_request_id: u64,
+}
+
+impl ObsConnection {
+    /// Connect, complete the WebSocket handshake and identify (authenticating if required)
+    async fn connect(settings: &ObsSettings) -> Result<Self> {
+        let stream = TcpStream::connect((settings.host.as_str(), settings.port))
+            .await
+            .with_context(|| {
+                format!(
+                    "failed to connect to OBS at {}:{}",
+                    settings.host, settings.port
+                )
+            })?;
+        let mut request = format!("ws://{}:{}/", settings.host, settings.port)
+            .into_client_request()
+            .context("invalid OBS address")?;
+        request.headers_mut().insert(
+            SEC_WEBSOCKET_PROTOCOL,
+            HeaderValue::from_static("obswebsocket.json"),
+        );
+        let (mut socket, _) = tokio_tungstenite::client_async(request, stream)
+            .await
+            .context("WebSocket upgrade refused")?;
+
+        let hello = read_message(&mut socket).await?;
+        if hello.get("op").and_then(Value::as_u64) != Some(op::HELLO) {
+            return Err(anyhow!("expected Hello from OBS, got {hello}"));
+        }
+
+        // No events are needed, only request responses
+        let mut identify = json!({ "rpcVersion": 1, "eventSubscriptions": 0 });
+        if let Some(auth) = hello.get("d").and_then(|d| d.get("authentication")) {
+            let password = settings
+                .password
+                .clone()
+                .or_else(|| std::env::var("OBS_WEBSOCKET_PASSWORD").ok())
+                .ok_or_else(|| {
+                    anyhow!("OBS requires a password: set password in [settings.obs]")
+                })?;
+            let challenge = auth.get("challenge").and_then(Value::as_str).unwrap_or("");
+            let salt = auth.get("salt").and_then(Value::as_str).unwrap_or("");
+            identify["authentication"] = auth_response(&password, salt, challenge).into();
+        }
+        send_message(&mut socket, &json!({ "op": op::IDENTIFY, "d": identify })).await?;
+
+        // A wrong password makes OBS close the connection instead of answering
+        let identified = read_message(&mut socket).await?;
+        if identified.get("op").and_then(Value::as_u64) != Some(op::IDENTIFIED) {
+            return Err(anyhow!("expected Identified from OBS, got {identified}"));
+        }
+
+        debug!(
+            host = settings.host,
+            port = settings.port,
+            "connected to OBS"
+        );
+        Ok(Self {
+            socket,
+            next_request_id: 1,
+        })
+    }
+
+    /// Send a request and wait for its `requestStatus`
+    async fn call(&mut self, request: &ObsRequest) -> Result<Value> {
+        let request_id = self.next_request_id.to_string();
+        self.next_request_id += 1;
+
+        let (request_type, request_data) = request.to_request();
+        let mut data = json!({ "requestType": request_type, "requestId": request_id });
+        if let Some(request_data) = request_data {
+            data["requestData"] = request_data;
+        }
+        send_message(&mut self.socket, &json!({ "op": op::REQUEST, "d": data })).await?;
+
+        loop {
+            let message = read_message(&mut self.socket).await?;
+            let Some(d) = message.get("d") else {
+                continue;
+            };
+            if message.get("op").and_then(Value::as_u64) == Some(op::REQUEST_RESPONSE)
+                && d.get("requestId").and_then(Value::as_str) == Some(request_id.as_str())
+            {
+                return d
+                    .get("requestStatus")
+                    .cloned()
+                    .ok_or_else(|| anyhow!("OBS response has no requestStatus"));
+            }
+        }
+    }
+}
+
+/// Compute the Identify `authentication` string:
+/// `base64(sha256(base64(sha256(password + salt)) + challenge))`
+fn auth_response(password: &str, salt: &str, challenge: &str) -> String {
+    let secret = BASE64.encode(Sha256::digest(format!("{password}{salt}")));
+    BASE64.encode(Sha256::digest(format!("{secret}{challenge}")))
+}
+
+async fn send_message(socket: &mut Socket, message: &Value) -> Result<()> {
+    socket.send(Message::text(message.to_string())).await?;
+    Ok(())
+}
+
+/// Read the next text message. Pings are answered by the WebSocket layer.
+async fn read_message(socket: &mut Socket) -> Result<Value> {
+    loop {
+        match socket.next().await {
+            Some(Ok(Message::Text(text))) => {
+                return serde_json::from_str(&text)
+                    .map_err(|e| anyhow!("invalid message from OBS: {e}"));
+            }
+            Some(Ok(Message::Close(frame))) => {
+                let (code, reason) = frame
+                    .map(|frame| (u16::from(frame.code), frame.reason.to_string()))
+                    .unwrap_or_default();
+                return Err(anyhow!("connection closed by OBS ({code}: {reason})"));
+            }
+            Some(Ok(_)) => {}
+            Some(Err(e)) => return Err(e.into()),
+            None => return Err(anyhow!("connection closed by OBS")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+
+    #[test]
+    fn test_auth_response() {
+        assert_eq!(
+            auth_response("hunter2", "sa", "ch"),
+            "8r0eL9SCCryjZ20fqXbxtf+J3EWkQ5tsbbBw7vihjXo="
+        );
+    }
+
+    #[test]
+    fn test_parse_requests() {
+        let parse = |s: &str| {
+            let value: toml::Value = toml::from_str::<toml::Table>(s).unwrap()["obs"].clone();
+            ObsRequest::deserialize(value)
+        };
+        assert_eq!(
+            parse(r#"obs = { scene = "Gaming" }"#).unwrap(),
+            ObsRequest::Scene("Gaming".to_string())
+        );
+        assert_eq!(
+            parse(r#"obs = { toggle = "replay_buffer" }"#).unwrap(),
+            ObsRequest::Toggle(ObsToggle::ReplayBuffer)
+        );
+        assert_eq!(
+            parse(r#"obs = { toggle_mute = "Mic/Aux" }"#).unwrap(),
+            ObsRequest::ToggleMute("Mic/Aux".to_string())
+        );
+        assert!(parse(r#"obs = { toggle = "lights" }"#).is_err());
+        assert!(parse(r#"obs = { transition = "Fade" }"#).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_authenticated_request() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        // Minimal obs-websocket server: demand auth, then fail the one request
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut socket = tokio_tungstenite::accept_hdr_async(
+                stream,
+                |request: &Request, mut response: Response| {
+                    let protocol = request.headers().get(SEC_WEBSOCKET_PROTOCOL).unwrap();
+                    assert_eq!(protocol, "obswebsocket.json");
+                    response
+                        .headers_mut()
+                        .insert(SEC_WEBSOCKET_PROTOCOL, protocol.clone());
+                    Ok(response)
+                },
+            )
+            .await
+            .unwrap();
+
+            let hello = json!({
+                "op": 0,
+                "d": { "rpcVersion": 1, "authentication": { "challenge": "ch", "salt": "sa" } },
+            });
+            send_message(&mut socket, &hello).await.unwrap();
+
+            let identify = read_message(&mut socket).await.unwrap();
+            assert_eq!(
+                identify
+                    .get("d")
+                    .and_then(|d| d.get("authentication"))
+                    .and_then(Value::as_str),
+                Some(auth_response("hunter2", "sa", "ch").as_str())
+            );
+            let identified = json!({ "op": 2, "d": { "negotiatedRpcVersion": 1 } });
+            send_message(&mut socket, &identified).await.unwrap();
+
+            let request = read_message(&mut socket).await.unwrap();
+            let d = request.get("d").unwrap();
+            assert_eq!(
+                d.get("requestType").and_then(Value::as_str),
+                Some("SetCurrentProgramScene")
+            );
+            assert_eq!(
+                d.get("requestData")
+                    .and_then(|data| data.get("sceneName"))
+                    .and_then(Value::as_str),
+                Some("Gaming")
+            );
+            let response = json!({
+                "op": 7,
+                "d": {
+                    "requestId": d["requestId"],
+                    "requestStatus": {
+                        "result": false,
+                        "code": 600,
+                        "comment": "No source was found",
+                    },
+                },
+            });
+            send_message(&mut socket, &response).await.unwrap();
+        });
+
+        let settings = ObsSettings {
+            host: "127.0.0.1".to_string(),
+            port,
+            password: Some("hunter2".to_string()),
+            ..Default::default()
+        };
+        let mut connection = ObsConnection::connect(&settings).await.unwrap();
+        let status = connection
+            .call(&ObsRequest::Scene("Gaming".to_string()))
+            .await
+            .unwrap();
+        let err = check_status(&status).unwrap_err();
+        assert!(err.to_string().contains("No source was found"));
+
+        server.await.unwrap();
+    }
+}
@@ -0,0 +1,183 @@
+//! Shell command action
+//!
+//! `{ run = "notify-send paused" }` runs a command line through the system shell
+//! (`sh -c` on Unix, `cmd /C` on Windows) without waiting for it to finish.
+//! `{ run = { command = "...", shell = "powershell" } }` picks the shell instead.
+//! Output is discarded; a failing exit status is logged.
+//!
+//! On Windows the child is created without a console window, so scripts run
+//! without a console flashing up.
+
+use serde::Deserialize;
+use tokio::process::Command;
+use tracing::{debug, warn};
+
+/// Shell that interprets a command line
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Shell {
+    /// `sh` on Unix, `cmd` on Windows
+    #[default]
+    System,
+    Sh,
+    Cmd,
+    /// Windows PowerShell (`powershell.exe`)
+    Powershell,
+    /// PowerShell 7+ (`pwsh`)
+    Pwsh,
+}
+
+/// A command line bound to a key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShellCommand {
+    pub command: String,
+    pub shell: Shell,
+}
+
+impl ShellCommand {
+    /// Build a command from `run = "..."` or `run = { command = "...", shell = "..." }`
+    pub fn from_args(args: &toml::Value) -> Result<Self, String> {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Args {
+            command: String,
+            #[serde(default)]
+            shell: Shell,
+        }
+
+        let args = match args {
+            toml::Value::String(command) => Args {
+                command: command.clone(),
+                shell: Shell::System,
+            },
+            args => Args::deserialize(args.clone()).map_err(|e| e.message().to_string())?,
+        };
+        if args.command.trim().is_empty() {
+            return Err("command must not be empty".to_string());
+        }
+        Ok(Self {
+            command: args.command,
+            shell: args.shell,
+        })
+    }
+
+    fn to_command(&self) -> Command {
+        let shell = match self.shell {
+            Shell::System if cfg!(windows) => Shell::Cmd,
+            Shell::System => Shell::Sh,
+            shell => shell,
+        };
+        let mut cmd = match shell {
+            Shell::Sh | Shell::System => {
+                let mut cmd = Command::new("sh");
+                cmd.arg("-c");
+                cmd
+            }
+            Shell::Cmd => {
+                let mut cmd = Command::new("cmd");
+                cmd.arg("/C");
+                cmd
+            }
+            Shell::Powershell | Shell::Pwsh => {
+                let program = if shell == Shell::Pwsh {
+                    "pwsh"
+                } else {
+                    "powershell"
+                };
+                let mut cmd = Command::new(program);
+                cmd.args([
+                    "-NoProfile",
+                    "-NonInteractive",
+                    "-WindowStyle",
+                    "Hidden",
+                    "-ExecutionPolicy",
+                    "Bypass",
+                    "-Command",
+                ]);
+                cmd
+            }
+        };
+        cmd.arg(&self.command);
+
+        #[cfg(windows)]
+        {
+            const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+            cmd.creation_flags(CREATE_NO_WINDOW);
+        }
+        cmd
+    }
+}
+
+/// Start the command on a background task
+pub fn run(command: ShellCommand) {
+    tokio::spawn(async move {
+        let status = command
+            .to_command()
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()
+            .await;
+        let shell = command.shell;
+        let command = command.command;
+        match status {
+            Ok(status) if status.success() => debug!(command, ?shell, "command finished"),
+            Ok(status) => warn!(command, ?shell, %status, "command failed"),
+            Err(e) => warn!(command, ?shell, "failed to run command: {e}"),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> toml::Value {
+        toml::from_str::<toml::Table>(s).unwrap()["run"].clone()
+    }
+
+    #[test]
+    fn test_shell_command_from_args() {
+        assert_eq!(
+            ShellCommand::from_args(&args(r#"run = "notify-send hi""#)).unwrap(),
+            ShellCommand {
+                command: "notify-send hi".to_string(),
+                shell: Shell::System,
+            }
+        );
+        assert_eq!(
+            ShellCommand::from_args(&args(
+                r#"run = { command = "Get-Process | Out-Null", shell = "powershell" }"#
+            ))
+            .unwrap()
+            .shell,
+            Shell::Powershell
+        );
+
+        for spec in [
+            r#"run = """#,
+            r#"run = { command = "x", shell = "fish" }"#,
+            r#"run = { shell = "cmd" }"#,
+            r#"run = { command = "x", hidden = true }"#,
+        ] {
+            assert!(
+                ShellCommand::from_args(&args(spec)).is_err(),
+                "expected error for {spec}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_powershell_arguments() {
+        let command = ShellCommand {
+            command: "Write-Output hi".to_string(),
+            shell: Shell::Pwsh,
+        };
+        let cmd = command.to_command();
+        let std_cmd = cmd.as_std();
+        assert_eq!(std_cmd.get_program(), "pwsh");
+        let args: Vec<_> = std_cmd.get_args().collect();
+        assert_eq!(args.last().unwrap(), &"Write-Output hi");
+        assert!(args.contains(&std::ffi::OsStr::new("-NonInteractive")));
+    }
+}
@@ -0,0 +1,164 @@
+//! Generic HTTP webhook action
+//!
+//! `{ http = { url = "...", method = "POST", body = "...", headers = { ... } } }`
+//! sends one request on a background task. The response body is ignored; non-2xx
+//! statuses are logged.
+
+use super::http::{self, Url};
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// A request bound to a key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Webhook {
+    pub method: String,
+    pub url: Url,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+    pub timeout_ms: u64,
+}
+
+impl Webhook {
+    /// Build a webhook from its action table. `method` defaults to POST when a
+    /// body is given and GET otherwise.
+    pub fn from_args(args: &toml::Value) -> Result<Self, String> {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Args {
+            url: String,
+            #[serde(default)]
+            method: Option<String>,
+            #[serde(default)]
+            headers: BTreeMap<String, String>,
+            #[serde(default)]
+            body: Option<String>,
+            #[serde(default = "default_timeout_ms")]
+            timeout_ms: u64,
+        }
+
+        let args = Args::deserialize(args.clone()).map_err(|e| e.message().to_string())?;
+        let url = Url::parse(&args.url)?;
+
+        let method = match args.method {
+            Some(method) => method.to_ascii_uppercase(),
+            None if args.body.is_some() => "POST".to_string(),
+            None => "GET".to_string(),
+        };
+        if method.is_empty() || !method.bytes().all(|b| b.is_ascii_alphabetic()) {
+            return Err(format!("invalid method '{method}'"));
+        }
+        for (name, value) in &args.headers {
+            if name.is_empty() || name.contains([':', '\r', '\n']) || value.contains(['\r', '\n']) {
+                return Err(format!("invalid header '{name}'"));
+            }
+        }
+
+        Ok(Self {
+            method,
+            url,
+            headers: args.headers.into_iter().collect(),
+            body: args.body.unwrap_or_default(),
+            timeout_ms: args.timeout_ms,
+        })
+    }
+}
+
+fn default_timeout_ms() -> u64 {
+    5000
+}
+
+/// Send the request on a background task, logging the outcome
+pub fn send(webhook: Webhook) {
+    tokio::spawn(async move {
+        let timeout = Duration::from_millis(webhook.timeout_ms);
+        let target = format!(
+            "{} {}:{}{}",
+            webhook.method, webhook.url.host, webhook.url.port, webhook.url.path
+        );
+        match tokio::time::timeout(timeout, execute(&webhook)).await {
+            Ok(Ok(status)) => debug!(target, status, "webhook sent"),
+            Ok(Err(e)) => warn!(target, "webhook failed: {e:#}"),
+            Err(_) => warn!(target, "webhook timed out"),
+        }
+    });
+}
+
+async fn execute(webhook: &Webhook) -> Result<u16> {
+    let headers: Vec<(&str, &str)> = webhook
+        .headers
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.as_str()))
+        .collect();
+    let response = http::request(
+        &webhook.method,
+        &webhook.url,
+        &headers,
+        webhook.body.as_bytes(),
+    )
+    .await?;
+
+    if !response.is_success() {
+        return Err(anyhow!(
+            "HTTP {}: {}",
+            response.status,
+            response.body_preview()
+        ));
+    }
+    Ok(response.status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> toml::Value {
+        toml::from_str::<toml::Table>(s).unwrap()["http"].clone()
+    }
+
+    #[test]
+    fn test_webhook_from_args() {
+        let webhook = Webhook::from_args(&args(
+            r#"http = { url = "http://nas.local:8080/hook", body = '{"key":"f13"}', headers = { "Content-Type" = "application/json" } }"#,
+        ))
+        .unwrap();
+        assert_eq!(webhook.method, "POST");
+        assert_eq!(webhook.url.host, "nas.local");
+        assert_eq!(webhook.url.path, "/hook");
+        assert_eq!(
+            webhook.headers,
+            vec![("Content-Type".to_string(), "application/json".to_string())]
+        );
+        assert_eq!(webhook.body, r#"{"key":"f13"}"#);
+        assert_eq!(webhook.timeout_ms, 5000);
+
+        let webhook = Webhook::from_args(&args(
+            r#"http = { url = "http://nas.local/ping", method = "put", timeout_ms = 500 }"#,
+        ))
+        .unwrap();
+        assert_eq!(webhook.method, "PUT");
+        assert_eq!(webhook.timeout_ms, 500);
+
+        let webhook =
+            Webhook::from_args(&args(r#"http = { url = "http://nas.local/ping" }"#)).unwrap();
+        assert_eq!(webhook.method, "GET");
+    }
+
+    #[test]
+    fn test_invalid_webhook() {
+        for spec in [
+            r#"http = { method = "POST" }"#,
+            r#"http = { url = "ftp://nas.local" }"#,
+            r#"http = { url = "http://nas.local", method = "GET /x" }"#,
+            r#"http = { url = "http://nas.local", headers = { "X-Bad" = "a\r\nInjected: 1" } }"#,
+            r#"http = { url = "http://nas.local", payload = "x" }"#,
+        ] {
+            assert!(
+                Webhook::from_args(&args(spec)).is_err(),
+                "expected error for {spec}"
+            );
+        }
+    }
+}
@@ -0,0 +1,1124 @@
+//! Platform-agnostic input event abstraction
+//!
+//! This module provides universal representations for input events that work across
+//! platforms. Key codes are platform-native (VK codes on Windows, evdev codes on
+//! Linux), and display names are queried from the platform layer.
+//!
+//! The module handles keyboard, mouse button and scroll wheel events, providing
+//! a unified `InputEvent` type for the event handler.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+
+use crate::platform;
+
+/// Platform-agnostic key code.
+///
+/// Stores the raw OS-specific key code internally. Display names are queried
+/// from the OS on demand via `display_name()`, not stored in the struct.
+///
+/// # Examples
+///
+/// ```ignore
+/// // From platform-native code
+/// let key = KeyCode::new(0x7C); // VK_F13 on Windows
+///
+/// // Display name
+/// println!("Key pressed: {}", key.display_name()); // "F13"
+///
+/// // Parse from config string
+/// let key = KeyCode::from_config_str("f13").unwrap();
+/// let key = KeyCode::from_config_str("0x7C").unwrap();
+///
+/// // Hardware scancode (Windows), for keys whose VK code is ambiguous
+/// let key = KeyCode::from_config_str("sc:0x64").unwrap();
+///
+/// // MIDI note or controller (Linux)
+/// let key = KeyCode::from_config_str("midi:note:36").unwrap();
+///
+/// // Gamepad button
+/// let key = KeyCode::from_config_str("pad:start").unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyCode(u32);
+
+/// Set on codes that hold a scancode rather than a platform key code. Both VK
+/// and evdev codes stay well below it.
+const SCANCODE_FLAG: u32 = 1 << 16;
+
+/// Set on codes that hold a MIDI input: the kind in bit 7, the note or
+/// controller number in the low 7 bits
+const MIDI_FLAG: u32 = 1 << 17;
+
+/// A MIDI message that can be bound like a key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MidiInput {
+    /// A note, pressed on note-on and released on note-off
+    Note(u8),
+    /// A continuous controller, pressed while its value is at least 64
+    Controller(u8),
+}
+
+/// Set on codes that hold a gamepad button, numbered by [`GamepadButton::ALL`]
+const GAMEPAD_FLAG: u32 = 1 << 18;
+
+/// A gamepad button, named by its place on an Xbox-style controller
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GamepadButton {
+    A,
+    B,
+    X,
+    Y,
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+    Back,
+    Start,
+    Guide,
+    LeftStick,
+    RightStick,
+    DpadUp,
+    DpadDown,
+    DpadLeft,
+    DpadRight,
+}
+
+/// Set on codes that hold a key name defined by a `[[hid]]` device, indexing
+/// [`HID_NAMES`]
+const HID_FLAG: u32 = 1 << 19;
+
+/// Key names defined by `[[hid]]` devices, in the order they were first seen.
+/// Names are never removed, so a name keeps its code across reloads.
+static HID_NAMES: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+impl GamepadButton {
+    /// Every button, in code order
+    pub const ALL: [Self; 17] = [
+        Self::A,
+        Self::B,
+        Self::X,
+        Self::Y,
+        Self::LeftBumper,
+        Self::RightBumper,
+        Self::LeftTrigger,
+        Self::RightTrigger,
+        Self::Back,
+        Self::Start,
+        Self::Guide,
+        Self::LeftStick,
+        Self::RightStick,
+        Self::DpadUp,
+        Self::DpadDown,
+        Self::DpadLeft,
+        Self::DpadRight,
+    ];
+
+    /// The name used in config after `pad:`
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::A => "a",
+            Self::B => "b",
+            Self::X => "x",
+            Self::Y => "y",
+            Self::LeftBumper => "lb",
+            Self::RightBumper => "rb",
+            Self::LeftTrigger => "lt",
+            Self::RightTrigger => "rt",
+            Self::Back => "back",
+            Self::Start => "start",
+            Self::Guide => "guide",
+            Self::LeftStick => "ls",
+            Self::RightStick => "rs",
+            Self::DpadUp => "up",
+            Self::DpadDown => "down",
+            Self::DpadLeft => "left",
+            Self::DpadRight => "right",
+        }
+    }
+
+    /// Look up a button by its config name, ignoring case
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|button| button.name().eq_ignore_ascii_case(name))
+    }
+}
+
+impl KeyCode {
+    /// Create a KeyCode from a raw platform-native code
+    pub const fn new(code: u32) -> Self {
+        Self(code)
+    }
+
+    /// Create a KeyCode for a hardware scancode. Extended scancodes carry the
+    /// `0xE0` prefix in the high byte (`0xE05B` for the left Windows key).
+    pub const fn from_scancode(scancode: u16) -> Self {
+        Self(SCANCODE_FLAG | scancode as u32)
+    }
+
+    /// The scancode, if this key was specified by one
+    pub fn scancode(&self) -> Option<u16> {
+        (self.0 & SCANCODE_FLAG != 0).then_some(self.0 as u16)
+    }
+
+    /// Create a KeyCode for a MIDI note or controller. Numbers above 127 are
+    /// masked to MIDI's 7-bit range.
+    pub const fn from_midi(input: MidiInput) -> Self {
+        match input {
+            MidiInput::Note(note) => Self(MIDI_FLAG | (note & 0x7F) as u32),
+            MidiInput::Controller(cc) => Self(MIDI_FLAG | 0x80 | (cc & 0x7F) as u32),
+        }
+    }
+
+    /// The MIDI input, if this key was specified by one
+    pub fn midi(&self) -> Option<MidiInput> {
+        if self.0 & MIDI_FLAG == 0 {
+            return None;
+        }
+        let number = (self.0 & 0x7F) as u8;
+        Some(if self.0 & 0x80 == 0 {
+            MidiInput::Note(number)
+        } else {
+            MidiInput::Controller(number)
+        })
+    }
+
+    /// Create a KeyCode for a gamepad button
+    pub const fn from_gamepad(button: GamepadButton) -> Self {
+        Self(GAMEPAD_FLAG | button as u32)
+    }
+
+    /// The gamepad button, if this key was specified by one
+    pub fn gamepad(&self) -> Option<GamepadButton> {
+        if self.0 & GAMEPAD_FLAG == 0 {
+            return None;
+        }
+        GamepadButton::ALL.get((self.0 & 0xFF) as usize).copied()
+    }
+
+    /// Create a KeyCode for a key name defined by a `[[hid]]` device,
+    /// assigning it a code the first time the name is seen
+    pub fn from_hid_name(name: &str) -> Self {
+        let mut names = HID_NAMES.write().unwrap_or_else(|e| e.into_inner());
+        let index = match names.iter().position(|known| known == name) {
+            Some(index) => index,
+            None => {
+                names.push(name.to_string());
+                names.len() - 1
+            }
+        };
+        Self(HID_FLAG | index as u32)
+    }
+
+    /// The `[[hid]]` key name, if this key was specified by one
+    pub fn hid_name(&self) -> Option<String> {
+        if self.0 & HID_FLAG == 0 {
+            return None;
+        }
+        let names = HID_NAMES.read().unwrap_or_else(|e| e.into_inner());
+        names.get((self.0 & !HID_FLAG) as usize).cloned()
+    }
+
+    /// Get the raw platform-native code
+    pub fn code(&self) -> u32 {
+        self.0
+    }
+
+    /// Whether this is a key the platform reports and can send itself, rather
+    /// than a scancode, MIDI, gamepad or HID input
+    pub fn is_platform_key(&self) -> bool {
+        self.0 & (SCANCODE_FLAG | MIDI_FLAG | GAMEPAD_FLAG | HID_FLAG) == 0
+    }
+
+    /// Get human-readable display name from the OS
+    ///
+    /// Returns OS-provided names like "F13", "Space", "Enter" on Windows,
+    /// or "KEY_F13", "KEY_SPACE" on Linux.
+    pub fn display_name(&self) -> String {
+        if let Some(button) = self.gamepad() {
+            return format!("pad:{}", button.name());
+        }
+        if let Some(name) = self.hid_name() {
+            return format!("hid:{name}");
+        }
+        match (self.scancode(), self.midi()) {
+            (Some(scancode), _) => format!("sc:{scancode:#x}"),
+            (_, Some(MidiInput::Note(note))) => format!("midi:note:{note}"),
+            (_, Some(MidiInput::Controller(cc))) => format!("midi:cc:{cc}"),
+            (None, None) => platform_key_name(self.0),
+        }
+    }
+
+    /// Parse a key specifier from config
+    ///
+    /// Accepts:
+    /// - Hex literals: "0x7C", "0X7c"
+    /// - Decimal numbers: "124"
+    /// - Key names: "f13", "KEY_F13", "space"
+    /// - Scancodes: "sc:0x64", "sc:0xE05B"
+    /// - MIDI notes and controllers: "midi:note:36", "midi:cc:64"
+    /// - Gamepad buttons: "pad:a", "pad:start", "pad:up"
+    /// - Keys of `[[hid]]` devices: "hid:pedal_left"
+    ///
+    /// Numbers are treated as raw codes. Names are looked up via the OS.
+    pub fn from_config_str(s: &str) -> Option<Self> {
+        parse_key_specifier(s)
+    }
+
+    /// Parse a key range specifier from config
+    ///
+    /// Accepts `start..end` (inclusive) where:
+    /// - Both ends are numbers: "0x7C..0x87" expands to every code in between
+    /// - Both ends are names sharing a prefix with a numeric suffix:
+    ///   "f13..f24" expands to f13, f14, ..., f24
+    ///
+    /// Returns an error message describing why the range is invalid.
+    pub fn range_from_config_str(s: &str) -> Result<Vec<Self>, String> {
+        parse_key_range(s)
+    }
+
+    /// Every key name accepted in config and the code it resolves to, sorted
+    /// by name
+    ///
+    /// Names from the active keyboard layout are included unless a
+    /// layout-independent name shadows them.
+    pub fn all_known() -> Vec<(String, Self)> {
+        all_names(&key_names())
+    }
+}
+
+impl std::fmt::Display for KeyCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.display_name())
+    }
+}
+
+/// A key event received from the platform
+#[derive(Debug, Clone)]
+pub struct KeyEvent {
+    /// The key that was pressed/released
+    pub key: KeyCode,
+    /// Whether this is a key-down (true) or key-up (false) event
+    pub down: bool,
+    /// When the platform received the event, captured on the hook or device
+    /// reader before it is queued for the handler. Strategies time holds and
+    /// taps from this rather than from when they get to run.
+    pub time: Instant,
+}
+
+impl KeyEvent {
+    /// Create a new key event happening now
+    pub fn new(key: KeyCode, down: bool) -> Self {
+        Self::at(key, down, Instant::now())
+    }
+
+    /// Create a key event that happened at `time`
+    pub fn at(key: KeyCode, down: bool, time: Instant) -> Self {
+        Self { key, down, time }
+    }
+}
+
+// ============================================================================
+// Unified Input Events
+// ============================================================================
+
+/// A unified input event from the platform (keyboard, mouse button or scroll)
+#[derive(Debug, Clone)]
+pub enum InputEvent {
+    /// A keyboard key press or release
+    Key(KeyEvent),
+    /// A mouse button press or release
+    MouseButton {
+        button: MouseButton,
+        /// Whether this is a press (true) or release (false)
+        down: bool,
+    },
+    /// A scroll wheel tick
+    Scroll {
+        /// true = scroll up (away from user), false = scroll down (toward user)
+        up: bool,
+    },
+    /// An event an earlier subscribed strategy already blocked
+    ///
+    /// Delivered to the remaining subscribers so they can keep their state
+    /// consistent (e.g. drop a pending tap) without acting on the event
+    /// themselves. Never produced by the platform.
+    Divert(Box<InputEvent>),
+}
+
+impl InputEvent {
+    /// Get the identifier for this event (for subscription matching)
+    ///
+    /// A diverted event has the identifier of the event it wraps.
+    pub fn id(&self) -> InputEventId {
+        match self {
+            InputEvent::Key(key_event) => InputEventId::Key(key_event.key),
+            InputEvent::MouseButton { button, .. } => InputEventId::MouseButton(*button),
+            InputEvent::Scroll { up } => InputEventId::Scroll { up: *up },
+            InputEvent::Divert(event) => event.id(),
+        }
+    }
+}
+
+/// A mouse button the platforms can intercept
+///
+/// The left and right buttons are deliberately absent: every click would have
+/// to wait on the daemon, and a stalled handler must never take the pointer
+/// away.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    Middle,
+    /// Side button, usually mapped to "back" (X11 button 8, `XBUTTON1`)
+    Back,
+    /// Side button, usually mapped to "forward" (X11 button 9, `XBUTTON2`)
+    Forward,
+}
+
+impl MouseButton {
+    pub const ALL: [MouseButton; 3] =
+        [MouseButton::Middle, MouseButton::Back, MouseButton::Forward];
+
+    /// The config name, e.g. `mouse_back`
+    pub fn name(self) -> &'static str {
+        match self {
+            MouseButton::Middle => "mouse_middle",
+            MouseButton::Back => "mouse_back",
+            MouseButton::Forward => "mouse_forward",
+        }
+    }
+
+    /// Parse a config name (case-insensitive)
+    pub fn from_config_str(s: &str) -> Option<Self> {
+        let normalized = s.to_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|button| button.name() == normalized)
+    }
+}
+
+/// Identifier for an input event type (for subscriptions and routing)
+///
+/// This is a hashable identifier that can represent either a specific key
+/// or a scroll direction. Used for strategy subscriptions and event routing.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum InputEventId {
+    /// A specific keyboard key
+    Key(KeyCode),
+    /// A specific mouse button
+    MouseButton(MouseButton),
+    /// A scroll wheel direction
+    Scroll {
+        /// true = scroll up, false = scroll down
+        up: bool,
+    },
+    /// Every keyboard key. Only meaningful as a subscription - events never
+    /// produce this identifier themselves.
+    AnyKey,
+}
+
+impl InputEventId {
+    /// Parse an event identifier from a config string
+    ///
+    /// Accepts:
+    /// - `"scroll_up"` → `Scroll { up: true }`
+    /// - `"scroll_down"` → `Scroll { up: false }`
+    /// - `"mouse_middle"`, `"mouse_back"`, `"mouse_forward"` → `MouseButton(..)`
+    /// - Any valid key specifier (see `KeyCode::from_config_str`)
+    pub fn from_config_str(s: &str) -> Option<Self> {
+        let normalized = s.to_lowercase();
+
+        // Check for scroll events first
+        match normalized.as_str() {
+            "scroll_up" => return Some(InputEventId::Scroll { up: true }),
+            "scroll_down" => return Some(InputEventId::Scroll { up: false }),
+            _ => {}
+        }
+        if let Some(button) = MouseButton::from_config_str(&normalized) {
+            return Some(InputEventId::MouseButton(button));
+        }
+
+        // Otherwise try to parse as a key
+        KeyCode::from_config_str(s).map(InputEventId::Key)
+    }
+}
+
+impl std::fmt::Display for InputEventId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InputEventId::Key(key) => write!(f, "{}", key.display_name()),
+            InputEventId::MouseButton(button) => write!(f, "{}", button.name()),
+            InputEventId::Scroll { up: true } => write!(f, "scroll_up"),
+            InputEventId::Scroll { up: false } => write!(f, "scroll_down"),
+            InputEventId::AnyKey => write!(f, "any_key"),
+        }
+    }
+}
+
+/// Parse a key specifier from config
+///
+/// Tries in order: scancode, MIDI input, gamepad button, HID key, hex literal,
+/// decimal number, key name lookup
+fn parse_key_specifier(s: &str) -> Option<KeyCode> {
+    // Try scancode: "sc:0x64"
+    if let Some(scancode) = s.strip_prefix("sc:").or_else(|| s.strip_prefix("SC:")) {
+        let scancode = parse_numeric_code(scancode.trim()).and_then(|c| u16::try_from(c).ok())?;
+        return (scancode != 0).then(|| KeyCode::from_scancode(scancode));
+    }
+
+    // Try MIDI: "midi:note:36", "midi:cc:64"
+    if let Some(midi) = s.strip_prefix("midi:").or_else(|| s.strip_prefix("MIDI:")) {
+        let (kind, number) = midi.split_once(':')?;
+        let number = parse_numeric_code(number.trim()).and_then(|n| u8::try_from(n).ok())?;
+        if number > 0x7F {
+            return None;
+        }
+        let input = match kind.to_lowercase().as_str() {
+            "note" => MidiInput::Note(number),
+            "cc" => MidiInput::Controller(number),
+            _ => return None,
+        };
+        return Some(KeyCode::from_midi(input));
+    }
+
+    // Try gamepad button: "pad:start"
+    if let Some(button) = s.strip_prefix("pad:").or_else(|| s.strip_prefix("PAD:")) {
+        return GamepadButton::from_name(button.trim()).map(KeyCode::from_gamepad);
+    }
+
+    // Try a key defined by a [[hid]] device: "hid:pedal_left"
+    if let Some(name) = s.strip_prefix("hid:").or_else(|| s.strip_prefix("HID:")) {
+        let names = HID_NAMES.read().unwrap_or_else(|e| e.into_inner());
+        let index = names.iter().position(|known| known == name)?;
+        return Some(KeyCode(HID_FLAG | index as u32));
+    }
+
+    // Try hex: "0x7C" -> 124
+    if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X"))
+        && let Ok(code) = u32::from_str_radix(hex, 16)
+    {
+        return Some(KeyCode(code));
+    }
+
+    // Try decimal if all digits: "124" -> 124
+    if s.chars().all(|c| c.is_ascii_digit())
+        && let Ok(code) = s.parse::<u32>()
+    {
+        return Some(KeyCode(code));
+    }
+
+    // Otherwise treat as name: "f13", "KEY_F13", etc.
+    platform_key_from_name(s)
+}
+
+/// Upper bound on keys produced by a single range, guarding against typos
+/// like "0..0xFFFFFFFF" expanding into billions of bindings
+const MAX_RANGE_LEN: u32 = 256;
+
+/// Parse a numeric key code (hex or decimal), without falling back to names
+fn parse_numeric_code(s: &str) -> Option<u32> {
+    if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        return u32::from_str_radix(hex, 16).ok();
+    }
+    if !s.is_empty() && s.chars().all(|c| c.is_ascii_digit()) {
+        return s.parse::<u32>().ok();
+    }
+    None
+}
+
+/// Split a key name into its alphabetic prefix and trailing number: "f13" -> ("f", 13)
+fn split_numeric_suffix(s: &str) -> Option<(&str, u32)> {
+    let digits_start = s.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    if digits_start == s.len() {
+        return None;
+    }
+    let (prefix, digits) = s.split_at(digits_start);
+    Some((prefix, digits.parse().ok()?))
+}
+
+/// Parse a key range specifier: "f13..f24" or "0x7C..0x87"
+fn parse_key_range(s: &str) -> Result<Vec<KeyCode>, String> {
+    let Some((start, end)) = s.split_once("..") else {
+        return Err(format!("'{s}' is not a range (expected start..end)"));
+    };
+    let (start, end) = (start.trim(), end.trim());
+    if start.is_empty() || end.is_empty() {
+        return Err(format!("range '{s}' is missing a start or end"));
+    }
+
+    // Numeric range: every raw code between the two ends
+    if let (Some(lo), Some(hi)) = (parse_numeric_code(start), parse_numeric_code(end)) {
+        check_range_bounds(s, lo, hi)?;
+        return Ok((lo..=hi).map(KeyCode).collect());
+    }
+
+    // Named range: shared prefix, numeric suffix resolved one name at a time
+    let (Some((start_prefix, lo)), Some((end_prefix, hi))) =
+        (split_numeric_suffix(start), split_numeric_suffix(end))
+    else {
+        return Err(format!(
+            "range '{s}' must use numeric codes or names ending in a number"
+        ));
+    };
+    if !start_prefix.eq_ignore_ascii_case(end_prefix) {
+        return Err(format!(
+            "range '{s}' mixes prefixes '{start_prefix}' and '{end_prefix}'"
+        ));
+    }
+    check_range_bounds(s, lo, hi)?;
+
+    (lo..=hi)
+        .map(|n| {
+            let name = format!("{start_prefix}{n}");
+            parse_key_specifier(&name)
+                .ok_or_else(|| format!("range '{s}' includes unknown key '{name}'"))
+        })
+        .collect()
+}
+
+/// Reject reversed or oversized ranges
+fn check_range_bounds(s: &str, lo: u32, hi: u32) -> Result<(), String> {
+    if lo > hi {
+        return Err(format!("range '{s}' is reversed (start is after end)"));
+    }
+    if hi - lo >= MAX_RANGE_LEN {
+        return Err(format!(
+            "range '{s}' covers {} keys (maximum is {MAX_RANGE_LEN})",
+            u64::from(hi - lo) + 1
+        ));
+    }
+    Ok(())
+}
+
+// ============================================================================
+// Platform-agnostic wrappers
+// ============================================================================
+
+fn platform_key_name(code: u32) -> String {
+    platform::get_key_name(code)
+}
+
+/// Key names accepted in config
+///
+/// Layout-independent names always resolve to the same code. Names taken from
+/// the active keyboard layout follow it, and never shadow a layout-independent
+/// name.
+#[derive(Debug, Default)]
+struct KeyNames {
+    fixed: HashMap<String, u32>,
+    layout: HashMap<String, u32>,
+}
+
+impl KeyNames {
+    fn build() -> Self {
+        Self {
+            fixed: platform::build_key_name_map(),
+            layout: platform::build_layout_key_name_map(),
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<u32> {
+        self.fixed
+            .get(name)
+            .or_else(|| self.layout.get(name))
+            .copied()
+    }
+
+    /// Layout names that resolve, i.e. aren't shadowed by a fixed name
+    fn layout_names(&self) -> impl Iterator<Item = (&String, &u32)> {
+        self.layout
+            .iter()
+            .filter(|(name, _)| !self.fixed.contains_key(*name))
+    }
+}
+
+/// Name maps, built on first use and rebuilt when the keyboard layout changes
+static KEY_NAMES: RwLock<Option<Arc<KeyNames>>> = RwLock::new(None);
+
+fn key_names() -> Arc<KeyNames> {
+    if let Some(names) = &*KEY_NAMES.read().unwrap_or_else(|e| e.into_inner()) {
+        return Arc::clone(names);
+    }
+    let mut slot = KEY_NAMES.write().unwrap_or_else(|e| e.into_inner());
+    Arc::clone(slot.get_or_insert_with(|| Arc::new(KeyNames::build())))
+}
+
+/// Rebuild the layout-dependent names after a keyboard layout change.
+/// Returns whether any name now resolves differently.
+pub fn refresh_layout_names() -> bool {
+    let layout = platform::build_layout_key_name_map();
+    let mut slot = KEY_NAMES.write().unwrap_or_else(|e| e.into_inner());
+    let Some(current) = slot.as_ref() else {
+        // Nothing resolved yet; the first lookup builds from the new layout
+        return false;
+    };
+    if current.layout == layout {
+        return false;
+    }
+    *slot = Some(Arc::new(KeyNames {
+        fixed: current.fixed.clone(),
+        layout,
+    }));
+    true
+}
+
+fn platform_key_from_name(name: &str) -> Option<KeyCode> {
+    key_names().get(&name.to_lowercase()).map(KeyCode)
+}
+
+/// The shortest name that resolves to `code` in config, preferring names that
+/// don't depend on the keyboard layout, or a hex literal if the code has no name.
+/// Digit names like "1" are skipped since config reads them as codes.
+pub fn config_name(code: KeyCode) -> String {
+    preferred_name(&key_names(), code)
+}
+
+fn preferred_name(names: &KeyNames, code: KeyCode) -> String {
+    // Scancodes, MIDI, gamepad and HID keys are always written with their prefix
+    if !code.is_platform_key() {
+        return code.display_name();
+    }
+    shortest_name(names.fixed.iter(), code)
+        .or_else(|| shortest_name(names.layout_names(), code))
+        .unwrap_or_else(|| format!("{:#x}", code.0))
+}
+
+fn shortest_name<'a>(
+    names: impl Iterator<Item = (&'a String, &'a u32)>,
+    code: KeyCode,
+) -> Option<String> {
+    names
+        .filter(|&(name, &c)| c == code.0 && parse_numeric_code(name).is_none())
+        .map(|(name, _)| name)
+        .min_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)))
+        .cloned()
+}
+
+fn all_names(names: &KeyNames) -> Vec<(String, KeyCode)> {
+    let mut all: Vec<(String, KeyCode)> = names
+        .fixed
+        .iter()
+        .chain(names.layout_names())
+        .map(|(name, &code)| (name.clone(), KeyCode(code)))
+        .collect();
+    all.sort_by(|(a, _), (b, _)| a.cmp(b));
+    all
+}
+
+/// The known key name closest to an unrecognized one, for "did you mean" hints
+pub fn suggest_key_name(name: &str) -> Option<String> {
+    closest_name(&KeyCode::all_known(), name)
+}
+
+/// The nearest name by edit distance, if it is close enough to be a typo.
+/// Ties go to the shorter name, then alphabetically.
+fn closest_name(known: &[(String, KeyCode)], name: &str) -> Option<String> {
+    let name = name.to_lowercase();
+    // A swapped pair of letters is two edits; allow that once there's enough
+    // of the name left to be sure what was meant
+    let max_distance = if name.chars().count() < 4 { 1 } else { 2 };
+    known
+        .iter()
+        .map(|(candidate, _)| (edit_distance(&name, candidate), candidate))
+        .filter(|&(distance, _)| distance <= max_distance)
+        .min_by(|(da, a), (db, b)| {
+            da.cmp(db)
+                .then_with(|| a.len().cmp(&b.len()))
+                .then_with(|| a.cmp(b))
+        })
+        .map(|(_, candidate)| candidate.clone())
+}
+
+/// Levenshtein distance between two strings, by character
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// The names that resolve to one key code
+#[derive(Debug, PartialEq, Eq)]
+pub struct KeyNameGroup {
+    pub code: KeyCode,
+    /// Names that resolve to this code under any keyboard layout
+    pub names: Vec<String>,
+    /// Names from the active keyboard layout, which may move or disappear
+    /// when the layout changes
+    pub layout_names: Vec<String>,
+}
+
+/// Every key name accepted in config, grouped by the code it resolves to
+///
+/// Codes are sorted ascending and each code's names alphabetically. With a
+/// filter, only codes with at least one name containing it (case-insensitive)
+/// are returned.
+pub fn known_key_names(filter: Option<&str>) -> Vec<KeyNameGroup> {
+    group_key_names(&key_names(), filter)
+}
+
+fn group_key_names(names: &KeyNames, filter: Option<&str>) -> Vec<KeyNameGroup> {
+    fn group(by_code: &mut BTreeMap<u32, KeyNameGroup>, code: u32) -> &mut KeyNameGroup {
+        by_code.entry(code).or_insert_with(|| KeyNameGroup {
+            code: KeyCode(code),
+            names: Vec::new(),
+            layout_names: Vec::new(),
+        })
+    }
+
+    let mut by_code = BTreeMap::new();
+    for (name, &code) in &names.fixed {
+        group(&mut by_code, code).names.push(name.clone());
+    }
+    for (name, &code) in names.layout_names() {
+        group(&mut by_code, code).layout_names.push(name.clone());
+    }
+
+    let filter = filter.map(str::to_lowercase);
+    by_code
+        .into_values()
+        .filter(|group| {
+            filter.as_deref().is_none_or(|f| {
+                group
+                    .names
+                    .iter()
+                    .chain(&group.layout_names)
+                    .any(|name| name.contains(f))
+            })
+        })
+        .map(|mut group| {
+            group.names.sort();
+            group.layout_names.sort();
+            group
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hex() {
+        let key = parse_key_specifier("0x7C").unwrap();
+        assert!(key.0 == 124);
+
+        let key = parse_key_specifier("0X7c").unwrap();
+        assert!(key.0 == 124);
+    }
+
+    #[test]
+    fn test_parse_decimal() {
+        let key = parse_key_specifier("124").unwrap();
+        assert!(key.0 == 124);
+
+        let key = parse_key_specifier("48").unwrap();
+        assert!(key.0 == 48);
+    }
+
+    #[test]
+    fn test_parse_scancode() {
+        let key = parse_key_specifier("sc:0x64").unwrap();
+        assert_eq!(key.scancode(), Some(0x64));
+        assert_ne!(key, KeyCode::new(0x64));
+        assert_eq!(key.display_name(), "sc:0x64");
+
+        let key = parse_key_specifier("SC:0xE05B").unwrap();
+        assert_eq!(key, KeyCode::from_scancode(0xE05B));
+        assert_eq!(key.to_string(), "sc:0xe05b");
+        assert_eq!(
+            parse_key_specifier("sc:100"),
+            Some(KeyCode::from_scancode(100))
+        );
+        assert_eq!(KeyCode::new(0x7C).scancode(), None);
+
+        for invalid in ["sc:", "sc:0", "sc:0x10000", "sc:f13"] {
+            assert_eq!(parse_key_specifier(invalid), None, "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_parse_midi() {
+        let key = parse_key_specifier("midi:note:36").unwrap();
+        assert_eq!(key.midi(), Some(MidiInput::Note(36)));
+        assert_eq!(key.scancode(), None);
+        assert_eq!(key.display_name(), "midi:note:36");
+        assert_eq!(config_name(key), "midi:note:36");
+
+        let key = parse_key_specifier("MIDI:CC:0x40").unwrap();
+        assert_eq!(key, KeyCode::from_midi(MidiInput::Controller(64)));
+        assert_eq!(key.to_string(), "midi:cc:64");
+        assert_ne!(key, KeyCode::from_midi(MidiInput::Note(64)));
+        assert_eq!(KeyCode::new(0x7C).midi(), None);
+        assert_eq!(KeyCode::from_scancode(0xE05B).midi(), None);
+
+        for invalid in [
+            "midi:",
+            "midi:note",
+            "midi:note:128",
+            "midi:pitch:1",
+            "midi:cc:x",
+        ] {
+            assert_eq!(parse_key_specifier(invalid), None, "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_parse_gamepad() {
+        let key = parse_key_specifier("pad:start").unwrap();
+        assert_eq!(key.gamepad(), Some(GamepadButton::Start));
+        assert_eq!(key.display_name(), "pad:start");
+        assert_eq!(config_name(key), "pad:start");
+        assert_eq!(key.midi(), None);
+        assert_eq!(key.scancode(), None);
+
+        for button in GamepadButton::ALL {
+            let key = KeyCode::from_gamepad(button);
+            assert_eq!(parse_key_specifier(&key.to_string()), Some(key));
+        }
+        assert_eq!(
+            parse_key_specifier("PAD:LB"),
+            Some(KeyCode::from_gamepad(GamepadButton::LeftBumper))
+        );
+        assert_eq!(KeyCode::new(0x130).gamepad(), None);
+
+        for invalid in ["pad:", "pad:z", "pad:0"] {
+            assert_eq!(parse_key_specifier(invalid), None, "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_parse_hid() {
+        assert_eq!(parse_key_specifier("hid:test_pedal"), None);
+        let key = KeyCode::from_hid_name("test_pedal");
+        assert_eq!(KeyCode::from_hid_name("test_pedal"), key);
+        assert_ne!(KeyCode::from_hid_name("test_pedal_2"), key);
+        assert_eq!(parse_key_specifier("hid:test_pedal"), Some(key));
+        assert_eq!(key.hid_name().as_deref(), Some("test_pedal"));
+        assert_eq!(config_name(key), "hid:test_pedal");
+        assert_eq!(key.gamepad(), None);
+        assert_eq!(KeyCode::new(0x7C).hid_name(), None);
+    }
+
+    #[test]
+    fn test_parse_name() {
+        // This test will vary by platform, so just verify it doesn't panic
+        let _ = parse_key_specifier("f13");
+        let _ = parse_key_specifier("KEY_F13");
+    }
+
+    #[test]
+    fn test_parse_numeric_range() {
+        let keys = KeyCode::range_from_config_str("0x7C..0x7E").unwrap();
+        assert_eq!(
+            keys,
+            vec![KeyCode::new(0x7C), KeyCode::new(0x7D), KeyCode::new(0x7E)]
+        );
+
+        // Single-key range is allowed
+        let keys = KeyCode::range_from_config_str("124..124").unwrap();
+        assert_eq!(keys, vec![KeyCode::new(124)]);
+    }
+
+    #[test]
+    fn test_parse_named_range() {
+        // Names resolve through the platform map; every name in the range must exist
+        #[cfg(unix)]
+        {
+            let keys = KeyCode::range_from_config_str("f13..f15").unwrap();
+            assert_eq!(keys.len(), 3);
+            assert_eq!(keys[0], KeyCode::from_config_str("f13").unwrap());
+            assert_eq!(keys[2], KeyCode::from_config_str("f15").unwrap());
+        }
+    }
+
+    #[test]
+    fn test_parse_invalid_ranges() {
+        assert!(KeyCode::range_from_config_str("0x7E..0x7C").is_err()); // reversed
+        assert!(KeyCode::range_from_config_str("0..100000").is_err()); // too large
+        assert!(KeyCode::range_from_config_str("f13..").is_err()); // missing end
+        assert!(KeyCode::range_from_config_str("f13..kp5").is_err()); // mixed prefixes
+        assert!(KeyCode::range_from_config_str("space..enter").is_err()); // no numeric suffix
+    }
+
+    #[test]
+    fn test_display_name() {
+        let key = KeyCode::new(124);
+        let name = key.display_name();
+        assert!(!name.is_empty());
+    }
+
+    #[test]
+    fn test_input_event_id_parse_scroll() {
+        let up = InputEventId::from_config_str("scroll_up").unwrap();
+        assert_eq!(up, InputEventId::Scroll { up: true });
+
+        let down = InputEventId::from_config_str("scroll_down").unwrap();
+        assert_eq!(down, InputEventId::Scroll { up: false });
+
+        // Case insensitive
+        let up_caps = InputEventId::from_config_str("SCROLL_UP").unwrap();
+        assert_eq!(up_caps, InputEventId::Scroll { up: true });
+    }
+
+    #[test]
+    fn test_input_event_id_parse_key() {
+        // Should fall through to key parsing
+        let key = InputEventId::from_config_str("0x7C").unwrap();
+        assert_eq!(key, InputEventId::Key(KeyCode::new(124)));
+    }
+
+    #[test]
+    fn test_input_event_id_display() {
+        assert_eq!(InputEventId::Scroll { up: true }.to_string(), "scroll_up");
+        assert_eq!(
+            InputEventId::Scroll { up: false }.to_string(),
+            "scroll_down"
+        );
+        assert_eq!(
+            InputEventId::MouseButton(MouseButton::Back).to_string(),
+            "mouse_back"
+        );
+    }
+
+    #[test]
+    fn test_mouse_button_event_id() {
+        assert_eq!(
+            InputEventId::from_config_str("mouse_forward"),
+            Some(InputEventId::MouseButton(MouseButton::Forward))
+        );
+        assert_eq!(
+            InputEventId::from_config_str("Mouse_Middle"),
+            Some(InputEventId::MouseButton(MouseButton::Middle))
+        );
+        assert_eq!(MouseButton::from_config_str("mouse_left"), None);
+    }
+
+    #[test]
+    fn test_input_event_id() {
+        let key_event = KeyEvent::new(KeyCode::new(124), true);
+        let input_event = InputEvent::Key(key_event);
+        assert_eq!(input_event.id(), InputEventId::Key(KeyCode::new(124)));
+
+        let scroll_event = InputEvent::Scroll { up: true };
+        assert_eq!(scroll_event.id(), InputEventId::Scroll { up: true });
+
+        let button_event = InputEvent::MouseButton {
+            button: MouseButton::Back,
+            down: true,
+        };
+        assert_eq!(
+            button_event.id(),
+            InputEventId::MouseButton(MouseButton::Back)
+        );
+
+        let diverted = InputEvent::Divert(Box::new(input_event));
+        assert_eq!(diverted.id(), InputEventId::Key(KeyCode::new(124)));
+    }
+
+    fn names(fixed: &[(&str, u32)], layout: &[(&str, u32)]) -> KeyNames {
+        let map = |pairs: &[(&str, u32)]| {
+            pairs
+                .iter()
+                .map(|&(name, code)| (name.to_string(), code))
+                .collect()
+        };
+        KeyNames {
+            fixed: map(fixed),
+            layout: map(layout),
+        }
+    }
+
+    #[test]
+    fn test_group_key_names() {
+        let names = names(
+            &[("key_f13", 183), ("f13", 183), ("a", 30), ("key_a", 30)],
+            // "a" is shadowed by the fixed name, "ß" only exists in this layout
+            &[("a", 16), ("ß", 12)],
+        );
+
+        let all = group_key_names(&names, None);
+        assert_eq!(all.len(), 3);
+        assert_eq!(
+            all[0],
+            KeyNameGroup {
+                code: KeyCode(12),
+                names: vec![],
+                layout_names: vec!["ß".to_string()],
+            }
+        );
+        assert_eq!(all[1].names, vec!["a".to_string(), "key_a".to_string()]);
+        assert!(all[1].layout_names.is_empty());
+        assert_eq!(all[2].names, vec!["f13".to_string(), "key_f13".to_string()]);
+
+        let filtered = group_key_names(&names, Some("F1"));
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].code, KeyCode(183));
+        assert_eq!(group_key_names(&names, Some("ß"))[0].code, KeyCode(12));
+        assert!(group_key_names(&names, Some("zzz")).is_empty());
+    }
+
+    #[test]
+    fn test_all_names() {
+        let names = names(&[("f13", 183), ("key_f13", 183)], &[("f13", 1), ("ß", 12)]);
+        assert_eq!(
+            all_names(&names),
+            vec![
+                ("f13".to_string(), KeyCode(183)),
+                ("key_f13".to_string(), KeyCode(183)),
+                ("ß".to_string(), KeyCode(12)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_closest_name() {
+        let known: Vec<(String, KeyCode)> = ["f13", "f14", "space", "enter", "key_space"]
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (name.to_string(), KeyCode(i as u32)))
+            .collect();
+        assert_eq!(closest_name(&known, "spcae").as_deref(), Some("space"));
+        assert_eq!(closest_name(&known, "F13").as_deref(), Some("f13"));
+        assert_eq!(closest_name(&known, "f1").as_deref(), Some("f13"));
+        assert_eq!(closest_name(&known, "entr").as_deref(), Some("enter"));
+        assert_eq!(closest_name(&known, "volume"), None);
+
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("ß", "ss"), 2);
+    }
+
+    #[test]
+    fn test_preferred_name() {
+        let names = names(
+            &[
+                ("key_f13", 183),
+                ("f13", 183),
+                ("key_b", 48),
+                ("b", 48),
+                ("key_1", 2),
+                ("1", 2),
+                ("2", 3),
+            ],
+            &[("ß", 12), ("b", 30), ("vk_b", 48)],
+        );
+        assert_eq!(preferred_name(&names, KeyCode(2)), "key_1");
+        assert_eq!(preferred_name(&names, KeyCode(3)), "0x3");
+        assert_eq!(preferred_name(&names, KeyCode(183)), "f13");
+        assert_eq!(preferred_name(&names, KeyCode(48)), "b");
+        assert_eq!(preferred_name(&names, KeyCode(12)), "ß");
+        assert_eq!(preferred_name(&names, KeyCode(30)), "0x1e");
+        assert_eq!(names.get("b"), Some(48));
+        assert_eq!(names.get("ß"), Some(12));
+    }
+}
@@ -0,0 +1,42 @@
+//! The remapping engine behind rebinded
+//!
+//! This crate holds everything except the CLI: config parsing and validation
+//! ([`config`]), key strategies ([`strategy`]), event resolution ([`engine`])
+//! and the accessibility modes it applies to every key ([`accessibility`]),
+//! the platform layer that hooks input and performs actions ([`platform`]), the
+//! protocol for controlling a running daemon ([`control`]), recording and
+//! replaying event traces ([`trace`]), and the clock strategies time against
+//! ([`clock`]).
+//! The `rebinded` binary is a thin shell around it, and other applications can
+//! embed the engine the same way:
+//!
+//! ```no_run
+//! use rebinded_core::platform::{Platform, PlatformInterface};
+//! use rebinded_core::{config, engine};
+//!
+//! # async fn run() -> anyhow::Result<()> {
+//! let (_, runtime) = config::load("config.toml").await?;
+//! let mut platform = Platform::new();
+//! engine::run(&mut platform, &runtime, std::future::pending()).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The loop runs on any [`platform::PlatformInterface`], so a platform of your
+//! own can take the OS platform's place.
+//!
+//! [`platform::MockPlatform`] stands in for the real platform in tests and dry
+//! runs, delivering queued events and recording actions instead of performing
+//! them. The `testing` feature adds its assertion helpers for tests outside this
+//! crate.
+
+pub mod accessibility;
+pub mod clock;
+pub mod config;
+pub mod control;
+pub mod engine;
+pub mod integrations;
+pub mod key;
+pub mod platform;
+pub mod strategy;
+pub mod trace;
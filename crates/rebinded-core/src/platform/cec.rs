@@ -0,0 +1,192 @@
+//! TV remote keys over HDMI-CEC, through libcec's `cec-client`
+//!
+//! `cec-client` registers as a playback device on the CEC adapter and logs
+//! each remote key the TV forwards (`key pressed: select (0, 0)`,
+//! `key released: select (0) D:170ms`). Keys are reported as the Linux keys
+//! the kernel's own CEC keymap uses (`ok`, `up`, `play`, `numeric_1`, ...),
+//! so they are bound by key name and work the same as with an adapter the
+//! kernel drives directly, which shows up as an rc-core device instead.
+//! Window conditions apply as usual, e.g. to send keys through to Kodi and
+//! act on them elsewhere.
+
+use serde::{Deserialize, Serialize};
+
+/// Settings from the `[settings.cec]` table
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CecSettings {
+    /// Run `cec-client` and read remote keys from it
+    #[serde(default)]
+    pub enabled: bool,
+    /// Adapter to open (e.g. `/dev/ttyACM0`), instead of the first one found
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<String>,
+}
+
+#[cfg(unix)]
+pub(super) use backend::forward;
+
+#[cfg(unix)]
+mod backend {
+    use std::process::Stdio;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use evdev::KeyCode as EvdevKey;
+    use tokio::io::{AsyncBufReadExt, BufReader};
+    use tokio::process::Command;
+    use tokio::sync::mpsc;
+    use tracing::{debug, info, warn};
+
+    use super::CecSettings;
+    use crate::key::{InputEvent, KeyCode, KeyEvent};
+    use crate::platform::PlatformState;
+
+    /// How long to wait before restarting a `cec-client` that exited
+    const RESTART_DELAY: Duration = Duration::from_secs(5);
+
+    /// libcec's debug log level, which includes key presses
+    const LOG_LEVEL: &str = "16";
+
+    /// Forward TV remote keys to `tx` for as long as the receiver is open,
+    /// following the CEC settings in `state`
+    pub(in crate::platform) async fn forward(
+        state: Arc<PlatformState>,
+        tx: mpsc::UnboundedSender<InputEvent>,
+    ) {
+        let mut settings = state.cec.subscribe();
+        settings.mark_changed();
+        while settings.changed().await.is_ok() {
+            let current = settings.borrow_and_update().clone();
+            if !current.enabled {
+                continue;
+            }
+            // The client is killed when the settings change
+            tokio::select! {
+                result = settings.changed() => {
+                    if result.is_err() {
+                        break;
+                    }
+                    settings.mark_changed();
+                }
+                () = run_client(&current, &tx) => {}
+            }
+            if tx.is_closed() {
+                break;
+            }
+        }
+    }
+
+    /// Run `cec-client` and forward its keys, restarting it whenever it exits
+    async fn run_client(settings: &CecSettings, tx: &mpsc::UnboundedSender<InputEvent>) {
+        loop {
+            let mut command = Command::new("cec-client");
+            // Register as a playback device, like a set-top box
+            command.args(["-d", LOG_LEVEL, "-t", "p"]);
+            command.args(settings.port.as_slice());
+            command
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .kill_on_drop(true);
+            match command.spawn() {
+                Ok(mut child) => {
+                    info!("reading TV remote keys from cec-client");
+                    if let Some(stdout) = child.stdout.take() {
+                        let mut lines = BufReader::new(stdout).lines();
+                        while let Ok(Some(line)) = lines.next_line().await {
+                            let Some((key, down)) = parse_line(&line) else {
+                                continue;
+                            };
+                            if tx.send(InputEvent::Key(KeyEvent::new(key, down))).is_err() {
+                                return;
+                            }
+                        }
+                    }
+                    warn!("cec-client exited");
+                }
+                Err(e) => warn!("failed to start cec-client (is libcec installed?): {e}"),
+            }
+            tokio::time::sleep(RESTART_DELAY).await;
+        }
+    }
+
+    /// The key and whether it was pressed, for a `cec-client` key log line
+    pub(super) fn parse_line(line: &str) -> Option<(KeyCode, bool)> {
+        let (rest, down) = match line.split_once("key pressed: ") {
+            Some((_, rest)) => (rest, true),
+            None => (line.split_once("key released: ")?.1, false),
+        };
+        // "select (0, 0)" or "select (0) D:170ms": the user control code is in hex
+        let (name, codes) = rest.split_once(" (")?;
+        let code = codes.split([',', ')']).next()?.trim();
+        let code = u8::from_str_radix(code, 16).ok()?;
+        let Some(key) = user_control_key(code) else {
+            debug!(name, code, "CEC key has no Linux key");
+            return None;
+        };
+        Some((KeyCode::new(key.0 as u32), down))
+    }
+
+    /// The Linux key for a CEC user control code, following the kernel's
+    /// `rc-cec` keymap
+    fn user_control_key(code: u8) -> Option<EvdevKey> {
+        Some(match code {
+            0x00 => EvdevKey::KEY_OK,
+            0x01 => EvdevKey::KEY_UP,
+            0x02 => EvdevKey::KEY_DOWN,
+            0x03 => EvdevKey::KEY_LEFT,
+            0x04 => EvdevKey::KEY_RIGHT,
+            0x09 => EvdevKey::KEY_ROOT_MENU,
+            0x0A => EvdevKey::KEY_SETUP,
+            0x0B => EvdevKey::KEY_MENU,
+            0x0D => EvdevKey::KEY_EXIT,
+            0x20..=0x29 => EvdevKey::new(EvdevKey::KEY_NUMERIC_0.0 + (code - 0x20) as u16),
+            0x30 => EvdevKey::KEY_CHANNELUP,
+            0x31 => EvdevKey::KEY_CHANNELDOWN,
+            0x35 => EvdevKey::KEY_INFO,
+            0x41 => EvdevKey::KEY_VOLUMEUP,
+            0x42 => EvdevKey::KEY_VOLUMEDOWN,
+            0x43 => EvdevKey::KEY_MUTE,
+            0x44 => EvdevKey::KEY_PLAY,
+            0x45 => EvdevKey::KEY_STOP,
+            0x46 => EvdevKey::KEY_PAUSE,
+            0x47 => EvdevKey::KEY_RECORD,
+            0x48 => EvdevKey::KEY_REWIND,
+            0x49 => EvdevKey::KEY_FASTFORWARD,
+            0x4B => EvdevKey::KEY_NEXTSONG,
+            0x4C => EvdevKey::KEY_PREVIOUSSONG,
+            0x53 => EvdevKey::KEY_EPG,
+            0x71 => EvdevKey::KEY_BLUE,
+            0x72 => EvdevKey::KEY_RED,
+            0x73 => EvdevKey::KEY_GREEN,
+            0x74 => EvdevKey::KEY_YELLOW,
+            _ => return None,
+        })
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::backend::parse_line;
+    use crate::key::KeyCode;
+
+    #[test]
+    fn test_parse_line() {
+        let ok = KeyCode::from_config_str("ok").unwrap();
+        assert_eq!(
+            parse_line("DEBUG:   [            5016]\tkey pressed: select (0, 0)"),
+            Some((ok, true))
+        );
+        assert_eq!(
+            parse_line("DEBUG:   [            5186]\tkey released: select (0) D:170ms"),
+            Some((ok, false))
+        );
+        assert_eq!(
+            parse_line("key pressed: 5 (25, 0)"),
+            Some((KeyCode::from_config_str("numeric_5").unwrap(), true))
+        );
+        assert_eq!(parse_line("key pressed: data (76, 0)"), None);
+        assert_eq!(parse_line("TRAFFIC: [ 5016] >> 01:44:00"), None);
+    }
+}
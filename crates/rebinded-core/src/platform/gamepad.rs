@@ -0,0 +1,402 @@
+//! Gamepad buttons as bindable inputs
+//!
+//! Buttons are reported as key events (`pad:a`, `pad:start`, `pad:up`) so a
+//! controller can drive media actions and strategies. Gamepads are only read,
+//! never grabbed, so games and other applications keep seeing every button.
+//! Reading starts once the config binds a gamepad button and is limited to
+//! the devices matching `[settings.gamepad] devices`. Devices are rescanned on
+//! every config load, so a controller connected later is picked up by a reload.
+//!
+//! On Linux gamepads are evdev devices reporting `BTN_SOUTH`. On Windows the
+//! four XInput slots are polled; they are named "XInput 1" to "XInput 4" for
+//! device filtering, and the guide button isn't available.
+
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+use tracing::warn;
+
+use super::PlatformState;
+use crate::key::{GamepadButton, InputEvent, KeyCode, KeyEvent};
+
+/// Settings from the `[settings.gamepad]` table
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct GamepadSettings {
+    /// Glob patterns matched against gamepad names, ignoring case. Every
+    /// gamepad is read when empty.
+    #[serde(default)]
+    pub devices: Vec<String>,
+}
+
+/// What the loaded config wants read, notified on every config load
+#[derive(Debug, Default)]
+pub(super) struct Capture {
+    pub(super) enabled: bool,
+    pub(super) devices: Vec<String>,
+}
+
+/// Whether the gamepad named `name` passes the device filter
+fn allowed(patterns: &[String], name: &str) -> bool {
+    let name = name.to_lowercase();
+    patterns.is_empty()
+        || patterns
+            .iter()
+            .any(|pattern| glob_match::glob_match(&pattern.to_lowercase(), &name))
+}
+
+/// Forward gamepad button presses to `tx` for as long as the receiver is
+/// open, reading the gamepads `state` asks for
+pub(super) async fn forward(state: Arc<PlatformState>, tx: mpsc::UnboundedSender<InputEvent>) {
+    let mut capture = state.gamepads.subscribe();
+    capture.mark_changed();
+    while capture.changed().await.is_ok() {
+        let mut readers = JoinSet::new();
+        {
+            let capture = capture.borrow_and_update();
+            if capture.enabled {
+                backend::spawn_readers(&capture.devices, &tx, &mut readers);
+                if readers.is_empty() {
+                    warn!("gamepad buttons are bound but no matching gamepad was found");
+                }
+            }
+        }
+
+        // Readers are dropped (closing their devices) when the config changes
+        tokio::select! {
+            result = capture.changed() => {
+                if result.is_err() {
+                    break;
+                }
+                capture.mark_changed();
+            }
+            () = async {
+                while let Some(result) = readers.join_next().await {
+                    if let Ok(Err(e)) = result {
+                        warn!("gamepad stopped: {e}");
+                    }
+                }
+                std::future::pending::<()>().await
+            } => {}
+        }
+        if tx.is_closed() {
+            break;
+        }
+    }
+}
+
+/// Buttons held on one gamepad, one bit per [`GamepadButton::ALL`] index
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Pressed(u32);
+
+impl Pressed {
+    fn set(&mut self, button: GamepadButton, down: bool) {
+        let bit = 1 << button as u32;
+        if down {
+            self.0 |= bit;
+        } else {
+            self.0 &= !bit;
+        }
+    }
+
+    /// Buttons pressed or released going from `self` to `next`
+    fn edges(self, next: Self) -> impl Iterator<Item = (GamepadButton, bool)> {
+        let changed = self.0 ^ next.0;
+        GamepadButton::ALL
+            .into_iter()
+            .filter(move |&button| changed & (1 << button as u32) != 0)
+            .map(move |button| (button, next.0 & (1 << button as u32) != 0))
+    }
+
+    /// Send the key events for going from `self` to `next`, returning false
+    /// once the receiver is gone
+    fn send_edges(self, next: Self, tx: &mpsc::UnboundedSender<InputEvent>) -> bool {
+        self.edges(next).all(|(button, down)| {
+            let event = KeyEvent::new(KeyCode::from_gamepad(button), down);
+            tx.send(InputEvent::Key(event)).is_ok()
+        })
+    }
+}
+
+#[cfg(unix)]
+mod backend {
+    use std::path::PathBuf;
+
+    use anyhow::Result;
+    use evdev::{AbsoluteAxisCode, Device, EventSummary, KeyCode as EvdevKey};
+    use tokio::sync::mpsc;
+    use tokio::task::JoinSet;
+    use tracing::{debug, info};
+
+    use super::{Pressed, allowed};
+    use crate::key::{GamepadButton, InputEvent};
+
+    pub(super) fn spawn_readers(
+        patterns: &[String],
+        tx: &mpsc::UnboundedSender<InputEvent>,
+        readers: &mut JoinSet<Result<()>>,
+    ) {
+        let Ok(entries) = std::fs::read_dir("/dev/input") else {
+            return;
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with("event"))
+            })
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            let Ok(device) = Device::open(&path) else {
+                continue;
+            };
+            let is_gamepad = device
+                .supported_keys()
+                .is_some_and(|keys| keys.contains(EvdevKey::BTN_SOUTH));
+            let name = device.name().unwrap_or("unknown").to_string();
+            // The virtual keyboard registers every key, gamepad buttons included
+            if !is_gamepad || name == crate::platform::linux::VIRTUAL_DEVICE_NAME {
+                continue;
+            }
+            if !allowed(patterns, &name) {
+                debug!(
+                    "skipping gamepad {name} ({}), not in device filter",
+                    path.display()
+                );
+                continue;
+            }
+            info!("reading gamepad {name} ({})", path.display());
+            readers.spawn(read_device(device, tx.clone()));
+        }
+    }
+
+    async fn read_device(device: Device, tx: mpsc::UnboundedSender<InputEvent>) -> Result<()> {
+        let mut stream = device.into_event_stream()?;
+        let mut pressed = Pressed::default();
+        loop {
+            let event = stream.next_event().await?;
+            let mut next = pressed;
+            apply(&mut next, event.destructure());
+            if !pressed.send_edges(next, &tx) {
+                return Ok(());
+            }
+            pressed = next;
+        }
+    }
+
+    /// Update `pressed` for one evdev event. D-pads that report a hat axis
+    /// instead of buttons press the direction the hat points.
+    pub(super) fn apply(pressed: &mut Pressed, event: EventSummary) {
+        match event {
+            // Autorepeat (value 2) isn't a new press
+            EventSummary::Key(_, key, value @ (0 | 1)) => {
+                if let Some(button) = button(key) {
+                    pressed.set(button, value == 1);
+                }
+            }
+            EventSummary::AbsoluteAxis(_, axis, value) => {
+                let (negative, positive) = match axis {
+                    AbsoluteAxisCode::ABS_HAT0X => {
+                        (GamepadButton::DpadLeft, GamepadButton::DpadRight)
+                    }
+                    AbsoluteAxisCode::ABS_HAT0Y => (GamepadButton::DpadUp, GamepadButton::DpadDown),
+                    _ => return,
+                };
+                pressed.set(negative, value < 0);
+                pressed.set(positive, value > 0);
+            }
+            _ => {}
+        }
+    }
+
+    /// Buttons are named by position, following the kernel's gamepad layout
+    fn button(key: EvdevKey) -> Option<GamepadButton> {
+        Some(match key {
+            EvdevKey::BTN_SOUTH => GamepadButton::A,
+            EvdevKey::BTN_EAST => GamepadButton::B,
+            EvdevKey::BTN_WEST => GamepadButton::X,
+            EvdevKey::BTN_NORTH => GamepadButton::Y,
+            EvdevKey::BTN_TL => GamepadButton::LeftBumper,
+            EvdevKey::BTN_TR => GamepadButton::RightBumper,
+            EvdevKey::BTN_TL2 => GamepadButton::LeftTrigger,
+            EvdevKey::BTN_TR2 => GamepadButton::RightTrigger,
+            EvdevKey::BTN_SELECT => GamepadButton::Back,
+            EvdevKey::BTN_START => GamepadButton::Start,
+            EvdevKey::BTN_MODE => GamepadButton::Guide,
+            EvdevKey::BTN_THUMBL => GamepadButton::LeftStick,
+            EvdevKey::BTN_THUMBR => GamepadButton::RightStick,
+            EvdevKey::BTN_DPAD_UP => GamepadButton::DpadUp,
+            EvdevKey::BTN_DPAD_DOWN => GamepadButton::DpadDown,
+            EvdevKey::BTN_DPAD_LEFT => GamepadButton::DpadLeft,
+            EvdevKey::BTN_DPAD_RIGHT => GamepadButton::DpadRight,
+            _ => return None,
+        })
+    }
+}
+
+#[cfg(windows)]
+mod backend {
+    use std::time::Duration;
+
+    use anyhow::Result;
+    use tokio::sync::mpsc;
+    use tokio::task::JoinSet;
+    use tracing::{debug, info};
+    use windows::Win32::UI::Input::XboxController::{XINPUT_STATE, XInputGetState};
+
+    use super::{Pressed, allowed};
+    use crate::key::{GamepadButton, InputEvent};
+
+    /// XInput supports this many controllers
+    const SLOTS: u32 = 4;
+
+    /// How often connected controllers are polled
+    const POLL_INTERVAL: Duration = Duration::from_millis(8);
+
+    /// Querying an empty slot is slow, so they are checked less often
+    const RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+
+    /// Trigger travel counted as a press (`XINPUT_GAMEPAD_TRIGGER_THRESHOLD`)
+    const TRIGGER_THRESHOLD: u8 = 30;
+
+    /// `wButtons` bits for each button
+    const BUTTON_BITS: [(u16, GamepadButton); 14] = [
+        (0x0001, GamepadButton::DpadUp),
+        (0x0002, GamepadButton::DpadDown),
+        (0x0004, GamepadButton::DpadLeft),
+        (0x0008, GamepadButton::DpadRight),
+        (0x0010, GamepadButton::Start),
+        (0x0020, GamepadButton::Back),
+        (0x0040, GamepadButton::LeftStick),
+        (0x0080, GamepadButton::RightStick),
+        (0x0100, GamepadButton::LeftBumper),
+        (0x0200, GamepadButton::RightBumper),
+        (0x1000, GamepadButton::A),
+        (0x2000, GamepadButton::B),
+        (0x4000, GamepadButton::X),
+        (0x8000, GamepadButton::Y),
+    ];
+
+    pub(super) fn spawn_readers(
+        patterns: &[String],
+        tx: &mpsc::UnboundedSender<InputEvent>,
+        readers: &mut JoinSet<Result<()>>,
+    ) {
+        for slot in 0..SLOTS {
+            let name = format!("XInput {}", slot + 1);
+            if !allowed(patterns, &name) {
+                debug!("skipping {name}, not in device filter");
+                continue;
+            }
+            info!("polling {name}");
+            readers.spawn(poll_slot(slot, tx.clone()));
+        }
+    }
+
+    async fn poll_slot(slot: u32, tx: mpsc::UnboundedSender<InputEvent>) -> Result<()> {
+        let mut pressed = Pressed::default();
+        loop {
+            let state = read_slot(slot);
+            let connected = state.is_some();
+            let next = state.unwrap_or_default();
+            if !pressed.send_edges(next, &tx) {
+                return Ok(());
+            }
+            pressed = next;
+            tokio::time::sleep(if connected {
+                POLL_INTERVAL
+            } else {
+                RECONNECT_INTERVAL
+            })
+            .await;
+        }
+    }
+
+    /// Buttons held on the controller in `slot`, or `None` if none is connected
+    fn read_slot(slot: u32) -> Option<Pressed> {
+        let mut state = XINPUT_STATE::default();
+        // SAFETY: state is a valid, writable XINPUT_STATE
+        if unsafe { XInputGetState(slot, &mut state) } != 0 {
+            return None;
+        }
+        let gamepad = state.Gamepad;
+        let mut pressed = Pressed::default();
+        for (bit, button) in BUTTON_BITS {
+            pressed.set(button, gamepad.wButtons.0 & bit != 0);
+        }
+        pressed.set(
+            GamepadButton::LeftTrigger,
+            gamepad.bLeftTrigger > TRIGGER_THRESHOLD,
+        );
+        pressed.set(
+            GamepadButton::RightTrigger,
+            gamepad.bRightTrigger > TRIGGER_THRESHOLD,
+        );
+        Some(pressed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_device_filter() {
+        assert!(allowed(&[], "Xbox Wireless Controller"));
+        let patterns = vec!["*xbox*".to_string()];
+        assert!(allowed(&patterns, "Xbox Wireless Controller"));
+        assert!(!allowed(&patterns, "Sony DualSense"));
+    }
+
+    #[test]
+    fn test_edges() {
+        let mut pressed = Pressed::default();
+        pressed.set(GamepadButton::A, true);
+        pressed.set(GamepadButton::Start, true);
+        let mut next = pressed;
+        next.set(GamepadButton::A, false);
+        next.set(GamepadButton::DpadUp, true);
+        assert_eq!(
+            pressed.edges(next).collect::<Vec<_>>(),
+            vec![(GamepadButton::A, false), (GamepadButton::DpadUp, true)]
+        );
+        assert_eq!(next.edges(next).count(), 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_evdev_events() {
+        use evdev::{AbsoluteAxisCode, EventType, InputEvent as EvdevEvent, KeyCode as EvdevKey};
+
+        let key = |key: EvdevKey, value| EvdevEvent::new(EventType::KEY.0, key.0, value);
+        let hat =
+            |axis: AbsoluteAxisCode, value| EvdevEvent::new(EventType::ABSOLUTE.0, axis.0, value);
+
+        let mut pressed = Pressed::default();
+        backend::apply(&mut pressed, key(EvdevKey::BTN_NORTH, 1).destructure());
+        backend::apply(
+            &mut pressed,
+            hat(AbsoluteAxisCode::ABS_HAT0X, -1).destructure(),
+        );
+        let mut expected = Pressed::default();
+        expected.set(GamepadButton::Y, true);
+        expected.set(GamepadButton::DpadLeft, true);
+        assert_eq!(pressed, expected);
+
+        // Autorepeat is ignored, the hat swinging over releases the old direction
+        backend::apply(&mut pressed, key(EvdevKey::BTN_NORTH, 2).destructure());
+        backend::apply(
+            &mut pressed,
+            hat(AbsoluteAxisCode::ABS_HAT0X, 1).destructure(),
+        );
+        expected.set(GamepadButton::DpadLeft, false);
+        expected.set(GamepadButton::DpadRight, true);
+        assert_eq!(pressed, expected);
+    }
+}
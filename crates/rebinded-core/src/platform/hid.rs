@@ -0,0 +1,583 @@
+//! Generic HID devices (foot pedals, macro boxes) as bindable inputs
+//!
+//! Many pedals report vendor-specific usages instead of keyboard keys, so the
+//! OS never turns them into key events. A `[[hid]]` table names the device and
+//! gives each usage it reports a key name, bound as `hid:<name>`:
+//!
+//! ```toml
+//! [[hid]]
+//! device = "*Foot Switch*"
+//! keys = { pedal_left = "0xFF00:0x01", pedal_right = "0xFF00:0x03" }
+//! ```
+//!
+//! On Linux the device's report descriptor is read from sysfs and its input
+//! reports from `/dev/hidrawN`, which doesn't stop other applications reading
+//! it too. Devices are opened on every config load that defines `[[hid]]`
+//! devices.
+// Reports are only read on Linux
+#![cfg_attr(windows, allow(dead_code))]
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// A HID usage: the usage page and the usage ID within it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct HidUsage {
+    pub page: u16,
+    pub id: u16,
+}
+
+impl FromStr for HidUsage {
+    type Err = String;
+
+    /// Parse `page:id`, each in hex (`0xFF00`) or decimal
+    fn from_str(s: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid HID usage '{s}', expected page:id (e.g. 0xFF00:0x01)");
+        let (page, id) = s.split_once(':').ok_or_else(invalid)?;
+        let number = |part: &str| {
+            let part = part.trim();
+            match part.strip_prefix("0x").or_else(|| part.strip_prefix("0X")) {
+                Some(hex) => u16::from_str_radix(hex, 16).ok(),
+                None => part.parse().ok(),
+            }
+        };
+        Ok(Self {
+            page: number(page).ok_or_else(invalid)?,
+            id: number(id).ok_or_else(invalid)?,
+        })
+    }
+}
+
+impl TryFrom<String> for HidUsage {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, String> {
+        s.parse()
+    }
+}
+
+impl From<HidUsage> for String {
+    fn from(usage: HidUsage) -> Self {
+        usage.to_string()
+    }
+}
+
+impl fmt::Display for HidUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#06x}:{:#06x}", self.page, self.id)
+    }
+}
+
+/// A `[[hid]]` table: which device to read and the key name for each usage
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct HidDevice {
+    /// Glob matched against the device name, ignoring case
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vendor: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub product: Option<u16>,
+    /// Key names (without `hid:`) and the usage each stands for
+    pub keys: BTreeMap<String, HidUsage>,
+}
+
+impl HidDevice {
+    /// Whether this table selects the device with the given name and IDs
+    pub fn matches(&self, name: &str, vendor: u16, product: u16) -> bool {
+        self.device.as_ref().is_none_or(|pattern| {
+            glob_match::glob_match(&pattern.to_lowercase(), &name.to_lowercase())
+        }) && self.vendor.is_none_or(|v| v == vendor)
+            && self.product.is_none_or(|p| p == product)
+    }
+}
+
+/// An input field of a report, as declared by the report descriptor
+#[derive(Debug, Clone, PartialEq)]
+struct Field {
+    report_id: u8,
+    /// Bit offset within the report, after the report ID byte
+    offset: usize,
+    size: usize,
+    count: usize,
+    kind: FieldKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum FieldKind {
+    /// Each of the `count` values is the state of one usage (buttons)
+    Variable(Vec<HidUsage>),
+    /// Each value names a pressed usage by index, offset by the logical minimum
+    Array {
+        usages: Vec<HidUsage>,
+        logical_min: i32,
+    },
+}
+
+/// The input fields declared by a report descriptor
+///
+/// Only what locating usages in input reports needs is interpreted: usage
+/// pages and usages, report size, count and ID, the logical minimum of array
+/// fields, and push/pop. Output and feature items are skipped.
+#[derive(Debug, Default)]
+struct ReportDescriptor {
+    fields: Vec<Field>,
+    /// Whether reports start with a report ID byte
+    numbered: bool,
+}
+
+/// Global item state, saved and restored by push and pop
+#[derive(Debug, Clone, Copy, Default)]
+struct Globals {
+    usage_page: u16,
+    logical_min: i32,
+    report_size: usize,
+    report_count: usize,
+    report_id: u8,
+}
+
+impl ReportDescriptor {
+    fn parse(bytes: &[u8]) -> Result<Self, String> {
+        let mut descriptor = Self::default();
+        let mut globals = Globals::default();
+        let mut stack = Vec::new();
+        let mut usages: Vec<HidUsage> = Vec::new();
+        let mut usage_min = None;
+        // Input bits used so far, per report ID
+        let mut offsets: BTreeMap<u8, usize> = BTreeMap::new();
+
+        let mut rest = bytes;
+        while let Some((&prefix, tail)) = rest.split_first() {
+            // Long items carry their size in the next byte and are never used for input
+            if prefix == 0xFE {
+                let size = *tail.first().ok_or("truncated long item")? as usize;
+                rest = tail.get(2 + size..).ok_or("truncated long item")?;
+                continue;
+            }
+            let size = match prefix & 0x03 {
+                3 => 4,
+                n => n as usize,
+            };
+            let data = tail.get(..size).ok_or("truncated item")?;
+            rest = &tail[size..];
+            let unsigned = data
+                .iter()
+                .rev()
+                .fold(0u32, |value, &byte| (value << 8) | byte as u32);
+            let signed = match size {
+                1 => unsigned as u8 as i8 as i32,
+                2 => unsigned as u16 as i16 as i32,
+                _ => unsigned as i32,
+            };
+            let usage = |globals: &Globals| match size {
+                4 => HidUsage {
+                    page: (unsigned >> 16) as u16,
+                    id: unsigned as u16,
+                },
+                _ => HidUsage {
+                    page: globals.usage_page,
+                    id: unsigned as u16,
+                },
+            };
+
+            match (prefix >> 2) & 0x03 {
+                // Main items
+                0 => {
+                    let tag = prefix >> 4;
+                    if tag == 0x8 {
+                        let offset = offsets.entry(globals.report_id).or_default();
+                        let bits = globals.report_size * globals.report_count;
+                        let constant = unsigned & 0x01 != 0;
+                        let variable = unsigned & 0x02 != 0;
+                        if !constant && globals.report_size > 0 {
+                            let kind = if variable {
+                                FieldKind::Variable(usages.clone())
+                            } else {
+                                FieldKind::Array {
+                                    usages: usages.clone(),
+                                    logical_min: globals.logical_min,
+                                }
+                            };
+                            descriptor.fields.push(Field {
+                                report_id: globals.report_id,
+                                offset: *offset,
+                                size: globals.report_size,
+                                count: globals.report_count,
+                                kind,
+                            });
+                        }
+                        *offset += bits;
+                    }
+                    // Every main item (input, output, feature, collection) ends the local items
+                    usages.clear();
+                    usage_min = None;
+                }
+                // Global items
+                1 => match prefix >> 4 {
+                    0x0 => globals.usage_page = unsigned as u16,
+                    0x1 => globals.logical_min = signed,
+                    0x7 => globals.report_size = unsigned as usize,
+                    0x8 => {
+                        globals.report_id = unsigned as u8;
+                        descriptor.numbered = true;
+                    }
+                    0x9 => globals.report_count = unsigned as usize,
+                    0xA => stack.push(globals),
+                    0xB => globals = stack.pop().ok_or("pop without push")?,
+                    _ => {}
+                },
+                // Local items
+                2 => match prefix >> 4 {
+                    0x0 => usages.push(usage(&globals)),
+                    0x1 => usage_min = Some(usage(&globals)),
+                    0x2 => {
+                        let max = usage(&globals);
+                        let min = usage_min.take().ok_or("usage maximum without minimum")?;
+                        usages.extend((min.id..=max.id).map(|id| HidUsage { page: min.page, id }));
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+        Ok(descriptor)
+    }
+
+    /// The usages pressed according to `report`, and every usage the report
+    /// carries (pressed or not), so state from other reports is left alone
+    fn read(&self, report: &[u8]) -> (Vec<HidUsage>, Vec<HidUsage>) {
+        let (report_id, data) = match (self.numbered, report.split_first()) {
+            (true, Some((&id, data))) => (id, data),
+            _ => (0, report),
+        };
+        let mut pressed = Vec::new();
+        let mut carried = Vec::new();
+        for field in self.fields.iter().filter(|f| f.report_id == report_id) {
+            let values = (0..field.count)
+                .map(|i| read_bits(data, field.offset + i * field.size, field.size));
+            match &field.kind {
+                FieldKind::Variable(usages) => {
+                    for (i, value) in values.enumerate() {
+                        // Extra values reuse the last usage
+                        let Some(&usage) = usages.get(i).or(usages.last()) else {
+                            break;
+                        };
+                        carried.push(usage);
+                        if value != 0 {
+                            pressed.push(usage);
+                        }
+                    }
+                }
+                FieldKind::Array {
+                    usages,
+                    logical_min,
+                } => {
+                    carried.extend(usages);
+                    for value in values {
+                        let index = value as i64 - *logical_min as i64;
+                        if let Some(&usage) =
+                            usize::try_from(index).ok().and_then(|i| usages.get(i))
+                            && usage.id != 0
+                        {
+                            pressed.push(usage);
+                        }
+                    }
+                }
+            }
+        }
+        (pressed, carried)
+    }
+}
+
+/// Read `size` bits (at most 32) starting at bit `offset`, least significant first
+fn read_bits(data: &[u8], offset: usize, size: usize) -> u32 {
+    (0..size.min(32)).fold(0, |value, bit| {
+        let position = offset + bit;
+        let set = data
+            .get(position / 8)
+            .is_some_and(|byte| byte & (1 << (position % 8)) != 0);
+        value | (set as u32) << bit
+    })
+}
+
+#[cfg(unix)]
+pub(super) use backend::forward;
+
+#[cfg(unix)]
+mod backend {
+    use std::collections::HashSet;
+    use std::fs::{File, OpenOptions};
+    use std::io::{self, Read};
+    use std::os::unix::fs::OpenOptionsExt;
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+
+    use tokio::io::unix::AsyncFd;
+    use tokio::sync::mpsc;
+    use tokio::task::JoinSet;
+    use tracing::{info, warn};
+
+    use super::{HidDevice, HidUsage, ReportDescriptor};
+    use crate::key::{InputEvent, KeyCode, KeyEvent};
+    use crate::platform::PlatformState;
+
+    /// Forward key events from the `[[hid]]` devices in `state` to `tx` for
+    /// as long as the receiver is open
+    pub(in crate::platform) async fn forward(
+        state: Arc<PlatformState>,
+        tx: mpsc::UnboundedSender<InputEvent>,
+    ) {
+        let mut capture = state.hid.subscribe();
+        capture.mark_changed();
+        while capture.changed().await.is_ok() {
+            let configs = capture.borrow_and_update().clone();
+            let mut readers = JoinSet::new();
+            if !configs.is_empty() {
+                for node in find_hidraw_devices() {
+                    let Some(config) = configs
+                        .iter()
+                        .find(|c| c.matches(&node.name, node.vendor, node.product))
+                    else {
+                        continue;
+                    };
+                    match open_device(&node) {
+                        Ok((device, descriptor)) => {
+                            info!("reading HID device {} ({})", node.name, node.path.display());
+                            readers.spawn(read_device(
+                                device,
+                                descriptor,
+                                config.clone(),
+                                tx.clone(),
+                            ));
+                        }
+                        Err(e) => {
+                            warn!("failed to open HID device {}: {e}", node.path.display())
+                        }
+                    }
+                }
+                if readers.is_empty() {
+                    warn!("no device matches the [[hid]] tables");
+                }
+            }
+
+            // Readers are dropped (closing their devices) when the config changes
+            tokio::select! {
+                result = capture.changed() => {
+                    if result.is_err() {
+                        break;
+                    }
+                    capture.mark_changed();
+                }
+                () = async {
+                    while let Some(result) = readers.join_next().await {
+                        if let Ok(Err(e)) = result {
+                            warn!("HID device stopped: {e}");
+                        }
+                    }
+                    std::future::pending::<()>().await
+                } => {}
+            }
+            if tx.is_closed() {
+                break;
+            }
+        }
+    }
+
+    /// A `/dev/hidrawN` node and the device behind it
+    struct HidrawNode {
+        path: PathBuf,
+        sysfs: PathBuf,
+        name: String,
+        vendor: u16,
+        product: u16,
+    }
+
+    fn find_hidraw_devices() -> Vec<HidrawNode> {
+        let Ok(entries) = std::fs::read_dir("/sys/class/hidraw") else {
+            return Vec::new();
+        };
+        let mut nodes: Vec<HidrawNode> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let sysfs = entry.path().join("device");
+                let uevent = std::fs::read_to_string(sysfs.join("uevent")).ok()?;
+                let (name, vendor, product) = parse_uevent(&uevent)?;
+                Some(HidrawNode {
+                    path: Path::new("/dev").join(entry.file_name()),
+                    sysfs,
+                    name,
+                    vendor,
+                    product,
+                })
+            })
+            .collect();
+        nodes.sort_by(|a, b| a.path.cmp(&b.path));
+        nodes
+    }
+
+    /// The name, vendor and product from a HID device's uevent
+    /// (`HID_ID=0003:000005F3:000000FF`, `HID_NAME=...`)
+    pub(super) fn parse_uevent(uevent: &str) -> Option<(String, u16, u16)> {
+        let mut name = None;
+        let mut ids = None;
+        for line in uevent.lines() {
+            if let Some(value) = line.strip_prefix("HID_NAME=") {
+                name = Some(value.to_string());
+            } else if let Some(value) = line.strip_prefix("HID_ID=") {
+                let mut parts = value.split(':').skip(1);
+                let vendor = u32::from_str_radix(parts.next()?, 16).ok()?;
+                let product = u32::from_str_radix(parts.next()?, 16).ok()?;
+                ids = Some((vendor as u16, product as u16));
+            }
+        }
+        let (vendor, product) = ids?;
+        Some((name.unwrap_or_default(), vendor, product))
+    }
+
+    fn open_device(node: &HidrawNode) -> io::Result<(AsyncFd<File>, ReportDescriptor)> {
+        let bytes = std::fs::read(node.sysfs.join("report_descriptor"))?;
+        let descriptor = ReportDescriptor::parse(&bytes)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let file = OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(&node.path)?;
+        Ok((AsyncFd::new(file)?, descriptor))
+    }
+
+    async fn read_device(
+        device: AsyncFd<File>,
+        descriptor: ReportDescriptor,
+        config: HidDevice,
+        tx: mpsc::UnboundedSender<InputEvent>,
+    ) -> io::Result<()> {
+        let keys: Vec<(HidUsage, KeyCode)> = config
+            .keys
+            .iter()
+            .map(|(name, &usage)| (usage, KeyCode::from_hid_name(name)))
+            .collect();
+        let mut held: HashSet<HidUsage> = HashSet::new();
+        let mut buf = [0u8; 4096];
+        loop {
+            let mut guard = device.readable().await?;
+            let read = match guard.try_io(|inner| inner.get_ref().read(&mut buf)) {
+                Ok(result) => result?,
+                Err(_would_block) => continue,
+            };
+            if read == 0 {
+                return Ok(());
+            }
+            let (pressed, carried) = descriptor.read(&buf[..read]);
+            for &(usage, key) in &keys {
+                if !carried.contains(&usage) {
+                    continue;
+                }
+                let down = pressed.contains(&usage);
+                let changed = if down {
+                    held.insert(usage)
+                } else {
+                    held.remove(&usage)
+                };
+                if changed && tx.send(InputEvent::Key(KeyEvent::new(key, down))).is_err() {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A pedal with three vendor-page buttons and 5 bits of padding, in report 2
+    const PEDAL: &[u8] = &[
+        0x06, 0x00, 0xFF, // Usage Page (Vendor 0xFF00)
+        0x09, 0x01, // Usage (0x01)
+        0xA1, 0x01, // Collection (Application)
+        0x85, 0x02, //   Report ID (2)
+        0x19, 0x01, //   Usage Minimum (0x01)
+        0x29, 0x03, //   Usage Maximum (0x03)
+        0x15, 0x00, //   Logical Minimum (0)
+        0x25, 0x01, //   Logical Maximum (1)
+        0x75, 0x01, //   Report Size (1)
+        0x95, 0x03, //   Report Count (3)
+        0x81, 0x02, //   Input (Data, Variable)
+        0x95, 0x05, //   Report Count (5)
+        0x81, 0x03, //   Input (Constant)
+        0xC0, // End Collection
+    ];
+
+    fn usage(page: u16, id: u16) -> HidUsage {
+        HidUsage { page, id }
+    }
+
+    #[test]
+    fn test_parse_usage() {
+        assert_eq!("0xFF00:0x01".parse(), Ok(usage(0xFF00, 1)));
+        assert_eq!("12:0xcd".parse(), Ok(usage(12, 0xCD)));
+        assert_eq!(usage(0xFF00, 1).to_string(), "0xff00:0x0001");
+        assert!("0xFF00".parse::<HidUsage>().is_err());
+        assert!("0x10000:1".parse::<HidUsage>().is_err());
+    }
+
+    #[test]
+    fn test_variable_report() {
+        let descriptor = ReportDescriptor::parse(PEDAL).unwrap();
+        assert!(descriptor.numbered);
+        let (pressed, carried) = descriptor.read(&[0x02, 0b101]);
+        assert_eq!(pressed, vec![usage(0xFF00, 1), usage(0xFF00, 3)]);
+        assert_eq!(carried.len(), 3);
+
+        // Reports with another ID carry none of these usages
+        assert_eq!(descriptor.read(&[0x01, 0xFF]), (Vec::new(), Vec::new()));
+    }
+
+    #[test]
+    fn test_array_report() {
+        // Consumer page array of two slots selecting from usages 0xB5..0xB7
+        let descriptor = ReportDescriptor::parse(&[
+            0x05, 0x0C, // Usage Page (Consumer)
+            0x15, 0x01, // Logical Minimum (1)
+            0x19, 0xB5, // Usage Minimum (0xB5)
+            0x29, 0xB7, // Usage Maximum (0xB7)
+            0x75, 0x08, // Report Size (8)
+            0x95, 0x02, // Report Count (2)
+            0x81, 0x00, // Input (Data, Array)
+        ])
+        .unwrap();
+        assert!(!descriptor.numbered);
+        let (pressed, carried) = descriptor.read(&[3, 0]);
+        assert_eq!(pressed, vec![usage(0x0C, 0xB7)]);
+        assert_eq!(carried.len(), 3);
+    }
+
+    #[test]
+    fn test_device_matching() {
+        let device = HidDevice {
+            device: Some("*foot switch*".to_string()),
+            vendor: Some(0x05F3),
+            product: None,
+            keys: BTreeMap::new(),
+        };
+        assert!(device.matches("VEC USB Foot Switch", 0x05F3, 0x00FF));
+        assert!(!device.matches("VEC USB Foot Switch", 0x1234, 0x00FF));
+        assert!(!device.matches("Keyboard", 0x05F3, 0x00FF));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_parse_uevent() {
+        let uevent =
+            "DRIVER=hid-generic\nHID_ID=0003:000005F3:000000FF\nHID_NAME=VEC  VEC USB Footpedal\n";
+        assert_eq!(
+            backend::parse_uevent(uevent),
+            Some(("VEC  VEC USB Footpedal".to_string(), 0x05F3, 0x00FF))
+        );
+    }
+}
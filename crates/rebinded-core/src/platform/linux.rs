@@ -0,0 +1,3198 @@
+//! Linux-specific platform implementation
+//!
+//! Key components:
+//! - evdev for raw input device access and virtual device creation
+//! - X11 (via x11rb) for window queries
+//! - D-Bus (via zbus) for MPRIS media control and PulseAudio volume
+
+use super::{
+    DISPLAY_OFF_DELAY, DesktopCommand, EventResponse, FocusTarget, HeldKeys, MediaCommand,
+    MicCommand, MonitorInfo, PlatformInterface, PlatformState, PlayerTarget, PointerButton,
+    PointerEvent, Rect, SessionCommand, SnapPosition, SyntheticKey, WindowCache, WindowSwitch,
+    window_switch_target,
+};
+use crate::config::{WindowCondition, WindowInfo};
+use crate::key::{InputEvent, KeyCode, KeyEvent, MouseButton};
+use crate::strategy::PlatformHandle;
+use anyhow::{Context, Result, anyhow};
+use evdev::uinput::VirtualDevice;
+use evdev::{Device, EventType, RelativeAxisCode};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fs::OpenOptions;
+use std::future::Future;
+use std::os::fd::{AsFd, AsRawFd, OwnedFd};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc as StdArc, Mutex as StdMutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, mpsc};
+use tracing::{debug, info, trace, warn};
+
+// ============================================================================
+// Key Name Resolution
+// ============================================================================
+
+/// Get human-readable key name from Linux evdev code
+pub fn get_key_name(code: u32) -> String {
+    if code > u16::MAX as u32 {
+        return format!("UNKNOWN_{:#06X}", code);
+    }
+    format!("{:?}", evdev::KeyCode::new(code as u16))
+}
+
+/// Build reverse lookup map: name -> evdev code
+pub fn build_key_name_map() -> HashMap<String, u32> {
+    let mut map = HashMap::new();
+
+    // Probe evdev key range (0-767 covers all standard keys)
+    for code in 0..768u32 {
+        let name = get_key_name(code);
+        if !name.starts_with("UNKNOWN") {
+            let normalized = name.to_lowercase();
+            map.insert(normalized.clone(), code);
+
+            // Strip "KEY_" prefix for convenience: "KEY_F13" -> "f13"
+            if let Some(short) = normalized.strip_prefix("key_") {
+                map.insert(short.to_string(), code);
+            }
+            // Strip "BTN_" prefix for buttons
+            if let Some(short) = normalized.strip_prefix("btn_") {
+                map.insert(short.to_string(), code);
+            }
+        }
+    }
+
+    map
+}
+
+/// Names for the characters keys type under the active X11 keyboard layout
+/// (e.g. "ß" or "é"), keyed to evdev codes. Empty without X11.
+pub fn build_layout_key_name_map() -> HashMap<String, u32> {
+    let (min_keycode, keysyms_per_keycode, keysyms) = match x11_keyboard_mapping() {
+        Ok(mapping) => mapping,
+        Err(e) => {
+            debug!("no keyboard layout names: {e}");
+            return HashMap::new();
+        }
+    };
+
+    let mut map = HashMap::new();
+    for (i, syms) in keysyms
+        .chunks(keysyms_per_keycode.max(1) as usize)
+        .enumerate()
+    {
+        // X11 keycodes are evdev codes offset by 8
+        let Some(code) = (u32::from(min_keycode) + i as u32).checked_sub(8) else {
+            continue;
+        };
+        if let Some(c) = syms.first().and_then(|&sym| keysym_char(sym)) {
+            map.entry(c.to_lowercase().to_string()).or_insert(code);
+        }
+    }
+    map
+}
+
+/// Identifies the active keyboard layout, changing whenever it does
+pub fn layout_fingerprint() -> Option<u64> {
+    use std::hash::{DefaultHasher, Hash, Hasher};
+
+    let mapping = x11_keyboard_mapping().ok()?;
+    let mut hasher = DefaultHasher::new();
+    mapping.hash(&mut hasher);
+    Some(hasher.finish())
+}
+
+/// The printable character a keysym types: Latin-1 keysyms match their code
+/// point, and Unicode keysyms carry it with a `0x01000000` tag
+fn keysym_char(keysym: u32) -> Option<char> {
+    let code_point = match keysym {
+        0x21..=0x7E | 0xA1..=0xFF => keysym,
+        0x0100_0100..=0x0110_FFFF => keysym - 0x0100_0000,
+        _ => return None,
+    };
+    char::from_u32(code_point).filter(|c| !c.is_whitespace() && !c.is_control())
+}
+
+/// Connection used for keyboard layout queries, reopened after an error
+static LAYOUT_X11: StdMutex<Option<x11rb::rust_connection::RustConnection>> = StdMutex::new(None);
+
+/// The X11 keyboard mapping: first keycode, keysyms per keycode, and keysyms
+fn x11_keyboard_mapping() -> Result<(u8, u8, Vec<u32>)> {
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::ConnectionExt;
+
+    let mut slot = LAYOUT_X11.lock().unwrap_or_else(|e| e.into_inner());
+    let conn = match slot.take() {
+        Some(conn) => conn,
+        None => x11rb::connect(None).context("failed to connect to X11")?.0,
+    };
+    let (min, max) = (conn.setup().min_keycode, conn.setup().max_keycode);
+    let reply = conn
+        .get_keyboard_mapping(min, max - min + 1)?
+        .reply()
+        .context("failed to read keyboard mapping")?;
+    *slot = Some(conn);
+    Ok((min, reply.keysyms_per_keycode, reply.keysyms))
+}
+
+/// Connection used for monitor queries, reopened after an error
+static MONITOR_X11: StdMutex<Option<(x11rb::rust_connection::RustConnection, usize)>> =
+    StdMutex::new(None);
+
+/// The RandR monitor holding the centre of the focused X11 window
+///
+/// Looked up on each call rather than cached with the focused window, since
+/// windows move between monitors without losing focus.
+pub fn active_monitor() -> Option<MonitorInfo> {
+    match x11_active_monitor() {
+        Ok(monitor) => monitor,
+        Err(e) => {
+            warn_once!(
+                "X11 monitor query failed: {}. Monitor conditions will not work.",
+                e
+            );
+            None
+        }
+    }
+}
+
+fn x11_active_monitor() -> Result<Option<MonitorInfo>> {
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::ConnectionExt as _;
+
+    let mut slot = MONITOR_X11.lock().unwrap_or_else(|e| e.into_inner());
+    let (conn, screen_num) = match slot.take() {
+        Some(conn) => conn,
+        None => x11rb::connect(None).context("failed to connect to X11")?,
+    };
+    let root = conn.setup().roots[screen_num].root;
+
+    let monitor = match get_x11_active_window(&conn, root)? {
+        Some(window) => match x11_window_monitor(&conn, root, window)? {
+            Some((index, monitor)) => Some(MonitorInfo {
+                index: index as u32 + 1,
+                name: String::from_utf8_lossy(&conn.get_atom_name(monitor.name)?.reply()?.name)
+                    .into_owned(),
+            }),
+            None => None,
+        },
+        None => None,
+    };
+    *slot = Some((conn, screen_num));
+    Ok(monitor)
+}
+
+/// The RandR monitor containing the centre of `window`, and its position in
+/// the monitor list
+fn x11_window_monitor(
+    conn: &x11rb::rust_connection::RustConnection,
+    root: u32,
+    window: u32,
+) -> Result<Option<(usize, x11rb::protocol::randr::MonitorInfo)>> {
+    use x11rb::protocol::randr::ConnectionExt as _;
+    use x11rb::protocol::xproto::ConnectionExt as _;
+
+    let geometry = conn.get_geometry(window)?.reply()?;
+    let origin = conn.translate_coordinates(window, root, 0, 0)?.reply()?;
+    let x = i32::from(origin.dst_x) + i32::from(geometry.width) / 2;
+    let y = i32::from(origin.dst_y) + i32::from(geometry.height) / 2;
+
+    let monitors = conn.randr_get_monitors(root, true)?.reply()?.monitors;
+    Ok(monitors.into_iter().enumerate().find(|(_, monitor)| {
+        let (left, top) = (i32::from(monitor.x), i32::from(monitor.y));
+        (left..left + i32::from(monitor.width)).contains(&x)
+            && (top..top + i32::from(monitor.height)).contains(&y)
+    }))
+}
+
+// ============================================================================
+// Platform Implementation
+// ============================================================================
+
+/// Linux platform implementation
+///
+/// Every field is shared, so clones (as held by `PlatformHandle`) use the same
+/// connections and virtual keyboard.
+#[derive(Clone)]
+pub struct Platform {
+    /// X11 connection for window queries (lazy-initialized)
+    x11_conn: Option<StdArc<Mutex<X11Connection>>>,
+    /// D-Bus connection for media control (lazy-initialized)
+    dbus_conn: Option<StdArc<zbus::Connection>>,
+    /// Virtual keyboard device for key injection (lazy-initialized)
+    /// Uses std::sync::Mutex (not tokio) to ensure synchronous, ordered event emission
+    uinput_device: Option<StdArc<StdMutex<VirtualDevice>>>,
+    /// MPRIS player state tracker for smart player selection
+    mpris_tracker: StdArc<Mutex<MprisPlayerTracker>>,
+    /// Focused window, key routes and the rest shared with the engine
+    state: StdArc<PlatformState>,
+}
+
+/// X11 connection wrapper
+struct X11Connection {
+    conn: x11rb::rust_connection::RustConnection,
+    screen_num: usize,
+}
+
+impl Default for Platform {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PlatformInterface for Platform {
+    fn new() -> Self {
+        Self {
+            x11_conn: None,
+            dbus_conn: None,
+            uinput_device: None,
+            mpris_tracker: StdArc::new(Mutex::new(MprisPlayerTracker::new())),
+            state: StdArc::default(),
+        }
+    }
+
+    async fn run<F, Fut, S>(&mut self, mut handler: F, shutdown: S) -> Result<()>
+    where
+        F: FnMut(InputEvent, PlatformHandle) -> Fut,
+        Fut: Future<Output = EventResponse>,
+        S: Future<Output = ()>,
+    {
+        info!("starting Linux input handler");
+
+        // Check permissions early with helpful error messages
+        check_permissions()?;
+
+        // Find and grab all keyboard devices
+        let devices = find_keyboard_devices().await?;
+        if devices.is_empty() {
+            return Err(anyhow!("no keyboard devices found"));
+        }
+
+        info!("found {} keyboard device(s)", devices.len());
+
+        let mut grabbed_devices = Vec::new();
+        for path in devices {
+            match grab_device(&path).await {
+                Ok(device) => {
+                    info!(
+                        "grabbed device: {} ({})",
+                        device.name().unwrap_or("unknown"),
+                        path.display()
+                    );
+                    grabbed_devices.push((path, device));
+                }
+                Err(e) => {
+                    warn!("failed to grab {:?}: {}", path, e);
+                }
+            }
+        }
+
+        if grabbed_devices.is_empty() {
+            return Err(anyhow!("failed to grab any keyboard devices"));
+        }
+
+        // Create virtual device for re-injection
+        let uinput = StdArc::new(StdMutex::new(create_virtual_keyboard().await?));
+        self.uinput_device = Some(StdArc::clone(&uinput));
+        *VIRTUAL_KEYBOARD.lock().unwrap_or_else(|e| e.into_inner()) = Some(StdArc::clone(&uinput));
+        info!("created virtual keyboard for re-injection");
+
+        // From here on, leaving this function (even by panicking) releases everything
+        let mut cleanup = InputCleanup {
+            uinput,
+            grabs: grabbed_devices
+                .iter()
+                .filter_map(|(_, device)| device.as_fd().try_clone_to_owned().ok())
+                .collect(),
+            tasks: Vec::new(),
+        };
+
+        // Create event channel for merging device streams
+        let (event_tx, mut event_rx) =
+            mpsc::unbounded_channel::<(evdev::InputEvent, Instant, PathBuf)>();
+
+        // Spawn task for each device
+        let mut device_tasks = Vec::new();
+        for (path, device) in grabbed_devices {
+            let tx = event_tx.clone();
+            let task = tokio::spawn(async move {
+                if let Err(e) = process_device_events(device, path.clone(), tx).await {
+                    warn!("device {} stopped: {}", path.display(), e);
+                }
+            });
+            cleanup.tasks.push(task.abort_handle());
+            device_tasks.push(task);
+        }
+        drop(event_tx); // Drop original sender so channel closes when all tasks exit
+
+        // MIDI devices, gamepads, HID devices, lircd and cec-client are only
+        // read, and only while the config uses them. Triggers arrive over HTTP.
+        let (shared_tx, mut shared_rx) = mpsc::unbounded_channel::<InputEvent>();
+        let state = &self.state;
+        for task in [
+            tokio::spawn(super::midi::forward(state.clone(), shared_tx.clone())),
+            tokio::spawn(super::gamepad::forward(state.clone(), shared_tx.clone())),
+            tokio::spawn(super::hid::forward(state.clone(), shared_tx.clone())),
+            tokio::spawn(super::lirc::forward(state.clone(), shared_tx.clone())),
+            tokio::spawn(super::cec::forward(state.clone(), shared_tx.clone())),
+            tokio::spawn(super::trigger::forward(
+                StdArc::clone(&self.state),
+                shared_tx,
+            )),
+        ] {
+            cleanup.tasks.push(task.abort_handle());
+        }
+
+        // Follow focus changes so window conditions are a cheap read
+        let state = StdArc::clone(&self.state);
+        std::thread::spawn(move || follow_x11_focus(&state));
+        let state = StdArc::clone(&self.state);
+        std::thread::spawn(move || follow_x11_layout(&state));
+
+        // Spawn MPRIS focus monitor task
+        // This tracks which media player windows are focused for smarter player selection
+        let tracker = StdArc::clone(&self.mpris_tracker);
+        let x11_conn = self.x11_conn.as_ref().map(StdArc::clone);
+        let state = StdArc::clone(&self.state);
+        tokio::spawn(async move {
+            mpris_focus_monitor(x11_conn, state, tracker).await;
+        });
+
+        // Create platform handle for handler
+        let platform_handle = PlatformHandle::new(self);
+
+        // Set up XInput2 pointer handling (scroll wheel and mouse button bindings
+        // without grabbing the mouse)
+        let (mut pointer_rx, replay_tx) = match setup_xinput2_pointer_grab() {
+            Ok((rx, tx)) => {
+                info!(
+                    "XInput2 pointer grab active - scroll wheel and mouse button bindings enabled"
+                );
+                (Some(rx), Some(tx))
+            }
+            Err(e) => {
+                warn!(
+                    "failed to set up XInput2 pointer grab: {}. Scroll wheel and mouse button bindings will not work.",
+                    e
+                );
+                (None, None)
+            }
+        };
+
+        // Process events from both evdev (keyboard) and XInput2 (scroll, mouse buttons)
+        // Mouse movement is not grabbed, so it goes directly through the physical device
+        let mut shutdown = std::pin::pin!(shutdown);
+        loop {
+            tokio::select! {
+                () = &mut shutdown => {
+                    info!("shutdown requested");
+                    break;
+                }
+
+
+                // Handle keyboard events from evdev
+                Some((raw_event, received, _device_path)) = event_rx.recv() => {
+                    // Only process KEY events
+                    if raw_event.event_type() != EventType::KEY {
+                        continue;
+                    }
+
+                    // Convert evdev InputEvent to our InputEvent
+                    let Some(input_event) = convert_event(&raw_event, received) else {
+                        continue;
+                    };
+
+                    trace!(?input_event, "processing keyboard event");
+
+                    // Unbound keys (nearly all of them) and keys with a fixed
+                    // response skip the handler
+                    let fixed = match &input_event {
+                        InputEvent::Key(key_event) => self.state.fixed_response(key_event.key),
+                        _ => None,
+                    };
+                    let response = match fixed {
+                        Some(response) => response,
+                        None => handler(input_event, platform_handle.clone()).await,
+                    };
+
+                    // Re-inject if passthrough
+                    if response == EventResponse::Passthrough
+                        && let Some(ref uinput) = self.uinput_device
+                    {
+                        let mut dev = uinput.lock().unwrap();
+                        if let Err(e) = dev.emit(&[raw_event]) {
+                            warn!("failed to emit passthrough event: {}", e);
+                        } else if raw_event.value() != 2 {
+                            HELD_KEYS.record(raw_event.code().into(), raw_event.value() == 1);
+                        }
+                    }
+                }
+
+                // MIDI, gamepad, HID, IR, CEC and triggered input isn't
+                // grabbed, so there is nothing to re-inject on passthrough
+                Some(input_event) = shared_rx.recv() => {
+                    trace!(?input_event, "processing MIDI, gamepad, HID, IR, CEC or triggered event");
+                    let fixed = match &input_event {
+                        InputEvent::Key(key_event) => self.state.fixed_response(key_event.key),
+                        _ => None,
+                    };
+                    if fixed.is_none() {
+                        handler(input_event, platform_handle.clone()).await;
+                    }
+                }
+
+                // Handle scroll and mouse button events from XInput2
+                Some(input_event) = async {
+                    match &mut pointer_rx {
+                        Some(rx) => rx.recv().await,
+                        None => std::future::pending().await,
+                    }
+                } => {
+                    trace!(?input_event, "processing pointer event from XInput2");
+
+                    // Call user handler
+                    let response = handler(input_event, platform_handle.clone()).await;
+
+                    // Send replay decision back to X11 thread
+                    // If passthrough: replay the event (as if grab never happened)
+                    // If blocked: do nothing (grab already consumed the event)
+                    if let Some(ref tx) = replay_tx {
+                        let should_replay = response == EventResponse::Passthrough;
+                        if let Err(e) = tx.send(should_replay) {
+                            warn!("failed to send pointer replay decision: {}", e);
+                        }
+                    }
+                }
+
+                // Exit when all event sources are closed
+                else => {
+                    info!("all event streams closed");
+                    break;
+                }
+            }
+        }
+
+        drop(cleanup);
+        for task in device_tasks {
+            let _ = task.await;
+        }
+        info!("input devices released");
+        Ok(())
+    }
+
+    fn state(&self) -> &StdArc<PlatformState> {
+        &self.state
+    }
+
+    fn get_active_window(&self) -> WindowInfo {
+        // Followed focus, else X11, falling back to empty on any error
+        match current_window(&self.state, self.x11_conn.as_ref()) {
+            Ok(info) => info,
+            Err(e) => {
+                warn_once!(
+                    "X11 window query failed: {}. Window conditions will not work.",
+                    e
+                );
+                WindowInfo::default()
+            }
+        }
+    }
+
+    fn send_key(&self, key: SyntheticKey) {
+        let uinput = match &self.uinput_device {
+            Some(device) => StdArc::clone(device),
+            None => {
+                warn!("uinput device not initialized");
+                return;
+            }
+        };
+
+        // Map to key combinations
+        let events = match key {
+            SyntheticKey::BrowserBack => create_key_combo(&[
+                (evdev::KeyCode::KEY_LEFTALT, true),
+                (evdev::KeyCode::KEY_LEFT, true),
+                (evdev::KeyCode::KEY_LEFT, false),
+                (evdev::KeyCode::KEY_LEFTALT, false),
+            ]),
+            SyntheticKey::BrowserForward => create_key_combo(&[
+                (evdev::KeyCode::KEY_LEFTALT, true),
+                (evdev::KeyCode::KEY_RIGHT, true),
+                (evdev::KeyCode::KEY_RIGHT, false),
+                (evdev::KeyCode::KEY_LEFTALT, false),
+            ]),
+        };
+
+        // Emit in separate task to avoid blocking the handler
+        tokio::spawn(async move {
+            let mut dev = uinput.lock().unwrap();
+            if let Err(e) = dev.emit(&events) {
+                warn!("failed to emit synthetic key: {}", e);
+            } else {
+                debug!(?key, "emitted synthetic key");
+            }
+        });
+    }
+
+    fn send_raw_key(&self, key: KeyCode, down: bool) {
+        let Some(ref uinput) = self.uinput_device else {
+            warn!("uinput device not initialized");
+            return;
+        };
+        let Ok(code) = u16::try_from(key.code()) else {
+            warn!(?key, "key code out of evdev range");
+            return;
+        };
+
+        // Emit synchronously so consecutive press/release calls stay ordered
+        let events = create_key_combo(&[(evdev::KeyCode::new(code), down)]);
+        let mut dev = uinput.lock().unwrap();
+        if let Err(e) = dev.emit(&events) {
+            warn!("failed to emit raw key: {}", e);
+        } else {
+            HELD_KEYS.record(code.into(), down);
+            trace!(?key, down, "emitted raw key");
+        }
+    }
+
+    fn send_pointer(&self, event: PointerEvent) {
+        let Some(pointer) = virtual_pointer() else {
+            return;
+        };
+        let rel = |axis: RelativeAxisCode, value: i32| {
+            evdev::InputEvent::new(EventType::RELATIVE.0, axis.0, value)
+        };
+        let mut events: Vec<_> = match event {
+            PointerEvent::Move { dx, dy } => vec![
+                rel(RelativeAxisCode::REL_X, dx),
+                rel(RelativeAxisCode::REL_Y, dy),
+            ],
+            PointerEvent::Button { button, down } => {
+                let code = match button {
+                    PointerButton::Left => evdev::KeyCode::BTN_LEFT,
+                    PointerButton::Right => evdev::KeyCode::BTN_RIGHT,
+                    PointerButton::Middle => evdev::KeyCode::BTN_MIDDLE,
+                };
+                vec![evdev::InputEvent::new(
+                    EventType::KEY.0,
+                    code.0,
+                    down.into(),
+                )]
+            }
+            PointerEvent::Scroll { dx, dy } => vec![
+                rel(RelativeAxisCode::REL_HWHEEL, dx),
+                rel(RelativeAxisCode::REL_WHEEL, dy),
+            ],
+        };
+        events.push(create_syn_report());
+
+        // Emit synchronously, like raw keys, so button edges stay ordered
+        let mut dev = pointer.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = dev.emit(&events) {
+            warn!("failed to emit pointer event: {}", e);
+        } else {
+            trace!(?event, "emitted pointer event");
+        }
+    }
+
+    fn send_media(&self, cmd: MediaCommand) {
+        // Clone the D-Bus connection (will be lazy-initialized on first use)
+        let dbus_conn = self.dbus_conn.as_ref().map(StdArc::clone);
+        let tracker = StdArc::clone(&self.mpris_tracker);
+
+        // Capture current window info for smart player selection (before spawning)
+        let window_info = self.get_active_window();
+
+        tokio::spawn(async move {
+            // Handle volume commands via pactl (PulseAudio/PipeWire)
+            match cmd {
+                MediaCommand::VolumeUp | MediaCommand::VolumeDown | MediaCommand::VolumeMute => {
+                    send_volume_command(cmd).await;
+                    return;
+                }
+                _ => {}
+            }
+
+            // Handle media commands via MPRIS D-Bus with smart player selection
+            if let Err(e) = send_mpris_command(dbus_conn, cmd, &window_info, tracker).await {
+                warn!("media command {:?} failed: {}", cmd, e);
+            }
+        });
+    }
+
+    fn change_volume(&self, percent: i32) {
+        tokio::spawn(async move {
+            run_pactl(
+                "set-sink-volume",
+                "@DEFAULT_SINK@",
+                &format!("{percent:+}%"),
+            )
+            .await;
+        });
+    }
+
+    fn send_player_media(&self, player: &PlayerTarget, cmd: MediaCommand) {
+        let dbus_conn = self.dbus_conn.as_ref().map(StdArc::clone);
+        let player = player.clone();
+
+        tokio::spawn(async move {
+            if let Err(e) = send_mpris_command_to(dbus_conn, &player, cmd).await {
+                warn!("media command {:?} for {} failed: {}", cmd, player, e);
+            }
+        });
+    }
+
+    fn set_mic(&self, cmd: MicCommand) {
+        let arg = match cmd {
+            MicCommand::Mute => "1",
+            MicCommand::Unmute => "0",
+            MicCommand::Toggle => "toggle",
+        };
+        tokio::spawn(async move {
+            run_pactl("set-source-mute", "@DEFAULT_SOURCE@", arg).await;
+        });
+    }
+
+    fn switch_desktop(&self, cmd: DesktopCommand) {
+        tokio::spawn(async move {
+            let result = if std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some() {
+                switch_desktop_hyprland(cmd).await
+            } else if std::env::var_os("SWAYSOCK").is_some() {
+                switch_desktop_sway(cmd).await
+            } else {
+                tokio::task::spawn_blocking(move || switch_desktop_x11(cmd))
+                    .await
+                    .map_err(|e| anyhow!("desktop switch task failed: {e}"))
+                    .and_then(|r| r)
+            };
+
+            match result {
+                Ok(()) => debug!(?cmd, "switched desktop"),
+                Err(e) => warn!(?cmd, "desktop switch failed: {e:#}"),
+            }
+        });
+    }
+
+    fn switch_window(&self, cmd: WindowSwitch) {
+        tokio::spawn(async move {
+            let result = if std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some() {
+                switch_window_hyprland(cmd).await
+            } else if std::env::var_os("SWAYSOCK").is_some() {
+                switch_window_sway(cmd).await
+            } else {
+                tokio::task::spawn_blocking(move || switch_window_x11(cmd))
+                    .await
+                    .map_err(|e| anyhow!("window switch task failed: {e}"))
+                    .and_then(|r| r)
+            };
+
+            match result {
+                Ok(()) => debug!(?cmd, "switched window"),
+                Err(e) => warn!(?cmd, "window switch failed: {e:#}"),
+            }
+        });
+    }
+
+    fn snap_window(&self, position: SnapPosition) {
+        tokio::spawn(async move {
+            let result = if std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some() {
+                snap_window_hyprland(position).await
+            } else if std::env::var_os("SWAYSOCK").is_some() {
+                snap_window_sway(position).await
+            } else {
+                tokio::task::spawn_blocking(move || snap_window_x11(position))
+                    .await
+                    .map_err(|e| anyhow!("window snap task failed: {e}"))
+                    .and_then(|r| r)
+            };
+
+            match result {
+                Ok(()) => debug!(?position, "snapped window"),
+                Err(e) => warn!(?position, "window snap failed: {e:#}"),
+            }
+        });
+    }
+
+    fn control_session(&self, cmd: SessionCommand) {
+        tokio::spawn(async move {
+            let result = match cmd {
+                SessionCommand::Lock => lock_session().await,
+                SessionCommand::Sleep => suspend().await,
+                SessionCommand::DisplayOff => display_off().await,
+            };
+            match result {
+                Ok(()) => debug!(?cmd, "session command sent"),
+                Err(e) => warn!(?cmd, "session command failed: {e:#}"),
+            }
+        });
+    }
+
+    fn focus_window(&self, target: &FocusTarget) {
+        let target = target.clone();
+        tokio::spawn(async move {
+            let window = target.window.clone();
+            let result = if std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some() {
+                focus_window_hyprland(&window).await
+            } else if std::env::var_os("SWAYSOCK").is_some() {
+                focus_window_sway(&window).await
+            } else {
+                tokio::task::spawn_blocking(move || focus_window_x11(&window))
+                    .await
+                    .unwrap_or_else(|e| Err(anyhow!("focus task panicked: {e}")))
+            };
+            match result {
+                Ok(true) => debug!(window = ?target.window, "focused window"),
+                Ok(false) => target.launch_fallback(),
+                Err(e) => warn!(window = ?target.window, "focus failed: {e:#}"),
+            }
+        });
+    }
+
+    fn process_running(&self, binary: &str) -> bool {
+        let Ok(entries) = std::fs::read_dir("/proc") else {
+            return false;
+        };
+        entries
+            .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<u32>().ok())
+            .map(binary_for_pid)
+            // Empty for other users' processes, whose executable can't be read
+            .any(|name| !name.is_empty() && glob_match::glob_match(binary, &name))
+    }
+
+    fn notify(&self, message: &str) {
+        let dbus_conn = self.dbus_conn.as_ref().map(StdArc::clone);
+        let message = message.to_string();
+
+        tokio::spawn(async move {
+            if let Err(e) = send_notification(dbus_conn, &message).await {
+                warn!("notification failed: {e:#}");
+            }
+        });
+    }
+
+    fn set_clipboard(&self, text: &str, paste: bool) {
+        let uinput = self.uinput_device.as_ref().map(StdArc::clone);
+        let text = text.to_string();
+
+        tokio::spawn(async move {
+            let result = if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+                set_clipboard_wayland(&text).await
+            } else {
+                tokio::task::spawn_blocking(move || set_clipboard_x11(text))
+                    .await
+                    .map_err(|e| anyhow!("clipboard task failed: {e}"))
+                    .and_then(|r| r)
+            };
+            if let Err(e) = result {
+                warn!("setting clipboard failed: {e:#}");
+                return;
+            }
+            debug!(paste, "set clipboard");
+
+            if paste {
+                let Some(uinput) = uinput else {
+                    warn!("uinput device not initialized");
+                    return;
+                };
+                let events = create_key_combo(&[
+                    (evdev::KeyCode::KEY_LEFTCTRL, true),
+                    (evdev::KeyCode::KEY_V, true),
+                    (evdev::KeyCode::KEY_V, false),
+                    (evdev::KeyCode::KEY_LEFTCTRL, false),
+                ]);
+                if let Err(e) = uinput.lock().unwrap().emit(&events) {
+                    warn!("failed to emit paste: {}", e);
+                }
+            }
+        });
+    }
+
+    fn get_clipboard(&self) -> Option<String> {
+        let result = if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            get_clipboard_wayland()
+        } else {
+            get_clipboard_x11()
+        };
+        result.unwrap_or_else(|e| {
+            warn!("reading clipboard failed: {e:#}");
+            None
+        })
+    }
+}
+
+// ============================================================================
+// XInput2 Pointer Handling
+// ============================================================================
+
+/// X11 buttons for the scroll wheel (4=up, 5=down)
+const SCROLL_BUTTONS: [u32; 2] = [4, 5];
+
+/// The X11 button number of each interceptable mouse button
+fn x11_button(button: MouseButton) -> u32 {
+    match button {
+        MouseButton::Middle => 2,
+        MouseButton::Back => 8,
+        MouseButton::Forward => 9,
+    }
+}
+
+/// The event for an X11 button press or release, if it is one we intercept
+fn pointer_event(detail: u32, down: bool) -> Option<InputEvent> {
+    match detail {
+        4 | 5 if down => Some(InputEvent::Scroll { up: detail == 4 }),
+        _ => MouseButton::ALL
+            .into_iter()
+            .find(|&button| x11_button(button) == detail)
+            .map(|button| InputEvent::MouseButton { button, down }),
+    }
+}
+
+/// Set up XInput2 passive grabs for the scroll wheel and interceptable mouse buttons
+/// Returns channels for pointer events (forward) and replay decisions (back)
+fn setup_xinput2_pointer_grab() -> Result<(
+    mpsc::UnboundedReceiver<InputEvent>,
+    mpsc::UnboundedSender<bool>,
+)> {
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xinput::{self, ConnectionExt as XInputExt, EventMask};
+    use x11rb::protocol::xproto::GrabStatus;
+
+    let (pointer_tx, pointer_rx) = mpsc::unbounded_channel::<InputEvent>();
+    let (replay_tx, mut replay_rx) = mpsc::unbounded_channel::<bool>();
+
+    // Connect to X11
+    let (conn, screen_num) =
+        x11rb::connect(None).context("failed to connect to X11 for pointer grab")?;
+    let screen = &conn.setup().roots[screen_num];
+    let root = screen.root;
+
+    // Query XInput2 extension (need version 2.0+)
+    let xi_info = conn
+        .xinput_xi_query_version(2, 0)?
+        .reply()
+        .context("failed to query XInput2 version")?;
+    info!(
+        "XInput2 version {}.{}",
+        xi_info.major_version, xi_info.minor_version
+    );
+
+    // Query all XInput2 devices and build whitelist of physical pointer devices
+    // This prevents re-capturing our own re-injected events from the virtual device
+    let devices_reply = conn
+        .xinput_xi_query_device(xinput::Device::ALL)?
+        .reply()
+        .context("failed to query XInput2 devices")?;
+
+    let mut physical_pointer_ids: HashSet<u16> = HashSet::new();
+    for info in &devices_reply.infos {
+        // Only include slave pointer devices (physical input devices)
+        if info.type_ == xinput::DeviceType::SLAVE_POINTER {
+            let name = String::from_utf8_lossy(&info.name);
+            // Exclude virtual devices by name pattern
+            if !name.contains("Virtual")
+                && !name.contains("XTEST")
+                && !name.contains(VIRTUAL_DEVICE_NAME)
+                && !name.contains(VIRTUAL_POINTER_NAME)
+            {
+                physical_pointer_ids.insert(info.deviceid);
+                debug!(
+                    "Whitelisted physical pointer: {} (id={})",
+                    name.trim_end_matches('\0'),
+                    info.deviceid
+                );
+            }
+        }
+    }
+    info!(
+        "Found {} physical pointer device(s) for pointer filtering",
+        physical_pointer_ids.len()
+    );
+
+    // CRITICAL: Select events on root window BEFORE setting up grabs
+    // Without this, the X11 server won't deliver any events to us
+    let mask = xinput::XIEventMask::BUTTON_PRESS | xinput::XIEventMask::BUTTON_RELEASE;
+    let event_mask = EventMask {
+        deviceid: xinput::Device::ALL.into(),
+        mask: vec![mask],
+    };
+    conn.xinput_xi_select_events(root, &[event_mask])?;
+    debug!("XInput2 event selection registered on root window");
+
+    // Set up passive grabs for the scroll wheel and the interceptable buttons
+    // (never left/right, see `MouseButton`)
+    // Use device ID 2 (VCP - Virtual Core Pointer) for passive grabs
+    let vcp_device_id: u16 = 2;
+    let buttons = SCROLL_BUTTONS
+        .into_iter()
+        .chain(MouseButton::ALL.into_iter().map(x11_button));
+    for button in buttons {
+        let result = conn.xinput_xi_passive_grab_device(
+            x11rb::CURRENT_TIME,
+            root,
+            0, // no cursor change
+            button,
+            vcp_device_id,
+            xinput::GrabType::BUTTON,
+            xinput::GrabMode22::ASYNC,
+            x11rb::protocol::xproto::GrabMode::ASYNC,
+            xinput::GrabOwner::OWNER,
+            &[u32::from(mask)],
+            &[0], // any modifier
+        )?;
+        let reply = result.reply()?;
+        if !reply.modifiers.is_empty() && reply.modifiers[0].status != GrabStatus::SUCCESS {
+            warn!(
+                "failed to grab button {}: {:?}",
+                button, reply.modifiers[0].status
+            );
+        } else {
+            debug!("passive grab set up for button {}", button);
+        }
+    }
+    conn.flush()?;
+
+    // Spawn blocking thread to handle X11 events and replay decisions
+    std::thread::spawn(move || {
+        loop {
+            // Non-blocking check for replay decisions first
+            while let Ok(should_replay) = replay_rx.try_recv() {
+                if should_replay {
+                    // Replay the event using XIAllowEvents with REPLAY_DEVICE mode
+                    // This causes the frozen event to be replayed as if the grab never happened
+                    use x11rb::protocol::xinput::{ConnectionExt as _, EventMode};
+                    if let Err(e) = conn.xinput_xi_allow_events(
+                        x11rb::CURRENT_TIME,
+                        xinput::Device::ALL,
+                        EventMode::REPLAY_DEVICE,
+                        0, // touchid (not used for button events)
+                        x11rb::NONE,
+                    ) {
+                        warn!("failed to replay pointer event: {}", e);
+                    }
+                } else {
+                    // Event was blocked, grab already consumed it - nothing to do
+                }
+            }
+
+            // Wait for next X11 event
+            match conn.wait_for_event() {
+                Ok(event) => {
+                    // Handle scroll presses and mouse button presses/releases
+                    // Filter by sourceid to only accept events from physical devices,
+                    // preventing infinite loops from previously re-injected events
+                    let pointer_event = match &event {
+                        x11rb::protocol::Event::XinputButtonPress(ev)
+                            if physical_pointer_ids.contains(&ev.sourceid) =>
+                        {
+                            pointer_event(ev.detail, true)
+                        }
+                        x11rb::protocol::Event::XinputButtonRelease(ev)
+                            if physical_pointer_ids.contains(&ev.sourceid) =>
+                        {
+                            pointer_event(ev.detail, false)
+                        }
+                        _ => None,
+                    };
+
+                    if let Some(input_event) = pointer_event
+                        && pointer_tx.send(input_event).is_err()
+                    {
+                        break; // Channel closed
+                    }
+                }
+                Err(e) => {
+                    warn!("X11 event error: {}", e);
+                    break;
+                }
+            }
+        }
+    });
+
+    Ok((pointer_rx, replay_tx))
+}
+
+// ============================================================================
+// Device Management
+// ============================================================================
+
+/// Find keyboard input devices (excluding mice to avoid virtual device sensitivity issues)
+///
+/// We only grab keyboards via evdev. Scroll wheel events are intercepted via XInput2
+/// at the X11 level, so we don't need to grab mouse devices.
+async fn find_keyboard_devices() -> Result<Vec<PathBuf>> {
+    let mut devices = Vec::new();
+
+    for entry in std::fs::read_dir("/dev/input").context("failed to read /dev/input directory")? {
+        let entry = entry?;
+        let path = entry.path();
+
+        // Only check event* devices
+        let Some(filename) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        if !filename.starts_with("event") {
+            continue;
+        }
+
+        // Try to open device to check capabilities
+        let Ok(device) = Device::open(&path) else {
+            continue;
+        };
+
+        // Never capture our own synthetic keys (or another instance's)
+        if device.name() == Some(VIRTUAL_DEVICE_NAME) {
+            debug!("skipping virtual keyboard {}", path.display());
+            continue;
+        }
+
+        // Check if device has keyboard capability (letter keys or function keys)
+        let has_keyboard = device
+            .supported_keys()
+            .map(|keys| {
+                keys.contains(evdev::KeyCode::KEY_A) || keys.contains(evdev::KeyCode::KEY_F1)
+            })
+            .unwrap_or(false);
+
+        // Skip devices with mouse motion (REL_X/REL_Y) - these would cause sensitivity issues
+        // when passed through a virtual device due to libinput's DPI handling
+        let has_mouse_motion = device
+            .supported_relative_axes()
+            .map(|axes| axes.contains(RelativeAxisCode::REL_X))
+            .unwrap_or(false);
+
+        // IR receivers decoded by the kernel (rc-core) only report media and
+        // remote keys, but are grabbed like keyboards so their buttons can be bound
+        let is_remote = is_rc_device(filename);
+
+        if (has_keyboard || is_remote) && !has_mouse_motion {
+            devices.push(path);
+        }
+    }
+
+    Ok(devices)
+}
+
+/// Whether the input device behind `/dev/input/<event>` belongs to an rc-core
+/// IR receiver (`/sys/class/rc/rcN/inputM/<event>`)
+fn is_rc_device(event: &str) -> bool {
+    std::fs::canonicalize(format!("/sys/class/input/{event}/device"))
+        .ok()
+        .and_then(|input| {
+            let parent = input.parent()?.file_name()?.to_str()?.to_string();
+            Some(parent.starts_with("rc") && parent[2..].chars().all(|c| c.is_ascii_digit()))
+        })
+        .unwrap_or(false)
+}
+
+/// Grab a device for exclusive access
+async fn grab_device(path: &Path) -> Result<Device> {
+    let mut device =
+        Device::open(path).with_context(|| format!("failed to open device: {}", path.display()))?;
+
+    device
+        .grab()
+        .with_context(|| format!("failed to grab device: {}", path.display()))?;
+
+    Ok(device)
+}
+
+/// Process events from a single device
+async fn process_device_events(
+    device: Device,
+    device_path: PathBuf,
+    event_tx: mpsc::UnboundedSender<(evdev::InputEvent, Instant, PathBuf)>,
+) -> Result<()> {
+    let mut stream = device.into_event_stream()?;
+
+    loop {
+        match stream.next_event().await {
+            Ok(event) => {
+                // Stamped here, before the event waits behind others in the channel
+                let received = Instant::now();
+                if event_tx
+                    .send((event, received, device_path.clone()))
+                    .is_err()
+                {
+                    // Channel closed, exit
+                    break;
+                }
+            }
+            Err(e) => {
+                return Err(e.into());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Convert evdev InputEvent to our InputEvent type. `received` is when the
+/// device reader got it.
+fn convert_event(ev: &evdev::InputEvent, received: Instant) -> Option<InputEvent> {
+    match ev.event_type() {
+        EventType::KEY => {
+            let key_code = KeyCode::new(ev.code() as u32);
+            // value: 1 = press, 0 = release, 2 = auto-repeat
+            // We only care about press (1) and release (0), ignore auto-repeat
+            let down = ev.value() == 1;
+
+            // Filter out auto-repeat events
+            if ev.value() == 2 {
+                return None;
+            }
+
+            Some(InputEvent::Key(KeyEvent::at(key_code, down, received)))
+        }
+        EventType::RELATIVE => {
+            // REL_WHEEL: value > 0 = up (away from user), value < 0 = down (toward user)
+            if ev.code() == RelativeAxisCode::REL_WHEEL.0 {
+                let up = ev.value() > 0;
+                trace!(
+                    "scroll event captured: {} (value={})",
+                    if up { "up" } else { "down" },
+                    ev.value()
+                );
+                Some(InputEvent::Scroll { up })
+            } else {
+                None // Ignore other relative axes (mouse movement, etc.)
+            }
+        }
+        _ => None, // Ignore all other event types
+    }
+}
+
+// ============================================================================
+// Virtual Device (uinput)
+// ============================================================================
+
+/// Name of the virtual keyboard that passthrough and synthetic keys are injected
+/// through. Capture skips devices with this name, so injected events are never
+/// fed back into the handler (a key-to-key remap would otherwise loop).
+pub(super) const VIRTUAL_DEVICE_NAME: &str = "rebinded-virtual-keyboard";
+
+/// Create a virtual keyboard for re-injecting events
+///
+/// Note: We intentionally DON'T include mouse axes (REL_X/REL_Y) because libinput
+/// applies different DPI handling to virtual devices, causing sensitivity issues.
+/// Mouse events go directly through the physical device (not grabbed).
+/// Scroll wheel is intercepted via XInput2 at the X11 level instead.
+async fn create_virtual_keyboard() -> Result<VirtualDevice> {
+    use evdev::AttributeSet;
+
+    // Create key set with all standard keys (including mouse buttons)
+    let mut keys = AttributeSet::<evdev::KeyCode>::new();
+    for code in 0..=767u16 {
+        keys.insert(evdev::KeyCode::new(code));
+    }
+
+    // Only include scroll wheel axes for re-injection (no mouse motion)
+    let mut relative_axes = AttributeSet::<RelativeAxisCode>::new();
+    relative_axes.insert(RelativeAxisCode::REL_WHEEL); // Scroll wheel
+    relative_axes.insert(RelativeAxisCode::REL_HWHEEL); // Horizontal scroll
+
+    let device = VirtualDevice::builder()?
+        .name(VIRTUAL_DEVICE_NAME)
+        .with_keys(&keys)?
+        .with_relative_axes(&relative_axes)?
+        .build()?;
+
+    Ok(device)
+}
+
+/// Name of the virtual pointer that synthetic pointer input is injected through
+const VIRTUAL_POINTER_NAME: &str = "rebinded-virtual-pointer";
+
+/// The virtual pointer, created on first use so that configs without pointer
+/// control don't add a mouse to the system; `None` if creating it failed
+static VIRTUAL_POINTER: OnceLock<Option<StdMutex<VirtualDevice>>> = OnceLock::new();
+
+/// The virtual pointer, creating it on first call
+///
+/// Unlike the virtual keyboard this one has motion axes, and libinput applies
+/// its pointer acceleration to them like to any other mouse.
+fn virtual_pointer() -> Option<&'static StdMutex<VirtualDevice>> {
+    VIRTUAL_POINTER
+        .get_or_init(|| match create_virtual_pointer() {
+            Ok(device) => {
+                info!("created virtual pointer");
+                Some(StdMutex::new(device))
+            }
+            Err(e) => {
+                warn!("failed to create virtual pointer: {e:#}");
+                None
+            }
+        })
+        .as_ref()
+}
+
+fn create_virtual_pointer() -> Result<VirtualDevice> {
+    use evdev::AttributeSet;
+
+    let mut buttons = AttributeSet::<evdev::KeyCode>::new();
+    buttons.insert(evdev::KeyCode::BTN_LEFT);
+    buttons.insert(evdev::KeyCode::BTN_RIGHT);
+    buttons.insert(evdev::KeyCode::BTN_MIDDLE);
+
+    let mut relative_axes = AttributeSet::<RelativeAxisCode>::new();
+    relative_axes.insert(RelativeAxisCode::REL_X);
+    relative_axes.insert(RelativeAxisCode::REL_Y);
+    relative_axes.insert(RelativeAxisCode::REL_WHEEL);
+    relative_axes.insert(RelativeAxisCode::REL_HWHEEL);
+
+    let device = VirtualDevice::builder()?
+        .name(VIRTUAL_POINTER_NAME)
+        .with_keys(&buttons)?
+        .with_relative_axes(&relative_axes)?
+        .build()?;
+
+    Ok(device)
+}
+
+/// Create a SYN_REPORT synchronization event
+fn create_syn_report() -> evdev::InputEvent {
+    evdev::InputEvent::new(
+        evdev::EventType::SYNCHRONIZATION.0,
+        0, // SYN_REPORT code
+        0,
+    )
+}
+
+/// Keys the virtual keyboard has pressed and not released
+static HELD_KEYS: HeldKeys = HeldKeys::new();
+
+/// The virtual keyboard while `run` is active, for releasing keys from the panic hook
+static VIRTUAL_KEYBOARD: StdMutex<Option<StdArc<StdMutex<VirtualDevice>>>> = StdMutex::new(None);
+
+/// Release every key the virtual keyboard still holds, without access to the
+/// platform. Called from the panic hook, so it never waits on a lock: if the
+/// panicking code holds the device, the cleanup guard releases keys instead.
+pub fn release_held_keys() {
+    let Ok(slot) = VIRTUAL_KEYBOARD.try_lock() else {
+        return;
+    };
+    if let Some(uinput) = slot.as_ref()
+        && let Ok(mut dev) = uinput.try_lock()
+    {
+        emit_held_releases(&mut dev);
+    }
+}
+
+/// Emit a release for every key the virtual keyboard still holds
+fn emit_held_releases(dev: &mut VirtualDevice) {
+    let held = HELD_KEYS.take();
+    if held.is_empty() {
+        return;
+    }
+    let releases: Vec<_> = held
+        .iter()
+        .filter_map(|&code| u16::try_from(code).ok())
+        .map(|code| (evdev::KeyCode::new(code), false))
+        .collect();
+    match dev.emit(&create_key_combo(&releases)) {
+        Ok(()) => info!(count = held.len(), "released held keys"),
+        Err(e) => warn!("failed to release held keys: {e}"),
+    }
+}
+
+/// Undoes the setup done by `run` when it returns or unwinds from a panicking
+/// handler: releases keys the virtual keyboard still holds, ungrabs the
+/// keyboards so they reach the system directly again, and stops the tasks
+/// reading them.
+struct InputCleanup {
+    uinput: StdArc<StdMutex<VirtualDevice>>,
+    /// Duplicates of the grabbed devices' descriptors. A grab belongs to the
+    /// open file, so these can release it whatever state the device tasks are in.
+    grabs: Vec<OwnedFd>,
+    tasks: Vec<tokio::task::AbortHandle>,
+}
+
+/// `_IOW('E', 0x90, int)` from linux/input.h
+const EVIOCGRAB: libc::c_ulong = 0x4004_4590;
+
+impl Drop for InputCleanup {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            warn!("event handler panicked, releasing input devices");
+        }
+        VIRTUAL_KEYBOARD
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take();
+        emit_held_releases(&mut self.uinput.lock().unwrap_or_else(|e| e.into_inner()));
+        for fd in &self.grabs {
+            // SAFETY: EVIOCGRAB with 0 only releases a grab; the descriptor is open
+            unsafe { libc::ioctl(fd.as_raw_fd(), EVIOCGRAB as _, 0) };
+        }
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+/// Create a key combo as evdev InputEvents with proper synchronization
+fn create_key_combo(keys: &[(evdev::KeyCode, bool)]) -> Vec<evdev::InputEvent> {
+    let mut events = Vec::new();
+    for (key, down) in keys {
+        let value = if *down { 1 } else { 0 };
+        events.push(evdev::InputEvent::new(EventType::KEY.0, key.0, value));
+        events.push(create_syn_report());
+    }
+    events
+}
+
+/// Send volume command via pactl (PulseAudio/PipeWire)
+///
+/// Uses `pactl` command to directly control system volume.
+/// This is more reliable than emitting XF86Audio keys, which may not be
+/// recognized by all desktop environments or audio systems.
+async fn send_volume_command(cmd: MediaCommand) {
+    let pactl_arg = match cmd {
+        MediaCommand::VolumeUp => "+2%",
+        MediaCommand::VolumeDown => "-2%",
+        MediaCommand::VolumeMute => "toggle",
+        _ => return,
+    };
+
+    let pactl_cmd = match cmd {
+        MediaCommand::VolumeMute => "set-sink-mute",
+        _ => "set-sink-volume",
+    };
+
+    run_pactl(pactl_cmd, "@DEFAULT_SINK@", pactl_arg).await;
+}
+
+/// Run `pactl <command> <device> <arg>`, logging failures
+async fn run_pactl(pactl_cmd: &str, device: &str, pactl_arg: &str) {
+    let result = tokio::process::Command::new("pactl")
+        .arg(pactl_cmd)
+        .arg(device)
+        .arg(pactl_arg)
+        .output()
+        .await;
+
+    match result {
+        Ok(output) if output.status.success() => {
+            debug!(
+                pactl_cmd,
+                device, pactl_arg, "pactl command executed successfully"
+            );
+        }
+        Ok(output) => {
+            warn!(
+                pactl_cmd,
+                pactl_arg,
+                stderr = ?String::from_utf8_lossy(&output.stderr),
+                "pactl command failed"
+            );
+        }
+        Err(e) => {
+            warn!(pactl_cmd, pactl_arg, error = ?e, "failed to execute pactl command");
+        }
+    }
+}
+
+// ============================================================================
+// Session
+// ============================================================================
+
+/// Lock the screen through logind, which tells the session's locker. A daemon
+/// started outside a login session (e.g. by some service managers) has no
+/// session of its own there, so fall back to `xdg-screensaver`.
+async fn lock_session() -> Result<()> {
+    match run_compositor_command("loginctl", &["lock-session"]).await {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            debug!("loginctl lock-session failed, trying xdg-screensaver: {e:#}");
+            run_compositor_command("xdg-screensaver", &["lock"]).await
+        }
+    }
+}
+
+/// Suspend through logind, as `systemctl suspend` does. Interactive, so polkit
+/// can ask for authorization where local users aren't allowed to by default.
+async fn suspend() -> Result<()> {
+    let conn = zbus::Connection::system()
+        .await
+        .context("failed to connect to the system bus")?;
+    conn.call_method(
+        Some("org.freedesktop.login1"),
+        "/org/freedesktop/login1",
+        Some("org.freedesktop.login1.Manager"),
+        "Suspend",
+        &(true,),
+    )
+    .await
+    .context("logind refused to suspend")?;
+    Ok(())
+}
+
+/// Turn the displays off with the compositor's DPMS control, or `xset` on X11
+async fn display_off() -> Result<()> {
+    // The release of the key that triggered this would wake the displays
+    // straight back up
+    tokio::time::sleep(DISPLAY_OFF_DELAY).await;
+    if std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some() {
+        run_compositor_command("hyprctl", &["dispatch", "dpms", "off"]).await
+    } else if std::env::var_os("SWAYSOCK").is_some() {
+        run_compositor_command("swaymsg", &["output * power off"]).await
+    } else {
+        run_compositor_command("xset", &["dpms", "force", "off"]).await
+    }
+}
+
+// ============================================================================
+// Virtual Desktops
+// ============================================================================
+
+/// Switch workspace via Hyprland's IPC (`hyprctl dispatch workspace`)
+async fn switch_desktop_hyprland(cmd: DesktopCommand) -> Result<()> {
+    let target = match cmd {
+        DesktopCommand::Next => "e+1".to_string(),
+        DesktopCommand::Previous => "e-1".to_string(),
+        DesktopCommand::Goto(n) => n.to_string(),
+    };
+    run_compositor_command("hyprctl", &["dispatch", "workspace", &target]).await
+}
+
+/// Switch workspace via Sway's IPC (`swaymsg workspace`)
+async fn switch_desktop_sway(cmd: DesktopCommand) -> Result<()> {
+    match cmd {
+        DesktopCommand::Next => run_compositor_command("swaymsg", &["workspace", "next"]).await,
+        DesktopCommand::Previous => run_compositor_command("swaymsg", &["workspace", "prev"]).await,
+        DesktopCommand::Goto(n) => {
+            run_compositor_command("swaymsg", &["workspace", "number", &n.to_string()]).await
+        }
+    }
+}
+
+async fn run_compositor_command(program: &str, args: &[&str]) -> Result<()> {
+    let output = tokio::process::Command::new(program)
+        .args(args)
+        .output()
+        .await
+        .with_context(|| format!("failed to execute {program}"))?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{program} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Switch desktop on X11 via EWMH: request a `_NET_CURRENT_DESKTOP` change
+/// from the window manager. Next/previous wrap around at either end.
+fn switch_desktop_x11(cmd: DesktopCommand) -> Result<()> {
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::*;
+
+    let (conn, screen_num) = x11rb::connect(None).context("failed to connect to X11")?;
+    let root = conn.setup().roots[screen_num].root;
+
+    let net_current_desktop = intern_atom_cached(&conn, "_NET_CURRENT_DESKTOP")?;
+    let net_number_of_desktops = intern_atom_cached(&conn, "_NET_NUMBER_OF_DESKTOPS")?;
+
+    let read_cardinal = |atom: u32| -> Result<u32> {
+        let reply = conn
+            .get_property(false, root, atom, AtomEnum::CARDINAL, 0, 1)?
+            .reply()?;
+        reply
+            .value32()
+            .and_then(|mut values| values.next())
+            .ok_or_else(|| anyhow!("window manager does not support EWMH desktops"))
+    };
+
+    let count = read_cardinal(net_number_of_desktops)?;
+    let current = read_cardinal(net_current_desktop)?;
+    if count == 0 {
+        return Err(anyhow!("window manager reports no desktops"));
+    }
+
+    let target = match cmd {
+        DesktopCommand::Next => (current + 1) % count,
+        DesktopCommand::Previous => (current + count - 1) % count,
+        DesktopCommand::Goto(n) if n >= 1 && n <= count => n - 1,
+        DesktopCommand::Goto(n) => {
+            return Err(anyhow!("desktop {n} does not exist (have {count})"));
+        }
+    };
+
+    let event = ClientMessageEvent::new(32, root, net_current_desktop, [target, 0, 0, 0, 0]);
+    conn.send_event(
+        false,
+        root,
+        EventMask::SUBSTRUCTURE_NOTIFY | EventMask::SUBSTRUCTURE_REDIRECT,
+        event,
+    )?;
+    conn.flush()?;
+    Ok(())
+}
+
+// ============================================================================
+// Window Switching
+// ============================================================================
+
+/// Cycle focus through the workspace's windows with Hyprland's `cyclenext`,
+/// raising floating windows so the focused one is also visible
+async fn switch_window_hyprland(cmd: WindowSwitch) -> Result<()> {
+    let batch = match cmd {
+        WindowSwitch::Next => "dispatch cyclenext ; dispatch bringactivetotop",
+        WindowSwitch::Previous => "dispatch cyclenext prev ; dispatch bringactivetotop",
+    };
+    run_compositor_command("hyprctl", &["--batch", batch]).await
+}
+
+/// Move focus to the next or previous window in Sway's layout
+async fn switch_window_sway(cmd: WindowSwitch) -> Result<()> {
+    let direction = match cmd {
+        WindowSwitch::Next => "next",
+        WindowSwitch::Previous => "prev",
+    };
+    run_compositor_command("swaymsg", &["focus", direction]).await
+}
+
+/// Switch window on X11 via EWMH: walk `_NET_CLIENT_LIST_STACKING` for the
+/// current desktop's windows, leaving out those kept off the taskbar, and ask
+/// the window manager to restack and activate them
+fn switch_window_x11(cmd: WindowSwitch) -> Result<()> {
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::*;
+
+    /// `_NET_WM_DESKTOP` of windows shown on every desktop
+    const ALL_DESKTOPS: u32 = 0xFFFF_FFFF;
+
+    let (conn, screen_num) = x11rb::connect(None).context("failed to connect to X11")?;
+    let root = conn.setup().roots[screen_num].root;
+
+    let read_u32s = |window: u32, atom: u32, kind: AtomEnum| -> Result<Vec<u32>> {
+        let reply = conn
+            .get_property(false, window, atom, kind, 0, u32::MAX)?
+            .reply()?;
+        Ok(reply.value32().map(Iterator::collect).unwrap_or_default())
+    };
+
+    let net_client_list_stacking = intern_atom_cached(&conn, "_NET_CLIENT_LIST_STACKING")?;
+    let net_current_desktop = intern_atom_cached(&conn, "_NET_CURRENT_DESKTOP")?;
+    let net_wm_desktop = intern_atom_cached(&conn, "_NET_WM_DESKTOP")?;
+    let net_wm_state = intern_atom_cached(&conn, "_NET_WM_STATE")?;
+    let net_wm_state_skip_taskbar = intern_atom_cached(&conn, "_NET_WM_STATE_SKIP_TASKBAR")?;
+
+    // Bottom to top, as EWMH lists it
+    let stacking = read_u32s(root, net_client_list_stacking, AtomEnum::WINDOW)?;
+    if stacking.is_empty() {
+        return Err(anyhow!(
+            "window manager does not support EWMH stacking order"
+        ));
+    }
+    let current_desktop = read_u32s(root, net_current_desktop, AtomEnum::CARDINAL)?
+        .first()
+        .copied();
+
+    let stack: Vec<u32> = stacking
+        .into_iter()
+        .rev()
+        .filter(|&window| {
+            let desktop = read_u32s(window, net_wm_desktop, AtomEnum::CARDINAL)
+                .ok()
+                .and_then(|values| values.first().copied());
+            let on_current = match (desktop, current_desktop) {
+                (Some(desktop), Some(current)) => desktop == current || desktop == ALL_DESKTOPS,
+                _ => true,
+            };
+            let skip_taskbar = read_u32s(window, net_wm_state, AtomEnum::ATOM)
+                .is_ok_and(|states| states.contains(&net_wm_state_skip_taskbar));
+            on_current && !skip_taskbar
+        })
+        .collect();
+
+    let Some(target) = window_switch_target(&stack, cmd) else {
+        return Ok(());
+    };
+
+    // Source indication 2 (pager) so the window manager honours the requests
+    // even though rebinded itself isn't focused
+    let send = |window: u32, atom: u32, data: [u32; 5]| -> Result<()> {
+        let event = ClientMessageEvent::new(32, window, atom, data);
+        conn.send_event(
+            false,
+            root,
+            EventMask::SUBSTRUCTURE_NOTIFY | EventMask::SUBSTRUCTURE_REDIRECT,
+            event,
+        )?;
+        Ok(())
+    };
+    if cmd == WindowSwitch::Next {
+        // Below with no sibling: the bottom of the stack
+        let net_restack_window = intern_atom_cached(&conn, "_NET_RESTACK_WINDOW")?;
+        let below = u32::from(StackMode::BELOW);
+        send(stack[0], net_restack_window, [2, 0, below, 0, 0])?;
+    }
+    let net_active_window = intern_atom_cached(&conn, "_NET_ACTIVE_WINDOW")?;
+    send(target, net_active_window, [2, x11rb::CURRENT_TIME, 0, 0, 0])?;
+    conn.flush()?;
+    Ok(())
+}
+
+// ============================================================================
+// Window Snapping
+// ============================================================================
+
+/// Float the active Hyprland window over part of the focused monitor, leaving
+/// out space reserved by bars
+async fn snap_window_hyprland(position: SnapPosition) -> Result<()> {
+    let monitors = compositor_json("hyprctl", &["monitors", "-j"]).await?;
+    let monitor = monitors
+        .as_array()
+        .and_then(|monitors| {
+            monitors
+                .iter()
+                .find(|monitor| monitor.get("focused").and_then(Value::as_bool) == Some(true))
+        })
+        .ok_or_else(|| anyhow!("hyprctl reported no focused monitor"))?;
+
+    let number = |key: &str| monitor.get(key).and_then(Value::as_f64).unwrap_or(0.0);
+    let scale = match number("scale") {
+        scale if scale > 0.0 => scale,
+        _ => 1.0,
+    };
+    // Width and height are in pixels, positions in scaled layout coordinates;
+    // odd transforms rotate the monitor a quarter turn
+    let (mut width, mut height) = (number("width") / scale, number("height") / scale);
+    if number("transform") as i64 % 2 == 1 {
+        std::mem::swap(&mut width, &mut height);
+    }
+    let reserved: Vec<f64> = monitor
+        .get("reserved")
+        .and_then(Value::as_array)
+        .map(|values| values.iter().filter_map(Value::as_f64).collect())
+        .unwrap_or_default();
+    let [left, top, right, bottom] = reserved.try_into().unwrap_or([0.0; 4]);
+
+    let rect = position.rect(Rect {
+        x: (number("x") + left) as i32,
+        y: (number("y") + top) as i32,
+        width: (width - left - right) as i32,
+        height: (height - top - bottom) as i32,
+    });
+    let batch = format!(
+        "dispatch setfloating ; dispatch resizeactive exact {} {} ; dispatch moveactive exact {} {}",
+        rect.width, rect.height, rect.x, rect.y
+    );
+    run_compositor_command("hyprctl", &["--batch", &batch]).await
+}
+
+/// Float the focused Sway window over part of the focused workspace, whose
+/// rectangle already leaves out bars
+async fn snap_window_sway(position: SnapPosition) -> Result<()> {
+    let workspaces = compositor_json("swaymsg", &["-t", "get_workspaces"]).await?;
+    let area = workspaces
+        .as_array()
+        .and_then(|workspaces| {
+            workspaces
+                .iter()
+                .find(|workspace| workspace.get("focused").and_then(Value::as_bool) == Some(true))
+        })
+        .and_then(|workspace| workspace.get("rect"))
+        .ok_or_else(|| anyhow!("swaymsg reported no focused workspace"))?;
+
+    let number = |key: &str| area.get(key).and_then(Value::as_f64).unwrap_or(0.0) as i32;
+    let rect = position.rect(Rect {
+        x: number("x"),
+        y: number("y"),
+        width: number("width"),
+        height: number("height"),
+    });
+    let command = format!(
+        "floating enable, resize set width {} px height {} px, move absolute position {} {}",
+        rect.width, rect.height, rect.x, rect.y
+    );
+    run_compositor_command("swaymsg", &[&command]).await
+}
+
+/// Snap the active X11 window via EWMH. Maximizing sets `_NET_WM_STATE`;
+/// anything else clears it and sends `_NET_MOVERESIZE_WINDOW` for part of the
+/// window's RandR monitor, clipped to the desktop's `_NET_WORKAREA` so panels
+/// stay uncovered.
+fn snap_window_x11(position: SnapPosition) -> Result<()> {
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::*;
+
+    /// `_NET_WM_STATE` actions
+    const STATE_REMOVE: u32 = 0;
+    const STATE_ADD: u32 = 1;
+    /// `_NET_MOVERESIZE_WINDOW` flags: NorthWest gravity, x, y, width and
+    /// height all set, from a pager
+    const MOVERESIZE_FLAGS: u32 = 1 | 0xF << 8 | 2 << 12;
+
+    let (conn, screen_num) = x11rb::connect(None).context("failed to connect to X11")?;
+    let root = conn.setup().roots[screen_num].root;
+    let window = get_x11_active_window(&conn, root)?.ok_or_else(|| anyhow!("no active window"))?;
+
+    let read_cardinals = |window: u32, name: &str| -> Result<Vec<u32>> {
+        let atom = intern_atom_cached(&conn, name)?;
+        let reply = conn
+            .get_property(false, window, atom, AtomEnum::CARDINAL, 0, u32::MAX)?
+            .reply()?;
+        Ok(reply.value32().map(Iterator::collect).unwrap_or_default())
+    };
+    let send = |name: &str, data: [u32; 5]| -> Result<()> {
+        let atom = intern_atom_cached(&conn, name)?;
+        let event = ClientMessageEvent::new(32, window, atom, data);
+        conn.send_event(
+            false,
+            root,
+            EventMask::SUBSTRUCTURE_NOTIFY | EventMask::SUBSTRUCTURE_REDIRECT,
+            event,
+        )?;
+        Ok(())
+    };
+
+    let maximized_vert = intern_atom_cached(&conn, "_NET_WM_STATE_MAXIMIZED_VERT")?;
+    let maximized_horz = intern_atom_cached(&conn, "_NET_WM_STATE_MAXIMIZED_HORZ")?;
+    if position == SnapPosition::Maximize {
+        send(
+            "_NET_WM_STATE",
+            [STATE_ADD, maximized_vert, maximized_horz, 2, 0],
+        )?;
+        conn.flush()?;
+        return Ok(());
+    }
+
+    let (_, monitor) = x11_window_monitor(&conn, root, window)?
+        .ok_or_else(|| anyhow!("active window is on no monitor"))?;
+    let mut area = Rect {
+        x: i32::from(monitor.x),
+        y: i32::from(monitor.y),
+        width: i32::from(monitor.width),
+        height: i32::from(monitor.height),
+    };
+    let desktop = read_cardinals(root, "_NET_CURRENT_DESKTOP")?
+        .first()
+        .copied()
+        .unwrap_or(0) as usize;
+    if let Some(&[x, y, width, height]) = read_cardinals(root, "_NET_WORKAREA")?
+        .chunks_exact(4)
+        .nth(desktop)
+    {
+        let (x, y) = (x as i32, y as i32);
+        let right = (area.x + area.width).min(x + width as i32);
+        let bottom = (area.y + area.height).min(y + height as i32);
+        area.x = area.x.max(x);
+        area.y = area.y.max(y);
+        area.width = (right - area.x).max(1);
+        area.height = (bottom - area.y).max(1);
+    }
+    let rect = position.rect(area);
+
+    // The requested size is the client's, so take off the window manager's
+    // decorations (left, right, top, bottom)
+    let frame = read_cardinals(window, "_NET_FRAME_EXTENTS")?;
+    let [left, right, top, bottom] = frame.try_into().unwrap_or([0; 4]);
+    let width = (rect.width - (left + right) as i32).max(1) as u32;
+    let height = (rect.height - (top + bottom) as i32).max(1) as u32;
+
+    send(
+        "_NET_WM_STATE",
+        [STATE_REMOVE, maximized_vert, maximized_horz, 2, 0],
+    )?;
+    send(
+        "_NET_MOVERESIZE_WINDOW",
+        [
+            MOVERESIZE_FLAGS,
+            rect.x as u32,
+            rect.y as u32,
+            width,
+            height,
+        ],
+    )?;
+    conn.flush()?;
+    Ok(())
+}
+
+// ============================================================================
+// Window Focus
+// ============================================================================
+
+/// Focus the first Hyprland client matching `condition` (`hyprctl clients -j`)
+async fn focus_window_hyprland(condition: &WindowCondition) -> Result<bool> {
+    let clients = compositor_json("hyprctl", &["clients", "-j"]).await?;
+    let Value::Array(clients) = clients else {
+        return Err(anyhow!("unexpected hyprctl clients output"));
+    };
+
+    for client in &clients {
+        let info = WindowInfo {
+            title: json_string(client, "title"),
+            class: json_string(client, "class"),
+            binary: json_pid(client).map(binary_for_pid).unwrap_or_default(),
+        };
+        if condition.matches(&info)
+            && let Some(address) = client.get("address").and_then(Value::as_str)
+        {
+            let target = format!("address:{address}");
+            run_compositor_command("hyprctl", &["dispatch", "focuswindow", &target]).await?;
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Focus the first Sway window matching `condition` (`swaymsg -t get_tree`)
+async fn focus_window_sway(condition: &WindowCondition) -> Result<bool> {
+    fn collect(node: &Value, windows: &mut Vec<(u64, WindowInfo)>) {
+        if let Some(pid) = json_pid(node)
+            && let Some(id) = node.get("id").and_then(Value::as_f64)
+        {
+            // Wayland clients report app_id, XWayland ones a window class
+            let class = node
+                .get("app_id")
+                .and_then(Value::as_str)
+                .or_else(|| {
+                    node.get("window_properties")
+                        .and_then(|props| props.get("class"))
+                        .and_then(Value::as_str)
+                })
+                .unwrap_or_default();
+            windows.push((
+                id as u64,
+                WindowInfo {
+                    title: json_string(node, "name"),
+                    class: class.to_string(),
+                    binary: binary_for_pid(pid),
+                },
+            ));
+        }
+        for key in ["nodes", "floating_nodes"] {
+            if let Some(Value::Array(children)) = node.get(key) {
+                children.iter().for_each(|child| collect(child, windows));
+            }
+        }
+    }
+
+    let tree = compositor_json("swaymsg", &["-t", "get_tree"]).await?;
+    let mut windows = Vec::new();
+    collect(&tree, &mut windows);
+
+    let Some((id, _)) = windows.iter().find(|(_, info)| condition.matches(info)) else {
+        return Ok(false);
+    };
+    run_compositor_command("swaymsg", &[&format!("[con_id={id}]"), "focus"]).await?;
+    Ok(true)
+}
+
+/// Focus the first X11 client matching `condition` via EWMH: find it in
+/// `_NET_CLIENT_LIST` and ask the window manager to activate it
+fn focus_window_x11(condition: &WindowCondition) -> Result<bool> {
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::*;
+
+    let (conn, screen_num) = x11rb::connect(None).context("failed to connect to X11")?;
+    let root = conn.setup().roots[screen_num].root;
+
+    let net_client_list = intern_atom_cached(&conn, "_NET_CLIENT_LIST")?;
+    let reply = conn
+        .get_property(false, root, net_client_list, AtomEnum::WINDOW, 0, u32::MAX)?
+        .reply()?;
+    let clients: Vec<u32> = reply
+        .value32()
+        .ok_or_else(|| anyhow!("window manager does not support EWMH client lists"))?
+        .collect();
+
+    for window in clients {
+        let info = WindowInfo {
+            title: get_x11_window_title(&conn, window).unwrap_or_default(),
+            class: get_x11_window_class(&conn, window).unwrap_or_default(),
+            binary: get_x11_window_binary(&conn, window).unwrap_or_default(),
+        };
+        if !condition.matches(&info) {
+            continue;
+        }
+
+        // Source indication 2 (pager) so the window manager honours the request
+        // even though rebinded itself isn't focused
+        let net_active_window = intern_atom_cached(&conn, "_NET_ACTIVE_WINDOW")?;
+        let event = ClientMessageEvent::new(
+            32,
+            window,
+            net_active_window,
+            [2, x11rb::CURRENT_TIME, 0, 0, 0],
+        );
+        conn.send_event(
+            false,
+            root,
+            EventMask::SUBSTRUCTURE_NOTIFY | EventMask::SUBSTRUCTURE_REDIRECT,
+            event,
+        )?;
+        conn.flush()?;
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+/// Run a compositor IPC command and parse its JSON output
+async fn compositor_json(program: &str, args: &[&str]) -> Result<Value> {
+    let output = tokio::process::Command::new(program)
+        .args(args)
+        .output()
+        .await
+        .with_context(|| format!("failed to execute {program}"))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{program} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    serde_json::from_slice(&output.stdout).map_err(|e| anyhow!("invalid JSON from {program}: {e}"))
+}
+
+fn json_string(value: &Value, key: &str) -> String {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn json_pid(value: &Value) -> Option<u32> {
+    value
+        .get("pid")
+        .and_then(Value::as_f64)
+        .filter(|pid| *pid > 0.0)
+        .map(|pid| pid as u32)
+}
+
+// ============================================================================
+// Notifications
+// ============================================================================
+
+/// ID of the last notification shown, so a new one replaces it instead of stacking
+static LAST_NOTIFICATION_ID: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(0);
+
+/// Show a notification through the freedesktop notification service (what
+/// libnotify talks to)
+/// Show a desktop notification from outside an event handler, over a new
+/// session bus connection
+pub fn show_notification(message: &str) {
+    let message = message.to_string();
+    tokio::spawn(async move {
+        if let Err(e) = send_notification(None, &message).await {
+            warn!("notification failed: {e:#}");
+        }
+    });
+}
+
+/// Whether the foreground app holds the display in exclusive fullscreen.
+/// Linux compositors have no such mode, so games are only recognized by the
+/// `[settings.auto_disable]` window list.
+pub fn fullscreen_exclusive() -> bool {
+    false
+}
+
+async fn send_notification(
+    dbus_conn: Option<StdArc<zbus::Connection>>,
+    message: &str,
+) -> Result<()> {
+    use std::sync::atomic::Ordering;
+    use zbus::proxy;
+    use zbus::zvariant::Value;
+
+    #[proxy(
+        interface = "org.freedesktop.Notifications",
+        default_service = "org.freedesktop.Notifications",
+        default_path = "/org/freedesktop/Notifications"
+    )]
+    trait Notifications {
+        #[allow(clippy::too_many_arguments)]
+        fn notify(
+            &self,
+            app_name: &str,
+            replaces_id: u32,
+            app_icon: &str,
+            summary: &str,
+            body: &str,
+            actions: &[&str],
+            hints: HashMap<&str, Value<'_>>,
+            expire_timeout: i32,
+        ) -> zbus::Result<u32>;
+    }
+
+    let conn = match dbus_conn {
+        Some(c) => c,
+        None => StdArc::new(zbus::Connection::session().await?),
+    };
+    let proxy = NotificationsProxy::new(&conn).await?;
+
+    const EXPIRE_MS: i32 = 2000;
+    let id = proxy
+        .notify(
+            "rebinded",
+            LAST_NOTIFICATION_ID.load(Ordering::Relaxed),
+            "input-keyboard",
+            "rebinded",
+            message,
+            &[],
+            HashMap::new(),
+            EXPIRE_MS,
+        )
+        .await?;
+    LAST_NOTIFICATION_ID.store(id, Ordering::Relaxed);
+    debug!(message, "showed notification");
+    Ok(())
+}
+
+// ============================================================================
+// Clipboard
+// ============================================================================
+
+/// Set the Wayland clipboard via `wl-copy`, which forks to serve the selection
+async fn set_clipboard_wayland(text: &str) -> Result<()> {
+    use std::process::Stdio;
+
+    // The forked server keeps inherited pipes open, so don't capture output
+    let status = tokio::process::Command::new("wl-copy")
+        .arg("--")
+        .arg(text)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await
+        .context("failed to execute wl-copy")?;
+
+    if !status.success() {
+        return Err(anyhow!("wl-copy exited with {status}"));
+    }
+    Ok(())
+}
+
+/// Atoms used when serving the X11 CLIPBOARD selection
+struct ClipboardAtoms {
+    clipboard: u32,
+    targets: u32,
+    utf8_string: u32,
+    text: u32,
+}
+
+/// How long the owner of the X11 clipboard gets to hand over its contents
+const CLIPBOARD_READ_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Read the clipboard with `wl-paste`; an empty clipboard is not an error
+fn get_clipboard_wayland() -> Result<Option<String>> {
+    let output = std::process::Command::new("wl-paste")
+        .args(["--no-newline", "--type", "text"])
+        .output()
+        .context("failed to execute wl-paste")?;
+    Ok(output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned()))
+}
+
+/// Ask the owner of the X11 CLIPBOARD selection for its text, as UTF-8
+fn get_clipboard_x11() -> Result<Option<String>> {
+    use x11rb::connection::Connection;
+    use x11rb::protocol::Event;
+    use x11rb::protocol::xproto::*;
+
+    let (conn, screen_num) = x11rb::connect(None).context("failed to connect to X11")?;
+    let root = conn.setup().roots[screen_num].root;
+    let clipboard = intern_atom_cached(&conn, "CLIPBOARD")?;
+    let utf8_string = intern_atom_cached(&conn, "UTF8_STRING")?;
+    let property = intern_atom_cached(&conn, "REBINDED_CLIPBOARD")?;
+    if conn.get_selection_owner(clipboard)?.reply()?.owner == x11rb::NONE {
+        return Ok(None);
+    }
+
+    // The owner writes the text to a property of the requesting window
+    let window = conn.generate_id()?;
+    conn.create_window(
+        x11rb::COPY_DEPTH_FROM_PARENT,
+        window,
+        root,
+        0,
+        0,
+        1,
+        1,
+        0,
+        WindowClass::INPUT_ONLY,
+        0,
+        &CreateWindowAux::new(),
+    )?;
+    conn.convert_selection(
+        window,
+        clipboard,
+        utf8_string,
+        property,
+        x11rb::CURRENT_TIME,
+    )?;
+    conn.flush()?;
+
+    let deadline = Instant::now() + CLIPBOARD_READ_TIMEOUT;
+    loop {
+        match conn.poll_for_event()? {
+            Some(Event::SelectionNotify(notify)) if notify.requestor == window => {
+                if notify.property == x11rb::NONE {
+                    return Ok(None);
+                }
+                let reply = conn
+                    .get_property(true, window, property, AtomEnum::ANY, 0, u32::MAX / 4)?
+                    .reply()?;
+                // Large selections arrive incrementally (INCR), which isn't supported
+                return Ok((reply.type_ == utf8_string)
+                    .then(|| String::from_utf8_lossy(&reply.value).into_owned()));
+            }
+            Some(_) => {}
+            None if Instant::now() >= deadline => {
+                return Err(anyhow!("the clipboard owner didn't answer"));
+            }
+            None => std::thread::sleep(Duration::from_millis(5)),
+        }
+    }
+}
+
+/// Take ownership of the X11 CLIPBOARD selection and serve `text` from a
+/// background thread until another client claims the selection
+fn set_clipboard_x11(text: String) -> Result<()> {
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::*;
+
+    let (conn, screen_num) = x11rb::connect(None).context("failed to connect to X11")?;
+    let root = conn.setup().roots[screen_num].root;
+
+    let atoms = ClipboardAtoms {
+        clipboard: intern_atom_cached(&conn, "CLIPBOARD")?,
+        targets: intern_atom_cached(&conn, "TARGETS")?,
+        utf8_string: intern_atom_cached(&conn, "UTF8_STRING")?,
+        text: intern_atom_cached(&conn, "TEXT")?,
+    };
+
+    // Selections are owned by a window, so create an invisible one
+    let window = conn.generate_id()?;
+    conn.create_window(
+        x11rb::COPY_DEPTH_FROM_PARENT,
+        window,
+        root,
+        0,
+        0,
+        1,
+        1,
+        0,
+        WindowClass::INPUT_ONLY,
+        0,
+        &CreateWindowAux::new(),
+    )?;
+    conn.set_selection_owner(window, atoms.clipboard, x11rb::CURRENT_TIME)?;
+    if conn.get_selection_owner(atoms.clipboard)?.reply()?.owner != window {
+        return Err(anyhow!("failed to take ownership of the clipboard"));
+    }
+
+    std::thread::spawn(move || {
+        if let Err(e) = serve_x11_clipboard(&conn, &atoms, &text) {
+            warn!("clipboard owner stopped: {e}");
+        }
+    });
+    Ok(())
+}
+
+/// Answer selection requests until ownership is lost
+fn serve_x11_clipboard(
+    conn: &x11rb::rust_connection::RustConnection,
+    atoms: &ClipboardAtoms,
+    text: &str,
+) -> Result<()> {
+    use x11rb::connection::Connection;
+    use x11rb::protocol::Event;
+    use x11rb::protocol::xproto::*;
+    use x11rb::wrapper::ConnectionExt as _;
+
+    let string_atom: u32 = AtomEnum::STRING.into();
+    loop {
+        let request = match conn.wait_for_event()? {
+            Event::SelectionClear(_) => return Ok(()),
+            Event::SelectionRequest(request) => request,
+            _ => continue,
+        };
+
+        // Obsolete clients leave the property unset and expect the target to be used
+        let property = if request.property == x11rb::NONE {
+            request.target
+        } else {
+            request.property
+        };
+
+        let stored = if request.target == atoms.targets {
+            let targets = [atoms.targets, atoms.utf8_string, string_atom, atoms.text];
+            conn.change_property32(
+                PropMode::REPLACE,
+                request.requestor,
+                property,
+                AtomEnum::ATOM,
+                &targets,
+            )
+            .is_ok()
+        } else if [atoms.utf8_string, string_atom, atoms.text].contains(&request.target) {
+            conn.change_property8(
+                PropMode::REPLACE,
+                request.requestor,
+                property,
+                atoms.utf8_string,
+                text.as_bytes(),
+            )
+            .is_ok()
+        } else {
+            false
+        };
+
+        let notify = SelectionNotifyEvent {
+            response_type: SELECTION_NOTIFY_EVENT,
+            sequence: 0,
+            time: request.time,
+            requestor: request.requestor,
+            selection: request.selection,
+            target: request.target,
+            property: if stored { property } else { x11rb::NONE },
+        };
+        conn.send_event(false, request.requestor, EventMask::NO_EVENT, notify)?;
+        conn.flush()?;
+    }
+}
+
+// ============================================================================
+// X11 Window Queries
+// ============================================================================
+
+/// Get window information via X11
+fn get_x11_window_info(x11_conn: Option<&StdArc<Mutex<X11Connection>>>) -> Result<WindowInfo> {
+    use x11rb::connection::Connection;
+
+    // Get or create X11 connection
+    let conn_arc = match x11_conn {
+        Some(c) => StdArc::clone(c),
+        None => {
+            // Lazy initialization - will fail if X11 not available
+            let (conn, screen_num) = x11rb::connect(None).context("failed to connect to X11")?;
+            StdArc::new(Mutex::new(X11Connection { conn, screen_num }))
+        }
+    };
+
+    // Block on the async lock (we're in a sync context from Platform::get_active_window)
+    let guard = match conn_arc.try_lock() {
+        Ok(g) => g,
+        Err(_) => {
+            // If we can't get the lock immediately, return empty rather than blocking
+            return Ok(WindowInfo::default());
+        }
+    };
+
+    let conn = &guard.conn;
+    let root = conn.setup().roots[guard.screen_num].root;
+    Ok(match get_x11_active_window(conn, root)? {
+        Some(window) => get_x11_window_properties(conn, window),
+        None => WindowInfo::default(),
+    })
+}
+
+/// The focused window from `_NET_ACTIVE_WINDOW`, if the window manager reports one
+fn get_x11_active_window(
+    conn: &x11rb::rust_connection::RustConnection,
+    root: u32,
+) -> Result<Option<u32>> {
+    use x11rb::protocol::xproto::ConnectionExt as _;
+
+    // Get active window atom
+    let net_active_window = intern_atom_cached(conn, "_NET_ACTIVE_WINDOW")?;
+    let window_atom = intern_atom_cached(conn, "WINDOW")?;
+
+    // Query active window ID
+    let reply = conn
+        .get_property(false, root, net_active_window, window_atom, 0, 1)?
+        .reply()?;
+
+    if reply.value.len() < 4 {
+        return Ok(None);
+    }
+
+    let active_window = u32::from_ne_bytes([
+        reply.value[0],
+        reply.value[1],
+        reply.value[2],
+        reply.value[3],
+    ]);
+    Ok((active_window != 0).then_some(active_window))
+}
+
+/// Title, class and binary of a window. Class and binary are only looked up
+/// again when focus has moved to a different window.
+fn get_x11_window_properties(
+    conn: &x11rb::rust_connection::RustConnection,
+    window: u32,
+) -> WindowInfo {
+    static ACTIVE: WindowCache = WindowCache::new();
+    let title = get_x11_window_title(conn, window).unwrap_or_default();
+    ACTIVE.get(u64::from(window), title, || {
+        (
+            get_x11_window_class(conn, window).unwrap_or_default(),
+            get_x11_window_binary(conn, window).unwrap_or_default(),
+        )
+    })
+}
+
+/// The focused window: kept current by `follow_x11_focus` when it runs,
+/// otherwise queried from X11
+fn current_window(
+    state: &PlatformState,
+    x11_conn: Option<&StdArc<Mutex<X11Connection>>>,
+) -> Result<WindowInfo> {
+    match state.focused_window() {
+        Some(window) => Ok(window),
+        None => get_x11_window_info(x11_conn),
+    }
+}
+
+/// Keep the focused window current from X11 property change events, so window
+/// conditions don't need a round trip to the X server on each keypress
+///
+/// Watches `_NET_ACTIVE_WINDOW` on the root window for focus changes and the
+/// focused window's name for title changes. Window queries go back to asking
+/// X11 directly if this stops. Native Wayland compositors have no common focus
+/// protocol, so there only XWayland windows are seen, as before.
+fn follow_x11_focus(state: &PlatformState) {
+    if let Err(e) = watch_x11_focus(state) {
+        warn!("stopped following focus changes: {e}");
+    }
+    state.set_focused_window(None);
+}
+
+fn watch_x11_focus(state: &PlatformState) -> Result<()> {
+    use x11rb::connection::Connection;
+    use x11rb::protocol::Event;
+    use x11rb::protocol::xproto::{
+        AtomEnum, ChangeWindowAttributesAux, ConnectionExt as _, EventMask,
+    };
+
+    let (conn, screen_num) =
+        x11rb::connect(None).context("failed to connect to X11 for focus tracking")?;
+    let root = conn.setup().roots[screen_num].root;
+    let net_active_window = intern_atom_cached(&conn, "_NET_ACTIVE_WINDOW")?;
+    let net_wm_name = intern_atom_cached(&conn, "_NET_WM_NAME")?;
+    let watch = |window, mask| {
+        conn.change_window_attributes(window, &ChangeWindowAttributesAux::new().event_mask(mask))
+    };
+    watch(root, EventMask::PROPERTY_CHANGE)?;
+
+    // Window whose title changes we're subscribed to
+    let mut watched: Option<u32> = None;
+    loop {
+        let active = get_x11_active_window(&conn, root)?;
+        if active != watched {
+            // Errors for windows that are already gone arrive as events and
+            // are ignored below
+            if let Some(old) = watched {
+                watch(old, EventMask::NO_EVENT)?;
+            }
+            if let Some(new) = active {
+                watch(new, EventMask::PROPERTY_CHANGE)?;
+            }
+            watched = active;
+        }
+        let window = match active {
+            Some(window) => get_x11_window_properties(&conn, window),
+            None => WindowInfo::default(),
+        };
+        trace!(?window, "focused window changed");
+        state.set_focused_window(Some(window));
+        conn.flush()?;
+
+        // Wait for a focus or title change
+        loop {
+            match conn.wait_for_event()? {
+                Event::PropertyNotify(ev)
+                    if (ev.window == root && ev.atom == net_active_window)
+                        || (Some(ev.window) == watched
+                            && (ev.atom == net_wm_name
+                                || ev.atom == u32::from(AtomEnum::WM_NAME))) =>
+                {
+                    break;
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Report keyboard layout changes to `state` as X11 announces them, leaving
+/// it to be polled if that stops
+fn follow_x11_layout(state: &PlatformState) {
+    if let Err(e) = watch_x11_layout(state) {
+        debug!("not following keyboard layout changes: {e}");
+    }
+    state.set_watching_layout(false);
+}
+
+fn watch_x11_layout(state: &PlatformState) -> Result<()> {
+    use x11rb::connection::Connection;
+    use x11rb::protocol::Event;
+    use x11rb::protocol::xkb::{self, ConnectionExt as _};
+
+    let (conn, _) = x11rb::connect(None).context("failed to connect to X11 for layout tracking")?;
+    // Core MappingNotify reaches every client unasked and covers setxkbmap;
+    // XKB adds group switches and keyboards being replaced
+    if conn.xkb_use_extension(1, 0)?.reply()?.supported {
+        let details = xkb::SelectEventsAux::new().state_notify(xkb::SelectEventsAuxStateNotify {
+            affect_state: xkb::StatePart::GROUP_STATE,
+            state_details: xkb::StatePart::GROUP_STATE,
+        });
+        conn.xkb_select_events(
+            xkb::ID::USE_CORE_KBD.into(),
+            xkb::EventType::from(0u16),
+            xkb::EventType::NEW_KEYBOARD_NOTIFY,
+            xkb::MapPart::KEY_SYMS,
+            xkb::MapPart::KEY_SYMS,
+            &details,
+        )?
+        .check()?;
+    }
+    state.set_watching_layout(true);
+
+    loop {
+        match conn.wait_for_event()? {
+            Event::MappingNotify(_)
+            | Event::XkbStateNotify(_)
+            | Event::XkbMapNotify(_)
+            | Event::XkbNewKeyboardNotify(_) => {
+                trace!("keyboard layout may have changed");
+                state.notify_layout_changed();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Intern an X11 atom (with caching)
+fn intern_atom_cached(conn: &x11rb::rust_connection::RustConnection, name: &str) -> Result<u32> {
+    use x11rb::protocol::xproto::ConnectionExt as _;
+
+    let reply = conn.intern_atom(false, name.as_bytes())?.reply()?;
+    Ok(reply.atom)
+}
+
+/// Get window title
+fn get_x11_window_title(
+    conn: &x11rb::rust_connection::RustConnection,
+    window: u32,
+) -> Result<String> {
+    use x11rb::protocol::xproto::ConnectionExt as _;
+    use x11rb::protocol::xproto::*;
+
+    // Try _NET_WM_NAME (UTF-8) first
+    let net_wm_name = intern_atom_cached(conn, "_NET_WM_NAME")?;
+    let utf8_string = intern_atom_cached(conn, "UTF8_STRING")?;
+
+    let reply = conn
+        .get_property(false, window, net_wm_name, utf8_string, 0, 1024)?
+        .reply()?;
+
+    if !reply.value.is_empty()
+        && let Ok(s) = String::from_utf8(reply.value)
+    {
+        return Ok(s);
+    }
+
+    // Fallback to WM_NAME
+    let wm_name: u32 = AtomEnum::WM_NAME.into();
+    let string_atom: u32 = AtomEnum::STRING.into();
+
+    let reply = conn
+        .get_property(false, window, wm_name, string_atom, 0, 1024)?
+        .reply()?;
+
+    Ok(String::from_utf8_lossy(&reply.value).into_owned())
+}
+
+/// Get window class (second element of WM_CLASS)
+fn get_x11_window_class(
+    conn: &x11rb::rust_connection::RustConnection,
+    window: u32,
+) -> Result<String> {
+    use x11rb::protocol::xproto::ConnectionExt as _;
+    use x11rb::protocol::xproto::*;
+
+    let wm_class: u32 = AtomEnum::WM_CLASS.into();
+    let string_atom: u32 = AtomEnum::STRING.into();
+
+    let reply = conn
+        .get_property(false, window, wm_class, string_atom, 0, 1024)?
+        .reply()?;
+
+    // WM_CLASS format: "instance\0class\0"
+    let s = String::from_utf8_lossy(&reply.value);
+    Ok(s.split('\0').nth(1).unwrap_or("").to_string())
+}
+
+/// Get window binary name via PID
+fn get_x11_window_binary(
+    conn: &x11rb::rust_connection::RustConnection,
+    window: u32,
+) -> Result<String> {
+    use x11rb::protocol::xproto::ConnectionExt as _;
+
+    // Get _NET_WM_PID
+    let net_wm_pid = intern_atom_cached(conn, "_NET_WM_PID")?;
+    let cardinal = intern_atom_cached(conn, "CARDINAL")?;
+
+    let reply = conn
+        .get_property(false, window, net_wm_pid, cardinal, 0, 1)?
+        .reply()?;
+
+    if reply.value.len() < 4 {
+        return Ok(String::new());
+    }
+
+    let pid = u32::from_ne_bytes([
+        reply.value[0],
+        reply.value[1],
+        reply.value[2],
+        reply.value[3],
+    ]);
+
+    Ok(binary_for_pid(pid))
+}
+
+/// Executable name of a process, from the /proc/<pid>/exe symlink
+fn binary_for_pid(pid: u32) -> String {
+    std::fs::read_link(format!("/proc/{pid}/exe"))
+        .ok()
+        .and_then(|exe_path| {
+            exe_path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+        })
+        .unwrap_or_default()
+}
+
+// ============================================================================
+// D-Bus / MPRIS Media Control
+// ============================================================================
+
+/// Information about an MPRIS media player
+#[derive(Debug)]
+struct MprisPlayerInfo {
+    /// D-Bus service name (e.g., "org.mpris.MediaPlayer2.spotify")
+    service_name: String,
+    /// Human-readable identity (e.g., "Spotify", "Firefox")
+    identity: String,
+    /// Current playback status: "Playing", "Paused", or "Stopped"
+    playback_status: String,
+}
+
+impl MprisPlayerInfo {
+    /// Check if this player is currently playing media
+    fn is_playing(&self) -> bool {
+        self.playback_status == "Playing"
+    }
+
+    /// Extract the player name from the service name
+    /// e.g., "org.mpris.MediaPlayer2.spotify" -> "spotify"
+    /// e.g., "org.mpris.MediaPlayer2.firefox.instance_1234" -> "firefox"
+    fn player_name(&self) -> &str {
+        const PREFIX: &str = "org.mpris.MediaPlayer2.";
+        let name = self
+            .service_name
+            .strip_prefix(PREFIX)
+            .unwrap_or(&self.service_name);
+        // Handle instance suffixes like "firefox.instance_1234"
+        name.split('.').next().unwrap_or(name)
+    }
+
+    /// Check if this player matches the given window info
+    /// Matches against window binary name and class (case-insensitive)
+    fn matches_window(&self, window: &WindowInfo) -> bool {
+        let player_name = self.player_name().to_lowercase();
+        let identity = self.identity.to_lowercase();
+
+        // Extract just the binary name from full path (e.g., "/usr/bin/firefox" -> "firefox")
+        let binary_name = window
+            .binary
+            .rsplit('/')
+            .next()
+            .unwrap_or(&window.binary)
+            .to_lowercase();
+        let class = window.class.to_lowercase();
+
+        // Helper to check bidirectional contains (only if both strings are non-empty)
+        let matches = |a: &str, b: &str| -> bool {
+            !a.is_empty() && !b.is_empty() && (a.contains(b) || b.contains(a))
+        };
+
+        // Check various matching strategies
+        matches(&binary_name, &player_name)
+            || matches(&class, &player_name)
+            || matches(&binary_name, &identity)
+            || matches(&class, &identity)
+    }
+
+    /// Check if this player's process family matches the window
+    /// More lenient than matches_window - checks if they share a common base
+    /// e.g., "vivaldi" matches "vivaldi-bin", "chromium" matches "chromium-browser"
+    fn matches_process_family(&self, window: &WindowInfo) -> bool {
+        let player_name = self.player_name().to_lowercase();
+
+        // Extract binary base name
+        let binary_name = window
+            .binary
+            .rsplit('/')
+            .next()
+            .unwrap_or(&window.binary)
+            .to_lowercase();
+
+        // Bail early if binary is empty - can't match process family without it
+        if binary_name.is_empty() {
+            return false;
+        }
+
+        // Strip common suffixes for comparison
+        let binary_base = binary_name
+            .strip_suffix("-bin")
+            .or_else(|| binary_name.strip_suffix("-browser"))
+            .or_else(|| binary_name.strip_suffix("-stable"))
+            .unwrap_or(&binary_name);
+
+        let player_base = player_name
+            .strip_suffix("-bin")
+            .or_else(|| player_name.strip_suffix("-browser"))
+            .or_else(|| player_name.strip_suffix("-stable"))
+            .unwrap_or(&player_name);
+
+        // Check if bases match or one contains the other
+        binary_base == player_base
+            || binary_base.starts_with(player_base)
+            || player_base.starts_with(binary_base)
+    }
+}
+
+/// Tracks historical state for MPRIS player selection
+///
+/// This enables smarter player selection by remembering which players were
+/// recently focused or playing, allowing media commands to target the "right"
+/// player even when focused on an unrelated window.
+#[derive(Debug, Default)]
+struct MprisPlayerTracker {
+    /// Player name -> last time window was focused (e.g., "spotify" -> Instant)
+    last_focused: HashMap<String, Instant>,
+    /// Player name -> last time player was in "Playing" state
+    last_playing: HashMap<String, Instant>,
+    /// Cached list of known MPRIS player names for window matching
+    known_players: Vec<String>,
+    /// Last time we refreshed the known players list
+    last_player_refresh: Option<Instant>,
+}
+
+impl MprisPlayerTracker {
+    /// Focus tracking expires after 10 minutes
+    const FOCUS_EXPIRY: Duration = Duration::from_secs(10 * 60);
+    /// How often to refresh the list of known MPRIS players
+    const PLAYER_REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+    fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record that a player's window was focused now
+    fn record_focus(&mut self, player_name: &str) {
+        self.last_focused
+            .insert(player_name.to_lowercase(), Instant::now());
+    }
+
+    /// Record that a player was playing now
+    fn record_playing(&mut self, player_name: &str) {
+        self.last_playing
+            .insert(player_name.to_lowercase(), Instant::now());
+    }
+
+    /// Get focus time if within expiry window, None otherwise
+    fn get_valid_focus(&self, player_name: &str) -> Option<Instant> {
+        self.last_focused
+            .get(&player_name.to_lowercase())
+            .copied()
+            .filter(|t| t.elapsed() < Self::FOCUS_EXPIRY)
+    }
+
+    /// Get last playing time (never expires)
+    fn get_last_playing(&self, player_name: &str) -> Option<Instant> {
+        self.last_playing.get(&player_name.to_lowercase()).copied()
+    }
+
+    /// Check if the known players cache needs refreshing
+    fn needs_player_refresh(&self) -> bool {
+        self.last_player_refresh
+            .map(|t| t.elapsed() >= Self::PLAYER_REFRESH_INTERVAL)
+            .unwrap_or(true)
+    }
+
+    /// Update the cached list of known players
+    fn update_known_players(&mut self, players: Vec<String>) {
+        self.known_players = players;
+        self.last_player_refresh = Some(Instant::now());
+    }
+
+    /// Find which player (if any) matches the given window
+    fn find_matching_player(&self, window: &WindowInfo) -> Option<&str> {
+        // Create temporary MprisPlayerInfo to use existing matching logic
+        for player_name in &self.known_players {
+            let temp_info = MprisPlayerInfo {
+                service_name: format!("org.mpris.MediaPlayer2.{}", player_name),
+                identity: player_name.clone(),
+                playback_status: String::new(),
+            };
+            if temp_info.matches_window(window) || temp_info.matches_process_family(window) {
+                return Some(player_name);
+            }
+        }
+        None
+    }
+}
+
+/// Background task that monitors window focus to track MPRIS player activity
+///
+/// Polls the active window every 500ms and records focus events for windows
+/// that match known MPRIS players. Also periodically refreshes the list of
+/// known players from D-Bus.
+async fn mpris_focus_monitor(
+    x11_conn: Option<StdArc<Mutex<X11Connection>>>,
+    state: StdArc<PlatformState>,
+    tracker: StdArc<Mutex<MprisPlayerTracker>>,
+) {
+    const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+    // Create our own D-Bus connection for querying MPRIS players
+    let dbus_conn = match zbus::Connection::session().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            warn!("focus monitor: failed to connect to D-Bus: {}", e);
+            return;
+        }
+    };
+
+    let mut last_focused_player: Option<String> = None;
+
+    loop {
+        tokio::time::sleep(POLL_INTERVAL).await;
+
+        // Get current active window
+        let window_info = match current_window(&state, x11_conn.as_ref()) {
+            Ok(info) if !info.binary.is_empty() || !info.class.is_empty() => info,
+            _ => continue, // Skip if we can't get window info
+        };
+
+        let mut tracker_guard = tracker.lock().await;
+
+        // Refresh known players list if needed
+        if tracker_guard.needs_player_refresh()
+            && let Some(players) = list_mpris_players(&dbus_conn).await
+        {
+            // Extract player names from service names
+            let player_names: Vec<String> = players
+                .iter()
+                .filter_map(|s| {
+                    s.strip_prefix("org.mpris.MediaPlayer2.")
+                        .map(|name| name.split('.').next().unwrap_or(name).to_string())
+                })
+                .collect();
+            tracker_guard.update_known_players(player_names);
+        }
+
+        // Check if current window matches any known player
+        if let Some(player_name) = tracker_guard.find_matching_player(&window_info) {
+            let player_name = player_name.to_string();
+
+            // Only record if this is a new focus (avoid spamming updates)
+            if last_focused_player.as_ref() != Some(&player_name) {
+                debug!(
+                    "focus changed to player: {} (window: {})",
+                    player_name, window_info.class
+                );
+                tracker_guard.record_focus(&player_name);
+                drop(tracker_guard); // Release lock before updating last_focused_player
+                last_focused_player = Some(player_name);
+            }
+        } else {
+            last_focused_player = None;
+        }
+    }
+}
+
+/// Send MPRIS media command with smart player selection
+async fn send_mpris_command(
+    dbus_conn: Option<StdArc<zbus::Connection>>,
+    cmd: MediaCommand,
+    window_info: &WindowInfo,
+    tracker: StdArc<Mutex<MprisPlayerTracker>>,
+) -> Result<()> {
+    // Get or create D-Bus connection
+    let conn = match dbus_conn {
+        Some(c) => c,
+        None => StdArc::new(zbus::Connection::session().await?),
+    };
+
+    // Find the best MPRIS player based on playback state and window focus
+    let player_name = find_best_mpris_player(&conn, window_info, &tracker)
+        .await
+        .context("no MPRIS media players found")?;
+
+    call_mpris_player(&conn, player_name, cmd).await
+}
+
+/// Send MPRIS media command to a named player, ignoring focus and playback state
+///
+/// `player` is matched against the player name ("spotify" matches
+/// "org.mpris.MediaPlayer2.spotify" and "org.mpris.MediaPlayer2.spotify.instance_42"),
+/// or may be a full bus name.
+async fn send_mpris_command_to(
+    dbus_conn: Option<StdArc<zbus::Connection>>,
+    player: &PlayerTarget,
+    cmd: MediaCommand,
+) -> Result<()> {
+    let conn = match dbus_conn {
+        Some(c) => c,
+        None => StdArc::new(zbus::Connection::session().await?),
+    };
+
+    let services = list_mpris_players(&conn)
+        .await
+        .context("failed to list MPRIS players")?;
+    let mut found = None;
+    for service in services {
+        let matches = match player {
+            PlayerTarget::Name(name) => mpris_service_matches(&service, name),
+            PlayerTarget::Binary(binary) => mpris_service_pid(&conn, &service)
+                .await
+                .is_some_and(|pid| glob_match::glob_match(binary, &binary_for_pid(pid))),
+        };
+        if matches {
+            found = Some(service);
+            break;
+        }
+    }
+    let service = found.with_context(|| format!("MPRIS player '{player}' is not running"))?;
+
+    call_mpris_player(&conn, service, cmd).await
+}
+
+/// The process behind an MPRIS bus name, as the bus daemon knows it
+async fn mpris_service_pid(conn: &zbus::Connection, service: &str) -> Option<u32> {
+    let reply = conn
+        .call_method(
+            Some("org.freedesktop.DBus"),
+            "/org/freedesktop/DBus",
+            Some("org.freedesktop.DBus"),
+            "GetConnectionUnixProcessID",
+            &(service,),
+        )
+        .await
+        .ok()?;
+    reply.body().deserialize::<u32>().ok()
+}
+
+/// Whether an MPRIS bus name belongs to the player the user named
+fn mpris_service_matches(service: &str, player: &str) -> bool {
+    if service == player {
+        return true;
+    }
+    let Some(name) = service.strip_prefix("org.mpris.MediaPlayer2.") else {
+        return false;
+    };
+    let base = name.split('.').next().unwrap_or(name);
+    name.eq_ignore_ascii_case(player) || base.eq_ignore_ascii_case(player)
+}
+
+/// Call a transport method on one MPRIS player
+async fn call_mpris_player(
+    conn: &zbus::Connection,
+    player_name: String,
+    cmd: MediaCommand,
+) -> Result<()> {
+    use zbus::proxy;
+
+    debug!("sending MPRIS command {:?} to {}", cmd, player_name);
+
+    // Define MPRIS Player interface
+    #[proxy(
+        interface = "org.mpris.MediaPlayer2.Player",
+        default_service = "org.mpris.MediaPlayer2",
+        default_path = "/org/mpris/MediaPlayer2"
+    )]
+    trait MediaPlayer2Player {
+        async fn play_pause(&self) -> zbus::Result<()>;
+        async fn pause(&self) -> zbus::Result<()>;
+        async fn next(&self) -> zbus::Result<()>;
+        async fn previous(&self) -> zbus::Result<()>;
+        async fn stop(&self) -> zbus::Result<()>;
+    }
+
+    // Create proxy for the player
+    let proxy = MediaPlayer2PlayerProxy::builder(conn)
+        .destination(player_name)?
+        .build()
+        .await?;
+
+    // Call appropriate method
+    match cmd {
+        MediaCommand::PlayPause => proxy.play_pause().await?,
+        MediaCommand::Pause => proxy.pause().await?,
+        MediaCommand::Next => proxy.next().await?,
+        MediaCommand::Previous => proxy.previous().await?,
+        MediaCommand::Stop => proxy.stop().await?,
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Find the best MPRIS player based on priority:
+/// 1. Currently playing media (highest priority)
+/// 2. Matches the focused window (current)
+/// 3. Same process family as focused window
+/// 4. Last focused within 10 minutes (more recent wins)
+/// 5. Last playing (more recent wins, never expires)
+/// 6. Any available player (fallback)
+async fn find_best_mpris_player(
+    conn: &zbus::Connection,
+    window_info: &WindowInfo,
+    tracker: &StdArc<Mutex<MprisPlayerTracker>>,
+) -> Option<String> {
+    // Get all MPRIS player service names
+    let player_services = list_mpris_players(conn).await?;
+
+    if player_services.is_empty() {
+        return None;
+    }
+
+    // Get detailed info for each player
+    let mut players: Vec<MprisPlayerInfo> = Vec::new();
+    for service in player_services {
+        if let Some(info) = get_mpris_player_info(conn, &service).await {
+            players.push(info);
+        }
+    }
+
+    if players.is_empty() {
+        return None;
+    }
+
+    // Update tracker with currently playing players
+    {
+        let mut tracker_guard = tracker.lock().await;
+        for player in &players {
+            if player.is_playing() {
+                tracker_guard.record_playing(player.player_name());
+            }
+        }
+    }
+
+    // Get tracker state for selection (read-only from here)
+    let tracker_guard = tracker.lock().await;
+
+    debug!(
+        "found {} MPRIS players, focused window: binary={:?} class={:?}",
+        players.len(),
+        window_info.binary,
+        window_info.class
+    );
+
+    // Log each player's selection factors
+    for player in &players {
+        let player_name = player.player_name();
+        debug!(
+            "player {} (identity={}, playing={}, window_match={}, family_match={}, last_focus={:?}, last_playing={:?})",
+            player.service_name,
+            player.identity,
+            player.is_playing(),
+            player.matches_window(window_info),
+            player.matches_process_family(window_info),
+            tracker_guard
+                .get_valid_focus(player_name)
+                .map(|t| t.elapsed()),
+            tracker_guard
+                .get_last_playing(player_name)
+                .map(|t| t.elapsed()),
+        );
+    }
+
+    // Select the best player using comparison-based priority:
+    // 1. Currently playing (highest)
+    // 2. Matches focused window (current)
+    // 3. Same process family as focused window
+    // 4. Last focused within 10 min (more recent wins)
+    // 5. Last playing (more recent wins)
+    let best_player = players.iter().max_by(|a, b| {
+        let a_name = a.player_name();
+        let b_name = b.player_name();
+
+        a.is_playing()
+            .cmp(&b.is_playing())
+            .then_with(|| {
+                a.matches_window(window_info)
+                    .cmp(&b.matches_window(window_info))
+            })
+            .then_with(|| {
+                a.matches_process_family(window_info)
+                    .cmp(&b.matches_process_family(window_info))
+            })
+            .then_with(|| {
+                // Last focused within 10 min - more recent wins (larger Instant = more recent)
+                tracker_guard
+                    .get_valid_focus(a_name)
+                    .cmp(&tracker_guard.get_valid_focus(b_name))
+            })
+            .then_with(|| {
+                // Last playing - more recent wins (larger Instant = more recent)
+                tracker_guard
+                    .get_last_playing(a_name)
+                    .cmp(&tracker_guard.get_last_playing(b_name))
+            })
+    });
+
+    best_player.map(|p| {
+        debug!("selected player: {}", p.service_name);
+        p.service_name.clone()
+    })
+}
+
+/// List all MPRIS media player D-Bus service names
+async fn list_mpris_players(conn: &zbus::Connection) -> Option<Vec<String>> {
+    use zbus::proxy;
+
+    #[proxy(
+        interface = "org.freedesktop.DBus",
+        default_service = "org.freedesktop.DBus",
+        default_path = "/org/freedesktop/DBus"
+    )]
+    trait DBus {
+        fn list_names(&self) -> zbus::Result<Vec<String>>;
+    }
+
+    let proxy = DBusProxy::new(conn).await.ok()?;
+    let names = proxy.list_names().await.ok()?;
+
+    Some(
+        names
+            .into_iter()
+            .filter(|name| name.starts_with("org.mpris.MediaPlayer2."))
+            .collect(),
+    )
+}
+
+/// Get detailed information about an MPRIS player
+async fn get_mpris_player_info(conn: &zbus::Connection, service: &str) -> Option<MprisPlayerInfo> {
+    use zbus::proxy;
+    use zbus::zvariant::OwnedValue;
+
+    #[proxy(
+        interface = "org.freedesktop.DBus.Properties",
+        default_path = "/org/mpris/MediaPlayer2"
+    )]
+    trait Properties {
+        fn get(&self, interface: &str, property: &str) -> zbus::Result<OwnedValue>;
+    }
+
+    let proxy = PropertiesProxy::builder(conn)
+        .destination(service)
+        .ok()?
+        .build()
+        .await
+        .ok()?;
+
+    // Get Identity from org.mpris.MediaPlayer2 interface
+    let identity = proxy
+        .get("org.mpris.MediaPlayer2", "Identity")
+        .await
+        .ok()
+        .and_then(|v| String::try_from(v).ok())
+        .unwrap_or_default();
+
+    // Get PlaybackStatus from org.mpris.MediaPlayer2.Player interface
+    let playback_status = proxy
+        .get("org.mpris.MediaPlayer2.Player", "PlaybackStatus")
+        .await
+        .ok()
+        .and_then(|v| String::try_from(v).ok())
+        .unwrap_or_else(|| "Stopped".to_string());
+
+    Some(MprisPlayerInfo {
+        service_name: service.to_string(),
+        identity,
+        playback_status,
+    })
+}
+
+// ============================================================================
+// Error Handling & Utilities
+// ============================================================================
+
+/// Check system permissions and requirements
+fn check_permissions() -> Result<()> {
+    // Check /dev/input exists
+    if !Path::new("/dev/input").exists() {
+        return Err(anyhow!("/dev/input not found. Are you running on Linux?"));
+    }
+
+    // Check if we can read at least one input device
+    let readable = std::fs::read_dir("/dev/input")?
+        .filter_map(|e| e.ok())
+        .any(|e| {
+            let path = e.path();
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .map(|n| n.starts_with("event"))
+                .unwrap_or(false)
+                && std::fs::File::open(&path).is_ok()
+        });
+
+    if !readable {
+        return Err(anyhow!(
+            "Cannot read /dev/input devices.\n\
+            Add yourself to the 'input' group:\n  \
+            sudo usermod -aG input $USER\n\
+            Then log out and back in."
+        ));
+    }
+
+    // Check uinput exists
+    if !Path::new("/dev/uinput").exists() {
+        return Err(anyhow!(
+            "/dev/uinput not found. Load the uinput module:\n  \
+            sudo modprobe uinput\n\n\
+            To load automatically at boot:\n  \
+            echo uinput | sudo tee /etc/modules-load.d/uinput.conf"
+        ));
+    }
+
+    // Check if we can write to uinput
+    if OpenOptions::new().write(true).open("/dev/uinput").is_err() {
+        return Err(anyhow!(
+            "Cannot write to /dev/uinput.\n\
+            Create a udev rule:\n  \
+            echo 'KERNEL==\"uinput\", GROUP=\"input\", MODE=\"0660\"' | \\\n    \
+            sudo tee /etc/udev/rules.d/99-input.rules\n  \
+            sudo udevadm control --reload-rules\n  \
+            sudo udevadm trigger"
+        ));
+    }
+
+    Ok(())
+}
+
+/// Macro for warning only once
+macro_rules! warn_once {
+    ($($arg:tt)*) => {{
+        use std::sync::Once;
+        static WARNED: Once = Once::new();
+        WARNED.call_once(|| {
+            warn!($($arg)*);
+        });
+    }};
+}
+
+// Export the macro for use in this module
+use warn_once;
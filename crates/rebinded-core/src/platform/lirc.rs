@@ -0,0 +1,152 @@
+//! IR remote buttons decoded by lircd
+//!
+//! lircd broadcasts each decoded button on its socket as
+//! `<code> <repeat> <button> <remote>`. Buttons named after Linux keys (the
+//! `KEY_*` namespace lircd configs use, e.g. `KEY_PLAYPAUSE`) are reported as
+//! those keys, so a remote drives the same bindings as a keyboard's media keys.
+//! lircd only reports presses, so each press is followed by a release, and
+//! repeats from holding a button are dropped like keyboard autorepeat. Other
+//! lircd clients still receive every button.
+//!
+//! Remotes handled by the kernel's rc-core decoders need none of this: they
+//! are evdev devices and are grabbed along with keyboards. lircd is Linux-only,
+//! so the config rejects a socket on Windows.
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+/// Settings from the `[settings.lirc]` table
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct LircSettings {
+    /// lircd's socket, usually `/run/lirc/lircd`. Remote buttons are only
+    /// read when set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub socket: Option<PathBuf>,
+}
+
+#[cfg(unix)]
+pub(super) use backend::forward;
+
+#[cfg(unix)]
+mod backend {
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use tokio::io::{AsyncBufReadExt, BufReader};
+    use tokio::net::UnixStream;
+    use tokio::sync::mpsc;
+    use tracing::{debug, info, warn};
+
+    use crate::key::{InputEvent, KeyCode, KeyEvent};
+    use crate::platform::PlatformState;
+
+    /// How long to wait before reconnecting to a lircd that went away
+    const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+    /// Forward remote buttons to `tx` for as long as the receiver is open,
+    /// from the lircd socket set in `state`
+    pub(in crate::platform) async fn forward(
+        state: Arc<PlatformState>,
+        tx: mpsc::UnboundedSender<InputEvent>,
+    ) {
+        let mut socket = state.lirc.subscribe();
+        socket.mark_changed();
+        while socket.changed().await.is_ok() {
+            let Some(path) = socket.borrow_and_update().clone() else {
+                continue;
+            };
+            // Reading stops (closing the connection) when the socket setting changes
+            tokio::select! {
+                result = socket.changed() => {
+                    if result.is_err() {
+                        break;
+                    }
+                    socket.mark_changed();
+                }
+                () = read_socket(&path, &tx) => {}
+            }
+            if tx.is_closed() {
+                break;
+            }
+        }
+    }
+
+    /// Read buttons from lircd, reconnecting whenever it goes away
+    async fn read_socket(path: &PathBuf, tx: &mpsc::UnboundedSender<InputEvent>) {
+        loop {
+            match UnixStream::connect(path).await {
+                Ok(stream) => {
+                    info!("reading IR remotes from lircd at {}", path.display());
+                    let mut lines = BufReader::new(stream).lines();
+                    loop {
+                        match lines.next_line().await {
+                            Ok(Some(line)) => {
+                                let Some(key) = parse_line(&line) else {
+                                    continue;
+                                };
+                                for down in [true, false] {
+                                    let event = InputEvent::Key(KeyEvent::new(key, down));
+                                    if tx.send(event).is_err() {
+                                        return;
+                                    }
+                                }
+                            }
+                            Ok(None) => {
+                                warn!("lircd closed the connection");
+                                break;
+                            }
+                            Err(e) => {
+                                warn!("lircd connection failed: {e}");
+                                break;
+                            }
+                        }
+                    }
+                }
+                Err(e) => warn!("failed to connect to lircd at {}: {e}", path.display()),
+            }
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    }
+
+    /// The key for a lircd button line, or `None` for repeats, replies to
+    /// commands and buttons without a matching key name
+    pub(super) fn parse_line(line: &str) -> Option<KeyCode> {
+        let mut fields = line.split_whitespace();
+        let (_code, repeat, button) = (fields.next()?, fields.next()?, fields.next()?);
+        // Also filters out lircd's BEGIN/END command replies
+        if u32::from_str_radix(repeat, 16).ok()? != 0 {
+            return None;
+        }
+        let key = KeyCode::from_config_str(button);
+        if key.is_none() {
+            debug!(button, "IR button has no matching key name");
+        }
+        key
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::backend::parse_line;
+    use crate::key::KeyCode;
+
+    #[test]
+    fn test_parse_line() {
+        let play = KeyCode::from_config_str("KEY_PLAYPAUSE").unwrap();
+        assert_eq!(
+            parse_line("000000037ff07bef 00 KEY_PLAYPAUSE samsung"),
+            Some(play)
+        );
+        // Held buttons repeat with a nonzero count
+        assert_eq!(
+            parse_line("000000037ff07bef 01 KEY_PLAYPAUSE samsung"),
+            None
+        );
+        assert_eq!(parse_line("000000037ff07bef 00 NOT_A_KEY samsung"), None);
+        assert_eq!(parse_line("BEGIN"), None);
+        assert_eq!(parse_line("SIGHUP"), None);
+    }
+}
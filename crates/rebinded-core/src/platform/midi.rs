@@ -0,0 +1,258 @@
+//! MIDI input from ALSA raw MIDI devices
+//!
+//! Notes and controllers on `/dev/snd/midiC*D*` are reported as key events
+//! (`midi:note:36`, `midi:cc:64`), so a pad controller can drive the same
+//! bindings and strategies as a keyboard. Messages from every channel are
+//! merged. Devices are only opened while the config binds a MIDI input, which
+//! leaves them free for other applications otherwise, and are rescanned on
+//! every config load so controllers plugged in later are picked up by a
+//! reload.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use tokio::io::unix::AsyncFd;
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+use tracing::{debug, info, warn};
+
+use super::PlatformState;
+use crate::key::{InputEvent, KeyCode, KeyEvent, MidiInput};
+
+/// Controller values at or above this count as pressed, matching the MIDI
+/// convention for switch controllers like the sustain pedal
+const CONTROLLER_THRESHOLD: u8 = 64;
+
+/// Forward MIDI input to `tx` for as long as the receiver is open, while
+/// `state` says the config binds MIDI inputs
+pub(super) async fn forward(state: Arc<PlatformState>, tx: mpsc::UnboundedSender<InputEvent>) {
+    let mut capture = state.midi.subscribe();
+    capture.mark_changed();
+    while capture.changed().await.is_ok() {
+        let mut readers = JoinSet::new();
+        if *capture.borrow_and_update() {
+            for path in find_midi_devices() {
+                match open_device(&path) {
+                    Ok(device) => {
+                        info!("reading MIDI device {}", path.display());
+                        readers.spawn(read_device(device, path, tx.clone()));
+                    }
+                    Err(e) => warn!("failed to open MIDI device {}: {e}", path.display()),
+                }
+            }
+            if readers.is_empty() {
+                warn!("MIDI inputs are bound but no MIDI devices were found");
+            }
+        }
+
+        // Readers are dropped (closing their devices) when the config changes
+        tokio::select! {
+            result = capture.changed() => {
+                if result.is_err() {
+                    break;
+                }
+                capture.mark_changed();
+            }
+            () = async {
+                while let Some(result) = readers.join_next().await {
+                    if let Ok(Err(e)) = result {
+                        warn!("MIDI device stopped: {e}");
+                    }
+                }
+                std::future::pending::<()>().await
+            } => {}
+        }
+        if tx.is_closed() {
+            break;
+        }
+    }
+}
+
+/// Raw MIDI devices, sorted for stable log output
+fn find_midi_devices() -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir("/dev/snd") else {
+        return Vec::new();
+    };
+    let mut devices: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("midiC"))
+        })
+        .collect();
+    devices.sort();
+    devices
+}
+
+fn open_device(path: &PathBuf) -> io::Result<AsyncFd<File>> {
+    let file = OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(path)?;
+    AsyncFd::new(file)
+}
+
+async fn read_device(
+    device: AsyncFd<File>,
+    path: PathBuf,
+    tx: mpsc::UnboundedSender<InputEvent>,
+) -> io::Result<()> {
+    let mut parser = MidiParser::default();
+    let mut buf = [0u8; 256];
+    loop {
+        let mut guard = device.readable().await?;
+        let read = match guard.try_io(|inner| inner.get_ref().read(&mut buf)) {
+            Ok(result) => result?,
+            Err(_would_block) => continue,
+        };
+        if read == 0 {
+            debug!("MIDI device {} closed", path.display());
+            return Ok(());
+        }
+        for &byte in &buf[..read] {
+            if let Some((input, down)) = parser.feed(byte) {
+                let event = InputEvent::Key(KeyEvent::new(KeyCode::from_midi(input), down));
+                if tx.send(event).is_err() {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// Turns a MIDI byte stream into press and release edges
+///
+/// Handles running status, and skips real-time, system common and SysEx
+/// messages. Controllers only report an edge when their value crosses
+/// [`CONTROLLER_THRESHOLD`], so sweeping a knob doesn't flood the handler.
+#[derive(Debug)]
+struct MidiParser {
+    /// Status byte of the channel message being read
+    status: Option<u8>,
+    data: [u8; 2],
+    len: usize,
+    in_sysex: bool,
+    /// Which controllers are currently above the threshold
+    controllers: [bool; 128],
+}
+
+impl Default for MidiParser {
+    fn default() -> Self {
+        Self {
+            status: None,
+            data: [0; 2],
+            len: 0,
+            in_sysex: false,
+            controllers: [false; 128],
+        }
+    }
+}
+
+impl MidiParser {
+    /// Feed one byte, returning the input and whether it was pressed once a
+    /// message completes
+    fn feed(&mut self, byte: u8) -> Option<(MidiInput, bool)> {
+        match byte {
+            // Real-time messages may appear anywhere, even mid-message
+            0xF8..=0xFF => return None,
+            0xF0 => {
+                self.status = None;
+                self.in_sysex = true;
+                return None;
+            }
+            0xF1..=0xF7 => {
+                self.status = None;
+                self.in_sysex = false;
+                return None;
+            }
+            0x80..=0xEF => {
+                self.status = Some(byte);
+                self.len = 0;
+                self.in_sysex = false;
+                return None;
+            }
+            _ => {}
+        }
+
+        let status = self.status.filter(|_| !self.in_sysex)?;
+        self.data[self.len] = byte;
+        self.len += 1;
+        let needed = match status & 0xF0 {
+            0xC0 | 0xD0 => 1,
+            _ => 2,
+        };
+        if self.len < needed {
+            return None;
+        }
+        // Running status: further data bytes reuse the same status
+        self.len = 0;
+
+        let [number, value] = self.data;
+        match status & 0xF0 {
+            0x90 if value > 0 => Some((MidiInput::Note(number), true)),
+            0x80 | 0x90 => Some((MidiInput::Note(number), false)),
+            0xB0 => {
+                let pressed = value >= CONTROLLER_THRESHOLD;
+                let held = &mut self.controllers[number as usize];
+                (*held != pressed).then(|| {
+                    *held = pressed;
+                    (MidiInput::Controller(number), pressed)
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(bytes: &[u8]) -> Vec<(MidiInput, bool)> {
+        let mut parser = MidiParser::default();
+        bytes.iter().filter_map(|&byte| parser.feed(byte)).collect()
+    }
+
+    #[test]
+    fn test_notes() {
+        // Note on, note on with velocity 0, note off on another channel
+        assert_eq!(
+            parse(&[0x90, 36, 100, 0x90, 36, 0, 0x81, 40, 64]),
+            vec![
+                (MidiInput::Note(36), true),
+                (MidiInput::Note(36), false),
+                (MidiInput::Note(40), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_running_status_and_interleaved_bytes() {
+        // Running status, a clock tick mid-message, and SysEx data ignored
+        assert_eq!(
+            parse(&[0x90, 36, 0xF8, 100, 38, 90, 0xF0, 0x7E, 36, 0xF7, 40, 1]),
+            vec![(MidiInput::Note(36), true), (MidiInput::Note(38), true)]
+        );
+        // Program change takes one data byte and doesn't produce input
+        assert_eq!(
+            parse(&[0xC0, 5, 0x90, 36, 1]),
+            vec![(MidiInput::Note(36), true)]
+        );
+    }
+
+    #[test]
+    fn test_controller_edges() {
+        assert_eq!(
+            parse(&[0xB0, 64, 10, 64, 70, 64, 127, 64, 63, 64, 0]),
+            vec![
+                (MidiInput::Controller(64), true),
+                (MidiInput::Controller(64), false),
+            ]
+        );
+    }
+}
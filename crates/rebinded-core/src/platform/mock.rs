@@ -0,0 +1,375 @@
+//! Mock platform implementation for testing and `--dry-run`
+//!
+//! This mock platform records all platform calls instead of executing them,
+//! preventing tests from triggering real media controls, key presses, or
+//! other system-level side effects.
+//!
+//! In dry-run mode the real platform still captures input, but actions are
+//! executed against a mock that observes the real platform's focused window,
+//! so window conditions resolve exactly as they would live. That mock logs
+//! each call and keeps none, since a dry run can go on for days.
+//!
+//! Events queued with [`MockPlatform::push_event`] are fed to the handler by
+//! `run`, so tests can drive the real event loop end to end. The `assert_*`
+//! helpers are built for this crate's tests and, with the `testing` feature,
+//! for tests of crates that depend on it:
+//!
+//! ```
+//! # #[cfg(feature = "testing")]
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() {
+//! use rebinded_core::key::{InputEvent, KeyCode, KeyEvent};
+//! use rebinded_core::platform::{MediaCommand, MockPlatform};
+//! use rebinded_core::{config, engine};
+//!
+//! let toml = "[bindings.f13]\naction = \"media_next\"";
+//! let (_, runtime) = config::load_from_str("test.toml", toml.to_string()).await.unwrap();
+//!
+//! let mut platform = MockPlatform::new();
+//! let f13 = KeyCode::from_config_str("f13").unwrap();
+//! platform.push_event(InputEvent::Key(KeyEvent::new(f13, true)));
+//! engine::run(&mut platform, &runtime, std::future::pending()).await.unwrap();
+//! platform.assert_media_sent(MediaCommand::Next);
+//! # }
+//! # #[cfg(not(feature = "testing"))]
+//! # fn main() {}
+//! ```
+
+use super::{
+    DesktopCommand, EventResponse, FocusTarget, MediaCommand, MicCommand, PlatformInterface,
+    PlatformState, PlayerTarget, PointerEvent, SessionCommand, SnapPosition, SyntheticKey,
+    WindowSwitch,
+};
+use crate::config::WindowInfo;
+use crate::key::{InputEvent, KeyCode};
+use crate::strategy::PlatformHandle;
+use anyhow::Result;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tracing::info;
+
+/// Recorded platform call
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlatformCall {
+    SendMedia(MediaCommand),
+    SendPlayerMedia {
+        player: PlayerTarget,
+        cmd: MediaCommand,
+    },
+    ChangeVolume(i32),
+    SetMic(MicCommand),
+    SendKey(SyntheticKey),
+    SendRawKey {
+        key: KeyCode,
+        down: bool,
+    },
+    SendPointer(PointerEvent),
+    SwitchDesktop(DesktopCommand),
+    SwitchWindow(WindowSwitch),
+    SnapWindow(SnapPosition),
+    ControlSession(SessionCommand),
+    SetClipboard {
+        text: String,
+        paste: bool,
+    },
+    Notify(String),
+    FocusWindow(FocusTarget),
+}
+
+/// Mock platform that records calls instead of executing them
+#[derive(Clone)]
+pub struct MockPlatform {
+    calls: Arc<Mutex<Vec<PlatformCall>>>,
+    /// Input for `run` to deliver, in order
+    events: Arc<Mutex<VecDeque<InputEvent>>>,
+    /// The handler's response to each delivered event
+    responses: Arc<Mutex<Vec<EventResponse>>>,
+    /// Where window and process queries go; `None` answers them from
+    /// `window` and `running`
+    windows: Option<PlatformHandle>,
+    /// The window reported as focused
+    window: Arc<Mutex<WindowInfo>>,
+    /// Executable names of the processes reported as running
+    running: Arc<Mutex<Vec<String>>>,
+    /// Text last placed on the clipboard
+    clipboard: Arc<Mutex<Option<String>>>,
+    /// Shared with the real platform when observing one
+    state: Arc<PlatformState>,
+}
+
+#[cfg(any(test, feature = "testing"))]
+impl MockPlatform {
+    /// Assert that a specific media command was sent
+    #[track_caller]
+    pub fn assert_media_sent(&self, cmd: MediaCommand) {
+        let calls = self.calls();
+        assert!(
+            calls.contains(&PlatformCall::SendMedia(cmd)),
+            "Expected SendMedia({:?}) but got calls: {:?}",
+            cmd,
+            calls
+        );
+    }
+
+    /// Assert that `call` was made, among any others
+    #[track_caller]
+    pub fn assert_called(&self, call: &PlatformCall) {
+        let calls = self.calls();
+        assert!(
+            calls.contains(call),
+            "Expected {:?} but got calls: {:?}",
+            call,
+            calls
+        );
+    }
+
+    /// Assert that exactly these calls were made, in this order
+    #[track_caller]
+    pub fn assert_calls(&self, expected: &[PlatformCall]) {
+        let calls = self.calls();
+        assert_eq!(
+            calls, expected,
+            "Expected calls {:?} but got {:?}",
+            expected, calls
+        );
+    }
+
+    /// Assert that no calls were made
+    #[track_caller]
+    pub fn assert_no_calls(&self) {
+        let calls = self.calls();
+        assert!(calls.is_empty(), "Expected no calls but got: {:?}", calls);
+    }
+
+    /// Assert that exactly N calls were made
+    #[track_caller]
+    pub fn assert_call_count(&self, expected: usize) {
+        let calls = self.calls();
+        assert_eq!(
+            calls.len(),
+            expected,
+            "Expected {} calls but got {}: {:?}",
+            expected,
+            calls.len(),
+            calls
+        );
+    }
+}
+
+impl Default for MockPlatform {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockPlatform {
+    /// Create a new mock platform
+    pub fn new() -> Self {
+        Self {
+            calls: Arc::new(Mutex::new(Vec::new())),
+            events: Arc::new(Mutex::new(VecDeque::new())),
+            responses: Arc::new(Mutex::new(Vec::new())),
+            windows: None,
+            window: Arc::new(Mutex::new(WindowInfo::default())),
+            running: Arc::new(Mutex::new(Vec::new())),
+            clipboard: Arc::new(Mutex::new(None)),
+            state: Arc::default(),
+        }
+    }
+
+    /// Get all recorded calls. Always empty for an [`observing`](Self::observing) mock.
+    pub fn calls(&self) -> Vec<PlatformCall> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    /// Clear all recorded calls
+    pub fn clear_calls(&self) {
+        self.calls.lock().unwrap().clear();
+    }
+
+    /// Create a mock that answers window queries from a real platform and
+    /// logs calls instead of recording them. It shares the real platform's
+    /// state, so observers and integration settings stay the same.
+    pub fn observing(platform: PlatformHandle) -> Self {
+        Self {
+            state: platform.state().clone(),
+            windows: Some(platform),
+            ..Self::new()
+        }
+    }
+
+    /// Report `window` as the focused window
+    pub fn set_window(&self, window: WindowInfo) {
+        *self.window.lock().unwrap() = window;
+    }
+
+    /// Report these executables as running
+    pub fn set_running(&self, binaries: &[&str]) {
+        *self.running.lock().unwrap() = binaries.iter().map(|b| b.to_string()).collect();
+    }
+
+    /// Queue an event for `run` to deliver
+    pub fn push_event(&self, event: InputEvent) {
+        self.events.lock().unwrap().push_back(event);
+    }
+
+    /// The handler's responses to the events `run` has delivered so far
+    pub fn responses(&self) -> Vec<EventResponse> {
+        self.responses.lock().unwrap().clone()
+    }
+
+    fn record(&self, call: PlatformCall) {
+        if self.windows.is_some() {
+            info!(?call, "dry run");
+        } else {
+            self.calls.lock().unwrap().push(call);
+        }
+    }
+}
+
+impl PlatformInterface for MockPlatform {
+    fn new() -> Self {
+        MockPlatform::new()
+    }
+
+    async fn run<F, Fut, S>(&mut self, mut handler: F, shutdown: S) -> Result<()>
+    where
+        F: FnMut(InputEvent, PlatformHandle) -> Fut,
+        Fut: Future<Output = EventResponse>,
+        S: Future<Output = ()>,
+    {
+        // Input ends once the queue is drained
+        let handle = PlatformHandle::from_mock(self);
+        let deliver = async {
+            loop {
+                let Some(event) = self.events.lock().unwrap().pop_front() else {
+                    break;
+                };
+                let response = handler(event, handle.clone()).await;
+                self.responses.lock().unwrap().push(response);
+            }
+        };
+        tokio::select! {
+            _ = deliver => {}
+            _ = shutdown => {}
+        }
+        Ok(())
+    }
+
+    fn state(&self) -> &Arc<PlatformState> {
+        &self.state
+    }
+
+    fn get_active_window(&self) -> WindowInfo {
+        // The set window for tests, the real window in dry-run mode
+        match &self.windows {
+            Some(platform) => platform.get_active_window(),
+            None => self.window.lock().unwrap().clone(),
+        }
+    }
+
+    fn send_key(&self, key: SyntheticKey) {
+        self.record(PlatformCall::SendKey(key));
+    }
+
+    fn send_raw_key(&self, key: KeyCode, down: bool) {
+        self.record(PlatformCall::SendRawKey { key, down });
+    }
+
+    fn send_pointer(&self, event: PointerEvent) {
+        self.record(PlatformCall::SendPointer(event));
+    }
+
+    fn send_media(&self, cmd: MediaCommand) {
+        self.record(PlatformCall::SendMedia(cmd));
+    }
+
+    fn change_volume(&self, percent: i32) {
+        self.record(PlatformCall::ChangeVolume(percent));
+    }
+
+    fn send_player_media(&self, player: &PlayerTarget, cmd: MediaCommand) {
+        self.record(PlatformCall::SendPlayerMedia {
+            player: player.clone(),
+            cmd,
+        });
+    }
+
+    fn set_mic(&self, cmd: MicCommand) {
+        self.record(PlatformCall::SetMic(cmd));
+    }
+
+    fn switch_desktop(&self, cmd: DesktopCommand) {
+        self.record(PlatformCall::SwitchDesktop(cmd));
+    }
+
+    fn switch_window(&self, cmd: WindowSwitch) {
+        self.record(PlatformCall::SwitchWindow(cmd));
+    }
+
+    fn snap_window(&self, position: SnapPosition) {
+        self.record(PlatformCall::SnapWindow(position));
+    }
+
+    fn control_session(&self, cmd: SessionCommand) {
+        self.record(PlatformCall::ControlSession(cmd));
+    }
+
+    fn set_clipboard(&self, text: &str, paste: bool) {
+        *self.clipboard.lock().unwrap() = Some(text.to_string());
+        self.record(PlatformCall::SetClipboard {
+            text: text.to_string(),
+            paste,
+        });
+    }
+
+    fn get_clipboard(&self) -> Option<String> {
+        // The text set by earlier actions for tests, the real clipboard in dry-run mode
+        match &self.windows {
+            Some(platform) => platform.get_clipboard(),
+            None => self.clipboard.lock().unwrap().clone(),
+        }
+    }
+
+    fn notify(&self, message: &str) {
+        self.record(PlatformCall::Notify(message.to_string()));
+    }
+
+    fn focus_window(&self, target: &FocusTarget) {
+        self.record(PlatformCall::FocusWindow(target.clone()));
+    }
+
+    fn process_running(&self, binary: &str) -> bool {
+        match &self.windows {
+            Some(platform) => platform.process_running(binary),
+            None => self
+                .running
+                .lock()
+                .unwrap()
+                .iter()
+                .any(|name| glob_match::glob_match(binary, name)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_observing_mock_keeps_no_calls() {
+        let real = MockPlatform::new();
+        real.set_window(WindowInfo {
+            binary: "mpv".to_string(),
+            ..WindowInfo::default()
+        });
+        let observer = MockPlatform::observing(PlatformHandle::from_mock(&real));
+
+        observer.send_media(MediaCommand::Next);
+        assert!(observer.calls().is_empty());
+        assert!(real.calls().is_empty());
+        assert_eq!(observer.get_active_window().binary, "mpv");
+        assert!(Arc::ptr_eq(observer.state(), real.state()));
+    }
+}
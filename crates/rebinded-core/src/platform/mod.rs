@@ -0,0 +1,1018 @@
+//! Platform abstraction layer
+//!
+//! Provides a unified interface for:
+//! - Input event capture (keyboard hooks)
+//! - Window information queries
+//! - Synthetic input (key simulation, pointer control, media control)
+//! - Key name resolution (OS-specific key code <-> name mapping), split into
+//!   layout-independent names and names from the active keyboard layout
+//!
+//! Each platform module (windows.rs, linux.rs) exports a `Platform` struct that
+//! implements the `PlatformInterface` trait. The trait is the primary interface -
+//! all platform methods are called through it. Only one OS platform is compiled
+//! per target (via cfg), so calls to it and to `MockPlatform` are dispatched
+//! statically; a custom platform is called through `dyn PlatformInterface`.
+//!
+//! Embedders can implement the trait for a platform of their own (e.g. one that
+//! forwards actions over the network) and drive it with
+//! [`crate::engine::run`]; strategies reach it through
+//! [`crate::strategy::PlatformHandle::custom`].
+
+mod cec;
+mod gamepad;
+mod hid;
+#[cfg(unix)]
+mod linux;
+mod lirc;
+#[cfg(unix)]
+mod midi;
+mod power;
+mod trigger;
+#[cfg(windows)]
+mod windows;
+
+// Re-export the platform-specific implementation
+pub use cec::CecSettings;
+pub use gamepad::GamepadSettings;
+pub use hid::{HidDevice, HidUsage};
+#[cfg(unix)]
+pub use linux::{
+    Platform, active_monitor, build_key_name_map, build_layout_key_name_map, fullscreen_exclusive,
+    get_key_name, layout_fingerprint, release_held_keys, show_notification,
+};
+pub use lirc::LircSettings;
+pub use power::{PowerCondition, PowerState, power_state};
+pub use trigger::{TriggerSettings, TriggerTargets};
+#[cfg(windows)]
+pub use windows::{
+    Platform, active_monitor, build_key_name_map, build_layout_key_name_map, fullscreen_exclusive,
+    get_key_name, layout_fingerprint, release_held_keys, show_notification,
+};
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashSet};
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::config::{WindowCondition, WindowInfo};
+use crate::engine::{OBSERVER_BUFFER, ProcessedEvent};
+use crate::integrations::Integrations;
+use crate::integrations::shell::ShellCommand;
+use crate::key::{InputEvent, KeyCode};
+use tokio::sync::{Notify, broadcast, watch};
+use tracing::debug;
+use trigger::TriggerEndpoint;
+
+/// Response from the event handler, telling the platform what to do with the key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventResponse {
+    /// Block the key from propagating to applications
+    Block,
+    /// Let the key pass through unchanged
+    Passthrough,
+    /// From a strategy: accept the event and hand it to the next strategy in
+    /// the binding's chain. With no strategy left, the binding's action runs as
+    /// it would without strategies, and a `block` binding lets the key through.
+    /// The engine resolves this, so platforms only ever see `Block` or
+    /// `Passthrough`.
+    Continue,
+}
+
+/// Media control commands (platform-agnostic)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaCommand {
+    PlayPause,
+    /// Pause without resuming if already paused; only sent to a
+    /// [`PlayerTarget`], since there is no pause-only media key
+    Pause,
+    Next,
+    Previous,
+    Stop,
+    VolumeUp,
+    VolumeDown,
+    VolumeMute,
+}
+
+/// The application a player-scoped media command goes to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlayerTarget {
+    /// A player name: an MPRIS player on Linux (`spotify` matches
+    /// `org.mpris.MediaPlayer2.spotify`), part of a media session's app ID on
+    /// Windows
+    Name(String),
+    /// Glob pattern on the player process's executable name
+    Binary(String),
+}
+
+impl std::fmt::Display for PlayerTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlayerTarget::Name(name) => f.write_str(name),
+            PlayerTarget::Binary(binary) => write!(f, "binary {binary}"),
+        }
+    }
+}
+
+/// Microphone commands for the default input device (platform-agnostic)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MicCommand {
+    Mute,
+    Unmute,
+    Toggle,
+}
+
+/// Virtual desktop / workspace commands (platform-agnostic)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DesktopCommand {
+    Next,
+    Previous,
+    /// Switch to a numbered desktop (1-based)
+    Goto(u32),
+}
+
+/// Switching between open windows (platform-agnostic)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowSwitch {
+    Next,
+    Previous,
+}
+
+/// Pick the window a switch activates from the switchable windows, topmost
+/// first. Next is the one under the active window, which is then sent to the
+/// bottom; previous is the bottom one. Either way repeated presses visit every
+/// window, and one of each lands back where it started.
+pub fn window_switch_target<T: Copy>(stack: &[T], cmd: WindowSwitch) -> Option<T> {
+    if stack.len() < 2 {
+        return None;
+    }
+    match cmd {
+        WindowSwitch::Next => Some(stack[1]),
+        WindowSwitch::Previous => stack.last().copied(),
+    }
+}
+
+/// Where a window snaps to on its monitor (platform-agnostic)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapPosition {
+    Left,
+    Right,
+    Top,
+    Bottom,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    Maximize,
+}
+
+/// A screen rectangle in pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl SnapPosition {
+    pub const ALL: [SnapPosition; 9] = [
+        SnapPosition::Left,
+        SnapPosition::Right,
+        SnapPosition::Top,
+        SnapPosition::Bottom,
+        SnapPosition::TopLeft,
+        SnapPosition::TopRight,
+        SnapPosition::BottomLeft,
+        SnapPosition::BottomRight,
+        SnapPosition::Maximize,
+    ];
+
+    /// The action name in config files, e.g. "snap_top_left"
+    pub fn name(self) -> &'static str {
+        match self {
+            SnapPosition::Left => "snap_left",
+            SnapPosition::Right => "snap_right",
+            SnapPosition::Top => "snap_top",
+            SnapPosition::Bottom => "snap_bottom",
+            SnapPosition::TopLeft => "snap_top_left",
+            SnapPosition::TopRight => "snap_top_right",
+            SnapPosition::BottomLeft => "snap_bottom_left",
+            SnapPosition::BottomRight => "snap_bottom_right",
+            SnapPosition::Maximize => "snap_maximize",
+        }
+    }
+
+    /// The part of `area`, a monitor's work area, the window fills. Odd sizes
+    /// give the extra pixel to the right and bottom halves, so halves never
+    /// overlap or leave a gap.
+    pub fn rect(self, area: Rect) -> Rect {
+        let (half_width, half_height) = (area.width / 2, area.height / 2);
+        let left = (area.x, half_width);
+        let right = (area.x + half_width, area.width - half_width);
+        let full_x = (area.x, area.width);
+        let top = (area.y, half_height);
+        let bottom = (area.y + half_height, area.height - half_height);
+        let full_y = (area.y, area.height);
+
+        let ((x, width), (y, height)) = match self {
+            SnapPosition::Left => (left, full_y),
+            SnapPosition::Right => (right, full_y),
+            SnapPosition::Top => (full_x, top),
+            SnapPosition::Bottom => (full_x, bottom),
+            SnapPosition::TopLeft => (left, top),
+            SnapPosition::TopRight => (right, top),
+            SnapPosition::BottomLeft => (left, bottom),
+            SnapPosition::BottomRight => (right, bottom),
+            SnapPosition::Maximize => (full_x, full_y),
+        };
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+/// Commands for the user's session (platform-agnostic)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionCommand {
+    /// Lock the screen
+    Lock,
+    /// Suspend the machine
+    Sleep,
+    /// Turn the displays off until the next input
+    DisplayOff,
+}
+
+impl SessionCommand {
+    /// Whether running this by accident would interrupt work, so it only runs
+    /// on a second press (see [`PlatformState::confirm_session`])
+    pub fn needs_confirmation(self) -> bool {
+        matches!(self, SessionCommand::Sleep)
+    }
+}
+
+/// How long `display_off` waits before turning the displays off, so the
+/// release of the key that asked for it doesn't wake them again
+const DISPLAY_OFF_DELAY: Duration = Duration::from_millis(500);
+
+/// How long a guarded session command waits for the press that confirms it
+pub const SESSION_CONFIRM_WINDOW: Duration = Duration::from_secs(3);
+
+/// Window to bring to the foreground (platform-agnostic)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FocusTarget {
+    /// Which window to focus, matched like a binding's window condition
+    pub window: WindowCondition,
+    /// Command that starts the application when no window matches
+    pub launch: Option<ShellCommand>,
+}
+
+impl FocusTarget {
+    /// Called by platforms when no open window matches: start the application
+    /// if a launch command is configured
+    pub fn launch_fallback(&self) {
+        match &self.launch {
+            Some(command) => {
+                debug!(command = command.command, "no matching window, launching");
+                crate::integrations::shell::run(command.clone());
+            }
+            None => debug!(window = ?self.window, "no matching window to focus"),
+        }
+    }
+}
+
+/// Keys this process has pressed in the OS and not yet released
+///
+/// Platforms record every key-down and key-up they inject, so that shutdown
+/// can release whatever is still held rather than leave the OS believing a key
+/// is stuck down.
+#[derive(Debug)]
+pub(crate) struct HeldKeys(Mutex<BTreeSet<u32>>);
+
+impl HeldKeys {
+    pub const fn new() -> Self {
+        Self(Mutex::new(BTreeSet::new()))
+    }
+
+    /// Record an injected press or release of a platform-native key code
+    pub fn record(&self, code: u32, down: bool) {
+        let mut held = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if down {
+            held.insert(code);
+        } else {
+            held.remove(&code);
+        }
+    }
+
+    /// Remove and return every key still held
+    pub fn take(&self) -> Vec<u32> {
+        let mut held = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        std::mem::take(&mut *held).into_iter().collect()
+    }
+}
+
+/// Class and binary of the window a platform last looked up
+///
+/// Neither changes during a window's lifetime, so while one window keeps focus
+/// only its title (which does change, e.g. with browser tabs) is read again.
+/// A different window ID, i.e. a focus change, replaces the entry.
+#[derive(Debug)]
+pub(crate) struct WindowCache(Mutex<Option<(u64, String, String)>>);
+
+impl WindowCache {
+    pub const fn new() -> Self {
+        Self(Mutex::new(None))
+    }
+
+    /// Info for `window` with its current `title`. `query` looks up the
+    /// `(class, binary)` pair and only runs when `window` isn't the cached one.
+    pub fn get(
+        &self,
+        window: u64,
+        title: String,
+        query: impl FnOnce() -> (String, String),
+    ) -> WindowInfo {
+        let mut cached = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        let (class, binary) = match &*cached {
+            Some((id, class, binary)) if *id == window => (class.clone(), binary.clone()),
+            _ => {
+                let (class, binary) = query();
+                *cached = Some((window, class.clone(), binary.clone()));
+                (class, binary)
+            }
+        };
+        WindowInfo {
+            title,
+            class,
+            binary,
+        }
+    }
+}
+
+/// Which keys need the event handler, and how to answer for the rest
+///
+/// Keys in neither set pass through.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyRoutes {
+    /// Keys the event handler has to see
+    pub handled: HashSet<KeyCode>,
+    /// Keys blocked outright, without asking the handler
+    pub blocked: HashSet<KeyCode>,
+}
+
+/// State one platform instance shares with the engine and daemon driving it
+///
+/// Every platform owns one, so two engines in one process keep their own
+/// focused window, key routes, pending confirmations, observers, integration
+/// settings, device capture and injection mode. Only OS hook plumbing that
+/// really is per-process stays global: the keys injected into the OS, and on
+/// Windows the hooks themselves, which only one platform per process can
+/// install and whose state is the one they read.
+pub struct PlatformState {
+    /// The focused window, kept current by the platform's focus-change
+    /// events; `None` while the platform isn't following focus
+    focused_window: RwLock<Option<WindowInfo>>,
+    /// Signalled whenever the focused window is recorded
+    focus_changed: Notify,
+    /// Published by the daemon; `None` hands every key to the event handler
+    ///
+    /// Read by the platform for every key event before it crosses into the
+    /// async handler, so unbound keys (the vast majority of presses) and keys
+    /// with a fixed response are answered right away.
+    key_routes: RwLock<Option<Arc<KeyRoutes>>>,
+    /// Guarded command waiting for confirmation, and when it was first asked for
+    pending_session: Mutex<Option<(SessionCommand, Instant)>>,
+    /// Published after every handled event; see [`crate::engine::subscribe`]
+    events: broadcast::Sender<ProcessedEvent>,
+    integrations: Integrations,
+    trigger: TriggerEndpoint,
+    /// Signalled when the platform sees the keyboard layout change
+    layout_changed: Notify,
+    /// Whether the platform is watching for layout changes, so nobody has to poll
+    watching_layout: AtomicBool,
+    /// Scancodes the config binds (`sc:0x1E`), reported as scancode keys
+    /// instead of virtual keys (Windows)
+    scancodes: RwLock<BTreeSet<u16>>,
+    /// `true` for [`InjectionMode::Scancode`]
+    scancode_injection: AtomicBool,
+    /// Whether the config binds MIDI inputs
+    midi: watch::Sender<bool>,
+    gamepads: watch::Sender<gamepad::Capture>,
+    /// `[[hid]]` devices of the loaded config
+    hid: watch::Sender<Vec<HidDevice>>,
+    /// lircd socket to read remote buttons from
+    lirc: watch::Sender<Option<PathBuf>>,
+    cec: watch::Sender<CecSettings>,
+}
+
+impl Default for PlatformState {
+    fn default() -> Self {
+        Self {
+            focused_window: RwLock::new(None),
+            focus_changed: Notify::new(),
+            key_routes: RwLock::new(None),
+            pending_session: Mutex::new(None),
+            events: broadcast::channel(OBSERVER_BUFFER).0,
+            integrations: Integrations::default(),
+            trigger: TriggerEndpoint::default(),
+            layout_changed: Notify::new(),
+            watching_layout: AtomicBool::new(false),
+            scancodes: RwLock::new(BTreeSet::new()),
+            scancode_injection: AtomicBool::new(false),
+            midi: watch::Sender::new(false),
+            gamepads: watch::Sender::new(gamepad::Capture::default()),
+            hid: watch::Sender::new(Vec::new()),
+            lirc: watch::Sender::new(None),
+            cec: watch::Sender::new(CecSettings::default()),
+        }
+    }
+}
+
+impl PlatformState {
+    /// Record the focused window. Platforms call this when focus moves or the
+    /// focused window's title changes, and with `None` when they stop following.
+    pub fn set_focused_window(&self, window: Option<WindowInfo>) {
+        *self
+            .focused_window
+            .write()
+            .unwrap_or_else(|e| e.into_inner()) = window;
+        self.focus_changed.notify_one();
+    }
+
+    /// Wait until the platform records the focused window again, after focus
+    /// moves or its title changes. A change made while nobody was waiting is
+    /// kept for the next wait.
+    pub async fn focus_changed(&self) {
+        self.focus_changed.notified().await;
+    }
+
+    /// The focused window, if the platform is following focus changes. Cheap
+    /// enough to call on every keypress, unlike querying the OS.
+    pub fn focused_window(&self) -> Option<WindowInfo> {
+        self.focused_window
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Replace the routing table. `None` (the default) hands every key to the
+    /// event handler.
+    pub fn set_key_routes(&self, routes: Option<KeyRoutes>) {
+        *self.key_routes.write().unwrap_or_else(|e| e.into_inner()) = routes.map(Arc::new);
+    }
+
+    /// The response for a key the event handler doesn't need to see, or `None`
+    /// when the event has to go through the handler
+    pub fn fixed_response(&self, key: KeyCode) -> Option<EventResponse> {
+        let routes = self.key_routes.read().unwrap_or_else(|e| e.into_inner());
+        let routes = routes.as_ref()?;
+        if routes.handled.contains(&key) {
+            None
+        } else if routes.blocked.contains(&key) {
+            Some(EventResponse::Block)
+        } else {
+            Some(EventResponse::Passthrough)
+        }
+    }
+
+    /// Whether a guarded session command should run now: the first request arms
+    /// it, and a second one within [`SESSION_CONFIRM_WINDOW`] confirms it
+    pub fn confirm_session(&self, cmd: SessionCommand, now: Instant) -> bool {
+        let mut pending = self
+            .pending_session
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        match pending.take() {
+            Some((armed, at))
+                if armed == cmd && now.duration_since(at) < SESSION_CONFIRM_WINDOW =>
+            {
+                true
+            }
+            _ => {
+                *pending = Some((cmd, now));
+                false
+            }
+        }
+    }
+
+    /// Where handled events are published for observers
+    pub(crate) fn events(&self) -> &broadcast::Sender<ProcessedEvent> {
+        &self.events
+    }
+
+    /// Connections and settings integration actions use
+    pub fn integrations(&self) -> &Integrations {
+        &self.integrations
+    }
+
+    /// Start, stop or move the trigger endpoint to match `settings`
+    pub fn configure_trigger(&self, settings: TriggerSettings) {
+        self.trigger.configure(settings);
+    }
+
+    /// Replace what trigger requests can fire. Called on every config load.
+    pub fn set_trigger_targets(&self, targets: TriggerTargets) {
+        self.trigger.set_targets(targets);
+    }
+
+    pub(crate) fn trigger(&self) -> &TriggerEndpoint {
+        &self.trigger
+    }
+
+    /// Whether [`Self::layout_changed`] fires on layout switches. While it
+    /// doesn't, the layout has to be polled.
+    pub fn watching_layout(&self) -> bool {
+        self.watching_layout.load(Ordering::Relaxed)
+    }
+
+    /// Platforms call this with `true` once they watch the OS for layout
+    /// changes, and with `false` when they stop
+    pub(crate) fn set_watching_layout(&self, watching: bool) {
+        self.watching_layout.store(watching, Ordering::Relaxed);
+    }
+
+    /// Report that the keyboard layout may have changed
+    pub(crate) fn notify_layout_changed(&self) {
+        self.layout_changed.notify_one();
+    }
+
+    /// Wait until the platform reports a possible keyboard layout change. A
+    /// report made while nobody was waiting is kept for the next wait.
+    pub async fn layout_changed(&self) {
+        self.layout_changed.notified().await;
+    }
+
+    /// Report these scancodes as scancode keys. Called on every config load.
+    pub fn capture_scancodes(&self, scancodes: BTreeSet<u16>) {
+        *self.scancodes.write().unwrap_or_else(|e| e.into_inner()) = scancodes;
+    }
+
+    /// Whether the config binds this scancode
+    #[cfg(windows)]
+    pub(crate) fn captures_scancode(&self, scancode: u16) -> bool {
+        self.scancodes
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains(&scancode)
+    }
+
+    /// Choose how injected keys are sent. Keys bound by scancode (`sc:0x1E`)
+    /// are always injected by scancode.
+    pub fn set_injection_mode(&self, mode: InjectionMode) {
+        self.scancode_injection
+            .store(mode == InjectionMode::Scancode, Ordering::Relaxed);
+    }
+
+    /// How injected keys are currently sent
+    pub fn injection_mode(&self) -> InjectionMode {
+        if self.scancode_injection.load(Ordering::Relaxed) {
+            InjectionMode::Scancode
+        } else {
+            InjectionMode::VirtualKey
+        }
+    }
+
+    /// Open MIDI devices if `enabled`, or close them. Called on every config load.
+    pub fn capture_midi(&self, enabled: bool) {
+        self.midi.send_replace(enabled);
+    }
+
+    /// Read gamepads if `enabled`, or stop. Called on every config load.
+    pub fn capture_gamepads(&self, enabled: bool) {
+        self.gamepads
+            .send_modify(|capture| capture.enabled = enabled);
+    }
+
+    /// Limit gamepad reading to devices whose name matches one of `patterns`
+    pub fn filter_gamepads(&self, patterns: Vec<String>) {
+        self.gamepads.send_if_modified(|capture| {
+            let changed = capture.devices != patterns;
+            capture.devices = patterns;
+            changed
+        });
+    }
+
+    /// Read the devices `devices` select, replacing the previous ones. Called
+    /// on every config load.
+    pub fn capture_hid(&self, devices: Vec<HidDevice>) {
+        self.hid.send_replace(devices);
+    }
+
+    /// Read remote buttons from lircd at `socket`, or stop if `None`
+    pub fn connect_lirc(&self, socket: Option<PathBuf>) {
+        self.lirc.send_if_modified(|current| {
+            let changed = *current != socket;
+            *current = socket;
+            changed
+        });
+    }
+
+    /// Start or stop reading TV remote keys to match `settings`
+    pub fn configure_cec(&self, settings: CecSettings) {
+        self.cec.send_if_modified(|current| {
+            let changed = *current != settings;
+            *current = settings;
+            changed
+        });
+    }
+}
+
+/// How injected keys are described to the OS
+///
+/// Windows only: many games read keyboard input through DirectInput or raw
+/// input, which ignore `SendInput` events carrying only a virtual key. Linux
+/// injects through uinput, which games already see as a keyboard.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InjectionMode {
+    /// Virtual key codes, which the OS translates for the focused app
+    #[default]
+    VirtualKey,
+    /// Hardware scancodes (`KEYEVENTF_SCANCODE`), as a physical key sends
+    Scancode,
+}
+
+/// Settings from the `[settings.auto_disable]` table: when to stand aside and
+/// pass every key through untouched
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct AutoDisableSettings {
+    /// Disable while the foreground app runs in exclusive fullscreen (Windows)
+    #[serde(default)]
+    pub fullscreen: bool,
+    /// Disable while a window matching any of these conditions is focused
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub windows: Vec<WindowCondition>,
+    /// Show a notification when rebinded disables or re-enables itself
+    #[serde(default)]
+    pub notify: bool,
+}
+
+impl AutoDisableSettings {
+    pub fn is_enabled(&self) -> bool {
+        self.fullscreen || !self.windows.is_empty()
+    }
+
+    /// Whether keys should pass through untouched with `window` focused
+    pub fn applies(&self, window: Option<&WindowInfo>) -> bool {
+        (self.fullscreen && fullscreen_exclusive())
+            || window.is_some_and(|window| {
+                self.windows
+                    .iter()
+                    .any(|condition| condition.matches(window))
+            })
+    }
+}
+
+/// The display holding the focused window
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MonitorInfo {
+    /// Position in the platform's monitor list, counting from 1
+    pub index: u32,
+    /// Output name, e.g. `DP-2` on Linux or `\\.\DISPLAY2` on Windows
+    pub name: String,
+}
+
+/// Condition on the monitor holding the focused window: its number
+/// (`monitor = 2`) or a glob pattern on its name (`monitor = "HDMI-*"`)
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum MonitorCondition {
+    Index(u32),
+    Name(String),
+}
+
+impl MonitorCondition {
+    pub fn matches(&self, monitor: &MonitorInfo) -> bool {
+        match self {
+            MonitorCondition::Index(index) => *index == monitor.index,
+            MonitorCondition::Name(pattern) => glob_match::glob_match(pattern, &monitor.name),
+        }
+    }
+}
+
+/// Synthetic keys that can be injected (platform-agnostic)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyntheticKey {
+    BrowserBack,
+    BrowserForward,
+}
+
+/// Synthetic pointer input (platform-agnostic)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointerEvent {
+    /// Move the pointer by a relative distance, in pixels before the OS
+    /// applies its pointer acceleration
+    Move { dx: i32, dy: i32 },
+    /// Press or release a button
+    Button { button: PointerButton, down: bool },
+    /// Scroll by whole wheel notches; positive is up (vertical) or right
+    /// (horizontal)
+    Scroll { dx: i32, dy: i32 },
+}
+
+/// Pointer buttons that can be injected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointerButton {
+    Left,
+    Right,
+    Middle,
+}
+
+/// Interface contract for platform implementations.
+///
+/// Both `windows::Platform` and `linux::Platform` implement this trait.
+/// All platform methods are called through this trait. A
+/// [`crate::strategy::PlatformHandle`] for the native or mock platform calls
+/// them statically, with no vtable.
+///
+/// Apart from `new` and `run`, the trait is dyn-compatible, so a custom
+/// platform can sit behind a [`crate::strategy::PlatformHandle`], which then
+/// calls it through a vtable.
+#[allow(async_fn_in_trait)]
+pub trait PlatformInterface {
+    /// Create a new platform instance
+    fn new() -> Self
+    where
+        Self: Sized;
+
+    /// Run the platform event loop with an async handler until `shutdown`
+    /// completes or input ends
+    ///
+    /// On shutdown the platform releases any keys it left pressed and lets go
+    /// of its hooks or grabbed devices before returning.
+    async fn run<F, Fut, S>(&mut self, handler: F, shutdown: S) -> anyhow::Result<()>
+    where
+        Self: Sized,
+        F: FnMut(InputEvent, crate::strategy::PlatformHandle) -> Fut,
+        Fut: Future<Output = EventResponse>,
+        S: Future<Output = ()>;
+
+    /// The state this platform shares with the engine and daemon driving it
+    fn state(&self) -> &Arc<PlatformState>;
+
+    /// Query information about the currently focused window
+    fn get_active_window(&self) -> WindowInfo;
+
+    /// Inject a synthetic key press
+    fn send_key(&self, key: SyntheticKey);
+
+    /// Inject a single press or release of a platform-native key code.
+    ///
+    /// Unlike `send_key`, this emits exactly one edge so strategies can replay
+    /// buffered keys or hold a key down across events. Successive calls must be
+    /// delivered to the OS in order.
+    fn send_raw_key(&self, key: KeyCode, down: bool);
+
+    /// Inject pointer motion, a button press or release, or scrolling.
+    /// Successive calls must be delivered to the OS in order, also relative to
+    /// `send_raw_key`.
+    fn send_pointer(&self, event: PointerEvent);
+
+    /// Execute a media control command
+    fn send_media(&self, cmd: MediaCommand);
+
+    /// Raise (positive) or lower (negative) the output volume by `percent`
+    fn change_volume(&self, percent: i32);
+
+    /// Execute a transport command (play/pause, next, previous, stop) on one
+    /// named player instead of whichever the OS would pick
+    fn send_player_media(&self, player: &PlayerTarget, cmd: MediaCommand);
+
+    /// Mute, unmute or toggle the default microphone
+    fn set_mic(&self, cmd: MicCommand);
+
+    /// Switch virtual desktop / workspace
+    fn switch_desktop(&self, cmd: DesktopCommand);
+
+    /// Activate the next or previous window in stacking order, on the
+    /// current desktop
+    fn switch_window(&self, cmd: WindowSwitch);
+
+    /// Move and resize the focused window to part of its monitor's work area
+    fn snap_window(&self, position: SnapPosition);
+
+    /// Lock the session, suspend the machine or turn the displays off.
+    /// Confirmation is up to the caller.
+    fn control_session(&self, cmd: SessionCommand);
+
+    /// Place text on the clipboard, then send Ctrl+V if `paste` is set
+    fn set_clipboard(&self, text: &str, paste: bool);
+
+    /// The text on the clipboard, if there is any. May block briefly while
+    /// the application holding the clipboard answers.
+    fn get_clipboard(&self) -> Option<String>;
+
+    /// Show a desktop notification
+    fn notify(&self, message: &str);
+
+    /// Focus the first open window matching `target`, or run its launch command
+    /// when there is none
+    fn focus_window(&self, target: &FocusTarget);
+
+    /// Whether a process whose executable name (without path) matches the
+    /// glob `binary` is running
+    fn process_running(&self, binary: &str) -> bool;
+}
+
+// Mock platform for testing and dry runs
+pub mod mock;
+
+pub use mock::MockPlatform;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_held_keys() {
+        let held = HeldKeys::new();
+        held.record(30, true);
+        held.record(42, true);
+        held.record(30, false);
+        held.record(56, false);
+        assert_eq!(held.take(), vec![42]);
+        assert!(held.take().is_empty());
+    }
+
+    #[test]
+    fn test_window_cache() {
+        let cache = WindowCache::new();
+        let queries = std::cell::Cell::new(0);
+        let query = |binary: &str| {
+            let binary = binary.to_string();
+            let queries = &queries;
+            move || {
+                queries.set(queries.get() + 1);
+                ("Main".to_string(), binary)
+            }
+        };
+
+        let info = cache.get(1, "one".to_string(), query("firefox"));
+        assert_eq!(
+            (info.title.as_str(), info.binary.as_str()),
+            ("one", "firefox")
+        );
+
+        // Same window: the title is fresh, the rest comes from the cache
+        let info = cache.get(1, "two".to_string(), query("unused"));
+        assert_eq!(
+            (info.title.as_str(), info.binary.as_str()),
+            ("two", "firefox")
+        );
+        assert_eq!(queries.get(), 1);
+
+        // Focus moved: looked up again
+        let info = cache.get(2, "term".to_string(), query("kitty"));
+        assert_eq!(info.binary, "kitty");
+        assert_eq!(queries.get(), 2);
+    }
+
+    #[test]
+    fn test_monitor_condition() {
+        let monitor = MonitorInfo {
+            index: 2,
+            name: "HDMI-1".to_string(),
+        };
+        assert!(MonitorCondition::Index(2).matches(&monitor));
+        assert!(!MonitorCondition::Index(1).matches(&monitor));
+        assert!(MonitorCondition::Name("HDMI-*".to_string()).matches(&monitor));
+        assert!(!MonitorCondition::Name("DP-*".to_string()).matches(&monitor));
+    }
+
+    #[test]
+    fn test_confirm_session() {
+        let state = PlatformState::default();
+        let start = Instant::now();
+        let sleep = SessionCommand::Sleep;
+        assert!(!state.confirm_session(sleep, start));
+        assert!(state.confirm_session(sleep, start + Duration::from_secs(1)));
+        // Confirming disarms it again
+        assert!(!state.confirm_session(sleep, start + Duration::from_secs(2)));
+
+        // Too late: the second press arms it afresh
+        let later = start + Duration::from_secs(10);
+        assert!(!state.confirm_session(sleep, later + SESSION_CONFIRM_WINDOW));
+        assert!(state.confirm_session(sleep, later + SESSION_CONFIRM_WINDOW * 3 / 2));
+
+        // Another platform's confirmation is its own
+        let other = PlatformState::default();
+        assert!(!state.confirm_session(sleep, start));
+        assert!(!other.confirm_session(sleep, start + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_fixed_response() {
+        let state = PlatformState::default();
+        let (bound, blocked) = (KeyCode::new(0x7C), KeyCode::new(0x7D));
+        assert_eq!(state.fixed_response(bound), None);
+        state.set_key_routes(Some(KeyRoutes {
+            handled: HashSet::from([bound]),
+            blocked: HashSet::from([blocked]),
+        }));
+        assert_eq!(state.fixed_response(bound), None);
+        assert_eq!(state.fixed_response(blocked), Some(EventResponse::Block));
+        assert_eq!(
+            state.fixed_response(KeyCode::new(0x7E)),
+            Some(EventResponse::Passthrough)
+        );
+        // Routes belong to one platform
+        assert_eq!(PlatformState::default().fixed_response(blocked), None);
+    }
+
+    #[tokio::test]
+    async fn test_focus_change_wakes_waiter() {
+        let state = Arc::new(PlatformState::default());
+        let waiter = tokio::spawn({
+            let state = Arc::clone(&state);
+            async move { state.focus_changed().await }
+        });
+        tokio::task::yield_now().await;
+        state.set_focused_window(Some(WindowInfo::default()));
+        tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_layout_change_kept_until_waited_for() {
+        let state = PlatformState::default();
+        assert!(!state.watching_layout());
+        state.set_watching_layout(true);
+        // Reported before anyone waits, e.g. while a reload is running
+        state.notify_layout_changed();
+        tokio::time::timeout(Duration::from_secs(1), state.layout_changed())
+            .await
+            .unwrap();
+        assert!(
+            tokio::time::timeout(Duration::from_millis(10), state.layout_changed())
+                .await
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_window_switch_target() {
+        let stack = ["editor", "browser", "terminal", "chat"];
+        assert_eq!(
+            window_switch_target(&stack, WindowSwitch::Next),
+            Some("browser")
+        );
+        assert_eq!(
+            window_switch_target(&stack, WindowSwitch::Previous),
+            Some("chat")
+        );
+        assert_eq!(window_switch_target(&stack[..1], WindowSwitch::Next), None);
+        assert_eq!(
+            window_switch_target::<&str>(&[], WindowSwitch::Previous),
+            None
+        );
+    }
+
+    #[test]
+    fn test_snap_rect() {
+        // A work area with a 30px panel on top and an odd width
+        let area = Rect {
+            x: 1920,
+            y: 30,
+            width: 1281,
+            height: 1050,
+        };
+        let rect = |position: SnapPosition| position.rect(area);
+
+        assert_eq!(
+            rect(SnapPosition::Left),
+            Rect {
+                x: 1920,
+                y: 30,
+                width: 640,
+                height: 1050
+            }
+        );
+        assert_eq!(
+            rect(SnapPosition::Right),
+            Rect {
+                x: 2560,
+                y: 30,
+                width: 641,
+                height: 1050
+            }
+        );
+        assert_eq!(
+            rect(SnapPosition::BottomRight),
+            Rect {
+                x: 2560,
+                y: 555,
+                width: 641,
+                height: 525
+            }
+        );
+        assert_eq!(rect(SnapPosition::Maximize), area);
+    }
+}
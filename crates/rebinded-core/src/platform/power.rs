@@ -0,0 +1,223 @@
+//! Power source state for `power` conditions
+//!
+//! Read from `/sys/class/power_supply` on Linux and `GetSystemPowerStatus` on
+//! Windows. Conditions are checked on every keypress they guard, so the state
+//! is cached for a few seconds rather than read each time.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// How long a power state reading is reused
+const CACHE_TTL: Duration = Duration::from_secs(2);
+
+/// Where the machine is drawing power from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PowerState {
+    /// Running on battery rather than mains power
+    pub on_battery: bool,
+    /// Charge of the battery in percent, `None` without one
+    pub battery_percent: Option<u8>,
+}
+
+/// A condition on the power source, e.g. `power = { on_battery = true, battery_below = 30 }`
+///
+/// All given fields must hold. Battery thresholds never match on a machine
+/// without a battery.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PowerCondition {
+    /// `true` to match only unplugged, `false` only on mains power
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_battery: Option<bool>,
+    /// Match while the battery charge is below this percentage
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub battery_below: Option<u8>,
+    /// Match while the battery charge is above this percentage
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub battery_above: Option<u8>,
+}
+
+impl PowerCondition {
+    pub fn matches(&self, state: &PowerState) -> bool {
+        self.on_battery
+            .is_none_or(|on_battery| on_battery == state.on_battery)
+            && self
+                .battery_below
+                .is_none_or(|limit| state.battery_percent.is_some_and(|p| p < limit))
+            && self
+                .battery_above
+                .is_none_or(|limit| state.battery_percent.is_some_and(|p| p > limit))
+    }
+}
+
+static CACHE: Mutex<Option<(Instant, PowerState)>> = Mutex::new(None);
+
+/// The current power state, read at most every couple of seconds
+pub fn power_state() -> PowerState {
+    let mut cache = CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    match *cache {
+        Some((read_at, state)) if read_at.elapsed() < CACHE_TTL => state,
+        _ => {
+            let state = backend::read();
+            *cache = Some((Instant::now(), state));
+            state
+        }
+    }
+}
+
+#[cfg(unix)]
+mod backend {
+    use std::path::Path;
+
+    use super::PowerState;
+
+    pub fn read() -> PowerState {
+        read_supplies(Path::new("/sys/class/power_supply"))
+    }
+
+    /// Combine every supply under a `power_supply` class directory: on battery
+    /// when no mains adapter is online (or, without one, a battery is
+    /// discharging), with the charge of the first battery
+    pub(super) fn read_supplies(dir: &Path) -> PowerState {
+        let read = |supply: &Path, attribute: &str| {
+            std::fs::read_to_string(supply.join(attribute))
+                .map(|value| value.trim().to_string())
+                .unwrap_or_default()
+        };
+
+        let mut supplies: Vec<_> = std::fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .collect();
+        supplies.sort();
+
+        let mut mains_online = None;
+        let mut discharging = false;
+        let mut battery_percent = None;
+        for supply in &supplies {
+            match read(supply, "type").as_str() {
+                "Mains" => {
+                    let online = read(supply, "online") == "1";
+                    mains_online = Some(mains_online.unwrap_or(false) || online);
+                }
+                // Peripheral batteries (mice, headsets) report a scope of Device
+                "Battery" if read(supply, "scope") != "Device" => {
+                    discharging |= read(supply, "status") == "Discharging";
+                    battery_percent =
+                        battery_percent.or_else(|| read(supply, "capacity").parse().ok());
+                }
+                _ => {}
+            }
+        }
+
+        PowerState {
+            on_battery: battery_percent.is_some() && mains_online.map_or(discharging, |on| !on),
+            battery_percent,
+        }
+    }
+}
+
+#[cfg(windows)]
+mod backend {
+    use windows::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+    use super::PowerState;
+
+    /// `BatteryFlag` bit for a machine without a battery
+    const NO_SYSTEM_BATTERY: u8 = 128;
+
+    pub fn read() -> PowerState {
+        let mut status = SYSTEM_POWER_STATUS::default();
+        if unsafe { GetSystemPowerStatus(&mut status) }.is_err()
+            || status.BatteryFlag & NO_SYSTEM_BATTERY != 0
+        {
+            return PowerState::default();
+        }
+        PowerState {
+            // 0 is offline, 1 online, 255 unknown
+            on_battery: status.ACLineStatus == 0,
+            // 255 when the charge is unknown
+            battery_percent: (status.BatteryLifePercent <= 100)
+                .then_some(status.BatteryLifePercent),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_power_condition() {
+        let unplugged = PowerState {
+            on_battery: true,
+            battery_percent: Some(25),
+        };
+        let desktop = PowerState::default();
+
+        let low = PowerCondition {
+            on_battery: Some(true),
+            battery_below: Some(30),
+            ..Default::default()
+        };
+        assert!(low.matches(&unplugged));
+        assert!(!low.matches(&desktop));
+
+        let above = PowerCondition {
+            battery_above: Some(25),
+            ..Default::default()
+        };
+        assert!(!above.matches(&unplugged));
+
+        let plugged_in = PowerCondition {
+            on_battery: Some(false),
+            ..Default::default()
+        };
+        assert!(plugged_in.matches(&desktop));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_read_supplies() {
+        let dir = std::env::temp_dir().join(format!("rebinded-power-{}", std::process::id()));
+        let supply = |name: &str, attributes: &[(&str, &str)]| {
+            let path = dir.join(name);
+            std::fs::create_dir_all(&path).unwrap();
+            for (attribute, value) in attributes {
+                std::fs::write(path.join(attribute), format!("{value}\n")).unwrap();
+            }
+        };
+        supply("AC", &[("type", "Mains"), ("online", "0")]);
+        supply(
+            "BAT0",
+            &[
+                ("type", "Battery"),
+                ("status", "Discharging"),
+                ("capacity", "42"),
+            ],
+        );
+        supply(
+            "hidpp_battery_0",
+            &[("type", "Battery"), ("scope", "Device"), ("capacity", "90")],
+        );
+
+        let state = backend::read_supplies(&dir);
+        assert_eq!(
+            state,
+            PowerState {
+                on_battery: true,
+                battery_percent: Some(42),
+            }
+        );
+
+        supply("AC", &[("online", "1")]);
+        assert!(!backend::read_supplies(&dir).on_battery);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(backend::read_supplies(&dir), PowerState::default());
+    }
+}
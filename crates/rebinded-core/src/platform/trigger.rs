@@ -0,0 +1,384 @@
+//! HTTP endpoint that fires bindings from outside the keyboard
+//!
+//! `POST /trigger/<name>` with `Authorization: Bearer <token>` presses and
+//! releases a bound key, so Stream Decks, phone shortcuts and scripts go
+//! through the same window conditions, strategies and pause state as the key
+//! itself. `<name>` is a key as bindings write it (`f13`, `midi:note:36`) or
+//! the name of an action some binding performs (`media_play_pause`,
+//! `obs%20scene%20Gaming`); an action performed by several bindings fires the
+//! first of their keys in name order. Like other non-keyboard input, nothing is
+//! typed when the binding passes the key through.
+//!
+//! The endpoint listens on loopback unless `allow_remote` is set, and every
+//! request needs the configured token.
+
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, watch};
+use tracing::{debug, info, warn};
+
+use crate::key::{InputEvent, KeyCode, KeyEvent};
+
+/// Largest request head accepted; triggers carry no body worth reading
+const MAX_REQUEST: usize = 8 * 1024;
+
+/// How long a client gets to send its request before the connection is dropped
+const READ_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Settings from the `[settings.trigger]` table
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct TriggerSettings {
+    /// Address to listen on, e.g. `127.0.0.1:7878`. The endpoint is off when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub listen: Option<SocketAddr>,
+    /// Bearer token every request must carry
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    /// Allow listening on an address other devices can reach
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub allow_remote: bool,
+}
+
+/// Keys that can be triggered, and the actions that name them
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TriggerTargets {
+    keys: HashSet<KeyCode>,
+    actions: HashMap<String, KeyCode>,
+}
+
+impl TriggerTargets {
+    /// Targets for bound keys, each with the names of the actions it performs
+    pub fn new(bindings: impl IntoIterator<Item = (KeyCode, Vec<String>)>) -> Self {
+        let mut bindings: Vec<(KeyCode, Vec<String>)> = bindings.into_iter().collect();
+        bindings.sort_by_key(|(key, _)| key.to_string());
+        let mut targets = Self::default();
+        for (key, actions) in bindings {
+            targets.keys.insert(key);
+            for action in actions {
+                targets.actions.entry(action).or_insert(key);
+            }
+        }
+        targets
+    }
+
+    /// The key to press for a trigger name
+    pub fn resolve(&self, name: &str) -> Option<KeyCode> {
+        KeyCode::from_config_str(name)
+            .filter(|key| self.keys.contains(key))
+            .or_else(|| self.actions.get(name).copied())
+    }
+}
+
+/// The endpoint's settings and what it can fire, owned by a platform's
+/// [`PlatformState`](super::PlatformState)
+pub(crate) struct TriggerEndpoint {
+    /// The trigger settings of the loaded config, notified when they change
+    settings: watch::Sender<TriggerSettings>,
+    /// What the loaded config lets requests trigger
+    targets: RwLock<TriggerTargets>,
+}
+
+impl Default for TriggerEndpoint {
+    fn default() -> Self {
+        Self {
+            settings: watch::Sender::new(TriggerSettings::default()),
+            targets: RwLock::default(),
+        }
+    }
+}
+
+impl TriggerEndpoint {
+    /// Start, stop or move the endpoint to match `settings`
+    pub fn configure(&self, settings: TriggerSettings) {
+        self.settings.send_if_modified(|current| {
+            let changed = *current != settings;
+            *current = settings;
+            changed
+        });
+    }
+
+    /// Replace what requests can trigger
+    pub fn set_targets(&self, targets: TriggerTargets) {
+        *self.targets.write().unwrap_or_else(|e| e.into_inner()) = targets;
+    }
+}
+
+/// Serve triggers as key events on `tx` for as long as the receiver is open
+pub(super) async fn forward(
+    state: Arc<super::PlatformState>,
+    tx: mpsc::UnboundedSender<InputEvent>,
+) {
+    let endpoint = state.trigger();
+    let mut settings = endpoint.settings.subscribe();
+    settings.mark_changed();
+    while settings.changed().await.is_ok() {
+        let current = settings.borrow_and_update().clone();
+        let (Some(addr), Some(token)) = (current.listen, current.token) else {
+            continue;
+        };
+        // The listener closes when the settings change
+        tokio::select! {
+            result = settings.changed() => {
+                if result.is_err() {
+                    break;
+                }
+                settings.mark_changed();
+            }
+            () = serve(addr, &token, &endpoint.targets, &tx) => {}
+        }
+        if tx.is_closed() {
+            break;
+        }
+    }
+}
+
+/// Accept trigger requests on `addr`. Requests are handled one at a time; if
+/// the address can't be bound, the endpoint stays off until the settings change.
+async fn serve(
+    addr: SocketAddr,
+    token: &str,
+    targets: &RwLock<TriggerTargets>,
+    tx: &mpsc::UnboundedSender<InputEvent>,
+) {
+    let listener = match TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            warn!("failed to listen for triggers on {addr}: {e}");
+            return std::future::pending().await;
+        }
+    };
+    info!("trigger endpoint listening on http://{addr}/trigger/");
+    loop {
+        match listener.accept().await {
+            Ok((stream, peer)) => {
+                if let Some(key) = handle_connection(stream, peer, token, targets).await {
+                    for down in [true, false] {
+                        if tx.send(InputEvent::Key(KeyEvent::new(key, down))).is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+            Err(e) => warn!("failed to accept trigger connection: {e}"),
+        }
+    }
+}
+
+/// Answer one request, returning the key it triggers
+async fn handle_connection(
+    mut stream: TcpStream,
+    peer: SocketAddr,
+    token: &str,
+    targets: &RwLock<TriggerTargets>,
+) -> Option<KeyCode> {
+    let (reply, key) = match tokio::time::timeout(READ_TIMEOUT, read_head(&mut stream)).await {
+        Ok(Ok(head)) => {
+            let targets = targets.read().unwrap_or_else(|e| e.into_inner());
+            respond(&head, token, &targets)
+        }
+        Ok(Err(e)) => (Reply::BadRequest(e), None),
+        Err(_) => (
+            Reply::BadRequest("timed out waiting for the request".into()),
+            None,
+        ),
+    };
+    debug!(%peer, status = reply.status(), ?key, "trigger request");
+    let (status, reason, body) = (reply.status(), reply.reason(), reply.body());
+    let response = format!(
+        "HTTP/1.1 {status} {reason}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    if let Err(e) = stream.write_all(response.as_bytes()).await {
+        debug!("failed to send trigger reply: {e}");
+    }
+    let _ = stream.shutdown().await;
+    key
+}
+
+/// Read up to the blank line ending the request head
+async fn read_head(stream: &mut TcpStream) -> Result<String, String> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        if head.len() > MAX_REQUEST {
+            return Err("request too large".into());
+        }
+        let n = stream
+            .read(&mut buf)
+            .await
+            .map_err(|e| format!("failed to read request: {e}"))?;
+        if n == 0 {
+            return Err("connection closed mid-request".into());
+        }
+        head.extend_from_slice(&buf[..n]);
+    }
+    String::from_utf8(head).map_err(|_| "request is not valid UTF-8".into())
+}
+
+/// The outcome of a trigger request
+#[derive(Debug, PartialEq)]
+enum Reply {
+    Triggered(String),
+    BadRequest(String),
+    Unauthorized,
+    NotFound(String),
+    MethodNotAllowed,
+}
+
+impl Reply {
+    fn status(&self) -> u16 {
+        match self {
+            Reply::Triggered(_) => 202,
+            Reply::BadRequest(_) => 400,
+            Reply::Unauthorized => 401,
+            Reply::NotFound(_) => 404,
+            Reply::MethodNotAllowed => 405,
+        }
+    }
+
+    fn reason(&self) -> &'static str {
+        match self {
+            Reply::Triggered(_) => "Accepted",
+            Reply::BadRequest(_) => "Bad Request",
+            Reply::Unauthorized => "Unauthorized",
+            Reply::NotFound(_) => "Not Found",
+            Reply::MethodNotAllowed => "Method Not Allowed",
+        }
+    }
+
+    fn body(&self) -> String {
+        match self {
+            Reply::Triggered(key) => format!("triggered {key}\n"),
+            Reply::BadRequest(message) => format!("{message}\n"),
+            Reply::Unauthorized => "missing or wrong bearer token\n".to_string(),
+            Reply::NotFound(name) => format!("nothing bound to '{name}'\n"),
+            Reply::MethodNotAllowed => "use POST /trigger/<binding-or-action>\n".to_string(),
+        }
+    }
+}
+
+/// Decide the reply to a request head, and the key to press if it triggers one
+fn respond(head: &str, token: &str, targets: &TriggerTargets) -> (Reply, Option<KeyCode>) {
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split(' ');
+    let (Some(method), Some(target)) = (request_line.next(), request_line.next()) else {
+        return (Reply::BadRequest("malformed request line".into()), None);
+    };
+    let authorized = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("authorization"))
+        .and_then(|(_, value)| value.trim().strip_prefix("Bearer "))
+        .is_some_and(|given| constant_time_eq(given.trim().as_bytes(), token.as_bytes()));
+    if !authorized {
+        return (Reply::Unauthorized, None);
+    }
+
+    let path = target.split('?').next().unwrap_or_default();
+    let Some(name) = path.strip_prefix("/trigger/") else {
+        return (Reply::NotFound(path.to_string()), None);
+    };
+    if method != "POST" {
+        return (Reply::MethodNotAllowed, None);
+    }
+    let Some(name) = percent_decode(name) else {
+        return (Reply::BadRequest("malformed percent-encoding".into()), None);
+    };
+    match targets.resolve(&name) {
+        Some(key) => (Reply::Triggered(key.to_string()), Some(key)),
+        None => (Reply::NotFound(name), None),
+    }
+}
+
+/// Decode `%XX` escapes in a path segment
+fn percent_decode(segment: &str) -> Option<String> {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// Compare tokens without returning early on the first differing byte
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn targets() -> TriggerTargets {
+        let f13 = KeyCode::from_config_str("f13").unwrap();
+        let f14 = KeyCode::from_config_str("f14").unwrap();
+        TriggerTargets::new([
+            (f14, vec!["media_next".to_string()]),
+            (
+                f13,
+                vec!["media_next".to_string(), "obs scene Gaming".to_string()],
+            ),
+        ])
+    }
+
+    fn request(method: &str, path: &str, token: &str) -> String {
+        format!(
+            "{method} {path} HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer {token}\r\n\r\n"
+        )
+    }
+
+    #[test]
+    fn test_resolve_names() {
+        let targets = targets();
+        let f13 = KeyCode::from_config_str("f13").unwrap();
+        assert_eq!(targets.resolve("F13"), Some(f13));
+        // Shared actions go to the first key by name
+        assert_eq!(targets.resolve("media_next"), Some(f13));
+        assert_eq!(targets.resolve("obs scene Gaming"), Some(f13));
+        assert_eq!(targets.resolve("f15"), None);
+        assert_eq!(targets.resolve("media_stop"), None);
+    }
+
+    #[test]
+    fn test_respond() {
+        let targets = targets();
+        let f13 = KeyCode::from_config_str("f13").unwrap();
+        let (reply, key) = respond(
+            &request("POST", "/trigger/f13", "secret"),
+            "secret",
+            &targets,
+        );
+        assert_eq!((reply.status(), key), (202, Some(f13)));
+        let (_, key) = respond(
+            &request("POST", "/trigger/obs%20scene%20Gaming", "secret"),
+            "secret",
+            &targets,
+        );
+        assert_eq!(key, Some(f13));
+
+        let status = |head: String| respond(&head, "secret", &targets).0.status();
+        assert_eq!(status(request("POST", "/trigger/f13", "wrong")), 401);
+        assert_eq!(
+            status("POST /trigger/f13 HTTP/1.1\r\n\r\n".to_string()),
+            401
+        );
+        assert_eq!(status(request("GET", "/trigger/f13", "secret")), 405);
+        assert_eq!(status(request("POST", "/trigger/f15", "secret")), 404);
+        assert_eq!(status(request("POST", "/other", "secret")), 404);
+        assert_eq!(status(request("POST", "/trigger/%zz", "secret")), 400);
+    }
+}
@@ -0,0 +1,1900 @@
+//! Windows-specific platform implementation
+//!
+//! Uses Win32 low-level keyboard hooks and APIs:
+//! - SetWindowsHookExW(WH_KEYBOARD_LL) for intercepting keys
+//! - GetForegroundWindow + GetWindowTextW for window title
+//! - GetClassNameW for window class
+//! - GetWindowThreadProcessId + OpenProcess + QueryFullProcessImageNameW for binary
+//! - SendInput for synthetic key and pointer injection
+//! - GetKeyNameTextW + MapVirtualKeyW for key name resolution
+
+use super::{
+    DISPLAY_OFF_DELAY, DesktopCommand, EventResponse, FocusTarget, HeldKeys, InjectionMode,
+    MediaCommand, MicCommand, MonitorInfo, PlatformInterface, PlatformState, PlayerTarget,
+    PointerButton, PointerEvent, Rect, SessionCommand, SnapPosition, SyntheticKey, WindowCache,
+    WindowSwitch, window_switch_target,
+};
+use crate::config::{WindowCondition, WindowInfo};
+use crate::key::{InputEvent, KeyCode, KeyEvent, MouseButton};
+use crate::strategy::PlatformHandle;
+use anyhow::{Result, anyhow};
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::future::Future;
+use std::os::windows::ffi::OsStringExt;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use tokio::sync::mpsc;
+use tracing::{debug, info, trace, warn};
+use windows::Win32::Foundation::{CloseHandle, HWND, LPARAM, LRESULT, WPARAM};
+use windows::Win32::System::Threading::{
+    GetCurrentThreadId, OpenProcess, PROCESS_NAME_FORMAT, PROCESS_QUERY_LIMITED_INFORMATION,
+    QueryFullProcessImageNameW,
+};
+use windows::Win32::UI::Accessibility::{HWINEVENTHOOK, SetWinEventHook, UnhookWinEvent};
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    ActivateKeyboardLayout, GetKeyNameTextW, GetKeyboardLayout, HKL, INPUT, INPUT_0,
+    INPUT_KEYBOARD, INPUT_MOUSE, KEYBD_EVENT_FLAGS, KEYBDINPUT, KEYEVENTF_EXTENDEDKEY,
+    KEYEVENTF_KEYUP, KEYEVENTF_SCANCODE, MAPVK_VK_TO_VSC_EX, MOUSE_EVENT_FLAGS, MOUSEEVENTF_HWHEEL,
+    MOUSEEVENTF_LEFTDOWN, MOUSEEVENTF_LEFTUP, MOUSEEVENTF_MIDDLEDOWN, MOUSEEVENTF_MIDDLEUP,
+    MOUSEEVENTF_MOVE, MOUSEEVENTF_RIGHTDOWN, MOUSEEVENTF_RIGHTUP, MOUSEEVENTF_WHEEL, MOUSEINPUT,
+    MapVirtualKeyW, SendInput, VIRTUAL_KEY,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    CHILDID_SELF, CallNextHookEx, DispatchMessageW, EVENT_OBJECT_NAMECHANGE,
+    EVENT_SYSTEM_FOREGROUND, GetClassNameW, GetForegroundWindow, GetMessageW, GetWindowTextW,
+    GetWindowThreadProcessId, KBDLLHOOKSTRUCT, KillTimer, LLKHF_EXTENDED, MSG, MSLLHOOKSTRUCT,
+    OBJID_WINDOW, PostThreadMessageW, SetTimer, SetWindowsHookExW, TranslateMessage,
+    UnhookWindowsHookEx, WH_KEYBOARD_LL, WH_MOUSE_LL, WINEVENT_OUTOFCONTEXT,
+    WINEVENT_SKIPOWNPROCESS, WM_KEYDOWN, WM_KEYUP, WM_MBUTTONDOWN, WM_MBUTTONUP, WM_MOUSEWHEEL,
+    WM_QUIT, WM_SYSKEYDOWN, WM_SYSKEYUP, WM_XBUTTONDOWN, WM_XBUTTONUP, XBUTTON1, XBUTTON2,
+};
+use windows::core::PWSTR;
+
+// ============================================================================
+// Key Name Resolution
+// ============================================================================
+
+/// Keys without scan codes on standard keyboards (GetKeyNameTextW can't look them up)
+#[rustfmt::skip]
+const HARDCODED_KEYS: &[(&str, u32)] = &[
+    // F13-F24
+    ("f13", 0x7C), ("f14", 0x7D), ("f15", 0x7E), ("f16", 0x7F),
+    ("f17", 0x80), ("f18", 0x81), ("f19", 0x82), ("f20", 0x83),
+    ("f21", 0x84), ("f22", 0x85), ("f23", 0x86), ("f24", 0x87),
+
+    // Media keys
+    ("media_next_track", 0xB0), ("media_next", 0xB0),
+    ("media_prev_track", 0xB1), ("media_prev", 0xB1),
+    ("media_stop", 0xB2),
+    ("media_play_pause", 0xB3), ("media_play", 0xB3),
+    ("volume_mute", 0xAD), ("mute", 0xAD),
+    ("volume_down", 0xAE), ("vol_down", 0xAE),
+    ("volume_up", 0xAF), ("vol_up", 0xAF),
+
+    // Browser keys
+    ("browser_back", 0xA6), ("browser_forward", 0xA7),
+    ("browser_refresh", 0xA8), ("browser_stop", 0xA9),
+    ("browser_search", 0xAA), ("browser_favorites", 0xAB),
+    ("browser_home", 0xAC),
+
+    // Launch keys
+    ("launch_mail", 0xB4), ("launch_media_select", 0xB5),
+    ("launch_app1", 0xB6), ("launch_app2", 0xB7),
+
+    // Left/right modifier variants (GetKeyNameTextW returns generic names)
+    ("lshift", 0xA0), ("rshift", 0xA1),
+    ("lctrl", 0xA2), ("lcontrol", 0xA2),
+    ("rctrl", 0xA3), ("rcontrol", 0xA3),
+    ("lalt", 0xA4), ("lmenu", 0xA4),
+    ("ralt", 0xA5), ("rmenu", 0xA5),
+    ("lwin", 0x5B), ("rwin", 0x5C),
+
+    // Common aliases
+    ("space", 0x20), ("spacebar", 0x20),
+    ("enter", 0x0D), ("return", 0x0D),
+    ("esc", 0x1B), ("escape", 0x1B),
+    ("backspace", 0x08), ("back", 0x08),
+    ("tab", 0x09),
+    ("insert", 0x2D), ("ins", 0x2D),
+    ("delete", 0x2E), ("del", 0x2E),
+    ("home", 0x24), ("end", 0x23),
+    ("pageup", 0x21), ("page_up", 0x21), ("pgup", 0x21),
+    ("pagedown", 0x22), ("page_down", 0x22), ("pgdn", 0x22),
+    ("up", 0x26), ("down", 0x28), ("left", 0x25), ("right", 0x27),
+    ("capslock", 0x14), ("caps_lock", 0x14), ("caps", 0x14),
+    ("numlock", 0x90), ("num_lock", 0x90),
+    ("scrolllock", 0x91), ("scroll_lock", 0x91),
+    ("printscreen", 0x2C), ("print_screen", 0x2C), ("prtsc", 0x2C),
+    ("pause", 0x13),
+
+    // Numpad keys
+    ("numpad0", 0x60), ("numpad1", 0x61), ("numpad2", 0x62),
+    ("numpad3", 0x63), ("numpad4", 0x64), ("numpad5", 0x65),
+    ("numpad6", 0x66), ("numpad7", 0x67), ("numpad8", 0x68),
+    ("numpad9", 0x69),
+    ("numpad_add", 0x6B), ("numpad_plus", 0x6B),
+    ("numpad_subtract", 0x6D), ("numpad_minus", 0x6D),
+    ("numpad_multiply", 0x6A), ("numpad_mul", 0x6A),
+    ("numpad_divide", 0x6F), ("numpad_div", 0x6F),
+    ("numpad_decimal", 0x6E), ("numpad_dot", 0x6E),
+];
+
+/// Get human-readable key name from Windows VK code
+pub fn get_key_name(vk: u32) -> String {
+    unsafe {
+        let mut buffer = [0u16; 64];
+
+        let scan_code = MapVirtualKeyW(vk, MAPVK_VK_TO_VSC_EX);
+        let extended = (scan_code & 0xFF00) == 0xE000 || (scan_code & 0xFF00) == 0xE100;
+        let lparam = ((scan_code & 0xFF) << 16) | (u32::from(extended) << 24);
+
+        let len = GetKeyNameTextW(lparam as i32, &mut buffer);
+        if len > 0 {
+            OsString::from_wide(&buffer[..len as usize])
+                .to_string_lossy()
+                .into_owned()
+        } else {
+            format!("VK_{:#04X}", vk)
+        }
+    }
+}
+
+/// Build reverse lookup map of layout-independent names: name -> VK code
+///
+/// VK codes for letters, digits and function keys are fixed, whichever key
+/// the layout puts them on.
+pub fn build_key_name_map() -> HashMap<String, u32> {
+    let mut map = HashMap::new();
+    for &(name, vk) in HARDCODED_KEYS {
+        map.insert(name.to_string(), vk);
+    }
+    for c in ('a'..='z').chain('0'..='9') {
+        map.insert(c.to_string(), c.to_ascii_uppercase() as u32);
+    }
+    for n in 1..=24 {
+        map.insert(format!("f{n}"), 0x6F + n);
+    }
+    map
+}
+
+/// Build reverse lookup map of names the active keyboard layout gives keys
+/// (e.g. "entf" for Delete on a German layout): name -> VK code
+pub fn build_layout_key_name_map() -> HashMap<String, u32> {
+    let mut map = HashMap::new();
+    // GetKeyNameTextW names keys by the calling thread's layout, so borrow the
+    // layout of the window the user is typing in
+    let previous = unsafe { ActivateKeyboardLayout(foreground_layout(), Default::default()) };
+
+    // Probe all VK codes for OS-provided names
+    for vk in 0..=255 {
+        let name = get_key_name(vk);
+        if !name.is_empty() && !name.starts_with("VK_") {
+            let normalized = name.to_lowercase();
+            map.insert(normalized.clone(), vk);
+            map.insert(format!("vk_{}", normalized), vk);
+        }
+    }
+
+    if let Ok(previous) = previous {
+        let _ = unsafe { ActivateKeyboardLayout(previous, Default::default()) };
+    }
+    map
+}
+
+/// Identifies the active keyboard layout, changing whenever it does
+pub fn layout_fingerprint() -> Option<u64> {
+    Some(foreground_layout().0 as u64)
+}
+
+/// Keyboard layout of the foreground window's thread
+fn foreground_layout() -> HKL {
+    unsafe {
+        let thread = GetWindowThreadProcessId(GetForegroundWindow(), None);
+        GetKeyboardLayout(thread)
+    }
+}
+
+// ============================================================================
+// Hook Thread
+// ============================================================================
+
+/// Channel message from hook thread to main thread
+struct HookEvent {
+    event: InputEvent,
+    response_tx: tokio::sync::oneshot::Sender<EventResponse>,
+}
+
+/// Global state for hook callback (Win32 requires static access)
+static HOOK_CHANNEL: OnceLock<mpsc::UnboundedSender<HookEvent>> = OnceLock::new();
+
+/// State of the platform whose hooks are installed; low-level hooks are
+/// per-process, so only one platform can run them
+static HOOK_STATE: OnceLock<Arc<PlatformState>> = OnceLock::new();
+
+/// Keys injected down by `send_raw_key` and not yet released
+static HELD_KEYS: HeldKeys = HeldKeys::new();
+
+/// Foreground keyboard layout last seen by the hook thread
+static HOOK_LAYOUT: AtomicU64 = AtomicU64::new(0);
+
+/// Pending layout check on the hook thread, 0 when none is scheduled
+static LAYOUT_TIMER: AtomicUsize = AtomicUsize::new(0);
+
+/// How long after a modifier is released to look at the layout, giving the
+/// foreground app time to act on a switch hotkey
+const LAYOUT_CHECK_DELAY_MS: u32 = 100;
+
+/// Thread ID of the hook thread, used to post WM_QUIT for clean shutdown
+static HOOK_THREAD_ID: OnceLock<u32> = OnceLock::new();
+
+/// Marker for synthetic key injections so we can skip them in the hook
+const INJECTED_MARKER: usize = u32::from_be_bytes(*b"RBND") as usize;
+
+/// Windows platform implementation
+///
+/// Hook and injection state is global (Win32 callbacks need static access).
+/// The platform only holds the state it shares with the engine, so it is cheap
+/// to clone.
+#[derive(Clone)]
+pub struct Platform {
+    /// Focused window, key routes and the rest shared with the engine
+    state: Arc<PlatformState>,
+}
+
+impl Default for Platform {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PlatformInterface for Platform {
+    fn new() -> Self {
+        Self {
+            state: Arc::default(),
+        }
+    }
+
+    /// Run the platform event loop with an async handler
+    ///
+    /// Captures keyboard and mouse wheel events and calls `handler` for each.
+    /// The handler receives the event and a PlatformHandle for
+    /// querying window info and executing actions.
+    async fn run<F, Fut, S>(&mut self, mut handler: F, shutdown: S) -> Result<()>
+    where
+        F: FnMut(InputEvent, PlatformHandle) -> Fut,
+        Fut: Future<Output = EventResponse>,
+        S: Future<Output = ()>,
+    {
+        info!("initializing Windows input hooks");
+
+        let (event_tx, mut event_rx) = mpsc::unbounded_channel();
+        // Store sender in global for hook callback access
+        HOOK_CHANNEL
+            .set(event_tx)
+            .map_err(|_| anyhow!("input hooks are already running"))?;
+        let _ = HOOK_STATE.set(Arc::clone(&self.state));
+
+        // Spawn the hook thread (Win32 message pump must run on dedicated thread)
+        let hook_handle = tokio::task::spawn_blocking(run_hook_thread);
+        // Leaving this function, even by panicking, releases keys and removes the hooks
+        let cleanup = HookCleanup;
+
+        // Create a handle that can be passed to the handler
+        let platform_handle = PlatformHandle::new(self);
+
+        // Gamepads are only polled, and only while the config binds their
+        // buttons. Triggers arrive over HTTP. Neither is hooked, so there is
+        // no response to send back.
+        let (shared_tx, mut shared_rx) = mpsc::unbounded_channel();
+        let shared = [
+            tokio::spawn(super::gamepad::forward(
+                Arc::clone(&self.state),
+                shared_tx.clone(),
+            )),
+            tokio::spawn(super::trigger::forward(Arc::clone(&self.state), shared_tx)),
+        ];
+
+        // Process events from hook thread until shutdown
+        let mut shutdown = std::pin::pin!(shutdown);
+        loop {
+            let hook_event = tokio::select! {
+                hook_event = event_rx.recv() => match hook_event {
+                    Some(hook_event) => hook_event,
+                    None => break,
+                },
+                Some(event) = shared_rx.recv() => {
+                    let fixed = match &event {
+                        InputEvent::Key(key_event) => self.state.fixed_response(key_event.key),
+                        _ => None,
+                    };
+                    if fixed.is_none() {
+                        handler(event, platform_handle.clone()).await;
+                    }
+                    continue;
+                }
+                () = &mut shutdown => {
+                    info!("shutdown requested");
+                    break;
+                }
+            };
+            let response = handler(hook_event.event, platform_handle.clone()).await;
+            // Send response back to hook thread (ignore if receiver dropped)
+            let _ = hook_event.response_tx.send(response);
+        }
+
+        for task in &shared {
+            task.abort();
+        }
+        drop(cleanup);
+
+        // Wait for hook thread to finish
+        hook_handle.await??;
+        Ok(())
+    }
+
+    fn state(&self) -> &Arc<PlatformState> {
+        &self.state
+    }
+
+    fn get_active_window(&self) -> WindowInfo {
+        self.state
+            .focused_window()
+            .unwrap_or_else(get_foreground_window_info)
+    }
+
+    fn send_key(&self, key: SyntheticKey) {
+        let vk = match key {
+            SyntheticKey::BrowserBack => 0xA6,    // VK_BROWSER_BACK
+            SyntheticKey::BrowserForward => 0xA7, // VK_BROWSER_FORWARD
+        };
+        send_key_press(vk);
+    }
+
+    fn send_raw_key(&self, key: KeyCode, down: bool) {
+        send_raw_input(key, down);
+    }
+
+    fn send_pointer(&self, event: PointerEvent) {
+        queue_injection(Injection::Pointer(event));
+    }
+
+    fn send_media(&self, cmd: MediaCommand) {
+        if matches!(
+            cmd,
+            MediaCommand::VolumeUp | MediaCommand::VolumeDown | MediaCommand::VolumeMute
+        ) {
+            send_key_press(media_vk(cmd));
+            return;
+        }
+        // Transport commands go to the current media session directly, since apps
+        // can swallow or ignore the media keys. Session lookup blocks on WinRT
+        // async operations; keep it off the hook thread.
+        std::thread::spawn(move || {
+            if let Err(e) = send_current_session_command(cmd) {
+                debug!(
+                    ?cmd,
+                    "media session command failed ({e}), sending the media key"
+                );
+                send_key_press_sync(media_vk(cmd));
+            }
+        });
+    }
+
+    fn change_volume(&self, percent: i32) {
+        // Each volume key press moves the system volume by 2%
+        let vk = if percent >= 0 { 0xAF } else { 0xAE }; // VK_VOLUME_UP / VK_VOLUME_DOWN
+        for _ in 0..percent.unsigned_abs().div_ceil(2) {
+            send_key_press(vk);
+        }
+    }
+
+    fn send_player_media(&self, player: &PlayerTarget, cmd: MediaCommand) {
+        let player = player.clone();
+        // Session lookup blocks on WinRT async operations; keep it off the hook thread
+        std::thread::spawn(move || {
+            if let Err(e) = send_session_command(&player, cmd) {
+                warn!(?cmd, %player, "media command failed: {e}");
+            }
+        });
+    }
+
+    fn set_mic(&self, cmd: MicCommand) {
+        // COM calls can block on the audio service; keep them off the hook thread
+        std::thread::spawn(move || {
+            if let Err(e) = set_mic_mute(cmd) {
+                warn!(?cmd, "microphone command failed: {e}");
+            }
+        });
+    }
+
+    fn switch_desktop(&self, cmd: DesktopCommand) {
+        // Explorer's Win+Ctrl+Left/Right shortcuts step one desktop at a time, so a
+        // numbered desktop is reached by stepping from the current one
+        let steps = match cmd {
+            DesktopCommand::Next => 1,
+            DesktopCommand::Previous => -1,
+            DesktopCommand::Goto(n) => match desktop_offset(n) {
+                Ok(steps) => steps,
+                Err(e) => {
+                    warn!(?cmd, "desktop switch failed: {e}");
+                    return;
+                }
+            },
+        };
+        step_desktop(steps);
+    }
+
+    fn switch_window(&self, cmd: WindowSwitch) {
+        switch_window(cmd);
+        debug!(?cmd, "switched window");
+    }
+
+    fn snap_window(&self, position: SnapPosition) {
+        match snap_window(position) {
+            Ok(()) => debug!(?position, "snapped window"),
+            Err(e) => warn!(?position, "window snap failed: {e}"),
+        }
+    }
+
+    fn control_session(&self, cmd: SessionCommand) {
+        use windows::Win32::System::Shutdown::LockWorkStation;
+
+        match cmd {
+            // Returns once the lock is requested; the lock itself is asynchronous
+            SessionCommand::Lock => match unsafe { LockWorkStation() } {
+                Ok(()) => debug!(?cmd, "session command sent"),
+                Err(e) => warn!(?cmd, "session command failed: {e}"),
+            },
+            // Suspending blocks until the machine wakes up again, and the
+            // release of the key that turned the displays off would wake them,
+            // so both run on their own thread
+            SessionCommand::Sleep => {
+                std::thread::spawn(move || match suspend() {
+                    Ok(()) => debug!(?cmd, "session command sent"),
+                    Err(e) => warn!(?cmd, "session command failed: {e}"),
+                });
+            }
+            SessionCommand::DisplayOff => {
+                std::thread::spawn(move || {
+                    std::thread::sleep(DISPLAY_OFF_DELAY);
+                    match display_off() {
+                        Ok(()) => debug!(?cmd, "session command sent"),
+                        Err(e) => warn!(?cmd, "session command failed: {e}"),
+                    }
+                });
+            }
+        }
+    }
+
+    fn focus_window(&self, target: &FocusTarget) {
+        match find_window(&target.window) {
+            Some(hwnd) => {
+                bring_to_front(hwnd);
+                debug!(window = ?target.window, "focused window");
+            }
+            None => target.launch_fallback(),
+        }
+    }
+
+    fn process_running(&self, binary: &str) -> bool {
+        use windows::Win32::System::Diagnostics::ToolHelp::{
+            CreateToolhelp32Snapshot, PROCESSENTRY32W, Process32FirstW, Process32NextW,
+            TH32CS_SNAPPROCESS,
+        };
+
+        // SAFETY: a process snapshot takes no pointers
+        let Ok(snapshot) = (unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) }) else {
+            return false;
+        };
+        let mut entry = PROCESSENTRY32W {
+            dwSize: std::mem::size_of::<PROCESSENTRY32W>() as u32,
+            ..Default::default()
+        };
+        let mut found = false;
+        // SAFETY: snapshot is valid and entry's dwSize is set
+        let mut more = unsafe { Process32FirstW(snapshot, &mut entry) }.is_ok();
+        while more {
+            let len = entry
+                .szExeFile
+                .iter()
+                .position(|&c| c == 0)
+                .unwrap_or(entry.szExeFile.len());
+            let name = String::from_utf16_lossy(&entry.szExeFile[..len]);
+            if glob_match::glob_match(binary, &name) {
+                found = true;
+                break;
+            }
+            // SAFETY: as for Process32FirstW
+            more = unsafe { Process32NextW(snapshot, &mut entry) }.is_ok();
+        }
+        // SAFETY: snapshot is a valid handle that we opened
+        let _ = unsafe { CloseHandle(snapshot) };
+        found
+    }
+
+    fn notify(&self, message: &str) {
+        show_notification(message);
+    }
+
+    fn set_clipboard(&self, text: &str, paste: bool) {
+        let text = text.to_string();
+        // Clipboard access can block while another app holds it open
+        std::thread::spawn(move || {
+            if let Err(e) = set_clipboard_text(&text) {
+                warn!("setting clipboard failed: {e}");
+                return;
+            }
+            debug!(paste, "set clipboard");
+
+            if paste {
+                const VK_LCONTROL: u16 = 0xA2;
+                const VK_V: u16 = 0x56;
+                send_raw_key_event(VK_LCONTROL, true);
+                send_raw_key_event(VK_V, true);
+                send_raw_key_event(VK_V, false);
+                send_raw_key_event(VK_LCONTROL, false);
+            }
+        });
+    }
+
+    fn get_clipboard(&self) -> Option<String> {
+        get_clipboard_text().unwrap_or_else(|e| {
+            warn!("reading clipboard failed: {e}");
+            None
+        })
+    }
+}
+
+/// Release every key we injected and never let go of. Also called from the
+/// panic hook, which has no access to the platform.
+pub fn release_held_keys() {
+    let held = HELD_KEYS.take();
+    if !held.is_empty() {
+        info!(count = held.len(), "releasing held keys");
+        for code in held {
+            send_input_sync(KeyCode::new(code), false);
+        }
+    }
+}
+
+/// Undoes the setup done by `run` when it returns or unwinds from a panicking
+/// handler. Without it a panic would leave injected keys held down, and the
+/// runtime would wait forever on a hook thread that is still pumping messages.
+struct HookCleanup;
+
+impl Drop for HookCleanup {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            warn!("event handler panicked, removing input hooks");
+        }
+
+        release_held_keys();
+
+        // Signal hook thread to exit by posting WM_QUIT; it unhooks on the way out
+        if let Some(&thread_id) = HOOK_THREAD_ID.get() {
+            info!("signaling hook thread to exit");
+            unsafe {
+                let _ = PostThreadMessageW(thread_id, WM_QUIT, WPARAM(0), LPARAM(0));
+            }
+        }
+    }
+}
+
+// ============================================================================
+// Hook Thread
+// ============================================================================
+
+/// Runs the Win32 message pump - must be called from a dedicated thread
+fn run_hook_thread() -> Result<()> {
+    unsafe {
+        // Store thread ID so main thread can signal us to exit
+        let thread_id = GetCurrentThreadId();
+        let _ = HOOK_THREAD_ID.set(thread_id);
+
+        // Install low-level keyboard hook
+        let keyboard_hook = SetWindowsHookExW(WH_KEYBOARD_LL, Some(keyboard_hook_proc), None, 0)
+            .map_err(|e| anyhow!("failed to install keyboard hook: {}", e))?;
+        info!("keyboard hook installed");
+
+        // Install low-level mouse hook
+        let mouse_hook = SetWindowsHookExW(WH_MOUSE_LL, Some(mouse_hook_proc), None, 0)
+            .map_err(|e| anyhow!("failed to install mouse hook: {}", e))?;
+        info!("mouse hook installed");
+
+        // Follow focus and title changes, delivered through the same message pump
+        let focus_hooks = [
+            (EVENT_SYSTEM_FOREGROUND, EVENT_SYSTEM_FOREGROUND),
+            (EVENT_OBJECT_NAMECHANGE, EVENT_OBJECT_NAMECHANGE),
+        ]
+        .map(|(min, max)| {
+            SetWinEventHook(
+                min,
+                max,
+                None,
+                Some(focus_event_proc),
+                0,
+                0,
+                WINEVENT_OUTOFCONTEXT | WINEVENT_SKIPOWNPROCESS,
+            )
+        });
+        if focus_hooks.iter().any(|hook| hook.is_invalid()) {
+            warn!("failed to follow focus changes; windows will be queried on each keypress");
+        } else {
+            set_focused_window(Some(get_foreground_window_info()));
+            HOOK_LAYOUT.store(layout_fingerprint().unwrap_or_default(), Ordering::Relaxed);
+            if let Some(state) = HOOK_STATE.get() {
+                state.set_watching_layout(true);
+            }
+        }
+        info!("starting message pump");
+
+        // Message pump - required for low-level hooks to work
+        // Exits when WM_QUIT is received (GetMessageW returns false)
+        let mut msg = MSG::default();
+        while GetMessageW(&mut msg, None, 0, 0).as_bool() {
+            let _ = TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+
+        // Reached once run() posts WM_QUIT at shutdown
+        let _ = UnhookWindowsHookEx(keyboard_hook);
+        let _ = UnhookWindowsHookEx(mouse_hook);
+        for hook in focus_hooks {
+            let _ = UnhookWinEvent(hook);
+        }
+        set_focused_window(None);
+        if let Some(state) = HOOK_STATE.get() {
+            state.set_watching_layout(false);
+        }
+        info!("input hooks uninstalled");
+    }
+
+    Ok(())
+}
+
+/// Record the focused window on the state of the platform running the hooks
+fn set_focused_window(window: Option<WindowInfo>) {
+    if let Some(state) = HOOK_STATE.get() {
+        state.set_focused_window(window);
+    }
+}
+
+/// Report a layout change if the foreground layout differs from the last one
+/// seen. Runs on the hook thread.
+///
+/// `WM_INPUTLANGCHANGE` only reaches the windows of the thread whose layout
+/// changed, never this one, so the hook thread looks for itself whenever the
+/// foreground window changes and shortly after a modifier is released, which
+/// is how layout hotkeys (Win+Space, Alt+Shift) end.
+fn check_layout() {
+    let layout = layout_fingerprint().unwrap_or_default();
+    if HOOK_LAYOUT.swap(layout, Ordering::Relaxed) != layout
+        && let Some(state) = HOOK_STATE.get()
+    {
+        trace!("keyboard layout changed");
+        state.notify_layout_changed();
+    }
+}
+
+/// Run [`check_layout`] on the hook thread after [`LAYOUT_CHECK_DELAY_MS`],
+/// replacing a check that is already pending
+fn schedule_layout_check() {
+    let pending = LAYOUT_TIMER.load(Ordering::Relaxed);
+    // SAFETY: a thread timer; its callback runs from this thread's message pump
+    let timer = unsafe {
+        SetTimer(
+            None,
+            pending,
+            LAYOUT_CHECK_DELAY_MS,
+            Some(layout_timer_proc),
+        )
+    };
+    LAYOUT_TIMER.store(timer, Ordering::Relaxed);
+}
+
+/// SAFETY: Called by Windows from the message pump thread
+unsafe extern "system" fn layout_timer_proc(_hwnd: HWND, _message: u32, id: usize, _time: u32) {
+    let _ = unsafe { KillTimer(None, id) };
+    LAYOUT_TIMER.store(0, Ordering::Relaxed);
+    check_layout();
+}
+
+/// Whether a virtual key is a modifier that ends layout hotkeys
+fn is_layout_modifier(vk: u32) -> bool {
+    // Shift, Ctrl and Alt (left and right), then the Windows keys
+    matches!(vk, 0xA0..=0xA5 | 0x5B | 0x5C)
+}
+
+/// Low-level keyboard hook callback
+/// SAFETY: Called by Windows from the message pump thread
+unsafe extern "system" fn keyboard_hook_proc(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    // code < 0 means we must pass to next hook without processing
+    if code < 0 {
+        // SAFETY: Windows requires us to call the next hook
+        return unsafe { CallNextHookEx(None, code, wparam, lparam) };
+    }
+
+    // SAFETY: lparam points to a valid KBDLLHOOKSTRUCT when code >= 0
+    let kb_struct = unsafe { &*(lparam.0 as *const KBDLLHOOKSTRUCT) };
+
+    // Skip our own synthetic injections
+    if kb_struct.dwExtraInfo == INJECTED_MARKER {
+        return unsafe { CallNextHookEx(None, code, wparam, lparam) };
+    }
+
+    let vk = kb_struct.vkCode;
+    let scancode = hook_scancode(kb_struct);
+
+    let is_keydown = matches!(wparam.0 as u32, WM_KEYDOWN | WM_SYSKEYDOWN);
+    let is_keyup = matches!(wparam.0 as u32, WM_KEYUP | WM_SYSKEYUP);
+
+    if !is_keydown && !is_keyup {
+        // SAFETY: Windows requires us to call the next hook
+        return unsafe { CallNextHookEx(None, code, wparam, lparam) };
+    }
+    if is_keyup && is_layout_modifier(vk) {
+        schedule_layout_check();
+    }
+
+    let key_code = if is_captured_scancode(scancode) {
+        KeyCode::from_scancode(scancode)
+    } else {
+        KeyCode::new(vk)
+    };
+    trace!(?key_code, scancode, is_keydown, "hook received key event");
+
+    // Unbound keys (nearly all of them) and keys with a fixed response never
+    // wait on the main thread
+    match HOOK_STATE
+        .get()
+        .and_then(|state| state.fixed_response(key_code))
+    {
+        Some(EventResponse::Block) => return LRESULT(1),
+        Some(EventResponse::Passthrough | EventResponse::Continue) => {
+            return unsafe { CallNextHookEx(None, code, wparam, lparam) };
+        }
+        None => {}
+    }
+
+    // Timestamped here, before waiting on the main thread
+    let key_event = KeyEvent::new(key_code, is_keydown);
+
+    // Try to send event to main thread and wait for response
+    let input_event = InputEvent::Key(key_event);
+    let should_block = process_hook_event(input_event);
+
+    if should_block {
+        // Return non-zero to block the key from propagating
+        LRESULT(1)
+    } else {
+        // SAFETY: Windows requires us to call the next hook
+        unsafe { CallNextHookEx(None, code, wparam, lparam) }
+    }
+}
+
+/// The event's scancode, with the `0xE0` prefix for extended keys
+fn hook_scancode(kb_struct: &KBDLLHOOKSTRUCT) -> u16 {
+    let scancode = (kb_struct.scanCode & 0xFF) as u16;
+    if kb_struct.flags.contains(LLKHF_EXTENDED) {
+        0xE000 | scancode
+    } else {
+        scancode
+    }
+}
+
+/// Whether the config of the platform running the hooks binds this scancode,
+/// so the hook reports the key by scancode instead of VK code
+fn is_captured_scancode(scancode: u16) -> bool {
+    HOOK_STATE
+        .get()
+        .is_some_and(|state| state.captures_scancode(scancode))
+}
+
+/// Send event to main thread and wait for response
+fn process_hook_event(event: InputEvent) -> bool {
+    let Some(tx) = HOOK_CHANNEL.get() else {
+        return false;
+    };
+
+    let (response_tx, response_rx) = tokio::sync::oneshot::channel();
+
+    // Send event to main thread
+    if tx.send(HookEvent { event, response_tx }).is_err() {
+        debug!("hook channel closed");
+        return false;
+    }
+
+    // Block waiting for response (we're on hook thread, not async)
+    match response_rx.blocking_recv() {
+        Ok(EventResponse::Block) => true,
+        Ok(EventResponse::Passthrough | EventResponse::Continue) => false,
+        Err(_) => {
+            debug!("response channel closed");
+            false
+        }
+    }
+}
+
+/// Low-level mouse hook callback
+/// SAFETY: Called by Windows from the message pump thread
+unsafe extern "system" fn mouse_hook_proc(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    // code < 0 means we must pass to next hook without processing
+    if code < 0 {
+        return unsafe { CallNextHookEx(None, code, wparam, lparam) };
+    }
+
+    // SAFETY: lparam points to a valid MSLLHOOKSTRUCT when code >= 0
+    let mouse_struct = unsafe { &*(lparam.0 as *const MSLLHOOKSTRUCT) };
+
+    // Skip our own injected pointer input (mouse keys clicks and scrolling)
+    if mouse_struct.dwExtraInfo == INJECTED_MARKER {
+        return unsafe { CallNextHookEx(None, code, wparam, lparam) };
+    }
+
+    // Only process wheel, middle and X button events; left/right clicks never
+    // wait on the daemon (see `MouseButton`)
+    let Some(input_event) = mouse_event(wparam.0 as u32, mouse_struct.mouseData) else {
+        return unsafe { CallNextHookEx(None, code, wparam, lparam) };
+    };
+    trace!(?input_event, "hook received mouse event");
+
+    // Try to send event to main thread and wait for response
+    let should_block = process_hook_event(input_event);
+
+    if should_block {
+        // Return non-zero to block the event from propagating
+        LRESULT(1)
+    } else {
+        unsafe { CallNextHookEx(None, code, wparam, lparam) }
+    }
+}
+
+/// Focus change or window title change, from `SetWinEventHook`
+/// SAFETY: Called by Windows from the message pump thread
+unsafe extern "system" fn focus_event_proc(
+    _hook: HWINEVENTHOOK,
+    event: u32,
+    hwnd: HWND,
+    id_object: i32,
+    id_child: i32,
+    _thread: u32,
+    _time: u32,
+) {
+    // Title changes arrive for every window and every accessible object;
+    // only the foreground window's own name matters
+    if event == EVENT_OBJECT_NAMECHANGE
+        && (id_object != OBJID_WINDOW.0
+            || id_child != CHILDID_SELF as i32
+            || hwnd != unsafe { GetForegroundWindow() })
+    {
+        return;
+    }
+    let window = get_foreground_window_info();
+    trace!(?window, "focused window changed");
+    set_focused_window(Some(window));
+    // Each thread has its own layout, so moving focus can change it too
+    if event == EVENT_SYSTEM_FOREGROUND {
+        check_layout();
+    }
+}
+
+/// The event for a mouse hook message, if it is one we intercept
+fn mouse_event(message: u32, mouse_data: u32) -> Option<InputEvent> {
+    // Wheel delta and X button number are in the high word of mouseData
+    let high_word = (mouse_data >> 16) as u16;
+    let (button, down) = match message {
+        // Positive = scroll up (away from user), negative = scroll down (toward user)
+        WM_MOUSEWHEEL => {
+            return Some(InputEvent::Scroll {
+                up: high_word as i16 > 0,
+            });
+        }
+        WM_MBUTTONDOWN => (MouseButton::Middle, true),
+        WM_MBUTTONUP => (MouseButton::Middle, false),
+        WM_XBUTTONDOWN | WM_XBUTTONUP => {
+            let button = match high_word {
+                XBUTTON1 => MouseButton::Back,
+                XBUTTON2 => MouseButton::Forward,
+                _ => return None,
+            };
+            (button, message == WM_XBUTTONDOWN)
+        }
+        _ => return None,
+    };
+    Some(InputEvent::MouseButton { button, down })
+}
+
+// ============================================================================
+// Window Queries
+// ============================================================================
+
+/// Query information about the currently focused window
+///
+/// Class and binary are cached for as long as the window keeps focus, sparing
+/// the `OpenProcess` lookup on rapid presses of a conditional binding.
+fn get_foreground_window_info() -> WindowInfo {
+    static FOREGROUND: WindowCache = WindowCache::new();
+
+    unsafe {
+        let hwnd = GetForegroundWindow();
+        if hwnd.0.is_null() {
+            return WindowInfo::default();
+        }
+
+        FOREGROUND.get(hwnd.0 as u64, get_window_title(hwnd), || {
+            (get_window_class(hwnd), get_window_binary(hwnd))
+        })
+    }
+}
+
+/// The monitor holding the foreground window
+///
+/// Numbered in `EnumDisplayMonitors` order, which need not follow the display
+/// numbers in Settings; the device name (`\\.\DISPLAY2`) does.
+pub fn active_monitor() -> Option<MonitorInfo> {
+    use windows::Win32::Foundation::RECT;
+    use windows::Win32::Graphics::Gdi::{
+        EnumDisplayMonitors, GetMonitorInfoW, HDC, HMONITOR, MONITOR_DEFAULTTONULL, MONITORINFO,
+        MONITORINFOEXW, MonitorFromWindow,
+    };
+    use windows::core::BOOL;
+
+    unsafe extern "system" fn collect(
+        monitor: HMONITOR,
+        _hdc: HDC,
+        _rect: *mut RECT,
+        lparam: LPARAM,
+    ) -> BOOL {
+        // SAFETY: lparam is the Vec passed to EnumDisplayMonitors below, alive for the call
+        let monitors = unsafe { &mut *(lparam.0 as *mut Vec<HMONITOR>) };
+        monitors.push(monitor);
+        BOOL(1)
+    }
+
+    // SAFETY: plain Win32 queries; the callback only touches `monitors`,
+    // which outlives the enumeration
+    unsafe {
+        let hwnd = GetForegroundWindow();
+        if hwnd.0.is_null() {
+            return None;
+        }
+        let monitor = MonitorFromWindow(hwnd, MONITOR_DEFAULTTONULL);
+        if monitor.is_invalid() {
+            return None;
+        }
+
+        let mut monitors: Vec<HMONITOR> = Vec::new();
+        EnumDisplayMonitors(
+            None,
+            None,
+            Some(collect),
+            LPARAM(&mut monitors as *mut Vec<HMONITOR> as isize),
+        )
+        .ok()
+        .ok()?;
+        let index = monitors.iter().position(|&m| m == monitor)?;
+
+        let mut info = MONITORINFOEXW::default();
+        info.monitorInfo.cbSize = std::mem::size_of::<MONITORINFOEXW>() as u32;
+        GetMonitorInfoW(
+            monitor,
+            &mut info as *mut MONITORINFOEXW as *mut MONITORINFO,
+        )
+        .ok()
+        .ok()?;
+        let len = info
+            .szDevice
+            .iter()
+            .position(|&c| c == 0)
+            .unwrap_or(info.szDevice.len());
+
+        Some(MonitorInfo {
+            index: index as u32 + 1,
+            name: String::from_utf16_lossy(&info.szDevice[..len]),
+        })
+    }
+}
+
+/// Visible, unowned top-level windows in Z-order, topmost first
+fn top_level_windows() -> Vec<HWND> {
+    use windows::Win32::UI::WindowsAndMessaging::{
+        EnumWindows, GW_OWNER, GetWindow, IsWindowVisible,
+    };
+    use windows::core::BOOL;
+
+    unsafe extern "system" fn collect(hwnd: HWND, lparam: LPARAM) -> BOOL {
+        // SAFETY: lparam is the Vec passed to EnumWindows below, alive for the call
+        let windows = unsafe { &mut *(lparam.0 as *mut Vec<HWND>) };
+        // SAFETY: EnumWindows passes valid top-level window handles
+        let visible = unsafe { IsWindowVisible(hwnd) }.as_bool();
+        let owned = unsafe { GetWindow(hwnd, GW_OWNER) }.is_ok_and(|owner| !owner.0.is_null());
+        if visible && !owned {
+            windows.push(hwnd);
+        }
+        BOOL(1)
+    }
+
+    let mut windows: Vec<HWND> = Vec::new();
+    // SAFETY: the callback only touches `windows`, which outlives the call
+    if let Err(e) = unsafe {
+        EnumWindows(
+            Some(collect),
+            LPARAM(&mut windows as *mut Vec<HWND> as isize),
+        )
+    } {
+        warn!("EnumWindows failed: {e}");
+    }
+    windows
+}
+
+/// Find the first visible, unowned top-level window matching `condition`
+fn find_window(condition: &WindowCondition) -> Option<HWND> {
+    top_level_windows().into_iter().find(|&hwnd| {
+        // SAFETY: hwnd came from EnumWindows
+        let info = unsafe {
+            WindowInfo {
+                title: get_window_title(hwnd),
+                class: get_window_class(hwnd),
+                binary: get_window_binary(hwnd),
+            }
+        };
+        condition.matches(&info)
+    })
+}
+
+/// Activate the next or previous window Alt+Tab would offer. Windows on
+/// other virtual desktops and suspended store apps are cloaked, and are
+/// left out like tool windows and untitled ones.
+fn switch_window(cmd: WindowSwitch) {
+    use windows::Win32::Graphics::Dwm::{DWMWA_CLOAKED, DwmGetWindowAttribute};
+    use windows::Win32::UI::WindowsAndMessaging::{
+        GWL_EXSTYLE, GetWindowLongW, GetWindowTextLengthW, HWND_BOTTOM, SWP_NOACTIVATE, SWP_NOMOVE,
+        SWP_NOSIZE, SetWindowPos, WS_EX_TOOLWINDOW,
+    };
+
+    let stack: Vec<HWND> = top_level_windows()
+        .into_iter()
+        .filter(|&hwnd| {
+            let mut cloaked = 0u32;
+            // SAFETY: hwnd came from EnumWindows, and DWMWA_CLOAKED writes a
+            // u32 into the buffer given
+            unsafe {
+                let tool = GetWindowLongW(hwnd, GWL_EXSTYLE) as u32 & WS_EX_TOOLWINDOW.0 != 0;
+                let titled = GetWindowTextLengthW(hwnd) > 0;
+                let _ = DwmGetWindowAttribute(
+                    hwnd,
+                    DWMWA_CLOAKED,
+                    &mut cloaked as *mut u32 as *mut _,
+                    std::mem::size_of::<u32>() as u32,
+                );
+                titled && !tool && cloaked == 0
+            }
+        })
+        .collect();
+
+    let Some(target) = window_switch_target(&stack, cmd) else {
+        return;
+    };
+    if cmd == WindowSwitch::Next {
+        // SAFETY: stack[0] is a valid top-level window handle
+        if let Err(e) = unsafe {
+            SetWindowPos(
+                stack[0],
+                Some(HWND_BOTTOM),
+                0,
+                0,
+                0,
+                0,
+                SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE,
+            )
+        } {
+            debug!("could not lower the active window: {e}");
+        }
+    }
+    bring_to_front(target);
+}
+
+/// Move the foreground window over part of its monitor's work area, leaving
+/// the taskbar uncovered
+fn snap_window(position: SnapPosition) -> Result<()> {
+    use windows::Win32::Foundation::RECT;
+    use windows::Win32::Graphics::Dwm::{DWMWA_EXTENDED_FRAME_BOUNDS, DwmGetWindowAttribute};
+    use windows::Win32::Graphics::Gdi::{
+        GetMonitorInfoW, MONITOR_DEFAULTTONEAREST, MONITORINFO, MonitorFromWindow,
+    };
+    use windows::Win32::UI::WindowsAndMessaging::{
+        GetWindowRect, IsZoomed, MoveWindow, SW_MAXIMIZE, SW_RESTORE, ShowWindow,
+    };
+
+    // SAFETY: plain Win32 calls on the foreground window, with buffers sized
+    // for what each one writes
+    unsafe {
+        let hwnd = GetForegroundWindow();
+        if hwnd.0.is_null() {
+            return Err(anyhow!("no foreground window"));
+        }
+        if position == SnapPosition::Maximize {
+            let _ = ShowWindow(hwnd, SW_MAXIMIZE);
+            return Ok(());
+        }
+        // A maximized window would keep its maximized placement
+        if IsZoomed(hwnd).as_bool() {
+            let _ = ShowWindow(hwnd, SW_RESTORE);
+        }
+
+        let monitor = MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST);
+        let mut info = MONITORINFO {
+            cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+            ..Default::default()
+        };
+        if !GetMonitorInfoW(monitor, &mut info).as_bool() {
+            return Err(anyhow!("GetMonitorInfoW failed"));
+        }
+        let work = info.rcWork;
+        let rect = position.rect(Rect {
+            x: work.left,
+            y: work.top,
+            width: work.right - work.left,
+            height: work.bottom - work.top,
+        });
+
+        // Since Windows 10 the window rectangle includes invisible resize
+        // borders; grow the target by them so the visible edges line up
+        let mut outer = RECT::default();
+        GetWindowRect(hwnd, &mut outer)?;
+        let mut visible = outer;
+        let _ = DwmGetWindowAttribute(
+            hwnd,
+            DWMWA_EXTENDED_FRAME_BOUNDS,
+            &mut visible as *mut RECT as *mut _,
+            std::mem::size_of::<RECT>() as u32,
+        );
+        let (left, top) = (visible.left - outer.left, visible.top - outer.top);
+        let (right, bottom) = (outer.right - visible.right, outer.bottom - visible.bottom);
+
+        MoveWindow(
+            hwnd,
+            rect.x - left,
+            rect.y - top,
+            rect.width + left + right,
+            rect.height + top + bottom,
+            true,
+        )?;
+    }
+    Ok(())
+}
+
+/// Restore a window if minimized and make it the foreground window
+fn bring_to_front(hwnd: HWND) {
+    use windows::Win32::UI::WindowsAndMessaging::{
+        IsIconic, SW_RESTORE, SetForegroundWindow, ShowWindow,
+    };
+
+    const VK_MENU: u16 = 0x12;
+
+    // SAFETY: hwnd is a valid top-level window handle from EnumWindows
+    unsafe {
+        if IsIconic(hwnd).as_bool() {
+            let _ = ShowWindow(hwnd, SW_RESTORE);
+        }
+        // Windows only lets the process that received the last input event take the
+        // foreground. A synthetic Alt press makes that us.
+        send_key_event_sync(VK_MENU, true);
+        let focused = SetForegroundWindow(hwnd).as_bool();
+        send_key_event_sync(VK_MENU, false);
+        if !focused {
+            warn!("SetForegroundWindow was refused");
+        }
+    }
+}
+
+/// Get the window title
+unsafe fn get_window_title(hwnd: HWND) -> String {
+    let mut buffer = [0u16; 512];
+    // SAFETY: hwnd is a valid window handle, buffer is correctly sized
+    let len = unsafe { GetWindowTextW(hwnd, &mut buffer) };
+    if len > 0 {
+        OsString::from_wide(&buffer[..len as usize])
+            .to_string_lossy()
+            .into_owned()
+    } else {
+        String::new()
+    }
+}
+
+/// Get the window class name
+unsafe fn get_window_class(hwnd: HWND) -> String {
+    let mut buffer = [0u16; 256];
+    // SAFETY: hwnd is a valid window handle, buffer is correctly sized
+    let len = unsafe { GetClassNameW(hwnd, &mut buffer) };
+    if len > 0 {
+        OsString::from_wide(&buffer[..len as usize])
+            .to_string_lossy()
+            .into_owned()
+    } else {
+        String::new()
+    }
+}
+
+/// Get the executable name for the window's process
+unsafe fn get_window_binary(hwnd: HWND) -> String {
+    let mut pid = 0u32;
+    // SAFETY: hwnd is a valid window handle
+    unsafe { GetWindowThreadProcessId(hwnd, Some(&mut pid)) };
+    if pid == 0 {
+        return String::new();
+    }
+
+    // SAFETY: pid is a valid process ID obtained from GetWindowThreadProcessId
+    let Ok(process) = (unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid) })
+    else {
+        return String::new();
+    };
+
+    let mut buffer = [0u16; 512];
+    let mut size = buffer.len() as u32;
+
+    // SAFETY: process is a valid handle, buffer and size are correctly initialized
+    let result = if unsafe {
+        QueryFullProcessImageNameW(
+            process,
+            PROCESS_NAME_FORMAT(0),
+            PWSTR(buffer.as_mut_ptr()),
+            &mut size,
+        )
+    }
+    .is_ok()
+    {
+        let path = OsString::from_wide(&buffer[..size as usize])
+            .to_string_lossy()
+            .into_owned();
+
+        // Extract just the filename
+        path.rsplit('\\').next().unwrap_or(&path).to_string()
+    } else {
+        String::new()
+    };
+
+    // SAFETY: process is a valid handle that we opened
+    let _ = unsafe { CloseHandle(process) };
+
+    result
+}
+
+// ============================================================================
+// Microphone
+// ============================================================================
+
+/// Mute, unmute or toggle the default communications microphone
+fn set_mic_mute(cmd: MicCommand) -> Result<()> {
+    use windows::Win32::Media::Audio::Endpoints::IAudioEndpointVolume;
+    use windows::Win32::Media::Audio::{
+        IMMDeviceEnumerator, MMDeviceEnumerator, eCapture, eCommunications,
+    };
+    use windows::Win32::System::Com::{
+        CLSCTX_ALL, COINIT_MULTITHREADED, CoCreateInstance, CoInitializeEx,
+    };
+
+    // SAFETY: COM is initialized on this (fresh) thread before any other call,
+    // and the null event context GUID is allowed
+    unsafe {
+        CoInitializeEx(None, COINIT_MULTITHREADED).ok()?;
+        let enumerator: IMMDeviceEnumerator =
+            CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)?;
+        let device = enumerator.GetDefaultAudioEndpoint(eCapture, eCommunications)?;
+        let volume: IAudioEndpointVolume = device.Activate(CLSCTX_ALL, None)?;
+        let mute = match cmd {
+            MicCommand::Mute => true,
+            MicCommand::Unmute => false,
+            MicCommand::Toggle => !volume.GetMute()?.as_bool(),
+        };
+        volume.SetMute(mute, std::ptr::null())?;
+        debug!(mute, "set microphone mute");
+    }
+    Ok(())
+}
+
+// ============================================================================
+// Media sessions
+// ============================================================================
+
+/// The virtual key for a media command, for injecting it as a key press
+fn media_vk(cmd: MediaCommand) -> u16 {
+    match cmd {
+        // There is no pause-only key
+        MediaCommand::PlayPause | MediaCommand::Pause => 0xB3, // VK_MEDIA_PLAY_PAUSE
+        MediaCommand::Next => 0xB0,                            // VK_MEDIA_NEXT_TRACK
+        MediaCommand::Previous => 0xB1,                        // VK_MEDIA_PREV_TRACK
+        MediaCommand::Stop => 0xB2,                            // VK_MEDIA_STOP
+        MediaCommand::VolumeUp => 0xAF,                        // VK_VOLUME_UP
+        MediaCommand::VolumeDown => 0xAE,                      // VK_VOLUME_DOWN
+        MediaCommand::VolumeMute => 0xAD,                      // VK_VOLUME_MUTE
+    }
+}
+
+/// Send a transport command to the session Windows considers current (the one
+/// its media flyout shows)
+fn send_current_session_command(cmd: MediaCommand) -> Result<()> {
+    use windows::Media::Control::GlobalSystemMediaTransportControlsSessionManager as SessionManager;
+
+    let manager = SessionManager::RequestAsync()?.get()?;
+    let session = manager
+        .GetCurrentSession()
+        .map_err(|_| anyhow!("no current media session"))?;
+    send_to_session(&session, cmd)?;
+    let app = session.SourceAppUserModelId().map(|id| id.to_string());
+    debug!(
+        ?cmd,
+        app = app.unwrap_or_default(),
+        "sent media session command"
+    );
+    Ok(())
+}
+
+/// Send a transport command to the media session of `player`. A name matches
+/// app IDs containing it (e.g. "spotify" matches "Spotify.exe" and
+/// "SpotifyAB.SpotifyMusic_zpdnekdrzrea0!Spotify"). A binary glob is matched
+/// against the whole app ID, ignoring case, which for desktop apps is their
+/// executable name.
+fn send_session_command(player: &PlayerTarget, cmd: MediaCommand) -> Result<()> {
+    use windows::Media::Control::GlobalSystemMediaTransportControlsSessionManager as SessionManager;
+
+    let matches = |id: String| {
+        let id = id.to_lowercase();
+        match player {
+            PlayerTarget::Name(name) => id.contains(&name.to_lowercase()),
+            PlayerTarget::Binary(binary) => glob_match::glob_match(&binary.to_lowercase(), &id),
+        }
+    };
+
+    let manager = SessionManager::RequestAsync()?.get()?;
+    let session = manager
+        .GetSessions()?
+        .into_iter()
+        .find(|session| {
+            session
+                .SourceAppUserModelId()
+                .is_ok_and(|id| matches(id.to_string()))
+        })
+        .ok_or_else(|| anyhow!("no media session for '{player}'"))?;
+
+    send_to_session(&session, cmd)?;
+    debug!(?cmd, %player, "sent media session command");
+    Ok(())
+}
+
+/// Ask a media session to perform a transport command
+fn send_to_session(
+    session: &windows::Media::Control::GlobalSystemMediaTransportControlsSession,
+    cmd: MediaCommand,
+) -> Result<()> {
+    let accepted = match cmd {
+        MediaCommand::PlayPause => session.TryTogglePlayPauseAsync()?.get()?,
+        MediaCommand::Pause => session.TryPauseAsync()?.get()?,
+        MediaCommand::Next => session.TrySkipNextAsync()?.get()?,
+        MediaCommand::Previous => session.TrySkipPreviousAsync()?.get()?,
+        MediaCommand::Stop => session.TryStopAsync()?.get()?,
+        MediaCommand::VolumeUp | MediaCommand::VolumeDown | MediaCommand::VolumeMute => {
+            return Err(anyhow!("media sessions have no volume control"));
+        }
+    };
+    if !accepted {
+        return Err(anyhow!("session rejected the command"));
+    }
+    Ok(())
+}
+
+// ============================================================================
+// Notifications
+// ============================================================================
+
+/// Show a toast notification without blocking the caller
+pub fn show_notification(message: &str) {
+    let message = message.to_string();
+    // WinRT activation can take a moment on first use; keep it off the hook thread
+    std::thread::spawn(move || {
+        if let Err(e) = show_toast(&message) {
+            warn!("notification failed: {e}");
+        } else {
+            debug!(message, "showed notification");
+        }
+    });
+}
+
+/// Suspend (not hibernate) the machine. Needs the shutdown privilege, which
+/// users hold but processes don't have enabled.
+fn suspend() -> windows::core::Result<()> {
+    use windows::Win32::Foundation::{HANDLE, LUID};
+    use windows::Win32::Security::{
+        AdjustTokenPrivileges, LUID_AND_ATTRIBUTES, LookupPrivilegeValueW, SE_PRIVILEGE_ENABLED,
+        SE_SHUTDOWN_NAME, TOKEN_ADJUST_PRIVILEGES, TOKEN_PRIVILEGES, TOKEN_QUERY,
+    };
+    use windows::Win32::System::Power::SetSuspendState;
+    use windows::Win32::System::Threading::{GetCurrentProcess, OpenProcessToken};
+    use windows::core::PCWSTR;
+
+    // SAFETY: the token handle is closed before returning, and every pointer
+    // passed refers to a local that outlives the call
+    unsafe {
+        let mut token = HANDLE::default();
+        OpenProcessToken(
+            GetCurrentProcess(),
+            TOKEN_ADJUST_PRIVILEGES | TOKEN_QUERY,
+            &mut token,
+        )?;
+        let mut luid = LUID::default();
+        let enabled =
+            LookupPrivilegeValueW(PCWSTR::null(), SE_SHUTDOWN_NAME, &mut luid).and_then(|()| {
+                let privileges = TOKEN_PRIVILEGES {
+                    PrivilegeCount: 1,
+                    Privileges: [LUID_AND_ATTRIBUTES {
+                        Luid: luid,
+                        Attributes: SE_PRIVILEGE_ENABLED,
+                    }],
+                };
+                AdjustTokenPrivileges(token, false, Some(&privileges), 0, None, None)
+            });
+        let _ = CloseHandle(token);
+        enabled?;
+
+        let suspended: bool = SetSuspendState(false, false, false).into();
+        if !suspended {
+            return Err(windows::core::Error::from_win32());
+        }
+    }
+    Ok(())
+}
+
+/// Ask every top-level window to power the monitors down, as the screen saver does
+fn display_off() -> windows::core::Result<()> {
+    use windows::Win32::UI::WindowsAndMessaging::{
+        HWND_BROADCAST, PostMessageW, SC_MONITORPOWER, WM_SYSCOMMAND,
+    };
+
+    /// `SC_MONITORPOWER` argument for powering off rather than low power
+    const MONITOR_OFF: isize = 2;
+
+    // SAFETY: posting a system command carries no pointers
+    unsafe {
+        PostMessageW(
+            Some(HWND_BROADCAST),
+            WM_SYSCOMMAND,
+            WPARAM(SC_MONITORPOWER as usize),
+            LPARAM(MONITOR_OFF),
+        )
+    }
+}
+
+/// Whether the foreground app holds the display in exclusive fullscreen
+/// (a Direct3D game that owns the screen)
+pub fn fullscreen_exclusive() -> bool {
+    use windows::Win32::UI::Shell::{QUNS_RUNNING_D3D_FULL_SCREEN, SHQueryUserNotificationState};
+
+    unsafe { SHQueryUserNotificationState() }
+        .is_ok_and(|state| state == QUNS_RUNNING_D3D_FULL_SCREEN)
+}
+
+/// Show a toast notification
+fn show_toast(message: &str) -> windows::core::Result<()> {
+    use windows::Data::Xml::Dom::XmlDocument;
+    use windows::UI::Notifications::{ToastNotification, ToastNotificationManager};
+    use windows::core::HSTRING;
+
+    // Toasts from unpackaged programs need a registered AppUserModelID. Borrow
+    // PowerShell's, which exists on every install, rather than writing a shortcut.
+    const APP_ID: &str =
+        r"{1AC14E77-02E7-4E5D-B744-2EB1AE5198B7}\WindowsPowerShell\v1.0\powershell.exe";
+
+    let xml = XmlDocument::new()?;
+    xml.LoadXml(&HSTRING::from(format!(
+        "<toast duration=\"short\"><visual><binding template=\"ToastGeneric\">\
+         <text>rebinded</text><text>{}</text></binding></visual>\
+         <audio silent=\"true\"/></toast>",
+        escape_xml(message)
+    )))?;
+    let toast = ToastNotification::CreateToastNotification(&xml)?;
+    ToastNotificationManager::CreateToastNotifierWithId(&HSTRING::from(APP_ID))?.Show(&toast)
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// ============================================================================
+// Clipboard
+// ============================================================================
+
+/// Replace the clipboard contents with `text` as CF_UNICODETEXT
+fn set_clipboard_text(text: &str) -> Result<()> {
+    use windows::Win32::Foundation::HANDLE;
+    use windows::Win32::System::DataExchange::{
+        CloseClipboard, EmptyClipboard, OpenClipboard, SetClipboardData,
+    };
+    use windows::Win32::System::Memory::{GMEM_MOVEABLE, GlobalAlloc, GlobalLock, GlobalUnlock};
+
+    const CF_UNICODETEXT: u32 = 13;
+
+    let wide: Vec<u16> = text.encode_utf16().chain(std::iter::once(0)).collect();
+
+    unsafe {
+        OpenClipboard(None).map_err(|e| anyhow!("failed to open clipboard: {e}"))?;
+
+        let result = (|| -> Result<()> {
+            EmptyClipboard()?;
+
+            // The clipboard takes ownership of the allocation once SetClipboardData succeeds
+            let memory = GlobalAlloc(GMEM_MOVEABLE, std::mem::size_of_val(wide.as_slice()))?;
+            let dest = GlobalLock(memory) as *mut u16;
+            if dest.is_null() {
+                return Err(anyhow!("failed to lock clipboard memory"));
+            }
+            std::ptr::copy_nonoverlapping(wide.as_ptr(), dest, wide.len());
+            let _ = GlobalUnlock(memory);
+
+            SetClipboardData(CF_UNICODETEXT, Some(HANDLE(memory.0)))?;
+            Ok(())
+        })();
+
+        let _ = CloseClipboard();
+        result
+    }
+}
+
+/// The clipboard's CF_UNICODETEXT contents, if it holds text
+fn get_clipboard_text() -> Result<Option<String>> {
+    use windows::Win32::Foundation::HGLOBAL;
+    use windows::Win32::System::DataExchange::{
+        CloseClipboard, GetClipboardData, IsClipboardFormatAvailable, OpenClipboard,
+    };
+    use windows::Win32::System::Memory::{GlobalLock, GlobalSize, GlobalUnlock};
+
+    const CF_UNICODETEXT: u32 = 13;
+
+    unsafe {
+        if IsClipboardFormatAvailable(CF_UNICODETEXT).is_err() {
+            return Ok(None);
+        }
+        OpenClipboard(None).map_err(|e| anyhow!("failed to open clipboard: {e}"))?;
+
+        let result = (|| -> Result<Option<String>> {
+            // The clipboard keeps ownership of the data; it is only borrowed here
+            let data = GetClipboardData(CF_UNICODETEXT)?;
+            let memory = HGLOBAL(data.0);
+            let text = GlobalLock(memory) as *const u16;
+            if text.is_null() {
+                return Err(anyhow!("failed to lock clipboard memory"));
+            }
+            let units = std::slice::from_raw_parts(text, GlobalSize(memory) / 2);
+            let len = units.iter().position(|&c| c == 0).unwrap_or(units.len());
+            let text = String::from_utf16_lossy(&units[..len]);
+            let _ = GlobalUnlock(memory);
+            Ok(Some(text))
+        })();
+
+        let _ = CloseClipboard();
+        result
+    }
+}
+
+// ============================================================================
+// Synthetic Input
+// ============================================================================
+
+/// Send a synthetic key press (key down + key up)
+///
+/// Spawns a thread to avoid blocking - some keys (especially media keys)
+/// can block SendInput for 600ms+ while Windows processes them.
+fn send_key_press(vk: u16) {
+    std::thread::spawn(move || send_key_press_sync(vk));
+}
+
+/// Step `steps` desktops right (positive) or left (negative) with Win+Ctrl+arrow
+fn step_desktop(steps: i64) {
+    const VK_LWIN: u16 = 0x5B;
+    const VK_LCONTROL: u16 = 0xA2;
+    let arrow = if steps > 0 { 0x27 } else { 0x25 }; // VK_RIGHT / VK_LEFT
+
+    send_raw_key_event(VK_LWIN, true);
+    send_raw_key_event(VK_LCONTROL, true);
+    for _ in 0..steps.unsigned_abs() {
+        send_raw_key_event(arrow, true);
+        send_raw_key_event(arrow, false);
+    }
+    send_raw_key_event(VK_LCONTROL, false);
+    send_raw_key_event(VK_LWIN, false);
+}
+
+/// Number of desktops between the current one and desktop `n` (1-based)
+///
+/// Explorer records desktop GUIDs in the registry: `VirtualDesktopIDs` lists
+/// every desktop in order, `CurrentVirtualDesktop` names the active one.
+fn desktop_offset(n: u32) -> Result<i64> {
+    const KEY: &str = r"Software\Microsoft\Windows\CurrentVersion\Explorer\VirtualDesktops";
+    const GUID_LEN: usize = 16;
+
+    let ids = read_registry_binary(KEY, "VirtualDesktopIDs")?;
+    let current = read_registry_binary(KEY, "CurrentVirtualDesktop")?;
+
+    let desktops: Vec<&[u8]> = ids.chunks_exact(GUID_LEN).collect();
+    let current = desktops
+        .iter()
+        .position(|id| *id == current.as_slice())
+        .ok_or_else(|| anyhow!("current desktop not found in desktop list"))?;
+    let target = n as usize - 1;
+    if target >= desktops.len() {
+        return Err(anyhow!(
+            "desktop {n} does not exist (have {})",
+            desktops.len()
+        ));
+    }
+    Ok(target as i64 - current as i64)
+}
+
+/// Read a REG_BINARY value from HKEY_CURRENT_USER
+fn read_registry_binary(key: &str, value: &str) -> Result<Vec<u8>> {
+    use windows::Win32::System::Registry::{HKEY_CURRENT_USER, RRF_RT_REG_BINARY, RegGetValueW};
+    use windows::core::HSTRING;
+
+    let key = HSTRING::from(key);
+    let value_name = HSTRING::from(value);
+
+    // First call sizes the buffer, second fills it
+    let mut len = 0u32;
+    let status = unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            &key,
+            &value_name,
+            RRF_RT_REG_BINARY,
+            None,
+            None,
+            Some(&mut len),
+        )
+    };
+    if status.is_err() {
+        return Err(anyhow!("failed to read registry value {value}: {status:?}"));
+    }
+
+    let mut data = vec![0u8; len as usize];
+    let status = unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            &key,
+            &value_name,
+            RRF_RT_REG_BINARY,
+            None,
+            Some(data.as_mut_ptr().cast()),
+            Some(&mut len),
+        )
+    };
+    if status.is_err() {
+        return Err(anyhow!("failed to read registry value {value}: {status:?}"));
+    }
+    data.truncate(len as usize);
+    Ok(data)
+}
+
+/// Input for the injection thread
+enum Injection {
+    Key(KeyCode, bool),
+    Pointer(PointerEvent),
+}
+
+/// Queue feeding the raw input injection thread
+static INJECTION_TX: OnceLock<std::sync::mpsc::Sender<Injection>> = OnceLock::new();
+
+/// Send a single synthetic key press or release
+///
+/// Like `send_key_press`, injection happens off the calling thread (SendInput
+/// would otherwise wait on our own hook). A single long-lived thread drains the
+/// queue so press/release pairs reach the OS in the order they were sent.
+fn send_raw_key_event(vk: u16, down: bool) {
+    send_raw_input(KeyCode::new(vk.into()), down);
+}
+
+/// `send_raw_key_event` for any key code, including scancode keys
+fn send_raw_input(key: KeyCode, down: bool) {
+    HELD_KEYS.record(key.code(), down);
+    queue_injection(Injection::Key(key, down));
+}
+
+/// Hand `injection` to the injection thread, starting it on first use.
+/// Keys and pointer input share the thread, so a modifier held by one call
+/// is down for a click sent by the next.
+fn queue_injection(injection: Injection) {
+    let tx = INJECTION_TX.get_or_init(|| {
+        let (tx, rx) = std::sync::mpsc::channel::<Injection>();
+        std::thread::spawn(move || {
+            for injection in rx {
+                match injection {
+                    Injection::Key(key, down) => send_input_sync(key, down),
+                    Injection::Pointer(event) => send_pointer_sync(event),
+                }
+            }
+        });
+        tx
+    });
+    let _ = tx.send(injection);
+}
+
+/// Synchronous pointer motion, button edge, or scroll
+fn send_pointer_sync(event: PointerEvent) {
+    /// One wheel notch
+    const WHEEL_DELTA: i32 = 120;
+
+    let mouse = |flags: MOUSE_EVENT_FLAGS, dx: i32, dy: i32, data: i32| INPUT {
+        r#type: INPUT_MOUSE,
+        Anonymous: INPUT_0 {
+            mi: MOUSEINPUT {
+                dx,
+                dy,
+                // Wheel deltas are signed, carried in an unsigned field
+                mouseData: data as u32,
+                dwFlags: flags,
+                time: 0,
+                dwExtraInfo: INJECTED_MARKER,
+            },
+        },
+    };
+    let inputs: Vec<INPUT> = match event {
+        PointerEvent::Move { dx, dy } => vec![mouse(MOUSEEVENTF_MOVE, dx, dy, 0)],
+        PointerEvent::Button { button, down } => {
+            let flags = match (button, down) {
+                (PointerButton::Left, true) => MOUSEEVENTF_LEFTDOWN,
+                (PointerButton::Left, false) => MOUSEEVENTF_LEFTUP,
+                (PointerButton::Right, true) => MOUSEEVENTF_RIGHTDOWN,
+                (PointerButton::Right, false) => MOUSEEVENTF_RIGHTUP,
+                (PointerButton::Middle, true) => MOUSEEVENTF_MIDDLEDOWN,
+                (PointerButton::Middle, false) => MOUSEEVENTF_MIDDLEUP,
+            };
+            vec![mouse(flags, 0, 0, 0)]
+        }
+        PointerEvent::Scroll { dx, dy } => {
+            let mut inputs = Vec::new();
+            if dy != 0 {
+                inputs.push(mouse(MOUSEEVENTF_WHEEL, 0, 0, dy * WHEEL_DELTA));
+            }
+            if dx != 0 {
+                inputs.push(mouse(MOUSEEVENTF_HWHEEL, 0, 0, dx * WHEEL_DELTA));
+            }
+            inputs
+        }
+    };
+
+    let sent = unsafe { SendInput(&inputs, std::mem::size_of::<INPUT>() as i32) };
+    if sent as usize != inputs.len() {
+        warn!(?event, "SendInput did not send pointer event");
+    } else {
+        trace!(?event, "sent pointer event");
+    }
+}
+
+/// Press or release a key synchronously, by scancode for scancode keys
+fn send_input_sync(key: KeyCode, down: bool) {
+    if let Some(scancode) = key.scancode() {
+        send_scancode_event_sync(scancode, down);
+        return;
+    }
+    let Ok(vk) = u16::try_from(key.code()) else {
+        warn!(?key, "key code out of VK range");
+        return;
+    };
+    // Injection is per-process like the hooks, so it follows the platform running them
+    let mode = HOOK_STATE.get().map(|state| state.injection_mode());
+    if mode == Some(InjectionMode::Scancode) {
+        // Keys without a scancode in the current layout (media keys on some
+        // keyboards) can only be sent as virtual keys
+        let scancode = unsafe { MapVirtualKeyW(vk.into(), MAPVK_VK_TO_VSC_EX) };
+        if let Ok(scancode @ 1..) = u16::try_from(scancode) {
+            send_scancode_event_sync(scancode, down);
+            return;
+        }
+    }
+    send_key_event_sync(vk, down);
+}
+
+/// Synchronous press or release of a hardware scancode
+fn send_scancode_event_sync(scancode: u16, down: bool) {
+    let mut flags = KEYEVENTF_SCANCODE;
+    if scancode >> 8 == 0xE0 {
+        flags |= KEYEVENTF_EXTENDEDKEY;
+    }
+    if !down {
+        flags |= KEYEVENTF_KEYUP;
+    }
+    unsafe {
+        let inputs = [INPUT {
+            r#type: INPUT_KEYBOARD,
+            Anonymous: INPUT_0 {
+                ki: KEYBDINPUT {
+                    wVk: VIRTUAL_KEY(0),
+                    wScan: scancode & 0xFF,
+                    dwFlags: flags,
+                    time: 0,
+                    dwExtraInfo: INJECTED_MARKER,
+                },
+            },
+        }];
+
+        let sent = SendInput(&inputs, std::mem::size_of::<INPUT>() as i32);
+        if sent != 1 {
+            warn!(scancode, down, "SendInput did not send scancode event");
+        } else {
+            trace!(scancode, down, "sent scancode event");
+        }
+    }
+}
+
+/// Synchronous implementation of a single key press or release
+fn send_key_event_sync(vk: u16, down: bool) {
+    unsafe {
+        let inputs = [INPUT {
+            r#type: INPUT_KEYBOARD,
+            Anonymous: INPUT_0 {
+                ki: KEYBDINPUT {
+                    wVk: VIRTUAL_KEY(vk),
+                    wScan: 0,
+                    dwFlags: if down {
+                        KEYBD_EVENT_FLAGS(0)
+                    } else {
+                        KEYEVENTF_KEYUP
+                    },
+                    time: 0,
+                    dwExtraInfo: INJECTED_MARKER,
+                },
+            },
+        }];
+
+        let sent = SendInput(&inputs, std::mem::size_of::<INPUT>() as i32);
+        if sent != 1 {
+            warn!(vk, down, "SendInput did not send raw key event");
+        } else {
+            trace!(vk, down, "sent raw key event");
+        }
+    }
+}
+
+/// Synchronous implementation of key press
+fn send_key_press_sync(vk: u16) {
+    unsafe {
+        let inputs = [
+            // Key down
+            INPUT {
+                r#type: INPUT_KEYBOARD,
+                Anonymous: INPUT_0 {
+                    ki: KEYBDINPUT {
+                        wVk: VIRTUAL_KEY(vk),
+                        wScan: 0,
+                        dwFlags: KEYBD_EVENT_FLAGS(0),
+                        time: 0,
+                        dwExtraInfo: INJECTED_MARKER,
+                    },
+                },
+            },
+            // Key up
+            INPUT {
+                r#type: INPUT_KEYBOARD,
+                Anonymous: INPUT_0 {
+                    ki: KEYBDINPUT {
+                        wVk: VIRTUAL_KEY(vk),
+                        wScan: 0,
+                        dwFlags: KEYEVENTF_KEYUP,
+                        time: 0,
+                        dwExtraInfo: INJECTED_MARKER,
+                    },
+                },
+            },
+        ];
+
+        let sent = SendInput(&inputs, std::mem::size_of::<INPUT>() as i32);
+        if sent != 2 {
+            warn!(vk, sent, "SendInput did not send all events");
+        } else {
+            trace!(vk, "sent synthetic key");
+        }
+    }
+}
@@ -0,0 +1,426 @@
+//! Simultaneous-combo strategy
+//!
+//! Fires an action when every key in a set is pressed within `window_ms` of the
+//! first one (home-row combo style), e.g. J+K together for play/pause while J
+//! and K keep typing normally on their own.
+//!
+//! Combo keys are buffered (blocked) while the window is open:
+//! - All keys arrive in time: the action runs and the keys are swallowed until released
+//! - The window expires, a buffered key is released, or an unrelated key is
+//!   pressed: the buffered presses are replayed in their original order
+//!
+//! The strategy subscribes to every key so unrelated presses can flush the buffer
+//! before they reach applications; it doesn't need to be bound to any key.
+
+use crate::config::Action;
+use crate::key::{InputEvent, InputEventId, KeyCode};
+use crate::platform::EventResponse;
+use crate::strategy::{KeyStrategy, PlatformHandle, StrategyContext};
+use serde_json::json;
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot};
+use tracing::debug;
+
+/// Configuration for combo behavior
+#[derive(Debug, Clone)]
+pub struct ComboConfig {
+    /// Keys that must all be pressed together (at least two)
+    pub keys: Vec<KeyCode>,
+    /// Maximum time between the first and last key press (ms)
+    pub window_ms: u64,
+    /// Action executed when the combo fires
+    pub action: Action,
+}
+
+/// Combo detection state
+#[derive(Debug, Default)]
+enum ComboState {
+    /// No combo keys held
+    #[default]
+    Idle,
+    /// Some combo keys are down and blocked, waiting for the rest.
+    /// `generation` ties the expiry timer to this particular buffer.
+    Buffering {
+        pressed: Vec<KeyCode>,
+        deadline: Instant,
+        generation: u64,
+        cancel_tx: oneshot::Sender<()>,
+    },
+    /// Combo fired; these keys are still physically held and their
+    /// releases (and OS repeats) are swallowed
+    Fired { held: HashSet<KeyCode> },
+}
+
+/// Simultaneous-combo strategy implementation
+pub struct ComboStrategy {
+    config: ComboConfig,
+    state: ComboState,
+    /// Incremented each time a new buffer starts
+    generation: u64,
+    /// Channel to receive expiry notifications from timer tasks.
+    /// The timer replays the buffered keys itself, then reports its generation
+    /// so the strategy can return to Idle.
+    timer_tx: mpsc::UnboundedSender<u64>,
+    timer_rx: mpsc::UnboundedReceiver<u64>,
+}
+
+impl ComboStrategy {
+    /// Create a new combo strategy with the given configuration
+    pub fn new(config: ComboConfig) -> Self {
+        let (timer_tx, timer_rx) = mpsc::unbounded_channel();
+        Self {
+            config,
+            state: ComboState::Idle,
+            generation: 0,
+            timer_tx,
+            timer_rx,
+        }
+    }
+
+    fn is_combo_key(&self, key: KeyCode) -> bool {
+        self.config.keys.contains(&key)
+    }
+
+    /// Process pending expiry notifications
+    ///
+    /// Expired buffers were already replayed by their timer task, so only the
+    /// state needs resetting.
+    fn process_timer_completions(&mut self) {
+        while let Ok(generation) = self.timer_rx.try_recv() {
+            if let ComboState::Buffering {
+                generation: current,
+                ..
+            } = &self.state
+                && *current == generation
+            {
+                debug!("combo: buffering -> idle (window expired)");
+                self.state = ComboState::Idle;
+            }
+        }
+    }
+
+    /// Spawn the expiry timer for the current buffer
+    fn spawn_timer(
+        &self,
+        pressed: Vec<KeyCode>,
+        deadline: Instant,
+        generation: u64,
+        platform_handle: PlatformHandle,
+    ) -> oneshot::Sender<()> {
+        let (cancel_tx, cancel_rx) = oneshot::channel();
+        let timer_tx = self.timer_tx.clone();
+
+        tokio::spawn(async move {
+            tokio::select! {
+                _ = platform_handle.clock().sleep_until(deadline) => {
+                    debug!(?pressed, "combo: window expired, replaying buffered keys");
+                    for key in pressed {
+                        platform_handle.send_raw_key(key, true);
+                    }
+                    let _ = timer_tx.send(generation);
+                }
+                _ = cancel_rx => {}
+            }
+        });
+
+        cancel_tx
+    }
+
+    /// Stop buffering and replay the buffered presses.
+    ///
+    /// Returns false if the timer already expired and replayed them itself.
+    fn flush(&mut self, ctx: &StrategyContext) -> bool {
+        let ComboState::Buffering {
+            pressed, cancel_tx, ..
+        } = std::mem::take(&mut self.state)
+        else {
+            return false;
+        };
+
+        // Cancel failing means the timer already fired and replayed
+        if cancel_tx.send(()).is_err() {
+            return false;
+        }
+
+        debug!(?pressed, "combo: buffering -> idle (flushed)");
+        for key in pressed {
+            ctx.send_raw_key(key, true);
+        }
+        true
+    }
+
+    /// Handle key-down event
+    fn key_down(&mut self, key: KeyCode, time: Instant, ctx: &StrategyContext) -> EventResponse {
+        if !self.is_combo_key(key) {
+            // Unrelated key: anything buffered must reach the OS before it
+            self.flush(ctx);
+            return EventResponse::Passthrough;
+        }
+
+        match std::mem::take(&mut self.state) {
+            ComboState::Idle => {
+                self.generation += 1;
+                // The window opens at the physical press, not when this runs
+                let deadline = time + Duration::from_millis(self.config.window_ms);
+                let cancel_tx =
+                    self.spawn_timer(vec![key], deadline, self.generation, ctx.platform_handle());
+
+                debug!(?key, "combo: idle -> buffering");
+                self.state = ComboState::Buffering {
+                    pressed: vec![key],
+                    deadline,
+                    generation: self.generation,
+                    cancel_tx,
+                };
+                EventResponse::Block
+            }
+            ComboState::Buffering {
+                mut pressed,
+                deadline,
+                generation,
+                cancel_tx,
+            } => {
+                if pressed.contains(&key) {
+                    // OS key repeat while buffering
+                    self.state = ComboState::Buffering {
+                        pressed,
+                        deadline,
+                        generation,
+                        cancel_tx,
+                    };
+                    return EventResponse::Block;
+                }
+
+                // Stop the old timer; if it already fired, the buffer was replayed
+                if cancel_tx.send(()).is_err() {
+                    return self.key_down(key, time, ctx);
+                }
+                pressed.push(key);
+
+                if self.config.keys.iter().all(|k| pressed.contains(k)) {
+                    debug!(?pressed, "combo: buffering -> fired");
+                    ctx.platform_handle().execute(&self.config.action);
+                    self.state = ComboState::Fired {
+                        held: pressed.into_iter().collect(),
+                    };
+                } else {
+                    let cancel_tx = self.spawn_timer(
+                        pressed.clone(),
+                        deadline,
+                        generation,
+                        ctx.platform_handle(),
+                    );
+                    self.state = ComboState::Buffering {
+                        pressed,
+                        deadline,
+                        generation,
+                        cancel_tx,
+                    };
+                }
+                EventResponse::Block
+            }
+            ComboState::Fired { held } => {
+                let response = if held.contains(&key) {
+                    // OS key repeat of a combo key
+                    EventResponse::Block
+                } else {
+                    EventResponse::Passthrough
+                };
+                self.state = ComboState::Fired { held };
+                response
+            }
+        }
+    }
+
+    /// Handle key-up event
+    fn key_up(&mut self, key: KeyCode, ctx: &StrategyContext) -> EventResponse {
+        let buffered = matches!(
+            &self.state,
+            ComboState::Buffering { pressed, .. } if pressed.contains(&key)
+        );
+        if buffered {
+            // Released before the combo completed: replay the presses, then
+            // the release, so the OS sees a normal tap
+            return if self.flush(ctx) {
+                ctx.send_raw_key(key, false);
+                EventResponse::Block
+            } else {
+                // Timer already replayed the press; let the release through
+                EventResponse::Passthrough
+            };
+        }
+
+        let ComboState::Fired { held } = &mut self.state else {
+            return EventResponse::Passthrough;
+        };
+        if !held.remove(&key) {
+            return EventResponse::Passthrough;
+        }
+        if held.is_empty() {
+            debug!("combo: fired -> idle");
+            self.state = ComboState::Idle;
+        }
+        EventResponse::Block
+    }
+}
+
+impl KeyStrategy for ComboStrategy {
+    fn subscriptions(&self) -> HashSet<InputEventId> {
+        HashSet::from([InputEventId::AnyKey])
+    }
+
+    fn debug_state(&self) -> serde_json::Value {
+        let names = |keys: &mut dyn Iterator<Item = &KeyCode>| -> Vec<String> {
+            keys.map(|key| key.display_name()).collect()
+        };
+
+        match &self.state {
+            ComboState::Idle => json!({ "state": "idle" }),
+            ComboState::Buffering {
+                pressed, deadline, ..
+            } => {
+                let ms_left = deadline
+                    .saturating_duration_since(Instant::now())
+                    .as_millis() as u64;
+                json!({
+                    "state": "buffering",
+                    "buffered": names(&mut pressed.iter()),
+                    "window_ms_left": ms_left,
+                })
+            }
+            ComboState::Fired { held } => {
+                json!({ "state": "fired", "held": names(&mut held.iter()) })
+            }
+        }
+    }
+
+    fn reset(&mut self, ctx: &StrategyContext) {
+        debug!("combo: reset");
+        // Buffered presses are still physically down, so hand them to the OS
+        self.flush(ctx);
+        self.state = ComboState::Idle;
+    }
+
+    fn process(&mut self, event: &InputEvent, ctx: &StrategyContext) -> EventResponse {
+        self.process_timer_completions();
+
+        match event {
+            InputEvent::Key(key_event) => {
+                if key_event.down {
+                    self.key_down(key_event.key, key_event.time, ctx)
+                } else {
+                    self.key_up(key_event.key, ctx)
+                }
+            }
+            InputEvent::MouseButton { .. } | InputEvent::Scroll { .. } | InputEvent::Divert(_) => {
+                EventResponse::Passthrough
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key::KeyEvent;
+    use crate::platform::mock::PlatformCall;
+    use crate::platform::{MediaCommand, MockPlatform};
+    use std::sync::Arc;
+
+    const J: KeyCode = KeyCode::new(36);
+    const K: KeyCode = KeyCode::new(37);
+    const X: KeyCode = KeyCode::new(45);
+
+    fn combo() -> ComboStrategy {
+        ComboStrategy::new(ComboConfig {
+            keys: vec![J, K],
+            window_ms: 50,
+            action: Action::MediaPlayPause,
+        })
+    }
+
+    fn key(code: KeyCode, down: bool) -> InputEvent {
+        InputEvent::Key(KeyEvent::new(code, down))
+    }
+
+    fn raw(key: KeyCode, down: bool) -> PlatformCall {
+        PlatformCall::SendRawKey { key, down }
+    }
+
+    #[tokio::test]
+    async fn test_combo_fires_and_swallows_releases() {
+        let mut strategy = combo();
+        let platform = Arc::new(MockPlatform::new());
+        let ctx = StrategyContext::new(PlatformHandle::from_mock(&platform), &Action::Block);
+
+        assert!(strategy.process(&key(J, true), &ctx) == EventResponse::Block);
+        assert!(strategy.process(&key(K, true), &ctx) == EventResponse::Block);
+        platform.assert_media_sent(MediaCommand::PlayPause);
+
+        assert!(strategy.process(&key(J, false), &ctx) == EventResponse::Block);
+        assert!(strategy.process(&key(K, false), &ctx) == EventResponse::Block);
+        assert!(matches!(strategy.state, ComboState::Idle));
+
+        // Expired timer never replays anything
+        tokio::time::sleep(Duration::from_millis(70)).await;
+        platform.assert_call_count(1);
+    }
+
+    #[tokio::test]
+    async fn test_timeout_replays_buffered_key() {
+        let mut strategy = combo();
+        let platform = Arc::new(MockPlatform::new());
+        let ctx = StrategyContext::new(PlatformHandle::from_mock(&platform), &Action::Block);
+
+        strategy.process(&key(J, true), &ctx);
+        tokio::time::sleep(Duration::from_millis(70)).await;
+        assert_eq!(platform.calls(), vec![raw(J, true)]);
+
+        // Release of the replayed key reaches the OS untouched
+        assert!(strategy.process(&key(J, false), &ctx) == EventResponse::Passthrough);
+        assert!(matches!(strategy.state, ComboState::Idle));
+    }
+
+    #[tokio::test]
+    async fn test_early_release_replays_tap() {
+        let mut strategy = combo();
+        let platform = Arc::new(MockPlatform::new());
+        let ctx = StrategyContext::new(PlatformHandle::from_mock(&platform), &Action::Block);
+
+        strategy.process(&key(J, true), &ctx);
+        assert!(strategy.process(&key(J, false), &ctx) == EventResponse::Block);
+        assert_eq!(platform.calls(), vec![raw(J, true), raw(J, false)]);
+
+        tokio::time::sleep(Duration::from_millis(70)).await;
+        platform.assert_call_count(2);
+    }
+
+    #[tokio::test]
+    async fn test_unrelated_key_flushes_buffer_first() {
+        let mut strategy = combo();
+        let platform = Arc::new(MockPlatform::new());
+        let ctx = StrategyContext::new(PlatformHandle::from_mock(&platform), &Action::Block);
+
+        strategy.process(&key(J, true), &ctx);
+        assert!(strategy.process(&key(X, true), &ctx) == EventResponse::Passthrough);
+        assert_eq!(platform.calls(), vec![raw(J, true)]);
+        assert!(matches!(strategy.state, ComboState::Idle));
+    }
+
+    #[tokio::test]
+    async fn test_unrelated_keys_pass_through_when_idle() {
+        let mut strategy = combo();
+        let platform = Arc::new(MockPlatform::new());
+        let ctx = StrategyContext::new(PlatformHandle::from_mock(&platform), &Action::Block);
+
+        assert!(strategy.process(&key(X, true), &ctx) == EventResponse::Passthrough);
+        assert!(strategy.process(&key(X, false), &ctx) == EventResponse::Passthrough);
+        platform.assert_no_calls();
+    }
+
+    #[test]
+    fn test_subscribes_to_all_keys() {
+        assert!(combo().subscriptions().contains(&InputEventId::AnyKey));
+    }
+}
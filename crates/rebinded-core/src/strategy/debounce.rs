@@ -0,0 +1,269 @@
+//! Bounce keys (chatter filter) strategy
+//!
+//! Ignores a press of a key that comes within `chatter_ms` of its last release,
+//! along with that press's release, so a failing switch that registers one
+//! keystroke as several only types once. Legitimate presses and releases
+//! continue down the binding's strategy chain (see [`EventResponse::Continue`]):
+//! after debounce alone, a `block` binding lets the key itself through and any
+//! other action executes as usual.
+
+use crate::key::{InputEvent, KeyCode};
+use crate::platform::EventResponse;
+use crate::strategy::{KeyStrategy, StrategyContext};
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// Configuration for chatter filtering
+#[derive(Debug, Clone)]
+pub struct DebounceConfig {
+    /// Presses this soon after the key's last release are chatter (ms)
+    pub chatter_ms: u64,
+}
+
+/// Bounce keys strategy implementation
+pub struct DebounceStrategy {
+    config: DebounceConfig,
+    /// When each key was last released, chatter included
+    last_release: HashMap<KeyCode, Instant>,
+    /// Keys whose current press was chatter; their release is dropped too
+    chattering: HashSet<KeyCode>,
+}
+
+impl DebounceStrategy {
+    /// Create a new debounce strategy with the given configuration
+    pub fn new(config: DebounceConfig) -> Self {
+        Self {
+            config,
+            last_release: HashMap::new(),
+            chattering: HashSet::new(),
+        }
+    }
+
+    /// Whether a press at `time` comes too soon after the key's last release
+    fn is_chatter(&self, key: KeyCode, time: Instant) -> bool {
+        self.last_release.get(&key).is_some_and(|released| {
+            time.saturating_duration_since(*released)
+                < Duration::from_millis(self.config.chatter_ms)
+        })
+    }
+
+    /// Handle key-down event
+    fn key_down(&mut self, key: KeyCode, time: Instant) -> EventResponse {
+        if self.chattering.contains(&key) {
+            return EventResponse::Block;
+        }
+        if self.is_chatter(key, time) {
+            debug!(%key, "debounce: ignoring chatter press");
+            self.chattering.insert(key);
+            return EventResponse::Block;
+        }
+        EventResponse::Continue
+    }
+
+    /// Handle key-up event
+    fn key_up(&mut self, key: KeyCode, time: Instant) -> EventResponse {
+        // Chatter keeps extending the window until the switch settles
+        self.last_release.insert(key, time);
+        if self.chattering.remove(&key) {
+            return EventResponse::Block;
+        }
+        EventResponse::Continue
+    }
+}
+
+impl KeyStrategy for DebounceStrategy {
+    fn debug_state(&self) -> serde_json::Value {
+        let chattering: Vec<String> = self.chattering.iter().map(KeyCode::display_name).collect();
+        json!({ "chattering": chattering })
+    }
+
+    fn reset(&mut self, _ctx: &StrategyContext) {
+        debug!("debounce: reset");
+        self.chattering.clear();
+    }
+
+    /// Chatter timing belongs to the switch, not the application, so focus
+    /// changes keep it
+    fn on_focus_change(&mut self, _window: &crate::config::WindowInfo, _ctx: &StrategyContext) {}
+
+    fn process(&mut self, event: &InputEvent, _ctx: &StrategyContext) -> EventResponse {
+        match event {
+            InputEvent::Key(key_event) => {
+                if key_event.down {
+                    self.key_down(key_event.key, key_event.time)
+                } else {
+                    self.key_up(key_event.key, key_event.time)
+                }
+            }
+            // Not subscribed to anything else
+            InputEvent::MouseButton { .. } | InputEvent::Scroll { .. } | InputEvent::Divert(_) => {
+                EventResponse::Passthrough
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{RuntimeConfig, WindowInfo, load_from_str};
+    use crate::key::KeyEvent;
+    use crate::platform::MediaCommand;
+    use crate::platform::mock::PlatformCall;
+    use crate::trace::{self, TraceEvent};
+    use std::time::Duration;
+
+    const KEY: KeyCode = KeyCode::new(0x7C);
+
+    /// A press, a bounce 10ms after its release, then a real press later, as
+    /// (milliseconds after start, down) pairs
+    const BOUNCE: [(u64, bool); 6] = [
+        (0, true),
+        (50, false),
+        (60, true),
+        (65, false),
+        (200, true),
+        (260, false),
+    ];
+
+    async fn load(binding: &str) -> RuntimeConfig {
+        let toml = format!(
+            r#"
+            [strategies.chatter]
+            type = "debounce"
+            chatter_ms = 30
+
+            [strategies.gate]
+            type = "gated_hold"
+            initial_hold_ms = 20
+            repeat_window_ms = 2000
+
+            [bindings.0x7C]
+            {binding}
+            "#
+        );
+        load_from_str("test.toml", toml).await.unwrap().1
+    }
+
+    /// Run `events` through the engine, returning each event's response and
+    /// the platform calls it led to
+    async fn replay(
+        runtime: &RuntimeConfig,
+        events: &[(u64, bool)],
+    ) -> Vec<(EventResponse, Vec<PlatformCall>)> {
+        let origin = Instant::now();
+        let trace = events
+            .iter()
+            .map(|(ms, down)| {
+                let at = Duration::from_millis(*ms);
+                TraceEvent {
+                    at,
+                    event: InputEvent::Key(KeyEvent::at(KEY, *down, origin + at)),
+                    window: WindowInfo::default(),
+                    response: EventResponse::Passthrough,
+                }
+            })
+            .collect();
+        trace::replay(runtime, trace, origin)
+            .await
+            .into_iter()
+            .map(|replayed| (replayed.response, replayed.calls))
+            .collect()
+    }
+
+    fn responses(replayed: &[(EventResponse, Vec<PlatformCall>)]) -> Vec<EventResponse> {
+        replayed.iter().map(|(response, _)| *response).collect()
+    }
+
+    fn media_sent(replayed: &[(EventResponse, Vec<PlatformCall>)]) -> usize {
+        replayed
+            .iter()
+            .flat_map(|(_, calls)| calls)
+            .filter(|call| **call == PlatformCall::SendMedia(MediaCommand::Next))
+            .count()
+    }
+
+    #[tokio::test]
+    async fn test_chatter_passes_real_presses_through() {
+        let runtime = load(
+            r#"action = "block"
+            strategy = "chatter""#,
+        )
+        .await;
+
+        let replayed = replay(&runtime, &BOUNCE).await;
+        use EventResponse::{Block, Passthrough};
+        assert_eq!(
+            responses(&replayed),
+            [
+                Passthrough,
+                Passthrough,
+                Block,
+                Block,
+                Passthrough,
+                Passthrough
+            ]
+        );
+        assert!(replayed.iter().all(|(_, calls)| calls.is_empty()));
+    }
+
+    #[tokio::test]
+    async fn test_chatter_extends_window() {
+        let runtime = load(
+            r#"action = "media_next"
+            strategy = "chatter""#,
+        )
+        .await;
+
+        // Each bounce lands within 30ms of the previous (bounced) release
+        let replayed = replay(
+            &runtime,
+            &[
+                (0, true),
+                (40, false),
+                (60, true),
+                (62, false),
+                (85, true),
+                (90, false),
+            ],
+        )
+        .await;
+        assert_eq!(media_sent(&replayed), 1);
+        assert!(
+            responses(&replayed)
+                .iter()
+                .all(|response| *response == EventResponse::Block)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_chained_before_gated_hold() {
+        let runtime = load(
+            r#"action = "media_next"
+            strategy = ["chatter", "gate"]"#,
+        )
+        .await;
+
+        // The first press passes the hold gate and opens the repeat window.
+        // Alone, gated_hold would fire again on the bounce; chained after
+        // debounce it never sees it, and only the real press fires.
+        let replayed = replay(&runtime, &BOUNCE).await;
+        assert_eq!(media_sent(&replayed[..2]), 1);
+        assert_eq!(media_sent(&replayed[2..4]), 0);
+        assert_eq!(media_sent(&replayed[4..]), 1);
+        assert!(
+            responses(&replayed)
+                .iter()
+                .all(|response| *response == EventResponse::Block)
+        );
+
+        let runtime = load(
+            r#"action = "media_next"
+            strategy = "gate""#,
+        )
+        .await;
+        assert_eq!(media_sent(&replay(&runtime, &BOUNCE).await), 3);
+    }
+}
@@ -9,13 +9,14 @@
 //! allowing intentional rapid activation (e.g., skipping multiple tracks).
 //!
 //! Keys sharing the same `GatedHoldStrategy` instance share gate state — if one key
-//! opens the gate, sibling keys can activate immediately.
+//! opens the gate, sibling keys can activate immediately. With `shared_gate`
+//! disabled, each key instead tracks its own repeat window.
 
 use crate::config::Action;
-use crate::key::{InputEvent, InputEventId};
+use crate::key::{InputEvent, InputEventId, KeyCode, MouseButton};
 use crate::platform::EventResponse;
-use crate::strategy::{KeyStrategy, PlatformHandle, StrategyContext};
-use async_trait::async_trait;
+use crate::strategy::{KeyStrategy, PlatformHandle, StrategyContext, remaining_ms};
+use serde_json::json;
 use std::collections::{HashMap, HashSet};
 use std::time::{Duration, Instant};
 use tokio::sync::{mpsc, oneshot};
@@ -32,6 +33,9 @@ pub struct GatedHoldConfig {
     /// When a divert event occurs while any key is in `Holding` or `Active` state,
     /// the key transitions to `Diverted` and the mapped action is executed.
     pub diverts: HashMap<InputEventId, Action>,
+    /// Whether keys bound to this instance share one gate. When false, each key
+    /// must pass the hold gate on its own and only reopens its own repeat window.
+    pub shared_gate: bool,
 }
 
 /// Tracks state for a single key
@@ -54,21 +58,26 @@ enum KeyState {
 /// Gated hold strategy implementation
 ///
 /// Multiple keys can share an instance to share gate state. When any key in the
-/// group activates, the gate opens for all keys in that group.
+/// group activates, the gate opens for all keys in that group (unless
+/// `shared_gate` is disabled).
 pub struct GatedHoldStrategy {
     config: GatedHoldConfig,
     /// Per-key state
-    key_states: HashMap<String, KeyState>,
-    /// When a key was last released (for repeat window)
+    key_states: HashMap<KeyCode, KeyState>,
+    /// When a key was last released (for repeat window, shared gate only)
     last_release: Option<Instant>,
+    /// When each key was last released (for repeat window, per-key gates only)
+    key_releases: HashMap<KeyCode, Instant>,
+    /// Mouse buttons whose press was diverted; their release is blocked too
+    diverted_buttons: HashSet<MouseButton>,
     /// Cached platform handle for executing divert actions
     /// Set on first key event, used for scroll events
     platform_handle: Option<PlatformHandle>,
     /// Channel to receive timer completion notifications
-    /// When a hold timer fires, it sends the key name here so the strategy
+    /// When a hold timer fires, it sends the key code here so the strategy
     /// can transition the key from Holding to Active state
-    timer_tx: mpsc::UnboundedSender<String>,
-    timer_rx: mpsc::UnboundedReceiver<String>,
+    timer_tx: mpsc::UnboundedSender<KeyCode>,
+    timer_rx: mpsc::UnboundedReceiver<KeyCode>,
 }
 
 impl GatedHoldStrategy {
@@ -79,6 +88,8 @@ impl GatedHoldStrategy {
             config,
             key_states: HashMap::new(),
             last_release: None,
+            key_releases: HashMap::new(),
+            diverted_buttons: HashSet::new(),
             platform_handle: None,
             timer_tx,
             timer_rx,
@@ -98,29 +109,33 @@ impl GatedHoldStrategy {
     /// Transition all held keys to Diverted state, cancelling timers and recording
     /// last_release as appropriate.
     fn divert_all_held_keys(&mut self) {
-        let keys_to_divert: Vec<String> = self
+        let now = self
+            .platform_handle
+            .as_ref()
+            .map_or_else(Instant::now, PlatformHandle::now);
+        let keys_to_divert: Vec<KeyCode> = self
             .key_states
             .iter()
             .filter(|(_, v)| matches!(v, KeyState::Holding { .. } | KeyState::Active))
-            .map(|(k, _)| k.clone())
+            .map(|(k, _)| *k)
             .collect();
 
-        for key_name in keys_to_divert {
-            if let Some(state) = self.key_states.remove(&key_name) {
+        for key in keys_to_divert {
+            if let Some(state) = self.key_states.remove(&key) {
                 match state {
                     KeyState::Holding { cancel_tx } => {
-                        debug!(key = %key_name, "gated_hold: holding -> diverted (scroll)");
+                        debug!(%key, "gated_hold: holding -> diverted (scroll)");
                         // Cancel the pending timer
                         let _ = cancel_tx.send(());
                     }
                     KeyState::Active => {
-                        debug!(key = %key_name, "gated_hold: active -> diverted (scroll)");
+                        debug!(%key, "gated_hold: active -> diverted (scroll)");
                         // Record release time so repeat window is preserved
-                        self.last_release = Some(Instant::now());
+                        self.record_release(key, now);
                     }
                     _ => {}
                 }
-                self.key_states.insert(key_name, KeyState::Diverted);
+                self.key_states.insert(key, KeyState::Diverted);
             }
         }
     }
@@ -150,12 +165,38 @@ impl GatedHoldStrategy {
         EventResponse::Block
     }
 
-    /// Check if the gate is currently open
+    /// Record a release at `time` for the repeat window of the gate `key` belongs to
+    fn record_release(&mut self, key: KeyCode, time: Instant) {
+        if self.config.shared_gate {
+            self.last_release = Some(time);
+        } else {
+            self.key_releases.insert(key, time);
+        }
+    }
+
+    /// Check if the gate is open for `key` at `now`
+    ///
+    /// With per-key gates, only `key`'s own last release counts. Otherwise
+    /// this is the shared gate (see [`Self::is_shared_gate_open`]).
+    fn is_gate_open(&self, key: KeyCode, now: Instant) -> bool {
+        if !self.config.shared_gate {
+            let repeat_window = Duration::from_millis(self.config.repeat_window_ms);
+            return self
+                .key_releases
+                .get(&key)
+                .is_some_and(|last| now.saturating_duration_since(*last) < repeat_window);
+        }
+        self.is_shared_gate_open(now)
+    }
+
+    /// Check if the shared gate is open at `now`
     ///
-    /// Gate is open if:
+    /// The gate is open if:
     /// - Any key is currently Active, OR
     /// - We're within repeat_window_ms of the last release
-    fn is_gate_open(&self) -> bool {
+    fn is_shared_gate_open(&self, now: Instant) -> bool {
+        let repeat_window = Duration::from_millis(self.config.repeat_window_ms);
+
         // Check if any key is active
         let any_active = self
             .key_states
@@ -166,11 +207,10 @@ impl GatedHoldStrategy {
         }
 
         // Check if we're in the repeat window
-        if let Some(last) = self.last_release {
-            let repeat_window = Duration::from_millis(self.config.repeat_window_ms);
-            if last.elapsed() < repeat_window {
-                return true;
-            }
+        if let Some(last) = self.last_release
+            && now.saturating_duration_since(last) < repeat_window
+        {
+            return true;
         }
 
         false
@@ -178,57 +218,56 @@ impl GatedHoldStrategy {
 
     /// Process pending timer completion notifications
     ///
-    /// When hold timers fire, they send the key name through the timer channel.
+    /// When hold timers fire, they send the key code through the timer channel.
     /// This method drains the channel and transitions keys from Holding to Active.
     /// Should be called at the start of `process()` to ensure state is up-to-date.
     fn process_timer_completions(&mut self) {
-        while let Ok(key_name) = self.timer_rx.try_recv() {
+        while let Ok(key) = self.timer_rx.try_recv() {
             // Check if key is still in Holding state
             // (it might have been released already, in which case we ignore the message)
-            if let Some(KeyState::Holding { .. }) = self.key_states.get(&key_name) {
-                debug!(key = %key_name, "gated_hold: holding -> active (timer completion)");
-                self.key_states.insert(key_name, KeyState::Active);
+            if let Some(KeyState::Holding { .. }) = self.key_states.get(&key) {
+                debug!(%key, "gated_hold: holding -> active (timer completion)");
+                self.key_states.insert(key, KeyState::Active);
             }
         }
     }
 
     /// Handle key-down event
-    fn key_down(&mut self, key_name: &str, ctx: &StrategyContext) -> EventResponse {
-        let gate_open = self.is_gate_open();
+    fn key_down(&mut self, key: KeyCode, time: Instant, ctx: &StrategyContext) -> EventResponse {
+        let gate_open = self.is_gate_open(key, time);
 
         // Get current state, defaulting to Idle
-        let current_state = self.key_states.remove(key_name).unwrap_or(KeyState::Idle);
+        let current_state = self.key_states.remove(&key).unwrap_or(KeyState::Idle);
 
         match current_state {
             KeyState::Idle => {
                 if gate_open {
-                    debug!(key = key_name, "gated_hold: idle -> active (gate open)");
-                    self.key_states
-                        .insert(key_name.to_string(), KeyState::Active);
+                    debug!(%key, "gated_hold: idle -> active (gate open)");
+                    self.key_states.insert(key, KeyState::Active);
                     ctx.execute();
                     EventResponse::Block
                 } else {
-                    debug!(key = key_name, "gated_hold: idle -> holding");
+                    debug!(%key, "gated_hold: idle -> holding");
 
                     // Spawn timer for delayed activation
                     let (cancel_tx, cancel_rx) = oneshot::channel();
-                    let hold_duration = Duration::from_millis(self.config.initial_hold_ms);
+                    // Counted from the physical press, not from when this runs
+                    let hold_deadline = time + Duration::from_millis(self.config.initial_hold_ms);
 
                     // Clone what we need for the spawned task
                     let action = ctx.action().clone();
                     let platform_handle = ctx.platform_handle();
                     let timer_tx = self.timer_tx.clone();
-                    let key_name_owned = key_name.to_string();
 
                     tokio::spawn(async move {
                         tokio::select! {
-                            _ = tokio::time::sleep(hold_duration) => {
+                            _ = platform_handle.clock().sleep_until(hold_deadline) => {
                                 // Hold threshold reached — execute action
                                 platform_handle.execute(&action);
                                 debug!("gated_hold: hold timer fired, action executed");
 
                                 // Notify strategy that timer completed so it can transition to Active
-                                let _ = timer_tx.send(key_name_owned);
+                                let _ = timer_tx.send(key);
                             }
                             _ = cancel_rx => {
                                 // Cancelled (key released early)
@@ -237,52 +276,48 @@ impl GatedHoldStrategy {
                         }
                     });
 
-                    self.key_states
-                        .insert(key_name.to_string(), KeyState::Holding { cancel_tx });
+                    self.key_states.insert(key, KeyState::Holding { cancel_tx });
                     EventResponse::Block
                 }
             }
             KeyState::Holding { cancel_tx } => {
                 // Still holding, put the state back
                 // This handles OS key repeat events while holding
-                self.key_states
-                    .insert(key_name.to_string(), KeyState::Holding { cancel_tx });
+                self.key_states.insert(key, KeyState::Holding { cancel_tx });
                 EventResponse::Block
             }
             KeyState::Active => {
                 // Already active, suppress repeated key-down events
-                self.key_states
-                    .insert(key_name.to_string(), KeyState::Active);
+                self.key_states.insert(key, KeyState::Active);
                 EventResponse::Block
             }
             KeyState::Diverted => {
                 // Already diverted, suppress repeated key-down events
-                self.key_states
-                    .insert(key_name.to_string(), KeyState::Diverted);
+                self.key_states.insert(key, KeyState::Diverted);
                 EventResponse::Block
             }
         }
     }
 
     /// Handle key-up event
-    pub(crate) fn key_up(&mut self, key_name: &str) -> EventResponse {
-        let current_state = self.key_states.remove(key_name).unwrap_or(KeyState::Idle);
+    pub(crate) fn key_up(&mut self, key: KeyCode, time: Instant) -> EventResponse {
+        let current_state = self.key_states.remove(&key).unwrap_or(KeyState::Idle);
 
         match current_state {
             KeyState::Holding { cancel_tx } => {
-                debug!(key = key_name, "gated_hold: holding -> idle (cancelled)");
+                debug!(%key, "gated_hold: holding -> idle (cancelled)");
                 // Cancel the pending timer
                 let _ = cancel_tx.send(());
                 // Don't reinsert - absence from map means Idle
             }
             KeyState::Active => {
-                debug!(key = key_name, "gated_hold: active -> idle");
+                debug!(%key, "gated_hold: active -> idle");
                 // Record release time for repeat window
-                self.last_release = Some(Instant::now());
+                self.record_release(key, time);
                 // Don't reinsert - absence from map means Idle
             }
             KeyState::Diverted => {
-                debug!(key = key_name, "gated_hold: diverted -> idle");
+                debug!(%key, "gated_hold: diverted -> idle");
                 // Don't record last_release here - it was already recorded when we
                 // transitioned to Diverted (if coming from Active)
                 // Don't reinsert - absence from map means Idle
@@ -297,14 +332,61 @@ impl GatedHoldStrategy {
     }
 }
 
-#[async_trait]
 impl KeyStrategy for GatedHoldStrategy {
+    fn debug_state(&self) -> serde_json::Value {
+        let repeat_window = Duration::from_millis(self.config.repeat_window_ms);
+        let window_ends = |last: &Instant| remaining_ms(*last + repeat_window);
+
+        let keys: serde_json::Map<_, _> = self
+            .key_states
+            .iter()
+            .map(|(name, state)| {
+                let state = match state {
+                    KeyState::Idle => "idle",
+                    KeyState::Holding { .. } => "holding",
+                    KeyState::Active => "active",
+                    KeyState::Diverted => "diverted",
+                };
+                (name.to_string(), state.into())
+            })
+            .collect();
+
+        let mut state = json!({ "shared_gate": self.config.shared_gate, "keys": keys });
+        if self.config.shared_gate {
+            state["gate_open"] = self.is_shared_gate_open(Instant::now()).into();
+            if let Some(last) = &self.last_release {
+                state["repeat_window_ms_left"] = window_ends(last).into();
+            }
+        } else {
+            let windows: serde_json::Map<_, _> = self
+                .key_releases
+                .iter()
+                .map(|(key, last)| (key.to_string(), window_ends(last).into()))
+                .collect();
+            state["repeat_window_ms_left"] = windows.into();
+        }
+        state
+    }
+
+    fn reset(&mut self, _ctx: &StrategyContext) {
+        debug!("gated_hold: reset");
+        for (_, state) in self.key_states.drain() {
+            if let KeyState::Holding { cancel_tx } = state {
+                let _ = cancel_tx.send(());
+            }
+        }
+        self.last_release = None;
+        self.key_releases.clear();
+        // Timers that already fired refer to keys that are now idle
+        while self.timer_rx.try_recv().is_ok() {}
+    }
+
     fn subscriptions(&self) -> HashSet<InputEventId> {
         // Subscribe to all events that have diverts configured
         self.config.diverts.keys().cloned().collect()
     }
 
-    async fn process(&mut self, event: &InputEvent, ctx: &StrategyContext) -> EventResponse {
+    fn process(&mut self, event: &InputEvent, ctx: &StrategyContext) -> EventResponse {
         // Process any pending timer completions first
         self.process_timer_completions();
 
@@ -315,17 +397,34 @@ impl KeyStrategy for GatedHoldStrategy {
 
         match event {
             InputEvent::Key(key_event) => {
-                let key_name = key_event.key.to_string();
                 if key_event.down {
-                    self.key_down(&key_name, ctx)
+                    self.key_down(key_event.key, key_event.time, ctx)
+                } else {
+                    self.key_up(key_event.key, key_event.time)
+                }
+            }
+            InputEvent::MouseButton { button, down: true } => {
+                let response = self.handle_divert(&InputEventId::MouseButton(*button));
+                if response == EventResponse::Block {
+                    self.diverted_buttons.insert(*button);
+                }
+                response
+            }
+            InputEvent::MouseButton {
+                button,
+                down: false,
+            } => {
+                if self.diverted_buttons.remove(button) {
+                    EventResponse::Block
                 } else {
-                    self.key_up(&key_name)
+                    EventResponse::Passthrough
                 }
             }
             InputEvent::Scroll { up } => {
                 let event_id = InputEventId::Scroll { up: *up };
                 self.handle_divert(&event_id)
             }
+            InputEvent::Divert(_) => EventResponse::Passthrough,
         }
     }
 }
@@ -334,34 +433,38 @@ impl KeyStrategy for GatedHoldStrategy {
 mod tests {
     use super::*;
 
+    const KEY1: KeyCode = KeyCode::new(0x1);
+    const KEY2: KeyCode = KeyCode::new(0x2);
+    const KEY3: KeyCode = KeyCode::new(0x3);
+    const KEY4: KeyCode = KeyCode::new(0x4);
+
     fn test_config() -> GatedHoldConfig {
         GatedHoldConfig {
             initial_hold_ms: 50,
             repeat_window_ms: 200,
             diverts: HashMap::new(),
+            shared_gate: true,
         }
     }
 
     #[test]
     fn test_gate_closed_initially() {
         let strategy = GatedHoldStrategy::new(test_config());
-        assert!(!strategy.is_gate_open());
+        assert!(!strategy.is_gate_open(KEY1, Instant::now()));
     }
 
     #[test]
     fn test_gate_open_with_active_key() {
         let mut strategy = GatedHoldStrategy::new(test_config());
-        strategy
-            .key_states
-            .insert("f15".to_string(), KeyState::Active);
-        assert!(strategy.is_gate_open());
+        strategy.key_states.insert(KEY1, KeyState::Active);
+        assert!(strategy.is_gate_open(KEY1, Instant::now()));
     }
 
     #[test]
     fn test_gate_open_in_repeat_window() {
         let mut strategy = GatedHoldStrategy::new(test_config());
         strategy.last_release = Some(Instant::now());
-        assert!(strategy.is_gate_open());
+        assert!(strategy.is_gate_open(KEY1, Instant::now()));
     }
 
     #[test]
@@ -370,9 +473,10 @@ mod tests {
             initial_hold_ms: 50,
             repeat_window_ms: 10, // Short window for testing
             diverts: HashMap::new(),
+            shared_gate: true,
         });
         strategy.last_release = Some(Instant::now() - Duration::from_millis(20));
-        assert!(!strategy.is_gate_open());
+        assert!(!strategy.is_gate_open(KEY1, Instant::now()));
     }
 
     #[test]
@@ -380,16 +484,14 @@ mod tests {
         let mut strategy = GatedHoldStrategy::new(test_config());
 
         // Set up key in Active state
-        strategy
-            .key_states
-            .insert("f15".to_string(), KeyState::Active);
+        strategy.key_states.insert(KEY1, KeyState::Active);
 
         // Call the actual key_up method
-        let response = strategy.key_up("f15");
+        let response = strategy.key_up(KEY1, Instant::now());
 
         assert!(matches!(response, EventResponse::Block));
         assert!(
-            !strategy.key_states.contains_key("f15"),
+            !strategy.key_states.contains_key(&KEY1),
             "key_states should not contain released key"
         );
     }
@@ -399,11 +501,9 @@ mod tests {
         let mut strategy = GatedHoldStrategy::new(test_config());
 
         // Simulate multiple keys going through Active -> release cycle
-        for key in ["f15", "f16", "f17", "f18"] {
-            strategy
-                .key_states
-                .insert(key.to_string(), KeyState::Active);
-            strategy.key_up(key);
+        for key in [KEY1, KEY2, KEY3, KEY4] {
+            strategy.key_states.insert(key, KeyState::Active);
+            strategy.key_up(key, Instant::now());
         }
 
         // After all keys are released, map should be empty
@@ -432,6 +532,7 @@ mod tests {
             initial_hold_ms: 50,
             repeat_window_ms: 200,
             diverts,
+            shared_gate: true,
         }
     }
 
@@ -444,7 +545,7 @@ mod tests {
         let (cancel_tx, _cancel_rx) = oneshot::channel();
         strategy
             .key_states
-            .insert("f15".to_string(), KeyState::Holding { cancel_tx });
+            .insert(KEY1, KeyState::Holding { cancel_tx });
 
         assert!(strategy.any_key_held(), "should detect Holding state");
     }
@@ -452,9 +553,7 @@ mod tests {
     #[test]
     fn test_any_key_held_detects_active_state() {
         let mut strategy = GatedHoldStrategy::new(config_with_diverts());
-        strategy
-            .key_states
-            .insert("f15".to_string(), KeyState::Active);
+        strategy.key_states.insert(KEY1, KeyState::Active);
 
         assert!(strategy.any_key_held(), "should detect Active state");
     }
@@ -462,9 +561,7 @@ mod tests {
     #[test]
     fn test_any_key_held_detects_diverted_state() {
         let mut strategy = GatedHoldStrategy::new(config_with_diverts());
-        strategy
-            .key_states
-            .insert("f15".to_string(), KeyState::Diverted);
+        strategy.key_states.insert(KEY1, KeyState::Diverted);
 
         assert!(strategy.any_key_held(), "should detect Diverted state");
     }
@@ -490,7 +587,7 @@ mod tests {
         let (cancel_tx, _cancel_rx) = oneshot::channel();
         strategy
             .key_states
-            .insert("f15".to_string(), KeyState::Holding { cancel_tx });
+            .insert(KEY1, KeyState::Holding { cancel_tx });
 
         let event_id = InputEventId::Scroll { up: true };
         let response = strategy.handle_divert(&event_id);
@@ -500,7 +597,7 @@ mod tests {
             "scroll during Holding should block"
         );
         assert!(
-            matches!(strategy.key_states.get("f15"), Some(KeyState::Diverted)),
+            matches!(strategy.key_states.get(&KEY1), Some(KeyState::Diverted)),
             "key should transition to Diverted state"
         );
         assert!(
@@ -514,9 +611,7 @@ mod tests {
         let mut strategy = GatedHoldStrategy::new(config_with_diverts());
 
         // Set up key in Active state
-        strategy
-            .key_states
-            .insert("f15".to_string(), KeyState::Active);
+        strategy.key_states.insert(KEY1, KeyState::Active);
         assert!(
             strategy.last_release.is_none(),
             "precondition: no last_release"
@@ -530,7 +625,7 @@ mod tests {
             "scroll during Active should block"
         );
         assert!(
-            matches!(strategy.key_states.get("f15"), Some(KeyState::Diverted)),
+            matches!(strategy.key_states.get(&KEY1), Some(KeyState::Diverted)),
             "key should transition to Diverted state"
         );
         assert!(
@@ -544,9 +639,7 @@ mod tests {
         let mut strategy = GatedHoldStrategy::new(config_with_diverts());
 
         // Set up key in Diverted state
-        strategy
-            .key_states
-            .insert("f15".to_string(), KeyState::Diverted);
+        strategy.key_states.insert(KEY1, KeyState::Diverted);
 
         let event_id = InputEventId::Scroll { up: true };
         let response = strategy.handle_divert(&event_id);
@@ -556,7 +649,7 @@ mod tests {
             "scroll during Diverted should block"
         );
         assert!(
-            matches!(strategy.key_states.get("f15"), Some(KeyState::Diverted)),
+            matches!(strategy.key_states.get(&KEY1), Some(KeyState::Diverted)),
             "key should remain in Diverted state"
         );
     }
@@ -566,18 +659,16 @@ mod tests {
         let mut strategy = GatedHoldStrategy::new(config_with_diverts());
 
         // Set up key in Diverted state
-        strategy
-            .key_states
-            .insert("f15".to_string(), KeyState::Diverted);
+        strategy.key_states.insert(KEY1, KeyState::Diverted);
 
-        let response = strategy.key_up("f15");
+        let response = strategy.key_up(KEY1, Instant::now());
 
         assert!(
             matches!(response, EventResponse::Block),
             "key up should block"
         );
         assert!(
-            !strategy.key_states.contains_key("f15"),
+            !strategy.key_states.contains_key(&KEY1),
             "key should be removed from map (Idle)"
         );
     }
@@ -590,22 +681,20 @@ mod tests {
         let (cancel_tx, _cancel_rx) = oneshot::channel();
         strategy
             .key_states
-            .insert("f15".to_string(), KeyState::Holding { cancel_tx });
-        strategy
-            .key_states
-            .insert("f16".to_string(), KeyState::Active);
+            .insert(KEY1, KeyState::Holding { cancel_tx });
+        strategy.key_states.insert(KEY2, KeyState::Active);
 
         let event_id = InputEventId::Scroll { up: true };
         let response = strategy.handle_divert(&event_id);
 
         assert!(matches!(response, EventResponse::Block), "should block");
         assert!(
-            matches!(strategy.key_states.get("f15"), Some(KeyState::Diverted)),
-            "f15 should transition to Diverted"
+            matches!(strategy.key_states.get(&KEY1), Some(KeyState::Diverted)),
+            "key1 should transition to Diverted"
         );
         assert!(
-            matches!(strategy.key_states.get("f16"), Some(KeyState::Diverted)),
-            "f16 should transition to Diverted"
+            matches!(strategy.key_states.get(&KEY2), Some(KeyState::Diverted)),
+            "key2 should transition to Diverted"
         );
     }
 
@@ -615,21 +704,20 @@ mod tests {
             initial_hold_ms: 50,
             repeat_window_ms: 500, // Long window
             diverts: config_with_diverts().diverts,
+            shared_gate: true,
         });
 
         // Set up key in Active state and divert
-        strategy
-            .key_states
-            .insert("f15".to_string(), KeyState::Active);
+        strategy.key_states.insert(KEY1, KeyState::Active);
         let event_id = InputEventId::Scroll { up: true };
         strategy.handle_divert(&event_id);
 
         // Release the key
-        strategy.key_up("f15");
+        strategy.key_up(KEY1, Instant::now());
 
         // Gate should still be open (we're in repeat window)
         assert!(
-            strategy.is_gate_open(),
+            strategy.is_gate_open(KEY1, Instant::now()),
             "gate should be open after divert from Active"
         );
     }
@@ -666,9 +754,7 @@ mod tests {
         let mut strategy = GatedHoldStrategy::new(config_with_diverts());
 
         // Set up key in Active state
-        strategy
-            .key_states
-            .insert("f15".to_string(), KeyState::Active);
+        strategy.key_states.insert(KEY1, KeyState::Active);
 
         // Try to divert with an event that's not in the diverts map
         let unknown_event = InputEventId::Key(crate::key::KeyCode::new(0x99));
@@ -679,7 +765,7 @@ mod tests {
             "unrecognized divert event should pass through"
         );
         assert!(
-            matches!(strategy.key_states.get("f15"), Some(KeyState::Active)),
+            matches!(strategy.key_states.get(&KEY1), Some(KeyState::Active)),
             "key should remain in Active state"
         );
     }
@@ -691,7 +777,7 @@ mod tests {
     #[tokio::test]
     async fn test_timer_completion_transitions_to_active() {
         use crate::config::Action;
-        use crate::key::{KeyCode, KeyEvent};
+        use crate::key::KeyEvent;
         use crate::platform::MockPlatform;
         use crate::strategy::{PlatformHandle, StrategyContext};
         use std::sync::Arc;
@@ -700,23 +786,24 @@ mod tests {
             initial_hold_ms: 50,
             repeat_window_ms: 200,
             diverts: HashMap::new(),
+            shared_gate: true,
         };
         let mut strategy = GatedHoldStrategy::new(config);
 
         // Create mock platform and context
         let platform = Arc::new(MockPlatform::new());
-        let platform_handle = unsafe { PlatformHandle::from_mock(&platform) };
+        let platform_handle = PlatformHandle::from_mock(&platform);
         let action = Action::MediaNext;
         let ctx = StrategyContext::new(platform_handle, &action);
 
         // Press key
-        let key_event = InputEvent::Key(KeyEvent::new(KeyCode::new(0x1), true));
-        let response = strategy.process(&key_event, &ctx).await;
+        let key_event = InputEvent::Key(KeyEvent::new(KEY1, true));
+        let response = strategy.process(&key_event, &ctx);
         assert!(matches!(response, EventResponse::Block));
 
         // Key should be in Holding state
         assert!(matches!(
-            strategy.key_states.get("KEY_ESC"),
+            strategy.key_states.get(&KEY1),
             Some(KeyState::Holding { .. })
         ));
 
@@ -729,13 +816,13 @@ mod tests {
 
         // Key should now be in Active state
         assert!(
-            matches!(strategy.key_states.get("KEY_ESC"), Some(KeyState::Active)),
+            matches!(strategy.key_states.get(&KEY1), Some(KeyState::Active)),
             "key should transition to Active after timer fires"
         );
 
         // Release key
-        let key_up = InputEvent::Key(KeyEvent::new(KeyCode::new(0x1), false));
-        strategy.process(&key_up, &ctx).await;
+        let key_up = InputEvent::Key(KeyEvent::new(KEY1, false));
+        strategy.process(&key_up, &ctx);
 
         // last_release should be recorded
         assert!(
@@ -751,7 +838,7 @@ mod tests {
     #[tokio::test]
     async fn test_repeat_window_after_timer_activation() {
         use crate::config::Action;
-        use crate::key::{KeyCode, KeyEvent};
+        use crate::key::KeyEvent;
         use crate::platform::MockPlatform;
         use crate::strategy::{PlatformHandle, StrategyContext};
         use std::sync::Arc;
@@ -760,39 +847,40 @@ mod tests {
             initial_hold_ms: 50,
             repeat_window_ms: 500, // Long window for testing
             diverts: HashMap::new(),
+            shared_gate: true,
         };
         let mut strategy = GatedHoldStrategy::new(config);
 
         let platform = Arc::new(MockPlatform::new());
-        let platform_handle = unsafe { PlatformHandle::from_mock(&platform) };
+        let platform_handle = PlatformHandle::from_mock(&platform);
         let action = Action::MediaNext;
         let ctx = StrategyContext::new(platform_handle, &action);
 
         // First press: hold until timer fires
-        let key_down = InputEvent::Key(KeyEvent::new(KeyCode::new(0x1), true));
-        strategy.process(&key_down, &ctx).await;
+        let key_down = InputEvent::Key(KeyEvent::new(KEY1, true));
+        strategy.process(&key_down, &ctx);
 
         // Wait for timer
         tokio::time::sleep(Duration::from_millis(60)).await;
         strategy.process_timer_completions();
 
         // Release
-        let key_up = InputEvent::Key(KeyEvent::new(KeyCode::new(0x1), false));
-        strategy.process(&key_up, &ctx).await;
+        let key_up = InputEvent::Key(KeyEvent::new(KEY1, false));
+        strategy.process(&key_up, &ctx);
 
         // Gate should be open
         assert!(
-            strategy.is_gate_open(),
+            strategy.is_gate_open(KEY1, Instant::now()),
             "gate should be open after release within repeat window"
         );
 
         // Quick second press (within repeat window)
         tokio::time::sleep(Duration::from_millis(50)).await;
-        let response = strategy.process(&key_down, &ctx).await;
+        let response = strategy.process(&key_down, &ctx);
 
         // Should activate immediately without waiting for hold timer
         assert!(
-            matches!(strategy.key_states.get("KEY_ESC"), Some(KeyState::Active)),
+            matches!(strategy.key_states.get(&KEY1), Some(KeyState::Active)),
             "second press should activate immediately (gate open)"
         );
         assert!(matches!(response, EventResponse::Block));
@@ -801,7 +889,7 @@ mod tests {
     #[tokio::test]
     async fn test_timer_completion_after_early_release() {
         use crate::config::Action;
-        use crate::key::{KeyCode, KeyEvent};
+        use crate::key::KeyEvent;
         use crate::platform::MockPlatform;
         use crate::strategy::{PlatformHandle, StrategyContext};
         use std::sync::Arc;
@@ -810,26 +898,27 @@ mod tests {
             initial_hold_ms: 100,
             repeat_window_ms: 200,
             diverts: HashMap::new(),
+            shared_gate: true,
         };
         let mut strategy = GatedHoldStrategy::new(config);
 
         let platform = Arc::new(MockPlatform::new());
-        let platform_handle = unsafe { PlatformHandle::from_mock(&platform) };
+        let platform_handle = PlatformHandle::from_mock(&platform);
         let action = Action::MediaNext;
         let ctx = StrategyContext::new(platform_handle, &action);
 
         // Press key
-        let key_down = InputEvent::Key(KeyEvent::new(KeyCode::new(0x1), true));
-        strategy.process(&key_down, &ctx).await;
+        let key_down = InputEvent::Key(KeyEvent::new(KEY1, true));
+        strategy.process(&key_down, &ctx);
 
         // Release before timer fires
         tokio::time::sleep(Duration::from_millis(20)).await;
-        let key_up = InputEvent::Key(KeyEvent::new(KeyCode::new(0x1), false));
-        strategy.process(&key_up, &ctx).await;
+        let key_up = InputEvent::Key(KeyEvent::new(KEY1, false));
+        strategy.process(&key_up, &ctx);
 
         // Key should be Idle
         assert!(
-            !strategy.key_states.contains_key("KEY_ESC"),
+            !strategy.key_states.contains_key(&KEY1),
             "key should be Idle after early release"
         );
 
@@ -841,15 +930,62 @@ mod tests {
 
         // Key should still be Idle (not transitioned to Active)
         assert!(
-            !strategy.key_states.contains_key("KEY_ESC"),
+            !strategy.key_states.contains_key(&KEY1),
             "key should remain Idle even after timer message arrives"
         );
     }
 
+    #[tokio::test]
+    async fn test_focus_change_closes_gate() {
+        use crate::config::{Action, WindowInfo};
+        use crate::platform::MockPlatform;
+        use crate::strategy::PlatformHandle;
+        use std::sync::Arc;
+
+        let mut strategy = GatedHoldStrategy::new(test_config());
+        let platform = Arc::new(MockPlatform::new());
+        let ctx = StrategyContext::new(PlatformHandle::from_mock(&platform), &Action::MediaNext);
+
+        strategy.key_states.insert(KEY1, KeyState::Active);
+        strategy.key_up(KEY1, Instant::now());
+        assert!(strategy.is_gate_open(KEY1, Instant::now()));
+
+        strategy.on_focus_change(&WindowInfo::default(), &ctx);
+        assert!(!strategy.is_gate_open(KEY1, Instant::now()));
+        assert!(strategy.key_states.is_empty());
+    }
+
+    #[test]
+    fn test_debug_state_reports_keys_and_gate() {
+        let mut strategy = GatedHoldStrategy::new(test_config());
+        strategy.key_states.insert(KEY1, KeyState::Active);
+
+        let state = strategy.debug_state();
+        assert_eq!(state["gate_open"].as_bool(), Some(true));
+        assert_eq!(state["keys"][&KEY1.to_string()].as_str(), Some("active"));
+    }
+
+    #[test]
+    fn test_isolated_gate_tracks_keys_separately() {
+        let mut strategy = GatedHoldStrategy::new(GatedHoldConfig {
+            shared_gate: false,
+            ..test_config()
+        });
+        strategy.key_states.insert(KEY1, KeyState::Active);
+
+        // Another key being active doesn't open this key's gate
+        assert!(!strategy.is_gate_open(KEY2, Instant::now()));
+
+        strategy.key_up(KEY1, Instant::now());
+        assert!(strategy.is_gate_open(KEY1, Instant::now()));
+        assert!(!strategy.is_gate_open(KEY2, Instant::now()));
+        assert!(strategy.last_release.is_none());
+    }
+
     #[tokio::test]
     async fn test_multiple_keys_share_gate_after_timer() {
         use crate::config::Action;
-        use crate::key::{KeyCode, KeyEvent};
+        use crate::key::KeyEvent;
         use crate::platform::MockPlatform;
         use crate::strategy::{PlatformHandle, StrategyContext};
         use std::sync::Arc;
@@ -858,34 +994,35 @@ mod tests {
             initial_hold_ms: 50,
             repeat_window_ms: 500,
             diverts: HashMap::new(),
+            shared_gate: true,
         };
         let mut strategy = GatedHoldStrategy::new(config);
 
         let platform = Arc::new(MockPlatform::new());
-        let platform_handle = unsafe { PlatformHandle::from_mock(&platform) };
+        let platform_handle = PlatformHandle::from_mock(&platform);
         let action = Action::MediaNext;
         let ctx = StrategyContext::new(platform_handle, &action);
 
         // Press key1, wait for timer, release
-        let key1_down = InputEvent::Key(KeyEvent::new(KeyCode::new(0x1), true));
-        strategy.process(&key1_down, &ctx).await;
+        let key1_down = InputEvent::Key(KeyEvent::new(KEY1, true));
+        strategy.process(&key1_down, &ctx);
 
         tokio::time::sleep(Duration::from_millis(60)).await;
         strategy.process_timer_completions();
 
-        let key1_up = InputEvent::Key(KeyEvent::new(KeyCode::new(0x1), false));
-        strategy.process(&key1_up, &ctx).await;
+        let key1_up = InputEvent::Key(KeyEvent::new(KEY1, false));
+        strategy.process(&key1_up, &ctx);
 
         // Gate should be open
-        assert!(strategy.is_gate_open());
+        assert!(strategy.is_gate_open(KEY1, Instant::now()));
 
         // Press different key2 quickly
-        let key2_down = InputEvent::Key(KeyEvent::new(KeyCode::new(0x2), true));
-        let response = strategy.process(&key2_down, &ctx).await;
+        let key2_down = InputEvent::Key(KeyEvent::new(KEY2, true));
+        let response = strategy.process(&key2_down, &ctx);
 
         // Key2 should activate immediately (shared gate is open)
         assert!(
-            matches!(strategy.key_states.get("KEY_1"), Some(KeyState::Active)),
+            matches!(strategy.key_states.get(&KEY2), Some(KeyState::Active)),
             "key2 should activate immediately due to shared gate"
         );
         assert!(matches!(response, EventResponse::Block));
@@ -894,6 +1031,46 @@ mod tests {
         platform.assert_call_count(2);
     }
 
+    #[tokio::test]
+    async fn test_mouse_button_divert_blocks_release() {
+        use crate::platform::{MediaCommand, MockPlatform};
+        use std::sync::Arc;
+
+        let mut config = test_config();
+        config.diverts.insert(
+            InputEventId::MouseButton(MouseButton::Forward),
+            Action::MediaNext,
+        );
+        let mut strategy = GatedHoldStrategy::new(config);
+        let platform = Arc::new(MockPlatform::new());
+        let ctx = StrategyContext::new(PlatformHandle::from_mock(&platform), &Action::Block);
+        let button = |down| InputEvent::MouseButton {
+            button: MouseButton::Forward,
+            down,
+        };
+
+        // Nothing held: the click goes through untouched
+        assert_eq!(
+            strategy.process(&button(true), &ctx),
+            EventResponse::Passthrough
+        );
+        assert_eq!(
+            strategy.process(&button(false), &ctx),
+            EventResponse::Passthrough
+        );
+        platform.assert_no_calls();
+
+        // Held: the press runs the divert and its release is swallowed too
+        strategy.key_states.insert(KEY1, KeyState::Active);
+        assert_eq!(strategy.process(&button(true), &ctx), EventResponse::Block);
+        platform.assert_media_sent(MediaCommand::Next);
+        assert_eq!(strategy.process(&button(false), &ctx), EventResponse::Block);
+        assert_eq!(
+            strategy.process(&button(false), &ctx),
+            EventResponse::Passthrough
+        );
+    }
+
     #[tokio::test]
     async fn test_mock_platform_records_actions() {
         use crate::config::Action;
@@ -902,7 +1079,7 @@ mod tests {
         use std::sync::Arc;
 
         let platform = Arc::new(MockPlatform::new());
-        let platform_handle = unsafe { PlatformHandle::from_mock(&platform) };
+        let platform_handle = PlatformHandle::from_mock(&platform);
 
         // Test that executing actions records them
         let action = Action::MediaNext;
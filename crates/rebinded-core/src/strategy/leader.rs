@@ -0,0 +1,237 @@
+//! Leader-key strategy
+//!
+//! Pressing the leader key is swallowed and opens a pending mode. The next key
+//! press within `timeout_ms` is looked up in the sequence map:
+//! - Mapped key: its action runs and the key (press and release) is swallowed
+//! - Unmapped key: pending mode ends and the key passes through untouched
+//! - No key in time: pending mode silently expires
+//!
+//! Pressing the leader again while pending cancels the pending mode. The
+//! strategy subscribes to every key, so it doesn't need a `[bindings]` entry.
+
+use crate::config::Action;
+use crate::key::{InputEvent, InputEventId, KeyCode};
+use crate::platform::EventResponse;
+use crate::strategy::{KeyStrategy, StrategyContext, remaining_ms};
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// Configuration for leader-key behavior
+#[derive(Debug, Clone)]
+pub struct LeaderConfig {
+    /// Key that opens pending mode
+    pub leader: KeyCode,
+    /// How long pending mode waits for a follow-up key (ms)
+    pub timeout_ms: u64,
+    /// Follow-up keys and the actions they trigger
+    pub sequences: HashMap<KeyCode, Action>,
+}
+
+/// Leader-key strategy implementation
+pub struct LeaderStrategy {
+    config: LeaderConfig,
+    /// When pending mode expires; `None` when not pending
+    pending_until: Option<Instant>,
+    /// Follow-up keys whose press was consumed, so their release is swallowed too
+    swallowed: HashSet<KeyCode>,
+}
+
+impl LeaderStrategy {
+    /// Create a new leader strategy with the given configuration
+    pub fn new(config: LeaderConfig) -> Self {
+        Self {
+            config,
+            pending_until: None,
+            swallowed: HashSet::new(),
+        }
+    }
+
+    /// Whether pending mode is active at `time`, expiring it if the timeout has passed
+    fn is_pending(&mut self, time: Instant) -> bool {
+        match self.pending_until {
+            Some(deadline) if time < deadline => true,
+            Some(_) => {
+                debug!("leader: pending -> idle (timeout)");
+                self.pending_until = None;
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// Handle key-down event
+    fn key_down(&mut self, key: KeyCode, time: Instant, ctx: &StrategyContext) -> EventResponse {
+        let pending = self.is_pending(time);
+
+        if key == self.config.leader {
+            if pending {
+                debug!("leader: pending -> idle (leader pressed again)");
+                self.pending_until = None;
+            } else {
+                debug!("leader: idle -> pending");
+                self.pending_until = Some(time + Duration::from_millis(self.config.timeout_ms));
+            }
+            return EventResponse::Block;
+        }
+
+        // OS key repeat of a consumed follow-up key
+        if self.swallowed.contains(&key) {
+            return EventResponse::Block;
+        }
+
+        if !pending {
+            return EventResponse::Passthrough;
+        }
+        self.pending_until = None;
+
+        match self.config.sequences.get(&key) {
+            Some(action) => {
+                debug!(?key, ?action, "leader: pending -> idle (sequence matched)");
+                ctx.platform_handle().execute(action);
+                self.swallowed.insert(key);
+                EventResponse::Block
+            }
+            None => {
+                debug!(?key, "leader: pending -> idle (unmapped key)");
+                EventResponse::Passthrough
+            }
+        }
+    }
+
+    /// Handle key-up event
+    fn key_up(&mut self, key: KeyCode) -> EventResponse {
+        if key == self.config.leader || self.swallowed.remove(&key) {
+            EventResponse::Block
+        } else {
+            EventResponse::Passthrough
+        }
+    }
+}
+
+impl KeyStrategy for LeaderStrategy {
+    fn subscriptions(&self) -> HashSet<InputEventId> {
+        HashSet::from([InputEventId::AnyKey])
+    }
+
+    fn debug_state(&self) -> serde_json::Value {
+        let swallowed: Vec<String> = self.swallowed.iter().map(KeyCode::display_name).collect();
+        let ms_left = self.pending_until.map(remaining_ms).unwrap_or(0);
+        json!({
+            "pending": ms_left > 0,
+            "pending_ms_left": ms_left,
+            "swallowed": swallowed,
+        })
+    }
+
+    fn reset(&mut self, _ctx: &StrategyContext) {
+        debug!("leader: reset");
+        self.pending_until = None;
+        self.swallowed.clear();
+    }
+
+    fn process(&mut self, event: &InputEvent, ctx: &StrategyContext) -> EventResponse {
+        match event {
+            InputEvent::Key(key_event) => {
+                if key_event.down {
+                    self.key_down(key_event.key, key_event.time, ctx)
+                } else {
+                    self.key_up(key_event.key)
+                }
+            }
+            InputEvent::MouseButton { .. } | InputEvent::Scroll { .. } | InputEvent::Divert(_) => {
+                EventResponse::Passthrough
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key::KeyEvent;
+    use crate::platform::{MediaCommand, MockPlatform};
+    use crate::strategy::PlatformHandle;
+    use std::sync::Arc;
+
+    const LEADER: KeyCode = KeyCode::new(0x7C);
+    const P: KeyCode = KeyCode::new(25);
+    const X: KeyCode = KeyCode::new(45);
+
+    fn leader(timeout_ms: u64) -> LeaderStrategy {
+        LeaderStrategy::new(LeaderConfig {
+            leader: LEADER,
+            timeout_ms,
+            sequences: HashMap::from([(P, Action::MediaPlayPause)]),
+        })
+    }
+
+    fn key(code: KeyCode, down: bool) -> InputEvent {
+        InputEvent::Key(KeyEvent::new(code, down))
+    }
+
+    async fn tap(
+        strategy: &mut LeaderStrategy,
+        code: KeyCode,
+        ctx: &StrategyContext,
+    ) -> [EventResponse; 2] {
+        [
+            strategy.process(&key(code, true), ctx),
+            strategy.process(&key(code, false), ctx),
+        ]
+    }
+
+    #[tokio::test]
+    async fn test_sequence_executes_action_and_swallows_key() {
+        let mut strategy = leader(500);
+        let platform = Arc::new(MockPlatform::new());
+        let ctx = StrategyContext::new(PlatformHandle::from_mock(&platform), &Action::Block);
+
+        assert!(tap(&mut strategy, LEADER, &ctx).await == [EventResponse::Block; 2]);
+        assert!(tap(&mut strategy, P, &ctx).await == [EventResponse::Block; 2]);
+        platform.assert_media_sent(MediaCommand::PlayPause);
+
+        // Back to idle: the same key types normally again
+        assert!(tap(&mut strategy, P, &ctx).await == [EventResponse::Passthrough; 2]);
+        platform.assert_call_count(1);
+    }
+
+    #[tokio::test]
+    async fn test_unmapped_key_passes_through_and_ends_pending() {
+        let mut strategy = leader(500);
+        let platform = Arc::new(MockPlatform::new());
+        let ctx = StrategyContext::new(PlatformHandle::from_mock(&platform), &Action::Block);
+
+        tap(&mut strategy, LEADER, &ctx).await;
+        assert!(tap(&mut strategy, X, &ctx).await == [EventResponse::Passthrough; 2]);
+        assert!(tap(&mut strategy, P, &ctx).await == [EventResponse::Passthrough; 2]);
+        platform.assert_no_calls();
+    }
+
+    #[tokio::test]
+    async fn test_pending_times_out() {
+        let mut strategy = leader(20);
+        let platform = Arc::new(MockPlatform::new());
+        let ctx = StrategyContext::new(PlatformHandle::from_mock(&platform), &Action::Block);
+
+        tap(&mut strategy, LEADER, &ctx).await;
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        assert!(tap(&mut strategy, P, &ctx).await == [EventResponse::Passthrough; 2]);
+        platform.assert_no_calls();
+    }
+
+    #[tokio::test]
+    async fn test_leader_twice_cancels() {
+        let mut strategy = leader(500);
+        let platform = Arc::new(MockPlatform::new());
+        let ctx = StrategyContext::new(PlatformHandle::from_mock(&platform), &Action::Block);
+
+        tap(&mut strategy, LEADER, &ctx).await;
+        assert_eq!(strategy.debug_state()["pending"].as_bool(), Some(true));
+        tap(&mut strategy, LEADER, &ctx).await;
+        assert!(strategy.pending_until.is_none());
+        assert_eq!(strategy.debug_state()["pending"].as_bool(), Some(false));
+        assert!(tap(&mut strategy, P, &ctx).await == [EventResponse::Passthrough; 2]);
+    }
+}
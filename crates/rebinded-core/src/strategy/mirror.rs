@@ -0,0 +1,282 @@
+//! One-handed mirror strategy (Half-QWERTY)
+//!
+//! While the `trigger` key is held, every key types its mirror image across
+//! the middle of the keyboard (J→F, K→D, P→Q, 7→4...), so either hand can type
+//! the whole alphabet alone. The mirror pairs are generated from QWERTY key
+//! positions; `pairs` adds more (e.g. Backspace↔Tab), mirrored both ways.
+//!
+//! Tapping the trigger without mirroring a key types the trigger itself, so a
+//! trigger such as Space keeps working. Mirrored keys type normally while the
+//! trigger is up.
+
+use crate::key::{InputEvent, InputEventId, KeyCode};
+use crate::platform::EventResponse;
+use crate::strategy::{KeyStrategy, StrategyContext};
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use tracing::debug;
+
+/// Key rows by QWERTY position, left to right; each row's keys mirror each
+/// other around its middle
+#[cfg(unix)]
+const ROWS: [[u32; 10]; 4] = [
+    [2, 3, 4, 5, 6, 7, 8, 9, 10, 11],         // KEY_1 .. KEY_0
+    [16, 17, 18, 19, 20, 21, 22, 23, 24, 25], // KEY_Q .. KEY_P
+    [30, 31, 32, 33, 34, 35, 36, 37, 38, 39], // KEY_A .. KEY_SEMICOLON
+    [44, 45, 46, 47, 48, 49, 50, 51, 52, 53], // KEY_Z .. KEY_SLASH
+];
+
+/// Key rows by QWERTY position, left to right; each row's keys mirror each
+/// other around its middle
+#[cfg(windows)]
+const ROWS: [[u32; 10]; 4] = [
+    [0x31, 0x32, 0x33, 0x34, 0x35, 0x36, 0x37, 0x38, 0x39, 0x30], // 1 .. 0
+    [0x51, 0x57, 0x45, 0x52, 0x54, 0x59, 0x55, 0x49, 0x4F, 0x50], // Q .. P
+    [0x41, 0x53, 0x44, 0x46, 0x47, 0x48, 0x4A, 0x4B, 0x4C, 0xBA], // A .. VK_OEM_1
+    [0x5A, 0x58, 0x43, 0x56, 0x42, 0x4E, 0x4D, 0xBC, 0xBE, 0xBF], // Z .. VK_OEM_2
+];
+
+/// Every key mapped to its mirror image on a QWERTY keyboard
+pub fn mirror_pairs() -> HashMap<KeyCode, KeyCode> {
+    ROWS.iter()
+        .flat_map(|row| {
+            row.iter()
+                .zip(row.iter().rev())
+                .map(|(key, mirror)| (KeyCode::new(*key), KeyCode::new(*mirror)))
+        })
+        .collect()
+}
+
+/// Configuration for one-handed mirroring
+#[derive(Debug, Clone)]
+pub struct MirrorConfig {
+    /// Key that mirrors the keyboard while held
+    pub trigger: KeyCode,
+    /// Each mirrored key and the key it types instead
+    pub pairs: HashMap<KeyCode, KeyCode>,
+}
+
+/// Mirror strategy implementation
+pub struct MirrorStrategy {
+    config: MirrorConfig,
+    /// Whether the trigger is held
+    active: bool,
+    /// Whether a key was mirrored since the trigger went down; if not, its
+    /// release taps the trigger
+    mirrored: bool,
+    /// Mirrored keys held down, with the key pressed in their place
+    sent: HashMap<KeyCode, KeyCode>,
+}
+
+impl MirrorStrategy {
+    /// Create a new mirror strategy with the given configuration
+    pub fn new(config: MirrorConfig) -> Self {
+        Self {
+            config,
+            active: false,
+            mirrored: false,
+            sent: HashMap::new(),
+        }
+    }
+
+    /// Handle key-down event
+    fn key_down(&mut self, key: KeyCode, ctx: &StrategyContext) -> EventResponse {
+        if key == self.config.trigger {
+            // OS key repeat keeps the same hold going
+            if !self.active {
+                debug!("mirror: trigger down");
+                self.active = true;
+                self.mirrored = false;
+            }
+            return EventResponse::Block;
+        }
+
+        // OS key repeat of a mirrored key repeats its mirror
+        let mirror = match self.sent.get(&key) {
+            Some(mirror) => *mirror,
+            None if self.active => match self.config.pairs.get(&key) {
+                Some(mirror) => *mirror,
+                None => return EventResponse::Passthrough,
+            },
+            None => return EventResponse::Passthrough,
+        };
+        debug!(%key, %mirror, "mirror: mirrored key");
+        ctx.send_raw_key(mirror, true);
+        self.sent.insert(key, mirror);
+        self.mirrored = true;
+        EventResponse::Block
+    }
+
+    /// Handle key-up event
+    fn key_up(&mut self, key: KeyCode, ctx: &StrategyContext) -> EventResponse {
+        if key == self.config.trigger {
+            debug!(mirrored = self.mirrored, "mirror: trigger up");
+            self.active = false;
+            if !self.mirrored {
+                ctx.send_raw_key(key, true);
+                ctx.send_raw_key(key, false);
+            }
+            return EventResponse::Block;
+        }
+
+        // Released after the trigger: release the mirror that went down
+        match self.sent.remove(&key) {
+            Some(mirror) => {
+                ctx.send_raw_key(mirror, false);
+                EventResponse::Block
+            }
+            None => EventResponse::Passthrough,
+        }
+    }
+}
+
+impl KeyStrategy for MirrorStrategy {
+    fn subscriptions(&self) -> HashSet<InputEventId> {
+        self.config
+            .pairs
+            .keys()
+            .chain([&self.config.trigger])
+            .map(|key| InputEventId::Key(*key))
+            .collect()
+    }
+
+    fn debug_state(&self) -> serde_json::Value {
+        let held: Vec<String> = self.sent.values().map(KeyCode::display_name).collect();
+        json!({ "active": self.active, "held": held })
+    }
+
+    fn reset(&mut self, ctx: &StrategyContext) {
+        debug!("mirror: reset");
+        self.active = false;
+        for (_, mirror) in self.sent.drain() {
+            ctx.send_raw_key(mirror, false);
+        }
+    }
+
+    fn process(&mut self, event: &InputEvent, ctx: &StrategyContext) -> EventResponse {
+        match event {
+            InputEvent::Key(key_event) => {
+                if key_event.down {
+                    self.key_down(key_event.key, ctx)
+                } else {
+                    self.key_up(key_event.key, ctx)
+                }
+            }
+            // Not subscribed to anything else
+            InputEvent::MouseButton { .. } | InputEvent::Scroll { .. } | InputEvent::Divert(_) => {
+                EventResponse::Passthrough
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Action;
+    use crate::key::KeyEvent;
+    use crate::platform::MockPlatform;
+    use crate::platform::mock::PlatformCall;
+    use crate::strategy::PlatformHandle;
+    use std::sync::Arc;
+
+    const TRIGGER: KeyCode = KeyCode::new(0xFFF0);
+
+    /// Physical key at `column` of QWERTY `row`, and its mirror image
+    fn key(row: usize, column: usize) -> (KeyCode, KeyCode) {
+        (
+            KeyCode::new(ROWS[row][column]),
+            KeyCode::new(ROWS[row][9 - column]),
+        )
+    }
+
+    fn setup() -> (MirrorStrategy, Arc<MockPlatform>, StrategyContext) {
+        let strategy = MirrorStrategy::new(MirrorConfig {
+            trigger: TRIGGER,
+            pairs: mirror_pairs(),
+        });
+        let platform = Arc::new(MockPlatform::new());
+        let ctx = StrategyContext::new(PlatformHandle::from_mock(&platform), &Action::Block);
+        (strategy, platform, ctx)
+    }
+
+    fn send(
+        strategy: &mut MirrorStrategy,
+        ctx: &StrategyContext,
+        key: KeyCode,
+        down: bool,
+    ) -> EventResponse {
+        strategy.process(&InputEvent::Key(KeyEvent::new(key, down)), ctx)
+    }
+
+    #[test]
+    fn test_mirror_pairs() {
+        let pairs = mirror_pairs();
+        assert_eq!(pairs.len(), 40);
+        for (key, mirror) in &pairs {
+            assert_ne!(key, mirror);
+            assert_eq!(pairs[mirror], *key);
+        }
+    }
+
+    #[test]
+    fn test_held_trigger_mirrors_keys() {
+        let (mut strategy, platform, ctx) = setup();
+        let (j, f) = key(2, 6);
+
+        // Without the trigger, keys type as themselves
+        assert_eq!(
+            send(&mut strategy, &ctx, j, true),
+            EventResponse::Passthrough
+        );
+        assert_eq!(
+            send(&mut strategy, &ctx, j, false),
+            EventResponse::Passthrough
+        );
+
+        assert_eq!(
+            send(&mut strategy, &ctx, TRIGGER, true),
+            EventResponse::Block
+        );
+        assert_eq!(send(&mut strategy, &ctx, j, true), EventResponse::Block);
+        // The trigger going up first still releases the mirror that went down
+        assert_eq!(
+            send(&mut strategy, &ctx, TRIGGER, false),
+            EventResponse::Block
+        );
+        assert_eq!(send(&mut strategy, &ctx, j, false), EventResponse::Block);
+
+        assert_eq!(
+            platform.calls(),
+            vec![
+                PlatformCall::SendRawKey { key: f, down: true },
+                PlatformCall::SendRawKey {
+                    key: f,
+                    down: false
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tapped_trigger_types_itself() {
+        let (mut strategy, platform, ctx) = setup();
+
+        send(&mut strategy, &ctx, TRIGGER, true);
+        send(&mut strategy, &ctx, TRIGGER, false);
+
+        assert_eq!(
+            platform.calls(),
+            vec![
+                PlatformCall::SendRawKey {
+                    key: TRIGGER,
+                    down: true
+                },
+                PlatformCall::SendRawKey {
+                    key: TRIGGER,
+                    down: false
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,499 @@
+//! Key event processing strategies
+//!
+//! Strategies transform key events into actions with optional stateful behavior.
+//! Examples include gated hold (require hold before activation), tap-vs-hold
+//! detection, and double-tap recognition.
+//!
+//! ## Public API
+//!
+//! This module exposes `PlatformHandle` and `StrategyContext` as public API
+//! for custom strategy implementations. Some methods may not be used internally
+//! but are available for strategy authors.
+
+mod combo;
+mod debounce;
+mod gated_hold;
+mod leader;
+mod mirror;
+mod mouse_keys;
+pub mod plugin;
+mod push_to_talk;
+mod registry;
+mod repeat;
+#[cfg(feature = "script")]
+mod script;
+mod tap_hold;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use combo::{ComboConfig, ComboStrategy};
+pub use debounce::{DebounceConfig, DebounceStrategy};
+pub use gated_hold::{GatedHoldConfig, GatedHoldStrategy};
+pub use leader::{LeaderConfig, LeaderStrategy};
+pub use mirror::{MirrorConfig, MirrorStrategy, mirror_pairs};
+pub use mouse_keys::{MouseControl, MouseKeysConfig, MouseKeysStrategy};
+pub use plugin::PluginStrategy;
+pub use push_to_talk::{PushToTalkApp, PushToTalkConfig, PushToTalkStrategy};
+pub use registry::StrategyRegistry;
+pub use repeat::{RepeatConfig, RepeatStrategy};
+#[cfg(feature = "script")]
+pub use script::{ScriptConfig, ScriptStrategy};
+pub use tap_hold::{TapHoldConfig, TapHoldStrategy};
+
+use crate::clock::{Clock, SystemClock};
+use crate::config::{Action, WindowInfo, template};
+use crate::key::{InputEvent, InputEventId, KeyCode};
+use crate::platform::{
+    DesktopCommand, EventResponse, MediaCommand, MicCommand, MockPlatform, Platform,
+    PlatformInterface, PlatformState, PointerEvent, SyntheticKey, WindowSwitch,
+};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::info;
+
+/// Trait for key event processing strategies.
+///
+/// Strategies receive key events and decide:
+/// 1. Whether to block or passthrough the original key event (returned quickly)
+/// 2. What actions to execute (via StrategyContext, can be immediate or delayed)
+///
+/// The `process` method must return quickly (< 100ms) to avoid OS hook timeouts.
+/// It is synchronous: strategies live in a `RefCell` on the current-thread
+/// runtime, so nothing may await while one is borrowed. For delayed actions,
+/// return `Block` and spawn async work via the context.
+///
+/// A binding may chain several strategies (`strategy = ["a", "b"]`). They see the
+/// event in order, and each response decides what happens next:
+/// - `Block` consumes the event; later strategies never see it
+/// - `Continue` accepts it and hands it to the next strategy. After the last
+///   one, the binding's action runs as if the binding had no strategies.
+/// - `Passthrough` leaves it alone and hands it to the next strategy. If the
+///   last strategy passes, the OS receives the key.
+pub trait KeyStrategy {
+    /// Additional events this strategy wants to receive (beyond its bound keys).
+    ///
+    /// Strategies can subscribe to events like scroll wheel ticks that aren't
+    /// directly bound to the strategy. The event handler will route these
+    /// subscribed events to the strategy's `process` method.
+    ///
+    /// Default implementation returns an empty set (no extra subscriptions).
+    fn subscriptions(&self) -> HashSet<InputEventId> {
+        HashSet::new()
+    }
+
+    /// Process an input event.
+    ///
+    /// Must return quickly (< 100ms recommended). For delayed actions,
+    /// return `EventResponse::Block` and use `ctx.execute_after()` to
+    /// schedule the action.
+    fn process(&mut self, event: &InputEvent, ctx: &StrategyContext) -> EventResponse;
+
+    /// Drop all in-flight state (pending timers, held keys, open gates).
+    ///
+    /// Called when a transition makes existing state meaningless. Keys the
+    /// strategy pressed on its own must be released here so nothing is left
+    /// stuck down. Default implementation does nothing.
+    fn reset(&mut self, _ctx: &StrategyContext) {}
+
+    /// Called when focus moves to a different application, before the event
+    /// that revealed the change is processed.
+    ///
+    /// Default implementation resets the strategy, so a hold or repeat window
+    /// started in one application never carries over into another.
+    fn on_focus_change(&mut self, _window: &WindowInfo, ctx: &StrategyContext) {
+        self.reset(ctx);
+    }
+
+    /// Snapshot of internal state for diagnostics (held keys, open gates,
+    /// time left on windows). Default implementation returns an empty object.
+    fn debug_state(&self) -> serde_json::Value {
+        serde_json::Value::Object(serde_json::Map::new())
+    }
+}
+
+/// Milliseconds until `deadline`, or zero if it has passed. Diagnostics only,
+/// so this reads the real time rather than a handle's clock.
+fn remaining_ms(deadline: Instant) -> i64 {
+    deadline
+        .saturating_duration_since(Instant::now())
+        .as_millis() as i64
+}
+
+/// The platform a handle executes actions on
+#[derive(Clone)]
+enum Target {
+    Native(Arc<Platform>),
+    Mock(Arc<MockPlatform>),
+    /// A platform supplied by an embedder
+    Custom(Arc<dyn PlatformInterface + Send + Sync>),
+}
+
+/// Calls the same method on whichever platform a handle targets
+macro_rules! dispatch {
+    ($handle:expr, $platform:ident => $call:expr) => {
+        match &$handle.target {
+            Target::Native($platform) => $call,
+            Target::Mock($platform) => $call,
+            Target::Custom($platform) => $call,
+        }
+    };
+}
+
+/// Shared handle to the platform for executing actions, including from
+/// tasks that strategies spawn for delayed execution.
+///
+/// Handles hold the platform through an `Arc`, so cloning one is cheap and a
+/// pending timer keeps the platform alive for as long as it needs it.
+#[derive(Clone)]
+pub struct PlatformHandle {
+    target: Target,
+    /// Log integration actions (OBS, webhooks, commands...) instead of running them
+    dry_run: bool,
+    /// What timers and chain delays wait on
+    clock: Arc<dyn Clock>,
+}
+
+impl PlatformHandle {
+    /// Create a new platform handle sharing `platform`'s connections and devices
+    pub fn new(platform: &Platform) -> Self {
+        Self {
+            target: Target::Native(Arc::new(platform.clone())),
+            dry_run: false,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Create a platform handle from MockPlatform, for tests and `--dry-run`.
+    /// Actions that bypass the platform (integrations, commands) are only logged.
+    pub fn from_mock(platform: &MockPlatform) -> Self {
+        Self {
+            target: Target::Mock(Arc::new(platform.clone())),
+            dry_run: true,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Create a platform handle for a custom platform. Every action, including
+    /// integrations and commands, runs for real.
+    pub fn custom(platform: impl PlatformInterface + Send + Sync + 'static) -> Self {
+        Self {
+            target: Target::Custom(Arc::new(platform)),
+            dry_run: false,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Time timers, chain delays and session confirmations with `clock`
+    /// instead of the system clock, e.g. a [`MockClock`](crate::clock::MockClock)
+    /// in tests
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// The clock this handle's timers run on, for tasks strategies spawn
+    pub fn clock(&self) -> Arc<dyn Clock> {
+        self.clock.clone()
+    }
+
+    /// The current time on this handle's clock
+    pub fn now(&self) -> Instant {
+        self.clock.now()
+    }
+
+    /// The state the platform shares with the engine and daemon driving it
+    pub fn state(&self) -> &Arc<PlatformState> {
+        dispatch!(self, platform => platform.state())
+    }
+
+    /// Execute an action on the platform
+    pub fn execute(&self, action: &Action) {
+        use Action::*;
+        match action {
+            Obs(_) | HomeAssistant(_) | Http(_) | Dbus(_) | Run(_) if self.dry_run => {
+                info!(%action, "dry run");
+            }
+            MediaPlayPause => self.send_media(MediaCommand::PlayPause),
+            MediaNext => self.send_media(MediaCommand::Next),
+            MediaPrevious => self.send_media(MediaCommand::Previous),
+            MediaStop => self.send_media(MediaCommand::Stop),
+            VolumeUp => self.send_media(MediaCommand::VolumeUp),
+            VolumeDown => self.send_media(MediaCommand::VolumeDown),
+            VolumeMute => self.send_media(MediaCommand::VolumeMute),
+            VolumeStep(percent) => dispatch!(self, platform => platform.change_volume(*percent)),
+            PlayerMedia { player, command } => {
+                dispatch!(self, platform => platform.send_player_media(player, *command))
+            }
+            Mic(cmd) => self.set_mic(*cmd),
+            BrowserBack => self.send_key(SyntheticKey::BrowserBack),
+            BrowserForward => self.send_key(SyntheticKey::BrowserForward),
+            DesktopNext => self.switch_desktop(DesktopCommand::Next),
+            DesktopPrevious => self.switch_desktop(DesktopCommand::Previous),
+            Desktop(n) => self.switch_desktop(DesktopCommand::Goto(*n)),
+            WindowNext => dispatch!(self, platform => platform.switch_window(WindowSwitch::Next)),
+            WindowPrevious => {
+                dispatch!(self, platform => platform.switch_window(WindowSwitch::Previous))
+            }
+            Snap(position) => dispatch!(self, platform => platform.snap_window(*position)),
+            Session(cmd) => {
+                if cmd.needs_confirmation() && !self.state().confirm_session(*cmd, self.now()) {
+                    info!(%action, "waiting for a second press to confirm");
+                    self.notify(&format!(
+                        "Press again within {}s to run {action}",
+                        crate::platform::SESSION_CONFIRM_WINDOW.as_secs()
+                    ));
+                } else {
+                    dispatch!(self, platform => platform.control_session(*cmd))
+                }
+            }
+            Clipboard { text, paste } if template::is_templated(text) => {
+                // Reading the clipboard and focused window can block, so the
+                // template is filled in off the event path
+                let handle = self.clone();
+                let (text, paste) = (text.clone(), *paste);
+                tokio::task::spawn_blocking(move || {
+                    let text = template::expand(
+                        &text,
+                        &template::Context {
+                            window: &handle.get_active_window(),
+                            clipboard: &|| handle.get_clipboard(),
+                            now: template::LocalTime::now(),
+                        },
+                    );
+                    dispatch!(handle, platform => platform.set_clipboard(&text, paste))
+                });
+            }
+            Clipboard { text, paste } => {
+                dispatch!(self, platform => platform.set_clipboard(text, *paste))
+            }
+            Obs(request) => self.state().integrations().send_obs(request.clone()),
+            HomeAssistant(call) => self
+                .state()
+                .integrations()
+                .send_home_assistant(call.clone()),
+            Http(webhook) => crate::integrations::webhook::send(webhook.clone()),
+            Dbus(call) => crate::integrations::dbus::send(call.clone()),
+            Notify(message) => self.notify(message),
+            Send(keys) => {
+                keys.iter().for_each(|key| self.send_raw_key(*key, true));
+                keys.iter()
+                    .rev()
+                    .for_each(|key| self.send_raw_key(*key, false));
+            }
+            Focus(target) => dispatch!(self, platform => platform.focus_window(target)),
+            Run(command) => crate::integrations::shell::run(command.clone()),
+            Chain(actions) => {
+                // Steps run on their own task so the hook can respond immediately
+                let handle = self.clone();
+                let actions = actions.clone();
+                tokio::spawn(async move { handle.run_steps(&actions).await });
+            }
+            Branch { .. } => {
+                if let Some(action) = action.branch_target(&self.get_active_window()) {
+                    self.execute(action);
+                }
+            }
+            // Delays only pace the steps of a chain
+            Delay(_) => {}
+            Passthrough | Block => {}
+        }
+    }
+
+    /// Run chain steps in order, waiting out delays. Branches check the focused
+    /// window when they are reached, and run their chosen steps before the next one.
+    fn run_steps<'a>(
+        &'a self,
+        actions: &'a [Action],
+    ) -> std::pin::Pin<Box<dyn Future<Output = ()> + Send + 'a>> {
+        Box::pin(async move {
+            for action in actions {
+                match action {
+                    Action::Delay(ms) => self.clock.sleep(Duration::from_millis(*ms)).await,
+                    Action::Chain(steps) => self.run_steps(steps).await,
+                    Action::Branch { .. } => {
+                        if let Some(target) = action.branch_target(&self.get_active_window()) {
+                            self.run_steps(std::slice::from_ref(target)).await;
+                        }
+                    }
+                    action => self.execute(action),
+                }
+            }
+        })
+    }
+
+    /// Send a media command
+    ///
+    /// Public API method for custom strategies that need direct platform control.
+    pub fn send_media(&self, cmd: MediaCommand) {
+        dispatch!(self, platform => platform.send_media(cmd))
+    }
+
+    /// Send a synthetic key
+    ///
+    /// Public API method for custom strategies that need direct platform control.
+    pub fn send_key(&self, key: SyntheticKey) {
+        dispatch!(self, platform => platform.send_key(key))
+    }
+
+    /// Send a single press or release of a platform-native key
+    pub fn send_raw_key(&self, key: KeyCode, down: bool) {
+        dispatch!(self, platform => platform.send_raw_key(key, down))
+    }
+
+    /// Inject pointer motion, a button press or release, or scrolling
+    pub fn send_pointer(&self, event: PointerEvent) {
+        dispatch!(self, platform => platform.send_pointer(event))
+    }
+
+    /// Press and release a platform-native key
+    pub fn tap_raw_key(&self, key: KeyCode) {
+        self.send_raw_key(key, true);
+        self.send_raw_key(key, false);
+    }
+
+    /// Mute, unmute or toggle the default microphone
+    pub fn set_mic(&self, cmd: MicCommand) {
+        dispatch!(self, platform => platform.set_mic(cmd))
+    }
+
+    /// Whether a process whose executable name matches the glob `binary` is running
+    pub fn process_running(&self, binary: &str) -> bool {
+        dispatch!(self, platform => platform.process_running(binary))
+    }
+
+    /// Switch virtual desktops
+    fn switch_desktop(&self, cmd: DesktopCommand) {
+        dispatch!(self, platform => platform.switch_desktop(cmd))
+    }
+
+    /// Get the active window info
+    pub fn get_active_window(&self) -> WindowInfo {
+        dispatch!(self, platform => platform.get_active_window())
+    }
+
+    /// The text on the clipboard, if any
+    pub fn get_clipboard(&self) -> Option<String> {
+        dispatch!(self, platform => platform.get_clipboard())
+    }
+
+    /// Show a desktop notification
+    pub fn notify(&self, message: &str) {
+        dispatch!(self, platform => platform.notify(message))
+    }
+}
+
+/// Context provided to strategies for action execution and platform queries.
+///
+/// Strategies use this to:
+/// - Execute actions immediately or after a delay
+/// - Query window information for conditional logic
+/// - Inject synthetic keys or media commands
+#[derive(Clone)]
+pub struct StrategyContext {
+    platform_handle: PlatformHandle,
+    action: Action,
+    /// Notification shown each time the bound action executes
+    notification: Option<String>,
+}
+
+impl StrategyContext {
+    /// Create a new strategy context
+    pub fn new(platform_handle: PlatformHandle, action: &Action) -> Self {
+        Self {
+            platform_handle,
+            action: action.clone(),
+            notification: None,
+        }
+    }
+
+    /// Show `notification` whenever the bound action executes
+    pub fn with_notification(mut self, notification: Option<String>) -> Self {
+        self.notification = notification;
+        self
+    }
+
+    /// Execute the bound action immediately
+    pub fn execute(&self) {
+        self.platform_handle.execute(&self.action);
+        if let Some(message) = &self.notification {
+            self.platform_handle.notify(message);
+        }
+    }
+
+    /// Execute the bound action after a delay.
+    ///
+    /// Spawns an async task that sleeps for `delay` then executes the action.
+    /// The task runs independently — this method returns immediately.
+    ///
+    /// Public API method for custom strategies implementing delayed actions.
+    pub fn execute_after(&self, delay: Duration) {
+        let handle = self.platform_handle.clone();
+        let action = self.action.clone();
+        let notification = self.notification.clone();
+
+        tokio::spawn(async move {
+            handle.clock.sleep(delay).await;
+            handle.execute(&action);
+            if let Some(message) = notification {
+                handle.notify(&message);
+            }
+        });
+    }
+
+    /// Get information about the currently focused window
+    ///
+    /// Public API method for context-aware strategies.
+    pub fn window_info(&self) -> WindowInfo {
+        self.platform_handle.get_active_window()
+    }
+
+    /// Inject a synthetic key press
+    ///
+    /// Public API method for strategies that need to inject custom keys.
+    pub fn send_key(&self, key: SyntheticKey) {
+        self.platform_handle.send_key(key);
+    }
+
+    /// Send a single press or release of a platform-native key
+    ///
+    /// Public API method for strategies that replay or hold keys.
+    pub fn send_raw_key(&self, key: KeyCode, down: bool) {
+        self.platform_handle.send_raw_key(key, down);
+    }
+
+    /// Inject pointer motion, a button press or release, or scrolling
+    ///
+    /// Public API method for strategies that control the pointer.
+    pub fn send_pointer(&self, event: PointerEvent) {
+        self.platform_handle.send_pointer(event);
+    }
+
+    /// Send a media command
+    ///
+    /// Public API method for strategies that need direct media control.
+    pub fn send_media(&self, cmd: MediaCommand) {
+        self.platform_handle.send_media(cmd);
+    }
+
+    /// Get a reference to the bound action
+    pub fn action(&self) -> &Action {
+        &self.action
+    }
+
+    /// Get a clone of the platform handle for spawning async tasks
+    pub fn platform_handle(&self) -> PlatformHandle {
+        self.platform_handle.clone()
+    }
+
+    /// The current time on the handle's clock. Event times are what holds and
+    /// taps are measured from; this is for moments that have no event.
+    pub fn now(&self) -> Instant {
+        self.platform_handle.now()
+    }
+
+    /// The clock to wait on in spawned tasks, instead of `tokio::time`
+    pub fn clock(&self) -> Arc<dyn Clock> {
+        self.platform_handle.clock()
+    }
+}
@@ -0,0 +1,505 @@
+//! Mouse keys strategy (pointer control from the keyboard)
+//!
+//! Maps keys to pointer movement, buttons and scrolling. Holding a movement key
+//! moves the pointer at `speed` pixels per second, accelerating linearly to
+//! `max_speed` over `accel_ms` of holding; movement keys combine, so up and
+//! left together move diagonally. Button keys hold their button for as long
+//! as they are held, so dragging works, and scroll keys scroll a notch on
+//! press, then keep scrolling while held.
+//!
+//! With a `toggle` key the mapped keys only control the pointer while mouse
+//! keys is switched on, and type normally otherwise.
+
+use crate::key::{InputEvent, InputEventId, KeyCode};
+use crate::platform::{EventResponse, PointerButton, PointerEvent};
+use crate::strategy::{KeyStrategy, PlatformHandle, StrategyContext};
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use tokio::sync::{oneshot, watch};
+use tracing::debug;
+
+/// How often a moving pointer is stepped
+const MOVE_TICK: Duration = Duration::from_millis(16);
+
+/// Delay before a held scroll key starts repeating
+const SCROLL_DELAY: Duration = Duration::from_millis(300);
+
+/// Interval between notches while a scroll key repeats
+const SCROLL_INTERVAL: Duration = Duration::from_millis(60);
+
+/// What a mapped key does to the pointer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseControl {
+    /// Move while held, by this many steps right (x) and down (y)
+    Move { x: i32, y: i32 },
+    /// Hold a button while held
+    Button(PointerButton),
+    /// Scroll a notch, then repeat while held; positive is right (x) or up (y)
+    Scroll { x: i32, y: i32 },
+}
+
+impl MouseControl {
+    /// Every control name, for error messages
+    pub const NAMES: &[&str] = &[
+        "up",
+        "down",
+        "left",
+        "right",
+        "up_left",
+        "up_right",
+        "down_left",
+        "down_right",
+        "left_button",
+        "right_button",
+        "middle_button",
+        "scroll_up",
+        "scroll_down",
+        "scroll_left",
+        "scroll_right",
+    ];
+}
+
+impl FromStr for MouseControl {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let control = match s {
+            "up" => Self::Move { x: 0, y: -1 },
+            "down" => Self::Move { x: 0, y: 1 },
+            "left" => Self::Move { x: -1, y: 0 },
+            "right" => Self::Move { x: 1, y: 0 },
+            "up_left" => Self::Move { x: -1, y: -1 },
+            "up_right" => Self::Move { x: 1, y: -1 },
+            "down_left" => Self::Move { x: -1, y: 1 },
+            "down_right" => Self::Move { x: 1, y: 1 },
+            "left_button" => Self::Button(PointerButton::Left),
+            "right_button" => Self::Button(PointerButton::Right),
+            "middle_button" => Self::Button(PointerButton::Middle),
+            "scroll_up" => Self::Scroll { x: 0, y: 1 },
+            "scroll_down" => Self::Scroll { x: 0, y: -1 },
+            "scroll_left" => Self::Scroll { x: -1, y: 0 },
+            "scroll_right" => Self::Scroll { x: 1, y: 0 },
+            _ => {
+                return Err(format!(
+                    "unknown pointer control '{s}' (expected one of: {})",
+                    Self::NAMES.join(", ")
+                ));
+            }
+        };
+        Ok(control)
+    }
+}
+
+/// Configuration for keyboard pointer control
+#[derive(Debug, Clone)]
+pub struct MouseKeysConfig {
+    /// What each mapped key does
+    pub keys: HashMap<KeyCode, MouseControl>,
+    /// Key that switches mouse keys on and off; always on without one
+    pub toggle: Option<KeyCode>,
+    /// Pointer speed when a movement key is first pressed (pixels per second)
+    pub speed: u32,
+    /// Speed reached after `accel_ms` of holding (pixels per second)
+    pub max_speed: u32,
+    /// Time over which the speed rises from `speed` to `max_speed` (ms)
+    pub accel_ms: u64,
+}
+
+impl MouseKeysConfig {
+    /// Pointer speed in pixels per second after moving for `moving`
+    fn speed_at(&self, moving: Duration) -> f64 {
+        let (start, max) = (
+            f64::from(self.speed),
+            f64::from(self.max_speed.max(self.speed)),
+        );
+        if self.accel_ms == 0 {
+            return max;
+        }
+        let ramp = (moving.as_secs_f64() * 1000.0 / self.accel_ms as f64).min(1.0);
+        start + (max - start) * ramp
+    }
+}
+
+/// Mouse keys strategy implementation
+pub struct MouseKeysStrategy {
+    config: MouseKeysConfig,
+    /// Whether the mapped keys control the pointer
+    enabled: bool,
+    /// Mapped keys pressed while enabled; their releases are blocked too
+    pressed: HashSet<KeyCode>,
+    /// Direction for the running movement task. Dropping it stops the task.
+    motion: Option<watch::Sender<(i32, i32)>>,
+    /// Scroll keys repeating, with a cancel sender for their repeat task
+    scrolling: HashMap<KeyCode, oneshot::Sender<()>>,
+    /// Buttons held down by keys, released with them
+    buttons: HashMap<KeyCode, PointerButton>,
+}
+
+impl MouseKeysStrategy {
+    /// Create a new mouse keys strategy with the given configuration
+    pub fn new(config: MouseKeysConfig) -> Self {
+        Self {
+            enabled: config.toggle.is_none(),
+            config,
+            pressed: HashSet::new(),
+            motion: None,
+            scrolling: HashMap::new(),
+            buttons: HashMap::new(),
+        }
+    }
+
+    /// Flip mouse keys on or off, letting go of everything when it turns off
+    fn toggle(&mut self, platform: &PlatformHandle) {
+        self.enabled = !self.enabled;
+        debug!(enabled = self.enabled, "mouse keys: toggled");
+        if !self.enabled {
+            self.release_all(platform);
+        }
+    }
+
+    /// Stop moving and scrolling, and release every held button
+    fn release_all(&mut self, platform: &PlatformHandle) {
+        self.pressed.clear();
+        self.motion = None;
+        for (_, cancel_tx) in self.scrolling.drain() {
+            let _ = cancel_tx.send(());
+        }
+        for (_, button) in self.buttons.drain() {
+            platform.send_pointer(PointerEvent::Button {
+                button,
+                down: false,
+            });
+        }
+    }
+
+    /// Combined direction of the movement keys held, one step at most per axis
+    fn direction(&self) -> (i32, i32) {
+        let (x, y) = self
+            .pressed
+            .iter()
+            .filter_map(|key| match self.config.keys.get(key) {
+                Some(MouseControl::Move { x, y }) => Some((*x, *y)),
+                _ => None,
+            })
+            .fold((0, 0), |(ax, ay), (x, y)| (ax + x, ay + y));
+        (x.signum(), y.signum())
+    }
+
+    /// Start, steer or stop the movement task to match the keys held
+    fn update_motion(&mut self, time: Instant, platform: &PlatformHandle) {
+        let direction = self.direction();
+        if direction == (0, 0) {
+            self.motion = None;
+            return;
+        }
+        if let Some(motion) = &self.motion {
+            motion.send_replace(direction);
+            return;
+        }
+
+        let (motion_tx, motion_rx) = watch::channel(direction);
+        tokio::spawn(move_pointer(
+            self.config.clone(),
+            time,
+            motion_rx,
+            platform.clone(),
+        ));
+        self.motion = Some(motion_tx);
+    }
+
+    /// Handle key-down event
+    fn key_down(&mut self, key: KeyCode, time: Instant, ctx: &StrategyContext) -> EventResponse {
+        let platform = ctx.platform_handle();
+        if Some(key) == self.config.toggle {
+            self.toggle(&platform);
+            return EventResponse::Block;
+        }
+        let Some(&control) = self.config.keys.get(&key).filter(|_| self.enabled) else {
+            return EventResponse::Passthrough;
+        };
+        // OS key repeat of a key already in effect
+        if !self.pressed.insert(key) {
+            return EventResponse::Block;
+        }
+
+        match control {
+            MouseControl::Move { .. } => self.update_motion(time, &platform),
+            MouseControl::Button(button) => {
+                platform.send_pointer(PointerEvent::Button { button, down: true });
+                self.buttons.insert(key, button);
+            }
+            MouseControl::Scroll { x, y } => {
+                platform.send_pointer(PointerEvent::Scroll { dx: x, dy: y });
+                let (cancel_tx, cancel_rx) = oneshot::channel();
+                tokio::spawn(repeat_scroll(x, y, cancel_rx, platform));
+                self.scrolling.insert(key, cancel_tx);
+            }
+        }
+        EventResponse::Block
+    }
+
+    /// Handle key-up event
+    fn key_up(&mut self, key: KeyCode, time: Instant, ctx: &StrategyContext) -> EventResponse {
+        if Some(key) == self.config.toggle {
+            return EventResponse::Block;
+        }
+        // Pressed before mouse keys was switched on, or before it was reset
+        if !self.pressed.remove(&key) {
+            return EventResponse::Passthrough;
+        }
+
+        let platform = ctx.platform_handle();
+        if let Some(button) = self.buttons.remove(&key) {
+            platform.send_pointer(PointerEvent::Button {
+                button,
+                down: false,
+            });
+        }
+        if let Some(cancel_tx) = self.scrolling.remove(&key) {
+            let _ = cancel_tx.send(());
+        }
+        self.update_motion(time, &platform);
+        EventResponse::Block
+    }
+}
+
+/// Step the pointer in the direction `motion` holds until its sender is dropped.
+/// The speed ramps up from `started`, the press that set the pointer moving.
+async fn move_pointer(
+    config: MouseKeysConfig,
+    started: Instant,
+    motion: watch::Receiver<(i32, i32)>,
+    platform: PlatformHandle,
+) {
+    let clock = platform.clock();
+    let mut last = clock.now();
+    // Fractions of a pixel carried over to the next step
+    let mut carry = (0.0, 0.0);
+    loop {
+        clock.sleep(MOVE_TICK).await;
+        if motion.has_changed().is_err() {
+            debug!("mouse keys: pointer stopped");
+            break;
+        }
+        let (x, y) = *motion.borrow();
+
+        let now = clock.now();
+        let distance = config.speed_at(now - started) * (now - last).as_secs_f64();
+        last = now;
+        carry.0 += f64::from(x) * distance;
+        carry.1 += f64::from(y) * distance;
+        let (dx, dy) = (carry.0.trunc(), carry.1.trunc());
+        carry = (carry.0 - dx, carry.1 - dy);
+        if dx != 0.0 || dy != 0.0 {
+            platform.send_pointer(PointerEvent::Move {
+                dx: dx as i32,
+                dy: dy as i32,
+            });
+        }
+    }
+}
+
+/// Keep scrolling by (`x`, `y`) notches until cancelled
+async fn repeat_scroll(
+    x: i32,
+    y: i32,
+    mut cancel_rx: oneshot::Receiver<()>,
+    platform: PlatformHandle,
+) {
+    let clock = platform.clock();
+    let mut delay = SCROLL_DELAY;
+    loop {
+        tokio::select! {
+            _ = clock.sleep(delay) => {
+                platform.send_pointer(PointerEvent::Scroll { dx: x, dy: y });
+            }
+            _ = &mut cancel_rx => break,
+        }
+        delay = SCROLL_INTERVAL;
+    }
+}
+
+impl KeyStrategy for MouseKeysStrategy {
+    fn subscriptions(&self) -> HashSet<InputEventId> {
+        self.config
+            .keys
+            .keys()
+            .chain(&self.config.toggle)
+            .map(|key| InputEventId::Key(*key))
+            .collect()
+    }
+
+    fn debug_state(&self) -> serde_json::Value {
+        let pressed: Vec<String> = self.pressed.iter().map(KeyCode::display_name).collect();
+        json!({ "enabled": self.enabled, "pressed": pressed })
+    }
+
+    fn reset(&mut self, ctx: &StrategyContext) {
+        debug!("mouse keys: reset");
+        self.release_all(&ctx.platform_handle());
+    }
+
+    /// Clicking another window moves focus; a drag in progress has to survive that
+    fn on_focus_change(&mut self, _window: &crate::config::WindowInfo, _ctx: &StrategyContext) {}
+
+    fn process(&mut self, event: &InputEvent, ctx: &StrategyContext) -> EventResponse {
+        match event {
+            InputEvent::Key(key_event) => {
+                if key_event.down {
+                    self.key_down(key_event.key, key_event.time, ctx)
+                } else {
+                    self.key_up(key_event.key, key_event.time, ctx)
+                }
+            }
+            // Not subscribed to anything else
+            InputEvent::MouseButton { .. } | InputEvent::Scroll { .. } | InputEvent::Divert(_) => {
+                EventResponse::Passthrough
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Action;
+    use crate::key::KeyEvent;
+    use crate::platform::MockPlatform;
+    use crate::platform::mock::PlatformCall;
+    use std::sync::Arc;
+
+    const UP: KeyCode = KeyCode::new(0x68);
+    const LEFT: KeyCode = KeyCode::new(0x64);
+    const CLICK: KeyCode = KeyCode::new(0x65);
+    const WHEEL: KeyCode = KeyCode::new(0x6B);
+    const TOGGLE: KeyCode = KeyCode::new(0x90);
+
+    fn test_config() -> MouseKeysConfig {
+        MouseKeysConfig {
+            keys: HashMap::from([
+                (UP, "up".parse().unwrap()),
+                (LEFT, "left".parse().unwrap()),
+                (CLICK, "left_button".parse().unwrap()),
+                (WHEEL, "scroll_down".parse().unwrap()),
+            ]),
+            toggle: None,
+            speed: 500,
+            max_speed: 1500,
+            accel_ms: 1000,
+        }
+    }
+
+    fn press(strategy: &mut MouseKeysStrategy, ctx: &StrategyContext, key: KeyCode, down: bool) {
+        let response = strategy.process(&InputEvent::Key(KeyEvent::new(key, down)), ctx);
+        assert_eq!(response, EventResponse::Block);
+    }
+
+    /// Total pointer motion recorded by `platform`
+    fn moved(platform: &MockPlatform) -> (i32, i32) {
+        platform
+            .calls()
+            .iter()
+            .fold((0, 0), |(x, y), call| match call {
+                PlatformCall::SendPointer(PointerEvent::Move { dx, dy }) => (x + dx, y + dy),
+                _ => (x, y),
+            })
+    }
+
+    #[test]
+    fn test_speed_accelerates() {
+        let config = test_config();
+        assert_eq!(config.speed_at(Duration::ZERO), 500.0);
+        assert_eq!(config.speed_at(Duration::from_millis(500)), 1000.0);
+        assert_eq!(config.speed_at(Duration::from_secs(5)), 1500.0);
+    }
+
+    #[test]
+    fn test_unknown_control() {
+        let err = "sideways".parse::<MouseControl>().unwrap_err();
+        assert!(err.contains("scroll_up"));
+    }
+
+    #[tokio::test]
+    async fn test_held_keys_move_diagonally() {
+        let mut strategy = MouseKeysStrategy::new(test_config());
+        let platform = Arc::new(MockPlatform::new());
+        let ctx = StrategyContext::new(PlatformHandle::from_mock(&platform), &Action::Block);
+
+        press(&mut strategy, &ctx, UP, true);
+        press(&mut strategy, &ctx, LEFT, true);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        press(&mut strategy, &ctx, UP, false);
+        press(&mut strategy, &ctx, LEFT, false);
+
+        let (x, y) = moved(&platform);
+        assert!(x < 0 && y < 0, "expected up-left motion, got ({x}, {y})");
+        assert_eq!(x, y);
+
+        // Movement stops with the keys
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(moved(&platform), (x, y));
+    }
+
+    #[tokio::test]
+    async fn test_buttons_and_scrolling() {
+        let mut strategy = MouseKeysStrategy::new(test_config());
+        let platform = Arc::new(MockPlatform::new());
+        let ctx = StrategyContext::new(PlatformHandle::from_mock(&platform), &Action::Block);
+
+        press(&mut strategy, &ctx, CLICK, true);
+        press(&mut strategy, &ctx, CLICK, true); // OS key repeat
+        press(&mut strategy, &ctx, CLICK, false);
+        press(&mut strategy, &ctx, WHEEL, true);
+        press(&mut strategy, &ctx, WHEEL, false);
+
+        let left = PointerButton::Left;
+        assert_eq!(
+            platform.calls(),
+            vec![
+                PlatformCall::SendPointer(PointerEvent::Button {
+                    button: left,
+                    down: true
+                }),
+                PlatformCall::SendPointer(PointerEvent::Button {
+                    button: left,
+                    down: false
+                }),
+                PlatformCall::SendPointer(PointerEvent::Scroll { dx: 0, dy: -1 }),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_toggle() {
+        let mut strategy = MouseKeysStrategy::new(MouseKeysConfig {
+            toggle: Some(TOGGLE),
+            ..test_config()
+        });
+        let platform = Arc::new(MockPlatform::new());
+        let ctx = StrategyContext::new(PlatformHandle::from_mock(&platform), &Action::Block);
+
+        // Off: mapped keys type normally
+        let event = InputEvent::Key(KeyEvent::new(CLICK, true));
+        assert_eq!(strategy.process(&event, &ctx), EventResponse::Passthrough);
+
+        press(&mut strategy, &ctx, TOGGLE, true);
+        press(&mut strategy, &ctx, TOGGLE, false);
+        // The release of a key pressed while off still reaches the OS
+        let event = InputEvent::Key(KeyEvent::new(CLICK, false));
+        assert_eq!(strategy.process(&event, &ctx), EventResponse::Passthrough);
+
+        // Switching off mid-drag lets go of the button
+        press(&mut strategy, &ctx, CLICK, true);
+        press(&mut strategy, &ctx, TOGGLE, true);
+        assert_eq!(
+            platform.calls().last(),
+            Some(&PlatformCall::SendPointer(PointerEvent::Button {
+                button: PointerButton::Left,
+                down: false
+            }))
+        );
+        platform.assert_call_count(2);
+    }
+}
@@ -0,0 +1,443 @@
+//! Dynamically loaded strategy plugins
+//!
+//! Third-party strategies ship as shared libraries (`.so` / `.dll`) placed in the
+//! plugins directory (`~/.config/rebinded/plugins` by default). Each library
+//! exports a single C ABI entry point:
+//!
+//! ```c
+//! const RebindedPluginDescriptor *rebinded_plugin_v1(void);
+//! ```
+//!
+//! The descriptor names the plugin and provides `create`/`destroy`/`process`
+//! callbacks. Once loaded, the plugin is registered in the
+//! [`StrategyRegistry`] and its name is usable as a strategy `type`:
+//!
+//! ```toml
+//! [strategies.mine]
+//! type = "my_plugin"   # name from the descriptor
+//! threshold = 3        # remaining fields are passed to `create` as TOML text
+//! ```
+//!
+//! Libraries are never unloaded; the process keeps them mapped until exit.
+//!
+//! Built with the `wasm` feature, the directory can also hold sandboxed
+//! WebAssembly modules (`.wasm`); see [`wasm`](super::wasm).
+
+use crate::key::{InputEvent, KeyCode, MouseButton};
+use crate::platform::EventResponse;
+use crate::strategy::{KeyStrategy, StrategyContext, StrategyRegistry};
+use anyhow::{Result, anyhow};
+use std::ffi::{CStr, CString, c_char, c_void};
+use std::path::Path;
+use tracing::{debug, info, warn};
+
+/// ABI version a plugin descriptor must report
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+
+/// Symbol every plugin library must export
+const PLUGIN_ENTRY_SYMBOL: &CStr = c"rebinded_plugin_v1";
+
+/// Input event passed to a plugin's `process` callback
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct PluginEvent {
+    /// Event kind: [`PluginEvent::KEY`], [`PluginEvent::SCROLL`] or
+    /// [`PluginEvent::MOUSE_BUTTON`]
+    pub kind: u32,
+    /// Platform-native key code; for mouse buttons 2 (middle), 8 (back) or
+    /// 9 (forward); 0 for scroll events
+    pub code: u32,
+    /// Key or button pressed (true) or released; for scroll, whether it scrolled up
+    pub down: bool,
+}
+
+impl PluginEvent {
+    pub const KEY: u32 = 0;
+    pub const SCROLL: u32 = 1;
+    pub const MOUSE_BUTTON: u32 = 2;
+
+    /// The plugin's view of an event. Diverted events are not shown to plugins.
+    pub(crate) fn from_event(event: &InputEvent) -> Option<Self> {
+        Some(match event {
+            InputEvent::Key(key_event) => Self {
+                kind: Self::KEY,
+                code: key_event.key.code(),
+                down: key_event.down,
+            },
+            InputEvent::MouseButton { button, down } => Self {
+                kind: Self::MOUSE_BUTTON,
+                code: match button {
+                    MouseButton::Middle => 2,
+                    MouseButton::Back => 8,
+                    MouseButton::Forward => 9,
+                },
+                down: *down,
+            },
+            InputEvent::Scroll { up } => Self {
+                kind: Self::SCROLL,
+                code: 0,
+                down: *up,
+            },
+            InputEvent::Divert(_) => return None,
+        })
+    }
+}
+
+/// Host services available to a plugin during a `process` call.
+///
+/// `ctx` is only valid for the duration of the call; plugins must not keep it.
+#[repr(C)]
+pub struct PluginHost {
+    pub ctx: *const c_void,
+    /// Execute the binding's action
+    pub execute_action: unsafe extern "C" fn(ctx: *const c_void),
+    /// Send a single press or release of a platform-native key
+    pub send_raw_key: unsafe extern "C" fn(ctx: *const c_void, code: u32, down: bool),
+}
+
+/// Response codes returned by a plugin's `process` callback.
+/// Unknown codes are treated as passthrough.
+pub const PLUGIN_PASSTHROUGH: u32 = 0;
+pub const PLUGIN_BLOCK: u32 = 1;
+/// See [`EventResponse::Continue`]
+pub const PLUGIN_CONTINUE: u32 = 2;
+
+/// Table of callbacks exported by a plugin library
+#[repr(C)]
+pub struct PluginDescriptor {
+    /// Must equal [`PLUGIN_ABI_VERSION`]
+    pub abi_version: u32,
+    /// NUL-terminated plugin name, used as the strategy `type`
+    pub name: *const c_char,
+    /// Create an instance from NUL-terminated TOML settings. Returns null on failure.
+    pub create: unsafe extern "C" fn(settings: *const c_char) -> *mut c_void,
+    /// Free an instance returned by `create`
+    pub destroy: unsafe extern "C" fn(instance: *mut c_void),
+    /// Handle an event, returning [`PLUGIN_PASSTHROUGH`], [`PLUGIN_BLOCK`] or
+    /// [`PLUGIN_CONTINUE`]
+    pub process: unsafe extern "C" fn(
+        instance: *mut c_void,
+        event: *const PluginEvent,
+        host: *const PluginHost,
+    ) -> u32,
+}
+
+// SAFETY: Descriptors are immutable static data inside loaded libraries
+unsafe impl Send for PluginDescriptor {}
+unsafe impl Sync for PluginDescriptor {}
+
+type PluginEntry = unsafe extern "C" fn() -> *const PluginDescriptor;
+
+/// Register a plugin descriptor as a strategy type under its own name
+///
+/// Fails if the descriptor reports a different ABI version or the name is taken.
+pub fn register(descriptor: &'static PluginDescriptor) -> Result<String> {
+    if descriptor.abi_version != PLUGIN_ABI_VERSION {
+        return Err(anyhow!(
+            "unsupported plugin ABI version {} (expected {PLUGIN_ABI_VERSION})",
+            descriptor.abi_version
+        ));
+    }
+    if descriptor.name.is_null() {
+        return Err(anyhow!("plugin descriptor has no name"));
+    }
+
+    // SAFETY: Non-null name is a NUL-terminated string per the ABI contract
+    let name = unsafe { CStr::from_ptr(descriptor.name) }
+        .to_str()
+        .map_err(|_| anyhow!("plugin name is not valid UTF-8"))?
+        .to_string();
+
+    StrategyRegistry::global().register(&name, move |settings| {
+        PluginStrategy::new(descriptor, &settings.to_string())
+    })?;
+    Ok(name)
+}
+
+/// Load every shared library (and, with the `wasm` feature, every WASM module)
+/// in `dir` and register its plugin
+///
+/// A missing directory is not an error. Plugins that fail to load are
+/// logged and skipped so one broken plugin doesn't stop the daemon.
+pub fn load_dir(dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        debug!(dir = %dir.display(), "no plugins directory");
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let loaded = match path.extension().and_then(|e| e.to_str()) {
+            Some(extension) if extension == std::env::consts::DLL_EXTENSION => {
+                load_library(&path).and_then(register)
+            }
+            #[cfg(feature = "wasm")]
+            Some("wasm") => super::wasm::load(&path),
+            #[cfg(not(feature = "wasm"))]
+            Some("wasm") => Err(anyhow!(
+                "WASM plugins need rebinded built with the `wasm` feature"
+            )),
+            _ => continue,
+        };
+
+        match loaded {
+            Ok(name) => info!(plugin = name, path = %path.display(), "loaded plugin"),
+            Err(e) => warn!(path = %path.display(), "failed to load plugin: {e}"),
+        }
+    }
+}
+
+/// Open a shared library and call its entry point
+#[cfg(unix)]
+fn load_library(path: &Path) -> Result<&'static PluginDescriptor> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes())?;
+
+    // SAFETY: Valid NUL-terminated path; the handle is intentionally leaked
+    let handle = unsafe { libc::dlopen(c_path.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+    if handle.is_null() {
+        // SAFETY: dlerror returns a NUL-terminated string or null
+        let err = unsafe { libc::dlerror() };
+        let reason = if err.is_null() {
+            "unknown error".into()
+        } else {
+            unsafe { CStr::from_ptr(err) }.to_string_lossy()
+        };
+        return Err(anyhow!("dlopen failed: {reason}"));
+    }
+
+    // SAFETY: Valid handle and symbol name
+    let symbol = unsafe { libc::dlsym(handle, PLUGIN_ENTRY_SYMBOL.as_ptr()) };
+    if symbol.is_null() {
+        return Err(anyhow!("missing entry point {PLUGIN_ENTRY_SYMBOL:?}"));
+    }
+
+    // SAFETY: The exported symbol has the PluginEntry signature per the ABI contract
+    let entry: PluginEntry = unsafe { std::mem::transmute(symbol) };
+    descriptor_from_entry(entry)
+}
+
+/// Open a shared library and call its entry point
+#[cfg(windows)]
+fn load_library(path: &Path) -> Result<&'static PluginDescriptor> {
+    use windows::Win32::System::LibraryLoader::{GetProcAddress, LoadLibraryW};
+    use windows::core::{HSTRING, PCSTR};
+
+    // SAFETY: Valid wide path; the module is intentionally never freed
+    let module = unsafe { LoadLibraryW(&HSTRING::from(path)) }
+        .map_err(|e| anyhow!("LoadLibrary failed: {e}"))?;
+
+    // SAFETY: Valid module and NUL-terminated symbol name
+    let symbol = unsafe { GetProcAddress(module, PCSTR(PLUGIN_ENTRY_SYMBOL.as_ptr() as _)) }
+        .ok_or_else(|| anyhow!("missing entry point {PLUGIN_ENTRY_SYMBOL:?}"))?;
+
+    // SAFETY: The exported symbol has the PluginEntry signature per the ABI contract
+    let entry: PluginEntry = unsafe { std::mem::transmute(symbol) };
+    descriptor_from_entry(entry)
+}
+
+fn descriptor_from_entry(entry: PluginEntry) -> Result<&'static PluginDescriptor> {
+    // SAFETY: Entry point returns a pointer to static data in the (never unloaded) library
+    let descriptor = unsafe { entry() };
+    if descriptor.is_null() {
+        return Err(anyhow!("entry point returned null"));
+    }
+    Ok(unsafe { &*descriptor })
+}
+
+/// Strategy backed by a plugin instance
+pub struct PluginStrategy {
+    descriptor: &'static PluginDescriptor,
+    instance: *mut c_void,
+}
+
+impl PluginStrategy {
+    /// Create a plugin instance with the given TOML settings
+    pub fn new(descriptor: &'static PluginDescriptor, settings: &str) -> Result<Self> {
+        let settings = CString::new(settings)?;
+
+        // SAFETY: Valid NUL-terminated settings string per the ABI contract
+        let instance = unsafe { (descriptor.create)(settings.as_ptr()) };
+        if instance.is_null() {
+            return Err(anyhow!("plugin rejected its settings"));
+        }
+
+        Ok(Self {
+            descriptor,
+            instance,
+        })
+    }
+}
+
+impl Drop for PluginStrategy {
+    fn drop(&mut self) {
+        // SAFETY: Instance came from this descriptor's `create` and is freed once
+        unsafe { (self.descriptor.destroy)(self.instance) };
+    }
+}
+
+unsafe extern "C" fn host_execute_action(ctx: *const c_void) {
+    // SAFETY: ctx points to the StrategyContext borrowed for the current call
+    let ctx = unsafe { &*(ctx as *const StrategyContext) };
+    ctx.execute();
+}
+
+unsafe extern "C" fn host_send_raw_key(ctx: *const c_void, code: u32, down: bool) {
+    // SAFETY: ctx points to the StrategyContext borrowed for the current call
+    let ctx = unsafe { &*(ctx as *const StrategyContext) };
+    ctx.send_raw_key(KeyCode::new(code), down);
+}
+
+impl KeyStrategy for PluginStrategy {
+    fn process(&mut self, event: &InputEvent, ctx: &StrategyContext) -> EventResponse {
+        let Some(plugin_event) = PluginEvent::from_event(event) else {
+            return EventResponse::Passthrough;
+        };
+        let host = PluginHost {
+            ctx: ctx as *const StrategyContext as *const c_void,
+            execute_action: host_execute_action,
+            send_raw_key: host_send_raw_key,
+        };
+
+        // SAFETY: Instance is live; event and host outlive the call
+        let response = unsafe { (self.descriptor.process)(self.instance, &plugin_event, &host) };
+        match response {
+            PLUGIN_BLOCK => EventResponse::Block,
+            PLUGIN_CONTINUE => EventResponse::Continue,
+            _ => EventResponse::Passthrough,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Action;
+    use crate::key::KeyEvent;
+    use crate::platform::mock::PlatformCall;
+    use crate::platform::{MediaCommand, MockPlatform};
+    use crate::strategy::PlatformHandle;
+    use std::sync::Arc;
+
+    /// Test plugin: blocks key 0x7C, executing the action on press and
+    /// tapping the key code given in its settings on release
+    unsafe extern "C" fn create(settings: *const c_char) -> *mut c_void {
+        let settings = unsafe { CStr::from_ptr(settings) }.to_str().unwrap();
+        let table: toml::Table = settings.parse().unwrap();
+        let Some(code) = table.get("tap").and_then(|v| v.as_integer()) else {
+            return std::ptr::null_mut();
+        };
+        Box::into_raw(Box::new(code as u32)) as *mut c_void
+    }
+
+    unsafe extern "C" fn destroy(instance: *mut c_void) {
+        drop(unsafe { Box::from_raw(instance as *mut u32) });
+    }
+
+    unsafe extern "C" fn process(
+        instance: *mut c_void,
+        event: *const PluginEvent,
+        host: *const PluginHost,
+    ) -> u32 {
+        let (tap, event, host) = unsafe { (*(instance as *const u32), &*event, &*host) };
+        if event.kind != PluginEvent::KEY || event.code != 0x7C {
+            return PLUGIN_PASSTHROUGH;
+        }
+        unsafe {
+            if event.down {
+                (host.execute_action)(host.ctx);
+            } else {
+                (host.send_raw_key)(host.ctx, tap, true);
+                (host.send_raw_key)(host.ctx, tap, false);
+            }
+        }
+        PLUGIN_BLOCK
+    }
+
+    static TEST_PLUGIN: PluginDescriptor = PluginDescriptor {
+        abi_version: PLUGIN_ABI_VERSION,
+        name: c"test_plugin".as_ptr(),
+        create,
+        destroy,
+        process,
+    };
+
+    #[tokio::test]
+    async fn test_plugin_strategy_round_trip() {
+        let mut strategy = PluginStrategy::new(&TEST_PLUGIN, "tap = 1").unwrap();
+        let platform = Arc::new(MockPlatform::new());
+        let ctx = StrategyContext::new(PlatformHandle::from_mock(&platform), &Action::MediaNext);
+
+        let key = KeyCode::new(0x7C);
+        let down = InputEvent::Key(KeyEvent::new(key, true));
+        let up = InputEvent::Key(KeyEvent::new(key, false));
+        assert!(strategy.process(&down, &ctx) == EventResponse::Block);
+        assert!(strategy.process(&up, &ctx) == EventResponse::Block);
+
+        let other = InputEvent::Key(KeyEvent::new(KeyCode::new(2), true));
+        assert!(strategy.process(&other, &ctx) == EventResponse::Passthrough);
+
+        assert_eq!(
+            platform.calls(),
+            vec![
+                PlatformCall::SendMedia(MediaCommand::Next),
+                PlatformCall::SendRawKey {
+                    key: KeyCode::new(1),
+                    down: true
+                },
+                PlatformCall::SendRawKey {
+                    key: KeyCode::new(1),
+                    down: false
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plugin_rejecting_settings() {
+        assert!(PluginStrategy::new(&TEST_PLUGIN, "").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_registered_plugin_usable_as_strategy_type() {
+        register(&TEST_PLUGIN).unwrap();
+        assert!(register(&TEST_PLUGIN).is_err(), "names must be unique");
+
+        let toml = r#"
+            [strategies.mine]
+            type = "test_plugin"
+            tap = 30
+
+            [bindings.0x7C]
+            action = "block"
+            strategy = "mine"
+        "#;
+        let (_, runtime) = crate::config::load_from_str("test.toml", toml.to_string())
+            .await
+            .unwrap();
+        assert!(runtime.strategies.contains_key("mine"));
+
+        // Settings the plugin rejects surface as config errors
+        let toml = r#"
+            [strategies.mine]
+            type = "test_plugin"
+        "#;
+        let result = crate::config::load_from_str("test.toml", toml.to_string()).await;
+        let msg = format!("{:?}", result.unwrap_err());
+        assert!(msg.contains("failed to initialize"));
+    }
+
+    #[test]
+    fn test_register_rejects_abi_mismatch() {
+        static OLD_PLUGIN: PluginDescriptor = PluginDescriptor {
+            abi_version: 0,
+            name: c"old_plugin".as_ptr(),
+            create,
+            destroy,
+            process,
+        };
+        assert!(register(&OLD_PLUGIN).is_err());
+        assert!(!StrategyRegistry::global().contains("old_plugin"));
+    }
+}
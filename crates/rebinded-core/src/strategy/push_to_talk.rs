@@ -0,0 +1,227 @@
+//! Push-to-talk strategy
+//!
+//! Talks for as long as the bound key is held. When one of the configured
+//! applications (Discord, Teams...) has focus, or failing that is running, its
+//! own push-to-talk key is held down instead of the bound key, so the app
+//! handles muting itself. Otherwise the default microphone is toggled on press
+//! and toggled back on release, which unmutes a muted microphone while held
+//! (or mutes an open one, push-to-mute).
+//!
+//! The choice is made on press and kept until release, even if focus moves
+//! in between.
+
+use crate::config::WindowCondition;
+use crate::key::{InputEvent, KeyCode};
+use crate::platform::{EventResponse, MicCommand};
+use crate::strategy::{KeyStrategy, StrategyContext};
+use serde_json::json;
+use std::collections::HashMap;
+use tracing::debug;
+
+/// An application with a push-to-talk key of its own
+#[derive(Debug, Clone)]
+pub struct PushToTalkApp {
+    /// Matched against the focused window; its `binary` pattern is also
+    /// matched against running processes
+    pub window: WindowCondition,
+    /// The application's push-to-talk key
+    pub key: KeyCode,
+}
+
+/// Configuration for push-to-talk
+#[derive(Debug, Clone)]
+pub struct PushToTalkConfig {
+    /// Applications checked in order, focused ones before running ones
+    pub apps: Vec<PushToTalkApp>,
+}
+
+/// How a held key is talking
+#[derive(Debug, Clone, Copy)]
+enum Talking {
+    /// Holding an application's push-to-talk key
+    Key(KeyCode),
+    /// Toggled the microphone; toggled back on release
+    Mic,
+}
+
+/// Push-to-talk strategy implementation
+pub struct PushToTalkStrategy {
+    config: PushToTalkConfig,
+    /// Bound keys currently held, and how each is talking
+    talking: HashMap<KeyCode, Talking>,
+}
+
+impl PushToTalkStrategy {
+    /// Create a new push-to-talk strategy with the given configuration
+    pub fn new(config: PushToTalkConfig) -> Self {
+        Self {
+            config,
+            talking: HashMap::new(),
+        }
+    }
+
+    /// The push-to-talk key of the focused application, or else of a running one
+    fn app_key(&self, ctx: &StrategyContext) -> Option<KeyCode> {
+        let window = ctx.window_info();
+        let focused = self
+            .config
+            .apps
+            .iter()
+            .find(|app| app.window.matches(&window));
+        let platform = ctx.platform_handle();
+        focused
+            .or_else(|| {
+                self.config.apps.iter().find(|app| {
+                    app.window
+                        .binary
+                        .as_deref()
+                        .is_some_and(|binary| platform.process_running(binary))
+                })
+            })
+            .map(|app| app.key)
+    }
+
+    /// Handle key-down event
+    fn key_down(&mut self, key: KeyCode, ctx: &StrategyContext) -> EventResponse {
+        // OS key repeat while already talking
+        if self.talking.contains_key(&key) {
+            return EventResponse::Block;
+        }
+
+        let talking = match self.app_key(ctx) {
+            Some(app_key) => {
+                debug!(%app_key, "push_to_talk: holding app key");
+                ctx.send_raw_key(app_key, true);
+                Talking::Key(app_key)
+            }
+            None => {
+                debug!("push_to_talk: toggling microphone");
+                ctx.platform_handle().set_mic(MicCommand::Toggle);
+                Talking::Mic
+            }
+        };
+        self.talking.insert(key, talking);
+        EventResponse::Block
+    }
+
+    /// Stop talking
+    fn stop(talking: Talking, ctx: &StrategyContext) {
+        match talking {
+            Talking::Key(app_key) => ctx.send_raw_key(app_key, false),
+            Talking::Mic => ctx.platform_handle().set_mic(MicCommand::Toggle),
+        }
+    }
+}
+
+impl KeyStrategy for PushToTalkStrategy {
+    fn debug_state(&self) -> serde_json::Value {
+        let talking: Vec<String> = self.talking.keys().map(KeyCode::display_name).collect();
+        json!({ "talking": talking })
+    }
+
+    fn reset(&mut self, ctx: &StrategyContext) {
+        debug!("push_to_talk: reset");
+        for (_, talking) in self.talking.drain() {
+            Self::stop(talking, ctx);
+        }
+    }
+
+    /// Talking started in one application keeps going when focus moves away
+    fn on_focus_change(&mut self, _window: &crate::config::WindowInfo, _ctx: &StrategyContext) {}
+
+    fn process(&mut self, event: &InputEvent, ctx: &StrategyContext) -> EventResponse {
+        match event {
+            InputEvent::Key(key_event) => {
+                if key_event.down {
+                    self.key_down(key_event.key, ctx)
+                } else {
+                    if let Some(talking) = self.talking.remove(&key_event.key) {
+                        Self::stop(talking, ctx);
+                    }
+                    EventResponse::Block
+                }
+            }
+            // Not subscribed to anything else
+            InputEvent::MouseButton { .. } | InputEvent::Scroll { .. } | InputEvent::Divert(_) => {
+                EventResponse::Passthrough
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Action, WindowInfo};
+    use crate::key::KeyEvent;
+    use crate::platform::MockPlatform;
+    use crate::platform::mock::PlatformCall;
+    use crate::strategy::PlatformHandle;
+    use std::sync::Arc;
+
+    const PTT: KeyCode = KeyCode::new(0x83);
+    const DISCORD_KEY: KeyCode = KeyCode::new(0x7C);
+    const TEAMS_KEY: KeyCode = KeyCode::new(0x7D);
+
+    fn test_config() -> PushToTalkConfig {
+        let app = |binary: &str, key| PushToTalkApp {
+            window: WindowCondition {
+                binary: Some(binary.to_string()),
+                ..Default::default()
+            },
+            key,
+        };
+        PushToTalkConfig {
+            apps: vec![app("Discord*", DISCORD_KEY), app("ms-teams*", TEAMS_KEY)],
+        }
+    }
+
+    /// Press and release the bound key, returning the platform calls made
+    fn talk(platform: MockPlatform) -> Vec<PlatformCall> {
+        let mut strategy = PushToTalkStrategy::new(test_config());
+        let platform = Arc::new(platform);
+        let ctx = StrategyContext::new(PlatformHandle::from_mock(&platform), &Action::Block);
+        for down in [true, true, false] {
+            let event = InputEvent::Key(KeyEvent::new(PTT, down));
+            assert_eq!(strategy.process(&event, &ctx), EventResponse::Block);
+        }
+        platform.calls()
+    }
+
+    fn held(key: KeyCode) -> Vec<PlatformCall> {
+        vec![
+            PlatformCall::SendRawKey { key, down: true },
+            PlatformCall::SendRawKey { key, down: false },
+        ]
+    }
+
+    #[test]
+    fn test_focused_app_gets_its_key() {
+        // Both running, Teams focused
+        let platform = MockPlatform::new();
+        platform.set_running(&["Discord", "ms-teams"]);
+        platform.set_window(WindowInfo {
+            binary: "ms-teams.exe".to_string(),
+            ..Default::default()
+        });
+        assert_eq!(talk(platform), held(TEAMS_KEY));
+    }
+
+    #[test]
+    fn test_running_app_gets_its_key() {
+        let platform = MockPlatform::new();
+        platform.set_running(&["Discord"]);
+        assert_eq!(talk(platform), held(DISCORD_KEY));
+    }
+
+    #[test]
+    fn test_no_app_toggles_mic() {
+        assert_eq!(
+            talk(MockPlatform::new()),
+            vec![
+                PlatformCall::SetMic(MicCommand::Toggle),
+                PlatformCall::SetMic(MicCommand::Toggle),
+            ]
+        );
+    }
+}
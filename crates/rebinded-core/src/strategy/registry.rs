@@ -0,0 +1,173 @@
+//! Strategy types registered at runtime
+//!
+//! The built-in strategy types (`gated_hold`, `tap_hold`, ...) are part of the
+//! config schema. Any other `type` is looked up in the [`StrategyRegistry`]:
+//! library users register a constructor under a type name, and loaded plugins
+//! register themselves the same way. Registration must happen before the config
+//! that uses the type is loaded.
+//!
+//! ```
+//! use rebinded_core::platform::EventResponse;
+//! use rebinded_core::key::InputEvent;
+//! use rebinded_core::strategy::{KeyStrategy, StrategyContext, StrategyRegistry};
+//!
+//! struct Swallow;
+//!
+//! impl KeyStrategy for Swallow {
+//!     fn process(&mut self, _event: &InputEvent, _ctx: &StrategyContext) -> EventResponse {
+//!         EventResponse::Block
+//!     }
+//! }
+//!
+//! StrategyRegistry::global()
+//!     .register("swallow", |_settings| Ok(Swallow))
+//!     .unwrap();
+//! ```
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::{Arc, LazyLock, RwLock};
+
+use anyhow::{Result, anyhow};
+
+use crate::strategy::KeyStrategy;
+
+/// Strategy types built into the config schema, which can't be registered
+const BUILTIN_TYPES: &[&str] = &[
+    "gated_hold",
+    "tap_hold",
+    "repeat",
+    "combo",
+    "leader",
+    "debounce",
+    "mouse_keys",
+    "mirror",
+    "push_to_talk",
+    "script",
+];
+
+/// Creates a strategy from its config table
+type Factory = dyn Fn(&toml::Table) -> Result<Rc<RefCell<dyn KeyStrategy>>> + Send + Sync;
+
+/// Constructors for strategy types, keyed by the name used as `type`
+pub struct StrategyRegistry {
+    factories: RwLock<HashMap<String, Arc<Factory>>>,
+}
+
+static REGISTRY: LazyLock<StrategyRegistry> = LazyLock::new(|| StrategyRegistry {
+    factories: RwLock::new(HashMap::new()),
+});
+
+impl StrategyRegistry {
+    /// The registry config loading consults
+    pub fn global() -> &'static Self {
+        &REGISTRY
+    }
+
+    /// Register `factory` as the constructor for strategies of type `name`
+    ///
+    /// The factory receives the strategy's table without `type` and
+    /// `condition`; an error it returns is reported as a config error. Fails if
+    /// `name` is a built-in type or already registered.
+    pub fn register<S, F>(&self, name: &str, factory: F) -> Result<()>
+    where
+        S: KeyStrategy + 'static,
+        F: Fn(&toml::Table) -> Result<S> + Send + Sync + 'static,
+    {
+        if BUILTIN_TYPES.contains(&name) {
+            return Err(anyhow!("'{name}' is a built-in strategy type"));
+        }
+        let mut factories = self.factories.write().unwrap();
+        if factories.contains_key(name) {
+            return Err(anyhow!("strategy type '{name}' is already registered"));
+        }
+        let factory: Arc<Factory> = Arc::new(move |settings| {
+            let strategy: Rc<RefCell<dyn KeyStrategy>> = Rc::new(RefCell::new(factory(settings)?));
+            Ok(strategy)
+        });
+        factories.insert(name.to_string(), factory);
+        Ok(())
+    }
+
+    /// Whether a strategy type named `name` is registered
+    pub fn contains(&self, name: &str) -> bool {
+        self.factories.read().unwrap().contains_key(name)
+    }
+
+    /// Create a strategy of type `name`, or `None` if no such type is registered
+    pub(crate) fn build(
+        &self,
+        name: &str,
+        settings: &toml::Table,
+    ) -> Option<Result<Rc<RefCell<dyn KeyStrategy>>>> {
+        // Cloned out so a factory may itself use the registry
+        let factory = self.factories.read().unwrap().get(name).cloned()?;
+        Some(factory(settings))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key::InputEvent;
+    use crate::platform::EventResponse;
+    use crate::strategy::StrategyContext;
+
+    /// Blocks every event
+    struct Swallow;
+
+    impl KeyStrategy for Swallow {
+        fn process(&mut self, _event: &InputEvent, _ctx: &StrategyContext) -> EventResponse {
+            EventResponse::Block
+        }
+    }
+
+    /// Refuses settings without `enabled = true`
+    fn swallow(settings: &toml::Table) -> Result<Swallow> {
+        match settings.get("enabled") {
+            Some(toml::Value::Boolean(true)) => Ok(Swallow),
+            _ => Err(anyhow!("swallow must be enabled")),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_registered_type_usable_in_config() {
+        StrategyRegistry::global()
+            .register("test_swallow", swallow)
+            .unwrap();
+
+        let toml = r#"
+            [strategies.mine]
+            type = "test_swallow"
+            enabled = true
+
+            [bindings.0x7C]
+            action = "block"
+            strategy = "mine"
+        "#;
+        let (_, runtime) = crate::config::load_from_str("test.toml", toml.to_string())
+            .await
+            .unwrap();
+        assert!(runtime.strategies.contains_key("mine"));
+
+        // Factory errors surface as config errors
+        let toml = r#"
+            [strategies.mine]
+            type = "test_swallow"
+        "#;
+        let result = crate::config::load_from_str("test.toml", toml.to_string()).await;
+        let msg = format!("{:?}", result.unwrap_err());
+        assert!(msg.contains("swallow must be enabled"));
+    }
+
+    #[test]
+    fn test_register_rejects_taken_names() {
+        let registry = StrategyRegistry::global();
+        assert!(registry.register("tap_hold", swallow).is_err());
+        assert!(!registry.contains("tap_hold"));
+
+        registry.register("test_unique", swallow).unwrap();
+        assert!(registry.register("test_unique", swallow).is_err());
+    }
+}
@@ -0,0 +1,252 @@
+//! Accelerating repeat strategy
+//!
+//! Executes the bound action on press, then keeps repeating it while the key is
+//! held. The repeat interval starts at `start_interval_ms` and shrinks linearly to
+//! `min_interval_ms` over `ramp_ms` of holding, so short presses stay precise
+//! (one step per tap) while long holds move quickly — useful for volume and seek.
+
+use crate::key::{InputEvent, KeyCode};
+use crate::platform::EventResponse;
+use crate::strategy::{KeyStrategy, StrategyContext};
+use serde_json::json;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+use tracing::debug;
+
+/// Configuration for accelerating repeat behavior
+#[derive(Debug, Clone)]
+pub struct RepeatConfig {
+    /// Delay before the first repeat, and the interval at the start of the ramp (ms)
+    pub start_interval_ms: u64,
+    /// Fastest interval reached once the ramp completes (ms)
+    pub min_interval_ms: u64,
+    /// How long the key must be held to go from start to min interval (ms)
+    pub ramp_ms: u64,
+}
+
+impl RepeatConfig {
+    /// Interval before the next repeat, given how long the key has been held
+    fn interval_at(&self, held: Duration) -> Duration {
+        let start = self.start_interval_ms;
+        let min = self.min_interval_ms.min(start);
+
+        if self.ramp_ms == 0 {
+            return Duration::from_millis(min);
+        }
+
+        let held_ms = (held.as_millis() as u64).min(self.ramp_ms);
+        Duration::from_millis(start - (start - min) * held_ms / self.ramp_ms)
+    }
+}
+
+/// Accelerating repeat strategy implementation
+pub struct RepeatStrategy {
+    config: RepeatConfig,
+    /// Keys currently repeating, with a cancel sender for their repeat task.
+    /// Absence from the map means the key is idle.
+    repeating: HashMap<KeyCode, oneshot::Sender<()>>,
+}
+
+impl RepeatStrategy {
+    /// Create a new repeat strategy with the given configuration
+    pub fn new(config: RepeatConfig) -> Self {
+        Self {
+            config,
+            repeating: HashMap::new(),
+        }
+    }
+
+    /// Handle key-down event
+    fn key_down(&mut self, key: KeyCode, time: Instant, ctx: &StrategyContext) -> EventResponse {
+        // OS key repeat while already repeating - our own task drives the rate
+        if self.repeating.contains_key(&key) {
+            return EventResponse::Block;
+        }
+
+        debug!(?key, "repeat: idle -> repeating");
+        ctx.execute();
+
+        let (cancel_tx, mut cancel_rx) = oneshot::channel();
+        let config = self.config.clone();
+        let action = ctx.action().clone();
+        let platform_handle = ctx.platform_handle();
+
+        tokio::spawn(async move {
+            let clock = platform_handle.clock();
+            // The ramp-up is measured from the physical press
+            let pressed_at = time;
+            loop {
+                let interval =
+                    config.interval_at(clock.now().saturating_duration_since(pressed_at));
+                tokio::select! {
+                    _ = clock.sleep(interval) => {
+                        platform_handle.execute(&action);
+                    }
+                    _ = &mut cancel_rx => {
+                        debug!("repeat: repeat task cancelled");
+                        break;
+                    }
+                }
+            }
+        });
+
+        self.repeating.insert(key, cancel_tx);
+        EventResponse::Block
+    }
+
+    /// Handle key-up event
+    fn key_up(&mut self, key: KeyCode) -> EventResponse {
+        if let Some(cancel_tx) = self.repeating.remove(&key) {
+            debug!(?key, "repeat: repeating -> idle");
+            let _ = cancel_tx.send(());
+        }
+        EventResponse::Block
+    }
+}
+
+impl KeyStrategy for RepeatStrategy {
+    fn debug_state(&self) -> serde_json::Value {
+        let repeating: Vec<String> = self.repeating.keys().map(KeyCode::display_name).collect();
+        json!({ "repeating": repeating })
+    }
+
+    fn reset(&mut self, _ctx: &StrategyContext) {
+        debug!("repeat: reset");
+        for (_, cancel_tx) in self.repeating.drain() {
+            let _ = cancel_tx.send(());
+        }
+    }
+
+    fn process(&mut self, event: &InputEvent, ctx: &StrategyContext) -> EventResponse {
+        match event {
+            InputEvent::Key(key_event) => {
+                if key_event.down {
+                    self.key_down(key_event.key, key_event.time, ctx)
+                } else {
+                    self.key_up(key_event.key)
+                }
+            }
+            // Not subscribed to anything else
+            InputEvent::MouseButton { .. } | InputEvent::Scroll { .. } | InputEvent::Divert(_) => {
+                EventResponse::Passthrough
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Action;
+    use crate::key::KeyEvent;
+    use crate::platform::mock::PlatformCall;
+    use crate::platform::{MediaCommand, MockPlatform};
+    use crate::strategy::PlatformHandle;
+    use std::sync::Arc;
+
+    fn test_config() -> RepeatConfig {
+        RepeatConfig {
+            start_interval_ms: 300,
+            min_interval_ms: 50,
+            ramp_ms: 1000,
+        }
+    }
+
+    #[test]
+    fn test_interval_ramps_linearly() {
+        let config = test_config();
+        assert_eq!(
+            config.interval_at(Duration::ZERO),
+            Duration::from_millis(300)
+        );
+        assert_eq!(
+            config.interval_at(Duration::from_millis(500)),
+            Duration::from_millis(175)
+        );
+        assert_eq!(
+            config.interval_at(Duration::from_millis(1000)),
+            Duration::from_millis(50)
+        );
+    }
+
+    #[test]
+    fn test_interval_clamps_after_ramp() {
+        let config = test_config();
+        assert_eq!(
+            config.interval_at(Duration::from_secs(10)),
+            Duration::from_millis(50)
+        );
+    }
+
+    #[test]
+    fn test_zero_ramp_uses_min_interval() {
+        let config = RepeatConfig {
+            ramp_ms: 0,
+            ..test_config()
+        };
+        assert_eq!(
+            config.interval_at(Duration::ZERO),
+            Duration::from_millis(50)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tap_executes_once() {
+        let mut strategy = RepeatStrategy::new(test_config());
+        let platform = Arc::new(MockPlatform::new());
+        let ctx = StrategyContext::new(PlatformHandle::from_mock(&platform), &Action::VolumeUp);
+
+        let key = KeyCode::new(0x7C);
+        strategy.process(&InputEvent::Key(KeyEvent::new(key, true)), &ctx);
+        strategy.process(&InputEvent::Key(KeyEvent::new(key, false)), &ctx);
+
+        tokio::time::sleep(Duration::from_millis(350)).await;
+        platform.assert_media_sent(MediaCommand::VolumeUp);
+        platform.assert_call_count(1);
+        assert!(strategy.repeating.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_notification_follows_each_execution() {
+        let mut strategy = RepeatStrategy::new(test_config());
+        let platform = Arc::new(MockPlatform::new());
+        let ctx = StrategyContext::new(PlatformHandle::from_mock(&platform), &Action::VolumeUp)
+            .with_notification(Some("Volume up".to_string()));
+
+        let key = KeyCode::new(0x7C);
+        strategy.process(&InputEvent::Key(KeyEvent::new(key, true)), &ctx);
+        strategy.process(&InputEvent::Key(KeyEvent::new(key, false)), &ctx);
+
+        assert_eq!(
+            platform.calls(),
+            vec![
+                PlatformCall::SendMedia(MediaCommand::VolumeUp),
+                PlatformCall::Notify("Volume up".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hold_repeats_until_release() {
+        let mut strategy = RepeatStrategy::new(RepeatConfig {
+            start_interval_ms: 20,
+            min_interval_ms: 20,
+            ramp_ms: 0,
+        });
+        let platform = Arc::new(MockPlatform::new());
+        let ctx = StrategyContext::new(PlatformHandle::from_mock(&platform), &Action::VolumeUp);
+
+        let key = KeyCode::new(0x7C);
+        strategy.process(&InputEvent::Key(KeyEvent::new(key, true)), &ctx);
+        tokio::time::sleep(Duration::from_millis(110)).await;
+        strategy.process(&InputEvent::Key(KeyEvent::new(key, false)), &ctx);
+
+        let count = platform.calls().len();
+        assert!(count >= 3, "expected several repeats, got {count}");
+
+        // No more repeats after release
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        platform.assert_call_count(count);
+    }
+}
@@ -0,0 +1,545 @@
+//! Script strategy: key handling written in Rhai
+//!
+//! With the `script` feature, `type = "script"` hands a binding's key events to
+//! the script in `file`, resolved against the config file that names it:
+//!
+//! ```toml
+//! [strategies.caps]
+//! type = "script"
+//! file = "caps.rhai"
+//! hold_ms = 200        # other fields are the script's `this`
+//! ```
+//!
+//! ```rhai
+//! fn on_key_down(key, time) {
+//!     set_timer(1, this.hold_ms);
+//!     "block"
+//! }
+//!
+//! fn on_key_up(key, time) {
+//!     cancel_timer(1);
+//!     if this.held ?? false { release(key("leftctrl")); this.held = false; } else { execute(); }
+//!     "block"
+//! }
+//!
+//! fn on_timer(id) {
+//!     press(key("leftctrl"));
+//!     this.held = true;
+//! }
+//! ```
+//!
+//! The script defines any of these functions:
+//!
+//! - `on_key_down(key, time)` / `on_key_up(key, time)`: a bound key was pressed
+//!   or released. `key` is its code and `time` when it happened, in milliseconds
+//!   since the first event the script handled. Returns `"block"`, `"continue"` or `"passthrough"`
+//!   (also what returning nothing means); see [`EventResponse`].
+//! - `on_timer(id)`: a timer set with `set_timer` fired
+//! - `on_reset()`: drop in-flight state, e.g. on a focus change
+//!
+//! State lives in `this`, which starts out as the strategy's other fields and
+//! keeps whatever the script adds. Top-level statements never run. Scripts can
+//! call:
+//!
+//! - `execute()`: execute the binding's action; `action()` names it
+//! - `key(name)`: the code of a key, by its config name
+//! - `press(code)`, `release(code)`, `tap(code)`: inject a key
+//! - `set_timer(id, ms)`: call `on_timer(id)` after `ms`, replacing any pending
+//!   timer with the same id; `cancel_timer(id)` drops it
+//! - `window()`: the focused window, as `#{ binary, class, title }`
+//!
+//! A call that fails, or runs past [`MAX_OPERATIONS`], is logged and the event
+//! passes through.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, anyhow};
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{AST, CallFnOptions, Dynamic, Engine, EvalAltResult, FuncArgs, Map, Scope};
+use tracing::{debug, info, warn};
+
+use crate::key::{InputEvent, KeyCode};
+use crate::platform::EventResponse;
+use crate::strategy::{KeyStrategy, StrategyContext};
+
+/// Operations one call into a script may take before it is stopped, so a
+/// script stuck in a loop can't hold up input
+pub const MAX_OPERATIONS: u64 = 100_000;
+
+/// Functions a script may define, with their parameter counts
+const HOOKS: [(&str, usize); 4] = [
+    ("on_key_down", 2),
+    ("on_key_up", 2),
+    ("on_timer", 1),
+    ("on_reset", 0),
+];
+
+/// Configuration for a script strategy
+#[derive(Debug, Clone)]
+pub struct ScriptConfig {
+    /// Where the script came from, for log messages
+    pub name: String,
+    /// Script source
+    pub source: String,
+    /// The strategy's other fields, which the script starts with as `this`
+    pub settings: toml::Table,
+}
+
+/// What host functions act on during a call
+#[derive(Default)]
+struct Host {
+    /// Context of the latest call, kept for the timers that fire after it
+    ctx: Option<StrategyContext>,
+    /// Timers set (with their delay) or cancelled during the call
+    timer_requests: Vec<(i64, Option<Duration>)>,
+}
+
+/// A compiled script with its state
+struct Script {
+    name: String,
+    engine: Engine,
+    ast: AST,
+    /// Hooks the script defines
+    hooks: HashSet<&'static str>,
+    this: Dynamic,
+    /// Generation of each pending timer. A timer that fires after being
+    /// replaced or cancelled finds a different generation, or none.
+    timers: HashMap<i64, u64>,
+    next_generation: u64,
+}
+
+/// Strategy backed by a Rhai script
+///
+/// Timers fire from spawned tasks, which share the script through the mutex.
+pub struct ScriptStrategy {
+    script: Arc<Mutex<Script>>,
+    host: Arc<Mutex<Host>>,
+    /// Event times are passed to the script relative to this, taken from the
+    /// context's clock on the first event
+    epoch: Option<Instant>,
+}
+
+impl ScriptStrategy {
+    /// Compile the script, failing on syntax errors
+    pub fn new(config: ScriptConfig) -> Result<Self> {
+        let host = Arc::new(Mutex::new(Host::default()));
+        let engine = engine(&config.name, &host);
+        let ast = engine.compile(&config.source).map_err(|e| anyhow!("{e}"))?;
+        let hooks = HOOKS
+            .into_iter()
+            .filter(|(name, params)| {
+                ast.iter_functions()
+                    .any(|f| f.name == *name && f.params.len() == *params)
+            })
+            .map(|(name, _)| name)
+            .collect();
+        let this = Dynamic::from_map(
+            config
+                .settings
+                .iter()
+                .map(|(key, value)| (key.as_str().into(), to_dynamic(value)))
+                .collect(),
+        );
+
+        Ok(Self {
+            script: Arc::new(Mutex::new(Script {
+                name: config.name,
+                engine,
+                ast,
+                hooks,
+                this,
+                timers: HashMap::new(),
+                next_generation: 0,
+            })),
+            host,
+            epoch: None,
+        })
+    }
+}
+
+/// An engine with the host functions, limited to what a strategy needs
+fn engine(name: &str, host: &Arc<Mutex<Host>>) -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .set_module_resolver(DummyModuleResolver::new())
+        .set_max_operations(MAX_OPERATIONS)
+        .set_max_call_levels(32)
+        .set_max_string_size(64 * 1024)
+        .set_max_array_size(10_000)
+        .set_max_map_size(10_000);
+
+    let script = name.to_string();
+    engine.on_print(move |text| info!(script, "{text}"));
+    let script = name.to_string();
+    engine.on_debug(move |text, _, _| debug!(script, "{text}"));
+
+    let with_ctx = |host: &Arc<Mutex<Host>>, f: fn(&StrategyContext)| {
+        let host = Arc::clone(host);
+        move || {
+            if let Some(ctx) = &host.lock().unwrap().ctx {
+                f(ctx);
+            }
+        }
+    };
+    engine.register_fn("execute", with_ctx(host, StrategyContext::execute));
+
+    let h = Arc::clone(host);
+    engine.register_fn("action", move || -> String {
+        h.lock()
+            .unwrap()
+            .ctx
+            .as_ref()
+            .map(|ctx| ctx.action().to_string())
+            .unwrap_or_default()
+    });
+    engine.register_fn("key", |name: &str| -> Result<i64, Box<EvalAltResult>> {
+        KeyCode::from_config_str(name)
+            .map(|key| i64::from(key.code()))
+            .ok_or_else(|| format!("unknown key '{name}'").into())
+    });
+    for (name, down) in [
+        ("press", Some(true)),
+        ("release", Some(false)),
+        ("tap", None),
+    ] {
+        let h = Arc::clone(host);
+        engine.register_fn(name, move |code: i64| {
+            let Some(ctx) = &h.lock().unwrap().ctx else {
+                return;
+            };
+            let key = KeyCode::new(code as u32);
+            match down {
+                Some(down) => ctx.send_raw_key(key, down),
+                None => ctx.platform_handle().tap_raw_key(key),
+            }
+        });
+    }
+
+    let h = Arc::clone(host);
+    engine.register_fn("set_timer", move |id: i64, ms: i64| {
+        let delay = Duration::from_millis(ms.max(0) as u64);
+        h.lock().unwrap().timer_requests.push((id, Some(delay)));
+    });
+    let h = Arc::clone(host);
+    engine.register_fn("cancel_timer", move |id: i64| {
+        h.lock().unwrap().timer_requests.push((id, None));
+    });
+
+    let h = Arc::clone(host);
+    engine.register_fn("window", move || -> Map {
+        let Some(window) = h.lock().unwrap().ctx.as_ref().map(|ctx| ctx.window_info()) else {
+            return Map::new();
+        };
+        [
+            ("binary", window.binary),
+            ("class", window.class),
+            ("title", window.title),
+        ]
+        .into_iter()
+        .map(|(field, value)| (field.into(), value.into()))
+        .collect()
+    });
+
+    engine
+}
+
+/// A TOML setting as a script value
+fn to_dynamic(value: &toml::Value) -> Dynamic {
+    match value {
+        toml::Value::String(s) => s.clone().into(),
+        toml::Value::Integer(i) => (*i).into(),
+        toml::Value::Float(f) => (*f).into(),
+        toml::Value::Boolean(b) => (*b).into(),
+        toml::Value::Datetime(d) => d.to_string().into(),
+        toml::Value::Array(items) => items.iter().map(to_dynamic).collect::<Vec<_>>().into(),
+        toml::Value::Table(table) => Dynamic::from_map(
+            table
+                .iter()
+                .map(|(key, value)| (key.as_str().into(), to_dynamic(value)))
+                .collect(),
+        ),
+    }
+}
+
+/// Call the hook `name` if the script defines it, with `ctx` available to host
+/// functions, then start or cancel the timers it asked for. Returns `None` if
+/// the script doesn't define the hook or the call failed.
+fn call(
+    shared: &Arc<Mutex<Script>>,
+    host: &Arc<Mutex<Host>>,
+    ctx: &StrategyContext,
+    name: &'static str,
+    args: impl FuncArgs,
+) -> Option<Dynamic> {
+    let mut script = shared.lock().unwrap();
+    if !script.hooks.contains(name) {
+        return None;
+    }
+    host.lock().unwrap().ctx = Some(ctx.clone());
+    let Script {
+        engine, ast, this, ..
+    } = &mut *script;
+    let options = CallFnOptions::new().eval_ast(false).bind_this_ptr(this);
+    let result =
+        engine.call_fn_with_options::<Dynamic>(options, &mut Scope::new(), ast, name, args);
+
+    let requests = std::mem::take(&mut host.lock().unwrap().timer_requests);
+    for (id, delay) in requests {
+        let Some(delay) = delay else {
+            script.timers.remove(&id);
+            continue;
+        };
+        script.next_generation += 1;
+        let generation = script.next_generation;
+        script.timers.insert(id, generation);
+        let (shared, host, clock) = (Arc::clone(shared), Arc::clone(host), ctx.clock());
+        tokio::spawn(async move {
+            clock.sleep(delay).await;
+            fire_timer(&shared, &host, id, generation);
+        });
+    }
+
+    result
+        .inspect_err(|e| warn!(script = script.name, "{name} failed: {e}"))
+        .ok()
+}
+
+/// Tell the script timer `id` fired, unless it was replaced or cancelled since
+fn fire_timer(shared: &Arc<Mutex<Script>>, host: &Arc<Mutex<Host>>, id: i64, generation: u64) {
+    {
+        let mut script = shared.lock().unwrap();
+        if script.timers.get(&id) != Some(&generation) {
+            return;
+        }
+        script.timers.remove(&id);
+    }
+    let Some(ctx) = host.lock().unwrap().ctx.clone() else {
+        return;
+    };
+    call(shared, host, &ctx, "on_timer", (id,));
+}
+
+impl KeyStrategy for ScriptStrategy {
+    fn process(&mut self, event: &InputEvent, ctx: &StrategyContext) -> EventResponse {
+        // Only bound keys reach a script
+        let InputEvent::Key(key_event) = event else {
+            return EventResponse::Passthrough;
+        };
+        let hook = if key_event.down {
+            "on_key_down"
+        } else {
+            "on_key_up"
+        };
+        let key = i64::from(key_event.key.code());
+        let epoch = *self.epoch.get_or_insert_with(|| ctx.now());
+        let time = key_event.time.saturating_duration_since(epoch).as_millis() as i64;
+        let response = call(&self.script, &self.host, ctx, hook, (key, time));
+        match response
+            .and_then(|r| r.into_immutable_string().ok())
+            .as_deref()
+        {
+            Some("block") => EventResponse::Block,
+            Some("continue") => EventResponse::Continue,
+            _ => EventResponse::Passthrough,
+        }
+    }
+
+    fn reset(&mut self, ctx: &StrategyContext) {
+        self.script.lock().unwrap().timers.clear();
+        call(&self.script, &self.host, ctx, "on_reset", ());
+    }
+
+    fn debug_state(&self) -> serde_json::Value {
+        let script = self.script.lock().unwrap();
+        serde_json::json!({
+            "this": script.this.to_string(),
+            "timers": script.timers.len(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::config::{Action, WindowInfo};
+    use crate::key::KeyEvent;
+    use crate::platform::mock::PlatformCall;
+    use crate::platform::{MediaCommand, MockPlatform};
+    use crate::strategy::PlatformHandle;
+
+    const CTRL: u32 = 0x1D;
+
+    /// Tap runs the action, holding past `hold_ms` holds ctrl instead
+    const TAP_HOLD: &str = r#"
+        fn on_key_down(key, time) {
+            set_timer(1, this.hold_ms);
+            "block"
+        }
+
+        fn on_key_up(key, time) {
+            cancel_timer(1);
+            if this.held ?? false {
+                release(29);
+                this.held = false;
+            } else {
+                execute();
+            }
+            "block"
+        }
+
+        fn on_timer(id) {
+            press(29);
+            this.held = true;
+        }
+    "#;
+
+    fn strategy(source: &str) -> ScriptStrategy {
+        let mut settings = toml::Table::new();
+        settings.insert("hold_ms".to_string(), 200.into());
+        ScriptStrategy::new(ScriptConfig {
+            name: "test.rhai".to_string(),
+            source: source.to_string(),
+            settings,
+        })
+        .unwrap()
+    }
+
+    fn key(code: u32, down: bool) -> InputEvent {
+        InputEvent::Key(KeyEvent::new(KeyCode::new(code), down))
+    }
+
+    fn raw(code: u32, down: bool) -> PlatformCall {
+        PlatformCall::SendRawKey {
+            key: KeyCode::new(code),
+            down,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tap_and_hold_with_timers() {
+        let mut strategy = strategy(TAP_HOLD);
+        let platform = MockPlatform::new();
+        let clock = Arc::new(MockClock::new(Instant::now()));
+        let handle = PlatformHandle::from_mock(&platform).with_clock(clock.clone());
+        let ctx = StrategyContext::new(handle, &Action::MediaNext);
+
+        assert!(strategy.process(&key(0x3A, true), &ctx) == EventResponse::Block);
+        clock.advance(Duration::from_millis(100)).await;
+        assert!(strategy.process(&key(0x3A, false), &ctx) == EventResponse::Block);
+        clock.advance(Duration::from_millis(300)).await;
+        assert_eq!(
+            platform.calls(),
+            [PlatformCall::SendMedia(MediaCommand::Next)]
+        );
+
+        platform.clear_calls();
+        strategy.process(&key(0x3A, true), &ctx);
+        clock.advance(Duration::from_millis(250)).await;
+        strategy.process(&key(0x3A, false), &ctx);
+        assert_eq!(platform.calls(), [raw(CTRL, true), raw(CTRL, false)]);
+
+        // A reset drops pending timers
+        platform.clear_calls();
+        strategy.process(&key(0x3A, true), &ctx);
+        strategy.reset(&ctx);
+        clock.advance(Duration::from_millis(250)).await;
+        assert!(platform.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_event_times_follow_the_clock() {
+        let mut strategy = strategy(
+            r#"
+            fn on_key_down(key, time) { this.down = time; }
+            fn on_key_up(key, time) { if this.down == 0 && time == 150 { "block" } }
+            "#,
+        );
+        let platform = MockPlatform::new();
+        // Far from the real time, which script times must not depend on
+        let clock = Arc::new(MockClock::new(Instant::now() + Duration::from_secs(3600)));
+        let handle = PlatformHandle::from_mock(&platform).with_clock(clock.clone());
+        let ctx = StrategyContext::new(handle, &Action::Block);
+        let key = |down| InputEvent::Key(KeyEvent::at(KeyCode::new(0x3A), down, ctx.now()));
+
+        strategy.process(&key(true), &ctx);
+        clock.advance(Duration::from_millis(150)).await;
+        assert!(strategy.process(&key(false), &ctx) == EventResponse::Block);
+    }
+
+    #[tokio::test]
+    async fn test_window_action_and_limits() {
+        let mut strategy = strategy(
+            r#"
+            fn on_key_down(key, time) {
+                if key == key("f2") { loop {} }
+                if window().binary == "mpv" && action() == "media_next" { "continue" }
+            }
+            "#,
+        );
+        let platform = MockPlatform::new();
+        let ctx = StrategyContext::new(PlatformHandle::from_mock(&platform), &Action::MediaNext);
+        let f1 = KeyCode::from_config_str("f1").unwrap().code();
+        let f2 = KeyCode::from_config_str("f2").unwrap().code();
+
+        platform.set_window(WindowInfo {
+            binary: "mpv".to_string(),
+            ..WindowInfo::default()
+        });
+        assert!(strategy.process(&key(f1, true), &ctx) == EventResponse::Continue);
+        // Without on_key_up, releases pass through
+        assert!(strategy.process(&key(f1, false), &ctx) == EventResponse::Passthrough);
+
+        // A script that never returns is stopped, and keeps working afterwards
+        assert!(strategy.process(&key(f2, true), &ctx) == EventResponse::Passthrough);
+        assert!(strategy.process(&key(f1, true), &ctx) == EventResponse::Continue);
+
+        platform.set_window(WindowInfo::default());
+        assert!(strategy.process(&key(f1, true), &ctx) == EventResponse::Passthrough);
+    }
+
+    #[test]
+    fn test_syntax_error_rejected() {
+        let config = ScriptConfig {
+            name: "broken.rhai".to_string(),
+            source: "fn on_key_down(key, time) {".to_string(),
+            settings: toml::Table::new(),
+        };
+        assert!(ScriptStrategy::new(config).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_script_strategy_from_config() {
+        let dir = std::env::temp_dir().join(format!("rebinded-script-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("caps.rhai"), TAP_HOLD).unwrap();
+        let config_path = dir.join("config.toml");
+        std::fs::write(
+            &config_path,
+            r#"
+                [strategies.caps]
+                type = "script"
+                file = "caps.rhai"
+                hold_ms = 200
+
+                [bindings.0x3A]
+                action = "media_next"
+                strategy = "caps"
+            "#,
+        )
+        .unwrap();
+        let (_, runtime) = crate::config::load(&config_path).await.unwrap();
+        assert!(runtime.strategies.contains_key("caps"));
+
+        std::fs::write(
+            &config_path,
+            "[strategies.caps]\ntype = \"script\"\nfile = \"missing.rhai\"",
+        )
+        .unwrap();
+        let msg = format!("{:?}", crate::config::load(&config_path).await.unwrap_err());
+        assert!(msg.contains("missing.rhai"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,486 @@
+//! Tap-hold (dual-function) strategy
+//!
+//! Gives a key two meanings depending on how long it is held:
+//! 1. **Tap**: released before `hold_ms` — the buffered key is replayed, or
+//!    `tap_key` is tapped in its place (e.g. CapsLock → Esc)
+//! 2. **Hold**: held past `hold_ms` — `hold_key` is pressed and kept down until
+//!    release (e.g. CapsLock → Ctrl), or the bound action runs once if no
+//!    `hold_key` is configured
+//!
+//! The original key event is always blocked; the decision is made purely on
+//! timing, so the key behaves as a tap until the threshold passes. On release
+//! the events' own timestamps settle it, so a release that arrives late still
+//! counts as the tap or hold it physically was.
+
+use crate::key::{InputEvent, KeyCode};
+use crate::platform::EventResponse;
+use crate::strategy::{KeyStrategy, StrategyContext};
+use serde_json::json;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot};
+use tracing::debug;
+
+/// Configuration for tap-hold behavior
+#[derive(Debug, Clone)]
+pub struct TapHoldConfig {
+    /// How long the key must be held before it counts as a hold (ms)
+    pub hold_ms: u64,
+    /// Key tapped on a short press. `None` replays the original key.
+    pub tap_key: Option<KeyCode>,
+    /// Key held down while the bound key is held past the threshold.
+    /// `None` executes the bound action once instead.
+    pub hold_key: Option<KeyCode>,
+}
+
+/// Tracks state for a single key
+#[derive(Debug, Default)]
+enum KeyState {
+    /// No activity
+    #[default]
+    Idle,
+    /// Key is down, waiting to find out whether this is a tap or a hold.
+    /// Contains when it was pressed and a cancel sender to abort the pending
+    /// hold timer.
+    Pending {
+        pressed_at: Instant,
+        cancel_tx: oneshot::Sender<()>,
+    },
+    /// Hold timer fired; hold key (if any) is currently pressed
+    Held { pressed_at: Instant },
+}
+
+/// Tap-hold strategy implementation
+pub struct TapHoldStrategy {
+    config: TapHoldConfig,
+    /// Per-key state
+    key_states: HashMap<KeyCode, KeyState>,
+    /// Channel to receive timer completion notifications
+    /// When a hold timer fires, it sends the key code here so the strategy
+    /// can transition the key from Pending to Held state
+    timer_tx: mpsc::UnboundedSender<KeyCode>,
+    timer_rx: mpsc::UnboundedReceiver<KeyCode>,
+}
+
+impl TapHoldStrategy {
+    /// Create a new tap-hold strategy with the given configuration
+    pub fn new(config: TapHoldConfig) -> Self {
+        let (timer_tx, timer_rx) = mpsc::unbounded_channel();
+        Self {
+            config,
+            key_states: HashMap::new(),
+            timer_tx,
+            timer_rx,
+        }
+    }
+
+    /// Process pending timer completion notifications
+    ///
+    /// Transitions keys whose hold timer fired from Pending to Held.
+    fn process_timer_completions(&mut self) {
+        while let Ok(key) = self.timer_rx.try_recv() {
+            if let Some(KeyState::Pending { pressed_at, .. }) = self.key_states.get(&key) {
+                debug!(?key, "tap_hold: pending -> held (timer completion)");
+                let pressed_at = *pressed_at;
+                self.key_states.insert(key, KeyState::Held { pressed_at });
+            }
+        }
+    }
+
+    /// Handle key-down event
+    fn key_down(&mut self, key: KeyCode, time: Instant, ctx: &StrategyContext) -> EventResponse {
+        let current_state = self.key_states.remove(&key).unwrap_or(KeyState::Idle);
+
+        let next_state = match current_state {
+            KeyState::Idle => {
+                debug!(?key, "tap_hold: idle -> pending");
+
+                let (cancel_tx, cancel_rx) = oneshot::channel();
+                // Counted from the physical press, not from when this runs
+                let hold_deadline = time + Duration::from_millis(self.config.hold_ms);
+                let hold_key = self.config.hold_key;
+                let action = ctx.action().clone();
+                let platform_handle = ctx.platform_handle();
+                let timer_tx = self.timer_tx.clone();
+
+                tokio::spawn(async move {
+                    tokio::select! {
+                        _ = platform_handle.clock().sleep_until(hold_deadline) => {
+                            // Hold threshold reached — start the hold behavior
+                            match hold_key {
+                                Some(hold_key) => platform_handle.send_raw_key(hold_key, true),
+                                None => platform_handle.execute(&action),
+                            }
+                            debug!("tap_hold: hold timer fired");
+                            let _ = timer_tx.send(key);
+                        }
+                        _ = cancel_rx => {
+                            debug!("tap_hold: hold timer cancelled");
+                        }
+                    }
+                });
+
+                KeyState::Pending {
+                    pressed_at: time,
+                    cancel_tx,
+                }
+            }
+            // OS key repeat while pending or held - nothing changes
+            state => state,
+        };
+
+        self.key_states.insert(key, next_state);
+        EventResponse::Block
+    }
+
+    /// Handle key-up event
+    fn key_up(&mut self, key: KeyCode, time: Instant, ctx: &StrategyContext) -> EventResponse {
+        let current_state = self.key_states.remove(&key).unwrap_or(KeyState::Idle);
+
+        let (pressed_at, hold_started) = match current_state {
+            // If the timer already fired but we haven't seen its completion yet,
+            // the cancel receiver is gone and the hold behavior has started
+            KeyState::Pending {
+                pressed_at,
+                cancel_tx,
+            } => (pressed_at, cancel_tx.send(()).is_err()),
+            KeyState::Held { pressed_at } => (pressed_at, true),
+            // Already idle, nothing to do
+            KeyState::Idle => return EventResponse::Block,
+        };
+
+        let held = time.saturating_duration_since(pressed_at)
+            >= Duration::from_millis(self.config.hold_ms);
+        if held {
+            debug!(?key, "tap_hold: released after hold");
+            if !hold_started {
+                self.start_hold(ctx);
+            }
+            self.release_hold(ctx);
+        } else {
+            // A hold the timer started for a release that came in late is let
+            // go; an action it ran can't be taken back
+            debug!(?key, "tap_hold: released as tap");
+            if hold_started {
+                self.release_hold(ctx);
+            }
+            ctx.platform_handle()
+                .tap_raw_key(self.config.tap_key.unwrap_or(key));
+        }
+
+        // Absence from map means Idle
+        EventResponse::Block
+    }
+
+    /// Start the hold behavior the timer would have, for a hold whose timer
+    /// hasn't run yet
+    fn start_hold(&self, ctx: &StrategyContext) {
+        match self.config.hold_key {
+            Some(hold_key) => ctx.send_raw_key(hold_key, true),
+            None => ctx.execute(),
+        }
+    }
+
+    /// Release the hold key, if the hold behavior pressed one
+    fn release_hold(&self, ctx: &StrategyContext) {
+        if let Some(hold_key) = self.config.hold_key {
+            ctx.send_raw_key(hold_key, false);
+        }
+    }
+}
+
+impl KeyStrategy for TapHoldStrategy {
+    fn debug_state(&self) -> serde_json::Value {
+        let keys: serde_json::Map<_, _> = self
+            .key_states
+            .iter()
+            .map(|(key, state)| {
+                let state = match state {
+                    KeyState::Idle => "idle",
+                    KeyState::Pending { .. } => "pending",
+                    KeyState::Held { .. } => "held",
+                };
+                (key.display_name(), state.into())
+            })
+            .collect();
+        json!({ "keys": keys })
+    }
+
+    fn reset(&mut self, ctx: &StrategyContext) {
+        debug!("tap_hold: reset");
+        self.process_timer_completions();
+        for (_, state) in std::mem::take(&mut self.key_states) {
+            let hold_started = match state {
+                // A failed cancel means the hold already started
+                KeyState::Pending { cancel_tx, .. } => cancel_tx.send(()).is_err(),
+                KeyState::Held { .. } => true,
+                KeyState::Idle => false,
+            };
+            if hold_started {
+                self.release_hold(ctx);
+            }
+        }
+    }
+
+    fn process(&mut self, event: &InputEvent, ctx: &StrategyContext) -> EventResponse {
+        self.process_timer_completions();
+
+        match event {
+            InputEvent::Key(key_event) => {
+                if key_event.down {
+                    self.key_down(key_event.key, key_event.time, ctx)
+                } else {
+                    self.key_up(key_event.key, key_event.time, ctx)
+                }
+            }
+            // Not subscribed to anything else
+            InputEvent::MouseButton { .. } | InputEvent::Scroll { .. } | InputEvent::Divert(_) => {
+                EventResponse::Passthrough
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{Clock, MockClock};
+    use crate::config::Action;
+    use crate::key::KeyEvent;
+    use crate::platform::mock::PlatformCall;
+    use crate::platform::{MediaCommand, MockPlatform};
+    use crate::strategy::PlatformHandle;
+    use std::sync::Arc;
+
+    const CAPS: KeyCode = KeyCode::new(0x3A);
+    const ESC: KeyCode = KeyCode::new(0x01);
+    const CTRL: KeyCode = KeyCode::new(0x1D);
+
+    fn key(code: KeyCode, down: bool) -> InputEvent {
+        InputEvent::Key(KeyEvent::new(code, down))
+    }
+
+    /// A context on a mock clock, so tests cross `hold_ms` without waiting
+    fn mock_context(platform: &MockPlatform, action: &Action) -> (Arc<MockClock>, StrategyContext) {
+        let clock = Arc::new(MockClock::new(Instant::now()));
+        let handle = PlatformHandle::from_mock(platform).with_clock(clock.clone());
+        (clock, StrategyContext::new(handle, action))
+    }
+
+    /// A key event stamped with the mock clock's time
+    fn key_at(clock: &MockClock, code: KeyCode, down: bool) -> InputEvent {
+        InputEvent::Key(KeyEvent::at(code, down, clock.now()))
+    }
+
+    fn raw(key: KeyCode, down: bool) -> PlatformCall {
+        PlatformCall::SendRawKey { key, down }
+    }
+
+    #[tokio::test]
+    async fn test_tap_replays_original_key() {
+        let mut strategy = TapHoldStrategy::new(TapHoldConfig {
+            hold_ms: 50,
+            tap_key: None,
+            hold_key: Some(CTRL),
+        });
+        let platform = Arc::new(MockPlatform::new());
+        let (clock, ctx) = mock_context(&platform, &Action::Block);
+
+        assert!(strategy.process(&key_at(&clock, CAPS, true), &ctx) == EventResponse::Block);
+        platform.assert_no_calls();
+
+        assert!(strategy.process(&key_at(&clock, CAPS, false), &ctx) == EventResponse::Block);
+        assert_eq!(platform.calls(), vec![raw(CAPS, true), raw(CAPS, false)]);
+
+        // Timer was cancelled - nothing fires later
+        clock.advance(Duration::from_millis(70)).await;
+        platform.assert_call_count(2);
+    }
+
+    #[tokio::test]
+    async fn test_tap_sends_tap_key() {
+        let mut strategy = TapHoldStrategy::new(TapHoldConfig {
+            hold_ms: 50,
+            tap_key: Some(ESC),
+            hold_key: Some(CTRL),
+        });
+        let platform = Arc::new(MockPlatform::new());
+        let ctx = StrategyContext::new(PlatformHandle::from_mock(&platform), &Action::Block);
+
+        strategy.process(&key(CAPS, true), &ctx);
+        strategy.process(&key(CAPS, false), &ctx);
+        assert_eq!(platform.calls(), vec![raw(ESC, true), raw(ESC, false)]);
+    }
+
+    #[tokio::test]
+    async fn test_hold_measured_from_event_time() {
+        let mut strategy = TapHoldStrategy::new(TapHoldConfig {
+            hold_ms: 200,
+            tap_key: None,
+            hold_key: Some(CTRL),
+        });
+        let platform = Arc::new(MockPlatform::new());
+        let (clock, ctx) = mock_context(&platform, &Action::Block);
+
+        // The press reached the strategy late; the hold counts from when it happened
+        let pressed = clock.now() - Duration::from_millis(180);
+        let press = InputEvent::Key(KeyEvent::at(CAPS, true, pressed));
+        strategy.process(&press, &ctx);
+        clock.advance(Duration::from_millis(19)).await;
+        platform.assert_no_calls();
+        clock.advance(Duration::from_millis(1)).await;
+        assert_eq!(platform.calls(), vec![raw(CTRL, true)]);
+    }
+
+    #[tokio::test]
+    async fn test_hold_threshold_on_mock_clock() {
+        let mut strategy = TapHoldStrategy::new(TapHoldConfig {
+            hold_ms: 200,
+            tap_key: None,
+            hold_key: Some(CTRL),
+        });
+        let platform = Arc::new(MockPlatform::new());
+        let clock = Arc::new(MockClock::new(Instant::now()));
+        let handle = PlatformHandle::from_mock(&platform).with_clock(clock.clone());
+        let ctx = StrategyContext::new(handle, &Action::Block);
+
+        let press = InputEvent::Key(KeyEvent::at(CAPS, true, clock.now()));
+        strategy.process(&press, &ctx);
+        clock.advance(Duration::from_millis(199)).await;
+        platform.assert_no_calls();
+
+        clock.advance(Duration::from_millis(1)).await;
+        assert_eq!(platform.calls(), vec![raw(CTRL, true)]);
+        let release = InputEvent::Key(KeyEvent::at(CAPS, false, clock.now()));
+        strategy.process(&release, &ctx);
+        assert_eq!(platform.calls(), vec![raw(CTRL, true), raw(CTRL, false)]);
+    }
+
+    #[tokio::test]
+    async fn test_release_decided_by_event_time() {
+        let mut strategy = TapHoldStrategy::new(TapHoldConfig {
+            hold_ms: 200,
+            tap_key: None,
+            hold_key: Some(CTRL),
+        });
+        let platform = Arc::new(MockPlatform::new());
+        let clock = Arc::new(MockClock::new(Instant::now()));
+        let handle = PlatformHandle::from_mock(&platform).with_clock(clock.clone());
+        let ctx = StrategyContext::new(handle, &Action::Block);
+        let pressed = clock.now();
+
+        // Released past the threshold, before the timer got to run: a hold
+        strategy.process(&InputEvent::Key(KeyEvent::at(CAPS, true, pressed)), &ctx);
+        let late = pressed + Duration::from_millis(250);
+        strategy.process(&InputEvent::Key(KeyEvent::at(CAPS, false, late)), &ctx);
+        assert_eq!(platform.calls(), vec![raw(CTRL, true), raw(CTRL, false)]);
+        clock.advance(Duration::from_millis(300)).await;
+        platform.assert_call_count(2);
+        platform.clear_calls();
+
+        // Released before the threshold, but delivered after the timer fired: a tap
+        let pressed = clock.now();
+        strategy.process(&InputEvent::Key(KeyEvent::at(CAPS, true, pressed)), &ctx);
+        clock.advance(Duration::from_millis(200)).await;
+        let early = pressed + Duration::from_millis(150);
+        strategy.process(&InputEvent::Key(KeyEvent::at(CAPS, false, early)), &ctx);
+        assert_eq!(
+            platform.calls(),
+            vec![
+                raw(CTRL, true),
+                raw(CTRL, false),
+                raw(CAPS, true),
+                raw(CAPS, false)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hold_presses_and_releases_hold_key() {
+        let mut strategy = TapHoldStrategy::new(TapHoldConfig {
+            hold_ms: 50,
+            tap_key: Some(ESC),
+            hold_key: Some(CTRL),
+        });
+        let platform = Arc::new(MockPlatform::new());
+        let (clock, ctx) = mock_context(&platform, &Action::Block);
+
+        strategy.process(&key_at(&clock, CAPS, true), &ctx);
+        clock.advance(Duration::from_millis(70)).await;
+        assert_eq!(platform.calls(), vec![raw(CTRL, true)]);
+
+        // OS repeat while held is swallowed without side effects
+        strategy.process(&key_at(&clock, CAPS, true), &ctx);
+        assert!(matches!(
+            strategy.key_states.get(&CAPS),
+            Some(KeyState::Held { .. })
+        ));
+
+        strategy.process(&key_at(&clock, CAPS, false), &ctx);
+        assert_eq!(platform.calls(), vec![raw(CTRL, true), raw(CTRL, false)]);
+        assert!(strategy.key_states.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_hold_without_hold_key_executes_action() {
+        let mut strategy = TapHoldStrategy::new(TapHoldConfig {
+            hold_ms: 50,
+            tap_key: None,
+            hold_key: None,
+        });
+        let platform = Arc::new(MockPlatform::new());
+        let (clock, ctx) = mock_context(&platform, &Action::MediaPlayPause);
+
+        strategy.process(&key_at(&clock, CAPS, true), &ctx);
+        clock.advance(Duration::from_millis(70)).await;
+        strategy.process(&key_at(&clock, CAPS, false), &ctx);
+
+        // Action ran once on hold, no tap replay on release
+        platform.assert_media_sent(MediaCommand::PlayPause);
+        platform.assert_call_count(1);
+    }
+
+    #[tokio::test]
+    async fn test_release_racing_timer_is_treated_as_hold() {
+        let mut strategy = TapHoldStrategy::new(TapHoldConfig {
+            hold_ms: 20,
+            tap_key: None,
+            hold_key: Some(CTRL),
+        });
+        let platform = Arc::new(MockPlatform::new());
+        let (clock, ctx) = mock_context(&platform, &Action::Block);
+
+        strategy.process(&key_at(&clock, CAPS, true), &ctx);
+        clock.advance(Duration::from_millis(40)).await;
+
+        // Release without draining timer completions first: state is still Pending
+        assert!(matches!(
+            strategy.key_states.get(&CAPS),
+            Some(KeyState::Pending { .. })
+        ));
+        strategy.key_up(CAPS, clock.now(), &ctx);
+
+        // Hold key released, original key never replayed
+        assert_eq!(platform.calls(), vec![raw(CTRL, true), raw(CTRL, false)]);
+    }
+
+    #[tokio::test]
+    async fn test_reset_releases_held_key() {
+        let mut strategy = TapHoldStrategy::new(TapHoldConfig {
+            hold_ms: 20,
+            tap_key: None,
+            hold_key: Some(CTRL),
+        });
+        let platform = Arc::new(MockPlatform::new());
+        let (clock, ctx) = mock_context(&platform, &Action::Block);
+
+        strategy.process(&key_at(&clock, CAPS, true), &ctx);
+        clock.advance(Duration::from_millis(40)).await;
+        strategy.reset(&ctx);
+        assert_eq!(platform.calls(), vec![raw(CTRL, true), raw(CTRL, false)]);
+
+        // The physical release afterwards doesn't replay anything
+        strategy.process(&key_at(&clock, CAPS, false), &ctx);
+        platform.assert_call_count(2);
+    }
+}
@@ -0,0 +1,487 @@
+//! Sandboxed strategy plugins compiled to WebAssembly
+//!
+//! With the `wasm` feature, `.wasm` modules in the plugins directory load next
+//! to native plugins and register under their file name: `sticky.wasm` becomes
+//! strategy `type = "sticky"`. Every strategy gets its own instance of the
+//! module. An instance reaches the host only through the imports below, is
+//! stopped if a call runs past its fuel budget, and can't grow its memory
+//! beyond [`MEMORY_LIMIT`].
+//!
+//! A module exports `memory` and these functions:
+//!
+//! - `rebinded_alloc(len: i32) -> i32`: a buffer of `len` bytes for the settings
+//! - `rebinded_create(ptr: i32, len: i32) -> i32`: read the strategy table,
+//!   written to the buffer as TOML text; anything but 0 rejects it
+//! - `rebinded_process(kind: i32, code: i32, down: i32, time_ms: i64) -> i32`:
+//!   handle an event, returning a `PLUGIN_*` response code. `kind`, `code` and
+//!   `down` are those of a native [`PluginEvent`]; `time_ms` is when the event
+//!   happened, in milliseconds since the first event the instance handled.
+//! - `rebinded_timer(id: i32)`, optional: a timer set with `set_timer` fired
+//! - `rebinded_reset()`, optional: drop in-flight state, e.g. on a focus change
+//!
+//! It may import these from the `rebinded` module:
+//!
+//! - `execute_action()`: execute the binding's action
+//! - `send_raw_key(code: i32, down: i32)`: press or release a platform-native key
+//! - `set_timer(id: i32, ms: i32)`: call `rebinded_timer(id)` after `ms`,
+//!   replacing any pending timer with the same id
+//! - `cancel_timer(id: i32)`: drop the pending timer with this id
+//! - `window(field: i32, ptr: i32, len: i32) -> i32`: copy up to `len` bytes of
+//!   the focused window's binary (0), class (1) or title (2) to `ptr`,
+//!   returning the field's full length
+//!
+//! A trap, including running out of fuel, is logged and the event passes through.
+//!
+//! Modules run on `wasmi`, an interpreter, rather than `wasmtime`: it is pure
+//! Rust with no JIT, so the hook process never maps executable memory, and its
+//! fuel metering is what bounds each call. Strategy code is small and called
+//! once per event, where compiling would cost more than it saves.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, anyhow};
+use tracing::warn;
+use wasmi::{
+    Caller, Config, Engine, Extern, Linker, Module, Store, StoreLimits, StoreLimitsBuilder,
+    TypedFunc,
+};
+
+use crate::key::{InputEvent, KeyCode};
+use crate::platform::EventResponse;
+use crate::strategy::plugin::{PLUGIN_BLOCK, PLUGIN_CONTINUE, PluginEvent};
+use crate::strategy::{KeyStrategy, StrategyContext, StrategyRegistry};
+
+/// Linear memory an instance may grow to
+pub const MEMORY_LIMIT: usize = 16 * 1024 * 1024;
+
+/// Fuel for one call into an instance, about one unit per instruction. Plenty
+/// for handling an event, and small enough that a plugin stuck in a loop
+/// can't hold up input for long.
+const FUEL_PER_CALL: u64 = 1_000_000;
+
+/// Module the host functions are imported from
+const HOST_MODULE: &str = "rebinded";
+
+/// Every module is compiled for this engine, which meters fuel
+static ENGINE: LazyLock<Engine> = LazyLock::new(|| {
+    let mut config = Config::default();
+    config.consume_fuel(true);
+    Engine::new(&config)
+});
+
+/// Compile the module at `path` and register it as a strategy type named
+/// after the file
+pub fn load(path: &Path) -> Result<String> {
+    let name = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .ok_or_else(|| anyhow!("plugin file name is not valid UTF-8"))?
+        .to_string();
+    register(&name, &std::fs::read(path)?)?;
+    Ok(name)
+}
+
+/// Compile a module, binary or text format, and register it as the strategy
+/// type `name`
+pub fn register(name: &str, wasm: &[u8]) -> Result<()> {
+    let module = Module::new(&ENGINE, wasm)?;
+    StrategyRegistry::global().register(name, move |settings| {
+        WasmStrategy::new(&module, &settings.to_string())
+    })
+}
+
+/// What host functions see during a call
+struct HostState {
+    /// Context of the latest call, kept for the timers that fire after it
+    ctx: Option<StrategyContext>,
+    /// Timers set (with their delay) or cancelled during the call
+    timer_requests: Vec<(i32, Option<Duration>)>,
+    limits: StoreLimits,
+}
+
+/// A module instance with its entry points
+struct Instance {
+    store: Store<HostState>,
+    process: TypedFunc<(i32, i32, i32, i64), i32>,
+    timer: Option<TypedFunc<i32, ()>>,
+    reset: Option<TypedFunc<(), ()>>,
+    /// Generation of each pending timer. A timer that fires after being
+    /// replaced or cancelled finds a different generation, or none.
+    timers: HashMap<i32, u64>,
+    next_generation: u64,
+}
+
+/// Strategy backed by a WASM module instance
+///
+/// Timers fire from spawned tasks, which share the instance through the mutex.
+pub struct WasmStrategy {
+    instance: Arc<Mutex<Instance>>,
+    /// Event times are passed to the module relative to this, taken from the
+    /// context's clock on the first event
+    epoch: Option<Instant>,
+}
+
+impl WasmStrategy {
+    fn new(module: &Module, settings: &str) -> Result<Self> {
+        let mut store = Store::new(
+            module.engine(),
+            HostState {
+                ctx: None,
+                timer_requests: Vec::new(),
+                limits: StoreLimitsBuilder::new().memory_size(MEMORY_LIMIT).build(),
+            },
+        );
+        store.limiter(|state| &mut state.limits);
+        store.set_fuel(FUEL_PER_CALL)?;
+
+        let instance = linker()?.instantiate_and_start(&mut store, module)?;
+        let memory = instance
+            .get_memory(&store, "memory")
+            .ok_or_else(|| anyhow!("module exports no memory"))?;
+        let alloc = instance.get_typed_func::<i32, i32>(&store, "rebinded_alloc")?;
+        let create = instance.get_typed_func::<(i32, i32), i32>(&store, "rebinded_create")?;
+        let process = instance.get_typed_func(&store, "rebinded_process")?;
+        let timer = instance
+            .get_func(&store, "rebinded_timer")
+            .map(|func| func.typed(&store))
+            .transpose()?;
+        let reset = instance
+            .get_func(&store, "rebinded_reset")
+            .map(|func| func.typed(&store))
+            .transpose()?;
+
+        let len = i32::try_from(settings.len())?;
+        let ptr = alloc.call(&mut store, len)?;
+        memory
+            .write(&mut store, ptr as u32 as usize, settings.as_bytes())
+            .map_err(|e| anyhow!("writing settings: {e}"))?;
+        if create.call(&mut store, (ptr, len))? != 0 {
+            return Err(anyhow!("plugin rejected its settings"));
+        }
+
+        Ok(Self {
+            instance: Arc::new(Mutex::new(Instance {
+                store,
+                process,
+                timer,
+                reset,
+                timers: HashMap::new(),
+                next_generation: 0,
+            })),
+            epoch: None,
+        })
+    }
+}
+
+/// Host functions a module may import
+fn linker() -> Result<Linker<HostState>> {
+    let mut linker = Linker::new(&ENGINE);
+    linker.func_wrap(
+        HOST_MODULE,
+        "execute_action",
+        |caller: Caller<'_, HostState>| {
+            if let Some(ctx) = &caller.data().ctx {
+                ctx.execute();
+            }
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "send_raw_key",
+        |caller: Caller<'_, HostState>, code: i32, down: i32| {
+            if let Some(ctx) = &caller.data().ctx {
+                ctx.send_raw_key(KeyCode::new(code as u32), down != 0);
+            }
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "set_timer",
+        |mut caller: Caller<'_, HostState>, id: i32, ms: i32| {
+            let delay = Duration::from_millis(ms.max(0) as u64);
+            caller.data_mut().timer_requests.push((id, Some(delay)));
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "cancel_timer",
+        |mut caller: Caller<'_, HostState>, id: i32| {
+            caller.data_mut().timer_requests.push((id, None));
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "window",
+        |mut caller: Caller<'_, HostState>,
+         field: i32,
+         ptr: i32,
+         len: i32|
+         -> Result<i32, wasmi::Error> {
+            let Some(ctx) = &caller.data().ctx else {
+                return Ok(0);
+            };
+            let window = ctx.window_info();
+            let text = match field {
+                0 => window.binary,
+                1 => window.class,
+                2 => window.title,
+                _ => return Err(wasmi::Error::new(format!("unknown window field {field}"))),
+            };
+            let Some(Extern::Memory(memory)) = caller.get_export("memory") else {
+                return Err(wasmi::Error::new("module exports no memory"));
+            };
+            let copied = text.len().min(len.max(0) as usize);
+            memory
+                .write(&mut caller, ptr as u32 as usize, &text.as_bytes()[..copied])
+                .map_err(|e| wasmi::Error::new(e.to_string()))?;
+            Ok(text.len() as i32)
+        },
+    )?;
+    Ok(linker)
+}
+
+/// Call into the instance with `ctx` available to host functions, then start
+/// or cancel the timers the call asked for. Returns `None` if the call trapped.
+fn call<R>(
+    shared: &Arc<Mutex<Instance>>,
+    ctx: &StrategyContext,
+    call: impl FnOnce(&mut Instance) -> Result<R, wasmi::Error>,
+) -> Option<R> {
+    let mut instance = shared.lock().unwrap();
+    instance.store.data_mut().ctx = Some(ctx.clone());
+    // Only fails when fuel metering is off, and the engine always meters
+    let _ = instance.store.set_fuel(FUEL_PER_CALL);
+    let result = call(&mut instance);
+
+    let requests = std::mem::take(&mut instance.store.data_mut().timer_requests);
+    for (id, delay) in requests {
+        let Some(delay) = delay else {
+            instance.timers.remove(&id);
+            continue;
+        };
+        instance.next_generation += 1;
+        let generation = instance.next_generation;
+        instance.timers.insert(id, generation);
+        let (shared, clock) = (Arc::clone(shared), ctx.clock());
+        tokio::spawn(async move {
+            clock.sleep(delay).await;
+            fire_timer(&shared, id, generation);
+        });
+    }
+    drop(instance);
+
+    result
+        .inspect_err(|e| warn!("wasm plugin trapped: {e}"))
+        .ok()
+}
+
+/// Tell the instance timer `id` fired, unless it was replaced or cancelled since
+fn fire_timer(shared: &Arc<Mutex<Instance>>, id: i32, generation: u64) {
+    let (timer, ctx) = {
+        let mut instance = shared.lock().unwrap();
+        if instance.timers.get(&id) != Some(&generation) {
+            return;
+        }
+        instance.timers.remove(&id);
+        let (Some(timer), Some(ctx)) = (instance.timer, instance.store.data().ctx.clone()) else {
+            return;
+        };
+        (timer, ctx)
+    };
+    call(shared, &ctx, |instance| timer.call(&mut instance.store, id));
+}
+
+impl KeyStrategy for WasmStrategy {
+    fn process(&mut self, event: &InputEvent, ctx: &StrategyContext) -> EventResponse {
+        let Some(plugin_event) = PluginEvent::from_event(event) else {
+            return EventResponse::Passthrough;
+        };
+        let time = match event {
+            InputEvent::Key(key_event) => key_event.time,
+            _ => ctx.now(),
+        };
+        let epoch = *self.epoch.get_or_insert_with(|| ctx.now());
+        let time_ms = time.saturating_duration_since(epoch).as_millis() as i64;
+        let args = (
+            plugin_event.kind as i32,
+            plugin_event.code as i32,
+            i32::from(plugin_event.down),
+            time_ms,
+        );
+        let response = call(&self.instance, ctx, |instance| {
+            instance.process.call(&mut instance.store, args)
+        });
+        match response.map(|code| code as u32) {
+            Some(PLUGIN_BLOCK) => EventResponse::Block,
+            Some(PLUGIN_CONTINUE) => EventResponse::Continue,
+            _ => EventResponse::Passthrough,
+        }
+    }
+
+    fn reset(&mut self, ctx: &StrategyContext) {
+        let reset = {
+            let mut instance = self.instance.lock().unwrap();
+            instance.timers.clear();
+            instance.reset
+        };
+        if let Some(reset) = reset {
+            call(&self.instance, ctx, |instance| {
+                reset.call(&mut instance.store, ())
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::config::{Action, WindowInfo};
+    use crate::key::KeyEvent;
+    use crate::platform::mock::PlatformCall;
+    use crate::platform::{MediaCommand, MockPlatform};
+    use crate::strategy::PlatformHandle;
+
+    /// Blocks key 0x7C: a release within 200ms executes the action, a hold
+    /// presses 0x1D. Continues key 2 while "mpv" is focused, and spins on key 3.
+    /// Rejects empty settings.
+    const TEST_MODULE: &str = r#"
+        (module
+          (import "rebinded" "execute_action" (func $execute))
+          (import "rebinded" "send_raw_key" (func $send_raw_key (param i32 i32)))
+          (import "rebinded" "set_timer" (func $set_timer (param i32 i32)))
+          (import "rebinded" "cancel_timer" (func $cancel_timer (param i32)))
+          (import "rebinded" "window" (func $window (param i32 i32 i32) (result i32)))
+          (memory (export "memory") 1)
+          (func (export "rebinded_alloc") (param i32) (result i32)
+            (i32.const 1024))
+          (func (export "rebinded_create") (param $ptr i32) (param $len i32) (result i32)
+            (i32.eqz (local.get $len)))
+          (func (export "rebinded_process")
+            (param $kind i32) (param $code i32) (param $down i32) (param $time i64)
+            (result i32)
+            (if (i32.eq (local.get $code) (i32.const 0x7C))
+              (then
+                (if (local.get $down)
+                  (then (call $set_timer (i32.const 1) (i32.const 200)))
+                  (else (call $cancel_timer (i32.const 1)) (call $execute)))
+                (return (i32.const 1))))
+            (if (i32.eq (local.get $code) (i32.const 2))
+              (then
+                (return
+                  (i32.shl
+                    (i32.eq (call $window (i32.const 0) (i32.const 0) (i32.const 16)) (i32.const 3))
+                    (i32.const 1)))))
+            (if (i32.eq (local.get $code) (i32.const 3))
+              (then (loop $spin (br $spin))))
+            (i32.const 0))
+          (func (export "rebinded_timer") (param $id i32)
+            (call $send_raw_key (i32.const 0x1D) (i32.const 1))))
+    "#;
+
+    fn strategy() -> WasmStrategy {
+        let module = Module::new(&ENGINE, TEST_MODULE).unwrap();
+        WasmStrategy::new(&module, "hold_ms = 200").unwrap()
+    }
+
+    fn key(code: u32, down: bool) -> InputEvent {
+        InputEvent::Key(KeyEvent::new(KeyCode::new(code), down))
+    }
+
+    #[tokio::test]
+    async fn test_timers_follow_the_clock() {
+        let mut strategy = strategy();
+        let platform = MockPlatform::new();
+        let clock = Arc::new(MockClock::new(Instant::now()));
+        let handle = PlatformHandle::from_mock(&platform).with_clock(clock.clone());
+        let ctx = StrategyContext::new(handle, &Action::MediaNext);
+
+        // Released in time: the timer is cancelled and the action runs
+        assert!(strategy.process(&key(0x7C, true), &ctx) == EventResponse::Block);
+        clock.advance(Duration::from_millis(100)).await;
+        assert!(strategy.process(&key(0x7C, false), &ctx) == EventResponse::Block);
+        clock.advance(Duration::from_millis(300)).await;
+        assert_eq!(
+            platform.calls(),
+            [PlatformCall::SendMedia(MediaCommand::Next)]
+        );
+
+        // Held: the timer fires
+        platform.clear_calls();
+        strategy.process(&key(0x7C, true), &ctx);
+        clock.advance(Duration::from_millis(250)).await;
+        assert_eq!(
+            platform.calls(),
+            [PlatformCall::SendRawKey {
+                key: KeyCode::new(0x1D),
+                down: true
+            }]
+        );
+
+        // A reset drops pending timers
+        platform.clear_calls();
+        strategy.process(&key(0x7C, true), &ctx);
+        strategy.reset(&ctx);
+        clock.advance(Duration::from_millis(250)).await;
+        assert!(platform.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_window_query_and_fuel_limit() {
+        let mut strategy = strategy();
+        let platform = MockPlatform::new();
+        let ctx = StrategyContext::new(PlatformHandle::from_mock(&platform), &Action::Block);
+
+        platform.set_window(WindowInfo {
+            binary: "mpv".to_string(),
+            ..WindowInfo::default()
+        });
+        assert!(strategy.process(&key(2, true), &ctx) == EventResponse::Continue);
+        platform.set_window(WindowInfo {
+            binary: "firefox".to_string(),
+            ..WindowInfo::default()
+        });
+        assert!(strategy.process(&key(2, true), &ctx) == EventResponse::Passthrough);
+
+        // A plugin that never returns is stopped, and keeps working afterwards
+        assert!(strategy.process(&key(3, true), &ctx) == EventResponse::Passthrough);
+        assert!(strategy.process(&key(0x7C, false), &ctx) == EventResponse::Block);
+    }
+
+    #[tokio::test]
+    async fn test_registered_module_usable_as_strategy_type() {
+        register("test_wasm", TEST_MODULE.as_bytes()).unwrap();
+
+        let toml = r#"
+            [strategies.mine]
+            type = "test_wasm"
+            hold_ms = 200
+
+            [bindings.0x7C]
+            action = "media_next"
+            strategy = "mine"
+        "#;
+        let (_, runtime) = crate::config::load_from_str("test.toml", toml.to_string())
+            .await
+            .unwrap();
+        assert!(runtime.strategies.contains_key("mine"));
+
+        // Settings the module rejects surface as config errors
+        let toml = r#"
+            [strategies.mine]
+            type = "test_wasm"
+        "#;
+        let result = crate::config::load_from_str("test.toml", toml.to_string()).await;
+        let msg = format!("{:?}", result.unwrap_err());
+        assert!(msg.contains("failed to initialize"));
+    }
+
+    #[test]
+    fn test_module_without_entry_points_rejected() {
+        let module = Module::new(&ENGINE, "(module (memory (export \"memory\") 1))").unwrap();
+        assert!(WasmStrategy::new(&module, "").is_err());
+    }
+}
@@ -0,0 +1,364 @@
+//! Recording input events and replaying them through the engine
+//!
+//! A trace is JSON Lines, one object per handled event: the time since
+//! recording started, the event, the window focused when it arrived and the
+//! response the engine gave.
+//!
+//! ```text
+//! {"ms":0,"type":"key","key":"f13","code":124,"down":true,"window":{"title":"Inbox","class":"firefox","binary":"firefox"},"response":"block"}
+//! {"ms":1350,"type":"scroll","up":false,"window":{...},"response":"passthrough"}
+//! ```
+//!
+//! [`replay`] feeds a trace through the engine at the recorded times, against a
+//! [`MockPlatform`] reporting the recorded windows, so holds, taps and window
+//! conditions resolve as they did on the machine that recorded it while no
+//! action touches the machine replaying it. Time runs on a [`MockClock`], so a
+//! replay takes no longer than the engine needs and timers fire exactly when
+//! the recording says they would. Keys are replayed by name, so a
+//! trace recorded on Windows replays on Linux; the platform's key code is the
+//! fallback for names the replaying machine doesn't know.
+
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{LineWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use serde_json::{Map, Value, json};
+
+use crate::clock::MockClock;
+use crate::config::{RuntimeConfig, WindowInfo};
+use crate::engine;
+use crate::key::{self, InputEvent, KeyCode, KeyEvent, MouseButton};
+use crate::platform::mock::PlatformCall;
+use crate::platform::{EventResponse, MockPlatform};
+use crate::strategy::PlatformHandle;
+
+/// How far [`replay`] runs the clock past the last event for timers it started
+/// (holds, repeats, tap timeouts) to fire
+const SETTLE: Duration = Duration::from_secs(1);
+
+/// One event of a trace
+#[derive(Debug, Clone)]
+pub struct TraceEvent {
+    /// Time since the trace started
+    pub at: Duration,
+    pub event: InputEvent,
+    /// The window focused when the event arrived
+    pub window: WindowInfo,
+    /// What the engine told the OS
+    pub response: EventResponse,
+}
+
+impl TraceEvent {
+    /// The event as one line of a trace, without the newline
+    pub fn to_line(&self) -> String {
+        let mut fields = Map::new();
+        fields.insert("ms".into(), json!(self.at.as_millis() as u64));
+        let event = match &self.event {
+            InputEvent::Key(key_event) => json!({
+                "type": "key",
+                "key": key::config_name(key_event.key),
+                "code": key_event.key.code(),
+                "down": key_event.down,
+            }),
+            InputEvent::MouseButton { button, down } => {
+                json!({ "type": "mouse", "button": button.name(), "down": down })
+            }
+            InputEvent::Scroll { up } => json!({ "type": "scroll", "up": up }),
+            // Never produced by a platform, so never handed to the engine
+            InputEvent::Divert(_) => json!({}),
+        };
+        if let Value::Object(event) = event {
+            fields.extend(event);
+        }
+        fields.insert(
+            "window".into(),
+            json!({
+                "title": self.window.title,
+                "class": self.window.class,
+                "binary": self.window.binary,
+            }),
+        );
+        let response = match self.response {
+            EventResponse::Block => "block",
+            EventResponse::Passthrough | EventResponse::Continue => "passthrough",
+        };
+        fields.insert("response".into(), json!(response));
+        Value::Object(fields).to_string()
+    }
+
+    /// Parse one line of a trace. Key events get `origin + at` as their time.
+    pub fn parse(line: &str, origin: Instant) -> Result<Self, String> {
+        let json: Value = serde_json::from_str(line).map_err(|e| e.to_string())?;
+        let field = |name: &str| json.get(name).ok_or(format!("missing `{name}`"));
+        let flag = |name: &str| {
+            field(name)?
+                .as_bool()
+                .ok_or(format!("`{name}` must be true or false"))
+        };
+
+        let ms = field("ms")?
+            .as_f64()
+            .filter(|ms| *ms >= 0.0)
+            .ok_or("`ms` must be a non-negative number")?;
+        let at = Duration::from_millis(ms as u64);
+        let event = match field("type")?.as_str() {
+            Some("key") => {
+                let by_name = json
+                    .get("key")
+                    .and_then(|name| name.as_str())
+                    .and_then(KeyCode::from_config_str);
+                let key = match by_name {
+                    Some(key) => key,
+                    None => field("code")?
+                        .as_f64()
+                        .filter(|code| {
+                            code.fract() == 0.0 && (0.0..=f64::from(u32::MAX)).contains(code)
+                        })
+                        .map(|code| KeyCode::new(code as u32))
+                        .ok_or("`code` must be a key code")?,
+                };
+                InputEvent::Key(KeyEvent::at(key, flag("down")?, origin + at))
+            }
+            Some("mouse") => {
+                let name = field("button")?.as_str().unwrap_or_default();
+                let button = MouseButton::from_config_str(name)
+                    .ok_or(format!("unknown mouse button `{name}`"))?;
+                InputEvent::MouseButton {
+                    button,
+                    down: flag("down")?,
+                }
+            }
+            Some("scroll") => InputEvent::Scroll { up: flag("up")? },
+            _ => return Err("`type` must be key, mouse or scroll".to_string()),
+        };
+
+        let window = json.get("window");
+        let text = |name: &str| {
+            window
+                .and_then(|window| window.get(name))
+                .and_then(|value| value.as_str())
+                .unwrap_or_default()
+                .to_string()
+        };
+        let window = WindowInfo {
+            title: text("title"),
+            class: text("class"),
+            binary: text("binary"),
+        };
+        let response = match field("response")?.as_str() {
+            Some("block") => EventResponse::Block,
+            Some("passthrough") => EventResponse::Passthrough,
+            _ => return Err("`response` must be block or passthrough".to_string()),
+        };
+
+        Ok(Self {
+            at,
+            event,
+            window,
+            response,
+        })
+    }
+}
+
+/// An event in a few words, e.g. `f13 down` or `scroll_up`
+pub fn describe(event: &InputEvent) -> String {
+    let direction = |down: bool| if down { "down" } else { "up" };
+    match event {
+        InputEvent::Key(key_event) => format!(
+            "{} {}",
+            key::config_name(key_event.key),
+            direction(key_event.down)
+        ),
+        InputEvent::MouseButton { button, down } => {
+            format!("{} {}", button.name(), direction(*down))
+        }
+        other => other.id().to_string(),
+    }
+}
+
+/// Read a whole trace, with key events timed from `origin`
+pub fn read(path: &Path, origin: Instant) -> anyhow::Result<Vec<TraceEvent>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read trace {}", path.display()))?;
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            TraceEvent::parse(line, origin)
+                .map_err(|e| anyhow::anyhow!("{}:{}: {e}", path.display(), i + 1))
+        })
+        .collect()
+}
+
+/// Appends every handled event to a trace file, one flushed line at a time so
+/// the trace survives a crash
+pub struct Recorder {
+    file: Mutex<LineWriter<File>>,
+    started: Instant,
+}
+
+impl Recorder {
+    /// Start a new trace at `path`, replacing any file there
+    pub fn create(path: &Path) -> anyhow::Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("failed to create trace {}", path.display()))?;
+        Ok(Self {
+            file: Mutex::new(LineWriter::new(file)),
+            started: Instant::now(),
+        })
+    }
+
+    /// Record an event captured at `time`
+    ///
+    /// For keys this should be the time the platform captured them, which is
+    /// what strategies time holds from.
+    pub fn record(
+        &self,
+        event: &InputEvent,
+        time: Instant,
+        window: &WindowInfo,
+        response: EventResponse,
+    ) {
+        let line = TraceEvent {
+            at: time.saturating_duration_since(self.started),
+            event: event.clone(),
+            window: window.clone(),
+            response,
+        }
+        .to_line();
+        let mut file = self.file.lock().unwrap();
+        if let Err(e) = writeln!(file, "{line}") {
+            tracing::warn!("failed to write trace: {e}");
+        }
+    }
+}
+
+/// One replayed event and what the engine did with it this time
+#[derive(Debug)]
+pub struct Replayed {
+    pub traced: TraceEvent,
+    /// The response this time, which may differ from `traced.response`
+    pub response: EventResponse,
+    /// Platform calls made from this event until the next one, including those
+    /// made by timers it started
+    pub calls: Vec<PlatformCall>,
+}
+
+/// Feed `trace`, read with [`read`] from `origin`, through the engine on a
+/// clock that starts at `origin` and jumps from one event's time to the next.
+/// Returns once the last event's timers had time to fire.
+pub async fn replay(
+    config: &RuntimeConfig,
+    trace: Vec<TraceEvent>,
+    origin: Instant,
+) -> Vec<Replayed> {
+    let mock = MockPlatform::new();
+    let clock = Arc::new(MockClock::new(origin));
+    let platform = PlatformHandle::from_mock(&mock).with_clock(clock.clone());
+    let last_window = RefCell::new(None);
+    let take_calls = || {
+        let calls = mock.calls();
+        mock.clear_calls();
+        calls
+    };
+
+    let mut replayed: Vec<Replayed> = Vec::with_capacity(trace.len());
+    for traced in trace {
+        clock.advance_to(origin + traced.at).await;
+        if let Some(previous) = replayed.last_mut() {
+            previous.calls.extend(take_calls());
+        }
+        mock.set_window(traced.window.clone());
+        let response =
+            engine::handle_event(traced.event.clone(), platform.clone(), config, &last_window)
+                .await;
+        replayed.push(Replayed {
+            traced,
+            response,
+            calls: Vec::new(),
+        });
+    }
+
+    clock.advance(SETTLE).await;
+    if let Some(last) = replayed.last_mut() {
+        last.calls.extend(take_calls());
+    }
+    replayed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::load_from_str;
+    use crate::platform::MediaCommand;
+
+    #[test]
+    fn test_trace_line_round_trip() {
+        let origin = Instant::now();
+        let events = [
+            InputEvent::Key(KeyEvent::at(KeyCode::new(0x7C), true, origin)),
+            InputEvent::MouseButton {
+                button: MouseButton::Back,
+                down: false,
+            },
+            InputEvent::Scroll { up: true },
+        ];
+        for event in events {
+            let traced = TraceEvent {
+                at: Duration::from_millis(1250),
+                event,
+                window: WindowInfo {
+                    title: "Inbox \"1\"".to_string(),
+                    class: "firefox".to_string(),
+                    binary: "firefox".to_string(),
+                },
+                response: EventResponse::Block,
+            };
+            let line = traced.to_line();
+            let parsed = TraceEvent::parse(&line, origin).unwrap();
+            assert_eq!(parsed.at, traced.at, "{line}");
+            assert_eq!(parsed.event.id(), traced.event.id(), "{line}");
+            assert_eq!(parsed.window.title, "Inbox \"1\"");
+            assert_eq!(parsed.response, EventResponse::Block);
+        }
+
+        let err = TraceEvent::parse(r#"{"ms":0,"type":"key","down":true}"#, origin).unwrap_err();
+        assert!(err.contains("code"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_replay_in_recorded_window() {
+        let toml = r#"
+            [bindings.f13]
+            action = [
+                { condition = { window = { binary = "mpv" } }, action = "media_next" },
+                { action = "passthrough" },
+            ]
+        "#;
+        let (_, runtime) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+        let trace = r#"
+            {"ms":0,"type":"key","key":"f13","code":124,"down":true,"window":{"binary":"mpv"},"response":"block"}
+            {"ms":20,"type":"key","key":"f13","code":0,"down":true,"window":{"binary":"kitty"},"response":"block"}
+        "#;
+        let origin = Instant::now();
+        let trace = trace
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| TraceEvent::parse(line.trim(), origin).unwrap())
+            .collect();
+
+        let replayed = replay(&runtime, trace, origin).await;
+        assert_eq!(replayed[0].response, EventResponse::Block);
+        assert_eq!(
+            replayed[0].calls,
+            [PlatformCall::SendMedia(MediaCommand::Next)]
+        );
+        // Recorded as blocked, but the config now lets it through
+        assert_eq!(replayed[1].response, EventResponse::Passthrough);
+        assert!(replayed[1].calls.is_empty());
+    }
+}
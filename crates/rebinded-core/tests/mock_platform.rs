@@ -0,0 +1,40 @@
+//! Driving the engine end to end through the public API, as a crate embedding
+//! rebinded-core would in its own tests
+
+use rebinded_core::config::{WindowInfo, load_from_str};
+use rebinded_core::engine;
+use rebinded_core::key::{InputEvent, KeyCode, KeyEvent};
+use rebinded_core::platform::mock::PlatformCall;
+use rebinded_core::platform::{EventResponse, MediaCommand, MockPlatform};
+
+#[tokio::test]
+async fn test_config_against_mock_platform() {
+    let toml = r#"
+        [bindings.f13]
+        action = [
+            { condition = { window = { binary = "mpv" } }, action = "media_next" },
+            { action = "passthrough" },
+        ]
+    "#;
+    let (_, runtime) = load_from_str("test.toml", toml.to_string()).await.unwrap();
+    let f13 = KeyCode::from_config_str("f13").unwrap();
+
+    let mut platform = MockPlatform::new();
+    platform.set_window(WindowInfo {
+        binary: "mpv".to_string(),
+        ..WindowInfo::default()
+    });
+    platform.push_event(InputEvent::Key(KeyEvent::new(f13, true)));
+    engine::run(&mut platform, &runtime, std::future::pending())
+        .await
+        .unwrap();
+    platform.assert_calls(&[PlatformCall::SendMedia(MediaCommand::Next)]);
+
+    let mut platform = MockPlatform::new();
+    platform.push_event(InputEvent::Key(KeyEvent::new(f13, true)));
+    engine::run(&mut platform, &runtime, std::future::pending())
+        .await
+        .unwrap();
+    platform.assert_no_calls();
+    assert_eq!(platform.responses(), [EventResponse::Passthrough]);
+}
@@ -0,0 +1,171 @@
+//! Finding the config file when `--config` isn't given
+//!
+//! `REBINDED_CONFIG` names the file outright. Otherwise `rebinded/config.toml`
+//! is looked up in `$XDG_CONFIG_HOME` (default `~/.config`) and then in each of
+//! `$XDG_CONFIG_DIRS` (default `/etc/xdg`), and the first that exists is used.
+//! On Windows the only location is `%APPDATA%\rebinded`. When no config exists
+//! yet the user location is returned, so errors and `record --append` point
+//! where the file should go.
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+pub const ENV_VAR: &str = "REBINDED_CONFIG";
+
+/// A config path and what it was taken from, for the log
+#[derive(Debug, PartialEq)]
+pub struct Resolved {
+    pub path: PathBuf,
+    pub source: &'static str,
+}
+
+/// Resolve the config path from the process environment
+pub fn resolve() -> Resolved {
+    resolve_with(|name| std::env::var_os(name), Path::is_file)
+}
+
+/// The path from `REBINDED_CONFIG`, if set
+pub fn from_env() -> Option<PathBuf> {
+    env_path(std::env::var_os(ENV_VAR))
+}
+
+fn env_path(value: Option<OsString>) -> Option<PathBuf> {
+    value.filter(|value| !value.is_empty()).map(PathBuf::from)
+}
+
+fn resolve_with(
+    var: impl Fn(&str) -> Option<OsString>,
+    exists: impl Fn(&Path) -> bool,
+) -> Resolved {
+    if let Some(path) = env_path(var(ENV_VAR)) {
+        return Resolved {
+            path,
+            source: ENV_VAR,
+        };
+    }
+
+    let (user_dir, user_source) = user_config_dir(&var);
+    let user = Resolved {
+        path: user_dir.join("rebinded").join("config.toml"),
+        source: user_source,
+    };
+    if exists(&user.path) {
+        return user;
+    }
+    system_config_dirs(&var)
+        .into_iter()
+        .map(|dir| dir.join("rebinded").join("config.toml"))
+        .find(|path| exists(path))
+        .map(|path| Resolved {
+            path,
+            source: "XDG_CONFIG_DIRS",
+        })
+        .unwrap_or(user)
+}
+
+/// `$XDG_CONFIG_HOME`, or `~/.config` when it is unset or relative, as the
+/// spec says to ignore relative paths
+#[cfg(unix)]
+fn user_config_dir(var: impl Fn(&str) -> Option<OsString>) -> (PathBuf, &'static str) {
+    if let Some(dir) = var("XDG_CONFIG_HOME").map(PathBuf::from)
+        && dir.is_absolute()
+    {
+        return (dir, "XDG_CONFIG_HOME");
+    }
+    let home = var("HOME")
+        .map(PathBuf::from)
+        .or_else(dirs::home_dir)
+        .unwrap_or_else(|| PathBuf::from("."));
+    (home.join(".config"), "default")
+}
+
+#[cfg(windows)]
+fn user_config_dir(_var: impl Fn(&str) -> Option<OsString>) -> (PathBuf, &'static str) {
+    (
+        dirs::config_dir().unwrap_or_else(|| PathBuf::from(".")),
+        "default",
+    )
+}
+
+/// `$XDG_CONFIG_DIRS` in order of preference, skipping relative entries
+#[cfg(unix)]
+fn system_config_dirs(var: impl Fn(&str) -> Option<OsString>) -> Vec<PathBuf> {
+    let dirs = var("XDG_CONFIG_DIRS").filter(|dirs| !dirs.is_empty());
+    let dirs = dirs.unwrap_or_else(|| OsString::from("/etc/xdg"));
+    std::env::split_paths(&dirs)
+        .filter(|dir| dir.is_absolute())
+        .collect()
+}
+
+#[cfg(windows)]
+fn system_config_dirs(_var: impl Fn(&str) -> Option<OsString>) -> Vec<PathBuf> {
+    Vec::new()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn env<'a>(vars: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<OsString> + 'a {
+        |name| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| OsString::from(value))
+        }
+    }
+
+    #[test]
+    fn test_resolve_order() {
+        let vars = [
+            ("HOME", "/home/me"),
+            ("XDG_CONFIG_DIRS", "/etc/xdg/custom:relative:/etc/xdg"),
+        ];
+        let nothing_exists = |_: &Path| false;
+
+        // Nothing exists: the user location, for errors to point at
+        assert_eq!(
+            resolve_with(env(&vars), nothing_exists),
+            Resolved {
+                path: PathBuf::from("/home/me/.config/rebinded/config.toml"),
+                source: "default",
+            }
+        );
+
+        // A system-wide config is used until the user has one
+        let system = |path: &Path| path == Path::new("/etc/xdg/rebinded/config.toml");
+        assert_eq!(
+            resolve_with(env(&vars), system).path,
+            PathBuf::from("/etc/xdg/rebinded/config.toml")
+        );
+        let both = |path: &Path| system(path) || path.starts_with("/home/me");
+        assert_eq!(resolve_with(env(&vars), both).source, "default");
+
+        // A relative XDG_CONFIG_HOME is ignored
+        let vars = [("HOME", "/home/me"), ("XDG_CONFIG_HOME", "conf")];
+        assert_eq!(
+            resolve_with(env(&vars), nothing_exists).path,
+            PathBuf::from("/home/me/.config/rebinded/config.toml")
+        );
+
+        let vars = [("HOME", "/home/me"), ("XDG_CONFIG_HOME", "/cfg")];
+        assert_eq!(
+            resolve_with(env(&vars), nothing_exists),
+            Resolved {
+                path: PathBuf::from("/cfg/rebinded/config.toml"),
+                source: "XDG_CONFIG_HOME",
+            }
+        );
+
+        // REBINDED_CONFIG wins even over an existing config, and need not exist
+        let vars = [("HOME", "/home/me"), (ENV_VAR, "/srv/work.toml")];
+        assert_eq!(
+            resolve_with(env(&vars), |_| true),
+            Resolved {
+                path: PathBuf::from("/srv/work.toml"),
+                source: ENV_VAR,
+            }
+        );
+        let vars = [("HOME", "/home/me"), (ENV_VAR, "")];
+        assert_eq!(resolve_with(env(&vars), |_| true).source, "default");
+    }
+}
@@ -0,0 +1,915 @@
+//! Control channel for a running daemon
+//!
+//! The daemon listens on a Unix domain socket (`$XDG_RUNTIME_DIR/rebinded.sock`)
+//! on Linux and a named pipe (`\\.\pipe\rebinded-<user>`) on Windows. A client
+//! sends one request per connection as a line and reads the reply until the
+//! daemon closes the connection. Lines starting with `{` are JSON-RPC requests
+//! and get JSON-RPC replies; anything else is a text command answered with
+//! text. The protocol and its methods are described in
+//! [`rebinded_core::control`].
+//!
+//! `rebinded ctl <command>` is the bundled client.
+
+use std::cell::{Cell, OnceCell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::watch;
+use tracing::{debug, info, warn};
+
+use rebinded_core::control::{
+    self as protocol, REQUEST_FAILED, Request, Response, RpcError, Status,
+};
+
+use crate::config::{self, Action, LoadOptions, RuntimeConfig};
+use crate::integrations::Settings;
+use crate::key::{InputEvent, InputEventId, KeyCode};
+use crate::latency::LatencyStats;
+use crate::platform::{self, AutoDisableSettings, KeyRoutes, PlatformState};
+use crate::strategy::{PlatformHandle, StrategyContext};
+
+/// How long a client gets to send its command before the connection is dropped
+const READ_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Runtime state of the daemon that control commands act on
+pub struct Daemon {
+    config_path: PathBuf,
+    /// How reloads and profile switches read config files
+    load_options: Cell<LoadOptions>,
+    /// Active profile and the file it was loaded from
+    profile: RefCell<Option<(String, PathBuf)>>,
+    runtime: RefCell<Rc<RuntimeConfig>>,
+    /// Keys pressed and not yet released, with the config that handled the press
+    held: RefCell<HashMap<KeyCode, Rc<RuntimeConfig>>>,
+    paused: watch::Sender<bool>,
+    /// When to pass every key through on its own, e.g. while a game is focused
+    auto_disable: RefCell<AutoDisableSettings>,
+    /// Passing every key through because of `auto_disable`, apart from any pause
+    auto_disabled: Cell<bool>,
+    /// Announce profile switches, pauses and auto-disable with a notification
+    notify_mode_changes: Cell<bool>,
+    disabled: RefCell<HashSet<KeyCode>>,
+    latency: RefCell<LatencyStats>,
+    /// Events slower than this are logged
+    slow_event: Cell<Duration>,
+    dry_run: Cell<bool>,
+    /// Handle strategies act through when reset outside an event, set on the first event
+    platform: OnceCell<PlatformHandle>,
+    /// Focused window, key routes and integrations of the platform this daemon drives
+    state: Arc<PlatformState>,
+}
+
+impl Daemon {
+    /// A daemon driving the platform that owns `state`
+    pub fn new(config_path: PathBuf, runtime: RuntimeConfig, state: Arc<PlatformState>) -> Self {
+        state.capture_scancodes(runtime.scancodes());
+        state.capture_midi(runtime.uses_midi());
+        state.capture_gamepads(runtime.uses_gamepad());
+        state.capture_hid(runtime.hid.clone());
+        state.set_trigger_targets(runtime.trigger_targets());
+        let daemon = Self {
+            config_path,
+            load_options: Cell::new(LoadOptions::default()),
+            profile: RefCell::new(None),
+            runtime: RefCell::new(Rc::new(runtime)),
+            held: RefCell::new(HashMap::new()),
+            paused: watch::Sender::new(false),
+            auto_disable: RefCell::new(AutoDisableSettings::default()),
+            auto_disabled: Cell::new(false),
+            notify_mode_changes: Cell::new(false),
+            disabled: RefCell::new(HashSet::new()),
+            latency: RefCell::new(LatencyStats::default()),
+            slow_event: Cell::new(Duration::from_millis(Settings::default().slow_event_ms)),
+            dry_run: Cell::new(false),
+            platform: OnceCell::new(),
+            state,
+        };
+        daemon.publish_routes();
+        daemon
+    }
+
+    /// Read configs with `options` on reloads and profile switches
+    pub fn set_load_options(&self, options: LoadOptions) {
+        self.load_options.set(options);
+    }
+
+    /// In a dry run nothing is blocked, so no key is blocked by the platform directly
+    pub fn set_dry_run(&self, dry_run: bool) {
+        self.dry_run.set(dry_run);
+        self.publish_routes();
+    }
+
+    /// Remember the handle events are handled through, so strategies reset by
+    /// a pause or a disabled key release their keys the same way
+    pub fn attach_platform(&self, platform: &PlatformHandle) {
+        self.platform.get_or_init(|| platform.clone());
+    }
+
+    /// Tell the platform which keys need the event handler
+    fn publish_routes(&self) {
+        self.state.set_key_routes(self.key_routes());
+    }
+
+    /// Which keys need the event handler, accounting for pause, disabled keys,
+    /// dry runs and keys still held from before a reload
+    fn key_routes(&self) -> Option<KeyRoutes> {
+        if self.is_paused() || self.auto_disabled.get() {
+            Some(KeyRoutes::default())
+        } else {
+            let current = self.runtime();
+            current.key_routes().map(|mut routes| {
+                // Releases of keys pressed under a previous config go to that config
+                for (key, runtime) in self.held.borrow().iter() {
+                    if !Rc::ptr_eq(runtime, &current) {
+                        routes.blocked.remove(key);
+                        routes.handled.insert(*key);
+                    }
+                }
+                let disabled = self.disabled.borrow();
+                routes.handled.retain(|key| !disabled.contains(key));
+                routes.blocked.retain(|key| !disabled.contains(key));
+                if self.dry_run.get() {
+                    routes.blocked.clear();
+                }
+                routes
+            })
+        }
+    }
+
+    /// Apply the `[settings]` table of a newly loaded config
+    pub fn configure(&self, settings: &Settings) {
+        self.state.integrations().configure(settings);
+        self.state.filter_gamepads(settings.gamepad.devices.clone());
+        self.state.connect_lirc(settings.lirc.socket.clone());
+        self.state.configure_cec(settings.cec.clone());
+        self.state.configure_trigger(settings.trigger.clone());
+        self.state.set_injection_mode(settings.injection);
+        self.slow_event
+            .set(Duration::from_millis(settings.slow_event_ms));
+        self.notify_mode_changes.set(settings.notify_mode_changes);
+        *self.auto_disable.borrow_mut() = settings.auto_disable.clone();
+        self.check_auto_disable();
+    }
+
+    /// Pass every key through while `[settings.auto_disable]` applies to the
+    /// focused app, and go back to normal once it doesn't
+    pub fn check_auto_disable(&self) {
+        let settings = self.auto_disable.borrow();
+        let window = self.state.focused_window();
+        let disable = settings.is_enabled() && settings.applies(window.as_ref());
+        if disable == self.auto_disabled.replace(disable) {
+            return;
+        }
+        self.publish_routes();
+
+        let binary = window.map(|window| window.binary).unwrap_or_default();
+        let message = if disable {
+            info!(
+                binary,
+                "fullscreen app or game focused, passing all keys through"
+            );
+            format!("Disabled while {binary} is focused")
+        } else {
+            info!("left fullscreen app or game, re-enabled");
+            "Re-enabled".to_string()
+        };
+        if settings.notify || self.notify_mode_changes.get() {
+            platform::show_notification(&message);
+        }
+    }
+
+    /// Tell the user which mode their keys are in now, if they asked to be told
+    fn announce_mode(&self, message: &str) {
+        if self.notify_mode_changes.get() {
+            platform::show_notification(message);
+        }
+    }
+
+    /// Record how long an event took to handle, warning when it was slow
+    pub fn record_latency(&self, event: &InputEventId, elapsed: Duration) {
+        let budget = self.slow_event.get();
+        let slow = elapsed > budget;
+        if slow {
+            warn!(
+                %event,
+                elapsed_ms = elapsed.as_secs_f64() * 1000.0,
+                budget_ms = budget.as_millis() as u64,
+                "slow event handling"
+            );
+        }
+        self.latency.borrow_mut().record(elapsed, slow);
+    }
+
+    /// The config currently in effect. Events keep the config they started with
+    /// even if a reload swaps it out mid-event.
+    pub fn runtime(&self) -> Rc<RuntimeConfig> {
+        self.runtime.borrow().clone()
+    }
+
+    /// The config to handle `event` with. A key's repeats and release go to the
+    /// config that handled its press, so a reload while the key is down can't
+    /// strand a strategy mid-decision or leave the key blocked.
+    pub fn runtime_for(&self, event: &InputEvent) -> Rc<RuntimeConfig> {
+        let InputEvent::Key(key_event) = event else {
+            return self.runtime();
+        };
+        let mut held = self.held.borrow_mut();
+        if key_event.down {
+            return Rc::clone(held.entry(key_event.key).or_insert_with(|| self.runtime()));
+        }
+        let Some(runtime) = held.remove(&key_event.key) else {
+            return self.runtime();
+        };
+        drop(held);
+        if !Rc::ptr_eq(&runtime, &self.runtime.borrow()) {
+            debug!(key = %key_event.key, "release handled by the config it was pressed under");
+            self.publish_routes();
+        }
+        runtime
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.paused.borrow()
+    }
+
+    /// Follow pause state changes, whichever client makes them
+    pub fn watch_paused(&self) -> watch::Receiver<bool> {
+        self.paused.subscribe()
+    }
+
+    /// Whether an event should skip all bindings and strategies
+    pub fn bypasses(&self, event: &InputEvent) -> bool {
+        if self.is_paused() || self.auto_disabled.get() {
+            return true;
+        }
+        match event {
+            InputEvent::Key(key_event) => self.disabled.borrow().contains(&key_event.key),
+            InputEvent::Divert(event) => self.bypasses(event),
+            InputEvent::MouseButton { .. } | InputEvent::Scroll { .. } => false,
+        }
+    }
+
+    /// Path of the config file for the active profile
+    pub fn active_config_path(&self) -> PathBuf {
+        match &*self.profile.borrow() {
+            Some((_, path)) => path.clone(),
+            None => self.config_path.clone(),
+        }
+    }
+
+    /// Carry out a request. Failures carry the text to show the client.
+    pub async fn apply(&self, request: Request) -> Result<Response, String> {
+        match request {
+            Request::Pause => Ok(self.set_paused(true)),
+            Request::Resume => Ok(self.set_paused(false)),
+            Request::Toggle => Ok(self.set_paused(!self.is_paused())),
+            Request::Reload => {
+                let path = self.active_config_path();
+                self.load(&path).await?;
+                Ok(Response::Reloaded(path))
+            }
+            Request::Profile(name) => {
+                let path = match &name {
+                    Some(name) => profile_path(&self.config_path, name)?,
+                    None => self.config_path.clone(),
+                };
+                self.load(&path).await?;
+                *self.profile.borrow_mut() = name.clone().map(|name| (name, path));
+                self.announce_mode(&format!(
+                    "Profile: {}",
+                    name.as_deref().unwrap_or("default")
+                ));
+                Ok(Response::ProfileSwitched(name))
+            }
+            Request::Enable(key) => {
+                self.disabled.borrow_mut().remove(&key);
+                self.publish_routes();
+                Ok(Response::KeyEnabled { key, enabled: true })
+            }
+            Request::Disable(key) => {
+                self.disabled.borrow_mut().insert(key);
+                // Its release will bypass the strategies, so drop what they hold for it now
+                let runtime = self.held.borrow_mut().remove(&key);
+                let runtime = runtime.unwrap_or_else(|| self.runtime());
+                let id = InputEventId::Key(key);
+                let mut names: Vec<&str> = runtime
+                    .subscriptions
+                    .get(&id)
+                    .into_iter()
+                    .flatten()
+                    .map(String::as_str)
+                    .collect();
+                if let Some(binding) = runtime.bindings.get(&key) {
+                    names.extend(binding.strategies.iter().map(|name| name.value().as_str()));
+                }
+                // Only these strategies: keys injected for other bindings may still be held
+                self.reset_strategies(&runtime, |name| names.contains(&name));
+                self.publish_routes();
+                Ok(Response::KeyEnabled {
+                    key,
+                    enabled: false,
+                })
+            }
+            Request::Status => Ok(Response::Status(self.status())),
+            Request::State => {
+                let runtime = self.runtime();
+                let mut states: Vec<(String, serde_json::Value)> = runtime
+                    .strategies
+                    .iter()
+                    .map(|(name, strategy)| (name.clone(), strategy.borrow().debug_state()))
+                    .collect();
+                states.sort_by(|(a, _), (b, _)| a.cmp(b));
+                Ok(Response::State(states))
+            }
+            Request::Stats => Ok(Response::Stats(
+                self.latency.borrow().summary(self.slow_event.get()),
+            )),
+        }
+    }
+
+    fn set_paused(&self, paused: bool) -> Response {
+        if self.paused.send_replace(paused) != paused {
+            if paused {
+                // Releases while paused never reach the strategies, so nothing
+                // they hold or time may outlive the pause
+                let mut runtimes = vec![self.runtime()];
+                for (_, runtime) in self.held.borrow_mut().drain() {
+                    if !runtimes.iter().any(|seen| Rc::ptr_eq(seen, &runtime)) {
+                        runtimes.push(runtime);
+                    }
+                }
+                for runtime in &runtimes {
+                    self.reset_strategies(runtime, |_| true);
+                }
+                platform::release_held_keys();
+            }
+            self.announce_mode(if paused { "Paused" } else { "Resumed" });
+        }
+        self.publish_routes();
+        Response::Paused(paused)
+    }
+
+    /// Reset the strategies of `runtime` that `affected` picks by name. Before
+    /// the first event no strategy has anything in flight, so there is nothing to do.
+    fn reset_strategies(&self, runtime: &RuntimeConfig, affected: impl Fn(&str) -> bool) {
+        let Some(platform) = self.platform.get() else {
+            return;
+        };
+        let ctx = StrategyContext::new(platform.clone(), &Action::Block);
+        for (name, strategy) in &runtime.strategies {
+            if affected(name) {
+                debug!(strategy = name, "resetting");
+                strategy.borrow_mut().reset(&ctx);
+            }
+        }
+    }
+
+    fn status(&self) -> Status {
+        let runtime = self.runtime();
+        let mut disabled: Vec<KeyCode> = self.disabled.borrow().iter().copied().collect();
+        disabled.sort_by_key(|key| key.to_string());
+        Status {
+            paused: self.is_paused(),
+            profile: self.profile.borrow().as_ref().map(|(name, _)| name.clone()),
+            config: self.active_config_path(),
+            bindings: runtime.bindings.len(),
+            strategies: runtime.strategies.len(),
+            disabled,
+        }
+    }
+
+    /// Load a config file and make it the active one. On failure the current
+    /// config stays in effect and the rendered diagnostics are returned.
+    async fn load(&self, path: &Path) -> Result<(), String> {
+        let options = self.load_options.get();
+        let (config, runtime) = config::load_with(path, options).await.map_err(|err| {
+            let mut rendered = String::new();
+            let handler = miette::GraphicalReportHandler::new_themed(
+                miette::GraphicalTheme::unicode_nocolor(),
+            );
+            let report = miette::Report::new(err);
+            if handler
+                .render_report(&mut rendered, report.as_ref())
+                .is_err()
+            {
+                rendered = report.to_string();
+            }
+            rendered
+        })?;
+
+        self.configure(&config.settings);
+        info!(
+            "loaded {}: {} key bindings, {} strategies",
+            path.display(),
+            runtime.bindings.len(),
+            runtime.strategies.len()
+        );
+        self.state.capture_scancodes(runtime.scancodes());
+        self.state.capture_midi(runtime.uses_midi());
+        self.state.capture_gamepads(runtime.uses_gamepad());
+        self.state.capture_hid(runtime.hid.clone());
+        self.state.set_trigger_targets(runtime.trigger_targets());
+        *self.runtime.borrow_mut() = Rc::new(runtime);
+        self.publish_routes();
+        Ok(())
+    }
+}
+
+/// `profiles/<name>.toml` beside the main config
+///
+/// The name comes from a control client, so anything that isn't a plain file
+/// name is refused rather than allowed to reach outside `profiles/`.
+fn profile_path(config_path: &Path, name: &str) -> Result<PathBuf, String> {
+    if config::is_url(&config_path.to_string_lossy()) {
+        return Err("profiles need a local config file, not a remote URL".to_string());
+    }
+    let file_name = format!("{name}.toml");
+    if name.is_empty()
+        || name.contains(['/', '\\'])
+        || name.contains("..")
+        || Path::new(&file_name).file_name() != Some(file_name.as_ref())
+    {
+        return Err(format!("invalid profile name '{name}'"));
+    }
+    Ok(config_path
+        .parent()
+        .unwrap_or(Path::new("."))
+        .join("profiles")
+        .join(file_name))
+}
+
+/// Read one request from a connection and write back the reply
+async fn handle_connection<S>(stream: S, daemon: &Daemon)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut stream = BufReader::new(stream);
+    let mut line = String::new();
+    // A line that fills the limit without ending is cut off, not buffered on
+    let mut limited = (&mut stream).take(protocol::MAX_LINE_LEN as u64);
+    let read = limited.read_line(&mut line);
+    let reply = match tokio::time::timeout(READ_TIMEOUT, read).await {
+        Ok(Ok(len)) if len == protocol::MAX_LINE_LEN && !line.ends_with('\n') => {
+            "error: command too long".to_string()
+        }
+        Ok(Ok(_)) => {
+            debug!(command = line.trim(), "control command");
+            if line.trim_start().starts_with('{') {
+                json_rpc_reply(&line, daemon).await
+            } else {
+                let reply = match Request::parse(&line) {
+                    Ok(request) => daemon.apply(request).await,
+                    Err(e) => Err(e),
+                };
+                match reply {
+                    Ok(response) => response.to_string(),
+                    Err(e) => format!("error: {e}"),
+                }
+            }
+        }
+        Ok(Err(e)) => format!("error: failed to read command: {e}"),
+        Err(_) => "error: timed out waiting for a command".to_string(),
+    };
+
+    let stream = stream.get_mut();
+    if let Err(e) = stream.write_all(format!("{reply}\n").as_bytes()).await {
+        debug!("failed to send control reply: {e}");
+    }
+    let _ = stream.shutdown().await;
+}
+
+/// Carry out a JSON-RPC request line, returning the JSON-RPC reply
+async fn json_rpc_reply(line: &str, daemon: &Daemon) -> String {
+    let (id, outcome) = match Request::from_json_rpc(line) {
+        Ok((id, request)) => {
+            let outcome = daemon.apply(request).await;
+            (id, outcome.map_err(|e| RpcError::new(REQUEST_FAILED, e)))
+        }
+        Err((id, error)) => (id, Err(error)),
+    };
+    protocol::reply(id, &outcome)
+}
+
+/// Socket path shared by the daemon and `rebinded ctl`
+#[cfg(unix)]
+pub fn socket_path() -> PathBuf {
+    match dirs::runtime_dir() {
+        Some(dir) => dir.join("rebinded.sock"),
+        // SAFETY: getuid has no preconditions and cannot fail
+        None => std::env::temp_dir()
+            .join(format!("rebinded-{}", unsafe { libc::getuid() }))
+            .join("rebinded.sock"),
+    }
+}
+
+/// Create `dir` readable only by us, or check that it already is
+///
+/// The socket in it is only chmod'ed after `bind`, so in a shared directory
+/// like `/tmp` another user could connect in between; a private directory
+/// closes that gap. A directory someone else created is refused.
+#[cfg(unix)]
+fn private_dir(dir: &Path) -> std::io::Result<()> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+
+    match std::fs::DirBuilder::new().mode(0o700).create(dir) {
+        Err(e) if e.kind() != std::io::ErrorKind::AlreadyExists => return Err(e),
+        _ => {}
+    }
+    let metadata = std::fs::symlink_metadata(dir)?;
+    // SAFETY: getuid has no preconditions and cannot fail
+    let uid = unsafe { libc::getuid() };
+    if !metadata.is_dir() || metadata.uid() != uid || metadata.mode() & 0o077 != 0 {
+        return Err(std::io::Error::other(format!(
+            "{} is not a private directory owned by this user",
+            dir.display()
+        )));
+    }
+    Ok(())
+}
+
+/// Pipe name shared by the daemon and `rebinded ctl`
+#[cfg(windows)]
+pub fn socket_path() -> PathBuf {
+    let user = std::env::var("USERNAME").unwrap_or_default();
+    PathBuf::from(format!(r"\\.\pipe\rebinded-{user}"))
+}
+
+/// Accept control connections until the daemon exits. Commands are handled one
+/// at a time; if the channel can't be opened, control is unavailable but the
+/// daemon keeps running.
+#[cfg(unix)]
+pub async fn serve(daemon: &Daemon) {
+    use std::os::unix::fs::PermissionsExt;
+    use tokio::net::{UnixListener, UnixStream};
+
+    let path = socket_path();
+    // $XDG_RUNTIME_DIR is private already; the fallback under the temp dir isn't
+    if dirs::runtime_dir().is_none()
+        && let Some(dir) = path.parent()
+        && let Err(e) = private_dir(dir)
+    {
+        warn!("control socket unavailable: {e}");
+        return std::future::pending().await;
+    }
+    if path.exists() {
+        if UnixStream::connect(&path).await.is_ok() {
+            warn!(
+                "control socket {} is in use by another instance; control disabled",
+                path.display()
+            );
+            return std::future::pending().await;
+        }
+        // Left behind by a daemon that didn't shut down cleanly
+        let _ = std::fs::remove_file(&path);
+    }
+
+    let listener = match UnixListener::bind(&path) {
+        Ok(listener) => listener,
+        Err(e) => {
+            warn!("failed to bind control socket {}: {e}", path.display());
+            return std::future::pending().await;
+        }
+    };
+    if let Err(e) = std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)) {
+        warn!("failed to restrict control socket permissions: {e}");
+    }
+    info!("control socket listening on {}", path.display());
+
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => handle_connection(stream, daemon).await,
+            Err(e) => warn!("failed to accept control connection: {e}"),
+        }
+    }
+}
+
+#[cfg(windows)]
+pub async fn serve(daemon: &Daemon) {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let path = socket_path();
+    let mut server = match ServerOptions::new().first_pipe_instance(true).create(&path) {
+        Ok(server) => server,
+        Err(e) => {
+            warn!("failed to create control pipe {}: {e}", path.display());
+            return std::future::pending().await;
+        }
+    };
+    info!("control pipe listening on {}", path.display());
+
+    loop {
+        if let Err(e) = server.connect().await {
+            warn!("failed to accept control connection: {e}");
+            continue;
+        }
+        let next = match ServerOptions::new().create(&path) {
+            Ok(next) => next,
+            Err(e) => {
+                warn!("failed to create control pipe instance: {e}");
+                return std::future::pending().await;
+            }
+        };
+        let connected = std::mem::replace(&mut server, next);
+        handle_connection(connected, daemon).await;
+    }
+}
+
+/// Send a request to the running daemon and return its reply. The outer error
+/// is a failure to reach the daemon, the inner one the daemon's answer.
+pub async fn send(request: &Request) -> anyhow::Result<Result<Response, RpcError>> {
+    let path = socket_path();
+    #[cfg(unix)]
+    let stream = tokio::net::UnixStream::connect(&path).await;
+    #[cfg(windows)]
+    let stream = tokio::net::windows::named_pipe::ClientOptions::new().open(&path);
+    let mut stream =
+        stream.map_err(|e| anyhow::anyhow!("is rebinded running? ({}: {e})", path.display()))?;
+
+    stream
+        .write_all(format!("{}\n", request.to_json_rpc(1)).as_bytes())
+        .await?;
+    let mut reply = String::new();
+    stream
+        .take(protocol::MAX_LINE_LEN as u64 + 1)
+        .read_to_string(&mut reply)
+        .await?;
+    Ok(protocol::parse_reply(request, &reply))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn daemon(config: &str) -> Daemon {
+        let (config, runtime) = config::load_from_str("test.toml", config.to_string())
+            .await
+            .unwrap();
+        let daemon = Daemon::new(
+            PathBuf::from("/nonexistent/config.toml"),
+            runtime,
+            Arc::default(),
+        );
+        daemon.configure(&config.settings);
+        daemon
+    }
+
+    #[tokio::test]
+    async fn test_pause_and_disable() {
+        let daemon = daemon("[bindings.0x7C]\naction = \"media_next\"").await;
+        let press = |code| InputEvent::Key(crate::key::KeyEvent::new(KeyCode::new(code), true));
+
+        assert!(!daemon.bypasses(&press(0x7C)));
+        daemon
+            .apply(Request::Disable(KeyCode::new(0x7C)))
+            .await
+            .unwrap();
+        assert!(daemon.bypasses(&press(0x7C)));
+        assert!(!daemon.bypasses(&press(0x7D)));
+        assert_eq!(daemon.status().disabled, vec![KeyCode::new(0x7C)]);
+        daemon
+            .apply(Request::Enable(KeyCode::new(0x7C)))
+            .await
+            .unwrap();
+        assert!(!daemon.bypasses(&press(0x7C)));
+
+        assert_eq!(
+            daemon.apply(Request::Toggle).await.unwrap(),
+            Response::Paused(true)
+        );
+        assert!(daemon.bypasses(&InputEvent::Scroll { up: true }));
+        assert!(daemon.status().paused);
+        daemon.apply(Request::Resume).await.unwrap();
+        assert!(!daemon.bypasses(&InputEvent::Scroll { up: true }));
+    }
+
+    #[tokio::test]
+    async fn test_pause_and_disable_reset_strategies() {
+        use crate::platform::MockPlatform;
+        use crate::platform::mock::PlatformCall;
+
+        let daemon = daemon(
+            "[strategies.caps]\ntype = \"tap_hold\"\nhold_ms = 20\nhold_key = \"0x1D\"\n\
+             [strategies.tab]\ntype = \"tap_hold\"\nhold_ms = 20\nhold_key = \"0x2A\"\n\
+             [bindings.0x3A]\naction = \"block\"\nstrategy = \"caps\"\n\
+             [bindings.0x0F]\naction = \"block\"\nstrategy = \"tab\"",
+        )
+        .await;
+        let platform = MockPlatform::new();
+        let handle = PlatformHandle::from_mock(&platform);
+        daemon.attach_platform(&handle);
+        let ctrl = |down| PlatformCall::SendRawKey {
+            key: KeyCode::new(0x1D),
+            down,
+        };
+        let shift = |down| PlatformCall::SendRawKey {
+            key: KeyCode::new(0x2A),
+            down,
+        };
+        let (daemon, handle) = (&daemon, &handle);
+        let hold = |key, strategy| async move {
+            let press = InputEvent::Key(crate::key::KeyEvent::new(KeyCode::new(key), true));
+            let runtime = daemon.runtime_for(&press);
+            let ctx = StrategyContext::new(handle.clone(), &Action::Block);
+            runtime.strategies[strategy]
+                .borrow_mut()
+                .process(&press, &ctx);
+            tokio::time::sleep(Duration::from_millis(40)).await;
+        };
+
+        // The release is bypassed while paused, so the pause lets go of the hold
+        hold(0x3A, "caps").await;
+        daemon.apply(Request::Pause).await.unwrap();
+        assert_eq!(platform.calls(), [ctrl(true), ctrl(false)]);
+        daemon.apply(Request::Resume).await.unwrap();
+
+        // Disabling a key lets go of its hold, but not of another binding's
+        platform.clear_calls();
+        hold(0x3A, "caps").await;
+        hold(0x0F, "tab").await;
+        daemon
+            .apply(Request::Disable(KeyCode::new(0x3A)))
+            .await
+            .unwrap();
+        assert_eq!(platform.calls(), [ctrl(true), shift(true), ctrl(false)]);
+    }
+
+    #[tokio::test]
+    async fn test_auto_disable_for_focused_game() {
+        let window = |binary: &str| crate::config::WindowInfo {
+            binary: binary.to_string(),
+            ..Default::default()
+        };
+        let config = "[settings.auto_disable]\nwindows = [{ binary = \"eldenring.exe\" }]\n\
+                      [bindings.0x7C]\naction = \"media_next\"";
+        // Each daemon reads focus from its own platform state, so focusing the
+        // game on one must leave the other alone
+        let other = daemon(config).await;
+        let daemon = daemon(config).await;
+        let press = InputEvent::Key(crate::key::KeyEvent::new(KeyCode::new(0x7C), true));
+        assert!(!daemon.bypasses(&press));
+
+        daemon
+            .state
+            .set_focused_window(Some(window("eldenring.exe")));
+        daemon.check_auto_disable();
+        other.check_auto_disable();
+        assert!(daemon.bypasses(&press));
+        assert!(!other.bypasses(&press));
+        assert_eq!(daemon.key_routes(), Some(KeyRoutes::default()));
+        // Separate from a pause the user asked for
+        assert!(!daemon.status().paused);
+
+        daemon.state.set_focused_window(Some(window("firefox")));
+        daemon.check_auto_disable();
+        assert!(!daemon.bypasses(&press));
+    }
+
+    #[tokio::test]
+    async fn test_latency_stats() {
+        let daemon = daemon("[settings]\nslow_event_ms = 5").await;
+        let id = InputEvent::Scroll { up: true }.id();
+        daemon.record_latency(&id, Duration::from_millis(2));
+        daemon.record_latency(&id, Duration::from_millis(8));
+        let stats = daemon.apply(Request::Stats).await.unwrap().to_string();
+        assert!(stats.starts_with("events: 2\n"), "{stats}");
+        assert!(stats.ends_with("slow: 1 (over 5.00ms)"), "{stats}");
+    }
+
+    #[tokio::test]
+    async fn test_failed_reload_keeps_config() {
+        let daemon = daemon("[bindings.0x7C]\naction = \"media_next\"").await;
+        assert!(daemon.apply(Request::Reload).await.is_err());
+        assert!(
+            daemon
+                .apply(Request::Profile(Some("missing".to_string())))
+                .await
+                .is_err()
+        );
+        assert_eq!(daemon.runtime().bindings.len(), 1);
+        assert_eq!(daemon.status().profile, None);
+    }
+
+    #[tokio::test]
+    async fn test_release_after_reload_goes_to_old_config() {
+        let dir = std::env::temp_dir().join(format!("rebinded-reload-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        std::fs::write(&path, "[bindings.0x7C]\naction = \"media_next\"").unwrap();
+        let (_, runtime) = config::load(&path).await.unwrap();
+        let daemon = Daemon::new(path.clone(), runtime, Arc::default());
+        let key = |code, down| InputEvent::Key(crate::key::KeyEvent::new(KeyCode::new(code), down));
+
+        let pressed_under = daemon.runtime_for(&key(0x7C, true));
+        std::fs::write(&path, "[bindings.0x7D]\naction = \"media_next\"").unwrap();
+        daemon.apply(Request::Reload).await.unwrap();
+
+        // Still routed to the handler, though the new config leaves it unbound
+        let routes = daemon.key_routes().unwrap();
+        assert!(routes.handled.contains(&KeyCode::new(0x7C)));
+        assert!(Rc::ptr_eq(
+            &daemon.runtime_for(&key(0x7C, true)),
+            &pressed_under
+        ));
+        assert!(Rc::ptr_eq(
+            &daemon.runtime_for(&key(0x7C, false)),
+            &pressed_under
+        ));
+        assert!(Rc::ptr_eq(
+            &daemon.runtime_for(&key(0x7C, true)),
+            &daemon.runtime()
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_handle_connection() {
+        let daemon = daemon("").await;
+        for (command, expected) in [
+            ("pause\n", "paused\n"),
+            ("state\n", "no strategies\n"),
+            ("stats\n", "no events handled yet\n"),
+            ("fly\n", "error: unknown command 'fly'\n"),
+            (
+                "{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"resume\"}\n",
+                "{\"jsonrpc\":\"2.0\",\"id\":1,\"result\":{\"paused\":false}}\n",
+            ),
+        ] {
+            let (mut client, server) = tokio::io::duplex(1024);
+            client.write_all(command.as_bytes()).await.unwrap();
+            handle_connection(server, &daemon).await;
+            let mut reply = String::new();
+            client.read_to_string(&mut reply).await.unwrap();
+            assert_eq!(reply, expected);
+        }
+
+        // An endless line is cut off at the limit
+        let (mut client, server) = tokio::io::duplex(4096);
+        let flood = async {
+            let _ = client
+                .write_all(&vec![b'a'; protocol::MAX_LINE_LEN + 1])
+                .await;
+            let mut reply = String::new();
+            client.read_to_string(&mut reply).await.unwrap();
+            reply
+        };
+        let ((), reply) = tokio::join!(handle_connection(server, &daemon), flood);
+        assert_eq!(reply, "error: command too long\n");
+
+        // Failures keep the request's id
+        let (mut client, server) = tokio::io::duplex(4096);
+        let request = Request::Reload.to_json_rpc(2);
+        client
+            .write_all(format!("{request}\n").as_bytes())
+            .await
+            .unwrap();
+        handle_connection(server, &daemon).await;
+        let mut reply = String::new();
+        client.read_to_string(&mut reply).await.unwrap();
+        assert!(
+            reply.starts_with(r#"{"jsonrpc":"2.0","id":2,"error":{"code":-32000,"#),
+            "{reply}"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_private_dir() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("rebinded-private-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        private_dir(&dir).unwrap();
+        let mode = std::fs::metadata(&dir).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+        // Reused as long as it stays private
+        private_dir(&dir).unwrap();
+
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(private_dir(&dir).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_profile_path() {
+        assert_eq!(
+            profile_path(Path::new("/home/u/.config/rebinded/config.toml"), "gaming"),
+            Ok(PathBuf::from(
+                "/home/u/.config/rebinded/profiles/gaming.toml"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_profile_path_rejects_traversal() {
+        let config = Path::new("/home/u/.config/rebinded/config.toml");
+        for name in ["", "..", "../config", "a/b", "a\\b", "/etc/passwd"] {
+            assert!(profile_path(config, name).is_err(), "{name:?}");
+        }
+        let err = profile_path(Path::new("https://example.com/config.toml"), "gaming");
+        assert!(err.unwrap_err().contains("remote"));
+    }
+}
@@ -0,0 +1,268 @@
+//! Running in the background without a service manager (`--daemon`)
+//!
+//! On Unix the process double-forks: the first child starts a new session, and
+//! its child, which can never reacquire a terminal, becomes the daemon with
+//! stdin on /dev/null and stdout and stderr appended to the log file. Windows
+//! has no fork, so the process starts a copy of itself without a console and
+//! exits. Either way the daemon writes its ID to a pid file, removed when it
+//! stops, so `kill $(cat ...)` or scripts can find it and a second `--daemon`
+//! refuses to start.
+
+use anyhow::Context;
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+
+/// Which side of the detach the caller is on
+pub enum Detached {
+    /// The process started from the terminal; it should exit
+    Parent,
+    /// The background process; it should go on to run the daemon
+    Daemon,
+}
+
+/// `$XDG_RUNTIME_DIR/rebinded.pid`, or the temp dir without one
+pub fn default_pid_file() -> PathBuf {
+    dirs::runtime_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("rebinded.pid")
+}
+
+/// `rebinded.log` in the state dir (`~/.local/state/rebinded`), or in the
+/// local data dir where there is none (`%LOCALAPPDATA%\rebinded`)
+pub fn default_log_file() -> PathBuf {
+    dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("rebinded")
+        .join("rebinded.log")
+}
+
+/// Open a log file for appending, creating it and its directory if needed
+pub fn open_log(path: &Path) -> anyhow::Result<File> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("failed to open log file {}", path.display()))
+}
+
+/// The running daemon's pid file, removed when dropped
+pub struct PidFile(PathBuf);
+
+impl PidFile {
+    /// Write this process's ID to `path`, unless a live process already has it
+    pub fn create(path: &Path) -> anyhow::Result<Self> {
+        check_not_running(path)?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("failed to create {}", dir.display()))?;
+        }
+        std::fs::write(path, format!("{}\n", std::process::id()))
+            .with_context(|| format!("failed to write pid file {}", path.display()))?;
+        Ok(Self(path.to_path_buf()))
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Fail if the pid file names a process that is still running. A file left
+/// behind by a daemon that was killed is ignored, and overwritten later.
+pub fn check_not_running(path: &Path) -> anyhow::Result<()> {
+    let Ok(text) = std::fs::read_to_string(path) else {
+        return Ok(());
+    };
+    match text.trim().parse::<u32>() {
+        Ok(pid) if backend::is_running(pid) => anyhow::bail!(
+            "rebinded is already running (pid {pid}, from {})",
+            path.display()
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// Move into the background, appending output to `log_file`. Must be called
+/// before the async runtime or any other thread starts.
+pub fn detach(pid_file: &Path, log_file: &Path) -> anyhow::Result<Detached> {
+    check_not_running(pid_file)?;
+    let log = open_log(log_file)?;
+    backend::detach(log, pid_file, log_file)
+}
+
+#[cfg(unix)]
+mod backend {
+    use super::Detached;
+    use anyhow::Context;
+    use std::fs::File;
+    use std::os::fd::AsRawFd;
+    use std::path::Path;
+
+    pub fn is_running(pid: u32) -> bool {
+        // Zero and anything past i32::MAX would signal a whole process group
+        let Ok(pid) = libc::pid_t::try_from(pid) else {
+            return false;
+        };
+        if pid <= 0 {
+            return false;
+        }
+        // SAFETY: signal 0 only checks that the process exists
+        let alive = unsafe { libc::kill(pid, 0) } == 0;
+        // EPERM: it exists, but belongs to another user
+        alive || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+    }
+
+    pub fn detach(log: File, _pid_file: &Path, _log_file: &Path) -> anyhow::Result<Detached> {
+        let null = File::open("/dev/null").context("failed to open /dev/null")?;
+
+        // SAFETY: the process is still single-threaded, so the children start
+        // with consistent state; they only make async-signal-safe calls until
+        // the second child returns to run the daemon
+        unsafe {
+            match libc::fork() {
+                -1 => {
+                    return Err(std::io::Error::last_os_error()).context("fork failed");
+                }
+                0 => {}
+                child => {
+                    // The first child exits as soon as it has forked the daemon
+                    let mut status = 0;
+                    libc::waitpid(child, &mut status, 0);
+                    if !libc::WIFEXITED(status) || libc::WEXITSTATUS(status) != 0 {
+                        anyhow::bail!("failed to start the background process");
+                    }
+                    return Ok(Detached::Parent);
+                }
+            }
+
+            if libc::setsid() == -1 {
+                libc::_exit(1);
+            }
+            match libc::fork() {
+                -1 => libc::_exit(1),
+                0 => {}
+                _ => libc::_exit(0),
+            }
+
+            // Don't keep the directory it was started from busy
+            libc::chdir(c"/".as_ptr());
+            libc::dup2(null.as_raw_fd(), libc::STDIN_FILENO);
+            libc::dup2(log.as_raw_fd(), libc::STDOUT_FILENO);
+            libc::dup2(log.as_raw_fd(), libc::STDERR_FILENO);
+        }
+        Ok(Detached::Daemon)
+    }
+}
+
+#[cfg(windows)]
+mod backend {
+    use super::Detached;
+    use anyhow::Context;
+    use std::ffi::OsString;
+    use std::fs::File;
+    use std::os::windows::process::CommandExt;
+    use std::path::Path;
+    use std::process::Stdio;
+
+    pub fn is_running(pid: u32) -> bool {
+        use windows::Win32::Foundation::CloseHandle;
+        use windows::Win32::System::Threading::{
+            GetExitCodeProcess, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION,
+        };
+
+        const STILL_ACTIVE: u32 = 259;
+
+        // SAFETY: the handle is closed after the one query
+        unsafe {
+            let Ok(process) = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid) else {
+                return false;
+            };
+            let mut code = 0u32;
+            let running = GetExitCodeProcess(process, &mut code).is_ok() && code == STILL_ACTIVE;
+            let _ = CloseHandle(process);
+            running
+        }
+    }
+
+    /// Start a copy of this process without `--daemon`, detached from the
+    /// console, and leave it running
+    pub fn detach(log: File, pid_file: &Path, log_file: &Path) -> anyhow::Result<Detached> {
+        const DETACHED_PROCESS: u32 = 0x0000_0008;
+        const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+
+        let exe = std::env::current_exe().context("failed to locate the executable")?;
+        let args = daemon_args(std::env::args_os().skip(1).collect(), pid_file, log_file);
+        // Panics and errors printed before logging starts land in the log too
+        let stderr = log
+            .try_clone()
+            .context("failed to duplicate the log file")?;
+        std::process::Command::new(exe)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::from(log))
+            .stderr(Stdio::from(stderr))
+            .creation_flags(DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP)
+            .spawn()
+            .context("failed to start the background process")?;
+        Ok(Detached::Parent)
+    }
+
+    /// The command line for the detached copy: the same arguments without
+    /// `--daemon`, with the pid and log files it would have defaulted to
+    fn daemon_args(args: Vec<OsString>, pid_file: &Path, log_file: &Path) -> Vec<OsString> {
+        let given = |flag: &str| {
+            args.iter().any(|arg| {
+                arg.to_str()
+                    .is_some_and(|arg| arg == flag || arg.starts_with(&format!("{flag}=")))
+            })
+        };
+        let (has_pid_file, has_log_file) = (given("--pid-file"), given("--log-file"));
+
+        let mut out: Vec<OsString> = args.into_iter().filter(|arg| arg != "--daemon").collect();
+        out.push("--foreground".into());
+        if !has_pid_file {
+            out.extend(["--pid-file".into(), pid_file.into()]);
+        }
+        if !has_log_file {
+            out.extend(["--log-file".into(), log_file.into()]);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pid_file() {
+        let dir = std::env::temp_dir().join(format!("rebinded-pid-test-{}", std::process::id()));
+        let path = dir.join("rebinded.pid");
+
+        let pid_file = PidFile::create(&path).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            format!("{}\n", std::process::id())
+        );
+        // This process is alive, so a second daemon must not start
+        let err = PidFile::create(&path).err().unwrap();
+        assert!(err.to_string().contains("already running"), "{err}");
+        drop(pid_file);
+        assert!(!path.exists());
+
+        // A pid file left behind by a process that is gone doesn't block
+        std::fs::write(&path, "999999999\n").unwrap();
+        assert!(check_not_running(&path).is_ok());
+        std::fs::write(&path, "0\n").unwrap();
+        assert!(check_not_running(&path).is_ok());
+        drop(PidFile::create(&path).unwrap());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,263 @@
+//! Linux checks: evdev and uinput access, the `input` group, and the desktop
+//! session the daemon queries windows and sends notifications through
+
+use super::{Check, Status};
+use std::ffi::CStr;
+use std::fs::OpenOptions;
+use std::path::Path;
+
+pub async fn platform_checks() -> Vec<Check> {
+    let devices = input_devices();
+    let devices_readable = devices.status != Status::Fail;
+    vec![
+        devices,
+        input_group(devices_readable),
+        uinput(),
+        display_session(),
+        desktop_portal().await,
+    ]
+}
+
+/// Whether the keyboards under /dev/input can be read
+fn input_devices() -> Check {
+    const NAME: &str = "input devices";
+
+    let Ok(entries) = std::fs::read_dir("/dev/input") else {
+        return Check::fail(
+            NAME,
+            "/dev/input does not exist",
+            "rebinded reads keys through evdev, which needs a Linux kernel with input devices",
+        );
+    };
+    let devices: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("event"))
+        })
+        .collect();
+    let readable = devices
+        .iter()
+        .filter(|path| std::fs::File::open(path).is_ok())
+        .count();
+
+    match readable {
+        0 => Check::fail(
+            NAME,
+            format!(
+                "none of {} devices in /dev/input are readable",
+                devices.len()
+            ),
+            "sudo usermod -aG input $USER\nthen log out and back in",
+        ),
+        n if n < devices.len() => Check::warn(
+            NAME,
+            format!(
+                "{n} of {} devices in /dev/input are readable",
+                devices.len()
+            ),
+            "devices owned by another group are not captured; check them with ls -l /dev/input",
+        ),
+        n => Check::ok(NAME, format!("all {n} devices in /dev/input are readable")),
+    }
+}
+
+/// Whether the user is in the `input` group, both on record and in this
+/// login session, which only picks up group changes at the next login
+fn input_group(devices_readable: bool) -> Check {
+    const NAME: &str = "input group";
+
+    // SAFETY: geteuid has no preconditions
+    if unsafe { libc::geteuid() } == 0 {
+        return Check::ok(NAME, "running as root");
+    }
+
+    let Some((gid, members)) = group("input") else {
+        return if devices_readable {
+            Check::ok(NAME, "no input group, but devices are readable another way")
+        } else {
+            Check::fail(
+                NAME,
+                "this system has no input group",
+                "create one and give it the input devices:\n\
+                 sudo groupadd input\n\
+                 echo 'SUBSYSTEM==\"input\", GROUP=\"input\", MODE=\"0660\"' | \
+                 sudo tee /etc/udev/rules.d/99-input.rules\n\
+                 sudo udevadm control --reload-rules && sudo udevadm trigger",
+            )
+        };
+    };
+
+    if session_groups().contains(&gid) {
+        return Check::ok(NAME, "this session is in the input group");
+    }
+    let user = std::env::var("USER").unwrap_or_default();
+    if members.contains(&user) {
+        Check::warn(
+            NAME,
+            format!("{user} was added to the input group after this session started"),
+            "log out and back in (or run `newgrp input` in this shell)",
+        )
+    } else if devices_readable {
+        Check::ok(
+            NAME,
+            "not in the input group, but devices are readable another way",
+        )
+    } else {
+        Check::fail(
+            NAME,
+            format!("{user} is not in the input group"),
+            "sudo usermod -aG input $USER\nthen log out and back in",
+        )
+    }
+}
+
+/// Whether keys can be injected through /dev/uinput
+fn uinput() -> Check {
+    const NAME: &str = "uinput";
+
+    if !Path::new("/dev/uinput").exists() {
+        return Check::fail(
+            NAME,
+            "/dev/uinput does not exist",
+            "sudo modprobe uinput\n\
+             to load it at boot: echo uinput | sudo tee /etc/modules-load.d/uinput.conf",
+        );
+    }
+    if OpenOptions::new().write(true).open("/dev/uinput").is_err() {
+        return Check::fail(
+            NAME,
+            "/dev/uinput is not writable",
+            "echo 'KERNEL==\"uinput\", GROUP=\"input\", MODE=\"0660\"' | \
+             sudo tee /etc/udev/rules.d/99-uinput.rules\n\
+             sudo udevadm control --reload-rules && sudo udevadm trigger",
+        );
+    }
+    Check::ok(NAME, "/dev/uinput is writable")
+}
+
+/// Where window information comes from. Window queries go through X11, so
+/// native Wayland windows are invisible to window conditions.
+fn display_session() -> Check {
+    const NAME: &str = "display";
+
+    let x11 = std::env::var_os("DISPLAY").is_some();
+    let compositor = if std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some() {
+        Some("Hyprland")
+    } else if std::env::var_os("SWAYSOCK").is_some() {
+        Some("Sway")
+    } else if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        Some("Wayland")
+    } else {
+        None
+    };
+
+    match (compositor, x11) {
+        (None, true) => Check::ok(NAME, "X11 session"),
+        (Some(compositor), true) => Check::warn(
+            NAME,
+            format!("{compositor} session with XWayland"),
+            "window conditions only see XWayland windows; native Wayland windows report \
+             no title, class or binary",
+        ),
+        (Some(compositor), false) => Check::warn(
+            NAME,
+            format!("{compositor} session without XWayland"),
+            "enable XWayland for window conditions to see any windows",
+        ),
+        (None, false) => Check::warn(
+            NAME,
+            "no graphical session (DISPLAY and WAYLAND_DISPLAY are unset)",
+            "run the daemon inside your desktop session, e.g. with \
+             `rebinded service install --user`, so window conditions, the clipboard and \
+             notifications work",
+        ),
+    }
+}
+
+/// Whether the session bus has a desktop portal, which Wayland desktops route
+/// notifications, screen and input requests through
+async fn desktop_portal() -> Check {
+    const NAME: &str = "desktop portal";
+    const PORTAL: &str = "org.freedesktop.portal.Desktop";
+
+    let conn = match zbus::Connection::session().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            return Check::warn(
+                NAME,
+                format!("no session bus: {e}"),
+                "media control, notifications and D-Bus actions need a session bus; \
+                 run the daemon inside your desktop session",
+            );
+        }
+    };
+    let dbus = match zbus::fdo::DBusProxy::new(&conn).await {
+        Ok(proxy) => proxy,
+        Err(e) => {
+            return Check::warn(
+                NAME,
+                format!("session bus query failed: {e}"),
+                "check that the D-Bus session daemon is running",
+            );
+        }
+    };
+
+    let running = match PORTAL.try_into() {
+        Ok(name) => dbus.name_has_owner(name).await.unwrap_or(false),
+        Err(_) => false,
+    };
+    let activatable = dbus
+        .list_activatable_names()
+        .await
+        .is_ok_and(|names| names.iter().any(|name| name.as_str() == PORTAL));
+    match (running, activatable) {
+        (true, _) => Check::ok(NAME, "xdg-desktop-portal is running"),
+        (false, true) => Check::ok(NAME, "xdg-desktop-portal starts on demand"),
+        (false, false) => Check::warn(
+            NAME,
+            "xdg-desktop-portal is not available on the session bus",
+            "install xdg-desktop-portal and the backend for your desktop \
+             (e.g. xdg-desktop-portal-hyprland, -wlr, -gnome or -kde)",
+        ),
+    }
+}
+
+/// A group's ID and the user names listed as its members
+fn group(name: &str) -> Option<(libc::gid_t, Vec<String>)> {
+    let name = std::ffi::CString::new(name).ok()?;
+    // SAFETY: getgrnam returns null or a pointer to static storage, read
+    // before any other call could overwrite it
+    unsafe {
+        let group = libc::getgrnam(name.as_ptr());
+        if group.is_null() {
+            return None;
+        }
+        let mut members = Vec::new();
+        let mut member = (*group).gr_mem;
+        while !member.is_null() && !(*member).is_null() {
+            members.push(CStr::from_ptr(*member).to_string_lossy().into_owned());
+            member = member.add(1);
+        }
+        Some(((*group).gr_gid, members))
+    }
+}
+
+/// The groups this process runs with
+fn session_groups() -> Vec<libc::gid_t> {
+    // SAFETY: a zero-sized call only returns the count; the second call writes
+    // at most that many IDs into a buffer of that size
+    unsafe {
+        let count = libc::getgroups(0, std::ptr::null_mut());
+        if count <= 0 {
+            return vec![libc::getegid()];
+        }
+        let mut groups = vec![0; count as usize];
+        let count = libc::getgroups(count, groups.as_mut_ptr());
+        groups.truncate(count.max(0) as usize);
+        groups.push(libc::getegid());
+        groups
+    }
+}
@@ -0,0 +1,155 @@
+//! `rebinded doctor`: checking that the machine can run the daemon
+//!
+//! Each check reports what it found and, when something is missing, the
+//! command or setting that fixes it. The platform checks cover what the daemon
+//! needs before it can see or send a single key (device permissions on Linux,
+//! elevation and the hook timeout on Windows); the config is checked the same
+//! way on both.
+
+#[cfg(unix)]
+mod linux;
+#[cfg(windows)]
+mod windows;
+
+#[cfg(unix)]
+use linux::platform_checks;
+#[cfg(windows)]
+use windows::platform_checks;
+
+use rebinded_core::{config, strategy};
+use std::path::Path;
+use std::process::ExitCode;
+
+/// How a check came out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    /// Works, but something is degraded or worth knowing
+    Warn,
+    /// The daemon won't work properly until this is fixed
+    Fail,
+}
+
+/// The outcome of one check
+#[derive(Debug)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    pub detail: String,
+    /// How to fix a warning or failure
+    pub fix: Option<String>,
+}
+
+impl Check {
+    pub fn ok(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Ok,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    pub fn warn(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Warn,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    pub fn fail(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Fail,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+/// Run every check and print the results, failing if any check failed
+pub async fn run(config_path: &Path, options: config::LoadOptions) -> ExitCode {
+    let mut checks = platform_checks().await;
+    checks.push(config_check(config_path, options).await);
+
+    print!("{}", report(&checks));
+    if checks.iter().any(|check| check.status == Status::Fail) {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Whether the config loads, with the first line of the error if not
+async fn config_check(config_path: &Path, options: config::LoadOptions) -> Check {
+    const NAME: &str = "config";
+
+    if let Some(config_dir) = config_path.parent() {
+        strategy::plugin::load_dir(&config_dir.join("plugins"));
+    }
+    match config::load_with(config_path, options).await {
+        Ok((_, runtime)) => Check::ok(
+            NAME,
+            format!(
+                "{} loads with {} bindings",
+                config_path.display(),
+                runtime.bindings.len()
+            ),
+        ),
+        Err(err) => Check::fail(
+            NAME,
+            format!(
+                "{} does not load: {}",
+                config_path.display(),
+                err.to_string().lines().next().unwrap_or_default()
+            ),
+            "run `rebinded config dump` to see every problem with its location",
+        ),
+    }
+}
+
+/// One line per check, with its fix indented below it
+fn report(checks: &[Check]) -> String {
+    let mut out = String::new();
+    for check in checks {
+        let status = match check.status {
+            Status::Ok => "ok",
+            Status::Warn => "warn",
+            Status::Fail => "FAIL",
+        };
+        out.push_str(&format!("{status:<5} {}: {}\n", check.name, check.detail));
+        if let Some(fix) = &check.fix {
+            for (i, line) in fix.lines().enumerate() {
+                let label = if i == 0 { "fix:" } else { "" };
+                out.push_str(&format!("      {label:<4} {line}\n"));
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        let checks = [
+            Check::ok("uinput", "/dev/uinput is writable"),
+            Check::fail(
+                "input devices",
+                "none of 4 devices are readable",
+                "sudo usermod -aG input $USER\nthen log out and back in",
+            ),
+        ];
+        assert_eq!(
+            report(&checks),
+            "ok    uinput: /dev/uinput is writable\n\
+             FAIL  input devices: none of 4 devices are readable\n      \
+             fix: sudo usermod -aG input $USER\n           \
+             then log out and back in\n"
+        );
+    }
+}
@@ -0,0 +1,155 @@
+//! Windows checks: elevation, which decides whose keys the hook sees, and the
+//! timeout after which Windows silently removes a slow low-level hook
+
+use super::Check;
+use std::ffi::c_void;
+
+/// The hook timeout worth having; Windows caps the value at about this anyway
+const RECOMMENDED_HOOK_TIMEOUT_MS: u32 = 1000;
+
+pub async fn platform_checks() -> Vec<Check> {
+    vec![elevation(), hook_timeout()]
+}
+
+/// Whether the daemon runs elevated. Windows doesn't pass keys typed into an
+/// elevated window to the hooks of unelevated processes.
+fn elevation() -> Check {
+    use windows::Win32::Foundation::{CloseHandle, HANDLE};
+    use windows::Win32::Security::{
+        GetTokenInformation, TOKEN_ELEVATION, TOKEN_QUERY, TokenElevation,
+    };
+    use windows::Win32::System::Threading::{GetCurrentProcess, OpenProcessToken};
+
+    const NAME: &str = "elevation";
+
+    let mut token = HANDLE::default();
+    let mut elevation = TOKEN_ELEVATION::default();
+    let mut len = 0u32;
+    // SAFETY: the token is closed after the one query, which writes at most
+    // size_of::<TOKEN_ELEVATION>() bytes into `elevation`
+    let elevated = unsafe {
+        if OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token).is_err() {
+            None
+        } else {
+            let result = GetTokenInformation(
+                token,
+                TokenElevation,
+                Some(&mut elevation as *mut TOKEN_ELEVATION as *mut c_void),
+                std::mem::size_of::<TOKEN_ELEVATION>() as u32,
+                &mut len,
+            );
+            let _ = CloseHandle(token);
+            result.ok().map(|()| elevation.TokenIsElevated != 0)
+        }
+    };
+
+    match elevated {
+        Some(true) => Check::ok(
+            NAME,
+            "running elevated, so keys typed into administrator windows are seen too",
+        ),
+        Some(false) => Check::warn(
+            NAME,
+            "not elevated: keys typed into elevated windows (Task Manager, administrator \
+             terminals) bypass rebinded",
+            "start rebinded from an elevated prompt when those windows need remapping too",
+        ),
+        None => Check::warn(
+            NAME,
+            "could not read this process's token",
+            "if keys in administrator windows aren't remapped, run rebinded elevated",
+        ),
+    }
+}
+
+/// Whether `LowLevelHooksTimeout` leaves the hook enough time. A hook that
+/// misses it is skipped for that key, and after repeated misses removed
+/// without notice, so remapping stops until the daemon restarts.
+fn hook_timeout() -> Check {
+    const NAME: &str = "hook timeout";
+
+    let fix = format!(
+        "reg add \"HKCU\\Control Panel\\Desktop\" /v LowLevelHooksTimeout /t REG_DWORD \
+         /d {RECOMMENDED_HOOK_TIMEOUT_MS} /f\nthen sign out and back in"
+    );
+    match read_hook_timeout() {
+        Ok(None) => Check::ok(NAME, "LowLevelHooksTimeout is not set (Windows default)"),
+        Ok(Some(ms)) if ms >= RECOMMENDED_HOOK_TIMEOUT_MS => {
+            Check::ok(NAME, format!("LowLevelHooksTimeout is {ms}ms"))
+        }
+        Ok(Some(ms)) => Check::warn(
+            NAME,
+            format!(
+                "LowLevelHooksTimeout is {ms}ms; a slow window query or action can get the \
+                 hook removed"
+            ),
+            fix,
+        ),
+        Err(e) => Check::warn(
+            NAME,
+            format!("LowLevelHooksTimeout could not be read: {e}"),
+            fix,
+        ),
+    }
+}
+
+/// `LowLevelHooksTimeout` in milliseconds, if set. Some tools write it as a
+/// string rather than a DWORD; Windows accepts both.
+fn read_hook_timeout() -> anyhow::Result<Option<u32>> {
+    use windows::Win32::Foundation::{ERROR_FILE_NOT_FOUND, ERROR_UNSUPPORTED_TYPE};
+    use windows::Win32::System::Registry::{
+        HKEY_CURRENT_USER, RRF_RT_REG_DWORD, RRF_RT_REG_SZ, RegGetValueW,
+    };
+    use windows::core::w;
+
+    let key = w!("Control Panel\\Desktop");
+    let value = w!("LowLevelHooksTimeout");
+
+    let mut ms = 0u32;
+    let mut len = std::mem::size_of::<u32>() as u32;
+    // SAFETY: the buffer is a u32 and its size is passed along
+    let status = unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            key,
+            value,
+            RRF_RT_REG_DWORD,
+            None,
+            Some(&mut ms as *mut u32 as *mut c_void),
+            Some(&mut len),
+        )
+    };
+    if status == ERROR_FILE_NOT_FOUND {
+        return Ok(None);
+    }
+    if status.is_ok() {
+        return Ok(Some(ms));
+    }
+    if status != ERROR_UNSUPPORTED_TYPE {
+        return Err(anyhow::anyhow!("{status:?}"));
+    }
+
+    let mut text = [0u16; 32];
+    let mut len = std::mem::size_of_val(&text) as u32;
+    // SAFETY: the buffer's size in bytes is passed along
+    let status = unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            key,
+            value,
+            RRF_RT_REG_SZ,
+            None,
+            Some(text.as_mut_ptr() as *mut c_void),
+            Some(&mut len),
+        )
+    };
+    if status.is_err() {
+        return Err(anyhow::anyhow!("{status:?}"));
+    }
+    let end = text.iter().position(|&c| c == 0).unwrap_or(text.len());
+    let text = String::from_utf16_lossy(&text[..end]);
+    text.trim()
+        .parse()
+        .map(Some)
+        .map_err(|_| anyhow::anyhow!("'{text}' is not a number"))
+}
@@ -0,0 +1,448 @@
+//! AutoHotkey scripts
+//!
+//! Translates remaps (`a::b`) and hotkeys whose body sends one key, sends
+//! plain text (pasted through the clipboard) or runs a program. Hotkeys with
+//! modifiers, hotstrings, `#If` sections and anything scripted are reported.
+
+use std::collections::HashSet;
+
+use super::{Import, Item, quoted, render, resolve_key};
+use crate::key::KeyCode;
+
+/// Commands that only configure the script itself, skipped without a warning
+const SETUP_COMMANDS: &[&str] = &[
+    "sendmode",
+    "setworkingdir",
+    "setbatchlines",
+    "setkeydelay",
+    "settitlematchmode",
+    "return",
+];
+
+/// What one statement of a hotkey body does
+enum Statement {
+    /// Sends a single key, which translates to a remap on its own
+    Key(KeyCode),
+    /// Any other action, as a TOML value
+    Action(String),
+}
+
+/// Translate an AutoHotkey script (v1 or v2 syntax) into rebinded TOML
+pub(super) fn translate(source: &str) -> anyhow::Result<Import> {
+    let mut import = Import::default();
+    let mut items: Vec<(KeyCode, Item)> = Vec::new();
+    let mut seen = HashSet::new();
+    let lines = strip_comments(source);
+    // Hotkeys after `#If` only fire in some windows, so they aren't imported
+    let mut context: Option<String> = None;
+
+    let mut i = 0;
+    while i < lines.len() {
+        let (number, line) = (lines[i].0, lines[i].1.as_str());
+        i += 1;
+        let lower = line.to_lowercase();
+
+        if let Some(directive) = lower.strip_prefix('#') {
+            let (name, args) = directive
+                .split_once(char::is_whitespace)
+                .unwrap_or((directive, ""));
+            if name.starts_with("if") || name == "hotif" {
+                context = (!args.trim().is_empty()).then(|| line.to_string());
+            } else if name == "include" || name == "includeagain" {
+                import.issues.push(format!("line {number}: skipped {line}"));
+            }
+            continue;
+        }
+        if line.starts_with(':') {
+            import.issues.push(format!(
+                "line {number}: hotstrings are not supported: {line}"
+            ));
+            continue;
+        }
+
+        let Some((trigger, action)) = line.split_once("::") else {
+            let command = lower
+                .split([',', ' ', '\t', '('])
+                .next()
+                .unwrap_or_default();
+            if SETUP_COMMANDS.contains(&command) {
+                continue;
+            }
+            if line.ends_with('{') {
+                // A function or other block outside any hotkey
+                i += block_len(&lines[i..]);
+            }
+            import.issues.push(format!("line {number}: skipped {line}"));
+            continue;
+        };
+
+        // The body is either on the same line or on the following lines, up to
+        // `return` (v1) or a closing brace (v2)
+        let action = action.trim();
+        let body: Vec<&str> = if action.is_empty() || action == "{" {
+            let len = if action == "{" {
+                block_len(&lines[i..])
+            } else {
+                lines[i..]
+                    .iter()
+                    .position(|(_, l)| l.eq_ignore_ascii_case("return"))
+                    .map_or(lines.len() - i, |n| n + 1)
+            };
+            let body = lines[i..i + len]
+                .iter()
+                .map(|(_, l)| l.as_str())
+                .filter(|l| *l != "{" && *l != "}" && !l.eq_ignore_ascii_case("return"))
+                .collect();
+            i += len;
+            body
+        } else if let Some(body) = action.strip_prefix('{') {
+            vec![body.trim_end_matches('}').trim()]
+        } else {
+            vec![action]
+        };
+
+        if let Some(context) = &context {
+            import.issues.push(format!(
+                "line {number}: skipped {trigger}:: because it is under {context}"
+            ));
+            continue;
+        }
+        let key = match hotkey(trigger) {
+            Ok(key) => key,
+            Err(issue) => {
+                import.issues.push(format!("line {number}: {issue}"));
+                continue;
+            }
+        };
+        if !seen.insert(key) {
+            import
+                .issues
+                .push(format!("line {number}: duplicate hotkey {trigger}"));
+            continue;
+        }
+        match translate_body(key, &body) {
+            Ok(Item::Same) => {}
+            Ok(item) => items.push((key, item)),
+            Err(issue) => import
+                .issues
+                .push(format!("line {number}: {trigger}:: {issue}")),
+        }
+    }
+
+    import.config = render("an AutoHotkey script", &items);
+    Ok(import)
+}
+
+/// The lines of `source` with comments removed, numbered from 1 and trimmed,
+/// skipping blank lines
+fn strip_comments(source: &str) -> Vec<(usize, String)> {
+    let mut lines = Vec::new();
+    let mut in_block = false;
+    for (i, line) in source.lines().enumerate() {
+        let line = line.trim();
+        if in_block {
+            in_block = !line.starts_with("*/");
+            continue;
+        }
+        if line.starts_with("/*") {
+            in_block = !line.ends_with("*/");
+            continue;
+        }
+        // `;` starts a comment at the start of a line or after whitespace
+        let end = line
+            .char_indices()
+            .find(|&(i, c)| c == ';' && (i == 0 || line[..i].ends_with(char::is_whitespace)))
+            .map_or(line.len(), |(i, _)| i);
+        let line = line[..end].trim();
+        if !line.is_empty() {
+            lines.push((i + 1, line.to_string()));
+        }
+    }
+    lines
+}
+
+/// How many lines the block opened just before `lines` spans, including its
+/// closing brace
+fn block_len(lines: &[(usize, String)]) -> usize {
+    let mut depth = 1usize;
+    for (n, (_, line)) in lines.iter().enumerate() {
+        depth += line.matches('{').count();
+        depth = depth.saturating_sub(line.matches('}').count());
+        if depth == 0 {
+            return n + 1;
+        }
+    }
+    lines.len()
+}
+
+/// The key a hotkey trigger fires on
+fn hotkey(trigger: &str) -> Result<KeyCode, String> {
+    // `*` (any modifiers) and `$` (no self-triggering) match how rebinded
+    // bindings already behave
+    let name = trigger.trim().trim_start_matches(['*', '$']);
+    if name.starts_with(['^', '!', '+', '#', '<', '>']) || name.contains(" & ") {
+        return Err(format!(
+            "{trigger}:: uses modifiers or key combinations, which bindings can't express"
+        ));
+    }
+    if name.starts_with('~') {
+        return Err(format!(
+            "{trigger}:: lets the key through as well, which bindings can't express"
+        ));
+    }
+    if name.to_lowercase().ends_with(" up") {
+        return Err(format!(
+            "{trigger}:: fires on release, which bindings can't express"
+        ));
+    }
+    ahk_key(name).ok_or_else(|| format!("unknown key in {trigger}::"))
+}
+
+/// What a hotkey body does when the hotkey is on `key`
+fn translate_body(key: KeyCode, body: &[&str]) -> Result<Item, String> {
+    if let [target] = body {
+        if let Some(action) = media_action(target) {
+            return Ok(Item::Action(quoted(action)));
+        }
+        if let Some(target) = ahk_key(target) {
+            return Ok(if target == key {
+                Item::Same
+            } else {
+                Item::Remap(target)
+            });
+        }
+    }
+
+    let statements = body
+        .iter()
+        .map(|line| statement(line))
+        .collect::<Result<Vec<_>, _>>()?;
+    match &statements[..] {
+        [] => Ok(Item::Block),
+        [Statement::Key(target)] if *target == key => Ok(Item::Same),
+        [Statement::Key(target)] => Ok(Item::Remap(*target)),
+        [Statement::Action(action)] => Ok(Item::Action(action.clone())),
+        _ => {
+            let actions = statements
+                .into_iter()
+                .map(|statement| match statement {
+                    Statement::Action(action) => Ok(action),
+                    Statement::Key(_) => Err(
+                        "sends a key alongside other statements, which can't be chained"
+                            .to_string(),
+                    ),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(Item::Action(format!("[{}]", actions.join(", "))))
+        }
+    }
+}
+
+/// Translate one statement of a hotkey body
+fn statement(line: &str) -> Result<Statement, String> {
+    let split = line.find([',', ' ', '\t', '(']).unwrap_or(line.len());
+    let (command, args) = line.split_at(split);
+    let args = command_args(args);
+    match command.to_lowercase().as_str() {
+        "send" | "sendinput" | "sendevent" | "sendplay" => send(&args, false),
+        "sendraw" | "sendtext" => send(&args, true),
+        "run" if !args.is_empty() && !args.contains('%') => {
+            Ok(Statement::Action(format!("{{ run = {} }}", quoted(&args))))
+        }
+        _ => Err(format!("'{line}' is not supported")),
+    }
+}
+
+/// The argument of a v1 (`Send, x`) or v2 (`Send "x"` / `Send("x")`) command
+fn command_args(args: &str) -> String {
+    let args = args.trim_start().trim_start_matches(',').trim();
+    let args = args
+        .strip_prefix('(')
+        .and_then(|a| a.strip_suffix(')'))
+        .map_or(args, str::trim);
+    args.strip_prefix('"')
+        .and_then(|a| a.strip_suffix('"'))
+        .or_else(|| args.strip_prefix('\'').and_then(|a| a.strip_suffix('\'')))
+        .unwrap_or(args)
+        .to_string()
+}
+
+/// Translate the keys of a Send command: a single key, a media key, or text
+fn send(keys: &str, raw: bool) -> Result<Statement, String> {
+    if !raw {
+        if let Some(name) = keys.strip_prefix('{').and_then(|k| k.strip_suffix('}'))
+            && !name.contains(['{', '}'])
+        {
+            if let Some(action) = media_action(name) {
+                return Ok(Statement::Action(quoted(action)));
+            }
+            if let Some(key) = ahk_key(name) {
+                return Ok(Statement::Key(key));
+            }
+        }
+        if keys.chars().count() == 1
+            && let Some(key) = ahk_key(keys)
+        {
+            return Ok(Statement::Key(key));
+        }
+    }
+    let text = if raw {
+        Some(unescape(keys))
+    } else {
+        send_text(keys)
+    };
+    match text {
+        Some(text) if !text.is_empty() => Ok(Statement::Action(format!(
+            "{{ clipboard = {{ text = {}, paste = true }} }}",
+            quoted(&text)
+        ))),
+        _ => Err(format!("Send {keys} uses modifiers or special keys")),
+    }
+}
+
+/// The text a Send command types, if it only types text
+fn send_text(keys: &str) -> Option<String> {
+    let mut text = String::new();
+    let mut rest = keys;
+    while let Some(c) = rest.chars().next() {
+        if c == '{' {
+            // `{{}` and `{}}` are literal braces
+            let end = rest.get(2..)?.find('}')? + 2;
+            let name = &rest[1..end];
+            text.push_str(match name.to_lowercase().as_str() {
+                "enter" => "\n",
+                "tab" => "\t",
+                "space" => " ",
+                "{" | "}" | "!" | "+" | "^" | "#" => name,
+                _ => return None,
+            });
+            rest = &rest[end + 1..];
+        } else if matches!(c, '^' | '!' | '+' | '#' | '}') {
+            return None;
+        } else {
+            text.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    Some(unescape(&text))
+}
+
+/// Resolve AutoHotkey's backtick escapes
+fn unescape(text: &str) -> String {
+    let mut out = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('`', Some(next)) => {
+                chars.next();
+                out.push(match next {
+                    'n' => '\n',
+                    't' => '\t',
+                    'r' => '\r',
+                    other => other,
+                });
+            }
+            (c, _) => out.push(c),
+        }
+    }
+    out
+}
+
+/// The rebinded action for one of AutoHotkey's media and browser key names
+fn media_action(name: &str) -> Option<&'static str> {
+    Some(match name.to_lowercase().as_str() {
+        "media_play_pause" => "media_play_pause",
+        "media_next" => "media_next",
+        "media_prev" => "media_previous",
+        "media_stop" => "media_stop",
+        "volume_up" => "volume_up",
+        "volume_down" => "volume_down",
+        "volume_mute" => "volume_mute",
+        "browser_back" => "browser_back",
+        "browser_forward" => "browser_forward",
+        _ => return None,
+    })
+}
+
+/// The key an AutoHotkey key name refers to
+fn ahk_key(name: &str) -> Option<KeyCode> {
+    let name = name.trim().to_lowercase();
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return None;
+    }
+    resolve_key(&name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRIPT: &str = r#"
+        #Requires AutoHotkey v2.0
+        #SingleInstance Force
+        SendMode Input ; fastest
+
+        /* swap caps and escape
+        */
+        CapsLock::Esc
+        $F1::Send "{Media_Play_Pause}"
+        F2::Send("hello{Enter}")
+        F3::
+            Run, notepad.exe
+            Send, done
+        return
+        F4:: {
+            Send "{Volume_Up}"
+        }
+        ^j::Send "x"
+        ::btw::by the way
+        #HotIf WinActive("ahk_exe code.exe")
+        F5::Send "y"
+        #HotIf
+        F6::Send "^c"
+        F7::Media_Next
+    "#;
+
+    #[tokio::test]
+    async fn test_translate_script() {
+        let import = translate(SCRIPT).unwrap();
+        let config = &import.config;
+
+        assert!(
+            config.contains("[bindings.capslock]\naction = \"block\"\nstrategy = \"remap_esc\"\n")
+        );
+        assert!(config.contains("[bindings.f1]\naction = \"media_play_pause\"\n"));
+        assert!(config.contains(
+            "[bindings.f2]\naction = { clipboard = { text = \"hello\\n\", paste = true } }\n"
+        ));
+        assert!(config.contains(
+            "[bindings.f3]\naction = [{ run = \"notepad.exe\" }, { clipboard = { text = \"done\", paste = true } }]\n"
+        ));
+        assert!(config.contains("[bindings.f4]\naction = \"volume_up\"\n"));
+        assert!(config.contains("[bindings.f7]\naction = \"media_next\"\n"));
+        for skipped in ["f5", "f6", "j"] {
+            assert!(
+                !config.contains(&format!("[bindings.{skipped}]")),
+                "{config}"
+            );
+        }
+
+        let issues = import.issues.join("\n");
+        assert_eq!(import.issues.len(), 4, "{issues}");
+        for expected in ["^j", "hotstrings", "#HotIf", "^c"] {
+            assert!(issues.contains(expected), "{issues}");
+        }
+
+        crate::config::load_from_str("import.toml", config.clone())
+            .await
+            .unwrap_or_else(|e| panic!("{config}\n{:?}", miette::Report::new(e)));
+    }
+
+    #[test]
+    fn test_send_text() {
+        assert_eq!(send_text("a{{}b{}}{!}").as_deref(), Some("a{b}!"));
+        assert_eq!(send_text("line`nnext").as_deref(), Some("line\nnext"));
+        assert_eq!(send_text("^v"), None);
+        assert_eq!(send_text("{Left}"), None);
+    }
+}
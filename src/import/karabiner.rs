@@ -0,0 +1,442 @@
+//! Karabiner-Elements rules
+//!
+//! Reads either a whole `karabiner.json` (the selected profile's simple and
+//! complex modifications) or a complex modifications file with a `rules`
+//! array. Basic manipulators translate when they map one key without
+//! mandatory modifiers or conditions:
+//! - `to` a single key becomes a remap, and `vk_none` becomes `block`
+//! - `to_if_alone` with `to`, or `to_if_held_down`, becomes `tap_hold`
+//! - media keys and `shell_command` become the matching actions
+
+use std::collections::HashSet;
+
+use anyhow::bail;
+use serde_json::Value;
+
+use super::{Import, Item, quoted, render, resolve_key};
+use crate::key::KeyCode;
+
+/// Hold time for `to` + `to_if_alone` rules. Karabiner presses the `to` key
+/// immediately and decides on release, while tap_hold decides on a timer.
+const ALONE_HOLD_MS: u64 = 200;
+
+/// Karabiner's default for `basic.to_if_held_down_threshold_milliseconds`
+const HELD_DOWN_THRESHOLD_MS: u64 = 500;
+
+/// What one `to` event does
+enum Event {
+    Key(KeyCode),
+    /// `vk_none`, which swallows the key
+    Nothing,
+    /// Any other action, as a TOML value
+    Action(String),
+}
+
+/// Translate Karabiner-Elements rules into rebinded TOML
+pub(super) fn translate(source: &str) -> anyhow::Result<Import> {
+    let root =
+        serde_json::from_str::<Value>(source).map_err(|e| anyhow::anyhow!("invalid JSON: {e}"))?;
+    let mut import = Import::default();
+    let mut items: Vec<(KeyCode, Item)> = Vec::new();
+    let mut seen = HashSet::new();
+    let mut add = |import: &mut Import, label: &str, result: Result<(KeyCode, Item), String>| {
+        match result {
+            // Karabiner uses the first manipulator that matches a key
+            Ok((key, _)) if !seen.insert(key) => import.issues.push(format!(
+                "{label}: shadowed by an earlier rule for the same key"
+            )),
+            Ok((_, Item::Same)) => {}
+            Ok(item) => items.push(item),
+            Err(issue) => import.issues.push(format!("{label}: {issue}")),
+        }
+    };
+
+    let (simple, complex) = if let Some(profiles) = root.get("profiles") {
+        let profiles = profiles.as_array().map(Vec::as_slice).unwrap_or_default();
+        let profile = profiles
+            .iter()
+            .find(|p| p.get("selected").and_then(Value::as_bool) == Some(true))
+            .or(profiles.first());
+        let Some(profile) = profile else {
+            bail!("karabiner.json has no profiles");
+        };
+        let devices = profile.get("devices").and_then(Value::as_array);
+        if devices.is_some_and(|devices| devices.iter().any(has_device_modifications)) {
+            import
+                .issues
+                .push("skipped device-specific modifications".to_string());
+        }
+        (
+            profile.get("simple_modifications"),
+            profile.get("complex_modifications"),
+        )
+    } else if root.get("rules").is_some() {
+        (None, Some(&root))
+    } else {
+        bail!(
+            "expected karabiner.json (with \"profiles\") or a complex modifications file (with \"rules\")"
+        );
+    };
+
+    for (i, modification) in simple
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .enumerate()
+    {
+        let label = format!("simple modification {}", i + 1);
+        add(&mut import, &label, simple_modification(modification));
+    }
+
+    let parameters = complex.and_then(|c| c.get("parameters"));
+    let rules = complex
+        .and_then(|c| c.get("rules"))
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    for (i, rule) in rules.iter().enumerate() {
+        let rule_label = match rule.get("description").and_then(Value::as_str) {
+            Some(description) => format!("rule '{description}'"),
+            None => format!("rule {}", i + 1),
+        };
+        let manipulators = rule
+            .get("manipulators")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        for (j, manipulator) in manipulators.iter().enumerate() {
+            let label = if manipulators.len() > 1 {
+                format!("{rule_label}, manipulator {}", j + 1)
+            } else {
+                rule_label.clone()
+            };
+            add(
+                &mut import,
+                &label,
+                complex_manipulator(manipulator, parameters),
+            );
+        }
+    }
+
+    import.config = render("Karabiner-Elements rules", &items);
+    Ok(import)
+}
+
+fn has_device_modifications(device: &Value) -> bool {
+    ["simple_modifications", "fn_function_keys"]
+        .iter()
+        .any(|field| {
+            device
+                .get(field)
+                .and_then(Value::as_array)
+                .is_some_and(|mods| !mods.is_empty())
+        })
+}
+
+/// A `{ "from": { "key_code": .. }, "to": [{ "key_code": .. }] }` entry
+fn simple_modification(modification: &Value) -> Result<(KeyCode, Item), String> {
+    let from = from_key(modification.get("from"))?;
+    let to = modification.get("to").map(events).transpose()?;
+    Ok((from, plain_item(from, to.as_deref())?))
+}
+
+/// A manipulator from a complex modifications rule
+fn complex_manipulator(
+    manipulator: &Value,
+    parameters: Option<&Value>,
+) -> Result<(KeyCode, Item), String> {
+    if manipulator.get("type").and_then(Value::as_str) != Some("basic") {
+        return Err("only \"basic\" manipulators are supported".to_string());
+    }
+    if manipulator
+        .get("conditions")
+        .and_then(Value::as_array)
+        .is_some_and(|conditions| !conditions.is_empty())
+    {
+        return Err("conditions are not supported".to_string());
+    }
+    for field in ["to_after_key_up", "to_delayed_action"] {
+        if manipulator.get(field).is_some() {
+            return Err(format!("{field} is not supported"));
+        }
+    }
+
+    let from = manipulator.get("from");
+    let mandatory = from
+        .and_then(|f| f.get("modifiers"))
+        .and_then(|m| m.get("mandatory"))
+        .and_then(Value::as_array);
+    if mandatory.is_some_and(|m| !m.is_empty()) {
+        return Err("mandatory modifiers are not supported".to_string());
+    }
+    let key = from_key(from)?;
+
+    let to = manipulator.get("to").map(events).transpose()?;
+    let alone = manipulator.get("to_if_alone").map(events).transpose()?;
+    let held = manipulator.get("to_if_held_down").map(events).transpose()?;
+    // Per-manipulator parameters override the rule file's
+    let parameter = |name: &str| {
+        [manipulator.get("parameters"), parameters]
+            .into_iter()
+            .flatten()
+            .find_map(|p| p.get(name).and_then(Value::as_f64))
+            .map(|ms| ms.max(0.0) as u64)
+    };
+
+    let item = match (to.as_deref(), alone.as_deref(), held.as_deref()) {
+        (to, None, None) => plain_item(key, to)?,
+        (Some([Event::Key(hold)]), Some([Event::Key(tap)]), None) => Item::TapHold {
+            hold_ms: parameter("basic.to_if_alone_timeout_milliseconds")
+                .map_or(ALONE_HOLD_MS, |ms| ms.min(ALONE_HOLD_MS)),
+            tap: *tap,
+            hold: *hold,
+        },
+        (None | Some([]), alone, Some([Event::Key(hold)])) => {
+            let tap = match alone {
+                None => key,
+                Some([Event::Key(tap)]) => *tap,
+                Some(_) => return Err("to_if_alone must be a single key".to_string()),
+            };
+            Item::TapHold {
+                hold_ms: parameter("basic.to_if_held_down_threshold_milliseconds")
+                    .unwrap_or(HELD_DOWN_THRESHOLD_MS),
+                tap,
+                hold: *hold,
+            }
+        }
+        _ => {
+            return Err(
+                "only single keys translate when combining to, to_if_alone and to_if_held_down"
+                    .to_string(),
+            );
+        }
+    };
+    Ok((key, item))
+}
+
+/// What a key does when it only has `to` events
+fn plain_item(key: KeyCode, to: Option<&[Event]>) -> Result<Item, String> {
+    Ok(match to {
+        None | Some([]) | Some([Event::Nothing]) => Item::Block,
+        Some([Event::Key(target)]) if *target == key => Item::Same,
+        Some([Event::Key(target)]) => Item::Remap(*target),
+        Some([Event::Action(action)]) => Item::Action(action.clone()),
+        Some(events) => {
+            let actions = events
+                .iter()
+                .map(|event| match event {
+                    Event::Action(action) => Ok(action.as_str()),
+                    _ => Err("sends a key alongside other events, which can't be chained"),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Item::Action(format!("[{}]", actions.join(", ")))
+        }
+    })
+}
+
+/// The key a `from` object matches
+fn from_key(from: Option<&Value>) -> Result<KeyCode, String> {
+    let Some(name) = from.and_then(|f| f.get("key_code")).and_then(Value::as_str) else {
+        return Err("only \"from\" with a key_code is supported".to_string());
+    };
+    karabiner_key(name).ok_or_else(|| format!("unknown key_code \"{name}\""))
+}
+
+/// Translate a `to` event list
+fn events(list: &Value) -> Result<Vec<Event>, String> {
+    let Some(list) = list.as_array() else {
+        // A lone object is accepted as a one-event list
+        return Ok(vec![event(list)?]);
+    };
+    list.iter().map(event).collect()
+}
+
+fn event(event: &Value) -> Result<Event, String> {
+    if event
+        .get("modifiers")
+        .and_then(Value::as_array)
+        .is_some_and(|m| !m.is_empty())
+    {
+        return Err("events with modifiers are not supported".to_string());
+    }
+    if let Some(command) = event.get("shell_command").and_then(Value::as_str) {
+        return Ok(Event::Action(format!("{{ run = {} }}", quoted(command))));
+    }
+    if let Some(name) = event
+        .get("consumer_key_code")
+        .or_else(|| event.get("key_code"))
+        .and_then(Value::as_str)
+    {
+        if name == "vk_none" {
+            return Ok(Event::Nothing);
+        }
+        if let Some(action) = media_action(name) {
+            return Ok(Event::Action(quoted(action)));
+        }
+        return karabiner_key(name)
+            .map(Event::Key)
+            .ok_or_else(|| format!("unknown key_code \"{name}\""));
+    }
+    Err(format!("unsupported event {event}"))
+}
+
+/// The rebinded action for one of Karabiner's media key codes
+fn media_action(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "play_or_pause" => "media_play_pause",
+        "scan_next_track" | "fastforward" => "media_next",
+        "scan_previous_track" | "rewind" => "media_previous",
+        "mute" => "volume_mute",
+        "volume_increment" | "volume_up" => "volume_up",
+        "volume_decrement" | "volume_down" => "volume_down",
+        "ac_back" => "browser_back",
+        "ac_forward" => "browser_forward",
+        _ => return None,
+    })
+}
+
+/// The key a Karabiner key code refers to
+fn karabiner_key(name: &str) -> Option<KeyCode> {
+    let candidates: &[&str] = match name {
+        "left_control" => &["lctl"],
+        "right_control" => &["rctl"],
+        "left_shift" => &["lsft"],
+        "right_shift" => &["rsft"],
+        "left_option" | "left_alt" => &["lalt"],
+        "right_option" | "right_alt" => &["ralt"],
+        "left_command" | "left_gui" => &["lmet"],
+        "right_command" | "right_gui" => &["rmet"],
+        "caps_lock" => &["capslock"],
+        "escape" => &["esc"],
+        "return_or_enter" => &["enter"],
+        "delete_or_backspace" => &["backspace"],
+        "delete_forward" => &["delete"],
+        "spacebar" => &["space"],
+        "grave_accent_and_tilde" => &["grave"],
+        "hyphen" => &["minus"],
+        "equal_sign" => &["equal"],
+        "open_bracket" => &["leftbrace"],
+        "close_bracket" => &["rightbrace"],
+        "quote" => &["apostrophe"],
+        "period" => &["dot"],
+        "non_us_backslash" => &["102nd"],
+        "up_arrow" => &["up"],
+        "down_arrow" => &["down"],
+        "left_arrow" => &["left"],
+        "right_arrow" => &["rght"],
+        "page_up" => &["pgup"],
+        "page_down" => &["pgdn"],
+        "print_screen" => &["prnt"],
+        "scroll_lock" => &["scrolllock"],
+        "application" => &["menu"],
+        _ => &[name],
+    };
+    candidates.iter().find_map(|name| resolve_key(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULES: &str = r#"{
+        "title": "Example",
+        "rules": [
+            {
+                "description": "Caps Lock to Control, Escape when alone",
+                "manipulators": [{
+                    "type": "basic",
+                    "from": { "key_code": "caps_lock", "modifiers": { "optional": ["any"] } },
+                    "to": [{ "key_code": "left_control" }],
+                    "to_if_alone": [{ "key_code": "escape" }]
+                }]
+            },
+            {
+                "description": "Media and misc",
+                "manipulators": [
+                    {
+                        "type": "basic",
+                        "from": { "key_code": "f8" },
+                        "to": [{ "consumer_key_code": "play_or_pause" }]
+                    },
+                    {
+                        "type": "basic",
+                        "from": { "key_code": "f9" },
+                        "to_if_held_down": [{ "key_code": "left_shift" }],
+                        "parameters": { "basic.to_if_held_down_threshold_milliseconds": 300 }
+                    },
+                    {
+                        "type": "basic",
+                        "from": { "key_code": "f10" },
+                        "to": [{ "shell_command": "open -a Terminal" }]
+                    },
+                    {
+                        "type": "basic",
+                        "from": { "key_code": "f11", "modifiers": { "mandatory": ["command"] } },
+                        "to": [{ "key_code": "a" }]
+                    },
+                    {
+                        "type": "basic",
+                        "from": { "key_code": "f12" },
+                        "to": [{ "key_code": "vk_none" }],
+                        "conditions": [{ "type": "frontmost_application_if" }]
+                    },
+                    {
+                        "type": "basic",
+                        "from": { "key_code": "f8" },
+                        "to": [{ "key_code": "a" }]
+                    }
+                ]
+            }
+        ]
+    }"#;
+
+    #[tokio::test]
+    async fn test_translate_rules() {
+        let import = translate(RULES).unwrap();
+        let config = &import.config;
+
+        assert!(
+            config.contains("[strategies.capslock_tap_hold]\ntype = \"tap_hold\"\nhold_ms = 200\n")
+        );
+        assert!(config.contains("[strategies.f9_tap_hold]\ntype = \"tap_hold\"\nhold_ms = 300\n"));
+        assert!(config.contains("[bindings.f8]\naction = \"media_play_pause\"\n"));
+        assert!(config.contains("[bindings.f10]\naction = { run = \"open -a Terminal\" }\n"));
+        assert!(!config.contains("[bindings.f11]"));
+        assert!(!config.contains("[bindings.f12]"));
+
+        let issues = import.issues.join("\n");
+        assert_eq!(import.issues.len(), 3, "{issues}");
+        for expected in ["mandatory modifiers", "conditions", "shadowed"] {
+            assert!(issues.contains(expected), "{issues}");
+        }
+
+        crate::config::load_from_str("import.toml", config.clone())
+            .await
+            .unwrap_or_else(|e| panic!("{config}\n{:?}", miette::Report::new(e)));
+    }
+
+    #[test]
+    fn test_translate_profile() {
+        let import = translate(
+            r#"{ "profiles": [
+                { "name": "Other", "simple_modifications": [
+                    { "from": { "key_code": "a" }, "to": [{ "key_code": "b" }] }
+                ] },
+                { "name": "Default", "selected": true, "simple_modifications": [
+                    { "from": { "key_code": "caps_lock" }, "to": [{ "key_code": "escape" }] }
+                ] }
+            ] }"#,
+        )
+        .unwrap();
+        assert!(
+            import
+                .config
+                .contains("[bindings.capslock]\naction = \"block\"\nstrategy = \"remap_esc\"\n")
+        );
+        assert!(!import.config.contains("[bindings.a]"));
+        assert!(import.issues.is_empty(), "{:?}", import.issues);
+
+        assert!(translate("{}").is_err());
+    }
+}
@@ -0,0 +1,450 @@
+//! kanata and kmonad configurations
+//!
+//! Both tools describe a keyboard with s-expressions: `defsrc` lists the
+//! physical keys, each `deflayer` says what those keys do, and `defalias`
+//! names actions for reuse as `@name`. rebinded has no layers, so:
+//! - the first (base) layer becomes the main config
+//! - `XX` becomes `block`
+//! - kanata's `tap-hold` family and kmonad's `tap-hold`/`tap-next` family
+//!   become `tap_hold`
+//! - every other layer becomes a profile (base layer bindings, with the
+//!   layer's own keys on top), and `layer-switch` runs `rebinded ctl profile`
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{Context, bail};
+
+use super::{Format, Import, Item, quoted, render, resolve_key};
+use crate::key::{self, KeyCode};
+
+/// Hold time for kmonad's `tap-next`, which waits for the next key instead
+const TAP_NEXT_HOLD_MS: u64 = 200;
+
+/// How deeply aliases may refer to other aliases
+const MAX_ALIAS_DEPTH: usize = 16;
+
+/// Translate a kanata or kmonad configuration into rebinded TOML
+pub(super) fn translate(format: Format, source: &str) -> anyhow::Result<Import> {
+    let mut import = Import::default();
+    let mut src = None;
+    let mut layers: Vec<(String, Vec<Expr>)> = Vec::new();
+    let mut aliases = HashMap::new();
+
+    for form in parse(source)? {
+        let Expr::List(items) = form else {
+            bail!("expected a top-level list, found '{}'", form);
+        };
+        let Some(Expr::Atom(head)) = items.first() else {
+            bail!("expected a top-level form like (defsrc ...)");
+        };
+        match head.as_str() {
+            "defsrc" => src = Some(items[1..].to_vec()),
+            "deflayer" => match items.get(1) {
+                Some(Expr::Atom(name)) => layers.push((name.clone(), items[2..].to_vec())),
+                _ => bail!("deflayer needs a name"),
+            },
+            "defalias" => {
+                for pair in items[1..].chunks(2) {
+                    match pair {
+                        [Expr::Atom(name), value] => {
+                            aliases.insert(name.clone(), value.clone());
+                        }
+                        _ => bail!("defalias expects name/value pairs"),
+                    }
+                }
+            }
+            "defcfg" => {}
+            other => import.issues.push(format!("skipped ({other} ...)")),
+        }
+    }
+
+    let src = src.context("no defsrc found")?;
+    let Some((base_name, _)) = layers.first() else {
+        bail!("no deflayer found");
+    };
+
+    // Profile names become file names, so keep them to a safe character set
+    let mut profile_names = HashMap::new();
+    profile_names.insert(base_name.clone(), "default".to_string());
+    for (name, _) in &layers[1..] {
+        let safe: String = name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        profile_names.insert(name.clone(), safe);
+    }
+
+    let mut keys = Vec::new();
+    let mut seen = HashSet::new();
+    for expr in &src {
+        let code = match expr {
+            Expr::Atom(name) => resolve_key(name),
+            Expr::List(_) => None,
+        };
+        match code {
+            Some(code) if !seen.insert(code) => {
+                import.issues.push(format!("defsrc lists '{expr}' twice"));
+                keys.push(None);
+            }
+            Some(code) => keys.push(Some(code)),
+            None => {
+                import
+                    .issues
+                    .push(format!("unknown key '{expr}' in defsrc"));
+                keys.push(None);
+            }
+        }
+    }
+
+    let resolver = Resolver {
+        format,
+        aliases: &aliases,
+        profile_names: &profile_names,
+    };
+    let mut translated = Vec::new();
+    for (name, items) in &layers {
+        if items.len() != src.len() {
+            import.issues.push(format!(
+                "layer '{name}' has {} keys but defsrc has {}",
+                items.len(),
+                src.len()
+            ));
+        }
+        let mut layer = Vec::new();
+        for (&key, expr) in keys.iter().zip(items) {
+            let Some(key) = key else { continue };
+            match resolver.resolve(key, expr, 0) {
+                Ok(Item::Transparent) => {}
+                Ok(item) => layer.push((key, item)),
+                Err(issue) => import.issues.push(format!(
+                    "layer '{name}', key '{}': {issue}",
+                    key::config_name(key)
+                )),
+            }
+        }
+        translated.push(layer);
+    }
+
+    let tool = if format == Format::Kmonad {
+        "kmonad"
+    } else {
+        "kanata"
+    };
+    let base = &translated[0];
+    import.config = render(&format!("{tool} layer '{base_name}'"), base);
+    for ((name, _), layer) in layers.iter().zip(&translated).skip(1) {
+        // Keys the layer leaves alone keep their base layer behavior
+        let overridden: HashSet<KeyCode> = layer.iter().map(|(key, _)| *key).collect();
+        let mut merged: Vec<(KeyCode, Item)> = base
+            .iter()
+            .filter(|(key, _)| !overridden.contains(key))
+            .cloned()
+            .collect();
+        merged.extend(layer.iter().cloned());
+        let order: Vec<Option<KeyCode>> = keys.clone();
+        merged.sort_by_key(|(key, _)| order.iter().position(|k| *k == Some(*key)));
+        import.profiles.push((
+            profile_names[name].clone(),
+            render(&format!("{tool} layer '{name}'"), &merged),
+        ));
+    }
+    Ok(import)
+}
+
+struct Resolver<'a> {
+    format: Format,
+    aliases: &'a HashMap<String, Expr>,
+    /// Layer name to profile name
+    profile_names: &'a HashMap<String, String>,
+}
+
+impl Resolver<'_> {
+    /// What `expr` does when placed on `key`
+    fn resolve(&self, key: KeyCode, expr: &Expr, depth: usize) -> Result<Item, String> {
+        match expr {
+            Expr::Atom(atom) if atom == "_" => Ok(Item::Transparent),
+            Expr::Atom(atom) if atom == "XX" => Ok(Item::Block),
+            Expr::Atom(atom) if atom.starts_with('@') => {
+                let name = &atom[1..];
+                let value = self
+                    .aliases
+                    .get(name)
+                    .ok_or_else(|| format!("undefined alias '{atom}'"))?;
+                if depth >= MAX_ALIAS_DEPTH {
+                    return Err(format!("alias '{atom}' refers to itself"));
+                }
+                self.resolve(key, value, depth + 1)
+            }
+            Expr::Atom(atom) => match resolve_key(atom) {
+                Some(code) if code == key => Ok(Item::Same),
+                Some(code) => Ok(Item::Remap(code)),
+                None => Err(format!("unknown key '{atom}'")),
+            },
+            Expr::List(items) => self.resolve_list(items, depth),
+        }
+    }
+
+    fn resolve_list(&self, items: &[Expr], depth: usize) -> Result<Item, String> {
+        let unsupported = || Err(format!("{} is not supported", Expr::List(items.to_vec())));
+        let Some(Expr::Atom(op)) = items.first() else {
+            return unsupported();
+        };
+        let args = &items[1..];
+        let (hold_ms, tap, hold) = match (self.format, op.as_str(), args) {
+            (
+                Format::Kanata,
+                "tap-hold" | "tap-hold-press" | "tap-hold-release",
+                [_, ms, tap, hold],
+            )
+            | (
+                Format::Kmonad,
+                "tap-hold" | "tap-hold-next" | "tap-hold-next-release",
+                [ms, tap, hold],
+            ) => {
+                let ms = match ms {
+                    Expr::Atom(ms) => ms.parse().ok(),
+                    Expr::List(_) => None,
+                };
+                let ms = ms.ok_or_else(|| format!("invalid timeout in ({op} ...)"))?;
+                (ms, tap, hold)
+            }
+            (Format::Kmonad, "tap-next" | "tap-next-release", [tap, hold]) => {
+                (TAP_NEXT_HOLD_MS, tap, hold)
+            }
+            (_, "layer-switch", [Expr::Atom(layer)]) => {
+                return self
+                    .profile_names
+                    .get(layer)
+                    .map(|profile| {
+                        Item::Action(format!(
+                            "{{ run = {} }}",
+                            quoted(&format!("rebinded ctl profile {profile}"))
+                        ))
+                    })
+                    .ok_or_else(|| format!("unknown layer '{layer}'"));
+            }
+            _ => return unsupported(),
+        };
+
+        let plain_key = |expr: &Expr| match self.resolve_alias(expr, depth) {
+            Some(Expr::Atom(atom)) => resolve_key(atom),
+            _ => None,
+        };
+        match (plain_key(tap), plain_key(hold)) {
+            (Some(tap), Some(hold)) => Ok(Item::TapHold { hold_ms, tap, hold }),
+            _ => Err(format!(
+                "({op} ...) only translates when tap and hold are plain keys"
+            )),
+        }
+    }
+
+    /// Follow `@alias` references to the expression they stand for
+    fn resolve_alias<'e>(&'e self, expr: &'e Expr, depth: usize) -> Option<&'e Expr> {
+        match expr {
+            Expr::Atom(atom) if atom.starts_with('@') && depth < MAX_ALIAS_DEPTH => {
+                self.resolve_alias(self.aliases.get(&atom[1..])?, depth + 1)
+            }
+            expr => Some(expr),
+        }
+    }
+}
+
+/// An s-expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+    Atom(String),
+    List(Vec<Expr>),
+}
+
+impl std::fmt::Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expr::Atom(atom) => f.write_str(atom),
+            Expr::List(items) => {
+                f.write_str("(")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" ")?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+/// Parse every top-level expression, skipping `;;` and `#| |#` comments
+fn parse(source: &str) -> anyhow::Result<Vec<Expr>> {
+    let mut stack: Vec<Vec<Expr>> = vec![Vec::new()];
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            ';' => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '#' if chars.peek() == Some(&'|') => {
+                chars.next();
+                let mut prev = ' ';
+                loop {
+                    match chars.next() {
+                        Some('#') if prev == '|' => break,
+                        Some(c) => prev = c,
+                        None => bail!("unterminated #| comment"),
+                    }
+                }
+            }
+            '(' => stack.push(Vec::new()),
+            ')' => {
+                let list = stack.pop().filter(|_| !stack.is_empty());
+                let Some(list) = list else {
+                    bail!("unbalanced ')'");
+                };
+                stack.last_mut().unwrap().push(Expr::List(list));
+            }
+            '"' => {
+                let mut atom = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => atom.extend(chars.next()),
+                        Some(c) => atom.push(c),
+                        None => bail!("unterminated string"),
+                    }
+                }
+                stack.last_mut().unwrap().push(Expr::Atom(atom));
+            }
+            c => {
+                let mut atom = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' {
+                        break;
+                    }
+                    atom.push(c);
+                    chars.next();
+                }
+                stack.last_mut().unwrap().push(Expr::Atom(atom));
+            }
+        }
+    }
+    if stack.len() != 1 {
+        bail!("unbalanced '(': missing {} ')'", stack.len() - 1);
+    }
+    Ok(stack.pop().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KANATA: &str = r#"
+        ;; home row mods
+        (defcfg process-unmapped-keys yes)
+        (defsrc caps a s f1 f2)
+        (defalias
+          a (tap-hold 200 180 a lctl)
+          nav (layer-switch nav)
+          hold (layer-while-held nav))
+        (deflayer base esc @a _ @nav XX)
+        #| second layer |#
+        (deflayer nav caps _ lsft (layer-switch base) @hold)
+    "#;
+
+    #[test]
+    fn test_parse() {
+        let forms = parse("(a (b \"c d\") ;; comment\n e) #| x |# f").unwrap();
+        assert_eq!(forms.len(), 2);
+        assert_eq!(forms[0].to_string(), "(a (b c d) e)");
+        assert!(parse("(a").is_err());
+        assert!(parse("a)").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_translate_kanata() {
+        let import = translate(Format::Kanata, KANATA).unwrap();
+
+        assert!(
+            import
+                .config
+                .contains("[strategies.remap_esc]\ntype = \"tap_hold\"\nhold_ms = 0\n")
+        );
+        assert!(
+            import
+                .config
+                .contains("[bindings.capslock]\naction = \"block\"\nstrategy = \"remap_esc\"\n")
+        );
+        assert!(
+            import
+                .config
+                .contains("[strategies.a_tap_hold]\ntype = \"tap_hold\"\nhold_ms = 180\n")
+        );
+        assert!(
+            import
+                .config
+                .contains("[bindings.f1]\naction = { run = \"rebinded ctl profile nav\" }\n")
+        );
+        assert!(
+            import
+                .config
+                .contains("[bindings.f2]\naction = \"block\"\n")
+        );
+        assert!(!import.config.contains("[bindings.s]"));
+        assert_eq!(import.issues.len(), 1, "{:?}", import.issues);
+        assert!(import.issues[0].contains("layer-while-held"));
+
+        let [(name, nav)] = &import.profiles[..] else {
+            panic!("expected one profile: {:?}", import.profiles);
+        };
+        assert_eq!(name, "nav");
+        assert!(
+            nav.contains("[strategies.a_tap_hold]"),
+            "base bindings carry over"
+        );
+        assert!(
+            !nav.contains("[bindings.capslock]"),
+            "identity overrides the base"
+        );
+        assert!(
+            nav.contains("[bindings.f1]\naction = { run = \"rebinded ctl profile default\" }\n")
+        );
+
+        for config in [&import.config, nav] {
+            crate::config::load_from_str("import.toml", config.clone())
+                .await
+                .unwrap_or_else(|e| panic!("{config}\n{:?}", miette::Report::new(e)));
+        }
+    }
+
+    #[test]
+    fn test_translate_kmonad() {
+        let import = translate(
+            Format::Kmonad,
+            "(defcfg fallthrough true)\n(defsrc a b)\n(deflayer base (tap-next a lsft) (tap-hold 150 b lctl))",
+        )
+        .unwrap();
+        assert!(
+            import
+                .config
+                .contains("[strategies.a_tap_hold]\ntype = \"tap_hold\"\nhold_ms = 200\n")
+        );
+        assert!(
+            import
+                .config
+                .contains("[strategies.b_tap_hold]\ntype = \"tap_hold\"\nhold_ms = 150\n")
+        );
+        assert!(import.issues.is_empty(), "{:?}", import.issues);
+
+        assert!(translate(Format::Kmonad, "(defsrc a)").is_err());
+    }
+}
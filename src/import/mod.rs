@@ -0,0 +1,256 @@
+//! Import configurations from other remapping tools
+//!
+//! Each format is parsed into per-key [`Item`]s, which are rendered as a
+//! rebinded config. rebinded has no plain key remaps, so a key remapped to
+//! another becomes a `tap_hold` strategy that taps and holds the new key.
+//! Anything without a rebinded equivalent is reported instead of guessed at.
+
+mod ahk;
+mod karabiner;
+mod kbd;
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+use anyhow::{Context, bail};
+use clap::ValueEnum;
+
+use crate::key::{self, KeyCode};
+
+/// Which tool a configuration comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// kanata `.kbd` files
+    Kanata,
+    /// kmonad `.kbd` files
+    Kmonad,
+    /// AutoHotkey scripts (remaps and hotkeys that send keys or text)
+    #[value(alias = "autohotkey")]
+    Ahk,
+    /// Karabiner-Elements `karabiner.json` or complex modifications rules
+    Karabiner,
+}
+
+/// A translated configuration
+#[derive(Debug, Default)]
+pub struct Import {
+    /// The main config, from the base layer
+    pub config: String,
+    /// `(profile name, config)` for every other layer
+    pub profiles: Vec<(String, String)>,
+    /// Constructs that didn't translate, or only approximately
+    pub issues: Vec<String>,
+}
+
+/// Translate `file` and write the result to `output` (with profiles in
+/// `profiles/` beside it), or print the main config without `output`
+pub fn run(format: Format, file: &Path, output: Option<&Path>) -> anyhow::Result<()> {
+    let source = std::fs::read_to_string(file)
+        .with_context(|| format!("failed to read {}", file.display()))?;
+    let import = translate(format, &source)?;
+    for issue in &import.issues {
+        eprintln!("warning: {issue}");
+    }
+
+    let Some(output) = output else {
+        print!("{}", import.config);
+        if !import.profiles.is_empty() {
+            eprintln!(
+                "note: {} layer(s) became profiles; pass --output to write them",
+                import.profiles.len()
+            );
+        }
+        return Ok(());
+    };
+
+    let profile_dir = output.parent().unwrap_or(Path::new(".")).join("profiles");
+    let mut files = vec![(output.to_path_buf(), &import.config)];
+    for (name, config) in &import.profiles {
+        files.push((profile_dir.join(format!("{name}.toml")), config));
+    }
+    if let Some((path, _)) = files.iter().find(|(path, _)| path.exists()) {
+        bail!(
+            "{} already exists, refusing to overwrite it",
+            path.display()
+        );
+    }
+    if !import.profiles.is_empty() {
+        std::fs::create_dir_all(&profile_dir)
+            .with_context(|| format!("failed to create {}", profile_dir.display()))?;
+    }
+    for (path, contents) in files {
+        std::fs::write(&path, contents)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        eprintln!("wrote {}", path.display());
+    }
+    Ok(())
+}
+
+/// Translate a configuration into rebinded TOML
+pub fn translate(format: Format, source: &str) -> anyhow::Result<Import> {
+    match format {
+        Format::Kanata | Format::Kmonad => kbd::translate(format, source),
+        Format::Ahk => ahk::translate(source),
+        Format::Karabiner => karabiner::translate(source),
+    }
+}
+
+/// What one key does
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Item {
+    /// Falls through to the base layer (`_`)
+    Transparent,
+    /// The key itself, with no binding
+    Same,
+    Block,
+    Remap(KeyCode),
+    TapHold {
+        hold_ms: u64,
+        tap: KeyCode,
+        hold: KeyCode,
+    },
+    /// Any other action, as a TOML value
+    Action(String),
+}
+
+/// rebinded names to try, in order, for another tool's key name. Linux and
+/// Windows name some keys differently, so the first one this platform knows wins.
+fn key_candidates(name: &str) -> &'static [&'static str] {
+    match name {
+        "lctl" | "lctrl" | "lcontrol" | "ctrl" | "control" => &["leftctrl", "lctrl"],
+        "rctl" | "rctrl" | "rcontrol" => &["rightctrl", "rctrl"],
+        "lsft" | "lshift" | "shift" => &["leftshift", "lshift"],
+        "rsft" | "rshift" => &["rightshift", "rshift"],
+        "lalt" | "alt" => &["leftalt", "lalt"],
+        "ralt" => &["rightalt", "ralt"],
+        "lmet" | "lmeta" | "lwin" | "lgui" | "lsuper" | "lcmd" => &["leftmeta", "lwin"],
+        "rmet" | "rmeta" | "rwin" | "rgui" | "rsuper" | "rcmd" => &["rightmeta", "rwin"],
+        "caps" => &["capslock"],
+        "spc" => &["space"],
+        "ret" | "ent" => &["enter"],
+        "bspc" | "bks" => &["backspace"],
+        "del" => &["delete"],
+        "ins" => &["insert"],
+        "pgup" => &["pageup"],
+        "pgdn" => &["pagedown"],
+        "rght" => &["right"],
+        "grv" => &["grave"],
+        "min" => &["minus"],
+        "eql" => &["equal"],
+        "lbrc" => &["leftbrace"],
+        "rbrc" => &["rightbrace"],
+        "bksl" => &["backslash"],
+        "scln" => &["semicolon"],
+        "apos" | "quot" => &["apostrophe"],
+        "comm" => &["comma"],
+        "." => &["dot"],
+        "/" | "slsh" => &["slash"],
+        "nlck" | "nlk" => &["numlock"],
+        "slck" | "slk" => &["scrolllock"],
+        "prnt" | "ssrq" | "printscreen" => &["sysrq", "printscreen"],
+        "comp" | "cmp" | "menu" | "appskey" => &["compose", "menu"],
+        _ => &[],
+    }
+}
+
+/// The key another tool's key name refers to on this platform
+fn resolve_key(name: &str) -> Option<KeyCode> {
+    // Config reads digits as key codes, so look digit keys up by name
+    if name.chars().all(|c| c.is_ascii_digit()) {
+        return KeyCode::all_known()
+            .into_iter()
+            .find_map(|(known, code)| (known == name).then_some(code));
+    }
+    key_candidates(name)
+        .iter()
+        .copied()
+        .chain(std::iter::once(name))
+        .find_map(KeyCode::from_config_str)
+}
+
+/// Render bindings as a rebinded config, noting `source` in a header comment
+fn render(source: &str, items: &[(KeyCode, Item)]) -> String {
+    let mut out = format!("# Translated from {source} by `rebinded import`\n");
+
+    let mut remaps: Vec<KeyCode> = Vec::new();
+    for (_, item) in items {
+        if let Item::Remap(to) = item
+            && !remaps.contains(to)
+        {
+            remaps.push(*to);
+        }
+    }
+    for to in &remaps {
+        let name = key::config_name(*to);
+        let _ = write!(
+            out,
+            "\n[strategies.{}]\ntype = \"tap_hold\"\nhold_ms = 0\ntap_key = {}\nhold_key = {}\n",
+            table_key(&format!("remap_{name}")),
+            quoted(&name),
+            quoted(&name)
+        );
+    }
+    for (key, item) in items {
+        if let Item::TapHold { hold_ms, tap, hold } = item {
+            let _ = write!(
+                out,
+                "\n[strategies.{}]\ntype = \"tap_hold\"\nhold_ms = {hold_ms}\ntap_key = {}\nhold_key = {}\n",
+                table_key(&format!("{}_tap_hold", key::config_name(*key))),
+                quoted(&key::config_name(*tap)),
+                quoted(&key::config_name(*hold))
+            );
+        }
+    }
+
+    for (key, item) in items {
+        let name = key::config_name(*key);
+        let body = match item {
+            Item::Transparent | Item::Same => continue,
+            Item::Block => "action = \"block\"".to_string(),
+            Item::Remap(to) => format!(
+                "action = \"block\"\nstrategy = {}",
+                quoted(&format!("remap_{}", key::config_name(*to)))
+            ),
+            Item::TapHold { .. } => format!(
+                "action = \"block\"\nstrategy = {}",
+                quoted(&format!("{name}_tap_hold"))
+            ),
+            Item::Action(action) => format!("action = {action}"),
+        };
+        let _ = write!(out, "\n[bindings.{}]\n{body}\n", table_key(&name));
+    }
+    out
+}
+
+/// A single-line TOML string literal, so it can sit inside inline tables
+fn quoted(value: &str) -> String {
+    let mut out = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04X}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// A TOML table key, quoted unless it is a bare key
+fn table_key(key: &str) -> String {
+    if !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        key.to_string()
+    } else {
+        quoted(key)
+    }
+}
@@ -0,0 +1,99 @@
+//! Event handling latency
+//!
+//! Every event is timed from the moment the platform hands it to the handler
+//! until a response is decided. On Windows the low-level hook is silently
+//! removed by the OS if it stalls for too long (`LowLevelHooksTimeout`, about
+//! a second by default), so slow decisions are logged as they happen and the
+//! distribution is available from `rebinded ctl stats`.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use rebinded_core::control::Stats;
+
+/// How many recent samples percentiles are computed over
+const WINDOW: usize = 1024;
+
+/// Rolling record of how long events took to handle
+#[derive(Debug, Default)]
+pub struct LatencyStats {
+    recent: VecDeque<Duration>,
+    count: u64,
+    slow: u64,
+    max: Duration,
+}
+
+impl LatencyStats {
+    /// Record one event. `slow` marks it as having exceeded the budget.
+    pub fn record(&mut self, elapsed: Duration, slow: bool) {
+        if self.recent.len() == WINDOW {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(elapsed);
+        self.count += 1;
+        self.slow += slow as u64;
+        self.max = self.max.max(elapsed);
+    }
+
+    /// The `p`th percentile (0-100) of recent samples, by nearest rank
+    pub fn percentile(&self, p: f64) -> Option<Duration> {
+        if self.recent.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<Duration> = self.recent.iter().copied().collect();
+        sorted.sort_unstable();
+        let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+        Some(sorted[rank.clamp(1, sorted.len()) - 1])
+    }
+
+    /// Summary for `ctl stats`, or `None` before the first event
+    pub fn summary(&self, budget: Duration) -> Option<Stats> {
+        let p50 = self.percentile(50.0)?;
+        Some(Stats {
+            events: self.count,
+            p50,
+            p99: self.percentile(99.0).unwrap_or(p50),
+            max: self.max,
+            slow: self.slow,
+            slow_threshold: budget,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_stats() {
+        let mut stats = LatencyStats::default();
+        assert_eq!(stats.percentile(50.0), None);
+        assert_eq!(stats.summary(Duration::from_millis(20)), None);
+
+        for ms in 1..=100 {
+            stats.record(Duration::from_millis(ms), ms > 20);
+        }
+        assert_eq!(stats.percentile(50.0), Some(Duration::from_millis(50)));
+        assert_eq!(stats.percentile(99.0), Some(Duration::from_millis(99)));
+        assert_eq!(stats.percentile(0.0), Some(Duration::from_millis(1)));
+        assert_eq!(
+            stats.summary(Duration::from_millis(20)),
+            Some(Stats {
+                events: 100,
+                p50: Duration::from_millis(50),
+                p99: Duration::from_millis(99),
+                max: Duration::from_millis(100),
+                slow: 80,
+                slow_threshold: Duration::from_millis(20),
+            })
+        );
+
+        // Only the most recent samples count towards percentiles
+        for _ in 0..WINDOW {
+            stats.record(Duration::from_micros(10), false);
+        }
+        assert_eq!(stats.percentile(99.0), Some(Duration::from_micros(10)));
+        assert_eq!(stats.count, 100 + WINDOW as u64);
+        assert_eq!(stats.max, Duration::from_millis(100));
+    }
+}
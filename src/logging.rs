@@ -0,0 +1,98 @@
+//! Log output setup
+//!
+//! Logs are human-readable text by default. `--log-format json` writes one JSON
+//! object per line instead, with every structured field kept as its own key, so
+//! logs can be shipped to journald, ELK or similar and queried by field. Event
+//! logs carry `key`, `action`, `window.binary`, `window.title` and `latency_us`,
+//! and the fields of the spans an event happened in are under `span` and `spans`.
+
+use std::fs::File;
+use std::sync::Mutex;
+
+use clap::ValueEnum;
+use tracing::Level;
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+
+/// How log lines are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+/// Install the global subscriber, writing to `file` if given and stdout
+/// otherwise
+pub fn init(verbose: bool, format: LogFormat, file: Option<File>) {
+    let filter = if verbose {
+        EnvFilter::new(Level::DEBUG.to_string())
+    } else {
+        EnvFilter::from_default_env().add_directive(Level::INFO.into())
+    };
+    let to_file = file.is_some();
+    let writer = match file {
+        Some(file) => BoxMakeWriter::new(Mutex::new(file)),
+        None => BoxMakeWriter::new(std::io::stdout),
+    };
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(writer)
+        .with_ansi(!to_file);
+    match format {
+        LogFormat::Text => builder.init(),
+        // Fields go at the top level next to `message`, rather than nested
+        // under `fields`, so they can be queried as `key`, `latency_us`, ...
+        LogFormat::Json => builder.json().flatten_event(true).init(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_json_format() {
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        let writer = {
+            let buffer = Arc::clone(&buffer);
+            move || BufferWriter(Arc::clone(&buffer))
+        };
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(writer)
+            .json()
+            .flatten_event(true)
+            .finish();
+
+        tracing::subscriber::with_default(subscriber, || {
+            let _span = tracing::info_span!("daemon", profile = "work").entered();
+            tracing::info!(key = "f13", latency_us = u64::MAX, blocked = true, window.binary = %"spotify", "executing \"action\"");
+        });
+
+        let output = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        assert_eq!(output.lines().count(), 1);
+        let line: serde_json::Value = serde_json::from_str(output.trim_end()).unwrap();
+        assert_eq!(line["level"], "INFO");
+        assert_eq!(line["message"], "executing \"action\"");
+        assert_eq!(line["key"], "f13");
+        // Integers are not squeezed through f64
+        assert_eq!(line["latency_us"].as_u64(), Some(u64::MAX));
+        assert_eq!(line["blocked"], true);
+        assert_eq!(line["window.binary"], "spotify");
+        assert_eq!(line["span"]["profile"], "work");
+        assert!(line["timestamp"].is_string());
+    }
+
+    struct BufferWriter(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for BufferWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+}
@@ -1,54 +1,388 @@
-mod config;
-mod key;
-mod platform;
-mod strategy;
+mod config_path;
+mod control;
+mod daemonize;
+mod doctor;
+mod import;
+mod latency;
+mod logging;
+mod service;
+mod simulate;
+mod tray;
 
-use clap::Parser;
-use config::{Action, RuntimeConfig};
+use clap::{Parser, Subcommand};
+use control::Daemon;
 use key::InputEvent;
-use platform::{EventResponse, Platform, PlatformInterface};
-use std::path::PathBuf;
+use logging::LogFormat;
+use platform::{EventResponse, MockPlatform, Platform, PlatformInterface, PlatformState};
+use rebinded_core::control::Request;
+use rebinded_core::{config, engine, integrations, key, platform, strategy, trace};
+use std::cell::{OnceCell, RefCell};
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
-use strategy::{PlatformHandle, StrategyContext};
-use tracing::{Level, debug, info, trace};
-use tracing_subscriber::EnvFilter;
+use std::time::{Duration, Instant};
+use strategy::PlatformHandle;
+use tracing::{debug, info, warn};
 
 #[derive(Parser)]
 #[command(name = "rebinded", about = "Cross-platform key remapping daemon")]
 struct Args {
-    /// Path to config file (default: ~/.config/rebinded/config.toml)
+    /// Path or https:// URL of the config file (default: $REBINDED_CONFIG, then
+    /// rebinded/config.toml in $XDG_CONFIG_HOME or $XDG_CONFIG_DIRS, or
+    /// %APPDATA% on Windows)
     #[arg(short, long)]
     config: Option<PathBuf>,
 
+    /// Accept config files and includes from plain http:// URLs, which anyone
+    /// on the network path could tamper with
+    #[arg(long)]
+    allow_http_config: bool,
+
     /// Enable verbose logging
     #[arg(short, long)]
     verbose: bool,
+
+    /// Log output format
+    #[arg(long, value_enum, default_value_t)]
+    log_format: LogFormat,
+
+    /// Run the full pipeline but log actions instead of executing them, and
+    /// never block keys
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Write every handled event, with its time and focused window, to this
+    /// file as JSON lines for `rebinded replay`
+    #[arg(long, value_name = "FILE")]
+    record_trace: Option<PathBuf>,
+
+    /// Show a system tray icon and close the console window (Windows)
+    #[arg(long)]
+    tray: bool,
+
+    /// Detach from the terminal and run in the background, with logs going to
+    /// --log-file and the process ID to --pid-file
+    #[arg(long, conflicts_with = "foreground")]
+    daemon: bool,
+
+    /// Stay attached to the terminal (the default)
+    #[arg(long)]
+    foreground: bool,
+
+    /// Write the daemon's process ID here while it runs (default with
+    /// --daemon: $XDG_RUNTIME_DIR/rebinded.pid)
+    #[arg(long)]
+    pid_file: Option<PathBuf>,
+
+    /// Append logs to this file instead of printing them (default with
+    /// --daemon: ~/.local/state/rebinded/rebinded.log)
+    #[arg(long)]
+    log_file: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List key names usable in [bindings] and the codes they resolve to
+    Keys {
+        /// Only show keys with a name containing this text
+        filter: Option<String>,
+    },
+    /// Wait for the next key press and print a [bindings] stanza for it
+    Record {
+        /// Append the stanza to the config file instead of only printing it
+        #[arg(short, long)]
+        append: bool,
+    },
+    /// Send a command to the running daemon (pause, resume, toggle, reload,
+    /// profile <name>, enable <key>, disable <key>, status, state)
+    Ctl {
+        #[arg(required = true, num_args = 1..)]
+        command: Vec<String>,
+    },
+    /// Translate a kanata, kmonad, AutoHotkey or Karabiner-Elements configuration
+    /// into a rebinded config
+    Import {
+        /// Which tool the configuration is for
+        #[arg(value_enum)]
+        format: import::Format,
+        /// The configuration file to translate
+        file: PathBuf,
+        /// Write the config here, and layers to `profiles/` beside it,
+        /// instead of printing the config
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Show which binding, rule, strategies and action a press of a key
+    /// would go through, without running anything
+    Explain {
+        /// Key name or code, as written in [bindings]
+        key: String,
+        /// Title of the focused window to assume
+        #[arg(long, default_value = "")]
+        title: String,
+        /// Window class to assume
+        #[arg(long, default_value = "")]
+        class: String,
+        /// Executable name of the focused window to assume
+        #[arg(long, default_value = "")]
+        binary: String,
+    },
+    /// Feed a trace recorded with --record-trace through the engine at its
+    /// recorded times, printing each event's response and the actions it ran
+    Replay {
+        /// Trace file (JSON lines)
+        file: PathBuf,
+    },
+    /// Run a script of timed events through the engine against a mock platform
+    /// and report responses and actions that don't match its expectations
+    Simulate {
+        /// Script file (TOML)
+        script: PathBuf,
+    },
+    /// Check that this machine can run the daemon (device permissions,
+    /// display session, elevation, hook timeout, config) and print fixes
+    Doctor,
+    /// Inspect the configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Manage the background service
+    Service {
+        #[command(subcommand)]
+        command: ServiceCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Print the configuration the daemon would run with: defaults filled in,
+    /// overrides applied, key ranges expanded and key names resolved
+    Dump,
+}
+
+#[derive(Subcommand)]
+enum ServiceCommand {
+    /// Register the daemon as a service (with --config, if given): a systemd
+    /// unit on Linux, a Windows service otherwise
+    Install {
+        /// Linux: install a user unit started with the graphical session
+        /// instead of a system unit
+        #[arg(long)]
+        user: bool,
+    },
+    /// Stop and remove the service
+    Uninstall {
+        /// Linux: remove the user unit
+        #[arg(long)]
+        user: bool,
+    },
+    /// Start the installed service
+    Start {
+        /// Linux: start the user unit
+        #[arg(long)]
+        user: bool,
+    },
+    /// Stop the running service
+    Stop {
+        /// Linux: stop the user unit
+        #[arg(long)]
+        user: bool,
+    },
+    /// Entry point used by the Windows service manager
+    #[command(hide = true)]
+    Run,
 }
 
 fn default_config_path() -> PathBuf {
-    dirs::config_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join("rebinded")
-        .join("config.toml")
+    config_path::resolve().path
 }
 
-#[tokio::main(flavor = "current_thread")]
-async fn main() -> ExitCode {
-    let args = Args::parse();
+fn main() -> ExitCode {
+    let mut args = Args::parse();
+
+    // Forking has to happen before the runtime starts any threads
+    if args.daemon {
+        if args.command.is_some() {
+            eprintln!("error: --daemon only applies to running the daemon, not subcommands");
+            return ExitCode::FAILURE;
+        }
+        let pid_file = args
+            .pid_file
+            .get_or_insert_with(daemonize::default_pid_file)
+            .clone();
+        let log_file = args
+            .log_file
+            .get_or_insert_with(daemonize::default_log_file)
+            .clone();
+        // The daemon runs from /, so a relative config path would no longer resolve
+        if let Some(config) = &mut args.config
+            && let Ok(absolute) = std::path::absolute(&*config)
+        {
+            *config = absolute;
+        }
+        match daemonize::detach(&pid_file, &log_file) {
+            Ok(daemonize::Detached::Parent) => {
+                println!(
+                    "rebinded is running in the background (logs: {}, pid file: {})",
+                    log_file.display(),
+                    pid_file.display()
+                );
+                return ExitCode::SUCCESS;
+            }
+            Ok(daemonize::Detached::Daemon) => {}
+            Err(err) => {
+                eprintln!("error: {err:#}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    match tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+    {
+        Ok(runtime) => runtime.block_on(run(args)),
+        Err(err) => {
+            eprintln!("error: failed to start the async runtime: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(args: Args) -> ExitCode {
+    let load_options = config::LoadOptions {
+        allow_plain_http: args.allow_http_config,
+    };
+    if let Some(Command::Keys { filter }) = &args.command {
+        print_keys(filter.as_deref());
+        return ExitCode::SUCCESS;
+    }
+    if let Some(Command::Ctl { command }) = &args.command {
+        return send_control_command(&command.join(" ")).await;
+    }
+    if let Some(Command::Import {
+        format,
+        file,
+        output,
+    }) = &args.command
+    {
+        return match import::run(*format, file, output.as_deref()) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(err) => {
+                eprintln!("error: {err:#}");
+                ExitCode::FAILURE
+            }
+        };
+    }
+    if let Some(Command::Config { command }) = &args.command {
+        let config_path = args.config.clone().unwrap_or_else(default_config_path);
+        return match command {
+            ConfigCommand::Dump => dump_config(&config_path, load_options).await,
+        };
+    }
+    if let Some(Command::Doctor) = &args.command {
+        let config_path = args.config.clone().unwrap_or_else(default_config_path);
+        return doctor::run(&config_path, load_options).await;
+    }
+    if let Some(Command::Explain {
+        key,
+        title,
+        class,
+        binary,
+    }) = &args.command
+    {
+        let config_path = args.config.clone().unwrap_or_else(default_config_path);
+        let window = config::WindowInfo {
+            title: title.clone(),
+            class: class.clone(),
+            binary: binary.clone(),
+        };
+        return explain_key(&config_path, key, &window, load_options).await;
+    }
+    if let Some(Command::Replay { file }) = &args.command {
+        let config_path = args.config.clone().unwrap_or_else(default_config_path);
+        return replay_trace(&config_path, file, load_options).await;
+    }
+    if let Some(Command::Simulate { script }) = &args.command {
+        return simulate::run(args.config.as_deref(), script, load_options).await;
+    }
+    if let Some(Command::Service { command }) = &args.command {
+        // The service doesn't see this shell's environment, so a config named
+        // by REBINDED_CONFIG goes on its command line
+        let config = args.config.clone().or_else(config_path::from_env);
+        let config = config.as_deref();
+        let result = match *command {
+            ServiceCommand::Install { user } => service::install(user, config),
+            ServiceCommand::Uninstall { user } => service::uninstall(user),
+            ServiceCommand::Start { user } => service::start(user),
+            ServiceCommand::Stop { user } => service::stop(user),
+            ServiceCommand::Run => service::run(config),
+        };
+        return match result {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(err) => {
+                eprintln!("error: {err:#}");
+                ExitCode::FAILURE
+            }
+        };
+    }
 
     // Initialize logging
-    let filter = if args.verbose {
-        EnvFilter::new(Level::DEBUG.to_string())
-    } else {
-        EnvFilter::from_default_env().add_directive(Level::INFO.into())
+    let log_file = match args
+        .log_file
+        .as_deref()
+        .map(daemonize::open_log)
+        .transpose()
+    {
+        Ok(file) => file,
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            return ExitCode::FAILURE;
+        }
+    };
+    logging::init(args.verbose, args.log_format, log_file);
+
+    // Keys the daemon pressed must not outlive it, even if it crashes. This also
+    // runs for panics tokio catches in spawned tasks; releasing there is harmless,
+    // a later release of the same key finds nothing held.
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        platform::release_held_keys();
+        default_hook(info);
+    }));
+
+    let (config_path, source) = match args.config {
+        Some(path) => (path, "--config"),
+        None => {
+            let resolved = config_path::resolve();
+            (resolved.path, resolved.source)
+        }
     };
-    tracing_subscriber::fmt().with_env_filter(filter).init();
+
+    if let Some(Command::Record { append }) = args.command {
+        return record_binding(&config_path, append).await;
+    }
+
+    // Held until the daemon stops, which removes the file
+    let _pid_file = match args.pid_file.as_deref().map(daemonize::PidFile::create) {
+        Some(Ok(pid_file)) => Some(pid_file),
+        Some(Err(err)) => {
+            eprintln!("error: {err:#}");
+            return ExitCode::FAILURE;
+        }
+        None => None,
+    };
+
+    // Plugins register strategy types, so they must load before the config
+    load_plugins(&config_path);
 
     // Load and validate config
-    let config_path = args.config.unwrap_or_else(default_config_path);
-    info!("loading config from {}", config_path.display());
+    info!("loading config from {} ({source})", config_path.display());
 
-    let (config, runtime_config) = match config::load(&config_path).await {
+    let (config, runtime_config) = match config::load_with(&config_path, load_options).await {
         Ok(result) => result,
         Err(err) => {
             // Use miette's fancy error display
@@ -71,13 +405,100 @@ async fn main() -> ExitCode {
 
     // Create platform and run event loop
     let mut platform = Platform::new();
+    let state = platform.state().clone();
+    let daemon = Daemon::new(config_path, runtime_config, state.clone());
+    daemon.set_load_options(load_options);
+    daemon.configure(&config.settings);
 
-    if let Err(err) = platform
-        .run(|event: InputEvent, platform_handle: PlatformHandle| {
-            handle_event(event, platform_handle, &runtime_config)
-        })
-        .await
-    {
+    let last_window = RefCell::new(None);
+
+    // Created on the first event, from the real platform's handle
+    let observer = OnceCell::new();
+    if args.dry_run {
+        info!("dry run: actions are logged, not executed, and no keys are blocked");
+        daemon.set_dry_run(true);
+    }
+
+    let tracer = match &args.record_trace {
+        Some(path) => match trace::Recorder::create(path) {
+            Ok(tracer) => {
+                info!("recording a trace to {}", path.display());
+                Some(tracer)
+            }
+            Err(err) => {
+                eprintln!("error: {err:#}");
+                return ExitCode::FAILURE;
+            }
+        },
+        None => None,
+    };
+
+    let (daemon, last_window, observer) = (&daemon, &last_window, &observer);
+    let tracer = tracer.as_ref();
+    let dry_run = args.dry_run;
+    let shutdown = async {
+        tokio::select! {
+            _ = shutdown_signal() => {}
+            _ = tray::run(daemon), if args.tray => {}
+        }
+    };
+    let event_loop = platform.run(
+        |event: InputEvent, platform_handle: PlatformHandle| {
+            // Key events carry the time the hook saw them, so latency includes
+            // any wait before the handler ran; other events start here
+            let captured = match &event {
+                InputEvent::Key(key) => key.time,
+                _ => Instant::now(),
+            };
+            let config = daemon.runtime_for(&event);
+            let platform_handle = if dry_run {
+                let observer = observer.get_or_init(|| MockPlatform::observing(platform_handle));
+                PlatformHandle::from_mock(observer)
+            } else {
+                platform_handle
+            };
+            daemon.attach_platform(&platform_handle);
+            // Traces carry the window for every event, so replays see the same
+            // focus changes even where the engine never looked it up
+            let window = tracer.map(|_| platform_handle.get_active_window());
+            async move {
+                if daemon.bypasses(&event) {
+                    return EventResponse::Passthrough;
+                }
+                let id = event.id();
+                let traced = window.map(|window| (event.clone(), window));
+                let response =
+                    engine::handle_event(event, platform_handle, &config, last_window).await;
+                daemon.record_latency(&id, captured.elapsed());
+                if let (Some(tracer), Some((event, window))) = (tracer, traced) {
+                    tracer.record(&event, captured, &window, response);
+                }
+                if dry_run {
+                    return EventResponse::Passthrough;
+                }
+                response
+            }
+        },
+        shutdown,
+    );
+
+    if args.tray {
+        tray::detach_console();
+    }
+
+    service::notify("READY=1");
+    let result = tokio::select! {
+        result = event_loop => result,
+        _ = dump_state_on_signal(daemon) => Ok(()),
+        _ = control::serve(daemon) => Ok(()),
+        _ = follow_layout_changes(daemon, &state) => Ok(()),
+        _ = follow_auto_disable(daemon, &state) => Ok(()),
+        _ = service::watchdog() => Ok(()),
+    };
+    service::notify("STOPPING=1");
+    info!("stopped");
+    let _ = std::io::Write::flush(&mut std::io::stdout());
+    if let Err(err) = result {
         eprintln!("error: {err:?}");
         return ExitCode::FAILURE;
     }
@@ -85,93 +506,394 @@ async fn main() -> ExitCode {
     ExitCode::SUCCESS
 }
 
-/// Handle an input event from the platform
-async fn handle_event(
-    event: InputEvent,
-    platform: PlatformHandle,
-    config: &RuntimeConfig,
-) -> EventResponse {
-    let event_id = event.id();
-
-    // Check if any strategy is subscribed to this event
-    if let Some(strategy_names) = config.subscriptions.get(&event_id) {
-        trace!(
-            ?event_id,
-            ?strategy_names,
-            "routing to subscribed strategies"
+/// Load strategy plugins from the config's directory. Plugins are native code,
+/// so a system config forbidding commands in user configs keeps them out too.
+fn load_plugins(config_path: &Path) {
+    let Some(config_dir) = config_path.parent() else {
+        return;
+    };
+    if config_path != config::system_config_path() && !config::user_commands_allowed() {
+        warn!(
+            "not loading plugins from {}: the system config disallows commands",
+            config_dir.display()
         );
+        return;
+    }
+    strategy::plugin::load_dir(&config_dir.join("plugins"));
+}
 
-        // Route to each subscribed strategy
-        // If any strategy blocks, return Block; otherwise Passthrough
-        for strategy_name in strategy_names {
-            if let Some(strategy) = config.strategies.get(strategy_name) {
-                // For subscribed events, we use a dummy action since the strategy
-                // will use its own divert actions
-                let ctx = StrategyContext::new(platform, &Action::Block);
-                let mut strategy_guard = strategy.lock().await;
-                let response = strategy_guard.process(&event, &ctx).await;
-
-                if response == EventResponse::Block {
-                    return EventResponse::Block;
-                }
+/// Print the resolved config as TOML. Runs before logging is set up, so load
+/// warnings don't end up mixed into the output.
+async fn dump_config(config_path: &Path, options: config::LoadOptions) -> ExitCode {
+    load_plugins(config_path);
+    match config::load_with(config_path, options).await {
+        Ok((config, runtime)) => {
+            print!("{}", config::dump(&config, &runtime));
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("{:?}", miette::Report::new(err));
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Print how a key press would be handled in the given window. Like
+/// `config dump`, runs before logging so load warnings stay out of the output.
+async fn explain_key(
+    config_path: &Path,
+    name: &str,
+    window: &config::WindowInfo,
+    options: config::LoadOptions,
+) -> ExitCode {
+    let Some(key) = key::KeyCode::from_config_str(name) else {
+        match key::suggest_key_name(name) {
+            Some(suggestion) => {
+                eprintln!("error: unknown key '{name}' (did you mean '{suggestion}'?)")
             }
+            None => eprintln!("error: unknown key '{name}'; `rebinded keys` lists key names"),
         }
+        return ExitCode::FAILURE;
+    };
+    load_plugins(config_path);
+    match config::load_with(config_path, options).await {
+        Ok((_, runtime)) => {
+            print!("{}", config::explain(&runtime, key, window));
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("{:?}", miette::Report::new(err));
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Replay a trace against the config, printing what happens to each event and
+/// failing if any response differs from the recorded one
+async fn replay_trace(config_path: &Path, file: &Path, options: config::LoadOptions) -> ExitCode {
+    load_plugins(config_path);
+    let runtime = match config::load_with(config_path, options).await {
+        Ok((_, runtime)) => runtime,
+        Err(err) => {
+            eprintln!("{:?}", miette::Report::new(err));
+            return ExitCode::FAILURE;
+        }
+    };
+    let origin = Instant::now();
+    let events = match trace::read(file, origin) {
+        Ok(events) => events,
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let replayed = trace::replay(&runtime, events, origin).await;
+    let mut differing = 0;
+    for event in &replayed {
+        let traced = &event.traced;
+        let response = format!("{:?}", event.response).to_lowercase();
+        let differs = if event.response == traced.response {
+            String::new()
+        } else {
+            differing += 1;
+            format!(
+                " (recorded: {})",
+                format!("{:?}", traced.response).to_lowercase()
+            )
+        };
+        println!(
+            "{:>9.3}s  {} in {:?}: {response}{differs}",
+            traced.at.as_secs_f64(),
+            trace::describe(&traced.event),
+            traced.window.binary
+        );
+        for call in &event.calls {
+            println!("            {call:?}");
+        }
+    }
+    println!(
+        "{} events replayed, {differing} responses differ from the recording",
+        replayed.len()
+    );
+    if differing > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Print each known key code with every name that resolves to it
+fn print_keys(filter: Option<&str>) {
+    let keys = key::known_key_names(filter);
+    if keys.is_empty() {
+        eprintln!("no key names match");
+        return;
+    }
+    for group in keys {
+        let mut names = group.names.join(", ");
+        if !group.layout_names.is_empty() {
+            if !names.is_empty() {
+                names.push_str("  ");
+            }
+            names.push_str(&format!("(layout: {})", group.layout_names.join(", ")));
+        }
+        println!(
+            "{:#06x}  {:>5}  {names}",
+            group.code.code(),
+            group.code.code()
+        );
+    }
+}
+
+/// Grab the keyboard until a key is pressed, then print (and optionally append)
+/// a binding stanza for it. The recorded press is swallowed.
+async fn record_binding(config_path: &Path, append: bool) -> ExitCode {
+    let mut platform = Platform::new();
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+
+    eprintln!("press the key to bind...");
+    let recorded = std::cell::Cell::new(None);
+    // Stop once a key arrives, letting the platform release the keyboard first
+    let shutdown = async {
+        tokio::select! {
+            Some(key) = rx.recv() => recorded.set(Some(key)),
+            _ = shutdown_signal() => {}
+        }
+    };
+    let result = platform
+        .run(
+            |event: InputEvent, _platform: PlatformHandle| {
+                let response = match event {
+                    InputEvent::Key(key_event) if key_event.down => {
+                        let _ = tx.send(key_event.key);
+                        EventResponse::Block
+                    }
+                    _ => EventResponse::Passthrough,
+                };
+                std::future::ready(response)
+            },
+            shutdown,
+        )
+        .await;
+
+    let key = match (result, recorded.get()) {
+        (Ok(()), Some(key)) => key,
+        (Ok(()), None) => return ExitCode::FAILURE,
+        (Err(err), _) => {
+            eprintln!("error: {err:?}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let name = key::config_name(key);
+    let snippet = format!(
+        "[bindings.{name}]\naction = \"passthrough\"  # {}, replace with the action to run\n",
+        key.display_name()
+    );
+    print!("{snippet}");
+
+    if append {
+        if let Err(err) = append_binding(config_path, key, &snippet) {
+            eprintln!("error: {err}");
+            return ExitCode::FAILURE;
+        }
+        eprintln!("appended to {}", config_path.display());
+    }
+    ExitCode::SUCCESS
+}
+
+/// Append a binding stanza, refusing if the config already binds the key
+fn append_binding(config_path: &Path, key: key::KeyCode, snippet: &str) -> anyhow::Result<()> {
+    use std::io::Write;
+
+    if config::is_url(&config_path.to_string_lossy()) {
+        anyhow::bail!(
+            "{} is a remote config; add the binding there",
+            config_path.display()
+        );
+    }
+    let existing = match std::fs::read_to_string(config_path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let parsed: toml::Table = toml::from_str(&existing)?;
+    if let Some(bindings) = parsed.get("bindings").and_then(toml::Value::as_table)
+        && let Some(name) = bindings
+            .keys()
+            .find(|name| key::KeyCode::from_config_str(name) == Some(key))
+    {
+        anyhow::bail!("{} already binds '{name}'", config_path.display());
+    }
 
-        // No strategy blocked, check if this is a key event that also has bindings
-        // (fall through to normal handling below)
+    if let Some(dir) = config_path.parent() {
+        std::fs::create_dir_all(dir)?;
     }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(config_path)?;
+    let separator = match existing.as_str() {
+        "" => "",
+        s if s.ends_with('\n') => "\n",
+        _ => "\n\n",
+    };
+    write!(file, "{separator}{snippet}")?;
+    Ok(())
+}
+
+/// Send one control command and print the daemon's reply
+async fn send_control_command(command: &str) -> ExitCode {
+    let request = match Request::parse(command) {
+        Ok(request) => request,
+        Err(err) => {
+            eprintln!("error: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    match control::send(&request).await {
+        Ok(Ok(response)) => {
+            println!("{response}");
+            ExitCode::SUCCESS
+        }
+        Ok(Err(err)) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
 
-    // For scroll events with no subscriptions, pass through
-    let key_event = match &event {
-        InputEvent::Key(key_event) => key_event,
-        InputEvent::Scroll { .. } => {
-            return EventResponse::Passthrough;
+/// Resolve on Ctrl+C or SIGTERM, so the platform can release keys and devices
+/// before exiting
+#[cfg(unix)]
+async fn shutdown_signal() {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut sigterm = match signal(SignalKind::terminate()) {
+        Ok(sigterm) => sigterm,
+        Err(e) => {
+            warn!("SIGTERM handling unavailable: {e}");
+            let _ = tokio::signal::ctrl_c().await;
+            return;
         }
     };
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => info!("received Ctrl+C"),
+        _ = sigterm.recv() => info!("received SIGTERM"),
+    }
+}
 
-    // TODO: Fast-path optimization - check static BOUND_KEYS set before crossing
-    // async boundary to avoid channel overhead for unbound keys (~99% of key presses)
+/// Resolve on Ctrl+C or when the console window is closed
+#[cfg(windows)]
+async fn shutdown_signal() {
+    use tokio::signal::windows::ctrl_close;
 
-    // Check if this key has a binding - if not, pass through
-    let Some(binding) = config.bindings.get(&key_event.key) else {
-        return EventResponse::Passthrough;
+    let mut close = match ctrl_close() {
+        Ok(close) => close,
+        Err(e) => {
+            warn!("console close handling unavailable: {e}");
+            let _ = tokio::signal::ctrl_c().await;
+            return;
+        }
     };
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => info!("received Ctrl+C"),
+        _ = close.recv() => info!("console closed"),
+    }
+}
 
-    // Resolve the action based on window context
-    let window = platform.get_active_window();
-    let Some(action) = config.resolve_action(key_event.key, &window) else {
-        return EventResponse::Passthrough;
+/// Log every strategy's internal state each time SIGUSR1 arrives
+/// (`kill -USR1 $(pidof rebinded)`), for diagnosing misbehaving strategies
+#[cfg(unix)]
+async fn dump_state_on_signal(daemon: &Daemon) {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut sigusr1 = match signal(SignalKind::user_defined1()) {
+        Ok(sigusr1) => sigusr1,
+        Err(e) => {
+            warn!("state dumps unavailable: {e}");
+            return std::future::pending().await;
+        }
     };
 
-    // Handle passthrough/block actions directly
-    if let Some(response) = action.as_response() {
-        return response;
+    while sigusr1.recv().await.is_some() {
+        let config = daemon.runtime();
+        let mut names: Vec<&String> = config.strategies.keys().collect();
+        names.sort();
+        for name in names {
+            let state = config.strategies[name].borrow().debug_state();
+            info!(strategy = name, "state: {state}");
+        }
     }
+}
 
-    // TODO: For strategies that don't need async (direct action execution),
-    // consider thread-local dispatch to avoid tokio scheduling overhead
+#[cfg(not(unix))]
+async fn dump_state_on_signal(_daemon: &Daemon) {
+    std::future::pending().await
+}
 
-    // If binding has a strategy, delegate to it
-    if let Some(ref strategy_ref) = binding.strategy {
-        let strategy_name = strategy_ref.value();
-        if let Some(strategy) = config.strategies.get(strategy_name) {
-            let ctx = StrategyContext::new(platform, action);
-            let mut strategy_guard = strategy.lock().await;
-            return strategy_guard.process(&event, &ctx).await;
-        } else {
-            // This should not happen if validation is working correctly
-            debug!(
-                strategy = strategy_name,
-                key = ?key_event.key,
-                "strategy not found, falling through to direct execution"
-            );
+/// How often the keyboard layout is checked for changes when the platform
+/// isn't watching for them
+const LAYOUT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How often the focused app is checked against `[settings.auto_disable]`
+/// without a focus change, for apps that go fullscreen while focused
+const AUTO_DISABLE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Watch the focused app for `[settings.auto_disable]`, which may be turned
+/// on by a reload at any time. Checked as soon as focus moves, and now and
+/// then in between.
+async fn follow_auto_disable(daemon: &Daemon, state: &PlatformState) {
+    loop {
+        tokio::select! {
+            () = state.focus_changed() => {}
+            () = tokio::time::sleep(AUTO_DISABLE_POLL_INTERVAL) => {}
         }
+        daemon.check_auto_disable();
     }
+}
 
-    // No strategy: execute action directly on key-down
-    if key_event.down {
-        debug!(key = ?key_event.key, ?action, "executing action directly");
-        platform.execute(action);
+/// Follow keyboard layout switches (Win+Space, setxkbmap): rebuild the key
+/// names taken from the layout, and reload the config so bindings written with
+/// them resolve under the new layout instead of silently keeping the old codes
+///
+/// Woken by the platform's layout-change notifications, and polls only while
+/// the platform has none.
+async fn follow_layout_changes(daemon: &Daemon, state: &PlatformState) {
+    let Some(mut layout) = platform::layout_fingerprint() else {
+        debug!("keyboard layout unavailable, not watching for layout changes");
+        return std::future::pending().await;
+    };
+
+    loop {
+        if state.watching_layout() {
+            // The timeout only re-checks that the platform is still watching
+            tokio::select! {
+                () = state.layout_changed() => {}
+                () = tokio::time::sleep(LAYOUT_POLL_INTERVAL) => continue,
+            }
+        } else {
+            tokio::time::sleep(LAYOUT_POLL_INTERVAL).await;
+        }
+        let Some(current) = platform::layout_fingerprint() else {
+            continue;
+        };
+        if current == layout {
+            continue;
+        }
+        layout = current;
+        if !key::refresh_layout_names() {
+            debug!("keyboard layout changed, key names unaffected");
+            continue;
+        }
+        info!("keyboard layout changed, reloading config");
+        if let Err(e) = daemon.apply(Request::Reload).await {
+            warn!("config no longer loads under the new keyboard layout:\n{e}");
+        }
     }
-    EventResponse::Block
 }
@@ -0,0 +1,16 @@
+//! Running rebinded as a background service
+//!
+//! On Linux the daemon integrates with systemd: it reports readiness and
+//! watchdog pings over `$NOTIFY_SOCKET`, and `rebinded service install` writes
+//! a unit file for it. On Windows it registers a service that launches the
+//! daemon into the interactive session.
+
+#[cfg(unix)]
+mod systemd;
+#[cfg(windows)]
+mod windows;
+
+#[cfg(unix)]
+pub use systemd::{install, notify, run, start, stop, uninstall, watchdog};
+#[cfg(windows)]
+pub use windows::{install, notify, run, start, stop, uninstall, watchdog};
@@ -0,0 +1,302 @@
+//! systemd integration
+//!
+//! `Type=notify` units get `READY=1` once the config is loaded and `STOPPING=1`
+//! on exit. When the unit sets `WatchdogSec=`, the main task pings the watchdog
+//! at half the interval, so a wedged event loop gets the daemon restarted.
+//!
+//! The protocol is a datagram to the socket named by `$NOTIFY_SOCKET` (a path,
+//! or an abstract socket when it starts with `@`), so no libsystemd is needed.
+
+use anyhow::Context;
+use std::ffi::OsStr;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::net::UnixDatagram;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Send a state update such as `READY=1` to the service manager. Does nothing
+/// when not started by systemd.
+pub fn notify(state: &str) {
+    let Some(socket) = std::env::var_os("NOTIFY_SOCKET") else {
+        return;
+    };
+    match send_notify(&socket, state) {
+        Ok(()) => debug!(state, "notified service manager"),
+        Err(e) => warn!(state, "failed to notify service manager: {e}"),
+    }
+}
+
+fn send_notify(socket: &OsStr, state: &str) -> io::Result<()> {
+    let sock = UnixDatagram::unbound()?;
+    if let Some(name) = socket.as_bytes().strip_prefix(b"@") {
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::linux::net::SocketAddrExt;
+            let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+            sock.send_to_addr(state.as_bytes(), &addr)?;
+            return Ok(());
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = name;
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "abstract notify sockets are Linux-only",
+            ));
+        }
+    }
+    sock.send_to(state.as_bytes(), Path::new(socket))?;
+    Ok(())
+}
+
+/// Ping the watchdog for as long as the daemon runs. Pending forever when the
+/// unit has no watchdog.
+pub async fn watchdog() {
+    let interval = watchdog_interval(
+        std::env::var("WATCHDOG_USEC").ok().as_deref(),
+        std::env::var("WATCHDOG_PID").ok().as_deref(),
+        std::process::id(),
+    );
+    let Some(interval) = interval else {
+        return std::future::pending().await;
+    };
+
+    debug!(?interval, "systemd watchdog enabled");
+    let mut ticker = tokio::time::interval(interval / 2);
+    loop {
+        ticker.tick().await;
+        notify("WATCHDOG=1");
+    }
+}
+
+/// The watchdog timeout, if it is set and meant for this process
+fn watchdog_interval(usec: Option<&str>, pid: Option<&str>, own_pid: u32) -> Option<Duration> {
+    if let Some(pid) = pid
+        && pid.parse::<u32>().ok() != Some(own_pid)
+    {
+        return None;
+    }
+    let usec: u64 = usec?.parse().ok()?;
+    (usec > 0).then(|| Duration::from_micros(usec))
+}
+
+const UNIT_NAME: &str = "rebinded.service";
+
+/// Where the system unit is written
+const SYSTEM_UNIT_PATH: &str = "/etc/systemd/system/rebinded.service";
+
+/// Write a unit file for the daemon and print how to enable it
+///
+/// `user` writes a user unit tied to the graphical session, which is what
+/// window conditions need. Otherwise a system unit is written that runs as the
+/// invoking user with the `input` group added, which needs root.
+pub fn install(user: bool, config: Option<&Path>) -> anyhow::Result<()> {
+    let exe = std::env::current_exe().context("could not locate the rebinded binary")?;
+    let config = config.map(std::path::absolute).transpose()?;
+
+    let path = unit_path(user)?;
+    let unit = if user {
+        unit_file(&exe, config.as_deref(), None)
+    } else {
+        // Under sudo, run as the user who asked rather than root
+        let account = std::env::var("SUDO_USER")
+            .or_else(|_| std::env::var("USER"))
+            .context("could not determine which user the service should run as")?;
+        unit_file(&exe, config.as_deref(), Some(&account))
+    };
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+    std::fs::write(&path, unit).with_context(|| {
+        let hint = if user {
+            ""
+        } else {
+            " (system units need sudo)"
+        };
+        format!("failed to write {}{hint}", path.display())
+    })?;
+
+    println!("wrote {}", path.display());
+    if user {
+        println!("enable it with:\n  systemctl --user daemon-reload");
+        println!("  systemctl --user enable --now rebinded.service");
+        println!(
+            "user services can't gain groups, so your account needs access to /dev/input and\n\
+             /dev/uinput (usually: sudo usermod -aG input $USER, then log in again)"
+        );
+    } else {
+        println!("enable it with:\n  sudo systemctl daemon-reload");
+        println!("  sudo systemctl enable --now rebinded.service");
+    }
+    Ok(())
+}
+
+/// Disable and remove the unit written by `install`
+pub fn uninstall(user: bool) -> anyhow::Result<()> {
+    let path = unit_path(user)?;
+    if !path.exists() {
+        anyhow::bail!("{} does not exist", path.display());
+    }
+    // Fails harmlessly if the unit was never enabled
+    let _ = systemctl(user, &["disable", "--now", UNIT_NAME]);
+    std::fs::remove_file(&path).with_context(|| {
+        let hint = if user {
+            ""
+        } else {
+            " (system units need sudo)"
+        };
+        format!("failed to remove {}{hint}", path.display())
+    })?;
+    systemctl(user, &["daemon-reload"])?;
+    println!("removed {}", path.display());
+    Ok(())
+}
+
+pub fn start(user: bool) -> anyhow::Result<()> {
+    systemctl(user, &["start", UNIT_NAME])
+}
+
+pub fn stop(user: bool) -> anyhow::Result<()> {
+    systemctl(user, &["stop", UNIT_NAME])
+}
+
+/// Only the Windows service manager launches `service run`
+pub fn run(_config: Option<&Path>) -> anyhow::Result<()> {
+    anyhow::bail!("`service run` is only used by the Windows service manager")
+}
+
+fn systemctl(user: bool, args: &[&str]) -> anyhow::Result<()> {
+    let mut command = std::process::Command::new("systemctl");
+    if user {
+        command.arg("--user");
+    }
+    let status = command
+        .args(args)
+        .status()
+        .context("failed to run systemctl")?;
+    if !status.success() {
+        anyhow::bail!("systemctl {} failed ({status})", args.join(" "));
+    }
+    Ok(())
+}
+
+fn unit_path(user: bool) -> anyhow::Result<PathBuf> {
+    if !user {
+        return Ok(PathBuf::from(SYSTEM_UNIT_PATH));
+    }
+    Ok(dirs::config_dir()
+        .context("could not determine the config directory")?
+        .join("systemd")
+        .join("user")
+        .join(UNIT_NAME))
+}
+
+/// Render the unit file. `system_user` selects a system unit running as that account.
+fn unit_file(exe: &Path, config: Option<&Path>, system_user: Option<&str>) -> String {
+    let mut exec_start = quote_arg(&exe.to_string_lossy());
+    if let Some(config) = config {
+        exec_start.push_str(" --config ");
+        exec_start.push_str(&quote_arg(&config.to_string_lossy()));
+    }
+
+    let (unit_deps, account, wanted_by) = match system_user {
+        Some(account) => (
+            String::new(),
+            format!("User={account}\nSupplementaryGroups=input\n"),
+            "multi-user.target",
+        ),
+        None => (
+            "PartOf=graphical-session.target\nAfter=graphical-session.target\n".to_string(),
+            String::new(),
+            "graphical-session.target",
+        ),
+    };
+
+    format!(
+        "[Unit]\n\
+         Description=rebinded key remapping daemon\n\
+         Documentation=https://github.com/xevion/rebinded\n\
+         {unit_deps}\
+         \n\
+         [Service]\n\
+         Type=notify\n\
+         ExecStart={exec_start}\n\
+         {account}\
+         Restart=on-failure\n\
+         RestartSec=2\n\
+         WatchdogSec=30\n\
+         \n\
+         [Install]\n\
+         WantedBy={wanted_by}\n"
+    )
+}
+
+/// Quote an `ExecStart=` argument if it contains whitespace or quotes
+fn quote_arg(arg: &str) -> String {
+    if arg.contains(|c: char| c.is_whitespace() || c == '"' || c == '\\') {
+        format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        arg.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watchdog_interval() {
+        assert_eq!(
+            watchdog_interval(Some("30000000"), None, 42),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            watchdog_interval(Some("30000000"), Some("42"), 42),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(watchdog_interval(Some("30000000"), Some("7"), 42), None);
+        assert_eq!(watchdog_interval(Some("0"), None, 42), None);
+        assert_eq!(watchdog_interval(Some("soon"), None, 42), None);
+        assert_eq!(watchdog_interval(None, None, 42), None);
+    }
+
+    #[test]
+    fn test_unit_file() {
+        let unit = unit_file(
+            Path::new("/usr/bin/rebinded"),
+            Some(Path::new("/home/me/my config.toml")),
+            None,
+        );
+        assert!(unit.contains("Type=notify\n"));
+        assert!(
+            unit.contains("ExecStart=/usr/bin/rebinded --config \"/home/me/my config.toml\"\n")
+        );
+        assert!(unit.contains("WantedBy=graphical-session.target\n"));
+        assert!(!unit.contains("User="));
+
+        let unit = unit_file(Path::new("/usr/bin/rebinded"), None, Some("me"));
+        assert!(unit.contains("ExecStart=/usr/bin/rebinded\n"));
+        assert!(unit.contains("User=me\nSupplementaryGroups=input\n"));
+        assert!(unit.contains("WantedBy=multi-user.target\n"));
+    }
+
+    #[test]
+    fn test_notify_socket() {
+        let dir = std::env::temp_dir().join(format!("rebinded-notify-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("notify.sock");
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixDatagram::bind(&path).unwrap();
+
+        send_notify(path.as_os_str(), "READY=1").unwrap();
+        let mut buf = [0u8; 64];
+        let n = listener.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"READY=1");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
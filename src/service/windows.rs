@@ -0,0 +1,356 @@
+//! Windows service
+//!
+//! Services run in session 0, which has no access to the interactive desktop,
+//! so a low-level keyboard hook installed there never sees the user's keys.
+//! The service is instead a supervisor: it launches the daemon into the active
+//! console session with the logged-on user's token, relaunches it when that
+//! session changes (log on, user switch) or the daemon exits, and terminates it
+//! when the service stops.
+//!
+//! `rebinded service install` registers the service to start automatically
+//! under LocalSystem, the account allowed to query session user tokens.
+
+use anyhow::{Context, anyhow};
+use std::ffi::c_void;
+use std::path::Path;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tracing::{info, warn};
+use windows::Win32::Foundation::{CloseHandle, HANDLE, WAIT_OBJECT_0};
+use windows::Win32::System::Environment::{CreateEnvironmentBlock, DestroyEnvironmentBlock};
+use windows::Win32::System::RemoteDesktop::{WTSGetActiveConsoleSessionId, WTSQueryUserToken};
+use windows::Win32::System::Services::{
+    CloseServiceHandle, ControlService, CreateServiceW, DeleteService, OpenSCManagerW,
+    OpenServiceW, RegisterServiceCtrlHandlerExW, SC_HANDLE, SC_MANAGER_ALL_ACCESS,
+    SC_MANAGER_CONNECT, SERVICE_ACCEPT_SESSIONCHANGE, SERVICE_ACCEPT_STOP, SERVICE_ALL_ACCESS,
+    SERVICE_AUTO_START, SERVICE_CONTROL_INTERROGATE, SERVICE_CONTROL_SESSIONCHANGE,
+    SERVICE_CONTROL_STOP, SERVICE_ERROR_NORMAL, SERVICE_RUNNING, SERVICE_START, SERVICE_STATUS,
+    SERVICE_STATUS_CURRENT_STATE, SERVICE_STATUS_HANDLE, SERVICE_STOP, SERVICE_STOP_PENDING,
+    SERVICE_STOPPED, SERVICE_TABLE_ENTRYW, SERVICE_WIN32_OWN_PROCESS, SetServiceStatus,
+    StartServiceCtrlDispatcherW, StartServiceW,
+};
+use windows::Win32::System::Threading::{
+    CREATE_NO_WINDOW, CREATE_UNICODE_ENVIRONMENT, CreateEventW, CreateProcessAsUserW,
+    PROCESS_INFORMATION, STARTUPINFOW, SetEvent, TerminateProcess, WaitForMultipleObjects,
+};
+use windows::core::{PCWSTR, PWSTR};
+
+const SERVICE_NAME: &str = "rebinded";
+const DISPLAY_NAME: &str = "rebinded key remapper";
+
+/// `WTS_SESSION_LOGON` session change notification
+const WTS_SESSION_LOGON: u32 = 0x5;
+/// `WTS_CONSOLE_CONNECT` session change notification (fast user switching)
+const WTS_CONSOLE_CONNECT: u32 = 0x1;
+
+/// How long to wait before relaunching a daemon that exited or couldn't start
+const RELAUNCH_DELAY_MS: u32 = 5000;
+
+/// Daemon command line, set before the dispatcher starts the service
+static DAEMON_COMMAND: OnceLock<String> = OnceLock::new();
+/// Handles shared with the control handler, stored as raw values since
+/// Win32 handles aren't `Send`
+static STATUS_HANDLE: AtomicUsize = AtomicUsize::new(0);
+static STOP_EVENT: AtomicUsize = AtomicUsize::new(0);
+static SESSION_EVENT: AtomicUsize = AtomicUsize::new(0);
+
+fn wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+/// Service control manager or service handle, closed on drop
+struct ScHandle(SC_HANDLE);
+
+impl Drop for ScHandle {
+    fn drop(&mut self) {
+        let _ = unsafe { CloseServiceHandle(self.0) };
+    }
+}
+
+fn open_manager(access: u32) -> anyhow::Result<ScHandle> {
+    unsafe { OpenSCManagerW(PCWSTR::null(), PCWSTR::null(), access) }
+        .map(ScHandle)
+        .map_err(|e| anyhow!("failed to open the service manager (run as administrator?): {e}"))
+}
+
+fn open_service(access: u32) -> anyhow::Result<ScHandle> {
+    let manager = open_manager(SC_MANAGER_CONNECT)?;
+    let name = wide(SERVICE_NAME);
+    unsafe { OpenServiceW(manager.0, PCWSTR(name.as_ptr()), access) }
+        .map(ScHandle)
+        .map_err(|e| anyhow!("failed to open the {SERVICE_NAME} service (is it installed?): {e}"))
+}
+
+/// The daemon's command line, with `--config` if one was given
+fn daemon_command_line(exe: &Path, config: Option<&Path>) -> String {
+    let mut command = format!("\"{}\"", exe.display());
+    if let Some(config) = config {
+        command.push_str(&format!(" --config \"{}\"", config.display()));
+    }
+    command
+}
+
+/// Register the service to start automatically at boot
+pub fn install(user: bool, config: Option<&Path>) -> anyhow::Result<()> {
+    if user {
+        anyhow::bail!("--user only applies to systemd; Windows services are always system-wide");
+    }
+    let exe = std::env::current_exe().context("could not locate the rebinded binary")?;
+    let config = config.map(std::path::absolute).transpose()?;
+    let mut bin_path = daemon_command_line(&exe, config.as_deref());
+    bin_path.push_str(" service run");
+
+    let manager = open_manager(SC_MANAGER_ALL_ACCESS)?;
+    let (name, display, bin_path) = (wide(SERVICE_NAME), wide(DISPLAY_NAME), wide(&bin_path));
+    let service = unsafe {
+        CreateServiceW(
+            manager.0,
+            PCWSTR(name.as_ptr()),
+            PCWSTR(display.as_ptr()),
+            SERVICE_ALL_ACCESS,
+            SERVICE_WIN32_OWN_PROCESS,
+            SERVICE_AUTO_START,
+            SERVICE_ERROR_NORMAL,
+            PCWSTR(bin_path.as_ptr()),
+            PCWSTR::null(),
+            None,
+            PCWSTR::null(),
+            PCWSTR::null(),
+            PCWSTR::null(),
+        )
+    }
+    .map(ScHandle)
+    .map_err(|e| anyhow!("failed to create the service (run as administrator?): {e}"))?;
+    drop(service);
+
+    println!("installed the {SERVICE_NAME} service; start it with:\n  rebinded service start");
+    Ok(())
+}
+
+/// Stop the service if it's running and remove it
+pub fn uninstall(user: bool) -> anyhow::Result<()> {
+    if user {
+        anyhow::bail!("--user only applies to systemd; Windows services are always system-wide");
+    }
+    let service = open_service(SERVICE_ALL_ACCESS)?;
+    let mut status = SERVICE_STATUS::default();
+    // Fails harmlessly when the service isn't running
+    let _ = unsafe { ControlService(service.0, SERVICE_CONTROL_STOP, &mut status) };
+    unsafe { DeleteService(service.0) }
+        .map_err(|e| anyhow!("failed to delete the service: {e}"))?;
+    println!("removed the {SERVICE_NAME} service");
+    Ok(())
+}
+
+pub fn start(_user: bool) -> anyhow::Result<()> {
+    let service = open_service(SERVICE_START)?;
+    unsafe { StartServiceW(service.0, None) }
+        .map_err(|e| anyhow!("failed to start the service: {e}"))?;
+    println!("started the {SERVICE_NAME} service");
+    Ok(())
+}
+
+pub fn stop(_user: bool) -> anyhow::Result<()> {
+    let service = open_service(SERVICE_STOP)?;
+    let mut status = SERVICE_STATUS::default();
+    unsafe { ControlService(service.0, SERVICE_CONTROL_STOP, &mut status) }
+        .map_err(|e| anyhow!("failed to stop the service: {e}"))?;
+    println!("stopped the {SERVICE_NAME} service");
+    Ok(())
+}
+
+/// Service entry point, invoked by the service manager as `rebinded service run`
+pub fn run(config: Option<&Path>) -> anyhow::Result<()> {
+    let exe = std::env::current_exe().context("could not locate the rebinded binary")?;
+    let _ = DAEMON_COMMAND.set(daemon_command_line(&exe, config));
+
+    let mut name = wide(SERVICE_NAME);
+    let table = [
+        SERVICE_TABLE_ENTRYW {
+            lpServiceName: PWSTR(name.as_mut_ptr()),
+            lpServiceProc: Some(service_main),
+        },
+        SERVICE_TABLE_ENTRYW::default(),
+    ];
+    // Blocks until the service stops
+    unsafe { StartServiceCtrlDispatcherW(table.as_ptr()) }
+        .map_err(|e| anyhow!("not started by the service manager: {e}"))
+}
+
+fn set_status(state: SERVICE_STATUS_CURRENT_STATE) {
+    let handle = SERVICE_STATUS_HANDLE(STATUS_HANDLE.load(Ordering::Acquire) as *mut c_void);
+    let controls_accepted = if state == SERVICE_RUNNING {
+        SERVICE_ACCEPT_STOP | SERVICE_ACCEPT_SESSIONCHANGE
+    } else {
+        0
+    };
+    let status = SERVICE_STATUS {
+        dwServiceType: SERVICE_WIN32_OWN_PROCESS,
+        dwCurrentState: state,
+        dwControlsAccepted: controls_accepted,
+        dwWaitHint: 3000,
+        ..Default::default()
+    };
+    if let Err(e) = unsafe { SetServiceStatus(handle, &status) } {
+        warn!("failed to report service status: {e}");
+    }
+}
+
+unsafe extern "system" fn control_handler(
+    control: u32,
+    event_type: u32,
+    _event_data: *mut c_void,
+    _context: *mut c_void,
+) -> u32 {
+    match control {
+        SERVICE_CONTROL_STOP => {
+            set_status(SERVICE_STOP_PENDING);
+            let stop = HANDLE(STOP_EVENT.load(Ordering::Acquire) as *mut c_void);
+            let _ = unsafe { SetEvent(stop) };
+        }
+        SERVICE_CONTROL_SESSIONCHANGE
+            if event_type == WTS_SESSION_LOGON || event_type == WTS_CONSOLE_CONNECT =>
+        {
+            let session = HANDLE(SESSION_EVENT.load(Ordering::Acquire) as *mut c_void);
+            let _ = unsafe { SetEvent(session) };
+        }
+        SERVICE_CONTROL_INTERROGATE | SERVICE_CONTROL_SESSIONCHANGE => {}
+        _ => return 120, // ERROR_CALL_NOT_IMPLEMENTED
+    }
+    0
+}
+
+unsafe extern "system" fn service_main(_argc: u32, _argv: *mut PWSTR) {
+    let name = wide(SERVICE_NAME);
+    let Ok(handle) = (unsafe {
+        RegisterServiceCtrlHandlerExW(PCWSTR(name.as_ptr()), Some(control_handler), None)
+    }) else {
+        return;
+    };
+    STATUS_HANDLE.store(handle.0 as usize, Ordering::Release);
+
+    let events = unsafe {
+        (
+            CreateEventW(None, true, false, PCWSTR::null()),
+            CreateEventW(None, false, false, PCWSTR::null()),
+        )
+    };
+    let (Ok(stop), Ok(session)) = events else {
+        set_status(SERVICE_STOPPED);
+        return;
+    };
+    STOP_EVENT.store(stop.0 as usize, Ordering::Release);
+    SESSION_EVENT.store(session.0 as usize, Ordering::Release);
+
+    set_status(SERVICE_RUNNING);
+    supervise(stop, session);
+    set_status(SERVICE_STOPPED);
+}
+
+/// Keep one daemon running in the active console session until `stop` is signaled
+fn supervise(stop: HANDLE, session: HANDLE) {
+    let command = DAEMON_COMMAND.get().cloned().unwrap_or_default();
+    let mut child: Option<HANDLE> = None;
+
+    loop {
+        if child.is_none() {
+            match launch_in_console_session(&command) {
+                Ok(process) => child = Some(process),
+                // Usually nobody is logged on yet; retry after a delay or at logon
+                Err(e) => warn!("failed to launch daemon: {e:#}"),
+            }
+        }
+
+        let mut handles = vec![stop, session];
+        handles.extend(child);
+        let wait = unsafe { WaitForMultipleObjects(&handles, false, RELAUNCH_DELAY_MS) };
+        match wait.0.wrapping_sub(WAIT_OBJECT_0.0) {
+            0 => break,
+            1 => {
+                info!("console session changed, relaunching daemon");
+                if let Some(process) = child.take() {
+                    terminate(process);
+                }
+            }
+            2 => {
+                warn!("daemon exited, relaunching");
+                if let Some(process) = child.take() {
+                    let _ = unsafe { CloseHandle(process) };
+                }
+                // Avoid a tight loop when the daemon fails on startup (e.g. a bad config)
+                let _ = unsafe { WaitForMultipleObjects(&[stop], false, RELAUNCH_DELAY_MS) };
+            }
+            _ => {} // timeout: retry a failed launch
+        }
+    }
+
+    if let Some(process) = child {
+        terminate(process);
+    }
+}
+
+fn terminate(process: HANDLE) {
+    let _ = unsafe { TerminateProcess(process, 0) };
+    let _ = unsafe { CloseHandle(process) };
+}
+
+/// Start the daemon on the interactive desktop of the active console session
+fn launch_in_console_session(command: &str) -> anyhow::Result<HANDLE> {
+    let session_id = unsafe { WTSGetActiveConsoleSessionId() };
+    if session_id == u32::MAX {
+        return Err(anyhow!("no active console session"));
+    }
+
+    let mut token = HANDLE::default();
+    unsafe { WTSQueryUserToken(session_id, &mut token) }
+        .map_err(|e| anyhow!("no user logged on to session {session_id}: {e}"))?;
+
+    let mut environment: *mut c_void = std::ptr::null_mut();
+    let have_environment =
+        unsafe { CreateEnvironmentBlock(&mut environment, Some(token), false) }.is_ok();
+
+    let mut desktop = wide(r"winsta0\default");
+    let startup = STARTUPINFOW {
+        cb: size_of::<STARTUPINFOW>() as u32,
+        lpDesktop: PWSTR(desktop.as_mut_ptr()),
+        ..Default::default()
+    };
+    let mut process = PROCESS_INFORMATION::default();
+    let mut command_line = wide(command);
+
+    let result = unsafe {
+        CreateProcessAsUserW(
+            Some(token),
+            PCWSTR::null(),
+            Some(PWSTR(command_line.as_mut_ptr())),
+            None,
+            None,
+            false,
+            CREATE_NO_WINDOW | CREATE_UNICODE_ENVIRONMENT,
+            have_environment.then_some(environment as *const c_void),
+            PCWSTR::null(),
+            &startup,
+            &mut process,
+        )
+    };
+
+    unsafe {
+        if have_environment {
+            let _ = DestroyEnvironmentBlock(environment);
+        }
+        let _ = CloseHandle(token);
+    }
+    result.map_err(|e| anyhow!("failed to start daemon in session {session_id}: {e}"))?;
+
+    let _ = unsafe { CloseHandle(process.hThread) };
+    info!(session_id, pid = process.dwProcessId, "launched daemon");
+    Ok(process.hProcess)
+}
+
+/// Unused on Windows; the service manager doesn't use readiness notifications
+pub fn notify(_state: &str) {}
+
+/// Unused on Windows; the supervisor relaunches a daemon that exits
+pub async fn watchdog() {
+    std::future::pending().await
+}
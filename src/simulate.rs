@@ -0,0 +1,356 @@
+//! `rebinded simulate`: scripted regression tests for a config
+//!
+//! A script lists timed input events and, optionally, what each should lead
+//! to. The events go through the engine at their times against a mock
+//! platform, as with `rebinded replay`, and every unmet expectation is
+//! reported.
+//!
+//! ```toml
+//! config = "../config.toml"   # relative to the script; --config overrides it
+//! window = { binary = "mpv" } # focused window until an event changes it
+//!
+//! [[events]]
+//! at = 0                      # ms since the start
+//! key = "f13"                 # a [bindings] key, mouse_back, scroll_up, ...
+//! down = true
+//! response = "block"          # optional
+//!
+//! [[events]]
+//! at = 600
+//! key = "f13"
+//! down = false
+//! actions = ["media_play_pause"] # optional: what runs until the next event
+//! ```
+//!
+//! Expected actions are written like binding actions and compared by the
+//! platform calls they make, so `media_play_pause` matches however a binding or
+//! strategy got there. Integrations and commands never reach the platform and
+//! can't be checked this way; list the steps of a chain one by one.
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+
+use crate::config::{self, Action, WindowInfo};
+use crate::key::{InputEvent, InputEventId, KeyEvent};
+use crate::platform::mock::PlatformCall;
+use crate::platform::{EventResponse, MockPlatform};
+use crate::strategy::{self, PlatformHandle};
+use crate::trace::{self, TraceEvent};
+
+/// A parsed script
+struct Script {
+    /// Config named by the script, resolved against its directory
+    config: Option<PathBuf>,
+    steps: Vec<Step>,
+}
+
+/// One event and what it should lead to
+struct Step {
+    event: TraceEvent,
+    response: Option<EventResponse>,
+    actions: Option<Vec<Action>>,
+}
+
+/// Run a script, printing each event's outcome; fails if any expectation isn't met
+pub async fn run(
+    config_override: Option<&Path>,
+    script_path: &Path,
+    options: config::LoadOptions,
+) -> ExitCode {
+    let origin = Instant::now();
+    let script = match std::fs::read_to_string(script_path)
+        .with_context(|| format!("failed to read script {}", script_path.display()))
+        .and_then(|text| {
+            let dir = script_path.parent().unwrap_or(Path::new("."));
+            parse(&text, dir, origin).map_err(|e| anyhow::anyhow!("{}: {e}", script_path.display()))
+        }) {
+        Ok(script) => script,
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let config_path = config_override
+        .map(Path::to_path_buf)
+        .or(script.config)
+        .unwrap_or_else(crate::default_config_path);
+    if let Some(config_dir) = config_path.parent() {
+        strategy::plugin::load_dir(&config_dir.join("plugins"));
+    }
+    let runtime = match config::load_with(&config_path, options).await {
+        Ok((_, runtime)) => runtime,
+        Err(err) => {
+            eprintln!("{:?}", miette::Report::new(err));
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let (events, expectations): (Vec<_>, Vec<_>) = script
+        .steps
+        .into_iter()
+        .map(|step| (step.event, (step.response, step.actions)))
+        .unzip();
+    let replayed = trace::replay(&runtime, events, origin).await;
+
+    let mut failed = 0;
+    for (event, (response, actions)) in replayed.iter().zip(expectations) {
+        let mut problems = Vec::new();
+        if let Some(expected) = response
+            && expected != event.response
+        {
+            problems.push(format!("expected response: {}", response_name(expected)));
+        }
+        if let Some(actions) = actions {
+            let expected = expected_calls(&actions);
+            if expected != event.calls {
+                let names: Vec<String> = actions.iter().map(|a| a.to_string()).collect();
+                problems.push(format!(
+                    "expected actions: {} {}",
+                    if names.is_empty() {
+                        "none".to_string()
+                    } else {
+                        names.join(", ")
+                    },
+                    describe_calls(&expected)
+                ));
+                problems.push(format!("actual calls: {}", describe_calls(&event.calls)));
+            }
+        }
+
+        let status = if problems.is_empty() { "ok" } else { "FAIL" };
+        println!(
+            "{status:<5} {:>6}ms  {}: {}",
+            event.traced.at.as_millis(),
+            trace::describe(&event.traced.event),
+            response_name(event.response)
+        );
+        if !problems.is_empty() {
+            failed += 1;
+            for problem in problems {
+                println!("      {problem}");
+            }
+        }
+    }
+
+    println!("{} of {} events failed", failed, replayed.len());
+    if failed > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// The platform calls `actions` make when run directly
+fn expected_calls(actions: &[Action]) -> Vec<PlatformCall> {
+    let mock = MockPlatform::new();
+    let platform = PlatformHandle::from_mock(&mock);
+    for action in actions {
+        platform.execute(action);
+    }
+    mock.calls()
+}
+
+fn describe_calls(calls: &[PlatformCall]) -> String {
+    if calls.is_empty() {
+        return "(no calls)".to_string();
+    }
+    let calls: Vec<String> = calls.iter().map(|call| format!("{call:?}")).collect();
+    format!("({})", calls.join(", "))
+}
+
+fn response_name(response: EventResponse) -> &'static str {
+    match response {
+        EventResponse::Block => "block",
+        EventResponse::Passthrough => "passthrough",
+        EventResponse::Continue => "continue",
+    }
+}
+
+fn parse(text: &str, dir: &Path, origin: Instant) -> Result<Script, String> {
+    let table: toml::Table = text.parse().map_err(|e: toml::de::Error| e.to_string())?;
+    check_keys(&table, &["config", "window", "events"], "the script")?;
+
+    let config = match table.get("config") {
+        Some(toml::Value::String(path)) => Some(dir.join(path)),
+        Some(_) => return Err("`config` must be a path".to_string()),
+        None => None,
+    };
+    let mut window = match table.get("window") {
+        Some(value) => parse_window(value)?,
+        None => WindowInfo::default(),
+    };
+    let Some(toml::Value::Array(events)) = table.get("events") else {
+        return Err("no [[events]]".to_string());
+    };
+
+    let mut steps = Vec::with_capacity(events.len());
+    let mut last_at = Duration::ZERO;
+    for (i, event) in events.iter().enumerate() {
+        let step =
+            parse_step(event, &mut window, origin).map_err(|e| format!("event {}: {e}", i + 1))?;
+        if step.event.at < last_at {
+            return Err(format!(
+                "event {}: `at` goes back in time ({}ms after {}ms)",
+                i + 1,
+                step.event.at.as_millis(),
+                last_at.as_millis()
+            ));
+        }
+        last_at = step.event.at;
+        steps.push(step);
+    }
+    Ok(Script { config, steps })
+}
+
+/// Parse one event; a `window` on it becomes the focused window from then on
+fn parse_step(
+    value: &toml::Value,
+    window: &mut WindowInfo,
+    origin: Instant,
+) -> Result<Step, String> {
+    let toml::Value::Table(table) = value else {
+        return Err("must be a table".to_string());
+    };
+    check_keys(
+        table,
+        &["at", "key", "down", "window", "response", "actions"],
+        "an event",
+    )?;
+
+    let at = match table.get("at") {
+        Some(toml::Value::Integer(ms)) if *ms >= 0 => Duration::from_millis(*ms as u64),
+        _ => return Err("`at` must be a number of milliseconds".to_string()),
+    };
+    let name = match table.get("key") {
+        Some(toml::Value::String(name)) => name,
+        _ => return Err("`key` must be a key name".to_string()),
+    };
+    let down = match table.get("down") {
+        Some(toml::Value::Boolean(down)) => Some(*down),
+        Some(_) => return Err("`down` must be true or false".to_string()),
+        None => None,
+    };
+    let event = match (InputEventId::from_config_str(name), down) {
+        (Some(InputEventId::Key(key)), Some(down)) => {
+            InputEvent::Key(KeyEvent::at(key, down, origin + at))
+        }
+        (Some(InputEventId::MouseButton(button)), Some(down)) => {
+            InputEvent::MouseButton { button, down }
+        }
+        (Some(InputEventId::Scroll { up }), None) => InputEvent::Scroll { up },
+        (Some(InputEventId::Scroll { .. }), Some(_)) => {
+            return Err(format!("{name} has no `down`"));
+        }
+        (Some(_), None) => return Err(format!("{name} needs `down = true` or `false`")),
+        _ => return Err(format!("unknown key '{name}'")),
+    };
+
+    if let Some(value) = table.get("window") {
+        *window = parse_window(value)?;
+    }
+    let response = match table.get("response").map(|value| value.as_str()) {
+        None => None,
+        Some(Some("block")) => Some(EventResponse::Block),
+        Some(Some("passthrough")) => Some(EventResponse::Passthrough),
+        Some(_) => return Err("`response` must be \"block\" or \"passthrough\"".to_string()),
+    };
+    let actions = match table.get("actions") {
+        None => None,
+        Some(toml::Value::Array(actions)) => Some(
+            actions
+                .iter()
+                .map(|action| Action::try_from(action.clone()))
+                .collect::<Result<Vec<_>, _>>()?,
+        ),
+        Some(_) => return Err("`actions` must be a list of actions".to_string()),
+    };
+
+    Ok(Step {
+        event: TraceEvent {
+            at,
+            event,
+            window: window.clone(),
+            // Not compared; expectations are in the step
+            response: EventResponse::Passthrough,
+        },
+        response,
+        actions,
+    })
+}
+
+fn parse_window(value: &toml::Value) -> Result<WindowInfo, String> {
+    let toml::Value::Table(table) = value else {
+        return Err("`window` must be a table of title, class and binary".to_string());
+    };
+    check_keys(table, &["title", "class", "binary"], "`window`")?;
+    let text = |name: &str| match table.get(name) {
+        Some(toml::Value::String(text)) => Ok(text.clone()),
+        Some(_) => Err(format!("window `{name}` must be a string")),
+        None => Ok(String::new()),
+    };
+    Ok(WindowInfo {
+        title: text("title")?,
+        class: text("class")?,
+        binary: text("binary")?,
+    })
+}
+
+fn check_keys(table: &toml::Table, known: &[&str], what: &str) -> Result<(), String> {
+    match table.keys().find(|key| !known.contains(&key.as_str())) {
+        Some(key) => Err(format!(
+            "unknown field `{key}` in {what}, expected {}",
+            known.join(", ")
+        )),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_script() {
+        let script = r#"
+            config = "config.toml"
+            window = { binary = "mpv" }
+
+            [[events]]
+            at = 0
+            key = "f13"
+            down = true
+            response = "block"
+
+            [[events]]
+            at = 600
+            key = "scroll_up"
+            window = { binary = "kitty" }
+            actions = ["media_next", "volume_up"]
+        "#;
+        let script = parse(script, Path::new("tests"), Instant::now()).unwrap();
+        assert_eq!(script.config, Some(PathBuf::from("tests/config.toml")));
+        assert_eq!(script.steps.len(), 2);
+        assert_eq!(script.steps[0].event.window.binary, "mpv");
+        assert_eq!(script.steps[0].response, Some(EventResponse::Block));
+        assert_eq!(script.steps[1].event.window.binary, "kitty");
+        assert_eq!(
+            expected_calls(script.steps[1].actions.as_deref().unwrap()).len(),
+            2
+        );
+
+        let error = |script: &str| match parse(script, Path::new("."), Instant::now()) {
+            Ok(_) => panic!("expected an error for {script}"),
+            Err(err) => err,
+        };
+        let event =
+            |at: u32, fields: &str| format!("[[events]]\nat = {at}\nkey = \"f13\"\n{fields}\n");
+        assert!(error(&event(0, "")).contains("needs `down"));
+        assert!(error(&event(0, "down = true\ndelay = 1")).contains("unknown field `delay`"));
+        let backwards = event(5, "down = true") + event(1, "down = false").as_str();
+        assert!(error(&backwards).contains("event 2: `at` goes back in time"));
+    }
+}
@@ -0,0 +1,125 @@
+//! System tray icon (Windows)
+//!
+//! With `--tray`, the daemon shows a notification area icon instead of keeping
+//! a console window open. The icon's tooltip shows whether remapping is paused,
+//! and its menu can pause/resume, reload the config, open the config file and
+//! quit. Double-clicking the icon toggles pause.
+//!
+//! The icon runs its own Win32 message loop on a dedicated thread and sends
+//! menu choices back to the main task, which applies them like control
+//! commands.
+
+#[cfg(windows)]
+mod windows;
+
+use std::path::Path;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use crate::control::Daemon;
+use rebinded_core::control::Request;
+
+/// A choice from the tray menu
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(not(windows), allow(dead_code))] // only the Windows icon sends these
+pub enum TrayCommand {
+    TogglePause,
+    Reload,
+    OpenConfig,
+    Quit,
+}
+
+#[cfg(windows)]
+use windows::Icon;
+#[cfg(windows)]
+pub use windows::detach_console;
+
+#[cfg(not(windows))]
+pub fn detach_console() {}
+
+#[cfg(not(windows))]
+struct Icon;
+
+#[cfg(not(windows))]
+impl Icon {
+    fn spawn(_commands: mpsc::UnboundedSender<TrayCommand>) -> anyhow::Result<Self> {
+        anyhow::bail!("tray icons are only available on Windows")
+    }
+
+    fn set_paused(&self, _paused: bool) {}
+
+    fn show_message(&self, _title: &str, _text: &str) {}
+}
+
+/// Show the tray icon and handle its menu until Quit is chosen. If the icon
+/// can't be created the daemon keeps running without it.
+pub async fn run(daemon: &Daemon) {
+    let (tx, mut commands) = mpsc::unbounded_channel();
+    let icon = match Icon::spawn(tx) {
+        Ok(icon) => icon,
+        Err(e) => {
+            warn!("tray icon unavailable: {e:#}");
+            return std::future::pending().await;
+        }
+    };
+
+    let mut paused = daemon.watch_paused();
+    icon.set_paused(*paused.borrow_and_update());
+
+    loop {
+        tokio::select! {
+            Some(command) = commands.recv() => match command {
+                TrayCommand::TogglePause => {
+                    let _ = daemon.apply(Request::Toggle).await;
+                }
+                TrayCommand::Reload => match daemon.apply(Request::Reload).await {
+                    Ok(reply) => info!("{reply}"),
+                    Err(e) => {
+                        warn!("reload failed:\n{e}");
+                        icon.show_message("Config reload failed", first_line(&e));
+                    }
+                },
+                TrayCommand::OpenConfig => open_file(&daemon.active_config_path()),
+                TrayCommand::Quit => {
+                    info!("quit from tray icon");
+                    return;
+                }
+            },
+            Ok(()) = paused.changed() => icon.set_paused(*paused.borrow_and_update()),
+        }
+    }
+}
+
+/// The headline of a rendered config error, short enough for a balloon
+fn first_line(text: &str) -> &str {
+    text.lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or(text)
+}
+
+/// Open a file with its associated application
+fn open_file(path: &Path) {
+    let program = if cfg!(windows) {
+        "explorer"
+    } else {
+        "xdg-open"
+    };
+    if let Err(e) = std::process::Command::new(program).arg(path).spawn() {
+        warn!("failed to open {}: {e}", path.display());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_line() {
+        assert_eq!(
+            first_line("\n  × unknown key 'f99'\n   ╭─[config.toml:2:1]"),
+            "× unknown key 'f99'"
+        );
+        assert_eq!(first_line(""), "");
+    }
+}
@@ -0,0 +1,299 @@
+//! Win32 notification area icon
+
+use super::TrayCommand;
+use anyhow::anyhow;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use tokio::sync::mpsc;
+use tracing::warn;
+use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, POINT, WPARAM};
+use windows::Win32::System::Console::FreeConsole;
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::UI::Shell::{
+    NIF_ICON, NIF_INFO, NIF_MESSAGE, NIF_TIP, NIIF_WARNING, NIM_ADD, NIM_DELETE, NIM_MODIFY,
+    NOTIFYICONDATAW, Shell_NotifyIconW,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    AppendMenuW, CreatePopupMenu, CreateWindowExW, DefWindowProcW, DestroyMenu, DestroyWindow,
+    DispatchMessageW, GetCursorPos, GetMessageW, IDI_APPLICATION, IDI_WARNING, LoadIconW,
+    MF_SEPARATOR, MF_STRING, MSG, PostMessageW, PostQuitMessage, RegisterClassW,
+    RegisterWindowMessageW, SendMessageW, SetForegroundWindow, TPM_RETURNCMD, TPM_RIGHTBUTTON,
+    TrackPopupMenu, TranslateMessage, WINDOW_EX_STYLE, WINDOW_STYLE, WM_APP, WM_CLOSE,
+    WM_CONTEXTMENU, WM_DESTROY, WM_LBUTTONDBLCLK, WM_RBUTTONUP, WNDCLASSW,
+};
+use windows::core::{PCWSTR, w};
+
+/// Notifications from the icon (mouse clicks), identified by `uCallbackMessage`
+const WM_TRAY_CALLBACK: u32 = WM_APP + 1;
+/// Posted by `Icon::set_paused`, with the new state in `wparam`
+const WM_TRAY_PAUSED: u32 = WM_APP + 2;
+/// Posted by `Icon::show_message` after storing the text in `PENDING_MESSAGE`
+const WM_TRAY_MESSAGE: u32 = WM_APP + 3;
+
+const ICON_ID: u32 = 1;
+
+const MENU_TOGGLE: usize = 1;
+const MENU_RELOAD: usize = 2;
+const MENU_OPEN_CONFIG: usize = 3;
+const MENU_QUIT: usize = 4;
+
+/// Where menu choices go; set once by the tray thread
+static COMMANDS: OnceLock<mpsc::UnboundedSender<TrayCommand>> = OnceLock::new();
+static PAUSED: AtomicBool = AtomicBool::new(false);
+/// Balloon title and text waiting for the tray thread to show them
+static PENDING_MESSAGE: Mutex<Option<(String, String)>> = Mutex::new(None);
+/// Broadcast by Explorer when the taskbar is recreated (e.g. after a crash);
+/// the icon has to be added again
+static TASKBAR_CREATED: OnceLock<u32> = OnceLock::new();
+
+/// Handle to the icon's window on the tray thread. Dropping it removes the icon.
+pub struct Icon {
+    /// The window handle, as a raw value since `HWND` isn't `Send`
+    hwnd: isize,
+}
+
+impl Icon {
+    /// Create the icon on a new thread. Menu choices are sent to `commands`.
+    pub fn spawn(commands: mpsc::UnboundedSender<TrayCommand>) -> anyhow::Result<Self> {
+        COMMANDS
+            .set(commands)
+            .map_err(|_| anyhow!("tray icon already created"))?;
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            // SAFETY: the window is created and used only on this thread
+            match unsafe { create_window() } {
+                Ok(hwnd) => {
+                    let _ = tx.send(Ok(hwnd.0 as isize));
+                    unsafe { message_loop() };
+                }
+                Err(e) => {
+                    let _ = tx.send(Err(e));
+                }
+            }
+        });
+        let hwnd = rx
+            .recv()
+            .map_err(|_| anyhow!("tray thread exited during startup"))??;
+        Ok(Self { hwnd })
+    }
+
+    fn hwnd(&self) -> HWND {
+        HWND(self.hwnd as *mut _)
+    }
+
+    /// Update the tooltip and icon to reflect the pause state
+    pub fn set_paused(&self, paused: bool) {
+        let _ = unsafe {
+            PostMessageW(
+                Some(self.hwnd()),
+                WM_TRAY_PAUSED,
+                WPARAM(paused as usize),
+                LPARAM(0),
+            )
+        };
+    }
+
+    /// Show a balloon notification from the icon
+    pub fn show_message(&self, title: &str, text: &str) {
+        *PENDING_MESSAGE.lock().unwrap() = Some((title.to_string(), text.to_string()));
+        let _ = unsafe { PostMessageW(Some(self.hwnd()), WM_TRAY_MESSAGE, WPARAM(0), LPARAM(0)) };
+    }
+}
+
+impl Drop for Icon {
+    fn drop(&mut self) {
+        // Synchronous, so the icon is gone before the process exits
+        unsafe { SendMessageW(self.hwnd(), WM_CLOSE, None, None) };
+    }
+}
+
+/// Close the console window this process was started with, if it owns it
+pub fn detach_console() {
+    let _ = unsafe { FreeConsole() };
+}
+
+/// Copy a string into a fixed-size, NUL-terminated UTF-16 buffer, truncating if needed
+fn copy_wide<const N: usize>(dest: &mut [u16; N], text: &str) {
+    let mut len = 0;
+    for (slot, unit) in dest.iter_mut().take(N - 1).zip(text.encode_utf16()) {
+        *slot = unit;
+        len += 1;
+    }
+    dest[len] = 0;
+}
+
+fn icon_data(hwnd: HWND) -> NOTIFYICONDATAW {
+    NOTIFYICONDATAW {
+        cbSize: size_of::<NOTIFYICONDATAW>() as u32,
+        hWnd: hwnd,
+        uID: ICON_ID,
+        ..Default::default()
+    }
+}
+
+/// Add the icon, or refresh its tooltip and image with `NIM_MODIFY`
+unsafe fn update_icon(hwnd: HWND, add: bool) {
+    let paused = PAUSED.load(Ordering::Relaxed);
+    let mut data = icon_data(hwnd);
+    data.uFlags = NIF_ICON | NIF_MESSAGE | NIF_TIP;
+    data.uCallbackMessage = WM_TRAY_CALLBACK;
+    let icon = if paused { IDI_WARNING } else { IDI_APPLICATION };
+    data.hIcon = unsafe { LoadIconW(None, icon) }.unwrap_or_default();
+    copy_wide(
+        &mut data.szTip,
+        if paused {
+            "rebinded (paused)"
+        } else {
+            "rebinded"
+        },
+    );
+    let message = if add { NIM_ADD } else { NIM_MODIFY };
+    if !unsafe { Shell_NotifyIconW(message, &data) }.as_bool() {
+        warn!("failed to update tray icon");
+    }
+}
+
+unsafe fn show_balloon(hwnd: HWND, title: &str, text: &str) {
+    let mut data = icon_data(hwnd);
+    data.uFlags = NIF_INFO;
+    data.dwInfoFlags = NIIF_WARNING;
+    copy_wide(&mut data.szInfoTitle, title);
+    copy_wide(&mut data.szInfo, text);
+    let _ = unsafe { Shell_NotifyIconW(NIM_MODIFY, &data) };
+}
+
+unsafe fn show_menu(hwnd: HWND) {
+    let Ok(menu) = (unsafe { CreatePopupMenu() }) else {
+        return;
+    };
+    let toggle = if PAUSED.load(Ordering::Relaxed) {
+        w!("Resume")
+    } else {
+        w!("Pause")
+    };
+    unsafe {
+        let _ = AppendMenuW(menu, MF_STRING, MENU_TOGGLE, toggle);
+        let _ = AppendMenuW(menu, MF_STRING, MENU_RELOAD, w!("Reload config"));
+        let _ = AppendMenuW(menu, MF_STRING, MENU_OPEN_CONFIG, w!("Open config file"));
+        let _ = AppendMenuW(menu, MF_SEPARATOR, 0, PCWSTR::null());
+        let _ = AppendMenuW(menu, MF_STRING, MENU_QUIT, w!("Quit"));
+    }
+
+    let mut cursor = POINT::default();
+    let _ = unsafe { GetCursorPos(&mut cursor) };
+    // Without this the menu doesn't close when clicking elsewhere
+    let _ = unsafe { SetForegroundWindow(hwnd) };
+    let choice = unsafe {
+        TrackPopupMenu(
+            menu,
+            TPM_RETURNCMD | TPM_RIGHTBUTTON,
+            cursor.x,
+            cursor.y,
+            None,
+            hwnd,
+            None,
+        )
+    };
+    let _ = unsafe { DestroyMenu(menu) };
+
+    let command = match choice.0 as usize {
+        MENU_TOGGLE => TrayCommand::TogglePause,
+        MENU_RELOAD => TrayCommand::Reload,
+        MENU_OPEN_CONFIG => TrayCommand::OpenConfig,
+        MENU_QUIT => TrayCommand::Quit,
+        _ => return,
+    };
+    send(command);
+}
+
+fn send(command: TrayCommand) {
+    if let Some(commands) = COMMANDS.get() {
+        let _ = commands.send(command);
+    }
+}
+
+unsafe extern "system" fn window_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    match msg {
+        WM_TRAY_CALLBACK => match (lparam.0 & 0xFFFF) as u32 {
+            WM_RBUTTONUP | WM_CONTEXTMENU => unsafe { show_menu(hwnd) },
+            WM_LBUTTONDBLCLK => send(TrayCommand::TogglePause),
+            _ => {}
+        },
+        WM_TRAY_PAUSED => {
+            PAUSED.store(wparam.0 != 0, Ordering::Relaxed);
+            unsafe { update_icon(hwnd, false) };
+        }
+        WM_TRAY_MESSAGE => {
+            if let Some((title, text)) = PENDING_MESSAGE.lock().unwrap().take() {
+                unsafe { show_balloon(hwnd, &title, &text) };
+            }
+        }
+        WM_CLOSE => {
+            let _ = unsafe { DestroyWindow(hwnd) };
+        }
+        WM_DESTROY => {
+            let _ = unsafe { Shell_NotifyIconW(NIM_DELETE, &icon_data(hwnd)) };
+            unsafe { PostQuitMessage(0) };
+        }
+        _ if Some(&msg) == TASKBAR_CREATED.get() => unsafe { update_icon(hwnd, true) },
+        _ => return unsafe { DefWindowProcW(hwnd, msg, wparam, lparam) },
+    }
+    LRESULT(0)
+}
+
+/// Register the window class, create the hidden window and add the icon
+unsafe fn create_window() -> anyhow::Result<HWND> {
+    let instance = unsafe { GetModuleHandleW(None) }
+        .map_err(|e| anyhow!("failed to get module handle: {e}"))?;
+    let class_name = w!("rebinded_tray");
+
+    let class = WNDCLASSW {
+        lpfnWndProc: Some(window_proc),
+        hInstance: instance.into(),
+        lpszClassName: class_name,
+        ..Default::default()
+    };
+    if unsafe { RegisterClassW(&class) } == 0 {
+        return Err(anyhow!("failed to register tray window class"));
+    }
+    let _ = TASKBAR_CREATED.set(unsafe { RegisterWindowMessageW(w!("TaskbarCreated")) });
+
+    // A regular (never shown) window rather than a message-only one, since the
+    // context menu needs a window that can take the foreground
+    let hwnd = unsafe {
+        CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            class_name,
+            w!("rebinded"),
+            WINDOW_STYLE::default(),
+            0,
+            0,
+            0,
+            0,
+            None,
+            None,
+            Some(instance.into()),
+            None,
+        )
+    }
+    .map_err(|e| anyhow!("failed to create tray window: {e}"))?;
+
+    unsafe { update_icon(hwnd, true) };
+    Ok(hwnd)
+}
+
+unsafe fn message_loop() {
+    let mut msg = MSG::default();
+    while unsafe { GetMessageW(&mut msg, None, 0, 0) }.as_bool() {
+        unsafe {
+            let _ = TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+    }
+}